@@ -16,12 +16,13 @@ use crate::commands::schedule::{
     handle_schedule_sessions,
 };
 use crate::commands::session::{handle_session_list, handle_session_remove};
+use crate::commands::stats::handle_stats;
 use crate::logging::setup_logging;
 use crate::recipes::recipe::{
     explain_recipe_with_parameters, load_recipe_as_template, load_recipe_content_as_template,
 };
 use crate::session;
-use crate::session::{build_session, SessionBuilderConfig, SessionSettings};
+use crate::session::{build_session, OutputMode, SessionBuilderConfig, SessionSettings};
 use goose_bench::bench_config::BenchRunConfig;
 use goose_bench::runners::bench_runner::BenchRunner;
 use goose_bench::runners::eval_runner::EvalRunner;
@@ -119,6 +120,91 @@ enum SessionCommand {
         )]
         output: Option<PathBuf>,
     },
+    #[command(about = "Import conversations exported from ChatGPT or Claude as sessions")]
+    Import {
+        #[arg(
+            value_name = "FILE",
+            help = "Path to the exported conversations JSON file"
+        )]
+        file: PathBuf,
+
+        #[arg(
+            short,
+            long,
+            help = "Name for the imported session",
+            long_help = "Name for the imported session. When the file contains several conversations, each session gets this name with a numeric suffix."
+        )]
+        name: Option<String>,
+    },
+    #[command(about = "Rewrite a session file and remove stored images it no longer uses")]
+    Compact {
+        #[command(flatten)]
+        identifier: Option<Identifier>,
+    },
+    #[command(about = "Show latency statistics for a session")]
+    Stats {
+        #[command(flatten)]
+        identifier: Option<Identifier>,
+
+        #[arg(
+            short,
+            long,
+            help = "Output format (text, json)",
+            default_value = "text"
+        )]
+        format: String,
+    },
+    #[command(about = "Search the messages and tool results of saved sessions")]
+    Search {
+        #[arg(value_name = "QUERY", help = "Words that must all appear in a message")]
+        query: String,
+
+        #[arg(
+            long,
+            value_name = "DATE",
+            help = "Only messages from this date on (YYYY-MM-DD)"
+        )]
+        since: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "DATE",
+            help = "Only messages up to and including this date (YYYY-MM-DD)"
+        )]
+        until: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "ROLE",
+            help = "Only messages from this role",
+            value_parser = ["user", "assistant", "tool"]
+        )]
+        role: Option<String>,
+
+        #[arg(short, long, help = "Maximum number of results", default_value = "20")]
+        limit: usize,
+
+        #[arg(
+            short,
+            long,
+            help = "Output format (text, json)",
+            default_value = "text"
+        )]
+        format: String,
+    },
+    #[command(about = "Resume a session, showing its history from a given message")]
+    Open {
+        #[arg(value_name = "ID", help = "ID of the session, as shown by search")]
+        id: String,
+
+        #[arg(
+            long,
+            value_name = "INDEX",
+            help = "Show the history starting at this message",
+            long_help = "Show the history starting at this message index, as shown by search. Defaults to the whole history."
+        )]
+        at: Option<usize>,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -304,6 +390,16 @@ enum Command {
         )]
         debug: bool,
 
+        /// How the session output is shown
+        #[arg(
+            long = "output",
+            value_name = "MODE",
+            value_enum,
+            help = "How to show output: rich, plain or quiet (default: rich on a terminal, plain otherwise)",
+            long_help = "Rich output uses colors, syntax highlighting and spinners. Plain output has no escape sequences or spinners, for files, pipes and screen readers. Quiet output shows only the final assistant text of each reply."
+        )]
+        output: Option<OutputMode>,
+
         /// Maximum number of consecutive identical tool calls allowed
         #[arg(
             long = "max-tool-repetitions",
@@ -313,6 +409,22 @@ enum Command {
         )]
         max_tool_repetitions: Option<u32>,
 
+        /// Skip checking requests against known model capabilities
+        #[arg(
+            long = "no-validate",
+            help = "Skip checking requests against known model capabilities",
+            long_help = "Send requests without checking max tokens, images and tools against the known capabilities of the model. Use this for models that differ from what goose expects."
+        )]
+        no_validate: bool,
+
+        /// Keep tools from reaching the network
+        #[arg(
+            long = "no-network-tools",
+            help = "Keep tools from reaching the network",
+            long_help = "Refuse extensions that may access the network and shell commands such as curl or ssh. Extensions declare their network access with `network` in their config, builtins such as developer don't need it."
+        )]
+        no_network_tools: bool,
+
         /// Add stdio extensions with environment variables and commands
         #[arg(
             long = "with-extension",
@@ -352,6 +464,14 @@ enum Command {
     #[command(about = "List recent project directories", visible_alias = "ps")]
     Projects,
 
+    /// Report tool and model usage across all sessions
+    #[command(about = "Show which tools and models sessions use, and how they perform")]
+    Stats {
+        /// Print the report as JSON
+        #[arg(long, help = "Output the report as JSON")]
+        json: bool,
+    },
+
     /// Execute commands from an instruction file
     #[command(about = "Execute commands from an instruction file or stdin")]
     Run {
@@ -441,6 +561,15 @@ enum Command {
         )]
         render_recipe: bool,
 
+        /// Print the provider request instead of sending it
+        #[arg(
+            long = "dry-run",
+            help = "Print the request that would be sent to the provider instead of sending it",
+            long_help = "Build the request for the input, including the system prompt, tools and payload, and print it as JSON without calling the provider. Credentials in the headers are redacted and nothing is saved to the session.",
+            conflicts_with = "interactive"
+        )]
+        dry_run: bool,
+
         /// Maximum number of consecutive identical tool calls allowed
         #[arg(
             long = "max-tool-repetitions",
@@ -450,6 +579,22 @@ enum Command {
         )]
         max_tool_repetitions: Option<u32>,
 
+        /// Skip checking requests against known model capabilities
+        #[arg(
+            long = "no-validate",
+            help = "Skip checking requests against known model capabilities",
+            long_help = "Send requests without checking max tokens, images and tools against the known capabilities of the model. Use this for models that differ from what goose expects."
+        )]
+        no_validate: bool,
+
+        /// Keep tools from reaching the network
+        #[arg(
+            long = "no-network-tools",
+            help = "Keep tools from reaching the network",
+            long_help = "Refuse extensions that may access the network and shell commands such as curl or ssh. Extensions declare their network access with `network` in their config, builtins such as developer don't need it."
+        )]
+        no_network_tools: bool,
+
         /// Identifier for this run session
         #[command(flatten)]
         identifier: Option<Identifier>,
@@ -510,6 +655,16 @@ enum Command {
         )]
         quiet: bool,
 
+        /// How the session output is shown
+        #[arg(
+            long = "output",
+            value_name = "MODE",
+            value_enum,
+            help = "How to show output: rich, plain or quiet (default: rich on a terminal, plain otherwise)",
+            long_help = "Rich output uses colors, syntax highlighting and spinners. Plain output has no escape sequences or spinners, for files, pipes and screen readers. Quiet output shows only the final assistant text of each reply."
+        )]
+        output: Option<OutputMode>,
+
         /// Scheduled job ID (used internally for scheduled executions)
         #[arg(
             long = "scheduled-job-id",
@@ -602,6 +757,20 @@ struct InputConfig {
 pub async fn cli() -> Result<()> {
     let cli = Cli::parse();
 
+    // Encrypted sessions ask for their passphrase the first time one is opened
+    goose::session::encryption::set_passphrase_prompt(|prompt| {
+        Ok(cliclack::password(prompt).mask('▪').interact()?)
+    });
+
+    // Providers that sign in with the device flow show the code to enter here
+    goose::providers::deviceauth::set_device_prompt(|code| {
+        let _ = cliclack::log::info(format!(
+            "To sign in, open {} and enter the code {}",
+            console::style(&code.verification_uri).cyan(),
+            console::style(&code.user_code).bold()
+        ));
+    });
+
     // Track the current directory in projects.json
     if let Err(e) = crate::project_tracker::update_project_tracker(None, None) {
         eprintln!("Warning: Failed to update project tracker: {}", e);
@@ -625,11 +794,15 @@ pub async fn cli() -> Result<()> {
             resume,
             history,
             debug,
+            output,
             max_tool_repetitions,
+            no_validate,
+            no_network_tools,
             extensions,
             remote_extensions,
             builtins,
         }) => {
+            session::set_output_mode(output.unwrap_or_else(OutputMode::detect));
             return match command {
                 Some(SessionCommand::List {
                     verbose,
@@ -660,6 +833,85 @@ pub async fn cli() -> Result<()> {
                     crate::commands::session::handle_session_export(session_identifier, output)?;
                     Ok(())
                 }
+                Some(SessionCommand::Import { file, name }) => {
+                    crate::commands::session::handle_session_import(file, name)?;
+                    Ok(())
+                }
+                Some(SessionCommand::Compact { identifier }) => {
+                    let session_identifier = if let Some(id) = identifier {
+                        extract_identifier(id)
+                    } else {
+                        match crate::commands::session::prompt_interactive_session_selection() {
+                            Ok(id) => id,
+                            Err(e) => {
+                                eprintln!("Error: {}", e);
+                                return Ok(());
+                            }
+                        }
+                    };
+
+                    crate::commands::session::handle_session_compact(session_identifier)?;
+                    Ok(())
+                }
+                Some(SessionCommand::Stats { identifier, format }) => {
+                    let session_identifier = if let Some(id) = identifier {
+                        extract_identifier(id)
+                    } else {
+                        match crate::commands::session::prompt_interactive_session_selection() {
+                            Ok(id) => id,
+                            Err(e) => {
+                                eprintln!("Error: {}", e);
+                                return Ok(());
+                            }
+                        }
+                    };
+
+                    crate::commands::session::handle_session_stats(session_identifier, format)?;
+                    Ok(())
+                }
+                Some(SessionCommand::Search {
+                    query,
+                    since,
+                    until,
+                    role,
+                    limit,
+                    format,
+                }) => {
+                    crate::commands::session::handle_session_search(
+                        query, since, until, role, limit, format,
+                    )?;
+                    Ok(())
+                }
+                Some(SessionCommand::Open { id, at }) => {
+                    let mut session: crate::Session = build_session(SessionBuilderConfig {
+                        identifier: Some(session::Identifier::Name(id)),
+                        resume: true,
+                        no_session: false,
+                        extensions,
+                        remote_extensions,
+                        builtins,
+                        extensions_override: None,
+                        additional_system_prompt: None,
+                        settings: None,
+                        debug,
+                        max_tool_repetitions,
+                        no_validate,
+                        no_network_tools,
+                        scheduled_job_id: None,
+                        interactive: true,
+                        quiet: false,
+                        sub_recipes: None,
+                    })
+                    .await;
+                    setup_logging(
+                        session.session_file().file_stem().and_then(|s| s.to_str()),
+                        None,
+                    )?;
+
+                    session.render_message_history_from(at.unwrap_or(0));
+                    let _ = session.interactive(None).await;
+                    Ok(())
+                }
                 None => {
                     // Run session command by default
                     let mut session: crate::Session = build_session(SessionBuilderConfig {
@@ -674,6 +926,8 @@ pub async fn cli() -> Result<()> {
                         settings: None,
                         debug,
                         max_tool_repetitions,
+                        no_validate,
+                        no_network_tools,
                         scheduled_job_id: None,
                         interactive: true,
                         quiet: false,
@@ -705,6 +959,10 @@ pub async fn cli() -> Result<()> {
             handle_projects_interactive()?;
             return Ok(());
         }
+        Some(Command::Stats { json }) => {
+            handle_stats(json).await?;
+            return Ok(());
+        }
 
         Some(Command::Run {
             instructions,
@@ -717,15 +975,24 @@ pub async fn cli() -> Result<()> {
             no_session,
             debug,
             max_tool_repetitions,
+            no_validate,
+            no_network_tools,
             extensions,
             remote_extensions,
             builtins,
             params,
             explain,
             render_recipe,
+            dry_run,
             scheduled_job_id,
             quiet,
+            output,
         }) => {
+            session::set_output_mode(if quiet {
+                OutputMode::Quiet
+            } else {
+                output.unwrap_or_else(OutputMode::detect)
+            });
             let (input_config, session_settings, sub_recipes) = match (
                 instructions,
                 input_text,
@@ -827,6 +1094,8 @@ pub async fn cli() -> Result<()> {
                 settings: session_settings,
                 debug,
                 max_tool_repetitions,
+                no_validate,
+                no_network_tools,
                 scheduled_job_id,
                 interactive, // Use the interactive flag from the Run command
                 quiet,
@@ -839,7 +1108,16 @@ pub async fn cli() -> Result<()> {
                 None,
             )?;
 
-            if interactive {
+            if dry_run {
+                let Some(contents) = input_config.contents else {
+                    eprintln!("Error: no text provided for prompt in dry run mode");
+                    std::process::exit(1);
+                };
+                if let Err(e) = session.dry_run(contents).await {
+                    eprintln!("{}: {}", console::style("Error").red().bold(), e);
+                    std::process::exit(1);
+                }
+            } else if interactive {
                 let _ = session.interactive(input_config.contents).await;
             } else if let Some(contents) = input_config.contents {
                 let _ = session.headless(contents).await;
@@ -946,6 +1224,8 @@ pub async fn cli() -> Result<()> {
                     settings: None::<SessionSettings>,
                     debug: false,
                     max_tool_repetitions: None,
+                    no_validate: false,
+                    no_network_tools: false,
                     scheduled_job_id: None,
                     interactive: true, // Default case is always interactive
                     quiet: false,
@@ -43,6 +43,8 @@ pub async fn agent_generator(
         settings: None,
         debug: false,
         max_tool_repetitions: None,
+        no_validate: false,
+        no_network_tools: false,
         interactive: false, // Benchmarking is non-interactive
         scheduled_job_id: None,
         quiet: false,
@@ -14,6 +14,7 @@ use goose::config::{
     PermissionManager,
 };
 use goose::message::Message;
+use goose::providers::errors::ProviderError;
 use goose::providers::{create, providers};
 use mcp_core::tool::ToolAnnotations;
 use mcp_core::Tool;
@@ -80,6 +81,7 @@ pub async fn handle_configure() -> Result<(), Box<dyn Error>> {
                         display_name: Some(goose::config::DEFAULT_DISPLAY_NAME.to_string()),
                         timeout: Some(goose::config::DEFAULT_EXTENSION_TIMEOUT),
                         bundled: Some(true),
+                        network: None,
                     },
                 })?;
             }
@@ -422,6 +424,12 @@ pub async fn configure_provider_dialog() -> Result<bool, Box<dyn Error>> {
             cliclack::outro("Configuration saved successfully")?;
             Ok(true)
         }
+        Err(ProviderError::Setup(e)) => {
+            spin.stop(style(e.to_string()).red());
+            let _ = cliclack::log::info(e.suggestion());
+            cliclack::outro(style("Failed to configure provider: init chat completion request with tool did not succeed.").on_red().white())?;
+            Ok(false)
+        }
         Err(e) => {
             spin.stop(style(e.to_string()).red());
             cliclack::outro(style("Failed to configure provider: init chat completion request with tool did not succeed.").on_red().white())?;
@@ -553,6 +561,7 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
                     display_name: Some(display_name),
                     timeout: Some(timeout),
                     bundled: Some(true),
+                    network: None,
                 },
             })?;
 
@@ -660,6 +669,7 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
                     description,
                     timeout: Some(timeout),
                     bundled: None,
+                    network: None,
                 },
             })?;
 
@@ -762,6 +772,7 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
                     description,
                     timeout: Some(timeout),
                     bundled: None,
+                    network: None,
                 },
             })?;
 
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use chrono::DateTime;
 use cliclack::{self, intro, outro};
+use goose::utils::safe_truncate;
 use std::path::Path;
 
 use crate::project_tracker::ProjectTracker;
@@ -199,11 +200,7 @@ pub fn handle_projects_interactive() -> Result<()> {
                     .last_instruction
                     .as_ref()
                     .map_or(String::new(), |instr| {
-                        let truncated = if instr.len() > 40 {
-                            format!("{}...", &instr[0..37])
-                        } else {
-                            instr.clone()
-                        };
+                        let truncated = safe_truncate(instr, 40);
                         format!(" [{}]", truncated)
                     });
 
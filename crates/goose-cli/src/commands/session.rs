@@ -1,8 +1,9 @@
-use crate::session::message_to_markdown;
+use crate::session::message_with_footnotes_to_markdown;
 use anyhow::{Context, Result};
 use cliclack::{confirm, multiselect, select};
 use goose::session::info::{get_session_info, SessionInfo, SortOrder};
-use goose::session::{self, Identifier};
+use goose::session::{self, Identifier, SearchFilter, SearchRole, SessionMetadata, SessionStats};
+use goose::utils::safe_truncate;
 use regex::Regex;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -21,7 +22,7 @@ pub fn remove_sessions(sessions: Vec<SessionInfo>) -> Result<()> {
 
     if should_delete {
         for session in sessions {
-            fs::remove_file(session.path.clone())
+            session::delete_session(Path::new(&session.path))
                 .with_context(|| format!("Failed to remove session file '{}'", session.path))?;
             println!("Session `{}` removed.", session.id);
         }
@@ -50,11 +51,7 @@ fn prompt_interactive_session_removal(sessions: &[SessionInfo]) -> Result<Vec<Se
             } else {
                 &s.metadata.description
             };
-            let truncated_desc = if desc.len() > TRUNCATED_DESC_LENGTH {
-                format!("{}...", &desc[..TRUNCATED_DESC_LENGTH - 3])
-            } else {
-                desc.to_string()
-            };
+            let truncated_desc = safe_truncate(desc, TRUNCATED_DESC_LENGTH);
             let display_text = format!("{} - {} ({})", s.modified, truncated_desc, s.id);
             (display_text, s.clone())
         })
@@ -212,6 +209,246 @@ pub fn handle_session_export(identifier: Identifier, output_path: Option<PathBuf
     Ok(())
 }
 
+/// Import conversations from a ChatGPT or Claude export, one resumable session each
+pub fn handle_session_import(file: PathBuf, name: Option<String>) -> Result<()> {
+    let conversations = session::import::import_file(&file)?;
+    if conversations.is_empty() {
+        println!("No conversations found in {}", file.display());
+        return Ok(());
+    }
+
+    let base_name = name.unwrap_or_else(session::generate_session_id);
+    let working_dir = std::env::current_dir()?;
+    let multiple = conversations.len() > 1;
+
+    for (index, conversation) in conversations.into_iter().enumerate() {
+        let session_name = if multiple {
+            format!("{}_{}", base_name, index + 1)
+        } else {
+            base_name.clone()
+        };
+        let title = conversation
+            .title
+            .clone()
+            .unwrap_or_else(|| "(untitled)".to_string());
+
+        for warning in &conversation.warnings {
+            eprintln!("Warning ({}): {}", title, warning);
+        }
+        if conversation.messages.is_empty() {
+            println!(
+                "Skipped '{}', it has no messages that could be imported",
+                title
+            );
+            continue;
+        }
+
+        let session_file = session::get_path(Identifier::Name(session_name.clone()))?;
+        if session_file.exists() {
+            return Err(anyhow::anyhow!(
+                "Session '{}' already exists, choose another name with --name",
+                session_name
+            ));
+        }
+
+        let mut metadata = SessionMetadata::new(working_dir.clone());
+        metadata.description = conversation.title.unwrap_or_default();
+        metadata.message_count = conversation.messages.len();
+        session::storage::save_messages_with_metadata(
+            &session_file,
+            &metadata,
+            &conversation.messages,
+        )?;
+
+        println!(
+            "Imported '{}' as session {} (resume with: goose session --resume --name {})",
+            title, session_name, session_name
+        );
+    }
+
+    Ok(())
+}
+
+/// Rewrite a session file in full and report the blobs it no longer needed
+pub fn handle_session_compact(identifier: Identifier) -> Result<()> {
+    let session_file_path = match goose::session::get_path(identifier) {
+        Ok(path) => path,
+        Err(e) => {
+            return Err(anyhow::anyhow!("Invalid session identifier: {}", e));
+        }
+    };
+
+    if !session_file_path.exists() {
+        return Err(anyhow::anyhow!(
+            "Session file not found (expected path: {})",
+            session_file_path.display()
+        ));
+    }
+
+    let report = session::compact_session(&session_file_path)?;
+    println!(
+        "Compacted {} messages, removed {} unused blobs ({} bytes)",
+        report.messages, report.removed_blobs, report.freed_bytes
+    );
+    Ok(())
+}
+
+/// Show latency statistics aggregated over the turns recorded in a session
+pub fn handle_session_stats(identifier: Identifier, format: String) -> Result<()> {
+    let session_file_path = match goose::session::get_path(identifier) {
+        Ok(path) => path,
+        Err(e) => {
+            return Err(anyhow::anyhow!("Invalid session identifier: {}", e));
+        }
+    };
+
+    if !session_file_path.exists() {
+        return Err(anyhow::anyhow!(
+            "Session file not found (expected path: {})",
+            session_file_path.display()
+        ));
+    }
+
+    let metadata = goose::session::read_metadata(&session_file_path)?;
+    let stats = SessionStats::from_turns(&metadata.turn_stats);
+
+    match format.as_str() {
+        "json" => {
+            println!("{}", serde_json::to_string(&stats)?);
+        }
+        _ => {
+            if stats.turns == 0 {
+                println!("No turn statistics recorded for this session");
+                return Ok(());
+            }
+
+            let ms = |value: Option<u64>| {
+                value.map_or_else(|| "n/a".to_string(), |v| format!("{} ms", v))
+            };
+            println!("Turns: {}", stats.turns);
+            println!("Provider calls: {}", stats.provider_calls);
+            println!(
+                "Average turn duration: {}",
+                ms(stats.average_turn_duration_ms)
+            );
+            println!(
+                "Average provider latency: {}",
+                ms(stats.average_provider_latency_ms)
+            );
+            println!(
+                "Average time to first token: {}",
+                ms(stats.average_time_to_first_token_ms)
+            );
+            println!("Total turn duration: {} ms", stats.total_turn_duration_ms);
+            println!(
+                "Total provider latency: {} ms",
+                stats.total_provider_latency_ms
+            );
+            if !stats.tools.is_empty() {
+                println!("Tools:");
+                for tool in &stats.tools {
+                    println!(
+                        "  {} - {} calls, {} ms total, {} ms max",
+                        tool.name, tool.calls, tool.total_ms, tool.max_ms
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parse a YYYY-MM-DD date given to session search
+fn parse_search_date(date: &str) -> Result<chrono::NaiveDate> {
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .with_context(|| format!("Invalid date '{}', expected YYYY-MM-DD", date))
+}
+
+/// The unix timestamp at which a date starts in local time
+fn start_of_day(date: chrono::NaiveDate) -> Option<i64> {
+    date.and_hms_opt(0, 0, 0)?
+        .and_local_timezone(chrono::Local)
+        .earliest()
+        .map(|time| time.timestamp())
+}
+
+pub fn handle_session_search(
+    query: String,
+    since: Option<String>,
+    until: Option<String>,
+    role: Option<String>,
+    limit: usize,
+    format: String,
+) -> Result<()> {
+    let since = since.as_deref().map(parse_search_date).transpose()?;
+    let until = until.as_deref().map(parse_search_date).transpose()?;
+    let filter = SearchFilter {
+        since: since.and_then(start_of_day),
+        // The end date is included, so the range ends when the next day starts
+        until: until.and_then(|date| start_of_day(date.succ_opt()?)),
+        role: role.as_deref().map(str::parse::<SearchRole>).transpose()?,
+        limit: Some(limit),
+    };
+
+    let hits = session::search_sessions(&query, &filter)?;
+    match format.as_str() {
+        "json" => {
+            println!("{}", serde_json::to_string(&hits)?);
+        }
+        _ => {
+            if hits.is_empty() {
+                println!("No matching messages found");
+                return Ok(());
+            }
+            for hit in &hits {
+                let created = chrono::DateTime::from_timestamp(hit.created, 0)
+                    .map(|time| {
+                        time.with_timezone(&chrono::Local)
+                            .format("%Y-%m-%d %H:%M")
+                            .to_string()
+                    })
+                    .unwrap_or_default();
+                let role = serde_json::to_value(hit.role)?
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+                println!(
+                    "{} {} {}",
+                    console::style(format!("{} #{}", hit.session_id, hit.message_index)).cyan(),
+                    console::style(role).dim(),
+                    console::style(created).dim()
+                );
+                println!("  {}\n", highlight_snippet(&hit.snippet, &hit.highlights));
+            }
+            println!(
+                "{}",
+                console::style("Open a result with: goose session open <ID> --at <INDEX>").dim()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn highlight_snippet(snippet: &str, highlights: &[(usize, usize)]) -> String {
+    let mut rendered = String::new();
+    let mut position = 0;
+    for &(start, end) in highlights {
+        if start < position || end > snippet.len() {
+            continue;
+        }
+        rendered.push_str(&snippet[position..start]);
+        rendered.push_str(
+            &console::style(&snippet[start..end])
+                .yellow()
+                .bold()
+                .to_string(),
+        );
+        position = end;
+    }
+    rendered.push_str(&snippet[position..]);
+    rendered
+}
+
 /// Convert a list of messages to markdown format for session export
 ///
 /// This function handles the formatting of a complete session including headers,
@@ -241,8 +478,13 @@ fn export_session_to_markdown(
 
     // Track if the last message had tool requests to properly handle tool responses
     let mut skip_next_if_tool_response = false;
+    // Footnotes of citations are numbered through the whole export
+    let mut footnotes = 0;
 
     for message in &messages {
+        let footnote_offset = footnotes;
+        footnotes += message.citation_sources().len();
+
         // Check if this is a User message containing only ToolResponses
         let is_only_tool_response = message.role == mcp_core::role::Role::User
             && message
@@ -254,7 +496,11 @@ fn export_session_to_markdown(
         // don't create a new User section - we'll attach the responses to the tool calls
         if skip_next_if_tool_response && is_only_tool_response {
             // Export the tool responses without a User heading
-            markdown_output.push_str(&message_to_markdown(message, false));
+            markdown_output.push_str(&message_with_footnotes_to_markdown(
+                message,
+                false,
+                footnote_offset,
+            ));
             markdown_output.push_str("\n\n---\n\n");
             skip_next_if_tool_response = false;
             continue;
@@ -273,7 +519,11 @@ fn export_session_to_markdown(
         }
 
         // Add the message content
-        markdown_output.push_str(&message_to_markdown(message, false));
+        markdown_output.push_str(&message_with_footnotes_to_markdown(
+            message,
+            false,
+            footnote_offset,
+        ));
         markdown_output.push_str("\n\n---\n\n");
 
         // Check if this message has any tool requests, to handle the next message differently
@@ -320,11 +570,7 @@ pub fn prompt_interactive_session_selection() -> Result<session::Identifier> {
             };
 
             // Truncate description if too long
-            let truncated_desc = if desc.len() > 40 {
-                format!("{}...", &desc[..37])
-            } else {
-                desc.to_string()
-            };
+            let truncated_desc = safe_truncate(desc, 40);
 
             let display_text = format!("{} - {} ({})", s.modified, truncated_desc, s.id);
             (display_text, s.clone())
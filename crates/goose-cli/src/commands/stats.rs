@@ -0,0 +1,76 @@
+use anyhow::Result;
+use goose::providers::pricing::get_all_pricing;
+use goose::session::analytics::aggregate_sessions;
+
+fn ms(value: Option<u64>) -> String {
+    value.map_or_else(|| "n/a".to_string(), |v| format!("{} ms", v))
+}
+
+fn tokens(value: Option<i32>) -> String {
+    value.map_or_else(|| "n/a".to_string(), |v| v.to_string())
+}
+
+/// Show which tools and models all saved sessions used, as a table or as JSON
+pub async fn handle_stats(json: bool) -> Result<()> {
+    let mut report = aggregate_sessions()?;
+
+    // Pricing is cached by provider, the usage of a session only knows the model
+    let pricing = get_all_pricing().await;
+    report.estimate_costs(|model| {
+        pricing
+            .values()
+            .find_map(|models| models.get(model))
+            .cloned()
+    });
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    if report.sessions == 0 {
+        println!("No tool or model usage recorded in any session");
+        return Ok(());
+    }
+
+    println!("Sessions: {}", report.sessions);
+    if !report.tools.is_empty() {
+        println!();
+        println!(
+            "{:<32} {:>8} {:>9} {:>10} {:>10} {:>14}",
+            "Tool", "Calls", "Failures", "p50", "p95", "Result tokens"
+        );
+        for tool in &report.tools {
+            println!(
+                "{:<32} {:>8} {:>8.1}% {:>10} {:>10} {:>14}",
+                tool.name,
+                tool.calls,
+                tool.failure_rate * 100.0,
+                ms(tool.p50_ms),
+                ms(tool.p95_ms),
+                tool.average_result_tokens
+                    .map_or_else(|| "n/a".to_string(), |v| v.to_string()),
+            );
+        }
+    }
+    if !report.models.is_empty() {
+        println!();
+        println!(
+            "{:<32} {:>8} {:>12} {:>12} {:>10}",
+            "Model", "Requests", "Input", "Output", "Cost"
+        );
+        for model in &report.models {
+            println!(
+                "{:<32} {:>8} {:>12} {:>12} {:>10}",
+                model.model,
+                model.requests,
+                tokens(model.input_tokens),
+                tokens(model.output_tokens),
+                model
+                    .estimated_cost
+                    .map_or_else(|| "n/a".to_string(), |cost| format!("${:.2}", cost)),
+            );
+        }
+    }
+    Ok(())
+}
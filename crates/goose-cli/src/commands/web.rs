@@ -618,6 +618,46 @@ async fn process_message_streaming(
                         // Log model change
                         tracing::info!("Model changed to {} in {} mode", model, mode);
                     }
+                    Ok(AgentEvent::TurnStats(stats)) => {
+                        tracing::info!("Turn stats: {:?}", stats);
+                    }
+                    Ok(AgentEvent::ApprovalTimedOut {
+                        tool_name,
+                        approved,
+                        ..
+                    }) => {
+                        tracing::warn!(
+                            "Approval for {} timed out, approved: {}",
+                            tool_name,
+                            approved
+                        );
+                    }
+                    Ok(AgentEvent::UserFacingToolOutput { tool_name, .. }) => {
+                        tracing::debug!(
+                            "User-facing output from {} is not shown on the web",
+                            tool_name
+                        );
+                    }
+                    Ok(AgentEvent::Thinking(_))
+                    | Ok(AgentEvent::ToolCall { .. })
+                    | Ok(AgentEvent::ToolCallProgress { .. })
+                    | Ok(AgentEvent::Usage(_))
+                    | Ok(AgentEvent::CumulativeUsage { .. })
+                    | Ok(AgentEvent::ToolProgress { .. })
+                    | Ok(AgentEvent::Compacted { .. })
+                    | Ok(AgentEvent::SoftStop)
+                    | Ok(AgentEvent::OversizedMessage(_)) => {
+                        // The full message is sent to the browser once it is complete
+                    }
+                    Ok(AgentEvent::Interjection(message)) => {
+                        // Record the interjection at its position in the conversation
+                        let current_messages = {
+                            let mut session_msgs = session_messages.lock().await;
+                            session_msgs.push(message);
+                            session_msgs.clone()
+                        };
+                        session::persist_messages(&session_file, &current_messages, None).await?;
+                    }
 
                     Err(e) => {
                         error!("Error in message stream: {}", e);
@@ -1,7 +1,10 @@
 use anyhow::Result;
 use goose_cli::cli::cli;
+use goose_cli::session::remove_saved_images;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    cli().await
+    let result = cli().await;
+    remove_saved_images();
+    result
 }
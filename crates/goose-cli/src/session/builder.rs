@@ -41,6 +41,10 @@ pub struct SessionBuilderConfig {
     pub debug: bool,
     /// Maximum number of consecutive identical tool calls allowed
     pub max_tool_repetitions: Option<u32>,
+    /// Whether requests skip the checks against known model capabilities
+    pub no_validate: bool,
+    /// Whether extensions and shell commands that reach the network are refused
+    pub no_network_tools: bool,
     /// ID of the scheduled job that triggered this session (if any)
     pub scheduled_job_id: Option<String>,
     /// Whether this session will be used interactively (affects debugging prompts)
@@ -145,6 +149,49 @@ async fn offer_extension_debugging_help(
     Ok(())
 }
 
+/// Offers to trust the workspace when its config has privileged settings, such as commands,
+/// which are ignored until it is trusted
+fn check_workspace_trust(interactive: bool) {
+    let config = Config::global();
+    let workspace = match config.workspace() {
+        Ok(Some(workspace)) => workspace,
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!("{}", style(format!("Warning: {}", e)).yellow());
+            return;
+        }
+    };
+    let privileged = workspace.privileged_keys();
+    if privileged.is_empty() || config.is_workspace_trusted(&workspace.root) {
+        return;
+    }
+
+    let prompt = format!(
+        "The workspace config in {} sets {}, which can run commands or send requests elsewhere. Do you trust this workspace?",
+        style(workspace.root.display()).cyan(),
+        privileged.join(", ")
+    );
+    let trusted = interactive
+        && cliclack::confirm(prompt)
+            .initial_value(false)
+            .interact()
+            .unwrap_or(false);
+    if !trusted {
+        eprintln!(
+            "{}",
+            style(format!(
+                "Ignoring {} from the untrusted workspace config",
+                privileged.join(", ")
+            ))
+            .yellow()
+        );
+        return;
+    }
+    if let Err(e) = config.trust_workspace(&workspace.root) {
+        output::render_error(&format!("Failed to trust the workspace: {}", e));
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct SessionSettings {
     pub goose_model: Option<String>,
@@ -155,6 +202,7 @@ pub struct SessionSettings {
 pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
     // Load config and get provider/model
     let config = Config::global();
+    check_workspace_trust(session_config.interactive);
 
     let provider_name = session_config
         .settings
@@ -172,8 +220,9 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
 
     let temperature = session_config.settings.as_ref().and_then(|s| s.temperature);
 
-    let model_config =
-        goose::model::ModelConfig::new(model_name.clone()).with_temperature(temperature);
+    let model_config = goose::model::ModelConfig::new(model_name.clone())
+        .with_temperature(temperature)
+        .with_no_validate(session_config.no_validate);
 
     // Create the agent
     let agent: Agent = Agent::new();
@@ -216,6 +265,10 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
             process::exit(1);
         });
 
+    if session_config.no_network_tools {
+        agent.disable_network_tools().await;
+    }
+
     // Configure tool monitoring if max_tool_repetitions is set
     if let Some(max_repetitions) = session_config.max_tool_repetitions {
         agent.configure_tool_monitor(Some(max_repetitions)).await;
@@ -324,6 +377,10 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
 
     for extension in extensions_to_run {
         if let Err(e) = agent.add_extension(extension.clone()).await {
+            if let ExtensionError::NetworkDisabled(_) = e {
+                eprintln!("{}", style(format!("Skipping extension: {}", e)).yellow());
+                continue;
+            }
             let err = match e {
                 ExtensionError::Transport(McpClientError::StdioProcessError(inner)) => inner,
                 _ => e.to_string(),
@@ -366,7 +423,8 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
         session_file.clone(),
         session_config.debug,
         session_config.scheduled_job_id.clone(),
-    );
+    )
+    .with_provider_name(&provider_name);
 
     // Add extensions if provided
     for extension_str in session_config.extensions {
@@ -476,6 +534,16 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
         session.agent.extend_system_prompt(additional_prompt).await;
     }
 
+    // The workspace may add instructions and limit the tools
+    if let Ok(Some(workspace)) = config.workspace() {
+        for instruction in workspace.config.instructions {
+            session.agent.extend_system_prompt(instruction).await;
+        }
+        if let Some(patterns) = workspace.config.allowed_tools {
+            session.agent.set_tool_allowlist(patterns).await;
+        }
+    }
+
     // Only override system prompt if a system override exists
     let system_prompt_file: Option<String> = config.get_param("GOOSE_SYSTEM_PROMPT_FILE_PATH").ok();
     if let Some(ref path) = system_prompt_file {
@@ -515,6 +583,8 @@ mod tests {
             settings: None,
             debug: true,
             max_tool_repetitions: Some(5),
+            no_validate: false,
+            no_network_tools: false,
             scheduled_job_id: None,
             interactive: true,
             quiet: false,
@@ -545,6 +615,8 @@ mod tests {
         assert!(config.additional_system_prompt.is_none());
         assert!(!config.debug);
         assert!(config.max_tool_repetitions.is_none());
+        assert!(!config.no_validate);
+        assert!(!config.no_network_tools);
         assert!(config.scheduled_job_id.is_none());
         assert!(!config.interactive);
         assert!(!config.quiet);
@@ -1,13 +1,14 @@
-use rustyline::completion::{Completer, Pair};
-use rustyline::highlight::{CmdKind, Highlighter};
-use rustyline::hint::Hinter;
-use rustyline::validate::Validator;
-use rustyline::{Helper, Result};
-use std::borrow::Cow;
+use reedline::{Completer, Highlighter, Hinter, History, Span, StyledText, Suggestion};
 use std::sync::Arc;
 
 use super::CompletionCache;
 
+/// A completion candidate, listed as `display` and inserted as `replacement`
+struct Pair {
+    display: String,
+    replacement: String,
+}
+
 /// Completer for Goose CLI commands
 pub struct GooseCompleter {
     completion_cache: Arc<std::sync::RwLock<CompletionCache>>,
@@ -20,7 +21,7 @@ impl GooseCompleter {
     }
 
     /// Complete prompt names for the /prompt command
-    fn complete_prompt_names(&self, line: &str) -> Result<(usize, Vec<Pair>)> {
+    fn complete_prompt_names(&self, line: &str) -> (usize, Vec<Pair>) {
         // Get the prefix of the prompt name being typed
         let prefix = if line.len() > 8 { &line[8..] } else { "" };
 
@@ -39,11 +40,11 @@ impl GooseCompleter {
             })
             .collect();
 
-        Ok((8, candidates))
+        (8, candidates)
     }
 
     /// Complete flags for the /prompt command
-    fn complete_prompt_flags(&self, line: &str) -> Result<(usize, Vec<Pair>)> {
+    fn complete_prompt_flags(&self, line: &str) -> (usize, Vec<Pair>) {
         // Get the last part of the line
         let parts: Vec<&str> = line.split_whitespace().collect();
         if let Some(last_part) = parts.last() {
@@ -66,24 +67,24 @@ impl GooseCompleter {
                     // Return matches for the partial flag
                     // The position is the start of the last word
                     let pos = line.len() - last_part.len();
-                    return Ok((pos, matching_flags));
+                    return (pos, matching_flags);
                 }
             }
         }
 
         // No flag completions available
-        Ok((line.len(), vec![]))
+        (line.len(), vec![])
     }
 
     /// Complete flags for the /mode command
-    fn complete_mode_flags(&self, line: &str) -> Result<(usize, Vec<Pair>)> {
+    fn complete_mode_flags(&self, line: &str) -> (usize, Vec<Pair>) {
         let modes = ["auto", "approve", "smart_approve", "chat"];
 
         let parts: Vec<&str> = line.split_whitespace().collect();
 
         // If we're just after "/mode" with a space, show all options
         if line == "/mode " {
-            return Ok((
+            return (
                 line.len(),
                 modes
                     .iter()
@@ -92,13 +93,13 @@ impl GooseCompleter {
                         replacement: format!("{} ", mode),
                     })
                     .collect(),
-            ));
+            );
         }
 
         // If we're typing a mode name, show the flags for that mode
         if parts.len() == 2 {
             let partial = parts[1].to_lowercase();
-            return Ok((
+            return (
                 line.len() - partial.len(),
                 modes
                     .iter()
@@ -108,15 +109,27 @@ impl GooseCompleter {
                         replacement: format!("{} ", mode),
                     })
                     .collect(),
-            ));
+            );
         }
 
         // No completions available
-        Ok((line.len(), vec![]))
+        (line.len(), vec![])
+    }
+
+    /// Complete the model names of the current provider for the /model command
+    fn complete_model_names(&self, line: &str) -> (usize, Vec<Pair>) {
+        let cache = self.completion_cache.read().unwrap();
+        complete_argument(line, &cache.models)
+    }
+
+    /// Complete provider names for the /provider command
+    fn complete_provider_names(&self, line: &str) -> (usize, Vec<Pair>) {
+        let cache = self.completion_cache.read().unwrap();
+        complete_argument(line, &cache.providers)
     }
 
     /// Complete slash commands
-    fn complete_slash_commands(&self, line: &str) -> Result<(usize, Vec<Pair>)> {
+    fn complete_slash_commands(&self, line: &str) -> (usize, Vec<Pair>) {
         // Define available slash commands
         let commands = [
             "/exit",
@@ -129,7 +142,13 @@ impl GooseCompleter {
             "/prompts",
             "/prompt",
             "/mode",
+            "/plan",
+            "/endplan",
             "/recipe",
+            "/summarize",
+            "/clear",
+            "/model",
+            "/provider",
         ];
 
         // Find commands that match the prefix
@@ -143,20 +162,20 @@ impl GooseCompleter {
             .collect();
 
         if !matching_commands.is_empty() {
-            return Ok((0, matching_commands));
+            return (0, matching_commands);
         }
 
         // No command completions available
-        Ok((line.len(), vec![]))
+        (line.len(), vec![])
     }
 
     /// Complete argument keys for a specific prompt
-    fn complete_argument_keys(&self, line: &str) -> Result<(usize, Vec<Pair>)> {
+    fn complete_argument_keys(&self, line: &str) -> (usize, Vec<Pair>) {
         let parts: Vec<&str> = line[8..].split_whitespace().collect();
 
         // We need at least the prompt name
         if parts.is_empty() {
-            return Ok((line.len(), vec![]));
+            return (line.len(), vec![]);
         }
 
         let prompt_name = parts[0];
@@ -185,7 +204,7 @@ impl GooseCompleter {
                     // ignore if last_part starts with = / \ for suggestions
                     if let Some(c) = last_part.chars().next() {
                         if matches!(c, '=' | '/' | '\\') {
-                            return Ok((line.len(), vec![]));
+                            return (line.len(), vec![]);
                         }
                     }
 
@@ -208,13 +227,13 @@ impl GooseCompleter {
                             // Return matches for the partial argument name
                             // The position is the start of the last word
                             let pos = line.len() - last_part.len();
-                            return Ok((pos, matching_args));
+                            return (pos, matching_args);
                         }
 
                         // If we have a partial argument that doesn't match anything,
                         // return an empty list rather than suggesting unrelated arguments
                         if !last_part.is_empty() && *last_part != prompt_name {
-                            return Ok((line.len(), vec![]));
+                            return (line.len(), vec![]);
                         }
                     }
                 }
@@ -233,7 +252,7 @@ impl GooseCompleter {
                 }
 
                 if !candidates.is_empty() {
-                    return Ok((line.len(), candidates));
+                    return (line.len(), candidates);
                 }
 
                 // If no required arguments left, suggest all optional ones
@@ -247,27 +266,21 @@ impl GooseCompleter {
                         });
                     }
                 }
-                return Ok((line.len(), candidates));
+                return (line.len(), candidates);
             }
         }
 
         // No completions available
-        Ok((line.len(), vec![]))
+        (line.len(), vec![])
     }
 }
 
-impl Completer for GooseCompleter {
-    type Candidate = Pair;
-
-    fn complete(
-        &self,
-        line: &str,
-        pos: usize,
-        _ctx: &rustyline::Context<'_>,
-    ) -> Result<(usize, Vec<Self::Candidate>)> {
+impl GooseCompleter {
+    /// Complete the line at `pos`, returning where the replacement starts and the candidates
+    fn complete_line(&self, line: &str, pos: usize) -> (usize, Vec<Pair>) {
         // If the cursor is not at the end of the line, don't try to complete
         if pos < line.len() {
-            return Ok((pos, vec![]));
+            return (pos, vec![]);
         }
 
         // If the line starts with '/', it might be a slash command
@@ -310,13 +323,13 @@ impl Completer for GooseCompleter {
                 // If we're just after "/prompts" with a space
                 if line == "/prompts " {
                     // Suggest the --extension flag
-                    return Ok((
+                    return (
                         line.len(),
                         vec![Pair {
                             display: "--extension".to_string(),
                             replacement: "--extension ".to_string(),
                         }],
-                    ));
+                    );
                 }
 
                 // Check if we might be typing the --extension flag
@@ -325,73 +338,111 @@ impl Completer for GooseCompleter {
                     && parts[1].starts_with('-')
                     && "--extension".starts_with(parts[1])
                 {
-                    return Ok((
+                    return (
                         line.len() - parts[1].len(),
                         vec![Pair {
                             display: "--extension".to_string(),
                             replacement: "--extension ".to_string(),
                         }],
-                    ));
+                    );
                 }
             }
 
+            if line.starts_with("/model ") {
+                return self.complete_model_names(line);
+            }
+
+            if line.starts_with("/provider ") {
+                return self.complete_provider_names(line);
+            }
+
             if line.starts_with("/mode") {
                 return self.complete_mode_flags(line);
             }
         }
 
         // Default: no completions
-        Ok((pos, vec![]))
+        (pos, vec![])
     }
 }
 
-// Implement the Helper trait which is required by rustyline
-impl Helper for GooseCompleter {}
-
-// Implement required traits with default implementations
-impl Hinter for GooseCompleter {
-    type Hint = String;
+/// Complete the first argument of a command from `choices`
+fn complete_argument(line: &str, choices: &[String]) -> (usize, Vec<Pair>) {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let partial = match parts[..] {
+        [_] if line.ends_with(' ') => "",
+        [_, partial] if !line.ends_with(' ') => partial,
+        _ => return (line.len(), vec![]),
+    };
+    let candidates = choices
+        .iter()
+        .filter(|choice| choice.starts_with(partial))
+        .map(|choice| Pair {
+            display: choice.clone(),
+            replacement: choice.clone(),
+        })
+        .collect();
+    (line.len() - partial.len(), candidates)
+}
 
-    fn hint(&self, line: &str, _pos: usize, _ctx: &rustyline::Context<'_>) -> Option<Self::Hint> {
-        // Only show hint when line is empty
-        if line.is_empty() {
-            Some("Press Enter to send, Ctrl-J for new line".to_string())
-        } else {
-            None
-        }
+impl Completer for GooseCompleter {
+    fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
+        let (start, candidates) = self.complete_line(line, pos);
+        candidates
+            .into_iter()
+            .map(|pair| Suggestion {
+                // The menu lists the value, so the separating space is appended on selection
+                append_whitespace: pair.replacement.ends_with(' '),
+                value: pair.display,
+                span: Span::new(start, pos),
+                ..Default::default()
+            })
+            .collect()
     }
 }
 
-impl Highlighter for GooseCompleter {
-    fn highlight_prompt<'b, 's: 'b, 'p: 'b>(
-        &'s self,
-        prompt: &'p str,
-        _default: bool,
-    ) -> Cow<'b, str> {
-        Cow::Borrowed(prompt)
-    }
+/// Hints how to send or continue the input while the line is empty
+pub struct GooseHinter;
 
-    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
-        // Style the hint text with a dim color
-        let styled = console::Style::new().dim().apply_to(hint).to_string();
-        Cow::Owned(styled)
+impl Hinter for GooseHinter {
+    fn handle(
+        &mut self,
+        line: &str,
+        _pos: usize,
+        _history: &dyn History,
+        use_ansi_coloring: bool,
+        _cwd: &str,
+    ) -> String {
+        if !line.is_empty() {
+            return String::new();
+        }
+        let hint = "Press Enter to send, Ctrl-J for new line";
+        if use_ansi_coloring {
+            // Style the hint text with a dim color
+            console::Style::new().dim().apply_to(hint).to_string()
+        } else {
+            hint.to_string()
+        }
     }
 
-    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
-        Cow::Borrowed(line)
+    // The hint only explains the keys, it is never inserted into the line
+    fn complete_hint(&self) -> String {
+        String::new()
     }
 
-    fn highlight_char(&self, _line: &str, _pos: usize, _cmd_kind: CmdKind) -> bool {
-        false
+    fn next_hint_token(&self) -> String {
+        String::new()
     }
 }
 
-impl Validator for GooseCompleter {
-    fn validate(
-        &self,
-        _ctx: &mut rustyline::validate::ValidationContext,
-    ) -> rustyline::Result<rustyline::validate::ValidationResult> {
-        Ok(rustyline::validate::ValidationResult::Valid(None))
+/// Leaves the input in the terminal's own colors, which suit light and dark themes alike
+pub struct PlainHighlighter;
+
+impl Highlighter for PlainHighlighter {
+    fn highlight(&self, line: &str, _cursor: usize) -> StyledText {
+        let mut styled = StyledText::new();
+        styled.push((Default::default(), line.to_string()));
+        styled
     }
 }
 
@@ -473,55 +524,115 @@ mod tests {
         let completer = GooseCompleter::new(cache);
 
         // Test complete match
-        let (pos, candidates) = completer.complete_slash_commands("/exit").unwrap();
+        let (pos, candidates) = completer.complete_slash_commands("/exit");
         assert_eq!(pos, 0);
         assert_eq!(candidates.len(), 1);
         assert_eq!(candidates[0].display, "/exit");
         assert_eq!(candidates[0].replacement, "/exit ");
 
         // Test partial match
-        let (pos, candidates) = completer.complete_slash_commands("/e").unwrap();
+        let (pos, candidates) = completer.complete_slash_commands("/e");
         assert_eq!(pos, 0);
         // There might be multiple commands starting with "e" like "/exit" and "/extension"
         assert!(candidates.len() >= 1);
 
         // Test multiple matches
-        let (pos, candidates) = completer.complete_slash_commands("/").unwrap();
+        let (pos, candidates) = completer.complete_slash_commands("/");
         assert_eq!(pos, 0);
         assert!(candidates.len() > 1);
 
+        // Commands without arguments complete too
+        let (_pos, candidates) = completer.complete_slash_commands("/sum");
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].replacement, "/summarize ");
+
         // Test no match
-        let (_pos, candidates) = completer.complete_slash_commands("/nonexistent").unwrap();
+        let (_pos, candidates) = completer.complete_slash_commands("/nonexistent");
         assert_eq!(candidates.len(), 0);
     }
 
+    #[test]
+    fn test_complete_model_and_provider_names() {
+        let cache = create_test_cache();
+        {
+            let mut cache = cache.write().unwrap();
+            cache.models = vec![
+                "gpt-4o".to_string(),
+                "gpt-4o-mini".to_string(),
+                "o3".to_string(),
+            ];
+            cache.providers = vec!["anthropic".to_string(), "openai".to_string()];
+        }
+        let completer = GooseCompleter::new(cache);
+
+        let (pos, candidates) = completer.complete_model_names("/model ");
+        assert_eq!(pos, 7);
+        assert_eq!(candidates.len(), 3);
+
+        let (pos, candidates) = completer.complete_model_names("/model gpt");
+        assert_eq!(pos, 7);
+        assert_eq!(
+            candidates
+                .iter()
+                .map(|c| c.display.as_str())
+                .collect::<Vec<_>>(),
+            vec!["gpt-4o", "gpt-4o-mini"]
+        );
+
+        let (pos, candidates) = completer.complete_provider_names("/provider an");
+        assert_eq!(pos, 10);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].replacement, "anthropic");
+
+        // Only the first argument completes
+        let (_pos, candidates) = completer.complete_provider_names("/provider openai ");
+        assert!(candidates.is_empty());
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let cache = create_test_cache();
+        let mut completer = GooseCompleter::new(cache);
+
+        let suggestions = completer.complete("/sum", 4);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].value, "/summarize");
+        assert_eq!(suggestions[0].span, Span::new(0, 4));
+        assert!(suggestions[0].append_whitespace);
+
+        let suggestions = completer.complete("/prompt test_prompt1 req", 24);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].value, "required_arg=");
+        assert_eq!(suggestions[0].span, Span::new(21, 24));
+        assert!(!suggestions[0].append_whitespace);
+
+        // Nothing completes in the middle of the line
+        assert!(completer.complete("/sum", 2).is_empty());
+    }
+
     #[test]
     fn test_complete_prompt_names() {
         let cache = create_test_cache();
         let completer = GooseCompleter::new(cache);
 
         // Test with just "/prompt "
-        let (pos, candidates) = completer.complete_prompt_names("/prompt ").unwrap();
+        let (pos, candidates) = completer.complete_prompt_names("/prompt ");
         assert_eq!(pos, 8);
         assert_eq!(candidates.len(), 3); // All prompts
 
         // Test with partial prompt name
-        let (pos, candidates) = completer.complete_prompt_names("/prompt test").unwrap();
+        let (pos, candidates) = completer.complete_prompt_names("/prompt test");
         assert_eq!(pos, 8);
         assert_eq!(candidates.len(), 2); // test_prompt1 and test_prompt2
 
         // Test with specific prompt name
-        let (pos, candidates) = completer
-            .complete_prompt_names("/prompt test_prompt1")
-            .unwrap();
+        let (pos, candidates) = completer.complete_prompt_names("/prompt test_prompt1");
         assert_eq!(pos, 8);
         assert_eq!(candidates.len(), 1);
         assert_eq!(candidates[0].display, "test_prompt1");
 
         // Test with no match
-        let (pos, candidates) = completer
-            .complete_prompt_names("/prompt nonexistent")
-            .unwrap();
+        let (pos, candidates) = completer.complete_prompt_names("/prompt nonexistent");
         assert_eq!(pos, 8);
         assert_eq!(candidates.len(), 0);
     }
@@ -532,29 +643,22 @@ mod tests {
         let completer = GooseCompleter::new(cache);
 
         // Test with partial flag
-        let (_pos, candidates) = completer
-            .complete_prompt_flags("/prompt test_prompt1 --")
-            .unwrap();
+        let (_pos, candidates) = completer.complete_prompt_flags("/prompt test_prompt1 --");
         assert_eq!(candidates.len(), 1);
         assert_eq!(candidates[0].display, "--info");
 
         // Test with exact flag
-        let (_pos, candidates) = completer
-            .complete_prompt_flags("/prompt test_prompt1 --info")
-            .unwrap();
+        let (_pos, candidates) = completer.complete_prompt_flags("/prompt test_prompt1 --info");
         assert_eq!(candidates.len(), 1);
         assert_eq!(candidates[0].display, "--info");
 
         // Test with no match
-        let (_pos, candidates) = completer
-            .complete_prompt_flags("/prompt test_prompt1 --nonexistent")
-            .unwrap();
+        let (_pos, candidates) =
+            completer.complete_prompt_flags("/prompt test_prompt1 --nonexistent");
         assert_eq!(candidates.len(), 0);
 
         // Test with no flag
-        let (_pos, candidates) = completer
-            .complete_prompt_flags("/prompt test_prompt1")
-            .unwrap();
+        let (_pos, candidates) = completer.complete_prompt_flags("/prompt test_prompt1");
         assert_eq!(candidates.len(), 0);
     }
 
@@ -565,42 +669,32 @@ mod tests {
 
         // Test with just a prompt name (no space after)
         // This case doesn't return any candidates in the current implementation
-        let (_pos, candidates) = completer
-            .complete_argument_keys("/prompt test_prompt1")
-            .unwrap();
+        let (_pos, candidates) = completer.complete_argument_keys("/prompt test_prompt1");
         assert_eq!(candidates.len(), 1);
         assert_eq!(candidates[0].display, "required_arg=");
 
         // Test with partial argument
-        let (_pos, candidates) = completer
-            .complete_argument_keys("/prompt test_prompt1 req")
-            .unwrap();
+        let (_pos, candidates) = completer.complete_argument_keys("/prompt test_prompt1 req");
         assert_eq!(candidates.len(), 1);
         assert_eq!(candidates[0].display, "required_arg=");
 
         // Test with one argument already provided
-        let (_pos, candidates) = completer
-            .complete_argument_keys("/prompt test_prompt1 required_arg=value")
-            .unwrap();
+        let (_pos, candidates) =
+            completer.complete_argument_keys("/prompt test_prompt1 required_arg=value");
         assert_eq!(candidates.len(), 1);
         assert_eq!(candidates[0].display, "optional_arg=");
 
         // Test with all arguments provided
         let (_pos, candidates) = completer
-            .complete_argument_keys("/prompt test_prompt1 required_arg=value optional_arg=value")
-            .unwrap();
+            .complete_argument_keys("/prompt test_prompt1 required_arg=value optional_arg=value");
         assert_eq!(candidates.len(), 0);
 
         // Test with prompt that has no arguments
-        let (_pos, candidates) = completer
-            .complete_argument_keys("/prompt test_prompt2")
-            .unwrap();
+        let (_pos, candidates) = completer.complete_argument_keys("/prompt test_prompt2");
         assert_eq!(candidates.len(), 0);
 
         // Test with nonexistent prompt
-        let (_pos, candidates) = completer
-            .complete_argument_keys("/prompt nonexistent")
-            .unwrap();
+        let (_pos, candidates) = completer.complete_argument_keys("/prompt nonexistent");
         assert_eq!(candidates.len(), 0);
     }
 }
@@ -202,6 +202,14 @@ pub fn tool_request_to_markdown(req: &ToolRequest, export_all_content: bool) ->
 pub fn tool_response_to_markdown(resp: &ToolResponse, export_all_content: bool) -> String {
     let mut md = String::new();
     md.push_str("#### Tool Response:\n");
+    if let Some(provenance) = &resp.provenance {
+        md.push_str(&format!(
+            "*Handled by `{}` in {:.2}s, for message {}*\n\n",
+            provenance.extension,
+            provenance.duration_ms() as f64 / 1000.0,
+            provenance.message_index
+        ));
+    }
 
     match &resp.tool_result {
         Ok(contents) => {
@@ -235,6 +243,10 @@ pub fn tool_response_to_markdown(resp: &ToolResponse, export_all_content: bool)
                             md.push_str("\n\n");
                         }
                     }
+                    McpContent::Result(result) => {
+                        md.push_str(&result.text);
+                        md.push_str("\n\n");
+                    }
                     McpContent::Image(image_content) => {
                         if image_content.mime_type.starts_with("image/") {
                             // For actual images, provide a placeholder that indicates it's an image
@@ -313,13 +325,30 @@ pub fn tool_response_to_markdown(resp: &ToolResponse, export_all_content: bool)
 }
 
 pub fn message_to_markdown(message: &Message, export_all_content: bool) -> String {
+    message_with_footnotes_to_markdown(message, export_all_content, 0)
+}
+
+/// Like [`message_to_markdown`], with citations as footnotes numbered after `footnote_offset`
+///
+/// Exports of several messages pass the number of footnotes so far, to keep them unique.
+pub fn message_with_footnotes_to_markdown(
+    message: &Message,
+    export_all_content: bool,
+    footnote_offset: usize,
+) -> String {
     let mut md = String::new();
-    for content in &message.content {
+    for (index, content) in message.content.iter().enumerate() {
         match content {
-            MessageContent::Text(text) => {
-                md.push_str(&text.text);
+            MessageContent::Text(_) => {
+                let text = message
+                    .text_with_citation_markers(index, |n| format!("[^{}]", n + footnote_offset))
+                    .unwrap_or_default();
+                md.push_str(&text);
                 md.push_str("\n\n");
             }
+            MessageContent::Citations(_) => {
+                // Marked in the text before them, and listed as footnotes below
+            }
             MessageContent::ToolRequest(req) => {
                 md.push_str(&tool_request_to_markdown(req, export_all_content));
                 md.push('\n');
@@ -352,13 +381,31 @@ pub fn message_to_markdown(message: &Message, export_all_content: bool) -> Strin
             }
         }
     }
+    for (number, source) in message.citation_sources().into_iter().enumerate() {
+        let label = source
+            .title
+            .as_deref()
+            .or(source.url.as_deref())
+            .unwrap_or("Source");
+        let footnote = match &source.url {
+            Some(url) => format!("[{}]({})", label, url),
+            None => label.to_string(),
+        };
+        md.push_str(&format!(
+            "[^{}]: {}\n",
+            number + 1 + footnote_offset,
+            footnote
+        ));
+    }
     md.trim_end_matches("\n").to_string()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use goose::message::{Message, ToolRequest, ToolResponse};
+    use goose::message::{
+        Citation, CitationSource, Message, ToolProvenance, ToolRequest, ToolResponse,
+    };
     use mcp_core::content::{Content as McpContent, TextContent};
     use mcp_core::tool::ToolCall;
     use serde_json::json;
@@ -526,6 +573,7 @@ mod tests {
         let tool_response = ToolResponse {
             id: "test-id".to_string(),
             tool_result: Ok(vec![McpContent::Text(text_content)]),
+            provenance: None,
         };
 
         let result = tool_response_to_markdown(&tool_response, true);
@@ -533,6 +581,27 @@ mod tests {
         assert!(result.contains("Command executed successfully"));
     }
 
+    #[test]
+    fn test_tool_response_to_markdown_with_provenance() {
+        let message = Message::user().with_traced_tool_response(
+            "test-id",
+            Ok(vec![McpContent::text("Command executed successfully")]),
+            ToolProvenance {
+                message_index: 3,
+                extension: "developer".to_string(),
+                started_at: 1_700_000_000_000,
+                finished_at: 1_700_000_001_250,
+            },
+        );
+        let tool_response = message.content[0].as_tool_response().unwrap();
+
+        let result = tool_response_to_markdown(tool_response, true);
+        assert!(result.starts_with(
+            "#### Tool Response:\n*Handled by `developer` in 1.25s, for message 3*\n\n"
+        ));
+        assert!(result.contains("Command executed successfully"));
+    }
+
     #[test]
     fn test_tool_response_to_markdown_json() {
         let json_text = r#"{"status": "success", "data": "test"}"#;
@@ -543,6 +612,7 @@ mod tests {
         let tool_response = ToolResponse {
             id: "test-id".to_string(),
             tool_result: Ok(vec![McpContent::Text(text_content)]),
+            provenance: None,
         };
 
         let result = tool_response_to_markdown(&tool_response, true);
@@ -573,6 +643,37 @@ mod tests {
         assert!(result.contains("**param**"));
     }
 
+    #[test]
+    fn test_message_to_markdown_citations() {
+        let source = |url: &str| CitationSource {
+            url: Some(url.to_string()),
+            title: Some("Café de Flore".to_string()),
+            cited_text: None,
+        };
+        let message = Message::assistant()
+            .with_text("Le café opened in 1887. Sartre wrote there.")
+            .with_citations(vec![
+                Citation {
+                    start: 0,
+                    end: 23,
+                    source: source("https://a.example"),
+                },
+                Citation {
+                    start: 24,
+                    end: 43,
+                    source: source("https://b.example"),
+                },
+            ]);
+
+        let result = message_with_footnotes_to_markdown(&message, true, 2);
+        assert_eq!(
+            result,
+            "Le café opened in 1887.[^3] Sartre wrote there.[^4]\n\n\
+             [^3]: [Café de Flore](https://a.example)\n\
+             [^4]: [Café de Flore](https://b.example)"
+        );
+    }
+
     #[test]
     fn test_message_to_markdown_thinking() {
         let message = Message::assistant()
@@ -645,6 +746,7 @@ if __name__ == "__main__":
         let tool_response = ToolResponse {
             id: "shell-cat".to_string(),
             tool_result: Ok(vec![McpContent::Text(text_content)]),
+            provenance: None,
         };
 
         let request_result = tool_request_to_markdown(&tool_request, true);
@@ -682,6 +784,7 @@ if __name__ == "__main__":
         let tool_response = ToolResponse {
             id: "git-status".to_string(),
             tool_result: Ok(vec![McpContent::Text(text_content)]),
+            provenance: None,
         };
 
         let request_result = tool_request_to_markdown(&tool_request, true);
@@ -727,6 +830,7 @@ warning: unused variable `x`
         let tool_response = ToolResponse {
             id: "cargo-build".to_string(),
             tool_result: Ok(vec![McpContent::Text(text_content)]),
+            provenance: None,
         };
 
         let response_result = tool_response_to_markdown(&tool_response, true);
@@ -770,6 +874,7 @@ warning: unused variable `x`
         let tool_response = ToolResponse {
             id: "curl-api".to_string(),
             tool_result: Ok(vec![McpContent::Text(text_content)]),
+            provenance: None,
         };
 
         let response_result = tool_response_to_markdown(&tool_response, true);
@@ -802,6 +907,7 @@ warning: unused variable `x`
         let tool_response = ToolResponse {
             id: "editor-write".to_string(),
             tool_result: Ok(vec![McpContent::Text(text_content)]),
+            provenance: None,
         };
 
         let request_result = tool_request_to_markdown(&tool_request, true);
@@ -855,6 +961,7 @@ def process_data(data: List[Dict]) -> List[Dict]:
         let tool_response = ToolResponse {
             id: "editor-view".to_string(),
             tool_result: Ok(vec![McpContent::Text(text_content)]),
+            provenance: None,
         };
 
         let response_result = tool_response_to_markdown(&tool_response, true);
@@ -888,6 +995,7 @@ Command failed with exit code 2"#;
         let tool_response = ToolResponse {
             id: "shell-error".to_string(),
             tool_result: Ok(vec![McpContent::Text(text_content)]),
+            provenance: None,
         };
 
         let response_result = tool_response_to_markdown(&tool_response, true);
@@ -924,6 +1032,7 @@ Command failed with exit code 2"#;
         let tool_response = ToolResponse {
             id: "script-exec".to_string(),
             tool_result: Ok(vec![McpContent::Text(text_content)]),
+            provenance: None,
         };
 
         let request_result = tool_request_to_markdown(&tool_request, true);
@@ -967,6 +1076,7 @@ drwx------   3 user  staff    96 Dec  6 16:20 com.apple.launchd.abc
         let tool_response = ToolResponse {
             id: "multi-cmd".to_string(),
             tool_result: Ok(vec![McpContent::Text(text_content)]),
+            provenance: None,
         };
 
         let request_result = tool_request_to_markdown(&_tool_request, true);
@@ -1006,6 +1116,7 @@ src/middleware.rs:12:async fn auth_middleware(req: Request, next: Next) -> Resul
         let tool_response = ToolResponse {
             id: "grep-search".to_string(),
             tool_result: Ok(vec![McpContent::Text(text_content)]),
+            provenance: None,
         };
 
         let request_result = tool_request_to_markdown(&tool_request, true);
@@ -1042,6 +1153,7 @@ src/middleware.rs:12:async fn auth_middleware(req: Request, next: Next) -> Resul
         let tool_response = ToolResponse {
             id: "json-test".to_string(),
             tool_result: Ok(vec![McpContent::Text(text_content)]),
+            provenance: None,
         };
 
         let response_result = tool_response_to_markdown(&tool_response, true);
@@ -1079,6 +1191,7 @@ found 0 vulnerabilities"#;
         let tool_response = ToolResponse {
             id: "npm-install".to_string(),
             tool_result: Ok(vec![McpContent::Text(text_content)]),
+            provenance: None,
         };
 
         let request_result = tool_request_to_markdown(&tool_request, true);
@@ -1,8 +1,16 @@
-use super::completion::GooseCompleter;
+use super::completion::{GooseCompleter, GooseHinter, PlainHighlighter};
+use super::CompletionCache;
 use anyhow::Result;
-use rustyline::Editor;
+use reedline::{
+    default_emacs_keybindings, ColumnarMenu, EditCommand, Emacs, FileBackedHistory, KeyCode,
+    KeyModifiers, MenuBuilder, Prompt, PromptEditMode, PromptHistorySearch,
+    PromptHistorySearchStatus, Reedline, ReedlineEvent, ReedlineMenu, Signal,
+};
 use shlex;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
 
 #[derive(Debug)]
 pub enum InputResult {
@@ -20,6 +28,8 @@ pub enum InputResult {
     Clear,
     Recipe(Option<String>),
     Summarize,
+    SwitchModel(String),
+    SwitchProvider(SwitchProviderOptions),
 }
 
 #[derive(Debug)]
@@ -34,28 +44,100 @@ pub struct PlanCommandOptions {
     pub message_text: String,
 }
 
-pub fn get_input(
-    editor: &mut Editor<GooseCompleter, rustyline::history::DefaultHistory>,
-) -> Result<InputResult> {
-    // Ensure Ctrl-J binding is set for newlines
-    editor.bind_sequence(
-        rustyline::KeyEvent(rustyline::KeyCode::Char('j'), rustyline::Modifiers::CTRL),
-        rustyline::EventHandler::Simple(rustyline::Cmd::Newline),
-    );
+#[derive(Debug)]
+pub struct SwitchProviderOptions {
+    pub provider: String,
+    /// The model to use, else the default model of the provider
+    pub model: Option<String>,
+}
 
-    let prompt = format!("{} ", console::style("( O)>").cyan().bold());
-    let input = match editor.readline(&prompt) {
-        Ok(text) => text,
-        Err(e) => match e {
-            rustyline::error::ReadlineError::Interrupted => return Ok(InputResult::Exit),
-            _ => return Err(e.into()),
-        },
-    };
+/// The prompt of the input, with a continuation prompt lining up the lines after the first
+struct GoosePrompt;
+
+impl Prompt for GoosePrompt {
+    fn render_prompt_left(&self) -> Cow<'_, str> {
+        Cow::Owned(console::style("( O)>").cyan().bold().to_string())
+    }
+
+    fn render_prompt_right(&self) -> Cow<'_, str> {
+        Cow::Borrowed("")
+    }
+
+    fn render_prompt_indicator(&self, _prompt_mode: PromptEditMode) -> Cow<'_, str> {
+        Cow::Borrowed(" ")
+    }
+
+    fn render_prompt_multiline_indicator(&self) -> Cow<'_, str> {
+        Cow::Borrowed("  ... ")
+    }
+
+    fn render_prompt_history_search_indicator(
+        &self,
+        history_search: PromptHistorySearch,
+    ) -> Cow<'_, str> {
+        let prefix = match history_search.status {
+            PromptHistorySearchStatus::Passing => "",
+            PromptHistorySearchStatus::Failing => "failing ",
+        };
+        Cow::Owned(format!(
+            " ({}history search: {}) ",
+            prefix, history_search.term
+        ))
+    }
+}
 
-    // Add valid input to history (history saving to file is handled in the Session::interactive method)
-    if !input.trim().is_empty() {
-        editor.add_history_entry(input.as_str())?;
+/// Create the line editor of the interactive session, keeping its input history in `history_file`
+pub fn create_editor(
+    completion_cache: Arc<RwLock<CompletionCache>>,
+    history_file: PathBuf,
+    history_size: usize,
+) -> Result<Reedline> {
+    let mut keybindings = default_emacs_keybindings();
+    // Ctrl-J adds a newline, with Alt-Enter for terminals where Ctrl-J can't be told apart
+    // from Enter, such as the Windows console
+    for (modifiers, key) in [
+        (KeyModifiers::CONTROL, KeyCode::Char('j')),
+        (KeyModifiers::ALT, KeyCode::Enter),
+    ] {
+        keybindings.add_binding(
+            modifiers,
+            key,
+            ReedlineEvent::Edit(vec![EditCommand::InsertNewline]),
+        );
     }
+    keybindings.add_binding(
+        KeyModifiers::NONE,
+        KeyCode::Tab,
+        ReedlineEvent::UntilFound(vec![
+            ReedlineEvent::Menu("completion_menu".to_string()),
+            ReedlineEvent::MenuNext,
+        ]),
+    );
+
+    let history = FileBackedHistory::with_file(history_size, history_file)?;
+    let completion_menu = ColumnarMenu::default().with_name("completion_menu");
+
+    // Pasted text is inserted as it is, newlines included, on terminals that support
+    // bracketed paste
+    Ok(Reedline::create()
+        .with_history(Box::new(history))
+        .with_completer(Box::new(GooseCompleter::new(completion_cache)))
+        .with_quick_completions(true)
+        .with_partial_completions(true)
+        .with_menu(ReedlineMenu::EngineCompleter(Box::new(completion_menu)))
+        .with_hinter(Box::new(GooseHinter))
+        .with_highlighter(Box::new(PlainHighlighter))
+        .with_edit_mode(Box::new(Emacs::new(keybindings)))
+        .use_bracketed_paste(true))
+}
+
+pub fn get_input(editor: &mut Reedline) -> Result<InputResult> {
+    // The editor adds the input to its history (saving the history to file is handled in the
+    // Session::interactive method)
+    let input = match editor.read_line(&GoosePrompt)? {
+        Signal::Success(text) => text,
+        Signal::CtrlC | Signal::CtrlD => return Ok(InputResult::Exit),
+    };
 
     // Handle non-slash commands first
     if !input.starts_with('/') {
@@ -95,6 +177,8 @@ fn handle_slash_command(input: &str) -> Option<InputResult> {
     const CMD_CLEAR: &str = "/clear";
     const CMD_RECIPE: &str = "/recipe";
     const CMD_SUMMARIZE: &str = "/summarize";
+    const CMD_MODEL: &str = "/model ";
+    const CMD_PROVIDER: &str = "/provider ";
 
     match input {
         "/exit" | "/quit" => Some(InputResult::Exit),
@@ -139,6 +223,8 @@ fn handle_slash_command(input: &str) -> Option<InputResult> {
         s if s == CMD_CLEAR => Some(InputResult::Clear),
         s if s.starts_with(CMD_RECIPE) => parse_recipe_command(s),
         s if s == CMD_SUMMARIZE => Some(InputResult::Summarize),
+        s if s.starts_with(CMD_MODEL) => parse_model_command(&s[CMD_MODEL.len()..]),
+        s if s.starts_with(CMD_PROVIDER) => parse_provider_command(&s[CMD_PROVIDER.len()..]),
         _ => None,
     }
 }
@@ -229,6 +315,31 @@ fn parse_plan_command(input: String) -> Option<InputResult> {
     Some(InputResult::Plan(options))
 }
 
+fn parse_model_command(args: &str) -> Option<InputResult> {
+    match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [model] => Some(InputResult::SwitchModel(model.to_string())),
+        _ => {
+            println!("Usage: /model <name>");
+            Some(InputResult::Retry)
+        }
+    }
+}
+
+fn parse_provider_command(args: &str) -> Option<InputResult> {
+    let (provider, model) = match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [provider] => (provider, None),
+        [provider, model] => (provider, Some(model.to_string())),
+        _ => {
+            println!("Usage: /provider <name> [model]");
+            return Some(InputResult::Retry);
+        }
+    };
+    Some(InputResult::SwitchProvider(SwitchProviderOptions {
+        provider: provider.to_string(),
+        model,
+    }))
+}
+
 fn print_help() {
     println!(
         "Available commands:
@@ -248,13 +359,19 @@ fn print_help() {
 /recipe [filepath] - Generate a recipe from the current conversation and save it to the specified filepath (must end with .yaml).
                        If no filepath is provided, it will be saved to ./recipe.yaml.
 /summarize - Summarize the current conversation to reduce context length while preserving key information.
+/model <name> - Continue the conversation with another model of the current provider
+/provider <name> [model] - Continue the conversation with another provider, with its default model unless one is given
 /? or /help - Display this help message
 /clear - Clears the current chat history
 
 Navigation:
-Ctrl+C - Interrupt goose (resets the interaction to before the interrupted user request)
-Ctrl+J - Add a newline
-Up/Down arrows - Navigate through command history"
+Ctrl+C - Stop goose once its running tools finish, with a summary of its progress
+Ctrl+C twice - Interrupt goose (resets the interaction to before the interrupted user request)
+Enter - Send the message
+Ctrl+J or Alt+Enter - Add a newline, pasted text keeps its newlines
+Up/Down arrows - Navigate through command history
+Ctrl+R - Search the command history
+Tab - Complete commands and prompt names"
     );
 }
 
@@ -493,4 +610,37 @@ mod tests {
         let result = handle_slash_command("  /summarize  ");
         assert!(matches!(result, Some(InputResult::Summarize)));
     }
+
+    #[test]
+    fn test_switch_commands() {
+        match handle_slash_command("/model gpt-4o") {
+            Some(InputResult::SwitchModel(model)) => assert_eq!(model, "gpt-4o"),
+            _ => panic!("Expected SwitchModel"),
+        }
+        // `/mode` is another command
+        assert!(matches!(
+            handle_slash_command("/mode auto"),
+            Some(InputResult::GooseMode(_))
+        ));
+
+        match handle_slash_command("/provider anthropic") {
+            Some(InputResult::SwitchProvider(options)) => {
+                assert_eq!(options.provider, "anthropic");
+                assert_eq!(options.model, None);
+            }
+            _ => panic!("Expected SwitchProvider"),
+        }
+        match handle_slash_command("/provider openai  o3") {
+            Some(InputResult::SwitchProvider(options)) => {
+                assert_eq!(options.provider, "openai");
+                assert_eq!(options.model.as_deref(), Some("o3"));
+            }
+            _ => panic!("Expected SwitchProvider"),
+        }
+
+        assert!(matches!(
+            handle_slash_command("/model gpt-4o o3"),
+            Some(InputResult::Retry)
+        ));
+    }
 }
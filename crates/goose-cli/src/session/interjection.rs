@@ -0,0 +1,111 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// How often the reader checks whether it should stop or pause, in milliseconds
+#[cfg(unix)]
+const POLL_INTERVAL_MS: u16 = 100;
+
+/// Reads lines typed by the user while the agent is replying, so they can be
+/// passed to the agent as interjections without cancelling the reply.
+///
+/// Stdin is only read once a full line is available, which keeps the reader from
+/// holding on to input after it is stopped and the regular prompt takes over again.
+pub struct InterjectionReader {
+    rx: mpsc::UnboundedReceiver<String>,
+    paused: Arc<AtomicBool>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl InterjectionReader {
+    /// Start reading interjections from stdin on a background thread
+    pub fn spawn() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let paused = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        #[cfg(unix)]
+        {
+            let paused = paused.clone();
+            let stopped = stopped.clone();
+            std::thread::spawn(move || read_lines(tx, paused, stopped));
+        }
+        #[cfg(not(unix))]
+        drop(tx);
+
+        Self {
+            rx,
+            paused,
+            stopped,
+        }
+    }
+
+    /// A reader that never yields anything, used for non-interactive sessions
+    pub fn disabled() -> Self {
+        let (_, rx) = mpsc::unbounded_channel();
+        Self {
+            rx,
+            paused: Arc::new(AtomicBool::new(true)),
+            stopped: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// Wait for the next interjection, returns None once the reader has stopped
+    pub async fn recv(&mut self) -> Option<String> {
+        self.rx.recv().await
+    }
+
+    /// Stop reading stdin, e.g. while another prompt needs the terminal
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// Resume reading stdin after a pause
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+}
+
+impl Drop for InterjectionReader {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(unix)]
+fn read_lines(
+    tx: mpsc::UnboundedSender<String>,
+    paused: Arc<AtomicBool>,
+    stopped: Arc<AtomicBool>,
+) {
+    use nix::poll::{poll, PollFd, PollFlags};
+    use std::io::BufRead;
+    use std::os::fd::AsFd;
+
+    let stdin = std::io::stdin();
+    while !stopped.load(Ordering::SeqCst) {
+        if paused.load(Ordering::SeqCst) {
+            std::thread::sleep(std::time::Duration::from_millis(POLL_INTERVAL_MS.into()));
+            continue;
+        }
+
+        let ready = {
+            let mut fds = [PollFd::new(stdin.as_fd(), PollFlags::POLLIN)];
+            matches!(poll(&mut fds, POLL_INTERVAL_MS), Ok(n) if n > 0)
+        };
+        if !ready || paused.load(Ordering::SeqCst) || stopped.load(Ordering::SeqCst) {
+            continue;
+        }
+
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                let text = line.trim();
+                if !text.is_empty() && tx.send(text.to_string()).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -2,42 +2,54 @@ mod builder;
 mod completion;
 mod export;
 mod input;
+mod interjection;
 mod output;
 mod prompt;
+mod renderer;
 mod thinking;
 
-pub use self::export::message_to_markdown;
+pub use self::export::{message_to_markdown, message_with_footnotes_to_markdown};
 pub use builder::{build_session, SessionBuilderConfig, SessionSettings};
 use console::Color;
 use goose::agents::AgentEvent;
 use goose::permission::permission_confirmation::PrincipalType;
 use goose::permission::Permission;
 use goose::permission::PermissionConfirmation;
+use goose::permission::RiskLevel;
 use goose::providers::base::Provider;
+use goose::providers::validation::repair_conversation;
 pub use goose::session::Identifier;
+pub use output::remove_saved_images;
+pub use renderer::{set_output_mode, OutputMode};
 
 use anyhow::{Context, Result};
-use completion::GooseCompleter;
 use etcetera::{choose_app_strategy, AppStrategy};
 use goose::agents::extension::{Envs, ExtensionConfig};
 use goose::agents::{Agent, SessionConfig};
 use goose::config::Config;
+use goose::context_mgmt::log_compression::LogCompressionConfig;
 use goose::message::{Message, MessageContent};
 use goose::session;
+use goose::utils::safe_truncate;
 use input::InputResult;
+use interjection::InterjectionReader;
 use mcp_core::handler::ToolError;
 use mcp_core::prompt::PromptMessage;
 use mcp_core::protocol::JsonRpcMessage;
 use mcp_core::protocol::JsonRpcNotification;
+use renderer::Span;
 
 use rand::{distributions::Alphanumeric, Rng};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Instant;
 use tokio;
 
+/// How many entries of the input history are kept across sessions
+const HISTORY_SIZE: usize = 1000;
+
 pub enum RunMode {
     Normal,
     Plan,
@@ -52,12 +64,15 @@ pub struct Session {
     debug: bool, // New field for debug mode
     run_mode: RunMode,
     scheduled_job_id: Option<String>, // ID of the scheduled job that triggered this session
+    provider_name: Option<String>,
 }
 
 // Cache structure for completion data
 struct CompletionCache {
     prompts: HashMap<String, Vec<String>>,
     prompt_info: HashMap<String, output::PromptInfo>,
+    providers: Vec<String>,
+    models: Vec<String>,
     last_updated: Instant,
 }
 
@@ -66,6 +81,8 @@ impl CompletionCache {
         Self {
             prompts: HashMap::new(),
             prompt_info: HashMap::new(),
+            providers: Vec::new(),
+            models: Vec::new(),
             last_updated: Instant::now(),
         }
     }
@@ -130,9 +147,16 @@ impl Session {
             debug,
             run_mode: RunMode::Normal,
             scheduled_job_id,
+            provider_name: None,
         }
     }
 
+    /// Set the name of the provider the agent was created with, for switching its model
+    pub fn with_provider_name(mut self, provider_name: impl Into<String>) -> Self {
+        self.provider_name = Some(provider_name.into());
+        self
+    }
+
     /// Helper function to summarize context messages
     async fn summarize_context_messages(
         messages: &mut Vec<Message>,
@@ -189,6 +213,7 @@ impl Session {
             // TODO: should set timeout
             timeout: Some(goose::config::DEFAULT_EXTENSION_TIMEOUT),
             bundled: None,
+            network: None,
         };
 
         self.agent
@@ -222,6 +247,7 @@ impl Session {
             // TODO: should set timeout
             timeout: Some(goose::config::DEFAULT_EXTENSION_TIMEOUT),
             bundled: None,
+            network: None,
         };
 
         self.agent
@@ -247,6 +273,7 @@ impl Session {
                 // TODO: should set a timeout
                 timeout: Some(goose::config::DEFAULT_EXTENSION_TIMEOUT),
                 bundled: None,
+                network: None,
             };
             self.agent
                 .add_extension(config)
@@ -307,9 +334,26 @@ impl Session {
         Ok(result.messages)
     }
 
+    /// A user message of `text`, with pasted logs compressed when GOOSE_LOG_COMPRESSION is on
+    async fn user_message(&self, text: &str) -> Message {
+        let message = Message::user().with_text(text);
+        let Some(config) = LogCompressionConfig::from_config() else {
+            return message;
+        };
+        let (message, note) = self
+            .agent
+            .compress_pasted_logs(message, &config, Some(&self.session_file))
+            .await;
+        if let Some(note) = note {
+            output::render_text(&note, None, true);
+        }
+        message
+    }
+
     /// Process a single message and get the response
     async fn process_message(&mut self, message: String) -> Result<()> {
-        self.messages.push(Message::user().with_text(&message));
+        let user_message = self.user_message(&message).await;
+        self.messages.push(user_message);
         // Get the provider from the agent for description generation
         let provider = self.agent.provider().await?;
 
@@ -342,8 +386,80 @@ impl Session {
         Ok(())
     }
 
+    /// Fix up a loaded history the provider would reject, such as tool calls left without
+    /// results or repeated roles, and save the repaired history
+    async fn repair_history(&mut self) -> Result<()> {
+        let Some(provider_name) = &self.provider_name else {
+            return Ok(());
+        };
+        let (messages, repairs) = repair_conversation(&self.messages, provider_name);
+        if repairs.is_empty() {
+            return Ok(());
+        }
+        tracing::warn!(?repairs, "Repaired the loaded session history");
+        self.messages = messages;
+        session::persist_messages_with_schedule_id(
+            &self.session_file,
+            &self.messages,
+            None,
+            self.scheduled_job_id.clone(),
+        )
+        .await
+    }
+
+    /// Complete a turn that was cut short by a crash while its tools ran
+    ///
+    /// Tool results saved before the crash are kept and unfinished calls are marked as
+    /// interrupted. Interactive sessions offer to let the agent continue from there. Whatever
+    /// else the provider would reject in the history is repaired.
+    async fn recover_interrupted_turn(&mut self, offer_to_continue: bool) -> Result<()> {
+        // The journal knows which calls were interrupted, so it goes before the generic repair
+        let turn = goose::agents::recover_interrupted_turn(&self.session_file, &mut self.messages)?;
+        self.repair_history().await?;
+        let Some(turn) = turn else {
+            return Ok(());
+        };
+        session::persist_messages_with_schedule_id(
+            &self.session_file,
+            &self.messages,
+            None,
+            self.scheduled_job_id.clone(),
+        )
+        .await?;
+
+        let mut notice = format!(
+            "The last turn was interrupted: recovered {} completed tool call(s).",
+            turn.completed.len()
+        );
+        if !turn.interrupted.is_empty() {
+            notice.push_str(&format!(
+                " Did not finish: {}.",
+                turn.interrupted.join(", ")
+            ));
+        }
+        output::render_text(&notice, Some(Color::Yellow), true);
+
+        let resume = offer_to_continue
+            && cliclack::confirm("Continue the interrupted turn?")
+                .initial_value(true)
+                .interact()
+                .unwrap_or(false);
+        if resume {
+            return self.process_agent_response(true).await;
+        }
+
+        // Only asked here, the model never made this reply so it stays out of the history.
+        // The next user message follows the tool responses, as an interjection would.
+        if offer_to_continue {
+            output::render_text("How would you like to proceed?", Some(Color::Yellow), true);
+        }
+        Ok(())
+    }
+
     /// Start an interactive session, optionally with an initial message
     pub async fn interactive(&mut self, message: Option<String>) -> Result<()> {
+        self.recover_interrupted_turn(true).await?;
+
         // Process initial message if provided
         if let Some(msg) = message {
             self.process_message(msg).await?;
@@ -352,48 +468,26 @@ impl Session {
         // Initialize the completion cache
         self.update_completion_cache().await?;
 
-        // Create a new editor with our custom completer
-        let config = rustyline::Config::builder()
-            .completion_type(rustyline::CompletionType::Circular)
-            .build();
-        let mut editor =
-            rustyline::Editor::<GooseCompleter, rustyline::history::DefaultHistory>::with_config(
-                config,
-            )?;
-
-        // Set up the completer with a reference to the completion cache
-        let completer = GooseCompleter::new(self.completion_cache.clone());
-        editor.set_helper(Some(completer));
-
         // Create and use a global history file in ~/.config/goose directory
         // This allows command history to persist across different chat sessions
-        // instead of being tied to each individual session's messages
+        // instead of being tied to each individual session's messages. The line editor
+        // stores multi-line entries in its own format, so it keeps them apart from the
+        // history.txt of earlier versions.
         let strategy =
             choose_app_strategy(crate::APP_STRATEGY.clone()).expect("goose requires a home dir");
-        let config_dir = strategy.config_dir();
-        let history_file = config_dir.join("history.txt");
-
-        // Ensure config directory exists
-        if let Some(parent) = history_file.parent() {
-            if !parent.exists() {
-                std::fs::create_dir_all(parent)?;
-            }
-        }
+        let history_file = strategy.config_dir().join("input_history.txt");
 
-        // Load history from the global file
-        if history_file.exists() {
-            if let Err(err) = editor.load_history(&history_file) {
-                eprintln!("Warning: Failed to load command history: {}", err);
-            }
-        }
+        // Create a new editor with our custom completer, which creates the config directory
+        // and loads the history from the global file
+        let mut editor =
+            input::create_editor(self.completion_cache.clone(), history_file, HISTORY_SIZE)?;
 
         // Helper function to save history after commands
-        let save_history =
-            |editor: &mut rustyline::Editor<GooseCompleter, rustyline::history::DefaultHistory>| {
-                if let Err(err) = editor.save_history(&history_file) {
-                    eprintln!("Warning: Failed to save command history: {}", err);
-                }
-            };
+        let save_history = |editor: &mut reedline::Reedline| {
+            if let Err(err) = editor.sync_history() {
+                eprintln!("Warning: Failed to save command history: {}", err);
+            }
+        };
 
         output::display_greeting();
         loop {
@@ -406,7 +500,8 @@ impl Session {
                         RunMode::Normal => {
                             save_history(&mut editor);
 
-                            self.messages.push(Message::user().with_text(&content));
+                            let user_message = self.user_message(&content).await;
+                            self.messages.push(user_message);
 
                             // Track the current directory and last instruction in projects.json
                             let session_id = self
@@ -551,7 +646,7 @@ impl Session {
                     self.handle_prompt_command(opts).await?;
                 }
                 InputResult::Recipe(filepath_opt) => {
-                    println!("{}", console::style("Generating Recipe").green());
+                    output::render_line(&[Span::new("Generating Recipe").color(Color::Green)]);
 
                     output::show_thinking();
                     let recipe = self.agent.create_recipe(self.messages.clone()).await;
@@ -562,27 +657,47 @@ impl Session {
                             // Use provided filepath or default
                             let filepath_str = filepath_opt.as_deref().unwrap_or("recipe.yaml");
                             match self.save_recipe(&recipe, filepath_str) {
-                                Ok(path) => println!(
-                                    "{}",
-                                    console::style(format!("Saved recipe to {}", path.display()))
-                                        .green()
-                                ),
+                                Ok(path) => output::render_line(&[Span::new(format!(
+                                    "Saved recipe to {}",
+                                    path.display()
+                                ))
+                                .color(Color::Green)]),
                                 Err(e) => {
-                                    println!("{}", console::style(e).red());
+                                    output::render_line(&[Span::new(e).color(Color::Red)]);
                                 }
                             }
                         }
                         Err(e) => {
-                            println!(
-                                "{}: {:?}",
-                                console::style("Failed to generate recipe").red(),
-                                e
-                            );
+                            output::render_line(&[
+                                Span::new("Failed to generate recipe").color(Color::Red),
+                                Span::new(format!(": {:?}", e)),
+                            ]);
                         }
                     }
 
                     continue;
                 }
+                InputResult::SwitchModel(model) => {
+                    save_history(&mut editor);
+
+                    match self.provider_name.clone() {
+                        Some(provider_name) => {
+                            if let Err(e) = self.switch_provider(provider_name, Some(model)).await {
+                                output::render_error(&format!("Failed to switch model: {}", e));
+                            }
+                        }
+                        None => output::render_error("The provider of this session is not known"),
+                    }
+                    continue;
+                }
+                InputResult::SwitchProvider(options) => {
+                    save_history(&mut editor);
+
+                    if let Err(e) = self.switch_provider(options.provider, options.model).await {
+                        output::render_error(&format!("Failed to switch provider: {}", e));
+                    }
+                    continue;
+                }
                 InputResult::Summarize => {
                     save_history(&mut editor);
 
@@ -600,7 +715,9 @@ impl Session {
                         };
 
                     if should_summarize {
-                        println!("{}", console::style("Summarizing conversation...").yellow());
+                        output::render_line(&[
+                            Span::new("Summarizing conversation...").color(Color::Yellow)
+                        ]);
                         output::show_thinking();
 
                         // Get the provider for summarization
@@ -623,19 +740,17 @@ impl Session {
                         .await?;
 
                         output::hide_thinking();
-                        println!(
-                            "{}",
-                            console::style("Conversation has been summarized.").green()
-                        );
-                        println!(
-                            "{}",
-                            console::style(
-                                "Key information has been preserved while reducing context length."
-                            )
-                            .green()
-                        );
+                        output::render_line(&[
+                            Span::new("Conversation has been summarized.").color(Color::Green)
+                        ]);
+                        output::render_line(&[Span::new(
+                            "Key information has been preserved while reducing context length.",
+                        )
+                        .color(Color::Green)]);
                     } else {
-                        println!("{}", console::style("Summarization cancelled.").yellow());
+                        output::render_line(&[
+                            Span::new("Summarization cancelled.").color(Color::Yellow)
+                        ]);
                     }
 
                     continue;
@@ -729,9 +844,19 @@ impl Session {
 
     /// Process a single message and exit
     pub async fn headless(&mut self, message: String) -> Result<()> {
+        self.recover_interrupted_turn(false).await?;
         self.process_message(message).await
     }
 
+    /// Print the request the message would send to the provider, without sending or saving it
+    pub async fn dry_run(&self, message: String) -> Result<()> {
+        let mut messages = self.messages.clone();
+        messages.push(Message::user().with_text(&message));
+        let request = self.agent.dry_run(&messages).await?;
+        println!("{}", serde_json::to_string_pretty(&request)?);
+        Ok(())
+    }
+
     async fn process_agent_response(&mut self, interactive: bool) -> Result<()> {
         let session_id = session::Identifier::Path(self.session_file.clone());
         let mut stream = self
@@ -749,6 +874,19 @@ impl Session {
             .await?;
 
         let mut progress_bars = output::McpSpinners::new();
+        let mut pending_tool_calls: Vec<String> = Vec::new();
+        // Calls whose path was already shown while their arguments streamed in
+        let mut shown_tool_paths: HashSet<String> = HashSet::new();
+
+        // The first Ctrl+C lets the running tools finish and asks for a summary, the second cancels
+        let mut stop_requested = false;
+
+        // Lines typed while goose is replying are passed to the agent as interjections
+        let mut interjections = if interactive {
+            InterjectionReader::spawn()
+        } else {
+            InterjectionReader::disabled()
+        };
 
         use futures::StreamExt;
         loop {
@@ -760,16 +898,23 @@ impl Session {
                             if let Some(MessageContent::ToolConfirmationRequest(confirmation)) = message.content.first() {
                                 output::hide_thinking();
 
+                                if let Some(risk) = confirmation.risk.as_ref().filter(|risk| risk.level > RiskLevel::Low) {
+                                    let color = if risk.level == RiskLevel::High { Color::Red } else { Color::Yellow };
+                                    output::render_text(&format!("This command is {} risk: {}", risk.level, risk.explanation), Some(color), true);
+                                }
+
                                 // Format the confirmation prompt
                                 let prompt = "Goose would like to call the above tool, do you allow?".to_string();
 
                                 // Get confirmation from user
+                                interjections.pause();
                                 let permission_result = cliclack::select(prompt)
                                     .item(Permission::AllowOnce, "Allow", "Allow the tool call once")
                                     .item(Permission::AlwaysAllow, "Always Allow", "Always allow the tool call")
                                     .item(Permission::DenyOnce, "Deny", "Deny the tool call")
                                     .item(Permission::Cancel, "Cancel", "Cancel the AI response and tool call")
                                     .interact();
+                                interjections.resume();
 
                                 let permission = match permission_result {
                                     Ok(p) => p, // If Ok, use the selected permission
@@ -818,11 +963,14 @@ impl Session {
                                         if interactive {
                                             // In interactive mode with no default, ask the user what to do
                                             let prompt = "The model's context length is maxed out. You will need to reduce the # msgs. Do you want to?".to_string();
-                                            cliclack::select(prompt)
+                                            interjections.pause();
+                                            let selected = cliclack::select(prompt)
                                                 .item("clear", "Clear Session", "Removes all messages from Goose's memory")
                                                 .item("truncate", "Truncate Messages", "Removes old messages till context is within limits")
                                                 .item("summarize", "Summarize Session", "Summarize the session to reduce context length")
-                                                .interact()?
+                                                .interact()?;
+                                            interjections.resume();
+                                            selected
                                         } else {
                                             // In headless mode, default to summarize
                                             "summarize"
@@ -895,6 +1043,12 @@ impl Session {
                                 let _ = progress_bars.hide();
                                 output::render_message(&message, self.debug);
                                 if interactive {output::show_thinking()};
+
+                                // Show which tools are running while we wait for their results
+                                if interactive && !pending_tool_calls.is_empty() {
+                                    output::set_thinking_message(&format!("Calling {}...", pending_tool_calls.join(", ")));
+                                }
+                                pending_tool_calls.clear();
                             }
                         }
                         Some(Ok(AgentEvent::McpNotification((_id, message)))) => {
@@ -940,11 +1094,7 @@ impl Session {
                                                             if min_priority > 0.1 && !self.debug {
                                                                 // High/Medium verbosity: show truncated response
                                                                 if let Some(response_content) = msg.strip_prefix("Responded: ") {
-                                                                    if response_content.len() > 100 {
-                                                                        format!("🤖 Responded: {}...", &response_content[..100])
-                                                                    } else {
-                                                                        format!("🤖 {}", msg)
-                                                                    }
+                                                                    format!("🤖 Responded: {}", safe_truncate(response_content, 100))
                                                                 } else {
                                                                     format!("🤖 {}", msg)
                                                                 }
@@ -975,7 +1125,7 @@ impl Session {
                                             // Show subagent notifications immediately (no buffering) with compact spacing
                                             if interactive {
                                                 let _ = progress_bars.hide();
-                                                println!("{}", console::style(&formatted_message).green().dim());
+                                                output::render_line(&[Span::new(formatted_message.as_str()).color(Color::Green).dim()]);
                                             } else {
                                                 progress_bars.log(&formatted_message);
                                             }
@@ -983,28 +1133,12 @@ impl Session {
                                             // Non-subagent notification, display immediately with compact spacing
                                             if interactive {
                                                 let _ = progress_bars.hide();
-                                                println!("{}", console::style(&formatted_message).green().dim());
+                                                output::render_line(&[Span::new(formatted_message.as_str()).color(Color::Green).dim()]);
                                             } else {
                                                 progress_bars.log(&formatted_message);
                                             }
                                         }
                                     },
-                                    "notifications/progress" => {
-                                        let progress = o.get("progress").and_then(|v| v.as_f64());
-                                        let token = o.get("progressToken").map(|v| v.to_string());
-                                        let message = o.get("message").and_then(|v| v.as_str());
-                                        let total = o
-                                            .get("total")
-                                            .and_then(|v| v.as_f64());
-                                        if let (Some(progress), Some(token)) = (progress, token) {
-                                            progress_bars.update(
-                                                token.as_str(),
-                                                progress,
-                                                total,
-                                                message,
-                                            );
-                                        }
-                                    },
                                     _ => (),
                                 }
                             }
@@ -1015,6 +1149,94 @@ impl Session {
                                 eprintln!("Model changed to {} in {} mode", model, mode);
                             }
                         }
+                        Some(Ok(AgentEvent::ToolCall { name, .. })) => {
+                            pending_tool_calls.push(name);
+                        }
+                        Some(Ok(AgentEvent::ToolCallProgress { id, name, arguments })) => {
+                            if interactive && name.ends_with("text_editor") {
+                                if let Some(path) = arguments.get("path").and_then(Value::as_str) {
+                                    if shown_tool_paths.insert(id) {
+                                        output::set_thinking_message(&format!("Calling {} on {}...", name, path));
+                                    }
+                                }
+                            }
+                        }
+                        Some(Ok(AgentEvent::ToolProgress { id, name, message, fraction })) => {
+                            // Tools that know how far along they are get a bar, the others update the status line
+                            match fraction {
+                                Some(fraction) => progress_bars.update(&id, fraction, Some(1.0), message.as_deref()),
+                                None if interactive => {
+                                    let status = message.unwrap_or_else(|| "still running".to_string());
+                                    output::set_thinking_message(&format!("{}: {}", name, status));
+                                }
+                                None => {}
+                            }
+                        }
+                        Some(Ok(AgentEvent::Thinking(_)))
+                        | Some(Ok(AgentEvent::Usage(_)))
+                        | Some(Ok(AgentEvent::CumulativeUsage { .. })) => {
+                            // Thinking is rendered with the message that contains it
+                        }
+                        Some(Ok(AgentEvent::Compacted { tokens_before, tokens_after, steps, .. })) => {
+                            if interactive {output::hide_thinking()};
+                            let strategies: Vec<&str> = steps.iter().map(|step| step.strategy.as_str()).collect();
+                            output::render_text(
+                                &format!("Compacted the conversation with {} from about {} to {} tokens", strategies.join(", "), tokens_before, tokens_after),
+                                Some(Color::Yellow),
+                                true,
+                            );
+                        }
+                        Some(Ok(AgentEvent::OversizedMessage(oversized))) => {
+                            if interactive {output::hide_thinking()};
+                            // The message is replaced as the agent sent it, or removed when it was not sent
+                            if self.messages.last().is_some_and(|message| message.role == mcp_core::role::Role::User) {
+                                self.messages.pop();
+                            }
+                            match &oversized.replacement {
+                                Some(replacement) => {
+                                    self.messages.push(replacement.clone());
+                                    output::render_text(&oversized.summary(), Some(Color::Yellow), true);
+                                }
+                                None => output::render_error(&oversized.summary()),
+                            }
+                            session::persist_messages_with_schedule_id(&self.session_file, &self.messages, None, self.scheduled_job_id.clone()).await?;
+                            if interactive {output::show_thinking()};
+                        }
+                        Some(Ok(AgentEvent::SoftStop)) => {
+                            if interactive {output::hide_thinking()};
+                            let _ = progress_bars.hide();
+                            output::render_text("Stopped calling tools, summarizing the progress so far", Some(Color::Yellow), true);
+                            if interactive {output::show_thinking()};
+                        }
+                        Some(Ok(AgentEvent::ApprovalTimedOut { tool_name, approved, .. })) => {
+                            if interactive {output::hide_thinking()};
+                            let decision = if approved { "running it as a read-only tool" } else { "denied" };
+                            output::render_text(&format!("No approval for {} in time, {}", tool_name, decision), Some(Color::Yellow), true);
+                        }
+                        Some(Ok(AgentEvent::UserFacingToolOutput { tool_name, content })) => {
+                            if interactive {output::hide_thinking()};
+                            let _ = progress_bars.hide();
+                            output::render_user_facing_output(&tool_name, &content);
+                        }
+                        Some(Ok(AgentEvent::TurnStats(stats))) => {
+                            let show_turn_stats = Config::global()
+                                .get_param::<bool>("GOOSE_CLI_SHOW_TURN_STATS")
+                                .unwrap_or(false);
+                            if interactive && show_turn_stats {
+                                output::hide_thinking();
+                                let _ = progress_bars.hide();
+                                output::display_turn_stats(&stats);
+                            }
+                        }
+                        Some(Ok(AgentEvent::Interjection(message))) => {
+                            self.messages.push(message.clone());
+                            session::persist_messages_with_schedule_id(&self.session_file, &self.messages, None, self.scheduled_job_id.clone()).await?;
+
+                            if interactive {output::hide_thinking()};
+                            let _ = progress_bars.hide();
+                            output::render_text(&format!("Interjection added: {}", message.as_concat_text()), Some(Color::Cyan), true);
+                            if interactive {output::show_thinking()};
+                        }
 
                         Some(Err(e)) => {
                             eprintln!("Error: {}", e);
@@ -1030,10 +1252,43 @@ impl Session {
                             );
                             break;
                         }
-                        None => break,
+                        None => {
+                            // Interjections that arrived after the last model call start a new turn
+                            let pending = self.agent.take_pending_interjections().await;
+                            if pending.is_empty() {
+                                break;
+                            }
+                            self.messages.push(Message::user().with_text(pending.join("\n")));
+                            session::persist_messages_with_schedule_id(&self.session_file, &self.messages, None, self.scheduled_job_id.clone()).await?;
+
+                            stream = self
+                                .agent
+                                .reply(
+                                    &self.messages,
+                                    Some(SessionConfig {
+                                        id: session_id.clone(),
+                                        working_dir: std::env::current_dir()
+                                            .expect("failed to get current session working directory"),
+                                        schedule_id: self.scheduled_job_id.clone(),
+                                        execution_mode: None,
+                                    }),
+                                )
+                                .await?;
+                        }
                     }
                 }
+                Some(text) = interjections.recv() => {
+                    self.agent.interject(text).await;
+                }
                 _ = tokio::signal::ctrl_c() => {
+                    if !stop_requested {
+                        stop_requested = true;
+                        self.agent.request_stop();
+                        if interactive {output::hide_thinking()};
+                        output::render_text("Stopping once the running tools finish, press Ctrl+C again to cancel now", Some(Color::Yellow), true);
+                        if interactive {output::show_thinking()};
+                        continue;
+                    }
                     drop(stream);
                     if let Err(e) = self.handle_interrupted_messages(true).await {
                         eprintln!("Error handling interruption: {}", e);
@@ -1043,6 +1298,11 @@ impl Session {
             }
         }
 
+        // Interjections that were never delivered, e.g. after a cancel, should not leak into the next reply
+        drop(interjections);
+        self.agent.take_pending_interjections().await;
+        output::render_end_reply();
+
         Ok(())
     }
 
@@ -1157,6 +1417,50 @@ impl Session {
         self.session_file.clone()
     }
 
+    /// Continue the conversation with `model_name` of `provider_name`, or its default model
+    ///
+    /// The history is kept, adapted to the new model, and ends with a note of the switch.
+    async fn switch_provider(
+        &mut self,
+        provider_name: String,
+        model_name: Option<String>,
+    ) -> Result<()> {
+        let model_name = match model_name {
+            Some(model_name) => model_name,
+            None => goose::providers::providers()
+                .into_iter()
+                .find(|metadata| metadata.name == provider_name)
+                .map(|metadata| metadata.default_model)
+                .ok_or_else(|| anyhow::anyhow!("Unknown provider '{}'", provider_name))?,
+        };
+        let current = self.agent.provider().await?.get_model_config();
+        let model_config = goose::model::ModelConfig::new(model_name)
+            .with_temperature(current.temperature)
+            .with_no_validate(current.no_validate);
+
+        output::show_thinking();
+        let switch = self
+            .agent
+            .switch_provider(&provider_name, model_config, &self.messages)
+            .await;
+        output::hide_thinking();
+        let switch = switch?;
+
+        self.messages = switch.messages;
+        self.provider_name = Some(provider_name);
+        session::persist_messages_with_schedule_id(
+            &self.session_file,
+            &self.messages,
+            None,
+            self.scheduled_job_id.clone(),
+        )
+        .await?;
+        if let Some(note) = self.messages.last() {
+            output::render_message(note, self.debug);
+        }
+        self.update_completion_cache().await
+    }
+
     /// Update the completion cache with fresh data
     /// This should be called before the interactive session starts
     pub async fn update_completion_cache(&mut self) -> Result<()> {
@@ -1185,6 +1489,18 @@ impl Session {
             }
         }
 
+        let providers = goose::providers::providers();
+        cache.models = providers
+            .iter()
+            .filter(|metadata| Some(&metadata.name) == self.provider_name.as_ref())
+            .flat_map(|metadata| &metadata.known_models)
+            .map(|model| model.name.clone())
+            .collect();
+        cache.providers = providers
+            .into_iter()
+            .map(|metadata| metadata.name)
+            .collect();
+
         cache.last_updated = Instant::now();
         Ok(())
     }
@@ -1204,27 +1520,40 @@ impl Session {
 
     /// Render all past messages from the session history
     pub fn render_message_history(&self) {
+        self.render_message_history_from(0);
+    }
+
+    /// Render the past messages of the session history from the message at `start` on
+    pub fn render_message_history_from(&self, start: usize) {
         if self.messages.is_empty() {
             return;
         }
 
         // Print session restored message
-        println!(
-            "\n{} {} messages loaded into context.",
-            console::style("Session restored:").green().bold(),
-            console::style(self.messages.len()).green()
-        );
+        output::render_line(&[]);
+        output::render_line(&[
+            Span::new("Session restored:").color(Color::Green).bold(),
+            Span::new(" "),
+            Span::new(self.messages.len()).color(Color::Green),
+            Span::new(" messages loaded into context."),
+        ]);
+        if start > 0 {
+            output::render_line(&[Span::new(format!(
+                "Showing messages from #{} on",
+                start.min(self.messages.len())
+            ))
+            .dim()]);
+        }
 
         // Render each message
-        for message in &self.messages {
-            output::render_message(message, self.debug);
+        for message in self.messages.iter().skip(start) {
+            output::render_history_message(message, self.debug);
         }
 
         // Add a visual separator after restored messages
-        println!(
-            "\n{}\n",
-            console::style("──────── New Messages ────────").dim()
-        );
+        output::render_line(&[]);
+        output::render_line(&[Span::new("──────── New Messages ────────").dim()]);
+        output::render_line(&[]);
     }
 
     /// Get the session metadata
@@ -1,17 +1,23 @@
-use bat::WrappingMode;
-use console::{style, Color};
+use console::Color;
 use goose::config::Config;
 use goose::message::{Message, MessageContent, ToolRequest, ToolResponse};
+use goose::providers::utils::{decode_base64, normalize_base64};
+use goose::session::TurnStats;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use mcp_core::prompt::PromptArgument;
+use mcp_core::role::Role;
 use mcp_core::tool::ToolCall;
+use mcp_core::Content;
 use serde_json::Value;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::io::Error;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, PoisonError};
 use std::time::Duration;
+use tempfile::NamedTempFile;
+
+use super::renderer::{with_renderer, Renderer, Span};
 
 // Re-export theme for use in main
 #[derive(Clone, Copy)]
@@ -22,7 +28,7 @@ pub enum Theme {
 }
 
 impl Theme {
-    fn as_str(&self) -> &'static str {
+    pub(super) fn as_str(&self) -> &'static str {
         match self {
             Theme::Light => "GitHub",
             Theme::Dark => "zenburn",
@@ -104,12 +110,18 @@ pub struct PromptInfo {
     pub extension: Option<String>,
 }
 
+/// Images saved while goose runs, which are deleted when they are dropped
+static SAVED_IMAGES: Mutex<Vec<NamedTempFile>> = Mutex::new(Vec::new());
+
 // Global thinking indicator
 thread_local! {
     static THINKING: RefCell<ThinkingIndicator> = RefCell::new(ThinkingIndicator::default());
 }
 
 pub fn show_thinking() {
+    if !with_renderer(|r| r.shows_progress()) {
+        return;
+    }
     THINKING.with(|t| t.borrow_mut().show());
 }
 
@@ -117,8 +129,7 @@ pub fn hide_thinking() {
     THINKING.with(|t| t.borrow_mut().hide());
 }
 
-#[allow(dead_code)]
-pub fn set_thinking_message(s: &String) {
+pub fn set_thinking_message(s: &str) {
     THINKING.with(|t| {
         if let Some(spinner) = t.borrow_mut().spinner.as_mut() {
             spinner.set_message(s);
@@ -127,98 +138,192 @@ pub fn set_thinking_message(s: &String) {
 }
 
 pub fn render_message(message: &Message, debug: bool) {
-    let theme = get_theme();
+    with_renderer(|r| write_message(r, message, debug, true));
+}
 
-    for content in &message.content {
+/// Render a message restored from the session history, which is never the answer of a reply
+pub fn render_history_message(message: &Message, debug: bool) {
+    with_renderer(|r| write_message(r, message, debug, false));
+}
+
+fn write_message(r: &mut dyn Renderer, message: &Message, debug: bool, reply: bool) {
+    for (index, content) in message.content.iter().enumerate() {
         match content {
-            MessageContent::Text(text) => print_markdown(&text.text, theme),
-            MessageContent::ToolRequest(req) => render_tool_request(req, theme, debug),
-            MessageContent::ToolResponse(resp) => render_tool_response(resp, theme, debug),
+            MessageContent::Text(_) => {
+                let text = message
+                    .text_with_citation_markers(index, superscript)
+                    .unwrap_or_default();
+                if reply && message.role == Role::Assistant {
+                    r.assistant_text(&text);
+                } else {
+                    r.markdown(&text);
+                }
+            }
+            MessageContent::Citations(_) => {
+                // Marked in the text before them, and listed below the message
+            }
+            MessageContent::ToolRequest(req) => write_tool_request(r, req, debug),
+            MessageContent::ToolResponse(resp) => write_tool_response(r, resp, debug, reply),
             MessageContent::Image(image) => {
-                println!("Image: [data: {}, type: {}]", image.data, image.mime_type);
+                r.line(&[Span::new(format!(
+                    "Image: [data: {}, type: {}]",
+                    image.data, image.mime_type
+                ))]);
             }
             MessageContent::Thinking(thinking) => {
                 if std::env::var("GOOSE_CLI_SHOW_THINKING").is_ok() {
-                    println!("\n{}", style("Thinking:").dim().italic());
-                    print_markdown(&thinking.thinking, theme);
+                    r.line(&[]);
+                    r.line(&[Span::new("Thinking:").dim().italic()]);
+                    r.markdown(&thinking.thinking);
                 }
             }
             MessageContent::RedactedThinking(_) => {
                 // For redacted thinking, print thinking was redacted
-                println!("\n{}", style("Thinking:").dim().italic());
-                print_markdown("Thinking was redacted", theme);
+                r.line(&[]);
+                r.line(&[Span::new("Thinking:").dim().italic()]);
+                r.markdown("Thinking was redacted");
             }
             _ => {
-                println!("WARNING: Message content type could not be rendered");
+                r.line(&[Span::new(
+                    "WARNING: Message content type could not be rendered",
+                )]);
             }
         }
     }
-    println!();
+    write_citation_sources(r, message);
+    r.line(&[]);
+}
+
+/// A number in superscript digits, to mark cited text
+fn superscript(number: usize) -> String {
+    const DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+    number
+        .to_string()
+        .chars()
+        .filter_map(|digit| digit.to_digit(10).map(|d| DIGITS[d as usize]))
+        .collect()
+}
+
+fn write_citation_sources(r: &mut dyn Renderer, message: &Message) {
+    let sources = message.citation_sources();
+    if sources.is_empty() {
+        return;
+    }
+    r.line(&[Span::new("Sources:").dim()]);
+    for (number, source) in sources.into_iter().enumerate() {
+        let title = source
+            .title
+            .as_deref()
+            .or(source.url.as_deref())
+            .unwrap_or("Source");
+        let mut spans = vec![
+            Span::new("  "),
+            Span::new(superscript(number + 1)).dim(),
+            Span::new(format!(" {}", title)),
+        ];
+        if let Some(url) = source.url.as_ref().filter(|_| source.title.is_some()) {
+            spans.push(Span::new(" "));
+            spans.push(Span::new(url).dim());
+        }
+        r.line(&spans);
+    }
+}
+
+/// A line of status output, such as the result of a slash command
+pub fn render_line(spans: &[Span]) {
+    with_renderer(|r| r.line(spans));
+}
+
+/// The agent finished replying, which is when the quiet output shows its answer
+pub fn render_end_reply() {
+    with_renderer(|r| r.end_reply());
 }
 
 pub fn render_text(text: &str, color: Option<Color>, dim: bool) {
-    render_text_no_newlines(format!("\n{}\n\n", text).as_str(), color, dim);
+    with_renderer(|r| write_text(r, text, color, dim));
 }
 
-pub fn render_text_no_newlines(text: &str, color: Option<Color>, dim: bool) {
-    let mut styled_text = style(text);
+fn write_text(r: &mut dyn Renderer, text: &str, color: Option<Color>, dim: bool) {
+    let mut span = Span::new(text).color(color.unwrap_or(Color::Green));
     if dim {
-        styled_text = styled_text.dim();
-    }
-    if let Some(color) = color {
-        styled_text = styled_text.fg(color);
-    } else {
-        styled_text = styled_text.green();
+        span = span.dim();
     }
-    print!("{}", styled_text);
+    r.line(&[]);
+    r.line(&[span]);
+    r.line(&[]);
 }
 
 pub fn render_enter_plan_mode() {
-    println!(
-        "\n{} {}\n",
-        style("Entering plan mode.").green().bold(),
-        style("You can provide instructions to create a plan and then act on it. To exit early, type /endplan")
-            .green()
-            .dim()
-    );
+    render_lines(&[
+        vec![],
+        vec![
+            Span::new("Entering plan mode.").color(Color::Green).bold(),
+            Span::new(" "),
+            Span::new("You can provide instructions to create a plan and then act on it. To exit early, type /endplan")
+                .color(Color::Green)
+                .dim(),
+        ],
+        vec![],
+    ]);
 }
 
 pub fn render_act_on_plan() {
-    println!(
-        "\n{}\n",
-        style("Exiting plan mode and acting on the above plan")
-            .green()
-            .bold(),
-    );
+    render_lines(&[
+        vec![],
+        vec![Span::new("Exiting plan mode and acting on the above plan")
+            .color(Color::Green)
+            .bold()],
+        vec![],
+    ]);
 }
 
 pub fn render_exit_plan_mode() {
-    println!("\n{}\n", style("Exiting plan mode.").green().bold());
+    render_lines(&[
+        vec![],
+        vec![Span::new("Exiting plan mode.").color(Color::Green).bold()],
+        vec![],
+    ]);
 }
 
 pub fn goose_mode_message(text: &str) {
-    println!("\n{}", style(text).yellow(),);
+    render_lines(&[vec![], vec![Span::new(text).color(Color::Yellow)]]);
 }
 
-fn render_tool_request(req: &ToolRequest, theme: Theme, debug: bool) {
+fn render_lines(lines: &[Vec<Span>]) {
+    with_renderer(|r| {
+        for line in lines {
+            r.line(line);
+        }
+    });
+}
+
+fn write_tool_request(r: &mut dyn Renderer, req: &ToolRequest, debug: bool) {
     match &req.tool_call {
         Ok(call) => match call.name.as_str() {
-            "developer__text_editor" => render_text_editor_request(call, debug),
-            "developer__shell" => render_shell_request(call, debug),
-            _ => render_default_request(call, debug),
+            "developer__text_editor" => write_text_editor_request(r, call, debug),
+            "developer__shell" => write_shell_request(r, call, debug),
+            _ => write_default_request(r, call, debug),
         },
-        Err(e) => print_markdown(&e.to_string(), theme),
+        Err(e) => r.markdown(&e.to_string()),
     }
 }
 
-fn render_tool_response(resp: &ToolResponse, theme: Theme, debug: bool) {
+fn write_tool_response(r: &mut dyn Renderer, resp: &ToolResponse, debug: bool, reply: bool) {
     let config = Config::global();
 
     match &resp.tool_result {
         Ok(contents) => {
             for content in contents {
                 if let Some(audience) = content.audience() {
-                    if !audience.contains(&mcp_core::role::Role::User) {
+                    if !audience.contains(&Role::User) {
+                        continue;
+                    }
+                    // During a reply, user-only content is rendered from the
+                    // UserFacingToolOutput event, and only the history shows it here
+                    if !audience.contains(&Role::Assistant) {
+                        if !reply {
+                            write_user_facing_content(r, content);
+                        }
                         continue;
                     }
                 }
@@ -237,121 +342,302 @@ fn render_tool_response(resp: &ToolResponse, theme: Theme, debug: bool) {
                 }
 
                 if debug {
-                    println!("{:#?}", content);
-                } else if let mcp_core::content::Content::Text(text) = content {
-                    print_markdown(&text.text, theme);
+                    r.line(&[Span::new(format!("{:#?}", content))]);
+                } else if let Some(text) = content.as_text() {
+                    r.markdown(text);
                 }
             }
         }
-        Err(e) => print_markdown(&e.to_string(), theme),
+        Err(e) => r.markdown(&e.to_string()),
     }
-}
 
-pub fn render_error(message: &str) {
-    println!("\n  {} {}\n", style("error:").red().bold(), message);
+    if let Some(duration) = resp
+        .duration()
+        .filter(|duration| duration.as_secs_f64() >= get_slow_tool_seconds())
+    {
+        r.line(&[Span::new(format!("Took {:.1}s", duration.as_secs_f64())).dim()]);
+    }
 }
 
-pub fn render_prompts(prompts: &HashMap<String, Vec<String>>) {
-    println!();
-    for (extension, prompts) in prompts {
-        println!(" {}", style(extension).green());
-        for prompt in prompts {
-            println!("  - {}", style(prompt).cyan());
+/// Render tool output meant only for the user. Text is printed inline and images are shown
+/// inline on terminals with an image protocol, or saved to a temp file otherwise.
+pub fn render_user_facing_output(tool_name: &str, content: &[Content]) {
+    with_renderer(|r| {
+        r.line(&[]);
+        r.line(&[Span::new(format!("Output from {}:", tool_name)).dim()]);
+        for item in content {
+            write_user_facing_content(r, item);
         }
+    });
+}
+
+fn write_user_facing_content(r: &mut dyn Renderer, content: &Content) {
+    match content {
+        Content::Text(text) => r.markdown(&text.text),
+        Content::Result(result) => r.markdown(&result.text),
+        Content::Resource(resource) => r.markdown(&resource.get_text()),
+        Content::Image(image) => write_image(r, &image.data, &image.mime_type),
     }
-    println!();
 }
 
-pub fn render_prompt_info(info: &PromptInfo) {
-    println!();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InlineImageProtocol {
+    Iterm,
+    Kitty,
+}
 
-    if let Some(ext) = &info.extension {
-        println!(" {}: {}", style("Extension").green(), ext);
+fn detect_inline_image_protocol() -> Option<InlineImageProtocol> {
+    let term_program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+    if term_program == "iTerm.app" || term_program == "WezTerm" {
+        return Some(InlineImageProtocol::Iterm);
     }
+    if std::env::var_os("KITTY_WINDOW_ID").is_some()
+        || std::env::var("TERM").is_ok_and(|term| term == "xterm-kitty")
+    {
+        return Some(InlineImageProtocol::Kitty);
+    }
+    None
+}
 
-    println!(" Prompt: {}", style(&info.name).cyan().bold());
+fn image_extension(mime_type: &str) -> &'static str {
+    match mime_type {
+        "image/png" => "png",
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        _ => "img",
+    }
+}
 
-    if let Some(desc) = &info.description {
-        println!("\n {}", desc);
+/// The escape sequence that displays a base64 encoded image inline
+fn inline_image_sequence(protocol: InlineImageProtocol, data: &str, size: usize) -> String {
+    match protocol {
+        InlineImageProtocol::Iterm => {
+            format!("\x1b]1337;File=inline=1;size={}:{}\x07", size, data)
+        }
+        // Kitty takes the payload in chunks of at most 4096 bytes
+        InlineImageProtocol::Kitty => {
+            let chunks: Vec<&[u8]> = data.as_bytes().chunks(4096).collect();
+            let mut sequence = String::new();
+            for (i, chunk) in chunks.iter().enumerate() {
+                let more = u8::from(i + 1 < chunks.len());
+                let control = if i == 0 {
+                    format!("f=100,a=T,m={}", more)
+                } else {
+                    format!("m={}", more)
+                };
+                sequence.push_str(&format!(
+                    "\x1b_G{};{}\x1b\\",
+                    control,
+                    String::from_utf8_lossy(chunk)
+                ));
+            }
+            sequence
+        }
     }
+}
 
-    if let Some(args) = &info.arguments {
-        println!("\n Arguments:");
-        for arg in args {
-            let required = arg.required.unwrap_or(false);
-            let req_str = if required {
-                style("(required)").red()
-            } else {
-                style("(optional)").dim()
-            };
-
-            println!(
-                "  {} {} {}",
-                style(&arg.name).yellow(),
-                req_str,
-                arg.description.as_deref().unwrap_or("")
-            );
+fn write_image(r: &mut dyn Renderer, data: &str, mime_type: &str) {
+    let bytes = match decode_base64(data) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            write_error(r, &format!("Could not decode image: {}", e));
+            return;
         }
+    };
+
+    // Kitty can only display PNG data directly
+    let protocol = detect_inline_image_protocol().filter(|_| r.shows_inline_images());
+    match protocol {
+        Some(InlineImageProtocol::Kitty) if mime_type != "image/png" => {}
+        Some(protocol) => {
+            r.raw(&format!(
+                "{}\n",
+                inline_image_sequence(protocol, &normalize_base64(data), bytes.len())
+            ));
+            return;
+        }
+        None => {}
+    }
+
+    let saved = tempfile::Builder::new()
+        .prefix("goose-image-")
+        .suffix(&format!(".{}", image_extension(mime_type)))
+        .tempfile()
+        .and_then(|mut file| {
+            std::io::Write::write_all(&mut file, &bytes)?;
+            Ok(file)
+        });
+    match saved {
+        Ok(file) => {
+            r.line(&[Span::new(format!(
+                "Image saved to {}",
+                file.path().display()
+            ))]);
+            SAVED_IMAGES
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(file);
+        }
+        Err(e) => write_error(r, &format!("Could not save image: {}", e)),
     }
-    println!();
+}
+
+/// Remove the images saved for terminals that can't show them inline, when goose exits
+pub fn remove_saved_images() {
+    SAVED_IMAGES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clear();
+}
+
+pub fn render_error(message: &str) {
+    with_renderer(|r| write_error(r, message));
+}
+
+fn write_error(r: &mut dyn Renderer, message: &str) {
+    r.line(&[]);
+    r.line(&[
+        Span::new("  "),
+        Span::new("error:").color(Color::Red).bold(),
+        Span::new(format!(" {}", message)),
+    ]);
+    r.line(&[]);
+}
+
+pub fn render_prompts(prompts: &HashMap<String, Vec<String>>) {
+    with_renderer(|r| {
+        r.line(&[]);
+        for (extension, prompts) in prompts {
+            r.line(&[Span::new(" "), Span::new(extension).color(Color::Green)]);
+            for prompt in prompts {
+                r.line(&[Span::new("  - "), Span::new(prompt).color(Color::Cyan)]);
+            }
+        }
+        r.line(&[]);
+    });
+}
+
+pub fn render_prompt_info(info: &PromptInfo) {
+    with_renderer(|r| {
+        r.line(&[]);
+
+        if let Some(ext) = &info.extension {
+            r.line(&[
+                Span::new(" "),
+                Span::new("Extension").color(Color::Green),
+                Span::new(format!(": {}", ext)),
+            ]);
+        }
+
+        r.line(&[
+            Span::new(" Prompt: "),
+            Span::new(&info.name).color(Color::Cyan).bold(),
+        ]);
+
+        if let Some(desc) = &info.description {
+            r.line(&[]);
+            r.line(&[Span::new(format!(" {}", desc))]);
+        }
+
+        if let Some(args) = &info.arguments {
+            r.line(&[]);
+            r.line(&[Span::new(" Arguments:")]);
+            for arg in args {
+                let required = arg.required.unwrap_or(false);
+                let req_str = if required {
+                    Span::new("(required)").color(Color::Red)
+                } else {
+                    Span::new("(optional)").dim()
+                };
+
+                r.line(&[
+                    Span::new("  "),
+                    Span::new(&arg.name).color(Color::Yellow),
+                    Span::new(" "),
+                    req_str,
+                    Span::new(format!(" {}", arg.description.as_deref().unwrap_or(""))),
+                ]);
+            }
+        }
+        r.line(&[]);
+    });
 }
 
 pub fn render_extension_success(name: &str) {
-    println!();
-    println!(
-        "  {} extension `{}`",
-        style("added").green(),
-        style(name).cyan(),
-    );
-    println!();
+    render_lines(&[
+        vec![],
+        vec![
+            Span::new("  "),
+            Span::new("added").color(Color::Green),
+            Span::new(" extension `"),
+            Span::new(name).color(Color::Cyan),
+            Span::new("`"),
+        ],
+        vec![],
+    ]);
 }
 
 pub fn render_extension_error(name: &str, error: &str) {
-    println!();
-    println!(
-        "  {} to add extension {}",
-        style("failed").red(),
-        style(name).red()
-    );
-    println!();
-    println!("{}", style(error).dim());
-    println!();
+    render_lines(&[
+        vec![],
+        vec![
+            Span::new("  "),
+            Span::new("failed").color(Color::Red),
+            Span::new(" to add extension "),
+            Span::new(name).color(Color::Red),
+        ],
+        vec![],
+        vec![Span::new(error).dim()],
+        vec![],
+    ]);
 }
 
 pub fn render_builtin_success(names: &str) {
-    println!();
-    println!(
-        "  {} builtin{}: {}",
-        style("added").green(),
-        if names.contains(',') { "s" } else { "" },
-        style(names).cyan()
-    );
-    println!();
+    render_lines(&[
+        vec![],
+        vec![
+            Span::new("  "),
+            Span::new("added").color(Color::Green),
+            Span::new(format!(
+                " builtin{}: ",
+                if names.contains(',') { "s" } else { "" }
+            )),
+            Span::new(names).color(Color::Cyan),
+        ],
+        vec![],
+    ]);
 }
 
 pub fn render_builtin_error(names: &str, error: &str) {
-    println!();
-    println!(
-        "  {} to add builtin{}: {}",
-        style("failed").red(),
-        if names.contains(',') { "s" } else { "" },
-        style(names).red()
-    );
-    println!();
-    println!("{}", style(error).dim());
-    println!();
+    render_lines(&[
+        vec![],
+        vec![
+            Span::new("  "),
+            Span::new("failed").color(Color::Red),
+            Span::new(format!(
+                " to add builtin{}: ",
+                if names.contains(',') { "s" } else { "" }
+            )),
+            Span::new(names).color(Color::Red),
+        ],
+        vec![],
+        vec![Span::new(error).dim()],
+        vec![],
+    ]);
 }
 
-fn render_text_editor_request(call: &ToolCall, debug: bool) {
-    print_tool_header(call);
+fn write_text_editor_request(r: &mut dyn Renderer, call: &ToolCall, debug: bool) {
+    write_tool_header(r, call);
 
     // Print path first with special formatting
     if let Some(Value::String(path)) = call.arguments.get("path") {
-        println!(
-            "{}: {}",
-            style("path").dim(),
-            style(shorten_path(path, debug)).green()
-        );
+        r.line(&[
+            Span::new("path").dim(),
+            Span::new(": "),
+            Span::new(shorten_path(path, debug)).color(Color::Green),
+        ]);
     }
 
     // Print other arguments normally, excluding path
@@ -362,46 +648,46 @@ fn render_text_editor_request(call: &ToolCall, debug: bool) {
                 other_args.insert(k.clone(), v.clone());
             }
         }
-        print_params(&Value::Object(other_args), 0, debug);
+        write_params(r, &Value::Object(other_args), 0, debug);
     }
-    println!();
+    r.line(&[]);
 }
 
-fn render_shell_request(call: &ToolCall, debug: bool) {
-    print_tool_header(call);
+fn write_shell_request(r: &mut dyn Renderer, call: &ToolCall, debug: bool) {
+    write_tool_header(r, call);
 
     match call.arguments.get("command") {
         Some(Value::String(s)) => {
-            println!("{}: {}", style("command").dim(), style(s).green());
+            r.line(&[
+                Span::new("command").dim(),
+                Span::new(": "),
+                Span::new(s).color(Color::Green),
+            ]);
         }
-        _ => print_params(&call.arguments, 0, debug),
+        _ => write_params(r, &call.arguments, 0, debug),
     }
 }
 
-fn render_default_request(call: &ToolCall, debug: bool) {
-    print_tool_header(call);
-    print_params(&call.arguments, 0, debug);
-    println!();
+fn write_default_request(r: &mut dyn Renderer, call: &ToolCall, debug: bool) {
+    write_tool_header(r, call);
+    write_params(r, &call.arguments, 0, debug);
+    r.line(&[]);
 }
 
 // Helper functions
 
-fn print_tool_header(call: &ToolCall) {
+fn write_tool_header(r: &mut dyn Renderer, call: &ToolCall) {
     let parts: Vec<_> = call.name.rsplit("__").collect();
-    let tool_header = format!(
-        "─── {} | {} ──────────────────────────",
-        style(parts.first().unwrap_or(&"unknown")),
-        style(
-            parts
-                .split_first()
-                .map(|(_, s)| s.iter().rev().copied().collect::<Vec<_>>().join("__"))
-                .unwrap_or_else(|| "unknown".to_string())
-        )
-        .magenta()
-        .dim(),
-    );
-    println!();
-    println!("{}", tool_header);
+    let extension = parts
+        .split_first()
+        .map(|(_, s)| s.iter().rev().copied().collect::<Vec<_>>().join("__"))
+        .unwrap_or_else(|| "unknown".to_string());
+    r.line(&[]);
+    r.line(&[
+        Span::new(format!("─── {} | ", parts.first().unwrap_or(&"unknown"))),
+        Span::new(extension).color(Color::Magenta).dim(),
+        Span::new(" ──────────────────────────"),
+    ]);
 }
 
 // Respect NO_COLOR, as https://crates.io/crates/console already does
@@ -410,17 +696,6 @@ pub fn env_no_color() -> bool {
     std::env::var_os("NO_COLOR").is_none()
 }
 
-fn print_markdown(content: &str, theme: Theme) {
-    bat::PrettyPrinter::new()
-        .input(bat::Input::from_bytes(content.as_bytes()))
-        .theme(theme.as_str())
-        .colored_output(env_no_color())
-        .language("Markdown")
-        .wrapping_mode(WrappingMode::NoWrapping(true))
-        .print()
-        .unwrap();
-}
-
 const INDENT: &str = "    ";
 
 fn get_tool_params_max_length() -> usize {
@@ -430,69 +705,71 @@ fn get_tool_params_max_length() -> usize {
         .unwrap_or(40)
 }
 
-fn print_params(value: &Value, depth: usize, debug: bool) {
+/// Tools that take at least this long show how long they took
+fn get_slow_tool_seconds() -> f64 {
+    Config::global()
+        .get_param::<f64>("GOOSE_CLI_SLOW_TOOL_SECONDS")
+        .ok()
+        .unwrap_or(10.0)
+}
+
+fn write_params(r: &mut dyn Renderer, value: &Value, depth: usize, debug: bool) {
     let indent = INDENT.repeat(depth);
 
     match value {
         Value::Object(map) => {
             for (key, val) in map {
+                let key_spans = [Span::new(&indent), Span::new(key).dim(), Span::new(":")];
+                let entry = |value: Span| {
+                    let mut spans = key_spans.to_vec();
+                    spans.push(Span::new(" "));
+                    spans.push(value);
+                    spans
+                };
                 match val {
                     Value::Object(_) => {
-                        println!("{}{}:", indent, style(key).dim());
-                        print_params(val, depth + 1, debug);
+                        r.line(&key_spans);
+                        write_params(r, val, depth + 1, debug);
                     }
                     Value::Array(arr) => {
-                        println!("{}{}:", indent, style(key).dim());
+                        r.line(&key_spans);
                         for item in arr.iter() {
-                            println!("{}{}- ", indent, INDENT);
-                            print_params(item, depth + 2, debug);
+                            r.line(&[Span::new(format!("{}{}- ", indent, INDENT))]);
+                            write_params(r, item, depth + 2, debug);
                         }
                     }
                     Value::String(s) => {
                         if !debug && s.len() > get_tool_params_max_length() {
-                            println!("{}{}: {}", indent, style(key).dim(), style("...").dim());
+                            r.line(&entry(Span::new("...").dim()));
                         } else {
-                            println!("{}{}: {}", indent, style(key).dim(), style(s).green());
+                            r.line(&entry(Span::new(s).color(Color::Green)));
                         }
                     }
-                    Value::Number(n) => {
-                        println!("{}{}: {}", indent, style(key).dim(), style(n).blue());
-                    }
-                    Value::Bool(b) => {
-                        println!("{}{}: {}", indent, style(key).dim(), style(b).blue());
-                    }
-                    Value::Null => {
-                        println!("{}{}: {}", indent, style(key).dim(), style("null").dim());
-                    }
+                    Value::Number(n) => r.line(&entry(Span::new(n).color(Color::Blue))),
+                    Value::Bool(b) => r.line(&entry(Span::new(b).color(Color::Blue))),
+                    Value::Null => r.line(&entry(Span::new("null").dim())),
                 }
             }
         }
         Value::Array(arr) => {
             for (i, item) in arr.iter().enumerate() {
-                println!("{}{}.", indent, i + 1);
-                print_params(item, depth + 1, debug);
+                r.line(&[Span::new(format!("{}{}.", indent, i + 1))]);
+                write_params(r, item, depth + 1, debug);
             }
         }
         Value::String(s) => {
             if !debug && s.len() > get_tool_params_max_length() {
-                println!(
-                    "{}{}",
-                    indent,
-                    style(format!("[REDACTED: {} chars]", s.len())).yellow()
-                );
+                r.line(&[
+                    Span::new(&indent),
+                    Span::new(format!("[REDACTED: {} chars]", s.len())).color(Color::Yellow),
+                ]);
             } else {
-                println!("{}{}", indent, style(s).green());
+                r.line(&[Span::new(&indent), Span::new(s).color(Color::Green)]);
             }
         }
-        Value::Number(n) => {
-            println!("{}{}", indent, style(n).yellow());
-        }
-        Value::Bool(b) => {
-            println!("{}{}", indent, style(b).yellow());
-        }
-        Value::Null => {
-            println!("{}{}", indent, style("null").dim());
-        }
+        Value::Number(n) => r.line(&[Span::new(&indent), Span::new(n).color(Color::Yellow)]),
+        Value::Bool(b) => r.line(&[Span::new(&indent), Span::new(b).color(Color::Yellow)]),
+        Value::Null => r.line(&[Span::new(&indent), Span::new("null").dim()]),
     }
 }
 
@@ -561,69 +838,68 @@ pub fn display_session_info(
         "starting session |"
     };
 
+    let mut models = vec![("model:", model.to_string())];
     // Check if we have lead/worker mode
-    if let Some(provider_inst) = provider_instance {
-        if let Some(lead_worker) = provider_inst.as_lead_worker() {
-            let (lead_model, worker_model) = lead_worker.get_model_info();
-            println!(
-                "{} {} {} {} {} {} {}",
-                style(start_session_msg).dim(),
-                style("provider:").dim(),
-                style(provider).cyan().dim(),
-                style("lead model:").dim(),
-                style(&lead_model).cyan().dim(),
-                style("worker model:").dim(),
-                style(&worker_model).cyan().dim(),
-            );
-        } else {
-            println!(
-                "{} {} {} {} {}",
-                style(start_session_msg).dim(),
-                style("provider:").dim(),
-                style(provider).cyan().dim(),
-                style("model:").dim(),
-                style(model).cyan().dim(),
-            );
-        }
-    } else {
-        // Fallback to original behavior if no provider instance
-        println!(
-            "{} {} {} {} {}",
-            style(start_session_msg).dim(),
-            style("provider:").dim(),
-            style(provider).cyan().dim(),
-            style("model:").dim(),
-            style(model).cyan().dim(),
-        );
+    if let Some(lead_worker) = provider_instance.and_then(|p| p.as_lead_worker()) {
+        let (lead_model, worker_model) = lead_worker.get_model_info();
+        models = vec![("lead model:", lead_model), ("worker model:", worker_model)];
     }
 
-    if session_file.to_str() != Some("/dev/null") && session_file.to_str() != Some("NUL") {
-        println!(
-            "    {} {}",
-            style("logging to").dim(),
-            style(session_file.display()).dim().cyan(),
-        );
+    let mut spans = vec![
+        Span::new(start_session_msg).dim(),
+        Span::new(" "),
+        Span::new("provider:").dim(),
+        Span::new(" "),
+        Span::new(provider).color(Color::Cyan).dim(),
+    ];
+    for (label, model) in models {
+        spans.push(Span::new(" "));
+        spans.push(Span::new(label).dim());
+        spans.push(Span::new(" "));
+        spans.push(Span::new(model).color(Color::Cyan).dim());
     }
 
-    println!(
-        "    {} {}",
-        style("working directory:").dim(),
-        style(std::env::current_dir().unwrap().display())
-            .cyan()
-            .dim()
-    );
+    with_renderer(|r| {
+        r.line(&spans);
+
+        if session_file.to_str() != Some("/dev/null") && session_file.to_str() != Some("NUL") {
+            r.line(&[
+                Span::new("    "),
+                Span::new("logging to").dim(),
+                Span::new(" "),
+                Span::new(session_file.display()).dim().color(Color::Cyan),
+            ]);
+        }
+
+        r.line(&[
+            Span::new("    "),
+            Span::new("working directory:").dim(),
+            Span::new(" "),
+            Span::new(std::env::current_dir().unwrap().display())
+                .color(Color::Cyan)
+                .dim(),
+        ]);
+    });
 }
 
 pub fn display_greeting() {
-    println!("\nGoose is running! Enter your instructions, or try asking what goose can do.\n");
+    render_lines(&[
+        vec![],
+        vec![Span::new(
+            "Goose is running! Enter your instructions, or try asking what goose can do.",
+        )],
+        vec![],
+    ]);
 }
 
 /// Display context window usage with both current and session totals
 pub fn display_context_usage(total_tokens: usize, context_limit: usize) {
-    use console::style;
+    with_renderer(|r| write_context_usage(r, total_tokens, context_limit));
+}
 
+fn write_context_usage(r: &mut dyn Renderer, total_tokens: usize, context_limit: usize) {
     if context_limit == 0 {
-        println!("Context: Error - context limit is zero");
+        r.line(&[Span::new("Context: Error - context limit is zero")]);
         return;
     }
 
@@ -642,19 +918,39 @@ pub fn display_context_usage(total_tokens: usize, context_limit: usize) {
 
     // Combine dots and apply color
     let dots = format!("{}{}", filled, empty);
-    let colored_dots = if percentage < 50 {
-        style(dots).green()
+    let color = if percentage < 50 {
+        Color::Green
     } else if percentage < 85 {
-        style(dots).yellow()
+        Color::Yellow
     } else {
-        style(dots).red()
+        Color::Red
     };
 
     // Print the status line
-    println!(
-        "Context: {} {}% ({}/{} tokens)",
-        colored_dots, percentage, total_tokens, context_limit
+    r.line(&[
+        Span::new("Context: "),
+        Span::new(dots).color(color),
+        Span::new(format!(
+            " {}% ({}/{} tokens)",
+            percentage, total_tokens, context_limit
+        )),
+    ]);
+}
+
+pub fn display_turn_stats(stats: &TurnStats) {
+    let first_token = stats
+        .time_to_first_token_ms
+        .map_or_else(|| "n/a".to_string(), |ms| format!("{} ms", ms));
+    let footer = format!(
+        "Turn: {} ms | provider: {} ms over {} calls | first token: {} | tools: {} ms over {} calls",
+        stats.turn_duration_ms,
+        stats.provider_latency_ms,
+        stats.provider_calls,
+        first_token,
+        stats.tool_time_ms(),
+        stats.tool_calls.len(),
     );
+    render_line(&[Span::new(footer).dim()]);
 }
 
 pub struct McpSpinners {
@@ -674,6 +970,9 @@ impl McpSpinners {
     }
 
     pub fn log(&mut self, message: &str) {
+        if !with_renderer(|r| r.shows_progress()) {
+            return;
+        }
         let spinner = self.log_spinner.get_or_insert_with(|| {
             let bar = self.multi_bar.add(
                 ProgressBar::new_spinner()
@@ -692,6 +991,9 @@ impl McpSpinners {
     }
 
     pub fn update(&mut self, token: &str, value: f64, total: Option<f64>, message: Option<&str>) {
+        if !with_renderer(|r| r.shows_progress()) {
+            return;
+        }
         let bar = self.bars.entry(token.to_string()).or_insert_with(|| {
             if let Some(total) = total {
                 self.multi_bar.add(
@@ -723,9 +1025,148 @@ impl McpSpinners {
 
 #[cfg(test)]
 mod tests {
+    use super::super::renderer::tests::SharedBuffer;
+    use super::super::renderer::PlainRenderer;
     use super::*;
+    use serde_json::json;
     use std::env;
 
+    fn render_plain(write: impl FnOnce(&mut dyn Renderer)) -> String {
+        let buffer = SharedBuffer::default();
+        write(&mut PlainRenderer::new(buffer.clone()));
+        buffer.contents()
+    }
+
+    #[test]
+    fn test_plain_message_snapshot() {
+        let message = Message::assistant()
+            .with_text("Here is the fix:\n\n```rust\nfn main() {}\n```")
+            .with_tool_request(
+                "1",
+                Ok(ToolCall::new(
+                    "developer__shell",
+                    json!({"command": "cargo test"}),
+                )),
+            )
+            .with_tool_request(
+                "2",
+                Ok(ToolCall::new(
+                    "github__create_issue",
+                    json!({"title": "Flaky test", "labels": ["ci"], "draft": false}),
+                )),
+            );
+
+        assert_eq!(
+            render_plain(|r| write_message(r, &message, false, true)),
+            "Here is the fix:\n\n```rust\nfn main() {}\n```\n\
+             \n\
+             ─── shell | developer ──────────────────────────\n\
+             command: cargo test\n\
+             \n\
+             ─── create_issue | github ──────────────────────────\n\
+             draft: false\n\
+             labels:\n    \
+                 -\n        \
+                     ci\n\
+             title: Flaky test\n\
+             \n\
+             \n"
+        );
+    }
+
+    #[test]
+    fn test_plain_status_snapshot() {
+        assert_eq!(
+            render_plain(|r| write_error(r, "Could not save image")),
+            "\n  error: Could not save image\n\n"
+        );
+        assert_eq!(
+            render_plain(|r| write_context_usage(r, 6000, 10000)),
+            "Context: ●●●●●●○○○○ 60% (6000/10000 tokens)\n"
+        );
+        assert_eq!(
+            render_plain(|r| write_text(r, "Interjection added: stop", None, true)),
+            "\nInterjection added: stop\n\n"
+        );
+    }
+
+    #[test]
+    fn test_slow_tool_shows_duration() {
+        let response = |finished_at| {
+            Message::user().with_traced_tool_response(
+                "call_1",
+                Ok(vec![Content::text("done").with_priority(1.0)]),
+                goose::message::ToolProvenance {
+                    message_index: 0,
+                    extension: "developer".to_string(),
+                    started_at: 1_000,
+                    finished_at,
+                },
+            )
+        };
+        assert_eq!(
+            render_plain(|r| write_message(r, &response(13_500), false, false)),
+            "done\nTook 12.5s\n\n"
+        );
+        assert_eq!(
+            render_plain(|r| write_message(r, &response(2_000), false, false)),
+            "done\n\n"
+        );
+    }
+
+    #[test]
+    fn test_history_shows_user_only_output() {
+        let message = Message::user().with_tool_response(
+            "call_1",
+            Ok(vec![
+                Content::text("for the model").with_audience(vec![Role::Assistant]),
+                Content::text("a chart for you").with_audience(vec![Role::User]),
+            ]),
+        );
+        // The live reply shows it from the UserFacingToolOutput event instead
+        assert_eq!(
+            render_plain(|r| write_message(r, &message, false, true)),
+            "\n"
+        );
+        assert_eq!(
+            render_plain(|r| write_message(r, &message, false, false)),
+            "a chart for you\n\n"
+        );
+    }
+
+    #[test]
+    fn test_saved_images_are_removed() {
+        const PNG_DATA: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=";
+        let output = render_plain(|r| write_image(r, PNG_DATA, "image/png"));
+        let path = Path::new(output.trim().strip_prefix("Image saved to ").unwrap());
+        assert!(path.exists());
+
+        remove_saved_images();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_image_extension() {
+        assert_eq!(image_extension("image/png"), "png");
+        assert_eq!(image_extension("image/jpeg"), "jpg");
+        assert_eq!(image_extension("application/octet-stream"), "img");
+    }
+
+    #[test]
+    fn test_superscript() {
+        assert_eq!(superscript(1), "¹");
+        assert_eq!(superscript(10), "¹⁰");
+    }
+
+    #[test]
+    fn test_kitty_sequence_is_chunked() {
+        let data = "A".repeat(5000);
+        let sequence = inline_image_sequence(InlineImageProtocol::Kitty, &data, 3750);
+        assert!(sequence.starts_with("\x1b_Gf=100,a=T,m=1;"));
+        assert!(sequence.contains("\x1b_Gm=0;"));
+        assert_eq!(sequence.matches("\x1b_G").count(), 2);
+    }
+
     #[test]
     fn test_short_paths_unchanged() {
         assert_eq!(shorten_path("/usr/bin", false), "/usr/bin");
@@ -9,7 +9,8 @@ pub fn get_cli_prompt() -> String {
 - /? or /help - Display help message
 
 Additional keyboard shortcuts:
-- Ctrl+C - Interrupt the current interaction (resets to before the interrupted request)
+- Ctrl+C - Stop once the running tools finish, with a summary of the progress
+- Ctrl+C twice - Interrupt the current interaction (resets to before the interrupted request)
 - Ctrl+J - Add a newline
 - Up/Down arrows - Navigate command history"
     )
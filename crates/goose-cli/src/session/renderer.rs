@@ -0,0 +1,300 @@
+use bat::WrappingMode;
+use console::{style, Color};
+use once_cell::sync::Lazy;
+use std::io::{IsTerminal, Write};
+use std::sync::Mutex;
+
+use super::output::{env_no_color, get_theme};
+
+/// How the CLI shows a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputMode {
+    /// Colors, syntax highlighting and spinners, for terminals
+    Rich,
+    /// No escape sequences or spinners, one line at a time, for files and screen readers
+    Plain,
+    /// Only the final assistant text of each reply
+    Quiet,
+}
+
+impl OutputMode {
+    /// Rich output when stdout is a terminal, plain output otherwise
+    pub fn detect() -> Self {
+        if std::io::stdout().is_terminal() {
+            OutputMode::Rich
+        } else {
+            OutputMode::Plain
+        }
+    }
+
+    pub fn renderer(self) -> Box<dyn Renderer + Send> {
+        match self {
+            OutputMode::Rich => Box::new(RichRenderer),
+            OutputMode::Plain => Box::new(PlainRenderer::new(std::io::stdout())),
+            OutputMode::Quiet => Box::new(QuietRenderer::new(std::io::stdout())),
+        }
+    }
+}
+
+/// A piece of a status line, shown with its style by the rich renderer
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub color: Option<Color>,
+    pub dim: bool,
+    pub bold: bool,
+    pub italic: bool,
+}
+
+impl Span {
+    pub fn new(text: impl ToString) -> Self {
+        Self {
+            text: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn dim(mut self) -> Self {
+        self.dim = true;
+        self
+    }
+
+    pub fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    pub fn italic(mut self) -> Self {
+        self.italic = true;
+        self
+    }
+
+    fn styled(&self) -> String {
+        let mut styled = style(&self.text);
+        if let Some(color) = self.color {
+            styled = styled.fg(color);
+        }
+        if self.dim {
+            styled = styled.dim();
+        }
+        if self.bold {
+            styled = styled.bold();
+        }
+        if self.italic {
+            styled = styled.italic();
+        }
+        styled.to_string()
+    }
+}
+
+/// Where the output of a session goes, and how it looks
+pub trait Renderer {
+    /// Text written by the model or a tool, as markdown
+    fn markdown(&mut self, text: &str);
+
+    /// The text of an assistant reply, which the quiet renderer keeps until the reply ends
+    fn assistant_text(&mut self, text: &str) {
+        self.markdown(text);
+    }
+
+    /// A line of status output, such as a tool call or a notice. No spans is an empty line.
+    fn line(&mut self, spans: &[Span]);
+
+    /// The agent finished replying
+    fn end_reply(&mut self) {}
+
+    /// Whether spinners and progress bars are shown
+    fn shows_progress(&self) -> bool {
+        false
+    }
+
+    /// Whether images can be shown with terminal escape sequences
+    fn shows_inline_images(&self) -> bool {
+        false
+    }
+
+    /// Output written as it is, such as an inline image sequence
+    fn raw(&mut self, text: &str);
+}
+
+/// Colors, markdown highlighted with bat, spinners and inline images
+pub struct RichRenderer;
+
+impl Renderer for RichRenderer {
+    fn markdown(&mut self, text: &str) {
+        bat::PrettyPrinter::new()
+            .input(bat::Input::from_bytes(text.as_bytes()))
+            .theme(get_theme().as_str())
+            .colored_output(env_no_color())
+            .language("Markdown")
+            .wrapping_mode(WrappingMode::NoWrapping(true))
+            .print()
+            .unwrap();
+    }
+
+    fn line(&mut self, spans: &[Span]) {
+        let line: String = spans.iter().map(Span::styled).collect();
+        println!("{}", line);
+    }
+
+    fn shows_progress(&self) -> bool {
+        true
+    }
+
+    fn shows_inline_images(&self) -> bool {
+        true
+    }
+
+    fn raw(&mut self, text: &str) {
+        print!("{}", text);
+    }
+}
+
+/// Text without escape sequences, with markdown written as it is
+///
+/// Write errors, such as a closed pipe, are ignored like they are for spinners.
+pub struct PlainRenderer<W> {
+    out: W,
+}
+
+impl<W: Write> PlainRenderer<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+}
+
+impl<W: Write> Renderer for PlainRenderer<W> {
+    fn markdown(&mut self, text: &str) {
+        let text = console::strip_ansi_codes(text);
+        let _ = writeln!(self.out, "{}", text.trim_end_matches('\n'));
+    }
+
+    fn line(&mut self, spans: &[Span]) {
+        let line: String = spans.iter().map(|span| span.text.as_str()).collect();
+        let _ = writeln!(self.out, "{}", console::strip_ansi_codes(line.trim_end()));
+    }
+
+    fn raw(&mut self, text: &str) {
+        let _ = write!(self.out, "{}", console::strip_ansi_codes(text));
+    }
+}
+
+/// Only the last assistant text of each reply, for scripts
+pub struct QuietRenderer<W> {
+    out: W,
+    last_text: Option<String>,
+}
+
+impl<W: Write> QuietRenderer<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            last_text: None,
+        }
+    }
+}
+
+impl<W: Write> Renderer for QuietRenderer<W> {
+    fn markdown(&mut self, _text: &str) {}
+
+    fn assistant_text(&mut self, text: &str) {
+        self.last_text = Some(text.to_string());
+    }
+
+    fn line(&mut self, _spans: &[Span]) {}
+
+    fn end_reply(&mut self) {
+        if let Some(text) = self.last_text.take() {
+            let _ = writeln!(self.out, "{}", text.trim_end_matches('\n'));
+            let _ = self.out.flush();
+        }
+    }
+
+    fn raw(&mut self, _text: &str) {}
+}
+
+static RENDERER: Lazy<Mutex<Box<dyn Renderer + Send>>> =
+    Lazy::new(|| Mutex::new(OutputMode::detect().renderer()));
+
+/// Show the output of sessions in `mode` from now on
+pub fn set_output_mode(mode: OutputMode) {
+    set_renderer(mode.renderer());
+}
+
+pub fn set_renderer(renderer: Box<dyn Renderer + Send>) {
+    *RENDERER.lock().unwrap_or_else(|e| e.into_inner()) = renderer;
+}
+
+/// Run `f` with the renderer of the session output
+pub fn with_renderer<T>(f: impl FnOnce(&mut dyn Renderer) -> T) -> T {
+    let mut renderer = RENDERER.lock().unwrap_or_else(|e| e.into_inner());
+    f(renderer.as_mut())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// A writer whose output can be read while a renderer owns it
+    #[derive(Clone, Default)]
+    pub(crate) struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl SharedBuffer {
+        pub(crate) fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_plain_renderer_has_no_escape_sequences() {
+        let buffer = SharedBuffer::default();
+        let mut renderer = PlainRenderer::new(buffer.clone());
+
+        renderer.line(&[
+            Span::new("added").color(Color::Green),
+            Span::new(" extension ").dim(),
+            Span::new("`developer`  ").color(Color::Cyan).bold(),
+        ]);
+        renderer.line(&[]);
+        renderer.markdown("# Title\n\n```rust\nfn main() {}\n```\n");
+        renderer.raw("\x1b[31mred\x1b[0m\n");
+
+        assert_eq!(
+            buffer.contents(),
+            "added extension `developer`\n\n# Title\n\n```rust\nfn main() {}\n```\nred\n"
+        );
+        assert!(!renderer.shows_progress());
+    }
+
+    #[test]
+    fn test_quiet_renderer_keeps_final_assistant_text() {
+        let buffer = SharedBuffer::default();
+        let mut renderer = QuietRenderer::new(buffer.clone());
+
+        renderer.line(&[Span::new("starting session")]);
+        renderer.assistant_text("Let me look at the files.");
+        renderer.markdown("src/main.rs");
+        renderer.assistant_text("There is one file.\n");
+        assert_eq!(buffer.contents(), "");
+
+        renderer.end_reply();
+        renderer.end_reply();
+        assert_eq!(buffer.contents(), "There is one file.\n");
+    }
+}
@@ -269,6 +269,29 @@ pub unsafe extern "C" fn goose_agent_send_message(
                 Ok(AgentEvent::ModelChange { .. }) => {
                     // Model change events are informational, just continue
                 }
+                Ok(AgentEvent::TurnStats(_)) => {
+                    // Turn stats are recorded in the session metadata, nothing to add to the response
+                }
+                Ok(AgentEvent::Thinking(_))
+                | Ok(AgentEvent::ToolCall { .. })
+                | Ok(AgentEvent::ToolCallProgress { .. })
+                | Ok(AgentEvent::Usage(_))
+                | Ok(AgentEvent::CumulativeUsage { .. })
+                | Ok(AgentEvent::ToolProgress { .. })
+                | Ok(AgentEvent::Compacted { .. })
+                | Ok(AgentEvent::SoftStop)
+                | Ok(AgentEvent::OversizedMessage(_)) => {
+                    // Progress events are repeated in the final messages
+                }
+                Ok(AgentEvent::ApprovalTimedOut { .. }) => {
+                    // The tool response already carries the applied decision
+                }
+                Ok(AgentEvent::UserFacingToolOutput { .. }) => {
+                    // User-facing output is for interactive frontends only
+                }
+                Ok(AgentEvent::Interjection(_)) => {
+                    // Interjections echo user input, nothing to add to the response
+                }
 
                 Err(e) => {
                     full_response.push_str(&format!("\nError in message stream: {}", e));
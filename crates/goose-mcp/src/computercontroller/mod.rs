@@ -8,6 +8,8 @@ use std::{
 };
 use tokio::{process::Command, sync::mpsc};
 
+use goose::sandbox::Sandbox;
+
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 
@@ -39,6 +41,8 @@ pub struct ComputerControllerRouter {
     http_client: Client,
     instructions: String,
     system_automation: Arc<Box<dyn SystemAutomation + Send + Sync>>,
+    sandbox: Arc<Sandbox>,
+    cache_sandbox: Arc<Sandbox>,
 }
 
 impl Default for ComputerControllerRouter {
@@ -442,6 +446,10 @@ impl ComputerControllerRouter {
         let system_automation: Arc<Box<dyn SystemAutomation + Send + Sync>> =
             Arc::new(create_system_automation());
 
+        // The file tools work in the workspace, and the cache tool only in the cache
+        let sandbox = Sandbox::from_env().expect("Failed to build the workspace sandbox");
+        let cache_sandbox = Sandbox::new(&cache_dir).unwrap_or_default();
+
         let os_specific_instructions = match std::env::consts::OS {
             "windows" => indoc! {r#"
             Here are some extra tools:
@@ -557,9 +565,32 @@ impl ComputerControllerRouter {
             http_client: Client::builder().user_agent("Goose/1.0").build().unwrap(),
             instructions: instructions.clone(),
             system_automation,
+            sandbox: Arc::new(sandbox),
+            cache_sandbox: Arc::new(cache_sandbox),
         }
     }
 
+    /// Route the paths of the file tools through `sandbox` instead of the one from the environment
+    pub fn with_sandbox(mut self, sandbox: Sandbox) -> Self {
+        self.sandbox = Arc::new(sandbox);
+        self
+    }
+
+    // Helper function to check a path against a sandbox, returning the path to use
+    fn sandboxed(sandbox: &Sandbox, path: &str, read: bool) -> Result<PathBuf, ToolError> {
+        let resolved = if read {
+            sandbox.resolve_read(path)
+        } else {
+            sandbox.resolve(path)
+        };
+        resolved.map_err(|violation| {
+            ToolError::ExecutionError(format!(
+                "Access denied by the workspace sandbox: {}",
+                violation
+            ))
+        })
+    }
+
     // Helper function to generate a cache file path
     fn get_cache_path(&self, prefix: &str, extension: &str) -> PathBuf {
         let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
@@ -838,10 +869,15 @@ impl ComputerControllerRouter {
             .get("operation")
             .and_then(|v| v.as_str())
             .ok_or_else(|| ToolError::InvalidParameters("Missing 'operation' parameter".into()))?;
+        let path = Self::sandboxed(
+            &self.sandbox,
+            path,
+            !matches!(operation, "update_cell" | "save"),
+        )?;
 
         match operation {
             "list_worksheets" => {
-                let xlsx = xlsx_tool::XlsxTool::new(path)
+                let xlsx = xlsx_tool::XlsxTool::new(&path)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
                 let worksheets = xlsx
                     .list_worksheets()
@@ -849,7 +885,7 @@ impl ComputerControllerRouter {
                 Ok(vec![Content::text(format!("{:#?}", worksheets))])
             }
             "get_columns" => {
-                let xlsx = xlsx_tool::XlsxTool::new(path)
+                let xlsx = xlsx_tool::XlsxTool::new(&path)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
                 let worksheet = if let Some(name) = params.get("worksheet").and_then(|v| v.as_str())
                 {
@@ -872,7 +908,7 @@ impl ComputerControllerRouter {
                         ToolError::InvalidParameters("Missing 'range' parameter".into())
                     })?;
 
-                let xlsx = xlsx_tool::XlsxTool::new(path)
+                let xlsx = xlsx_tool::XlsxTool::new(&path)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
                 let worksheet = if let Some(name) = params.get("worksheet").and_then(|v| v.as_str())
                 {
@@ -900,7 +936,7 @@ impl ComputerControllerRouter {
                     .and_then(|v| v.as_bool())
                     .unwrap_or(false);
 
-                let xlsx = xlsx_tool::XlsxTool::new(path)
+                let xlsx = xlsx_tool::XlsxTool::new(&path)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
                 let worksheet = if let Some(name) = params.get("worksheet").and_then(|v| v.as_str())
                 {
@@ -939,11 +975,11 @@ impl ComputerControllerRouter {
                     .and_then(|v| v.as_str())
                     .unwrap_or("Sheet1");
 
-                let mut xlsx = xlsx_tool::XlsxTool::new(path)
+                let mut xlsx = xlsx_tool::XlsxTool::new(&path)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
                 xlsx.update_cell(worksheet_name, row as u32, col as u32, value)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
-                xlsx.save(path)
+                xlsx.save(&path)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
                 Ok(vec![Content::text(format!(
                     "Updated cell ({}, {}) to '{}' in worksheet '{}'",
@@ -951,9 +987,9 @@ impl ComputerControllerRouter {
                 ))])
             }
             "save" => {
-                let xlsx = xlsx_tool::XlsxTool::new(path)
+                let xlsx = xlsx_tool::XlsxTool::new(&path)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
-                xlsx.save(path)
+                xlsx.save(&path)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
                 Ok(vec![Content::text("File saved successfully.")])
             }
@@ -966,7 +1002,7 @@ impl ComputerControllerRouter {
                     ToolError::InvalidParameters("Missing 'col' parameter".into())
                 })?;
 
-                let xlsx = xlsx_tool::XlsxTool::new(path)
+                let xlsx = xlsx_tool::XlsxTool::new(&path)
                     .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
                 let worksheet = if let Some(name) = params.get("worksheet").and_then(|v| v.as_str())
                 {
@@ -1000,8 +1036,10 @@ impl ComputerControllerRouter {
             .and_then(|v| v.as_str())
             .ok_or_else(|| ToolError::InvalidParameters("Missing 'operation' parameter".into()))?;
 
+        let path = Self::sandboxed(&self.sandbox, path, operation != "update_doc")?;
+
         crate::computercontroller::docx_tool::docx_tool(
-            path,
+            &path.to_string_lossy(),
             operation,
             params.get("content").and_then(|v| v.as_str()),
             params.get("params"),
@@ -1020,7 +1058,14 @@ impl ComputerControllerRouter {
             .and_then(|v| v.as_str())
             .ok_or_else(|| ToolError::InvalidParameters("Missing 'operation' parameter".into()))?;
 
-        crate::computercontroller::pdf_tool::pdf_tool(path, operation, &self.cache_dir).await
+        let path = Self::sandboxed(&self.sandbox, path, true)?;
+
+        crate::computercontroller::pdf_tool::pdf_tool(
+            &path.to_string_lossy(),
+            operation,
+            &self.cache_dir,
+        )
+        .await
     }
 
     async fn cache(&self, params: Value) -> Result<Vec<Content>, ToolError> {
@@ -1051,7 +1096,8 @@ impl ComputerControllerRouter {
                     ToolError::InvalidParameters("Missing 'path' parameter for view".into())
                 })?;
 
-                let content = fs::read_to_string(path).map_err(|e| {
+                let resolved = Self::sandboxed(&self.cache_sandbox, path, true)?;
+                let content = fs::read_to_string(resolved).map_err(|e| {
                     ToolError::ExecutionError(format!("Failed to read file: {}", e))
                 })?;
 
@@ -1065,7 +1111,8 @@ impl ComputerControllerRouter {
                     ToolError::InvalidParameters("Missing 'path' parameter for delete".into())
                 })?;
 
-                fs::remove_file(path).map_err(|e| {
+                let resolved = Self::sandboxed(&self.cache_sandbox, path, false)?;
+                fs::remove_file(resolved).map_err(|e| {
                     ToolError::ExecutionError(format!("Failed to delete file: {}", e))
                 })?;
 
@@ -13,6 +13,7 @@ use std::{
     io::Cursor,
     path::{Path, PathBuf},
     pin::Pin,
+    time::{Duration, Instant},
 };
 use tokio::{
     io::{AsyncBufReadExt, BufReader},
@@ -34,12 +35,14 @@ use mcp_core::{
     tool::ToolAnnotations,
 };
 use mcp_server::router::CapabilitiesBuilder;
-use mcp_server::Router;
+use mcp_server::{ProgressReporter, Router};
 
 use mcp_core::role::Role;
 
 use self::editor_models::{create_editor_model, EditorModel};
 use self::shell::{expand_path, get_shell_config, is_absolute_path, normalize_line_endings};
+use goose::sandbox::Sandbox;
+use goose::utils::safe_truncate;
 use indoc::indoc;
 use std::process::Stdio;
 use std::sync::{Arc, Mutex};
@@ -93,12 +96,33 @@ pub fn load_prompt_files() -> HashMap<String, Prompt> {
     prompts
 }
 
+/// How often a running shell command reports its progress
+const SHELL_PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Keep the last non-empty line of a command's output for its progress reports
+fn remember_line(last_line: &Mutex<String>, line: &str) {
+    let line = line.trim();
+    if !line.is_empty() {
+        *last_line.lock().unwrap() = line.to_string();
+    }
+}
+
+fn shell_progress_message(elapsed: Duration, last_line: &str) -> String {
+    let running = format!("Running for {}s", elapsed.as_secs());
+    if last_line.is_empty() {
+        running
+    } else {
+        format!("{}: {}", running, safe_truncate(last_line, 80))
+    }
+}
+
 pub struct DeveloperRouter {
     tools: Vec<Tool>,
     prompts: Arc<HashMap<String, Prompt>>,
     instructions: String,
     file_history: Arc<Mutex<HashMap<PathBuf, Vec<String>>>>,
     ignore_patterns: Arc<Gitignore>,
+    sandbox: Arc<Sandbox>,
     editor_model: Option<EditorModel>,
 }
 
@@ -479,6 +503,7 @@ impl DeveloperRouter {
         }
 
         let ignore_patterns = builder.build().expect("Failed to build ignore patterns");
+        let sandbox = Sandbox::from_env().expect("Failed to build the workspace sandbox");
 
         Self {
             tools: vec![
@@ -492,15 +517,37 @@ impl DeveloperRouter {
             instructions,
             file_history: Arc::new(Mutex::new(HashMap::new())),
             ignore_patterns: Arc::new(ignore_patterns),
+            sandbox: Arc::new(sandbox),
             editor_model,
         }
     }
 
+    /// Route the paths of the tools through `sandbox` instead of the one from the environment
+    pub fn with_sandbox(mut self, sandbox: Sandbox) -> Self {
+        self.sandbox = Arc::new(sandbox);
+        self
+    }
+
     // Helper method to check if a path should be ignored
     fn is_ignored(&self, path: &Path) -> bool {
         self.ignore_patterns.matched(path, false).is_ignore()
     }
 
+    // Helper method to check a path against the workspace sandbox, returning the path to use
+    fn sandboxed(&self, path: &Path, read: bool) -> Result<PathBuf, ToolError> {
+        let resolved = if read {
+            self.sandbox.resolve_read(path)
+        } else {
+            self.sandbox.resolve(path)
+        };
+        resolved.map_err(|violation| {
+            ToolError::ExecutionError(format!(
+                "Access denied by the workspace sandbox: {}",
+                violation
+            ))
+        })
+    }
+
     // Helper method to resolve a path relative to cwd with platform-specific handling
     fn resolve_path(&self, path_str: &str) -> Result<PathBuf, ToolError> {
         let cwd = std::env::current_dir().expect("should have a current working dir");
@@ -524,6 +571,7 @@ impl DeveloperRouter {
         &self,
         params: Value,
         notifier: mpsc::Sender<JsonRpcMessage>,
+        progress: ProgressReporter,
     ) -> Result<Vec<Content>, ToolError> {
         let command =
             params
@@ -552,6 +600,10 @@ impl DeveloperRouter {
                     arg
                 )));
             }
+            // Existing paths canonicalize, so relative ones are checked against the cwd
+            if let Ok(path) = path.canonicalize() {
+                self.sandboxed(&path, true)?;
+            }
         }
 
         // Get platform-specific shell configuration
@@ -574,6 +626,8 @@ impl DeveloperRouter {
         let mut stdout_reader = BufReader::new(stdout);
         let mut stderr_reader = BufReader::new(stderr);
 
+        let last_line = Arc::new(Mutex::new(String::new()));
+        let output_last_line = last_line.clone();
         let output_task = tokio::spawn(async move {
             let mut combined_output = String::new();
 
@@ -602,6 +656,7 @@ impl DeveloperRouter {
                                     }
                                 })),
                             })).ok();
+                            remember_line(&output_last_line, &line);
 
                             combined_output.push_str(&line);
                             stdout_buf.clear();
@@ -625,6 +680,7 @@ impl DeveloperRouter {
                                     }
                                 })),
                             })).ok();
+                            remember_line(&output_last_line, &line);
 
                             combined_output.push_str(&line);
                             stderr_buf.clear();
@@ -641,11 +697,24 @@ impl DeveloperRouter {
             Ok::<_, std::io::Error>(combined_output)
         });
 
-        // Wait for the command to complete and get output
-        child
-            .wait()
-            .await
-            .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+        // Wait for the command to complete, reporting how long it runs so it does not look stuck
+        let started = Instant::now();
+        let mut ticker = tokio::time::interval_at(
+            tokio::time::Instant::now() + SHELL_PROGRESS_INTERVAL,
+            SHELL_PROGRESS_INTERVAL,
+        );
+        loop {
+            tokio::select! {
+                status = child.wait() => {
+                    status.map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+                    break;
+                }
+                _ = ticker.tick() => {
+                    let line = last_line.lock().unwrap().clone();
+                    progress.report(shell_progress_message(started.elapsed(), &line), None);
+                }
+            }
+        }
 
         let output_str = match output_task.await {
             Ok(result) => result.map_err(|e| ToolError::ExecutionError(e.to_string()))?,
@@ -686,6 +755,7 @@ impl DeveloperRouter {
             .ok_or_else(|| ToolError::InvalidParameters("Missing 'path' parameter".into()))?;
 
         let path = self.resolve_path(path_str)?;
+        let path = self.sandboxed(&path, command == "view")?;
 
         // Check if file is ignored before proceeding with any text editor operation
         if self.is_ignored(&path) {
@@ -1265,6 +1335,7 @@ impl DeveloperRouter {
                 p
             }
         };
+        let path = self.sandboxed(&path, true)?;
 
         // Check if file is ignored before proceeding
         if self.is_ignored(&path) {
@@ -1432,12 +1503,23 @@ impl Router for DeveloperRouter {
         tool_name: &str,
         arguments: Value,
         notifier: mpsc::Sender<JsonRpcMessage>,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Content>, ToolError>> + Send + 'static>> {
+        let progress = ProgressReporter::new(notifier.clone(), None);
+        self.call_tool_with_progress(tool_name, arguments, notifier, progress)
+    }
+
+    fn call_tool_with_progress(
+        &self,
+        tool_name: &str,
+        arguments: Value,
+        notifier: mpsc::Sender<JsonRpcMessage>,
+        progress: ProgressReporter,
     ) -> Pin<Box<dyn Future<Output = Result<Vec<Content>, ToolError>> + Send + 'static>> {
         let this = self.clone();
         let tool_name = tool_name.to_string();
         Box::pin(async move {
             match tool_name.as_str() {
-                "shell" => this.bash(arguments, notifier).await,
+                "shell" => this.bash(arguments, notifier, progress).await,
                 "text_editor" => this.text_editor(arguments).await,
                 "list_windows" => this.list_windows(arguments).await,
                 "screen_capture" => this.screen_capture(arguments).await,
@@ -1499,6 +1581,7 @@ impl Clone for DeveloperRouter {
             instructions: self.instructions.clone(),
             file_history: Arc::clone(&self.file_history),
             ignore_patterns: Arc::clone(&self.ignore_patterns),
+            sandbox: Arc::clone(&self.sandbox),
             editor_model: create_editor_model(), // Recreate the editor model since it's not Clone
         }
     }
@@ -1600,6 +1683,22 @@ mod tests {
         temp_dir.close().unwrap();
     }
 
+    #[test]
+    fn test_shell_progress_message() {
+        let last_line = Mutex::new(String::new());
+        assert_eq!(
+            shell_progress_message(Duration::from_secs(5), &last_line.lock().unwrap()),
+            "Running for 5s"
+        );
+
+        remember_line(&last_line, "test result: ok. 12 passed\n");
+        remember_line(&last_line, "\n");
+        assert_eq!(
+            shell_progress_message(Duration::from_millis(10_900), &last_line.lock().unwrap()),
+            "Running for 10s: test result: ok. 12 passed"
+        );
+    }
+
     #[tokio::test]
     #[serial]
     #[cfg(windows)]
@@ -1929,6 +2028,7 @@ mod tests {
             instructions: String::new(),
             file_history: Arc::new(Mutex::new(HashMap::new())),
             ignore_patterns: Arc::new(ignore_patterns),
+            sandbox: Arc::new(Sandbox::unrestricted()),
             editor_model: None,
         };
 
@@ -1980,6 +2080,7 @@ mod tests {
             instructions: String::new(),
             file_history: Arc::new(Mutex::new(HashMap::new())),
             ignore_patterns: Arc::new(ignore_patterns),
+            sandbox: Arc::new(Sandbox::unrestricted()),
             editor_model: None,
         };
 
@@ -2040,6 +2141,7 @@ mod tests {
             instructions: String::new(),
             file_history: Arc::new(Mutex::new(HashMap::new())),
             ignore_patterns: Arc::new(ignore_patterns),
+            sandbox: Arc::new(Sandbox::unrestricted()),
             editor_model: None,
         };
 
@@ -2080,6 +2182,77 @@ mod tests {
         temp_dir.close().unwrap();
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn test_tools_respect_the_sandbox() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = temp_dir.path().join("workspace");
+        std::fs::create_dir_all(&workspace).unwrap();
+        std::env::set_current_dir(&workspace).unwrap();
+        std::fs::write(temp_dir.path().join("outside.txt"), "outside").unwrap();
+
+        let router = DeveloperRouter::new().with_sandbox(Sandbox::new(&workspace).unwrap());
+
+        let result = router
+            .call_tool(
+                "text_editor",
+                json!({
+                    "command": "write",
+                    "path": workspace.join("..").join("escaped.txt").to_str().unwrap(),
+                    "file_text": "test content"
+                }),
+                dummy_sender(),
+            )
+            .await;
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("outside the workspace"), "{}", err);
+        assert!(!temp_dir.path().join("escaped.txt").exists());
+
+        let result = router
+            .call_tool(
+                "shell",
+                json!({"command": "cat ../outside.txt"}),
+                dummy_sender(),
+            )
+            .await;
+        assert!(
+            result.is_err(),
+            "Should not be able to cat outside the sandbox"
+        );
+
+        let result = router
+            .call_tool(
+                "text_editor",
+                json!({
+                    "command": "write",
+                    "path": workspace.join("inside.txt").to_str().unwrap(),
+                    "file_text": "test content"
+                }),
+                dummy_sender(),
+            )
+            .await;
+        assert!(result.is_ok(), "Should be able to write inside the sandbox");
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(temp_dir.path(), workspace.join("parent")).unwrap();
+            let result = router
+                .call_tool(
+                    "text_editor",
+                    json!({
+                        "command": "view",
+                        "path": workspace.join("parent").join("outside.txt").to_str().unwrap()
+                    }),
+                    dummy_sender(),
+                )
+                .await;
+            let err = result.unwrap_err().to_string();
+            assert!(err.contains("symlink"), "{}", err);
+        }
+
+        temp_dir.close().unwrap();
+    }
+
     #[tokio::test]
     #[serial]
     async fn test_gitignore_fallback_when_no_gooseignore() {
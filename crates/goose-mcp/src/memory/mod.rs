@@ -226,10 +226,15 @@ impl MemoryRouter {
              - Acknowledge the user about what is stored and where, for transparency and ease of future retrieval.
             "#};
 
-        // Check for .goose/memory in current directory
-        let local_memory_dir = std::env::var("GOOSE_WORKING_DIR")
+        // Check for .goose/memory in current directory, or in the root of its workspace when
+        // one has a .goose/config.yaml, so the memories of a repository are shared
+        let working_dir = std::env::var("GOOSE_WORKING_DIR")
             .map(PathBuf::from)
-            .unwrap_or_else(|_| std::env::current_dir().unwrap())
+            .unwrap_or_else(|_| std::env::current_dir().unwrap());
+        let local_memory_dir = working_dir
+            .ancestors()
+            .find(|dir| dir.join(".goose").join("config.yaml").is_file())
+            .unwrap_or(&working_dir)
             .join(".goose")
             .join("memory");
 
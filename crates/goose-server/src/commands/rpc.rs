@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use goose::agents::Agent;
+use goose::config::{Config, ExtensionConfigManager};
+use goose::model::ModelConfig;
+use goose::providers::create;
+use tokio::io::{stdin, stdout};
+
+pub async fn run(socket: Option<PathBuf>) -> Result<()> {
+    // Initialize logging
+    crate::logging::setup_logging(Some("goosed-rpc"))?;
+
+    let agent = Arc::new(configured_agent().await?);
+
+    match socket {
+        None => goose::rpc::serve(agent, stdin(), stdout()).await,
+        #[cfg(unix)]
+        Some(path) => {
+            let listener = tokio::net::UnixListener::bind(&path)
+                .with_context(|| format!("Failed to bind {}", path.display()))?;
+            tracing::info!("listening on {}", path.display());
+            loop {
+                let (stream, _) = listener.accept().await?;
+                let (reader, writer) = stream.into_split();
+                let agent = agent.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = goose::rpc::serve(agent, reader, writer).await {
+                        tracing::error!("RPC connection failed: {}", e);
+                    }
+                });
+            }
+        }
+        #[cfg(not(unix))]
+        Some(_) => anyhow::bail!("Unix sockets are not supported on this platform"),
+    }
+}
+
+/// An agent with the configured provider and enabled extensions, like a CLI session has
+async fn configured_agent() -> Result<Agent> {
+    let config = Config::global();
+    let provider_name: String = config
+        .get_param("GOOSE_PROVIDER")
+        .context("No provider configured. Run 'goose configure' first")?;
+    let model_name: String = config
+        .get_param("GOOSE_MODEL")
+        .context("No model configured. Run 'goose configure' first")?;
+
+    let agent = Agent::new();
+    agent
+        .update_provider(create(&provider_name, ModelConfig::new(model_name))?)
+        .await?;
+
+    for entry in ExtensionConfigManager::get_all()? {
+        if !entry.enabled {
+            continue;
+        }
+        if let Err(e) = agent.add_extension(entry.config.clone()).await {
+            tracing::warn!("Failed to start extension '{}': {}", entry.config.name(), e);
+        }
+    }
+    Ok(agent)
+}
@@ -64,8 +64,10 @@ pub fn setup_logging(name: Option<&str>) -> Result<()> {
         .with_ansi(false)
         .with_file(true);
 
-    // Create console logging layer for development - INFO and above only
+    // Create console logging layer for development - INFO and above only. It writes to stderr,
+    // since the stdio servers use stdout for their protocol
     let console_layer = fmt::layer()
+        .with_writer(std::io::stderr)
         .with_target(true)
         .with_level(true)
         .with_ansi(true)
@@ -6,6 +6,8 @@ mod openapi;
 mod routes;
 mod state;
 
+use std::path::PathBuf;
+
 use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
@@ -25,6 +27,12 @@ enum Commands {
         /// Name of the MCP server type
         name: String,
     },
+    /// Serve the agent over JSON-RPC, on stdio or a unix socket
+    Rpc {
+        /// Listen on this unix socket instead of stdio
+        #[arg(long)]
+        socket: Option<PathBuf>,
+    },
 }
 
 #[tokio::main]
@@ -38,6 +46,9 @@ async fn main() -> anyhow::Result<()> {
         Commands::Mcp { name } => {
             commands::mcp::run(name).await?;
         }
+        Commands::Rpc { socket } => {
+            commands::rpc::run(socket.clone()).await?;
+        }
     }
 
     Ok(())
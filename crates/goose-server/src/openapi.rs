@@ -1,17 +1,21 @@
 use goose::agents::extension::Envs;
 use goose::agents::extension::ToolInfo;
-use goose::agents::ExtensionConfig;
+use goose::agents::{ExtensionConfig, GuardrailRewrite};
 use goose::config::permission::PermissionLevel;
 use goose::config::ExtensionEntry;
 use goose::message::{
-    ContextLengthExceeded, FrontendToolRequest, Message, MessageContent, RedactedThinkingContent,
-    SummarizationRequested, ThinkingContent, ToolConfirmationRequest, ToolRequest, ToolResponse,
+    Citation, CitationSource, CitationsContent, ContextLengthExceeded, FrontendToolRequest,
+    Message, MessageContent, RedactedThinkingContent, SummarizationRequested, ThinkingContent,
+    ToolConfirmationRequest, ToolProvenance, ToolRequest, ToolResponse,
 };
 use goose::permission::permission_confirmation::PrincipalType;
+use goose::permission::{CommandRisk, RiskLevel};
 use goose::providers::base::{ConfigKey, ModelInfo, ProviderMetadata};
 use goose::session::info::SessionInfo;
-use goose::session::SessionMetadata;
-use mcp_core::content::{Annotations, Content, EmbeddedResource, ImageContent, TextContent};
+use goose::session::{SessionMetadata, ToolCallTiming, TurnStats};
+use mcp_core::content::{
+    Annotations, Content, EmbeddedResource, ImageContent, ResultContent, TextContent,
+};
 use mcp_core::handler::ToolResultSchema;
 use mcp_core::resource::ResourceContents;
 use mcp_core::role::Role;
@@ -73,16 +77,23 @@ use utoipa::OpenApi;
         ImageContent,
         Annotations,
         TextContent,
+        ResultContent,
         ToolResponse,
+        ToolProvenance,
         ToolRequest,
         ToolResultSchema,
         ToolConfirmationRequest,
+        CommandRisk,
+        RiskLevel,
         ThinkingContent,
         RedactedThinkingContent,
         FrontendToolRequest,
         ResourceContents,
         ContextLengthExceeded,
         SummarizationRequested,
+        CitationsContent,
+        Citation,
+        CitationSource,
         Role,
         ProviderMetadata,
         ExtensionEntry,
@@ -97,6 +108,9 @@ use utoipa::OpenApi;
         ModelInfo,
         SessionInfo,
         SessionMetadata,
+        TurnStats,
+        ToolCallTiming,
+        GuardrailRewrite,
         super::routes::schedule::CreateScheduleRequest,
         super::routes::schedule::UpdateScheduleRequest,
         super::routes::schedule::KillJobResponse,
@@ -175,6 +175,7 @@ async fn add_extension(
             description: None,
             timeout,
             bundled: None,
+            network: None,
         },
         ExtensionConfigRequest::Stdio {
             name,
@@ -205,6 +206,7 @@ async fn add_extension(
                 env_keys,
                 timeout,
                 bundled: None,
+                network: None,
             }
         }
         ExtensionConfigRequest::Builtin {
@@ -216,6 +218,7 @@ async fn add_extension(
             display_name,
             timeout,
             bundled: None,
+            network: None,
         },
         ExtensionConfigRequest::Frontend {
             name,
@@ -226,6 +229,7 @@ async fn add_extension(
             tools,
             instructions,
             bundled: None,
+            network: None,
         },
     };
 
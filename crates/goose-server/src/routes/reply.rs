@@ -97,6 +97,36 @@ enum MessageEvent {
         request_id: String,
         message: JsonRpcMessage,
     },
+    TurnStats {
+        stats: session::TurnStats,
+    },
+    ToolCall {
+        id: String,
+        name: String,
+        arguments: Value,
+    },
+    ApprovalTimedOut {
+        request_id: String,
+        tool_name: String,
+        approved: bool,
+    },
+    UserFacingToolOutput {
+        tool_name: String,
+        content: Vec<Content>,
+    },
+    ToolProgress {
+        id: String,
+        name: String,
+        message: Option<String>,
+        fraction: Option<f64>,
+    },
+    SoftStop,
+    OversizedMessage {
+        tokens: usize,
+        limit: usize,
+        replacement: Option<Message>,
+        summary: String,
+    },
 }
 
 async fn stream_event(
@@ -263,6 +293,118 @@ async fn handler(
                                 ).await;
                             }
                         }
+                        Ok(Some(Ok(AgentEvent::TurnStats(stats)))) => {
+                            if let Err(e) = stream_event(MessageEvent::TurnStats { stats }, &tx).await {
+                                tracing::error!("Error sending turn stats through channel: {}", e);
+                                let _ = stream_event(
+                                    MessageEvent::Error {
+                                        error: e.to_string(),
+                                    },
+                                    &tx,
+                                ).await;
+                            }
+                        }
+                        Ok(Some(Ok(AgentEvent::ToolCall { id, name, arguments }))) => {
+                            if let Err(e) = stream_event(MessageEvent::ToolCall { id, name, arguments }, &tx).await {
+                                tracing::error!("Error sending tool call through channel: {}", e);
+                                let _ = stream_event(
+                                    MessageEvent::Error {
+                                        error: e.to_string(),
+                                    },
+                                    &tx,
+                                ).await;
+                            }
+                        }
+                        Ok(Some(Ok(AgentEvent::UserFacingToolOutput { tool_name, content }))) => {
+                            if let Err(e) = stream_event(MessageEvent::UserFacingToolOutput { tool_name, content }, &tx).await {
+                                tracing::error!("Error sending user-facing tool output through channel: {}", e);
+                                let _ = stream_event(
+                                    MessageEvent::Error {
+                                        error: e.to_string(),
+                                    },
+                                    &tx,
+                                ).await;
+                            }
+                        }
+                        Ok(Some(Ok(AgentEvent::ToolProgress { id, name, message, fraction }))) => {
+                            if let Err(e) = stream_event(MessageEvent::ToolProgress { id, name, message, fraction }, &tx).await {
+                                tracing::error!("Error sending tool progress through channel: {}", e);
+                                let _ = stream_event(
+                                    MessageEvent::Error {
+                                        error: e.to_string(),
+                                    },
+                                    &tx,
+                                ).await;
+                            }
+                        }
+                        Ok(Some(Ok(AgentEvent::OversizedMessage(oversized)))) => {
+                            // The history keeps the message as the agent sent it, or drops it when it was not sent
+                            if all_messages.last().is_some_and(|message| message.role == Role::User) {
+                                all_messages.pop();
+                            }
+                            all_messages.extend(oversized.replacement.clone());
+                            let event = MessageEvent::OversizedMessage {
+                                tokens: oversized.tokens,
+                                limit: oversized.limit,
+                                summary: oversized.summary(),
+                                replacement: oversized.replacement,
+                            };
+                            if let Err(e) = stream_event(event, &tx).await {
+                                tracing::error!("Error sending oversized message through channel: {}", e);
+                                let _ = stream_event(
+                                    MessageEvent::Error {
+                                        error: e.to_string(),
+                                    },
+                                    &tx,
+                                ).await;
+                            }
+                        }
+                        Ok(Some(Ok(AgentEvent::SoftStop))) => {
+                            if let Err(e) = stream_event(MessageEvent::SoftStop, &tx).await {
+                                tracing::error!("Error sending soft stop through channel: {}", e);
+                                let _ = stream_event(
+                                    MessageEvent::Error {
+                                        error: e.to_string(),
+                                    },
+                                    &tx,
+                                ).await;
+                            }
+                        }
+                        Ok(Some(Ok(AgentEvent::ApprovalTimedOut { request_id, tool_name, approved }))) => {
+                            if let Err(e) = stream_event(MessageEvent::ApprovalTimedOut { request_id, tool_name, approved }, &tx).await {
+                                tracing::error!("Error sending approval timeout through channel: {}", e);
+                                let _ = stream_event(
+                                    MessageEvent::Error {
+                                        error: e.to_string(),
+                                    },
+                                    &tx,
+                                ).await;
+                            }
+                        }
+                        Ok(Some(Ok(AgentEvent::Thinking(_))))
+                        | Ok(Some(Ok(AgentEvent::Usage(_))))
+                        | Ok(Some(Ok(AgentEvent::CumulativeUsage { .. }))) => {
+                            // Thinking is part of the message that follows, and usage is kept in the session
+                        }
+                        Ok(Some(Ok(AgentEvent::Compacted { trigger, steps, .. }))) => {
+                            tracing::info!("Compacted the conversation ({:?}): {:?}", trigger, steps);
+                        }
+                        Ok(Some(Ok(AgentEvent::ToolCallProgress { .. }))) => {
+                            // The client is told about the call once its arguments are complete
+                        }
+                        Ok(Some(Ok(AgentEvent::Interjection(message)))) => {
+                            all_messages.push(message.clone());
+                            if let Err(e) = stream_event(MessageEvent::Message { message }, &tx).await {
+                                tracing::error!("Error sending interjection through channel: {}", e);
+                                let _ = stream_event(
+                                    MessageEvent::Error {
+                                        error: e.to_string(),
+                                    },
+                                    &tx,
+                                ).await;
+                                break;
+                            }
+                        }
                         Ok(Some(Ok(AgentEvent::McpNotification((request_id, n))))) => {
                             if let Err(e) = stream_event(MessageEvent::Notification{
                                 request_id: request_id.clone(),
@@ -393,6 +535,36 @@ async fn ask_handler(
                 // Handle notifications if needed
                 tracing::info!("Received notification: {:?}", n);
             }
+            Ok(AgentEvent::Interjection(message)) => {
+                // The non-streaming endpoint only collects the assistant response
+                tracing::info!("Received interjection: {:?}", message);
+            }
+            Ok(AgentEvent::TurnStats(stats)) => {
+                tracing::info!("Turn stats: {:?}", stats);
+            }
+            Ok(AgentEvent::Thinking(_))
+            | Ok(AgentEvent::ToolCall { .. })
+            | Ok(AgentEvent::ToolCallProgress { .. })
+            | Ok(AgentEvent::Usage(_))
+            | Ok(AgentEvent::CumulativeUsage { .. })
+            | Ok(AgentEvent::UserFacingToolOutput { .. })
+            | Ok(AgentEvent::ToolProgress { .. })
+            | Ok(AgentEvent::Compacted { .. })
+            | Ok(AgentEvent::SoftStop)
+            | Ok(AgentEvent::OversizedMessage(_)) => {
+                // The non-streaming endpoint only collects the assistant response
+            }
+            Ok(AgentEvent::ApprovalTimedOut {
+                tool_name,
+                approved,
+                ..
+            }) => {
+                tracing::warn!(
+                    "Approval for {} timed out, approved: {}",
+                    tool_name,
+                    approved
+                );
+            }
 
             Err(e) => {
                 tracing::error!("Error processing as_ai message: {}", e);
@@ -0,0 +1,100 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use goose::message::Message;
+use goose::session::{self, storage, SessionMetadata};
+
+const HISTORY_LENGTHS: [usize; 3] = [10, 100, 1_000];
+// Start again from the base history before it grows far past its benchmarked length
+const RESET_EVERY: u64 = 100;
+
+fn history(length: usize) -> Vec<Message> {
+    (0..length)
+        .map(|i| {
+            let text = format!(
+                "Message {} of the conversation, {}",
+                i,
+                "lorem ipsum ".repeat(40)
+            );
+            if i % 2 == 0 {
+                Message::user().with_text(text)
+            } else {
+                Message::assistant().with_text(text)
+            }
+        })
+        .collect()
+}
+
+/// Time writing one new message at the end of a history of `length` messages
+fn time_turns(
+    runtime: &tokio::runtime::Runtime,
+    session_file: &Path,
+    length: usize,
+    iters: u64,
+    append: bool,
+) -> Duration {
+    let base = history(length);
+    let mut messages = base.clone();
+    let mut elapsed = Duration::ZERO;
+
+    for i in 0..iters {
+        if i % RESET_EVERY == 0 {
+            messages = base.clone();
+            storage::save_messages_with_metadata(
+                session_file,
+                &SessionMetadata::default(),
+                &messages,
+            )
+            .unwrap();
+        }
+        messages.push(Message::assistant().with_text(format!("Reply {}", i)));
+
+        let start = Instant::now();
+        if append {
+            runtime
+                .block_on(session::persist_messages(session_file, &messages, None))
+                .unwrap();
+        } else {
+            storage::save_messages_with_metadata(
+                session_file,
+                &SessionMetadata::default(),
+                &messages,
+            )
+            .unwrap();
+        }
+        elapsed += start.elapsed();
+    }
+    elapsed
+}
+
+fn benchmark_session_persistence(c: &mut Criterion) {
+    // Session files must live in the session directory, so point it at a scratch location
+    let home = tempfile::tempdir().unwrap();
+    std::env::set_var("HOME", home.path());
+    std::env::set_var("XDG_DATA_HOME", home.path().join("data"));
+    let session_dir = session::ensure_session_dir().unwrap();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    for fsync in ["never", "always"] {
+        std::env::set_var("GOOSE_SESSION_FSYNC", fsync);
+        let mut group = c.benchmark_group(format!("persist_turn_fsync_{}", fsync));
+        for length in HISTORY_LENGTHS {
+            let session_file = session_dir.join(format!("bench_{}_{}.jsonl", fsync, length));
+            group.bench_with_input(BenchmarkId::new("append", length), &length, |b, &length| {
+                b.iter_custom(|iters| time_turns(&runtime, &session_file, length, iters, true))
+            });
+            group.bench_with_input(
+                BenchmarkId::new("rewrite", length),
+                &length,
+                |b, &length| {
+                    b.iter_custom(|iters| time_turns(&runtime, &session_file, length, iters, false))
+                },
+            );
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, benchmark_session_persistence);
+criterion_main!(benches);
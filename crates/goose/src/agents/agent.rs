@@ -1,24 +1,35 @@
 use std::collections::HashMap;
 use std::future::Future;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Result};
 use futures::stream::BoxStream;
 use futures::{FutureExt, Stream, TryStreamExt};
 use futures_util::stream;
 use futures_util::stream::StreamExt;
+use mcp_client::client::McpClientTrait;
 use mcp_core::protocol::JsonRpcMessage;
 
 use crate::agents::sub_recipe_manager::SubRecipeManager;
+use crate::config::workspace::tool_allowed;
 use crate::config::{Config, ExtensionConfigManager, PermissionManager};
-use crate::message::Message;
+use crate::context_mgmt::compaction::{
+    CompactionConfig, CompactionStep, CompactionStrategy, CompactionTrigger,
+};
+use crate::context_mgmt::log_compression::{compress_logs, LogCompressionConfig};
+use crate::determinism;
+use crate::message::{Message, MessageContent, ToolProvenance};
 use crate::permission::permission_judge::check_tool_permissions;
-use crate::permission::PermissionConfirmation;
-use crate::providers::base::Provider;
+use crate::permission::{CommandRiskPolicy, PermissionConfirmation};
+use crate::providers::base::{CompletionOptions, Provider, ProviderUsage, UsageTotals};
 use crate::providers::errors::ProviderError;
 use crate::recipe::{Author, Recipe, Settings, SubRecipe};
 use crate::scheduler_trait::SchedulerTrait;
+use crate::session::{self, AnalyticsRecorder, TurnStats};
 use crate::tool_monitor::{ToolCall, ToolMonitor};
 use regex::Regex;
 use serde_json::Value;
@@ -27,9 +38,11 @@ use tracing::{debug, error, instrument};
 
 use crate::agents::extension::{ExtensionConfig, ExtensionError, ExtensionResult, ToolInfo};
 use crate::agents::extension_manager::{get_parameter_names, ExtensionManager};
+use crate::agents::network_policy::is_network_tool;
 use crate::agents::platform_tools::{
     PLATFORM_LIST_RESOURCES_TOOL_NAME, PLATFORM_MANAGE_EXTENSIONS_TOOL_NAME,
     PLATFORM_MANAGE_SCHEDULE_TOOL_NAME, PLATFORM_READ_RESOURCE_TOOL_NAME,
+    PLATFORM_READ_RESULT_TOOL_NAME, PLATFORM_REFRESH_CONTEXT_TOOL_NAME,
     PLATFORM_SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME,
 };
 use crate::agents::prompt_manager::PromptManager;
@@ -37,6 +50,7 @@ use crate::agents::router_tool_selector::{
     create_tool_selector, RouterToolSelectionStrategy, RouterToolSelector,
 };
 use crate::agents::router_tools::{ROUTER_LLM_SEARCH_TOOL_NAME, ROUTER_VECTOR_SEARCH_TOOL_NAME};
+use crate::agents::tool_descriptor::{self, ToolDescriptor};
 use crate::agents::tool_router_index_manager::ToolRouterIndexManager;
 use crate::agents::tool_vectordb::generate_table_id;
 use crate::agents::types::SessionConfig;
@@ -47,11 +61,26 @@ use mcp_core::{
 
 use crate::agents::subagent_tools::SUBAGENT_RUN_TASK_TOOL_NAME;
 
+/// Rounds of tool calls a reply runs before it stops, unless GOOSE_MAX_TOOL_ITERATIONS is set
+pub const DEFAULT_MAX_TOOL_ITERATIONS: usize = 100;
+
+use super::approval::{ApprovalMonitor, DefaultDecision};
+use super::errors::AgentError;
+use super::guardrail::Guardrail;
+use super::oversized_message::OversizedMessage;
 use super::platform_tools;
+use super::result_pager::ResultPager;
 use super::router_tools;
+use super::soft_stop::{with_stop_instruction, without_tool_requests};
 use super::subagent_manager::SubAgentManager;
 use super::subagent_tools;
-use super::tool_execution::{ToolCallResult, CHAT_MODE_TOOL_SKIPPED_RESPONSE, DECLINED_RESPONSE};
+use super::tool_execution::{
+    tool_progress, user_facing_content, ToolCallResult, CHAT_MODE_TOOL_SKIPPED_RESPONSE,
+    DECLINED_RESPONSE,
+};
+use super::tool_limits::ToolLimits;
+use super::turn_journal::TurnJournal;
+use super::workspace_context::{collect_workspace_context, WorkspaceContextConfig};
 
 /// The main goose Agent
 pub struct Agent {
@@ -62,7 +91,7 @@ pub struct Agent {
     pub(super) frontend_instructions: Mutex<Option<String>>,
     pub(super) prompt_manager: Mutex<PromptManager>,
     pub(super) confirmation_tx: mpsc::Sender<(String, PermissionConfirmation)>,
-    pub(super) confirmation_rx: Mutex<mpsc::Receiver<(String, PermissionConfirmation)>>,
+    pub(super) approval_monitor: ApprovalMonitor,
     pub(super) tool_result_tx: mpsc::Sender<(String, ToolResult<Vec<Content>>)>,
     pub(super) tool_result_rx: ToolResultReceiver,
     pub(super) tool_monitor: Mutex<Option<ToolMonitor>>,
@@ -70,13 +99,96 @@ pub struct Agent {
     pub(super) scheduler_service: Mutex<Option<Arc<dyn SchedulerTrait>>>,
     pub(super) subagent_manager: Mutex<Option<SubAgentManager>>,
     pub(super) mcp_notification_rx: Arc<Mutex<mpsc::Receiver<JsonRpcMessage>>>,
+    pub(super) interjection_tx: mpsc::Sender<String>,
+    pub(super) interjection_rx: Mutex<mpsc::Receiver<String>>,
+    pub(super) guardrails: Mutex<Vec<Arc<dyn Guardrail>>>,
+    pub(super) result_pager: Mutex<ResultPager>,
+    pub(super) workspace_context: WorkspaceContextConfig,
+    /// The directory the workspace context is collected from, the session's once it started
+    pub(super) workspace_root: Mutex<Option<PathBuf>>,
+    /// Token usage of all provider calls of the agent, per model
+    pub(super) usage: Mutex<UsageTotals>,
+    pub(super) command_risk: CommandRiskPolicy,
+    pub(super) compaction: Mutex<CompactionConfig>,
+    /// A strategy set through the API, used instead of the configured ones
+    pub(super) compaction_strategy: Mutex<Option<Arc<dyn CompactionStrategy>>>,
+    /// Rounds of tool calls a reply runs before it stops and hands back to the user
+    pub(super) max_tool_iterations: Mutex<usize>,
+    /// Whether tools that reach the network are refused
+    pub(super) network_tools_disabled: Mutex<bool>,
+    /// Patterns of the tools the model may use, all tools when not set
+    pub(super) tool_allowlist: Mutex<Option<Vec<String>>>,
+    /// Whether the reply in progress should stop once its running tools finish
+    pub(super) stop_requested: AtomicBool,
 }
 
 #[derive(Clone, Debug)]
 pub enum AgentEvent {
     Message(Message),
     McpNotification((String, JsonRpcMessage)),
-    ModelChange { model: String, mode: String },
+    ModelChange {
+        model: String,
+        mode: String,
+    },
+    Interjection(Message),
+    TurnStats(TurnStats),
+    /// Reasoning from the model, emitted before the message that contains it
+    Thinking(String),
+    /// A tool call the model committed to, emitted before the tool runs, with the arguments
+    /// of the complete response.
+    ToolCall {
+        id: String,
+        name: String,
+        arguments: Value,
+    },
+    /// The arguments of a tool call known so far, while the response is still streaming in.
+    /// Only for display, the call runs with the arguments of its `ToolCall` event.
+    ToolCallProgress {
+        id: String,
+        name: String,
+        arguments: Value,
+    },
+    /// Progress a running tool reported, with the fraction of its work done when it knows it
+    ToolProgress {
+        id: String,
+        name: String,
+        message: Option<String>,
+        fraction: Option<f64>,
+    },
+    /// Token usage of a single provider call
+    Usage(ProviderUsage),
+    /// Token usage of the reply so far, after each of its provider calls. The turn counts the
+    /// provider calls of the reply from 1.
+    CumulativeUsage {
+        turn: usize,
+        usage: UsageTotals,
+    },
+    /// No approval decision arrived in time and the default decision was applied
+    ApprovalTimedOut {
+        request_id: String,
+        tool_name: String,
+        approved: bool,
+    },
+    /// Parts of a tool result meant only for the user, which are never sent to the model
+    UserFacingToolOutput {
+        tool_name: String,
+        content: Vec<Content>,
+    },
+    /// The agent compacted the conversation before sending it, with what each strategy removed.
+    /// Token counts are estimates.
+    Compacted {
+        trigger: CompactionTrigger,
+        tokens_before: usize,
+        tokens_after: usize,
+        steps: Vec<CompactionStep>,
+    },
+    /// A soft stop was requested, so the tools of this round finished and the model is asked
+    /// for a summary instead of more tool calls. A hard cancel drops the reply stream instead,
+    /// without an event.
+    SoftStop,
+    /// The user message of the reply alone was too large for the context. It was replaced by
+    /// the replacement of the event, or the reply ends without calling the provider.
+    OversizedMessage(OversizedMessage),
 }
 
 impl Default for Agent {
@@ -126,6 +238,7 @@ impl Agent {
         let (tool_tx, tool_rx) = mpsc::channel(32);
         // Add MCP notification channel
         let (mcp_tx, mcp_rx) = mpsc::channel(100);
+        let (interjection_tx, interjection_rx) = mpsc::channel(32);
 
         Self {
             provider: Mutex::new(None),
@@ -135,7 +248,7 @@ impl Agent {
             frontend_instructions: Mutex::new(None),
             prompt_manager: Mutex::new(PromptManager::new()),
             confirmation_tx: confirm_tx,
-            confirmation_rx: Mutex::new(confirm_rx),
+            approval_monitor: ApprovalMonitor::from_config(confirm_rx),
             tool_result_tx: tool_tx,
             tool_result_rx: Arc::new(Mutex::new(tool_rx)),
             tool_monitor: Mutex::new(None),
@@ -144,6 +257,24 @@ impl Agent {
             // Initialize with MCP notification support
             subagent_manager: Mutex::new(Some(SubAgentManager::new(mcp_tx))),
             mcp_notification_rx: Arc::new(Mutex::new(mcp_rx)),
+            interjection_tx,
+            interjection_rx: Mutex::new(interjection_rx),
+            guardrails: Mutex::new(Vec::new()),
+            result_pager: Mutex::new(ResultPager::from_config()),
+            workspace_context: WorkspaceContextConfig::from_config(),
+            workspace_root: Mutex::new(None),
+            usage: Mutex::new(UsageTotals::new()),
+            command_risk: CommandRiskPolicy::from_config(),
+            compaction: Mutex::new(CompactionConfig::from_config()),
+            compaction_strategy: Mutex::new(None),
+            max_tool_iterations: Mutex::new(
+                Config::global()
+                    .get_param("GOOSE_MAX_TOOL_ITERATIONS")
+                    .unwrap_or(DEFAULT_MAX_TOOL_ITERATIONS),
+            ),
+            network_tools_disabled: Mutex::new(false),
+            tool_allowlist: Mutex::new(None),
+            stop_requested: AtomicBool::new(false),
         }
     }
 
@@ -163,6 +294,109 @@ impl Agent {
         }
     }
 
+    /// Token usage of all provider calls the agent made while replying, per model
+    pub async fn usage(&self) -> UsageTotals {
+        self.usage.lock().await.clone()
+    }
+
+    /// Compact conversations with the built-in strategies of `config`
+    pub async fn set_compaction(&self, config: CompactionConfig) {
+        *self.compaction.lock().await = config;
+    }
+
+    /// Compact conversations with `strategy`, at the threshold of the compaction config
+    pub async fn set_compaction_strategy(&self, strategy: Arc<dyn CompactionStrategy>) {
+        *self.compaction_strategy.lock().await = Some(strategy);
+    }
+
+    /// Set how many rounds of tool calls a reply runs before it stops
+    pub async fn set_max_tool_iterations(&self, max_tool_iterations: usize) {
+        *self.max_tool_iterations.lock().await = max_tool_iterations;
+    }
+
+    /// Set the page size, in tokens, of tool results that are too large to return at once
+    pub async fn set_result_page_tokens(&self, page_tokens: usize) {
+        self.result_pager.lock().await.set_page_tokens(page_tokens);
+    }
+
+    /// Compress the logs pasted into the text of a user message
+    ///
+    /// The original text is stored with the tool results of the session, so the model can read
+    /// it with `platform__read_result`, and a note of what was done is appended to the message.
+    /// Returns the note as well, for showing to the user.
+    pub async fn compress_pasted_logs(
+        &self,
+        mut message: Message,
+        config: &LogCompressionConfig,
+        session_file: Option<&Path>,
+    ) -> (Message, Option<String>) {
+        let mut pager = self.result_pager.lock().await;
+        if let Some(session_file) = session_file {
+            pager.set_store_dir(session::storage::tool_results_dir(session_file));
+        }
+
+        let mut notes = Vec::new();
+        for content in message.content.iter_mut() {
+            let MessageContent::Text(text) = content else {
+                continue;
+            };
+            let Some(compressed) = compress_logs(&text.text, config) else {
+                continue;
+            };
+            match pager.store(&text.text) {
+                Ok(id) => {
+                    notes.push(format!(
+                        "[goose {}. The original is stored as result {}, call {} with id \"{}\" and page 1 to read it.]",
+                        compressed.summary(),
+                        id,
+                        PLATFORM_READ_RESULT_TOOL_NAME,
+                        id
+                    ));
+                    text.text = compressed.text;
+                }
+                Err(e) => tracing::warn!("Failed to store pasted logs, sending them whole: {}", e),
+            }
+        }
+
+        if notes.is_empty() {
+            return (message, None);
+        }
+        let note = notes.join("\n");
+        (message.with_text(note.clone()), Some(note))
+    }
+
+    /// Collect the workspace context again and put it in the system prompt
+    ///
+    /// The context is collected from `root` when given, and otherwise from the directory of
+    /// the last collection, or the current directory the first time.
+    pub async fn refresh_workspace_context(&self, root: Option<PathBuf>) -> Option<String> {
+        if !self.workspace_context.enabled {
+            return None;
+        }
+        let root = {
+            let mut workspace_root = self.workspace_root.lock().await;
+            if root.is_some() {
+                *workspace_root = root;
+            }
+            workspace_root
+                .clone()
+                .or_else(|| std::env::current_dir().ok())?
+        };
+        let config = self.workspace_context.clone();
+        let context =
+            tokio::task::spawn_blocking(move || collect_workspace_context(&root, &config))
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Failed to collect the workspace context: {}", e);
+                    None
+                });
+        self.prompt_manager
+            .lock()
+            .await
+            .set_workspace_context(context.clone());
+        context
+    }
+
     /// Set the scheduler service for this agent
     pub async fn set_scheduler(&self, scheduler: Arc<dyn SchedulerTrait>) {
         let mut scheduler_service = self.scheduler_service.lock().await;
@@ -217,6 +451,12 @@ impl Agent {
         tool_call: mcp_core::tool::ToolCall,
         request_id: String,
     ) -> (String, Result<ToolCallResult, ToolError>) {
+        // Guardrails can block the call or replace it before anything else sees it
+        let tool_call = match self.guard_tool_call(tool_call).await {
+            Ok(tool_call) => tool_call,
+            Err(e) => return (request_id, Err(e)),
+        };
+
         // Check if this tool call should be allowed based on repetition monitoring
         if let Some(monitor) = self.tool_monitor.lock().await.as_mut() {
             let tool_call_info = ToolCall::new(tool_call.name.clone(), tool_call.arguments.clone());
@@ -238,6 +478,25 @@ impl Agent {
             return (request_id, Ok(ToolCallResult::from(result)));
         }
 
+        if tool_call.name == PLATFORM_REFRESH_CONTEXT_TOOL_NAME {
+            let result = match self.refresh_workspace_context(None).await {
+                Some(context) => Ok(vec![Content::text(context)]),
+                None => Err(ToolError::ExecutionError(
+                    "The workspace context is disabled or could not be collected".to_string(),
+                )),
+            };
+            return (request_id, Ok(ToolCallResult::from(result)));
+        }
+
+        if tool_call.name == PLATFORM_READ_RESULT_TOOL_NAME {
+            let result = self
+                .result_pager
+                .lock()
+                .await
+                .read_result(&tool_call.arguments);
+            return (request_id, Ok(ToolCallResult::from(result)));
+        }
+
         if tool_call.name == PLATFORM_MANAGE_EXTENSIONS_TOOL_NAME {
             let extension_name = tool_call
                 .arguments
@@ -328,6 +587,7 @@ impl Agent {
             }
         };
 
+        let pager = self.result_pager.lock().await.clone();
         (
             request_id,
             Ok(ToolCallResult {
@@ -335,12 +595,28 @@ impl Agent {
                 result: Box::new(
                     result
                         .result
-                        .map(super::large_response_handler::process_tool_response),
+                        .map(move |output| output.map(|contents| pager.paginate(contents))),
                 ),
             }),
         )
     }
 
+    /// The extension that handles a tool, or the prefix of its name for tools of the agent
+    async fn tool_extension(&self, tool_name: &str) -> String {
+        if let Some(extension) = self
+            .extension_manager
+            .read()
+            .await
+            .extension_for_tool(tool_name)
+        {
+            return extension;
+        }
+        tool_name
+            .split_once("__")
+            .map_or("platform", |(prefix, _)| prefix)
+            .to_string()
+    }
+
     pub(super) async fn manage_extensions(
         &self,
         action: String,
@@ -452,12 +728,13 @@ impl Agent {
     }
 
     pub async fn add_extension(&self, extension: ExtensionConfig) -> ExtensionResult<()> {
+        self.check_network_access(&extension).await?;
+
         match &extension {
             ExtensionConfig::Frontend {
-                name: _,
                 tools,
                 instructions,
-                bundled: _,
+                ..
             } => {
                 // For frontend tools, just store them in the frontend_tools map
                 let mut frontend_tools = self.frontend_tools.lock().await;
@@ -511,6 +788,19 @@ impl Agent {
         Ok(())
     }
 
+    /// Add an initialized MCP client as an extension, with the tool limits it declares
+    pub async fn add_client(
+        &self,
+        name: &str,
+        client: Box<dyn McpClientTrait>,
+        limits: ToolLimits,
+    ) {
+        self.extension_manager
+            .write()
+            .await
+            .add_client(name, client, limits);
+    }
+
     pub async fn list_tools(&self, extension_name: Option<String>) -> Vec<Tool> {
         let extension_manager = self.extension_manager.read().await;
         let mut prefixed_tools = extension_manager
@@ -524,8 +814,13 @@ impl Agent {
                 platform_tools::search_available_extensions_tool(),
                 platform_tools::manage_extensions_tool(),
                 platform_tools::manage_schedule_tool(),
+                platform_tools::read_result_tool(),
             ]);
 
+            if self.workspace_context.enabled {
+                prefixed_tools.push(platform_tools::refresh_context_tool());
+            }
+
             // Add subagent tool (only if ALPHA_FEATURES is enabled)
             let config = Config::global();
             if config.get_param::<bool>("ALPHA_FEATURES").unwrap_or(false) {
@@ -546,9 +841,21 @@ impl Agent {
             prefixed_tools.extend(sub_recipe_manager.sub_recipe_tools.values().cloned());
         }
 
+        if self.network_tools_disabled().await {
+            prefixed_tools.retain(|tool| !is_network_tool(tool));
+        }
+        if let Some(patterns) = self.tool_allowlist.lock().await.as_deref() {
+            prefixed_tools.retain(|tool| tool_allowed(Some(patterns), &tool.name));
+        }
+
         prefixed_tools
     }
 
+    /// Describe the tools of [`Agent::list_tools`], for tool pickers and documentation
+    pub async fn describe_tools(&self, extension_name: Option<String>) -> Vec<ToolDescriptor> {
+        tool_descriptor::describe_tools(&self.list_tools(extension_name).await)
+    }
+
     pub async fn list_tools_for_router(
         &self,
         strategy: Option<RouterToolSelectionStrategy>,
@@ -628,6 +935,40 @@ impl Agent {
         }
     }
 
+    /// Stop waiting for tool approvals after `timeout` and apply `default_decision` instead,
+    /// so unattended runs don't block on a confirmation that never comes
+    pub async fn set_approval_timeout(&self, timeout: Duration, default_decision: DefaultDecision) {
+        self.approval_monitor
+            .set_timeout(Some((timeout, default_decision)));
+    }
+
+    /// Queue a user note to be added to the conversation while a reply is in progress
+    ///
+    /// The note is picked up at the next safe boundary of the reply loop, after the
+    /// current tool responses are assembled and before the next provider call, and is
+    /// inserted as a user message. An `AgentEvent::Interjection` is emitted once it is
+    /// part of the conversation.
+    pub async fn interject(&self, text: impl Into<String>) {
+        if let Err(e) = self.interjection_tx.send(text.into()).await {
+            error!("Failed to queue interjection: {}", e);
+        }
+    }
+
+    /// Take any interjections that were queued but not yet incorporated into a reply
+    ///
+    /// A reply that finishes without another provider call leaves its interjections
+    /// queued, so frontends should drain them here and decide how to handle them.
+    pub async fn take_pending_interjections(&self) -> Vec<String> {
+        let mut interjections = Vec::new();
+        let mut rx = self.interjection_rx.lock().await;
+
+        while let Ok(interjection) = rx.try_recv() {
+            interjections.push(interjection);
+        }
+
+        interjections
+    }
+
     #[instrument(skip(self, messages, session), fields(user_message))]
     pub async fn reply(
         &self,
@@ -640,6 +981,12 @@ impl Agent {
         // Load settings from config
         let config = Config::global();
 
+        // Summarize the workspace once, so the model doesn't spend turns looking around
+        if !self.prompt_manager.lock().await.has_workspace_context() {
+            let working_dir = session.as_ref().map(|session| session.working_dir.clone());
+            self.refresh_workspace_context(working_dir).await;
+        }
+
         // Setup tools and prompt
         let (mut tools, mut toolshim_tools, mut system_prompt) =
             self.prepare_tools_and_prompt().await?;
@@ -667,6 +1014,18 @@ impl Agent {
         let (tools_with_readonly_annotation, tools_without_annotation) =
             Self::categorize_tools_by_annotation(&tools);
 
+        // Large tool results of a session are stored with it, as is the journal of its turns
+        let mut turn_journal = None;
+        if let Some(session_config) = &session {
+            if let Ok(session_file) = session::storage::get_path(session_config.id.clone()) {
+                self.result_pager
+                    .lock()
+                    .await
+                    .set_store_dir(session::storage::tool_results_dir(&session_file));
+                turn_journal = Some(TurnJournal::for_session(&session_file));
+            }
+        }
+
         if let Some(content) = messages
             .last()
             .and_then(|msg| msg.content.first())
@@ -675,8 +1034,18 @@ impl Agent {
             debug!("user_message" = &content);
         }
 
-        Ok(Box::pin(async_stream::try_stream! {
+        let max_tool_iterations = *self.max_tool_iterations.lock().await;
+        let analytics_session = session.clone();
+        // A stop requested before this reply was meant for an earlier one
+        self.take_stop_request();
+
+        let events: BoxStream<'_, Result<AgentEvent>> = Box::pin(async_stream::try_stream! {
             let _ = reply_span.enter();
+            let turn_start = Instant::now();
+            let mut turn_stats = TurnStats::default();
+            let mut reply_usage = UsageTotals::new();
+            let mut compacted_after_error = false;
+            let mut tool_iterations = 0;
             loop {
                 // Check for MCP notifications from subagents
                 let mcp_notifications = self.get_mcp_notifications().await;
@@ -700,14 +1069,71 @@ impl Agent {
                     }
                 }
 
-                match Self::generate_response_from_provider(
-                    self.provider().await?,
-                    &system_prompt,
-                    &messages,
-                    &tools,
-                    &toolshim_tools,
-                ).await {
+                // A user message that alone fills the context is handled before the first provider call
+                if turn_stats.provider_calls == 0 {
+                    if let Some(oversized) = self.check_message_size(&messages).await? {
+                        let rejected = oversized.replacement.is_none();
+                        if let (Some(replacement), Some(last)) = (&oversized.replacement, messages.last_mut()) {
+                            *last = replacement.clone();
+                        }
+                        yield AgentEvent::OversizedMessage(oversized);
+                        if rejected {
+                            break;
+                        }
+                    }
+                }
+
+                match self.compact_messages(&messages, CompactionTrigger::Threshold).await {
+                    Ok(Some((compacted, event))) => {
+                        messages = compacted;
+                        yield event;
+                    }
+                    Ok(None) => {}
+                    Err(e) => tracing::warn!("Failed to compact the conversation: {}", e),
+                }
+
+                let provider_start = Instant::now();
+                let provider = self.provider().await?;
+                let provider_messages = self.guard_provider_request(&messages).await;
+                let (progress_tx, mut progress_rx) = mpsc::unbounded_channel();
+                let response = {
+                    let mut response = std::pin::pin!(Self::generate_response_from_provider(
+                        provider,
+                        &system_prompt,
+                        &provider_messages,
+                        &tools,
+                        &toolshim_tools,
+                        Some(progress_tx),
+                        &CompletionOptions::default(),
+                    ));
+                    // Tool calls that stream in are shown before the response is complete
+                    loop {
+                        let progress = tokio::select! {
+                            response = &mut response => break response,
+                            Some(progress) = progress_rx.recv() => progress,
+                        };
+                        yield AgentEvent::ToolCallProgress {
+                            id: progress.id,
+                            name: progress.name,
+                            arguments: progress.arguments,
+                        };
+                    }
+                };
+                match response {
                     Ok((response, usage)) => {
+                        turn_stats.record_provider_call(provider_start.elapsed(), usage.time_to_first_byte_ms);
+                        if usage.is_truncated() {
+                            tracing::warn!("The response of {} was cut off by the output token limit", usage.model);
+                        }
+
+                        // Guardrails may replace the response, the original is kept in the session metadata
+                        let (response, guardrail_rewrites) = self.guard_model_response(response).await;
+                        if let Some(session_config) = session.clone() {
+                            if !guardrail_rewrites.is_empty() {
+                                Self::record_guardrail_rewrites(session_config, guardrail_rewrites).await?;
+                            }
+                        }
+
                         // Emit model change event if provider is lead-worker
                         let provider = self.provider().await?;
                         if let Some(lead_worker) = provider.as_lead_worker() {
@@ -728,10 +1154,38 @@ impl Agent {
                             };
                         }
 
+                        self.usage.lock().await.record(&usage);
+
                         // record usage for the session in the session file
                         if let Some(session_config) = session.clone() {
                             Self::update_session_metrics(session_config, &usage, messages.len()).await?;
                         }
+                        yield AgentEvent::Usage(usage.clone());
+                        reply_usage.record(&usage);
+                        yield AgentEvent::CumulativeUsage {
+                            turn: turn_stats.provider_calls,
+                            usage: reply_usage.clone(),
+                        };
+
+                        // Announce reasoning and tool calls before the full message, so frontends
+                        // can show them before any tool starts running
+                        for content in &response.content {
+                            match content {
+                                MessageContent::Thinking(thinking) => {
+                                    yield AgentEvent::Thinking(thinking.thinking.clone());
+                                }
+                                MessageContent::ToolRequest(request) => {
+                                    if let Ok(tool_call) = &request.tool_call {
+                                        yield AgentEvent::ToolCall {
+                                            id: request.id.clone(),
+                                            name: tool_call.name.clone(),
+                                            arguments: tool_call.arguments.clone(),
+                                        };
+                                    }
+                                }
+                                _ => {}
+                            }
+                        }
 
                         // categorize the type of requests we need to handle
                         let (frontend_requests,
@@ -769,6 +1223,13 @@ impl Agent {
                             break;
                         }
 
+                        // Results that arrive before the turn is saved survive a crash in the journal
+                        if let Some(journal) = &turn_journal {
+                            if let Err(e) = journal.begin(&filtered_response) {
+                                tracing::warn!("Failed to start turn journal: {}", e);
+                            }
+                        }
+
                         // Process tool requests depending on frontend tools and then goose_mode
                         let message_tool_response = Arc::new(Mutex::new(Message::user()));
 
@@ -779,10 +1240,10 @@ impl Agent {
                         );
 
                         // we have a stream of frontend tools to handle, inside the stream
-                        // execution is yeield back to this reply loop, and is of the same Message
-                        // type, so we can yield that back up to be handled
-                        while let Some(msg) = frontend_tool_stream.try_next().await? {
-                            yield AgentEvent::Message(msg);
+                        // execution is yeield back to this reply loop, and its events are
+                        // yielded back up to be handled
+                        while let Some(event) = frontend_tool_stream.try_next().await? {
+                            yield event;
                         }
 
                         // Clone goose_mode once before the match to avoid move issues
@@ -801,13 +1262,14 @@ impl Agent {
                             // What remains is handling the remaining tool requests (enable extension,
                             // regular tool calls) in goose_mode == ["auto", "approve" or "smart_approve"]
                             let mut permission_manager = PermissionManager::default();
-                            let (permission_check_result, enable_extension_request_ids) = check_tool_permissions(
+                            let (mut permission_check_result, enable_extension_request_ids) = check_tool_permissions(
                                 &remaining_requests,
                                 &mode,
                                 tools_with_readonly_annotation.clone(),
                                 tools_without_annotation.clone(),
                                 &mut permission_manager,
                                 self.provider().await?).await;
+                            permission_check_result.scope_auto_approvals(&self.command_risk);
 
                             // Handle pre-approved and read-only tools in parallel
                             let mut tool_futures: Vec<(String, ToolStream)> = Vec::new();
@@ -844,6 +1306,7 @@ impl Agent {
                             // Process tools requiring approval (enable extension, regular tool calls)
                             let mut tool_approval_stream = self.handle_approval_tool_requests(
                                 &permission_check_result.needs_approval,
+                                &tools_with_readonly_annotation,
                                 tool_futures_arc.clone(),
                                 &mut permission_manager,
                                 message_tool_response.clone()
@@ -853,8 +1316,8 @@ impl Agent {
                             // Execution is yielded back to this reply loop, and is of the same Message
                             // type, so we can yield the Message back up to be handled and grab any
                             // confirmations or denials
-                            while let Some(msg) = tool_approval_stream.try_next().await? {
-                                yield AgentEvent::Message(msg);
+                            while let Some(event) = tool_approval_stream.try_next().await? {
+                                yield event;
                             }
 
                             tool_futures = {
@@ -875,17 +1338,72 @@ impl Agent {
 
                             let mut all_install_successful = true;
 
+                            // Tools run concurrently once the combined stream is polled, so each
+                            // call is timed from here until its result arrives
+                            let tool_names: HashMap<String, String> = remaining_requests
+                                .iter()
+                                .filter_map(|request| {
+                                    let tool_call = request.tool_call.as_ref().ok()?;
+                                    Some((request.id.clone(), tool_call.name.clone()))
+                                })
+                                .collect();
+                            let mut tool_extensions: HashMap<String, String> = HashMap::new();
+                            for (request_id, name) in &tool_names {
+                                tool_extensions.insert(request_id.clone(), self.tool_extension(name).await);
+                            }
+                            // The response will follow the assistant message that requested the calls
+                            let message_index = messages.len();
+                            let tools_start = Instant::now();
+                            let started_at = determinism::now().timestamp_millis();
+
                             while let Some((request_id, item)) = combined.next().await {
                                 match item {
                                     ToolStreamItem::Result(output) => {
+                                        if let Some(name) = tool_names.get(&request_id) {
+                                            turn_stats.record_tool_call(request_id.clone(), name.clone(), tools_start.elapsed());
+
+                                            // Providers drop user-only content, so hand it to the frontend directly
+                                            let content = output.as_ref().map(|contents| user_facing_content(contents)).unwrap_or_default();
+                                            if !content.is_empty() {
+                                                yield AgentEvent::UserFacingToolOutput {
+                                                    tool_name: name.clone(),
+                                                    content,
+                                                };
+                                            }
+                                        }
                                         if enable_extension_request_ids.contains(&request_id) && output.is_err(){
                                             all_install_successful = false;
                                         }
+                                        if let Some(journal) = &turn_journal {
+                                            if let Err(e) = journal.record(&request_id, &output) {
+                                                tracing::warn!("Failed to journal tool result: {}", e);
+                                            }
+                                        }
                                         let mut response = message_tool_response.lock().await;
-                                        *response = response.clone().with_tool_response(request_id, output);
+                                        *response = match tool_extensions.get(&request_id) {
+                                            Some(extension) => {
+                                                let provenance = ToolProvenance {
+                                                    message_index,
+                                                    extension: extension.clone(),
+                                                    started_at,
+                                                    finished_at: determinism::now().timestamp_millis(),
+                                                };
+                                                response.clone().with_traced_tool_response(request_id, output, provenance)
+                                            }
+                                            None => response.clone().with_tool_response(request_id, output),
+                                        };
                                     },
                                     ToolStreamItem::Message(msg) => {
-                                        yield AgentEvent::McpNotification((request_id, msg))
+                                        if let Some((message, fraction)) = tool_progress(&msg) {
+                                            yield AgentEvent::ToolProgress {
+                                                name: tool_names.get(&request_id).cloned().unwrap_or_default(),
+                                                id: request_id,
+                                                message,
+                                                fraction,
+                                            };
+                                        } else {
+                                            yield AgentEvent::McpNotification((request_id, msg))
+                                        }
                                     }
                                 }
                             }
@@ -898,10 +1416,73 @@ impl Agent {
 
                         let final_message_tool_resp = message_tool_response.lock().await.clone();
                         yield AgentEvent::Message(final_message_tool_resp.clone());
+                        if let Some(journal) = &turn_journal {
+                            if let Err(e) = journal.finish() {
+                                tracing::warn!("Failed to remove turn journal: {}", e);
+                            }
+                        }
 
                         messages.push(response);
                         messages.push(final_message_tool_resp);
 
+                        // Incorporate any user interjections before the next provider call
+                        for interjection in self.take_pending_interjections().await {
+                            let interjection_message = Message::user().with_text(interjection);
+                            messages.push(interjection_message.clone());
+                            yield AgentEvent::Interjection(interjection_message);
+                        }
+
+                        // After a soft stop the model summarizes the results instead of calling more tools
+                        if self.take_stop_request() {
+                            yield AgentEvent::SoftStop;
+                            let provider_start = Instant::now();
+                            let provider = self.provider().await?;
+                            let provider_messages = with_stop_instruction(self.guard_provider_request(&messages).await);
+                            let summary = Self::generate_response_from_provider(
+                                provider,
+                                &system_prompt,
+                                &provider_messages,
+                                &tools,
+                                &toolshim_tools,
+                                None,
+                                &CompletionOptions::default().with_no_tool_calls(),
+                            ).await;
+                            match summary {
+                                Ok((summary, usage)) => {
+                                    turn_stats.record_provider_call(provider_start.elapsed(), usage.time_to_first_byte_ms);
+                                    self.usage.lock().await.record(&usage);
+                                    if let Some(session_config) = session.clone() {
+                                        Self::update_session_metrics(session_config, &usage, messages.len()).await?;
+                                    }
+                                    yield AgentEvent::Usage(usage.clone());
+                                    reply_usage.record(&usage);
+                                    yield AgentEvent::CumulativeUsage {
+                                        turn: turn_stats.provider_calls,
+                                        usage: reply_usage.clone(),
+                                    };
+                                    yield AgentEvent::Message(without_tool_requests(summary));
+                                }
+                                Err(e) => {
+                                    error!("Failed to summarize after a stop: {}", e);
+                                    yield AgentEvent::Message(Message::assistant().with_text(format!(
+                                        "I stopped as you asked, but could not summarize the progress: {e}."
+                                    )));
+                                }
+                            }
+                            break;
+                        }
+
+                        // A model that keeps calling tools stops here instead of running forever
+                        tool_iterations += 1;
+                        if tool_iterations >= max_tool_iterations {
+                            tracing::warn!("Stopping the reply after {} rounds of tool calls", tool_iterations);
+                            yield AgentEvent::Message(Message::assistant().with_text(format!(
+                                "I stopped after {} rounds of tool calls, the limit for one reply. Send a message to let me continue.",
+                                tool_iterations
+                            )));
+                            break;
+                        }
+
                         // Check for MCP notifications from subagents again before next iteration
                         // Note: These are already handled as McpNotification events above,
                         // so we don't need to convert them to assistant messages here.
@@ -928,6 +1509,20 @@ impl Agent {
                         // }
                     },
                     Err(ProviderError::ContextLengthExceeded(_)) => {
+                        // Compact once and retry, when the agent is configured to
+                        if !compacted_after_error {
+                            compacted_after_error = true;
+                            match self.compact_messages(&messages, CompactionTrigger::ContextLengthExceeded).await {
+                                Ok(Some((compacted, event))) => {
+                                    messages = compacted;
+                                    yield event;
+                                    continue;
+                                }
+                                Ok(None) => {}
+                                Err(e) => tracing::warn!("Failed to compact the conversation: {}", e),
+                            }
+                        }
+
                         // At this point, the last message should be a user message
                         // because call to provider led to context length exceeded error
                         // Immediately yield a special message and break
@@ -938,6 +1533,10 @@ impl Agent {
                     },
                     Err(e) => {
                         // Create an error message & terminate the stream
+                        let provider_name = Config::global()
+                            .get_param::<String>("GOOSE_PROVIDER")
+                            .unwrap_or_else(|_| "The provider".to_string());
+                        let e = AgentError::provider_failure(provider_name, &e);
                         error!("Error: {}", e);
                         yield AgentEvent::Message(Message::assistant().with_text(format!("Ran into this error: {e}.\n\nPlease retry if you think this is a transient or recoverable error.")));
                         break;
@@ -947,6 +1546,30 @@ impl Agent {
                 // Yield control back to the scheduler to prevent blocking
                 tokio::task::yield_now().await;
             }
+
+            turn_stats.finish(turn_start.elapsed());
+            if let Some(session_config) = session.clone() {
+                if let Err(e) = Self::record_turn_stats(session_config, &turn_stats).await {
+                    tracing::warn!("Failed to record turn stats: {}", e);
+                }
+            }
+            yield AgentEvent::TurnStats(turn_stats);
+        });
+
+        // Tool and model usage of the reply is taken from its events and kept with the session
+        Ok(Box::pin(async_stream::try_stream! {
+            let mut events = events;
+            let mut analytics = AnalyticsRecorder::new();
+            while let Some(event) = events.try_next().await? {
+                analytics.record(&event);
+                yield event;
+            }
+            if let Some(session_config) = analytics_session {
+                let analytics = analytics.take();
+                if !analytics.is_empty() {
+                    Self::record_analytics(session_config, analytics).await?;
+                }
+            }
         }))
     }
 
@@ -1142,13 +1765,14 @@ impl Agent {
 
         messages.push(Message::user().with_text(recipe_prompt));
 
-        let (result, _usage) = self
-            .provider
-            .lock()
-            .await
-            .as_ref()
-            .unwrap()
-            .complete(&system_prompt, &messages, &tools)
+        // Start the response with the JSON object when the provider can prefill it
+        let options = if provider.supports_prefill() {
+            CompletionOptions::default().with_prefill("{")
+        } else {
+            CompletionOptions::default()
+        };
+        let (result, _usage) = provider
+            .complete_with_options(&system_prompt, &messages, &tools, &options)
             .await?;
 
         let content = result.as_concat_text();
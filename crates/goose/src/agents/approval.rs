@@ -0,0 +1,192 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, Mutex};
+
+use crate::config::Config;
+use crate::permission::{Permission, PermissionConfirmation};
+
+/// Reason given to the model when a tool call is denied because nobody answered in time
+pub const APPROVAL_TIMED_OUT_REASON: &str = "approval timed out";
+
+/// What to do with a tool call when no approval decision arrives in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefaultDecision {
+    /// Deny every tool call
+    Deny,
+    /// Run tools annotated as read-only and deny everything else
+    ApproveReadOnly,
+}
+
+impl DefaultDecision {
+    fn approves(&self, read_only: bool) -> bool {
+        match self {
+            DefaultDecision::Deny => false,
+            DefaultDecision::ApproveReadOnly => read_only,
+        }
+    }
+}
+
+/// The result of waiting for a decision on a tool call
+#[derive(Debug, Clone)]
+pub enum ApprovalOutcome {
+    /// A decision arrived on the confirmation channel
+    Decided(PermissionConfirmation),
+    /// No decision arrived before the timeout and the default decision was applied
+    TimedOut { approved: bool },
+}
+
+/// Waits for approval decisions sent through [`crate::agents::Agent::handle_confirmation`]
+///
+/// Without a timeout the monitor waits until a decision arrives, which is what an interactive
+/// session wants. Unattended runs should set one with [`ApprovalMonitor::with_timeout`] so a
+/// missing answer falls back to a default decision instead of blocking forever.
+pub struct ApprovalMonitor {
+    rx: Mutex<mpsc::Receiver<(String, PermissionConfirmation)>>,
+    timeout: std::sync::Mutex<Option<(Duration, DefaultDecision)>>,
+}
+
+impl ApprovalMonitor {
+    pub fn new(rx: mpsc::Receiver<(String, PermissionConfirmation)>) -> Self {
+        Self {
+            rx: Mutex::new(rx),
+            timeout: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Create a monitor using the timeout from the GOOSE_APPROVAL_TIMEOUT (seconds) and
+    /// GOOSE_APPROVAL_DEFAULT_DECISION config, when a timeout is configured
+    pub fn from_config(rx: mpsc::Receiver<(String, PermissionConfirmation)>) -> Self {
+        let config = Config::global();
+        let monitor = Self::new(rx);
+        match config.get_param::<u64>("GOOSE_APPROVAL_TIMEOUT") {
+            Ok(secs) => {
+                let decision = config
+                    .get_param("GOOSE_APPROVAL_DEFAULT_DECISION")
+                    .unwrap_or(DefaultDecision::Deny);
+                monitor.with_timeout(Duration::from_secs(secs), decision)
+            }
+            Err(_) => monitor,
+        }
+    }
+
+    pub fn with_timeout(self, timeout: Duration, default_decision: DefaultDecision) -> Self {
+        self.set_timeout(Some((timeout, default_decision)));
+        self
+    }
+
+    /// Change the timeout, which applies to waits started after the change
+    pub fn set_timeout(&self, timeout: Option<(Duration, DefaultDecision)>) {
+        *self.timeout.lock().unwrap_or_else(|e| e.into_inner()) = timeout;
+    }
+
+    /// Wait for the decision on a tool request, skipping decisions for other requests
+    pub async fn wait_for(&self, request_id: &str, read_only: bool) -> ApprovalOutcome {
+        let timeout = *self.timeout.lock().unwrap_or_else(|e| e.into_inner());
+        let Some((timeout, default_decision)) = timeout else {
+            return self.recv_decision(request_id).await;
+        };
+
+        match tokio::time::timeout(timeout, self.recv_decision(request_id)).await {
+            Ok(outcome) => outcome,
+            Err(_) => {
+                let approved = default_decision.approves(read_only);
+                tracing::warn!(
+                    "No approval decision for tool request {} after {:?}, {}",
+                    request_id,
+                    timeout,
+                    if approved { "approving" } else { "denying" }
+                );
+                ApprovalOutcome::TimedOut { approved }
+            }
+        }
+    }
+
+    async fn recv_decision(&self, request_id: &str) -> ApprovalOutcome {
+        let mut rx = self.rx.lock().await;
+        while let Some((id, confirmation)) = rx.recv().await {
+            if id == request_id {
+                return ApprovalOutcome::Decided(confirmation);
+            }
+        }
+        // The agent keeps the sender for as long as it lives, so no decision can arrive
+        // anymore, the same as a decision that never comes
+        std::future::pending().await
+    }
+}
+
+impl ApprovalOutcome {
+    pub fn is_approved(&self) -> bool {
+        match self {
+            ApprovalOutcome::Decided(confirmation) => matches!(
+                confirmation.permission,
+                Permission::AllowOnce | Permission::AlwaysAllow
+            ),
+            ApprovalOutcome::TimedOut { approved } => *approved,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permission::permission_confirmation::PrincipalType;
+
+    fn confirmation(permission: Permission) -> PermissionConfirmation {
+        PermissionConfirmation {
+            principal_type: PrincipalType::Tool,
+            permission,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_matching_request() {
+        let (tx, rx) = mpsc::channel(4);
+        let monitor = ApprovalMonitor::new(rx);
+        tx.send(("other".to_string(), confirmation(Permission::AllowOnce)))
+            .await
+            .unwrap();
+        tx.send(("req".to_string(), confirmation(Permission::DenyOnce)))
+            .await
+            .unwrap();
+
+        let outcome = monitor.wait_for("req", false).await;
+        assert!(matches!(outcome, ApprovalOutcome::Decided(_)));
+        assert!(!outcome.is_approved());
+    }
+
+    #[tokio::test]
+    async fn test_never_responding_monitor_times_out() {
+        let timeout = Duration::from_millis(20);
+        let (_tx, rx) = mpsc::channel(4);
+        let monitor = ApprovalMonitor::new(rx).with_timeout(timeout, DefaultDecision::Deny);
+        let outcome = monitor.wait_for("req", true).await;
+        assert!(matches!(
+            outcome,
+            ApprovalOutcome::TimedOut { approved: false }
+        ));
+
+        monitor.set_timeout(Some((timeout, DefaultDecision::ApproveReadOnly)));
+        assert!(monitor.wait_for("req", true).await.is_approved());
+        assert!(!monitor.wait_for("req", false).await.is_approved());
+    }
+
+    #[tokio::test]
+    async fn test_timeout_changes_while_waiting() {
+        let (tx, rx) = mpsc::channel(4);
+        let monitor = std::sync::Arc::new(ApprovalMonitor::new(rx));
+        let waiting = tokio::spawn({
+            let monitor = monitor.clone();
+            async move { monitor.wait_for("req", false).await }
+        });
+        tokio::task::yield_now().await;
+
+        // The timeout can be set while another request waits for its decision
+        monitor.set_timeout(Some((Duration::from_millis(20), DefaultDecision::Deny)));
+        tx.send(("req".to_string(), confirmation(Permission::AllowOnce)))
+            .await
+            .unwrap();
+        assert!(waiting.await.unwrap().is_approved());
+    }
+}
@@ -1,13 +1,18 @@
+use std::sync::Arc;
+
 use anyhow::Ok;
 
 use crate::message::Message;
 use crate::token_counter::TokenCounter;
 
+use crate::context_mgmt::compaction::{
+    CompactionStrategy, CompactionTrigger, DropOldest, MessageTokenCounter, TokenBudget,
+    TokenEstimator,
+};
+use crate::context_mgmt::estimate_target_context_limit;
 use crate::context_mgmt::summarize::summarize_messages;
-use crate::context_mgmt::truncate::{truncate_messages, OldestFirstTruncation};
-use crate::context_mgmt::{estimate_target_context_limit, get_messages_token_counts};
 
-use super::super::agents::Agent;
+use super::super::agents::{Agent, AgentEvent};
 
 impl Agent {
     /// Public API to truncate oldest messages so that the conversation's token count is within the allowed context limit.
@@ -17,15 +22,14 @@ impl Agent {
     ) -> Result<(Vec<Message>, Vec<usize>), anyhow::Error> {
         let provider = self.provider().await?;
         let token_counter = TokenCounter::new(provider.get_model_config().tokenizer_name());
-        let target_context_limit = estimate_target_context_limit(provider);
-        let token_counts = get_messages_token_counts(&token_counter, messages);
+        let family = provider.get_model_config().tokenizer_family();
+        let budget = TokenBudget::for_provider(&provider);
+        let target_context_limit = budget.limit;
 
-        let (mut new_messages, mut new_token_counts) = truncate_messages(
-            messages,
-            &token_counts,
-            target_context_limit,
-            &OldestFirstTruncation,
-        )?;
+        let result = DropOldest { family }
+            .compact(messages, budget, &token_counter)
+            .await?;
+        let (mut new_messages, mut new_token_counts) = (result.messages, result.token_counts);
 
         // Only add an assistant message if we have room for it and it won't cause another overflow
         let assistant_message = Message::assistant().with_text("I had run into a context length exceeded error so I truncated some of the oldest messages in our conversation.");
@@ -79,4 +83,47 @@ impl Agent {
 
         Ok((new_messages, new_token_counts))
     }
+
+    /// The compacted messages and the event reporting it, when the agent compacts and they need it
+    ///
+    /// Before a provider call the conversation is only compacted once its estimated tokens pass
+    /// the threshold, after a context length error it always is.
+    pub(super) async fn compact_messages(
+        &self,
+        messages: &[Message],
+        trigger: CompactionTrigger,
+    ) -> Result<Option<(Vec<Message>, AgentEvent)>, anyhow::Error> {
+        let provider = self.provider().await?;
+        let config = self.compaction.lock().await.clone();
+        let strategy = match self.compaction_strategy.lock().await.clone() {
+            Some(strategy) => strategy,
+            None => match config.strategy(Arc::clone(&provider)) {
+                Some(strategy) => strategy,
+                None => return Ok(None),
+            },
+        };
+
+        let model_config = provider.get_model_config();
+        let counter = TokenEstimator::new(model_config.tokenizer_family());
+        let tokens_before: usize = counter.count_messages(messages).iter().sum();
+        if trigger == CompactionTrigger::Threshold
+            && tokens_before as f32 <= model_config.context_limit() as f32 * config.threshold
+        {
+            return Ok(None);
+        }
+
+        let result = strategy
+            .compact(messages, TokenBudget::for_provider(&provider), &counter)
+            .await?;
+        if result.steps.is_empty() {
+            return Ok(None);
+        }
+        let event = AgentEvent::Compacted {
+            trigger,
+            tokens_before,
+            tokens_after: result.total_tokens(),
+            steps: result.steps,
+        };
+        Ok(Some((result.messages, event)))
+    }
 }
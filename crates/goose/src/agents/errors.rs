@@ -0,0 +1,66 @@
+use thiserror::Error;
+
+use crate::providers::errors::ProviderError;
+
+/// Errors that end a reply, kept apart by kind so callers can retry, summarize or report them
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum AgentError {
+    #[error("{provider} request failed{}: {message}", status.map(|status| format!(" with status {}", status)).unwrap_or_default())]
+    ProviderFailure {
+        provider: String,
+        /// The HTTP status the provider answered with, if it answered at all
+        status: Option<u16>,
+        message: String,
+    },
+}
+
+impl AgentError {
+    /// A failed request to the provider named `provider`
+    pub fn provider_failure(provider: impl Into<String>, error: &ProviderError) -> Self {
+        // The status is shown on its own, so it isn't repeated in the message
+        let message = match error {
+            ProviderError::Status { message, .. } => message.clone(),
+            error => error.to_string(),
+        };
+        AgentError::ProviderFailure {
+            provider: provider.into(),
+            status: error.status(),
+            message,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_failure() {
+        let error = AgentError::provider_failure(
+            "openai",
+            &ProviderError::Status {
+                status: 502,
+                message: "Bad gateway".to_string(),
+            },
+        );
+        let AgentError::ProviderFailure {
+            provider, status, ..
+        } = &error;
+        assert_eq!(provider, "openai");
+        assert_eq!(*status, Some(502));
+        assert_eq!(
+            error.to_string(),
+            "openai request failed with status 502: Bad gateway"
+        );
+
+        // Errors raised before the provider answered have no status
+        let error = AgentError::provider_failure(
+            "openai",
+            &ProviderError::ExecutionError("connection reset".to_string()),
+        );
+        assert!(matches!(
+            error,
+            AgentError::ProviderFailure { status: None, .. }
+        ));
+    }
+}
@@ -28,8 +28,13 @@ pub enum ExtensionError {
     SetupError(String),
     #[error("Join error occurred during task execution: {0}")]
     TaskJoinError(#[from] tokio::task::JoinError),
+    #[error("Extension `{0}` may access the network, which is disabled for this session")]
+    NetworkDisabled(String),
 }
 
+/// Bundled builtins whose tools only work on the local machine
+const LOCAL_BUILTINS: &[&str] = &["developer", "memory", "jetbrains", "tutorial"];
+
 pub type ExtensionResult<T> = Result<T, ExtensionError>;
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default, ToSchema)]
@@ -139,6 +144,9 @@ pub enum ExtensionConfig {
         /// Whether this extension is bundled with Goose
         #[serde(default)]
         bundled: Option<bool>,
+        /// Whether the tools of this extension access the network
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        network: Option<bool>,
     },
     /// Standard I/O client with command and arguments
     #[serde(rename = "stdio")]
@@ -156,6 +164,9 @@ pub enum ExtensionConfig {
         /// Whether this extension is bundled with Goose
         #[serde(default)]
         bundled: Option<bool>,
+        /// Whether the tools of this extension access the network
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        network: Option<bool>,
     },
     /// Built-in extension that is part of the goose binary
     #[serde(rename = "builtin")]
@@ -167,6 +178,9 @@ pub enum ExtensionConfig {
         /// Whether this extension is bundled with Goose
         #[serde(default)]
         bundled: Option<bool>,
+        /// Whether the tools of this extension access the network
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        network: Option<bool>,
     },
     /// Frontend-provided tools that will be called through the frontend
     #[serde(rename = "frontend")]
@@ -180,6 +194,9 @@ pub enum ExtensionConfig {
         /// Whether this extension is bundled with Goose
         #[serde(default)]
         bundled: Option<bool>,
+        /// Whether the tools of this extension access the network
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        network: Option<bool>,
     },
 }
 
@@ -190,6 +207,7 @@ impl Default for ExtensionConfig {
             display_name: Some(config::DEFAULT_DISPLAY_NAME.to_string()),
             timeout: Some(config::DEFAULT_EXTENSION_TIMEOUT),
             bundled: Some(true),
+            network: None,
         }
     }
 }
@@ -204,6 +222,7 @@ impl ExtensionConfig {
             description: Some(description.into()),
             timeout: Some(timeout.into()),
             bundled: None,
+            network: None,
         }
     }
 
@@ -222,6 +241,7 @@ impl ExtensionConfig {
             description: Some(description.into()),
             timeout: Some(timeout.into()),
             bundled: None,
+            network: None,
         }
     }
 
@@ -239,6 +259,7 @@ impl ExtensionConfig {
                 timeout,
                 description,
                 bundled,
+                network,
                 ..
             } => Self::Stdio {
                 name,
@@ -249,11 +270,37 @@ impl ExtensionConfig {
                 description,
                 timeout,
                 bundled,
+                network,
             },
             other => other,
         }
     }
 
+    /// Declare whether the tools of this extension access the network
+    pub fn with_network(mut self, network_access: bool) -> Self {
+        match &mut self {
+            Self::Sse { network, .. }
+            | Self::Stdio { network, .. }
+            | Self::Builtin { network, .. }
+            | Self::Frontend { network, .. } => *network = Some(network_access),
+        }
+        self
+    }
+
+    /// Whether the tools of this extension may access the network
+    ///
+    /// Extensions that don't declare it are assumed to, except the bundled builtins that only
+    /// work locally. The developer shell is checked command by command instead.
+    pub fn network_access(&self) -> bool {
+        let (declared, builtin) = match self {
+            Self::Sse { network, .. } | Self::Stdio { network, .. } => (network, None),
+            Self::Builtin { name, network, .. } => (network, Some(name)),
+            Self::Frontend { network, .. } => (network, None),
+        };
+        declared
+            .unwrap_or_else(|| !builtin.is_some_and(|name| LOCAL_BUILTINS.contains(&name.as_str())))
+    }
+
     pub fn key(&self) -> String {
         let name = self.name();
         name_to_key(&name)
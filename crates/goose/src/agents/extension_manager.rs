@@ -6,14 +6,15 @@ use mcp_core::protocol::GetPromptResult;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::sync::LazyLock;
-use std::time::Duration;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
 use tokio::task;
 use tokio_stream::wrappers::ReceiverStream;
 use tracing::{error, warn};
 
 use super::extension::{ExtensionConfig, ExtensionError, ExtensionInfo, ExtensionResult, ToolInfo};
 use super::tool_execution::ToolCallResult;
+use super::tool_limits::{limit_output, ToolLimits};
 use crate::agents::extension::Envs;
 use crate::config::{Config, ExtensionConfigManager};
 use crate::prompt_template;
@@ -27,13 +28,23 @@ use serde_json::Value;
 static DEFAULT_TIMESTAMP: LazyLock<DateTime<Utc>> =
     LazyLock::new(|| Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap());
 
-type McpClientBox = Arc<Mutex<Box<dyn McpClientTrait>>>;
+type McpClientBox = Arc<dyn McpClientTrait>;
+
+/// The limits of an extension, with the semaphore that bounds its concurrent calls
+#[derive(Clone, Default)]
+struct ExtensionLimits {
+    limits: ToolLimits,
+    calls: Option<Arc<Semaphore>>,
+}
 
 /// Manages Goose extensions / MCP clients and their interactions
 pub struct ExtensionManager {
     clients: HashMap<String, McpClientBox>,
     instructions: HashMap<String, String>,
     resource_capable_extensions: HashSet<String>,
+    limits: HashMap<String, ExtensionLimits>,
+    /// Extensions that declared their tools don't access the network
+    local_extensions: HashSet<String>,
 }
 
 /// A flattened representation of a resource used by the agent to prepare inference
@@ -104,6 +115,8 @@ impl ExtensionManager {
             clients: HashMap::new(),
             instructions: HashMap::new(),
             resource_capable_extensions: HashSet::new(),
+            limits: HashMap::new(),
+            local_extensions: HashSet::new(),
         }
     }
 
@@ -174,6 +187,15 @@ impl ExtensionManager {
             Ok(all_envs)
         }
 
+        // The timeout an extension is configured with also bounds each of its tool calls
+        let declared_limits = match &config {
+            ExtensionConfig::Sse { timeout, .. }
+            | ExtensionConfig::Stdio { timeout, .. }
+            | ExtensionConfig::Builtin { timeout, .. } => ToolLimits::new()
+                .with_timeout(timeout.unwrap_or(crate::config::DEFAULT_EXTENSION_TIMEOUT)),
+            ExtensionConfig::Frontend { .. } => ToolLimits::new(),
+        };
+
         let mut client: Box<dyn McpClientTrait> = match &config {
             ExtensionConfig::Sse {
                 uri,
@@ -220,7 +242,7 @@ impl ExtensionManager {
                 name,
                 display_name: _,
                 timeout,
-                bundled: _,
+                ..
             } => {
                 let cmd = std::env::current_exe()
                     .expect("should find the current executable")
@@ -269,11 +291,63 @@ impl ExtensionManager {
         }
 
         self.clients
-            .insert(sanitized_name.clone(), Arc::new(Mutex::new(client)));
+            .insert(sanitized_name.clone(), Arc::from(client));
+        self.set_tool_limits(&sanitized_name, declared_limits);
+        self.set_network_access(&sanitized_name, config.network_access());
 
         Ok(())
     }
 
+    /// Add an initialized client, such as one running in process, with the limits it declares
+    pub fn add_client(&mut self, name: &str, client: Box<dyn McpClientTrait>, limits: ToolLimits) {
+        let sanitized_name = normalize(name.to_string());
+        self.clients
+            .insert(sanitized_name.clone(), Arc::from(client));
+        self.set_tool_limits(&sanitized_name, limits);
+    }
+
+    /// Set the limits an extension declares, the ones the user configured take precedence
+    pub fn set_tool_limits(&mut self, name: &str, declared: ToolLimits) {
+        let sanitized_name = normalize(name.to_string());
+        let limits = match ToolLimits::configured(&sanitized_name) {
+            Some(configured) => declared.overridden_by(configured),
+            None => declared,
+        };
+        let calls = limits
+            .max_concurrent_calls
+            .map(|calls| Arc::new(Semaphore::new(calls.max(1))));
+        self.limits
+            .insert(sanitized_name, ExtensionLimits { limits, calls });
+    }
+
+    /// The limits that apply to the tool calls of an extension
+    pub fn tool_limits(&self, name: &str) -> ToolLimits {
+        self.limits
+            .get(&normalize(name.to_string()))
+            .map(|extension| extension.limits.clone())
+            .unwrap_or_default()
+    }
+
+    /// Declare whether the tools of an extension access the network. Extensions added as
+    /// clients are assumed to until they declare otherwise.
+    pub fn set_network_access(&mut self, name: &str, network_access: bool) {
+        let sanitized_name = normalize(name.to_string());
+        if network_access {
+            self.local_extensions.remove(&sanitized_name);
+        } else {
+            self.local_extensions.insert(sanitized_name);
+        }
+    }
+
+    /// Names of the extensions whose tools may access the network
+    pub fn network_extensions(&self) -> Vec<String> {
+        self.clients
+            .keys()
+            .filter(|name| !self.local_extensions.contains(*name))
+            .cloned()
+            .collect()
+    }
+
     /// Get extensions info
     pub async fn get_extensions_info(&self) -> Vec<ExtensionInfo> {
         self.clients
@@ -293,6 +367,8 @@ impl ExtensionManager {
         self.clients.remove(&sanitized_name);
         self.instructions.remove(&sanitized_name);
         self.resource_capable_extensions.remove(&sanitized_name);
+        self.limits.remove(&sanitized_name);
+        self.local_extensions.remove(&sanitized_name);
         Ok(())
     }
 
@@ -352,8 +428,7 @@ impl ExtensionManager {
 
             task::spawn(async move {
                 let mut tools = Vec::new();
-                let client_guard = client.lock().await;
-                let mut client_tools = client_guard.list_tools(None).await?;
+                let mut client_tools = client.list_tools(None).await?;
 
                 loop {
                     for tool in client_tools.tools {
@@ -370,7 +445,7 @@ impl ExtensionManager {
                         break;
                     }
 
-                    client_tools = client_guard.list_tools(client_tools.next_cursor).await?;
+                    client_tools = client.list_tools(client_tools.next_cursor).await?;
                 }
 
                 Ok::<Vec<Tool>, ExtensionError>(tools)
@@ -398,8 +473,7 @@ impl ExtensionManager {
         let mut result: Vec<ResourceItem> = Vec::new();
 
         for (name, client) in &self.clients {
-            let client_guard = client.lock().await;
-            let resources = client_guard.list_resources(None).await?;
+            let resources = client.list_resources(None).await?;
 
             for resource in resources.resources {
                 // Skip reading the resource if it's not marked active
@@ -408,7 +482,7 @@ impl ExtensionManager {
                     continue;
                 }
 
-                if let Ok(contents) = client_guard.read_resource(&resource.uri).await {
+                if let Ok(contents) = client.read_resource(&resource.uri).await {
                     for content in contents.contents {
                         let (uri, content_str) = match content {
                             mcp_core::resource::ResourceContents::TextResourceContents {
@@ -454,6 +528,12 @@ impl ExtensionManager {
             .map(|(name, client)| (name.as_str(), Arc::clone(client)))
     }
 
+    /// The name of the extension that handles a tool
+    pub fn extension_for_tool(&self, prefixed_name: &str) -> Option<String> {
+        self.get_client_for_tool(prefixed_name)
+            .map(|(name, _)| name.to_string())
+    }
+
     // Function that gets executed for read_resource tool
     pub async fn read_resource(&self, params: Value) -> Result<Vec<Content>, ToolError> {
         let uri = params
@@ -519,8 +599,7 @@ impl ExtensionManager {
             .get(extension_name)
             .ok_or(ToolError::InvalidParameters(error_msg))?;
 
-        let client_guard = client.lock().await;
-        let read_result = client_guard.read_resource(uri).await.map_err(|_| {
+        let read_result = client.read_resource(uri).await.map_err(|_| {
             ToolError::ExecutionError(format!("Could not read resource with uri: {}", uri))
         })?;
 
@@ -545,8 +624,7 @@ impl ExtensionManager {
             ToolError::InvalidParameters(format!("Extension {} is not valid", extension_name))
         })?;
 
-        let client_guard = client.lock().await;
-        client_guard
+        client
             .list_resources(None)
             .await
             .map_err(|e| {
@@ -633,15 +711,45 @@ impl ExtensionManager {
 
         let arguments = tool_call.arguments.clone();
         let client = client.clone();
-        let notifications_receiver = client.lock().await.subscribe().await;
+        let notifications_receiver = client.subscribe().await;
+
+        let ExtensionLimits { limits, calls } =
+            self.limits.get(client_name).cloned().unwrap_or_default();
+        let timeout = limits.timeout_for(&tool_name);
+        let prefixed_name = tool_call.name;
 
         let fut = async move {
-            let client_guard = client.lock().await;
-            client_guard
-                .call_tool(&tool_name, arguments)
-                .await
-                .map(|call| call.content)
-                .map_err(|e| ToolError::ExecutionError(e.to_string()))
+            // Waiting for a free slot doesn't count towards the timeout, only the call itself
+            let _permit = match &calls {
+                Some(calls) => calls.acquire().await.ok(),
+                None => None,
+            };
+            let start = Instant::now();
+            let call = async {
+                client
+                    .call_tool(&tool_name, arguments)
+                    .await
+                    .map(|call| call.content)
+                    .map_err(|e| ToolError::ExecutionError(e.to_string()))
+            };
+
+            let result = match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, call)
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(ToolError::Timeout(format!(
+                            "{} was cancelled after {:.1}s, as it did not finish within its {}s limit. It may succeed if retried.",
+                            prefixed_name,
+                            start.elapsed().as_secs_f64(),
+                            timeout.as_secs()
+                        )))
+                    }),
+                None => call.await,
+            };
+            match limits.max_output_bytes {
+                Some(max_bytes) => result.map(|contents| limit_output(contents, max_bytes)),
+                None => result,
+            }
         };
 
         Ok(ToolCallResult {
@@ -658,8 +766,7 @@ impl ExtensionManager {
             ToolError::InvalidParameters(format!("Extension {} is not valid", extension_name))
         })?;
 
-        let client_guard = client.lock().await;
-        client_guard
+        client
             .list_prompts(None)
             .await
             .map_err(|e| {
@@ -724,8 +831,7 @@ impl ExtensionManager {
             .get(extension_name)
             .ok_or_else(|| anyhow::anyhow!("Extension {} not found", extension_name))?;
 
-        let client_guard = client.lock().await;
-        client_guard
+        client
             .get_prompt(name, arguments)
             .await
             .map_err(|e| anyhow::anyhow!("Failed to get prompt: {}", e))
@@ -875,23 +981,21 @@ mod tests {
         // Add some mock clients
         extension_manager.clients.insert(
             normalize("test_client".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient {}),
         );
 
-        extension_manager.clients.insert(
-            normalize("__client".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
-        );
+        extension_manager
+            .clients
+            .insert(normalize("__client".to_string()), Arc::new(MockClient {}));
 
         extension_manager.clients.insert(
             normalize("__cli__ent__".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient {}),
         );
 
-        extension_manager.clients.insert(
-            normalize("client 🚀".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
-        );
+        extension_manager
+            .clients
+            .insert(normalize("client 🚀".to_string()), Arc::new(MockClient {}));
 
         // Test basic case
         assert!(extension_manager
@@ -923,18 +1027,17 @@ mod tests {
         // Add some mock clients
         extension_manager.clients.insert(
             normalize("test_client".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient {}),
         );
 
         extension_manager.clients.insert(
             normalize("__cli__ent__".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient {}),
         );
 
-        extension_manager.clients.insert(
-            normalize("client 🚀".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
-        );
+        extension_manager
+            .clients
+            .insert(normalize("client 🚀".to_string()), Arc::new(MockClient {}));
 
         // verify a normal tool call
         let tool_call = ToolCall {
@@ -1013,4 +1116,148 @@ mod tests {
             panic!("Expected ToolError::NotFound");
         }
     }
+
+    /// Answers every tool call with `output` after `delay`
+    struct SlowClient {
+        delay: Duration,
+        output: String,
+    }
+
+    #[async_trait::async_trait]
+    impl McpClientTrait for SlowClient {
+        async fn initialize(
+            &mut self,
+            _info: ClientInfo,
+            _capabilities: ClientCapabilities,
+        ) -> Result<InitializeResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn list_resources(
+            &self,
+            _next_cursor: Option<String>,
+        ) -> Result<ListResourcesResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn read_resource(&self, _uri: &str) -> Result<ReadResourceResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn list_tools(&self, _next_cursor: Option<String>) -> Result<ListToolsResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn call_tool(&self, _name: &str, _arguments: Value) -> Result<CallToolResult, Error> {
+            tokio::time::sleep(self.delay).await;
+            Ok(CallToolResult {
+                content: vec![Content::text(self.output.clone())],
+                is_error: None,
+            })
+        }
+
+        async fn list_prompts(
+            &self,
+            _next_cursor: Option<String>,
+        ) -> Result<ListPromptsResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn get_prompt(
+            &self,
+            _name: &str,
+            _arguments: Value,
+        ) -> Result<GetPromptResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn subscribe(&self) -> mpsc::Receiver<JsonRpcMessage> {
+            mpsc::channel(1).1
+        }
+    }
+
+    async fn call(
+        extension_manager: &ExtensionManager,
+        name: &str,
+    ) -> Result<Vec<Content>, ToolError> {
+        let tool_call = ToolCall::new(name, json!({}));
+        extension_manager
+            .dispatch_tool_call(tool_call)
+            .await
+            .unwrap()
+            .result
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_tool_call_limits() {
+        let mut extension_manager = ExtensionManager::new();
+        extension_manager.add_client(
+            "slow",
+            Box::new(SlowClient {
+                delay: Duration::from_millis(200),
+                output: "0123456789".to_string(),
+            }),
+            ToolLimits::new()
+                .with_tool_timeout("hang", 0)
+                .with_max_output_bytes(4),
+        );
+
+        let result = call(&extension_manager, "slow__hang").await;
+        let Err(ToolError::Timeout(message)) = result else {
+            panic!("Expected a timeout, got {:?}", result);
+        };
+        assert!(message.starts_with("slow__hang was cancelled after 0."));
+
+        // Other tools of the extension have no timeout, and their output is truncated
+        let contents = call(&extension_manager, "slow__work").await.unwrap();
+        assert_eq!(
+            contents[0].as_text(),
+            Some("0123\n\n[Output truncated after 4 of 10 bytes]")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_calls_to_slow_extension() {
+        let slow_client = || {
+            Box::new(SlowClient {
+                delay: Duration::from_millis(600),
+                output: "done".to_string(),
+            })
+        };
+
+        let mut extension_manager = ExtensionManager::new();
+        extension_manager.add_client(
+            "slow",
+            slow_client(),
+            ToolLimits::new()
+                .with_timeout(1)
+                .with_max_concurrent_calls(2),
+        );
+        let start = Instant::now();
+        let (first, second) = tokio::join!(
+            call(&extension_manager, "slow__first"),
+            call(&extension_manager, "slow__second")
+        );
+        assert!(first.is_ok() && second.is_ok());
+        // The calls ran at once rather than one after the other
+        assert!(start.elapsed() < Duration::from_millis(1200));
+
+        // With one call at a time, waiting for the other call doesn't count towards the timeout
+        let mut extension_manager = ExtensionManager::new();
+        extension_manager.add_client(
+            "slow",
+            slow_client(),
+            ToolLimits::new()
+                .with_timeout(1)
+                .with_max_concurrent_calls(1),
+        );
+        let start = Instant::now();
+        let (first, second) = tokio::join!(
+            call(&extension_manager, "slow__first"),
+            call(&extension_manager, "slow__second")
+        );
+        assert!(first.is_ok() && second.is_ok());
+        assert!(start.elapsed() >= Duration::from_millis(1200));
+    }
 }
@@ -0,0 +1,128 @@
+use async_trait::async_trait;
+use mcp_core::tool::ToolCall;
+use mcp_core::ToolError;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::message::Message;
+
+use super::Agent;
+
+/// The outcome of checking a tool call against a guardrail
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuardrailDecision {
+    /// Let the tool call run unchanged
+    Allow,
+    /// Refuse the tool call, the reason is returned to the model as a tool error
+    Block { reason: String },
+    /// Run this call instead of the one the model requested
+    Rewrite { call: ToolCall },
+}
+
+/// Validation hooks run by the agent around provider requests and tool calls.
+///
+/// Guardrails are registered with [`Agent::add_guardrail`] and run in the order they were
+/// added. Every hook has a default that lets everything through, so a guardrail only needs
+/// to implement the checks it cares about.
+#[async_trait]
+pub trait Guardrail: Send + Sync {
+    /// A short name used to attribute blocks and rewrites
+    fn name(&self) -> &str;
+
+    /// Inspect or modify the messages about to be sent to the provider. Changes only apply
+    /// to this request and are not stored in the conversation.
+    async fn before_provider_request(&self, _messages: &mut Vec<Message>) {}
+
+    /// Decide whether a tool call may run
+    async fn before_tool_dispatch(&self, _tool_call: &ToolCall) -> GuardrailDecision {
+        GuardrailDecision::Allow
+    }
+
+    /// Return a replacement for a model response, or None to keep it. A replacement takes
+    /// the place of the original in the conversation history.
+    async fn after_model_response(&self, _response: &Message) -> Option<Message> {
+        None
+    }
+}
+
+/// Provenance of a model response that was replaced by a guardrail
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct GuardrailRewrite {
+    /// Name of the guardrail that replaced the response
+    pub guardrail: String,
+    /// The response as the model returned it
+    pub original: Message,
+}
+
+impl Agent {
+    /// Register a guardrail, which runs after any guardrails added before it
+    pub async fn add_guardrail(&self, guardrail: std::sync::Arc<dyn Guardrail>) {
+        self.guardrails.lock().await.push(guardrail);
+    }
+
+    /// Build the messages for a provider request after every guardrail had a chance to modify them
+    pub(super) async fn guard_provider_request(&self, messages: &[Message]) -> Vec<Message> {
+        let guardrails = self.guardrails.lock().await.clone();
+        let mut payload_messages = messages.to_vec();
+        for guardrail in guardrails {
+            guardrail
+                .before_provider_request(&mut payload_messages)
+                .await;
+        }
+        payload_messages
+    }
+
+    /// Apply response rewrites, returning the final response and the provenance of each rewrite
+    pub(super) async fn guard_model_response(
+        &self,
+        mut response: Message,
+    ) -> (Message, Vec<GuardrailRewrite>) {
+        let guardrails = self.guardrails.lock().await.clone();
+        let mut rewrites = Vec::new();
+        for guardrail in guardrails {
+            if let Some(rewritten) = guardrail.after_model_response(&response).await {
+                tracing::info!("Guardrail '{}' rewrote a model response", guardrail.name());
+                rewrites.push(GuardrailRewrite {
+                    guardrail: guardrail.name().to_string(),
+                    original: std::mem::replace(&mut response, rewritten),
+                });
+            }
+        }
+        (response, rewrites)
+    }
+
+    /// Check a tool call against every guardrail, returning the call to run or the block reason
+    pub(super) async fn guard_tool_call(
+        &self,
+        mut tool_call: ToolCall,
+    ) -> Result<ToolCall, ToolError> {
+        let guardrails = self.guardrails.lock().await.clone();
+        for guardrail in guardrails {
+            match guardrail.before_tool_dispatch(&tool_call).await {
+                GuardrailDecision::Allow => {}
+                GuardrailDecision::Block { reason } => {
+                    tracing::info!(
+                        "Guardrail '{}' blocked a call to {}: {}",
+                        guardrail.name(),
+                        tool_call.name,
+                        reason
+                    );
+                    return Err(ToolError::ExecutionError(format!(
+                        "Tool call blocked by guardrail '{}': {}",
+                        guardrail.name(),
+                        reason
+                    )));
+                }
+                GuardrailDecision::Rewrite { call } => {
+                    tracing::info!(
+                        "Guardrail '{}' rewrote a call to {}",
+                        guardrail.name(),
+                        tool_call.name
+                    );
+                    tool_call = call;
+                }
+            }
+        }
+        Ok(tool_call)
+    }
+}
@@ -1,31 +1,54 @@
 mod agent;
+mod approval;
 mod context;
+mod errors;
 pub mod extension;
 pub mod extension_manager;
-mod large_response_handler;
+mod guardrail;
+pub mod network_policy;
+mod oversized_message;
 pub mod platform_tools;
 pub mod prompt_manager;
+mod provider_switch;
 mod recipe_tools;
 mod reply_parts;
+mod result_pager;
 mod router_tool_selector;
 mod router_tools;
 mod schedule_tool;
+mod soft_stop;
 pub mod sub_recipe_manager;
 pub mod subagent;
 pub mod subagent_handler;
 pub mod subagent_manager;
 pub mod subagent_tools;
 pub mod subagent_types;
+mod tool_allowlist;
+pub mod tool_descriptor;
 mod tool_execution;
+pub mod tool_limits;
 mod tool_router_index_manager;
 pub(crate) mod tool_vectordb;
+pub mod turn_journal;
 mod types;
+pub mod workspace_context;
 
 pub use agent::{Agent, AgentEvent};
+pub use approval::{ApprovalMonitor, ApprovalOutcome, DefaultDecision, APPROVAL_TIMED_OUT_REASON};
+pub use errors::AgentError;
 pub use extension::ExtensionConfig;
 pub use extension_manager::ExtensionManager;
+pub use guardrail::{Guardrail, GuardrailDecision, GuardrailRewrite};
+pub use network_policy::NoNetworkGuardrail;
+pub use oversized_message::OversizedMessage;
 pub use prompt_manager::PromptManager;
+pub use provider_switch::ProviderSwitch;
 pub use subagent::{SubAgent, SubAgentConfig, SubAgentProgress, SubAgentStatus};
 pub use subagent_manager::SubAgentManager;
 pub use subagent_types::SpawnSubAgentArgs;
+pub use tool_allowlist::ToolAllowlistGuardrail;
+pub use tool_descriptor::ToolDescriptor;
+pub use tool_limits::ToolLimits;
+pub use turn_journal::{recover_interrupted_turn, RecoveredTurn, TurnJournal};
 pub use types::{FrontendTool, SessionConfig};
+pub use workspace_context::WorkspaceContextConfig;
@@ -0,0 +1,193 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use mcp_core::tool::{Tool, ToolCall};
+
+use super::extension::ExtensionError;
+use super::guardrail::{Guardrail, GuardrailDecision};
+use super::Agent;
+use crate::permission::command_risk::{network_programs, shell_command};
+
+/// Told to the model when network tools are disabled, so it doesn't keep trying them
+pub const NO_NETWORK_INSTRUCTION: &str = "Network access is disabled for this session. \
+Tools that reach the network are not available, and shell commands such as curl, wget, nc or \
+ssh are refused. Work with what is on this machine, and tell the user when a task needs the \
+network.";
+
+/// Whether a tool declares that it reaches the network with its open world hint
+///
+/// Tools without annotations are judged by their extension.
+pub fn is_network_tool(tool: &Tool) -> bool {
+    tool.annotations
+        .as_ref()
+        .is_some_and(|annotations| annotations.open_world_hint)
+}
+
+/// Refuses shell commands that run a program reaching the network
+pub struct NoNetworkGuardrail;
+
+#[async_trait]
+impl Guardrail for NoNetworkGuardrail {
+    fn name(&self) -> &str {
+        "no_network"
+    }
+
+    async fn before_tool_dispatch(&self, tool_call: &ToolCall) -> GuardrailDecision {
+        let Some(command) = shell_command(tool_call) else {
+            return GuardrailDecision::Allow;
+        };
+        let programs = network_programs(command);
+        if programs.is_empty() {
+            return GuardrailDecision::Allow;
+        }
+        GuardrailDecision::Block {
+            reason: format!(
+                "policy violation, network access is disabled and the command runs `{}`",
+                programs.join("`, `")
+            ),
+        }
+    }
+}
+
+impl Agent {
+    /// Keep the tools of this agent from reaching the network
+    ///
+    /// Extensions that may access the network are removed and refused from now on, tools that
+    /// declare it are hidden from the model, and shell commands that reach the network fail.
+    pub async fn disable_network_tools(&self) {
+        {
+            let mut disabled = self.network_tools_disabled.lock().await;
+            if *disabled {
+                return;
+            }
+            *disabled = true;
+        }
+
+        let mut extension_manager = self.extension_manager.write().await;
+        for name in extension_manager.network_extensions() {
+            tracing::info!("Removing extension {} as network access is disabled", name);
+            if let Err(e) = extension_manager.remove_extension(&name).await {
+                tracing::warn!("Failed to remove extension {}: {}", name, e);
+            }
+        }
+        drop(extension_manager);
+        self.frontend_tools.lock().await.clear();
+
+        self.add_guardrail(Arc::new(NoNetworkGuardrail)).await;
+        self.extend_system_prompt(NO_NETWORK_INSTRUCTION.to_string())
+            .await;
+    }
+
+    pub async fn network_tools_disabled(&self) -> bool {
+        *self.network_tools_disabled.lock().await
+    }
+
+    /// Refuse an extension that may access the network while network tools are disabled
+    pub(super) async fn check_network_access(
+        &self,
+        extension: &super::ExtensionConfig,
+    ) -> Result<(), ExtensionError> {
+        if extension.network_access() && self.network_tools_disabled().await {
+            return Err(ExtensionError::NetworkDisabled(extension.name()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::ExtensionConfig;
+    use mcp_core::tool::ToolAnnotations;
+    use serde_json::json;
+
+    fn shell(command: &str) -> ToolCall {
+        ToolCall::new("developer__shell", json!({ "command": command }))
+    }
+
+    #[tokio::test]
+    async fn test_no_network_guardrail() {
+        let guardrail = NoNetworkGuardrail;
+        assert_eq!(
+            guardrail.before_tool_dispatch(&shell("cargo build")).await,
+            GuardrailDecision::Allow
+        );
+        assert_eq!(
+            guardrail
+                .before_tool_dispatch(&shell("tar cz . | ssh host 'cat > backup.tgz'"))
+                .await,
+            GuardrailDecision::Block {
+                reason: "policy violation, network access is disabled and the command runs `ssh`"
+                    .to_string()
+            }
+        );
+        let other = ToolCall::new("memory__remember", json!({ "command": "curl" }));
+        assert_eq!(
+            guardrail.before_tool_dispatch(&other).await,
+            GuardrailDecision::Allow
+        );
+    }
+
+    #[test]
+    fn test_network_declarations() {
+        let developer = ExtensionConfig::Builtin {
+            name: "developer".to_string(),
+            display_name: None,
+            timeout: None,
+            bundled: Some(true),
+            network: None,
+        };
+        assert!(!developer.network_access());
+        assert!(developer.clone().with_network(true).network_access());
+
+        let stdio = ExtensionConfig::stdio("fetch", "uvx", "Fetches pages", 300u64);
+        assert!(stdio.network_access());
+        assert!(!stdio.with_network(false).network_access());
+
+        let tool = |annotations| Tool::new("scrape", "Scrape a page", json!({}), annotations);
+        assert!(!is_network_tool(&tool(None)));
+        assert!(is_network_tool(&tool(Some(ToolAnnotations::default()))));
+        assert!(!is_network_tool(&tool(Some(
+            ToolAnnotations::default().with_open_world(false)
+        ))));
+    }
+
+    #[tokio::test]
+    async fn test_agent_refuses_network_extensions() {
+        let agent = Agent::new();
+        agent.disable_network_tools().await;
+        assert!(agent.network_tools_disabled().await);
+
+        let result = agent
+            .add_extension(ExtensionConfig::sse(
+                "search",
+                "http://localhost:1/sse",
+                "Searches the web",
+                5u64,
+            ))
+            .await;
+        assert!(matches!(result, Err(ExtensionError::NetworkDisabled(name)) if name == "search"));
+
+        let frontend = ExtensionConfig::Frontend {
+            name: "browser".to_string(),
+            tools: vec![Tool::new("open_url", "Open a page", json!({}), None)],
+            instructions: None,
+            bundled: None,
+            network: None,
+        };
+        assert!(agent.add_extension(frontend.clone()).await.is_err());
+        assert!(agent
+            .add_extension(frontend.with_network(false))
+            .await
+            .is_ok());
+
+        let prompt = agent.prompt_manager.lock().await.build_system_prompt(
+            vec![],
+            None,
+            serde_json::Value::Null,
+            None,
+            None,
+        );
+        assert!(prompt.contains("Network access is disabled"));
+    }
+}
@@ -0,0 +1,287 @@
+//! A user message too large for the context of the model on its own
+//!
+//! Compaction removes older messages, which does not help when the newest message alone fills
+//! the context, such as a pasted file. Before the first provider call of a reply, a user
+//! message above `max_message_fraction` of the context limit is rejected with its size and the
+//! limit. With `spill_oversized_messages` its text is stored with the tool results instead, and
+//! replaced by a note the model can page through with `platform__read_result`.
+
+use anyhow::Result;
+use mcp_core::role::Role;
+
+use super::platform_tools::PLATFORM_READ_RESULT_TOOL_NAME;
+use super::Agent;
+use crate::context_mgmt::compaction::{MessageTokenCounter, TokenEstimator};
+use crate::message::{Message, MessageContent};
+
+/// How much of a spilled message the note that replaces it shows, in bytes
+const PREVIEW_BYTES: usize = 2_000;
+
+/// A user message that alone takes more than its share of the context, and what the agent
+/// did about it. Token counts are estimates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OversizedMessage {
+    pub tokens: usize,
+    /// The most tokens a single message may take
+    pub limit: usize,
+    /// The note that replaced the message once its text was stored, none when it was rejected
+    pub replacement: Option<Message>,
+}
+
+impl OversizedMessage {
+    /// A line for the user about the message and what was done with it
+    pub fn summary(&self) -> String {
+        let size = format!(
+            "The message is about {} tokens, more than the {} tokens a single message may take",
+            self.tokens, self.limit
+        );
+        match self.replacement {
+            Some(_) => format!("{}. It was stored for goose to read in pages.", size),
+            None => format!(
+                "{}. Shorten it, or set GOOSE_SPILL_OVERSIZED_MESSAGES to let goose read it in pages.",
+                size
+            ),
+        }
+    }
+}
+
+impl Agent {
+    /// Check the newest message before it is sent, when it is from the user
+    pub(super) async fn check_message_size(
+        &self,
+        messages: &[Message],
+    ) -> Result<Option<OversizedMessage>> {
+        let Some(message) = messages.last().filter(|message| message.role == Role::User) else {
+            return Ok(None);
+        };
+        let model_config = self.provider().await?.get_model_config();
+        let config = self.compaction.lock().await.clone();
+        let limit = (model_config.context_limit() as f32 * config.max_message_fraction) as usize;
+        let tokens = TokenEstimator::new(model_config.tokenizer_family()).count_message(message);
+        if tokens <= limit {
+            return Ok(None);
+        }
+
+        let replacement = if config.spill_oversized_messages {
+            self.spill_message(message, tokens).await
+        } else {
+            None
+        };
+        Ok(Some(OversizedMessage {
+            tokens,
+            limit,
+            replacement,
+        }))
+    }
+
+    /// Store the text of the message, returning the message with a note of where it is instead.
+    /// A message without text, or one that can't be stored, is rejected.
+    async fn spill_message(&self, message: &Message, tokens: usize) -> Option<Message> {
+        let text = message
+            .content
+            .iter()
+            .filter_map(MessageContent::as_text)
+            .collect::<Vec<_>>()
+            .join("\n");
+        if text.is_empty() {
+            return None;
+        }
+        let id = match self.result_pager.lock().await.store(&text) {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::warn!("Failed to store an oversized message: {}", e);
+                return None;
+            }
+        };
+
+        let mut end = PREVIEW_BYTES.min(text.len());
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        let note = format!(
+            "[The user sent a message of about {} tokens, too large to include whole. It is stored as result {}, call {} with id \"{}\" and page 1 to read it. It begins:]\n\n{}",
+            tokens,
+            id,
+            PLATFORM_READ_RESULT_TOOL_NAME,
+            id,
+            &text[..end]
+        );
+        let mut replacement = message.clone();
+        replacement
+            .content
+            .retain(|content| !matches!(content, MessageContent::Text(_)));
+        replacement.content.insert(0, MessageContent::text(note));
+        Some(replacement)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::AgentEvent;
+    use crate::context_mgmt::compaction::CompactionConfig;
+    use crate::model::ModelConfig;
+    use crate::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+    use crate::providers::errors::ProviderError;
+    use futures::StreamExt;
+    use mcp_core::tool::Tool;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+    use tempfile::tempdir;
+
+    /// A model with a small context, which records the messages of each request
+    struct SmallContextProvider {
+        context_limit: usize,
+        requests: Mutex<Vec<Vec<Message>>>,
+    }
+
+    impl SmallContextProvider {
+        fn new(context_limit: usize) -> Arc<Self> {
+            Arc::new(Self {
+                context_limit,
+                requests: Mutex::new(Vec::new()),
+            })
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for SmallContextProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("gpt-4o".to_string()).with_context_limit(Some(self.context_limit))
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            self.requests.lock().unwrap().push(messages.to_vec());
+            Ok((
+                Message::assistant().with_text("read it"),
+                ProviderUsage::new("gpt-4o".to_string(), Usage::default()),
+            ))
+        }
+    }
+
+    fn pasted_file() -> Message {
+        Message::user().with_text("fn main() {}\n".repeat(400))
+    }
+
+    fn tokens_of(message: &Message) -> usize {
+        TokenEstimator::new(ModelConfig::new("gpt-4o".to_string()).tokenizer_family())
+            .count_message(message)
+    }
+
+    async fn agent_with_context(
+        context_limit: usize,
+        spill: bool,
+    ) -> (Agent, Arc<SmallContextProvider>) {
+        let provider = SmallContextProvider::new(context_limit);
+        let agent = Agent::new();
+        agent.update_provider(provider.clone()).await.unwrap();
+        agent
+            .set_compaction(CompactionConfig {
+                max_message_fraction: 1.0,
+                spill_oversized_messages: spill,
+                ..CompactionConfig::default()
+            })
+            .await;
+        (agent, provider)
+    }
+
+    #[tokio::test]
+    async fn test_message_that_barely_fits() {
+        let messages = vec![pasted_file()];
+        let tokens = tokens_of(&messages[0]);
+
+        let (agent, _) = agent_with_context(tokens, false).await;
+        assert_eq!(agent.check_message_size(&messages).await.unwrap(), None);
+
+        let (agent, _) = agent_with_context(tokens - 1, false).await;
+        let oversized = agent.check_message_size(&messages).await.unwrap().unwrap();
+        assert_eq!(oversized.tokens, tokens);
+        assert_eq!(oversized.limit, tokens - 1);
+
+        // Only a message from the user is checked
+        let answer = vec![Message::assistant().with_text("fn main() {}\n".repeat(400))];
+        assert_eq!(agent.check_message_size(&answer).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_message_is_rejected_before_any_request() {
+        let (agent, provider) = agent_with_context(1_000, false).await;
+        let messages = vec![pasted_file()];
+        let events: Vec<AgentEvent> = agent
+            .reply(&messages, None)
+            .await
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+
+        assert!(provider.requests.lock().unwrap().is_empty());
+        let AgentEvent::OversizedMessage(oversized) = &events[0] else {
+            panic!("expected an oversized message event, got {:?}", events[0]);
+        };
+        assert_eq!(oversized.replacement, None);
+        assert_eq!(oversized.limit, 1_000);
+        assert!(oversized
+            .summary()
+            .ends_with("more than the 1000 tokens a single message may take. Shorten it, or set GOOSE_SPILL_OVERSIZED_MESSAGES to let goose read it in pages."));
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, AgentEvent::Message(_))));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_message_is_spilled() {
+        let (agent, provider) = agent_with_context(1_000, true).await;
+        let dir = tempdir().unwrap();
+        agent
+            .result_pager
+            .lock()
+            .await
+            .set_store_dir(dir.path().to_path_buf());
+        let messages = vec![pasted_file().with_image("aGVsbG8=", "image/png")];
+
+        let events: Vec<AgentEvent> = agent
+            .reply(&messages, None)
+            .await
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+        let AgentEvent::OversizedMessage(oversized) = &events[0] else {
+            panic!("expected an oversized message event, got {:?}", events[0]);
+        };
+        let replacement = oversized.replacement.clone().unwrap();
+
+        // The model got the note in place of the message, and the image with it
+        let requests = provider.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0], vec![replacement.clone()]);
+        assert!(matches!(replacement.content[1], MessageContent::Image(_)));
+        let note = replacement.content[0].as_text().unwrap();
+        assert!(note.starts_with("[The user sent a message of about"));
+        assert!(tokens_of(&replacement) <= 1_000);
+
+        // The model pages through the original
+        let id = note.split("stored as result ").nth(1).unwrap();
+        let id = &id[..id.find(',').unwrap()];
+        let page = agent
+            .result_pager
+            .lock()
+            .await
+            .read_result(&json!({"id": id, "page": 1}))
+            .unwrap();
+        assert!(page[0]
+            .as_text()
+            .unwrap()
+            .starts_with(pasted_file().as_concat_text().as_str()));
+    }
+}
@@ -8,6 +8,8 @@ pub const PLATFORM_SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME: &str =
     "platform__search_available_extensions";
 pub const PLATFORM_MANAGE_EXTENSIONS_TOOL_NAME: &str = "platform__manage_extensions";
 pub const PLATFORM_MANAGE_SCHEDULE_TOOL_NAME: &str = "platform__manage_schedule";
+pub const PLATFORM_READ_RESULT_TOOL_NAME: &str = "platform__read_result";
+pub const PLATFORM_REFRESH_CONTEXT_TOOL_NAME: &str = "platform__refresh_context";
 
 pub fn read_resource_tool() -> Tool {
     Tool::new(
@@ -158,3 +160,58 @@ pub fn manage_schedule_tool() -> Tool {
         }),
     )
 }
+
+pub fn read_result_tool() -> Tool {
+    Tool::new(
+        PLATFORM_READ_RESULT_TOOL_NAME.to_string(),
+        indoc! {r#"
+            Read a page of a tool result that was too large to return at once.
+
+            When a tool produces more output than fits in a single response, only the first
+            page is returned, followed by a note with the result id and the number of pages.
+            Use this tool with that id to read the remaining pages.
+        "#}
+        .to_string(),
+        json!({
+            "type": "object",
+            "required": ["id", "page"],
+            "properties": {
+                "id": {"type": "string", "description": "Result id from the note after the first page"},
+                "page": {"type": "integer", "minimum": 1, "description": "Page number, starting at 1"}
+            }
+        }),
+        Some(ToolAnnotations {
+            title: Some("Read a result page".to_string()),
+            read_only_hint: true,
+            destructive_hint: false,
+            idempotent_hint: true,
+            open_world_hint: false,
+        }),
+    )
+}
+
+pub fn refresh_context_tool() -> Tool {
+    Tool::new(
+        PLATFORM_REFRESH_CONTEXT_TOOL_NAME.to_string(),
+        indoc! {r#"
+            Collect the workspace context again and return it.
+
+            The workspace context in the system prompt is collected at the start of the session.
+            Use this tool after changes that make it outdated, such as switching branches or
+            creating many files, rather than running commands to look around.
+        "#}
+        .to_string(),
+        json!({
+            "type": "object",
+            "required": [],
+            "properties": {}
+        }),
+        Some(ToolAnnotations {
+            title: Some("Refresh the workspace context".to_string()),
+            read_only_hint: true,
+            destructive_hint: false,
+            idempotent_hint: true,
+            open_world_hint: false,
+        }),
+    )
+}
@@ -3,14 +3,17 @@ use serde_json::Value;
 use std::collections::HashMap;
 
 use crate::agents::extension::ExtensionInfo;
+use crate::agents::platform_tools::PLATFORM_REFRESH_CONTEXT_TOOL_NAME;
 use crate::agents::router_tool_selector::RouterToolSelectionStrategy;
 use crate::agents::router_tools::{llm_search_tool_prompt, vector_search_tool_prompt};
+use crate::prompt_template::SystemPrompt;
 use crate::providers::base::get_current_model;
 use crate::{config::Config, prompt_template};
 
 pub struct PromptManager {
     system_prompt_override: Option<String>,
     system_prompt_extras: Vec<String>,
+    workspace_context: Option<String>,
     current_date_timestamp: String,
 }
 
@@ -25,6 +28,7 @@ impl PromptManager {
         PromptManager {
             system_prompt_override: None,
             system_prompt_extras: Vec::new(),
+            workspace_context: None,
             // Use the fixed current date time so that prompt cache can be used.
             current_date_timestamp: Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
         }
@@ -40,6 +44,15 @@ impl PromptManager {
         self.system_prompt_override = Some(template);
     }
 
+    /// Set the summary of the workspace that follows the system prompt
+    pub fn set_workspace_context(&mut self, context: Option<String>) {
+        self.workspace_context = context;
+    }
+
+    pub fn has_workspace_context(&self) -> bool {
+        self.workspace_context.is_some()
+    }
+
     /// Normalize a model name (replace - and / with _, lower case)
     fn normalize_model_name(name: &str) -> String {
         name.replace(['-', '/', '.'], "_").to_lowercase()
@@ -71,6 +84,28 @@ impl PromptManager {
         model_name: Option<&str>,
         tool_selection_strategy: Option<RouterToolSelectionStrategy>,
     ) -> String {
+        self.build_system_prompt_blocks(
+            extensions_info,
+            frontend_instructions,
+            suggest_disable_extensions_prompt,
+            model_name,
+            tool_selection_strategy,
+        )
+        .render()
+    }
+
+    /// Build the final system prompt as blocks
+    ///
+    /// The base prompt and additional instructions are stable for the session, the mode and
+    /// the workspace context follow them as dynamic blocks.
+    pub fn build_system_prompt_blocks(
+        &self,
+        extensions_info: Vec<ExtensionInfo>,
+        frontend_instructions: Option<String>,
+        suggest_disable_extensions_prompt: Value,
+        model_name: Option<&str>,
+        tool_selection_strategy: Option<RouterToolSelectionStrategy>,
+    ) -> SystemPrompt {
         let mut context: HashMap<&str, Value> = HashMap::new();
         let mut extensions_info = extensions_info.clone();
 
@@ -136,28 +171,33 @@ impl PromptManager {
                 .expect("Prompt should render")
         };
 
-        let mut system_prompt_extras = self.system_prompt_extras.clone();
+        let mut system_prompt = SystemPrompt::new();
+        system_prompt.push_stable(base_prompt);
+        if !self.system_prompt_extras.is_empty() {
+            system_prompt.push_stable(format!(
+                "# Additional Instructions:\n\n{}",
+                prompt_template::compose_system_prompt(&self.system_prompt_extras, "\n\n")
+            ));
+        }
+
         let config = Config::global();
         let goose_mode = config.get_param("GOOSE_MODE").unwrap_or("auto".to_string());
         if goose_mode == "chat" {
-            system_prompt_extras.push(
-                "Right now you are in the chat only mode, no access to any tool use and system."
-                    .to_string(),
+            system_prompt.push_dynamic(
+                "Right now you are in the chat only mode, no access to any tool use and system.",
             );
         } else {
-            system_prompt_extras
-                .push("Right now you are *NOT* in the chat only mode and have access to tool use and system.".to_string());
+            system_prompt.push_dynamic("Right now you are *NOT* in the chat only mode and have access to tool use and system.");
         }
 
-        if system_prompt_extras.is_empty() {
-            base_prompt
-        } else {
-            format!(
-                "{}\n\n# Additional Instructions:\n\n{}",
-                base_prompt,
-                system_prompt_extras.join("\n\n")
-            )
+        if let Some(context) = &self.workspace_context {
+            system_prompt.push_dynamic(format!(
+                "# Workspace\n\nThis was collected at the start of the session, refresh it \
+                 with the {} tool when it is outdated.\n\n{}",
+                PLATFORM_REFRESH_CONTEXT_TOOL_NAME, context
+            ));
         }
+        system_prompt
     }
 
     pub async fn get_recipe_prompt(&self) -> String {
@@ -169,6 +209,7 @@ impl PromptManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::prompt_template::SystemBlockKind;
 
     #[test]
     fn test_normalize_model_name() {
@@ -221,4 +262,44 @@ mod tests {
             "system.md"
         );
     }
+
+    #[test]
+    fn test_workspace_context_follows_base_prompt() {
+        let mut manager = PromptManager::new();
+        manager.set_system_prompt_override("Base prompt".to_string());
+        manager.add_system_prompt_extra("Be brief".to_string());
+        manager.set_workspace_context(Some("Working directory: /repo".to_string()));
+
+        let prompt = manager.build_system_prompt(vec![], None, Value::Null, None, None);
+        let workspace = prompt.find("# Workspace").unwrap();
+        assert!(prompt.starts_with("Base prompt"));
+        assert!(prompt[workspace..].contains("Working directory: /repo"));
+        assert!(prompt.find("# Additional Instructions").unwrap() < workspace);
+
+        manager.set_workspace_context(None);
+        let prompt = manager.build_system_prompt(vec![], None, Value::Null, None, None);
+        assert!(!prompt.contains("# Workspace"));
+    }
+
+    #[test]
+    fn test_system_prompt_blocks_put_stable_first() {
+        let mut manager = PromptManager::new();
+        manager.set_system_prompt_override("Base prompt".to_string());
+        manager.add_system_prompt_extra("Be brief".to_string());
+        manager.set_workspace_context(Some("Working directory: /repo".to_string()));
+
+        let prompt = manager.build_system_prompt_blocks(vec![], None, Value::Null, None, None);
+        let kinds: Vec<_> = prompt.blocks().iter().map(|block| block.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                SystemBlockKind::Stable,
+                SystemBlockKind::Stable,
+                SystemBlockKind::Dynamic,
+                SystemBlockKind::Dynamic
+            ]
+        );
+        assert_eq!(prompt.blocks()[0].text, "Base prompt");
+        assert!(prompt.blocks()[3].text.contains("Working directory: /repo"));
+    }
 }
@@ -0,0 +1,264 @@
+//! Switching the provider or model of an agent in the middle of a conversation
+//!
+//! The history is kept, but what only the previous model accepts is removed: thinking blocks
+//! are signed for the model that wrote them, and response fields are kept per API format.
+//! Images go when the new model does not accept them, and a history longer than its context
+//! is compacted. Usage stays attributed to the model that served each call.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use super::{Agent, AgentEvent};
+use crate::context_mgmt::compaction::CompactionTrigger;
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+use crate::providers::base::Provider;
+use crate::providers::capabilities::ModelCapabilities;
+use crate::providers::formats::payload_limits::{count_images, remove_old_images};
+
+/// The conversation after a switch of provider, and what changed for the new model
+#[derive(Debug, Clone)]
+pub struct ProviderSwitch {
+    /// The history to continue with, ending with a note of the switch
+    pub messages: Vec<Message>,
+    pub previous_model: Option<String>,
+    pub model: String,
+    /// Thinking blocks removed, as only the model that wrote them accepts them back
+    pub thinking_removed: usize,
+    /// Images replaced with a note, as the new model does not accept images
+    pub images_removed: usize,
+    /// The compaction of a history too long for the context of the new model
+    pub compaction: Option<AgentEvent>,
+}
+
+impl ProviderSwitch {
+    /// A line for the user about the switch and what it changed
+    pub fn summary(&self) -> String {
+        let mut summary = match &self.previous_model {
+            Some(previous) => format!("Switched from {} to {}", previous, self.model),
+            None => format!("Switched to {}", self.model),
+        };
+        if self.thinking_removed > 0 {
+            summary.push_str(&format!(
+                ", removed {} thinking block(s)",
+                self.thinking_removed
+            ));
+        }
+        if self.images_removed > 0 {
+            summary.push_str(&format!(
+                ", removed {} image(s) it does not accept",
+                self.images_removed
+            ));
+        }
+        if let Some(AgentEvent::Compacted {
+            tokens_before,
+            tokens_after,
+            ..
+        }) = &self.compaction
+        {
+            summary.push_str(&format!(
+                ", compacted the conversation from ~{} to ~{} tokens",
+                tokens_before, tokens_after
+            ));
+        }
+        summary
+    }
+}
+
+impl Agent {
+    /// Continue the conversation of `messages` with the provider `provider_name` and
+    /// `model_config`, created as for a new session
+    pub async fn switch_provider(
+        &self,
+        provider_name: &str,
+        model_config: ModelConfig,
+        messages: &[Message],
+    ) -> Result<ProviderSwitch> {
+        let provider = crate::providers::create(provider_name, model_config)?;
+        self.switch_to_provider(provider, messages).await
+    }
+
+    /// Continue the conversation of `messages` with `provider`
+    pub async fn switch_to_provider(
+        &self,
+        provider: Arc<dyn Provider>,
+        messages: &[Message],
+    ) -> Result<ProviderSwitch> {
+        let previous_model = self
+            .provider()
+            .await
+            .ok()
+            .map(|previous| previous.get_model_config().model_name);
+        let model_config = provider.get_model_config();
+        self.update_provider(provider).await?;
+
+        let (mut history, thinking_removed) = without_provider_state(messages);
+        let images_removed = if accepts_images(&model_config) {
+            0
+        } else {
+            let images = count_images(&history);
+            history = remove_old_images(&history, 0);
+            images
+        };
+
+        let compaction = match self
+            .compact_messages(&history, CompactionTrigger::Threshold)
+            .await?
+        {
+            Some((compacted, event)) => {
+                history = compacted;
+                Some(event)
+            }
+            None => None,
+        };
+
+        let mut switch = ProviderSwitch {
+            messages: Vec::new(),
+            previous_model,
+            model: model_config.model_name,
+            thinking_removed,
+            images_removed,
+            compaction,
+        };
+        history.push(Message::assistant().with_ephemeral_text(switch.summary()));
+        switch.messages = history;
+        Ok(switch)
+    }
+}
+
+/// Whether the model accepts images, assuming models that are not known do
+fn accepts_images(model_config: &ModelConfig) -> bool {
+    model_config.no_validate
+        || ModelCapabilities::for_model(&model_config.model_name)
+            .is_none_or(|capabilities| capabilities.vision)
+}
+
+/// The messages without thinking blocks and kept response fields, with how many thinking
+/// blocks were removed. Messages left empty are dropped.
+fn without_provider_state(messages: &[Message]) -> (Vec<Message>, usize) {
+    let mut removed = 0;
+    let history = messages
+        .iter()
+        .filter_map(|message| {
+            let mut message = message.clone();
+            message.extra.clear();
+            let before = message.content.len();
+            message.content.retain(|content| {
+                !matches!(
+                    content,
+                    MessageContent::Thinking(_) | MessageContent::RedactedThinking(_)
+                )
+            });
+            removed += before - message.content.len();
+            (before == 0 || !message.content.is_empty()).then_some(message)
+        })
+        .collect();
+    (history, removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::{ProviderMetadata, ProviderUsage, Usage};
+    use crate::providers::errors::ProviderError;
+    use mcp_core::tool::Tool;
+    use serde_json::json;
+
+    const PNG_DATA: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=";
+
+    struct NamedProvider(&'static str);
+
+    #[async_trait::async_trait]
+    impl Provider for NamedProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new(self.0.to_string())
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            Ok((
+                Message::assistant().with_text("done"),
+                ProviderUsage::new(self.0.to_string(), Usage::default()),
+            ))
+        }
+    }
+
+    fn history() -> Vec<Message> {
+        let mut fields = serde_json::Map::new();
+        fields.insert("reasoning_details".to_string(), json!([]));
+        vec![
+            Message::user()
+                .with_text("What is in this picture?")
+                .with_image(PNG_DATA, "image/png"),
+            Message::assistant()
+                .with_thinking("A single pixel", "signature")
+                .with_text("A single pixel")
+                .with_extra("openai", fields),
+            Message::assistant().with_redacted_thinking("redacted"),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_switch_provider_keeps_history() {
+        let agent = Agent::new();
+        agent
+            .update_provider(Arc::new(NamedProvider("claude-sonnet-4")))
+            .await
+            .unwrap();
+
+        let switch = agent
+            .switch_to_provider(Arc::new(NamedProvider("gpt-4o")), &history())
+            .await
+            .unwrap();
+        assert_eq!(
+            agent
+                .provider()
+                .await
+                .unwrap()
+                .get_model_config()
+                .model_name,
+            "gpt-4o"
+        );
+        assert_eq!(switch.thinking_removed, 2);
+        assert_eq!(switch.images_removed, 0);
+        assert!(switch.compaction.is_none());
+
+        // The message of only redacted thinking is gone, the note of the switch is added
+        let messages = &switch.messages;
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].content, history()[0].content);
+        assert_eq!(messages[1].as_concat_text(), "A single pixel");
+        assert!(messages[1].extra.is_empty());
+        assert_eq!(
+            messages[2].content[0].as_text(),
+            Some("Switched from claude-sonnet-4 to gpt-4o, removed 2 thinking block(s)")
+        );
+        // The note is only for the user
+        assert!(messages[2].without_ephemeral().content.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_switch_to_model_without_vision() {
+        let agent = Agent::new();
+        let switch = agent
+            .switch_to_provider(Arc::new(NamedProvider("gpt-3.5-turbo")), &history())
+            .await
+            .unwrap();
+        assert_eq!(switch.previous_model, None);
+        assert_eq!(switch.images_removed, 1);
+        assert_eq!(count_images(&switch.messages), 0);
+        assert_eq!(
+            switch.summary(),
+            "Switched to gpt-3.5-turbo, removed 2 thinking block(s), removed 1 image(s) it does not accept"
+        );
+    }
+}
@@ -5,17 +5,40 @@ use std::sync::Arc;
 use crate::agents::router_tool_selector::RouterToolSelectionStrategy;
 use crate::config::Config;
 use crate::message::{Message, MessageContent, ToolRequest};
-use crate::providers::base::{Provider, ProviderUsage};
+use crate::prompt_template::SystemPrompt;
+use crate::providers::base::{
+    CompletionOptions, DryRunRequest, PayloadSize, Provider, ProviderUsage, SystemBlockInfo,
+    ToolCallProgressSender, Usage,
+};
 use crate::providers::errors::ProviderError;
 use crate::providers::toolshim::{
     augment_message_with_tool_calls, convert_tool_messages_to_text,
     modify_system_prompt_for_tool_json, OllamaInterpreter,
 };
+use crate::providers::utils::resolve_tool;
 use crate::session;
 use mcp_core::tool::Tool;
 
 use super::super::agents::Agent;
 
+/// The messages as models see them, without ephemeral content or messages left empty by it
+fn model_history(messages: &[Message]) -> Vec<Message> {
+    messages
+        .iter()
+        .map(Message::without_ephemeral)
+        .filter(|message| !message.content.is_empty())
+        .collect()
+}
+
+/// The system prompt in the form the provider takes, see `Provider::supports_system_blocks`
+fn system_prompt_for(provider: &dyn Provider, system_prompt: &str) -> String {
+    if provider.supports_system_blocks() {
+        system_prompt.to_string()
+    } else {
+        SystemPrompt::decode(system_prompt).render()
+    }
+}
+
 impl Agent {
     /// Prepares tools and system prompt for a provider request
     pub(crate) async fn prepare_tools_and_prompt(
@@ -61,19 +84,23 @@ impl Agent {
         let model_name = &model_config.model_name;
 
         let prompt_manager = self.prompt_manager.lock().await;
-        let mut system_prompt = prompt_manager.build_system_prompt(
+        let system_prompt_blocks = prompt_manager.build_system_prompt_blocks(
             extensions_info,
             self.frontend_instructions.lock().await.clone(),
             extension_manager.suggest_disable_extensions_prompt().await,
             Some(model_name),
             tool_selection_strategy,
         );
+        // The blocks are joined for providers that don't send them separately, when the
+        // request is made, as the provider can change during a reply
+        let mut system_prompt = system_prompt_blocks.encode();
 
         // Handle toolshim if enabled
         let mut toolshim_tools = vec![];
         if model_config.toolshim {
             // If tool interpretation is enabled, modify the system prompt
-            system_prompt = modify_system_prompt_for_tool_json(&system_prompt, &tools);
+            system_prompt =
+                modify_system_prompt_for_tool_json(&system_prompt_blocks.render(), &tools);
             // Make a copy of tools before emptying
             toolshim_tools = tools.clone();
             // Empty the tools vector for provider completion
@@ -106,15 +133,20 @@ impl Agent {
     }
 
     /// Generate a response from the LLM provider
-    /// Handles toolshim transformations if needed
+    /// Handles toolshim transformations if needed, and reports tool call arguments to
+    /// `progress` while they stream in. Requests with `options` other than the defaults
+    /// report no progress.
     pub(crate) async fn generate_response_from_provider(
         provider: Arc<dyn Provider>,
         system_prompt: &str,
         messages: &[Message],
         tools: &[Tool],
         toolshim_tools: &[Tool],
+        progress: Option<ToolCallProgressSender>,
+        options: &CompletionOptions,
     ) -> Result<(Message, ProviderUsage), ProviderError> {
         let config = provider.get_model_config();
+        let messages = &model_history(messages);
 
         // Convert tool messages to text if toolshim is enabled
         let messages_for_provider = if config.toolshim {
@@ -123,10 +155,26 @@ impl Agent {
             messages.to_vec()
         };
 
+        let system_prompt = &system_prompt_for(provider.as_ref(), system_prompt);
+
         // Call the provider to get a response
-        let (mut response, usage) = provider
-            .complete(system_prompt, &messages_for_provider, tools)
-            .await?;
+        let (mut response, usage) = match progress {
+            _ if *options != CompletionOptions::default() => {
+                provider
+                    .complete_with_options(system_prompt, &messages_for_provider, tools, options)
+                    .await?
+            }
+            Some(progress) => {
+                provider
+                    .complete_with_progress(system_prompt, &messages_for_provider, tools, progress)
+                    .await?
+            }
+            None => {
+                provider
+                    .complete(system_prompt, &messages_for_provider, tools)
+                    .await?
+            }
+        };
 
         // Store the model information in the global store
         crate::providers::base::set_current_model(&usage.model);
@@ -144,9 +192,56 @@ impl Agent {
                 })?;
         }
 
+        // Map tool names back to the registered tools, as providers may only see sanitized names
+        let available_tools = if config.toolshim {
+            toolshim_tools
+        } else {
+            tools
+        };
+        for content in response.content.iter_mut() {
+            if let MessageContent::ToolRequest(request) = content {
+                if let Ok(tool_call) = &mut request.tool_call {
+                    if let Some(tool) = resolve_tool(available_tools, &tool_call.name) {
+                        tool_call.name = tool.name.clone();
+                    }
+                }
+            }
+        }
+
         Ok((response, usage))
     }
 
+    /// Build the request the next reply would send to the provider, without sending it
+    pub async fn dry_run(&self, messages: &[Message]) -> Result<DryRunRequest> {
+        let (tools, _, system_prompt) = self.prepare_tools_and_prompt().await?;
+        let provider = self.provider().await?;
+
+        let messages = model_history(&self.guard_provider_request(messages).await);
+        let messages = if provider.get_model_config().toolshim {
+            convert_tool_messages_to_text(&messages)
+        } else {
+            messages
+        };
+
+        let mut request = provider
+            .dry_run(
+                &system_prompt_for(provider.as_ref(), &system_prompt),
+                &messages,
+                &tools,
+            )
+            .await?;
+        request.system_blocks = Some(SystemBlockInfo::for_prompt(
+            &SystemPrompt::decode(&system_prompt),
+            provider.supports_system_blocks(),
+        ));
+        request.payload_size = Some(PayloadSize::for_request(
+            &provider.get_model_config(),
+            &messages,
+            &request.payload,
+        ));
+        Ok(request)
+    }
+
     /// Categorize tool requests from the response into different types
     /// Returns:
     /// - frontend_requests: Tool requests that should be handled by the frontend
@@ -194,6 +289,7 @@ impl Agent {
             role: response.role.clone(),
             created: response.created,
             content: filtered_content,
+            extra: response.extra.clone(),
         };
 
         // Categorize tool requests
@@ -238,20 +334,57 @@ impl Agent {
 
         metadata.message_count = messages_length + 1;
 
-        let accumulate = |a: Option<i32>, b: Option<i32>| -> Option<i32> {
-            match (a, b) {
-                (Some(x), Some(y)) => Some(x + y),
-                _ => a.or(b),
-            }
-        };
-        metadata.accumulated_total_tokens =
-            accumulate(metadata.accumulated_total_tokens, usage.usage.total_tokens);
-        metadata.accumulated_input_tokens =
-            accumulate(metadata.accumulated_input_tokens, usage.usage.input_tokens);
-        metadata.accumulated_output_tokens = accumulate(
+        let accumulated = Usage::new(
+            metadata.accumulated_input_tokens,
             metadata.accumulated_output_tokens,
-            usage.usage.output_tokens,
-        );
+            metadata.accumulated_total_tokens,
+        ) + usage.usage.clone();
+        metadata.accumulated_total_tokens = accumulated.total_tokens;
+        metadata.accumulated_input_tokens = accumulated.input_tokens;
+        metadata.accumulated_output_tokens = accumulated.output_tokens;
+        metadata.usage_by_model.record(usage);
+
+        session::storage::update_metadata(&session_file_path, &metadata).await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn record_guardrail_rewrites(
+        session_config: crate::agents::types::SessionConfig,
+        rewrites: Vec<crate::agents::guardrail::GuardrailRewrite>,
+    ) -> Result<()> {
+        let session_file_path = session::storage::get_path(session_config.id)?;
+        let mut metadata = session::storage::read_metadata(&session_file_path)?;
+
+        metadata.guardrail_rewrites.extend(rewrites);
+
+        session::storage::update_metadata(&session_file_path, &metadata).await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn record_turn_stats(
+        session_config: crate::agents::types::SessionConfig,
+        turn_stats: &crate::session::TurnStats,
+    ) -> Result<()> {
+        let session_file_path = session::storage::get_path(session_config.id)?;
+        let mut metadata = session::storage::read_metadata(&session_file_path)?;
+
+        metadata.turn_stats.push(turn_stats.clone());
+
+        session::storage::update_metadata(&session_file_path, &metadata).await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn record_analytics(
+        session_config: crate::agents::types::SessionConfig,
+        analytics: crate::session::SessionAnalytics,
+    ) -> Result<()> {
+        let session_file_path = session::storage::get_path(session_config.id)?;
+        let mut metadata = session::storage::read_metadata(&session_file_path)?;
+
+        metadata.analytics.merge(analytics);
 
         session::storage::update_metadata(&session_file_path, &metadata).await?;
 
@@ -0,0 +1,281 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use mcp_core::{Content, ToolError};
+use serde_json::Value;
+
+use super::platform_tools::PLATFORM_READ_RESULT_TOOL_NAME;
+use crate::config::Config;
+
+/// Default page size of large tool results, configured with GOOSE_RESULT_PAGE_TOKENS. At
+/// 200,000 characters, results below the size that used to be spilled to a file stay whole.
+pub const DEFAULT_RESULT_PAGE_TOKENS: usize = 50_000;
+
+// Rough approximation: 1 token per 4 characters for English text
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Results stored in the temp dir are not removed with a session, so they expire instead
+const TEMP_RESULT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Splits tool output that is too large for a single response into pages
+///
+/// The full text is spilled to a store directory, which is next to the session file when
+/// there is a session so it is removed with it. The model gets the first page plus a note
+/// and reads the rest with the `platform__read_result` tool. Pages are returned as regular
+/// tool responses, so guardrails see them before they reach the provider.
+#[derive(Debug, Clone)]
+pub struct ResultPager {
+    store_dir: PathBuf,
+    page_tokens: usize,
+}
+
+impl ResultPager {
+    pub fn new(store_dir: PathBuf, page_tokens: usize) -> Self {
+        Self {
+            store_dir,
+            page_tokens: page_tokens.max(1),
+        }
+    }
+
+    /// Create a pager that stores results in the temp dir until a session sets its own store,
+    /// removing results earlier agents left there more than a day ago
+    pub fn from_config() -> Self {
+        let page_tokens = Config::global()
+            .get_param("GOOSE_RESULT_PAGE_TOKENS")
+            .unwrap_or(DEFAULT_RESULT_PAGE_TOKENS);
+        let store_dir = std::env::temp_dir().join("goose_tool_results");
+        remove_expired_results(&store_dir, TEMP_RESULT_MAX_AGE);
+        Self::new(store_dir, page_tokens)
+    }
+
+    pub fn set_store_dir(&mut self, store_dir: PathBuf) {
+        self.store_dir = store_dir;
+    }
+
+    pub fn set_page_tokens(&mut self, page_tokens: usize) {
+        self.page_tokens = page_tokens.max(1);
+    }
+
+    fn page_chars(&self) -> usize {
+        self.page_tokens.saturating_mul(CHARS_PER_TOKEN)
+    }
+
+    /// Replace text content larger than a page with its first page
+    pub fn paginate(&self, contents: Vec<Content>) -> Vec<Content> {
+        contents
+            .into_iter()
+            .map(|content| match content {
+                Content::Text(text) if text.text.len() > self.page_chars() => {
+                    match self.store(&text.text) {
+                        Ok(id) => Content::text(self.render_page(&id, &text.text, 1)),
+                        Err(e) => {
+                            tracing::warn!("Failed to store large tool result: {}", e);
+                            Content::Text(text)
+                        }
+                    }
+                }
+                content => content,
+            })
+            .collect()
+    }
+
+    /// Serve a call of the `platform__read_result` tool
+    pub fn read_result(&self, arguments: &Value) -> Result<Vec<Content>, ToolError> {
+        let id = arguments
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'id' parameter".to_string()))?;
+        let page = arguments
+            .get("page")
+            .and_then(|v| v.as_u64())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'page' parameter".to_string()))?;
+
+        // Ids are generated by the pager, anything else could escape the store
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(ToolError::InvalidParameters(format!(
+                "Invalid result id '{}'",
+                id
+            )));
+        }
+        let text = fs::read_to_string(self.store_dir.join(format!("{}.txt", id)))
+            .map_err(|_| ToolError::ExecutionError(format!("No stored result with id '{}'", id)))?;
+
+        let pages = split_pages(&text, self.page_chars()).len();
+        if page == 0 || page as usize > pages {
+            return Err(ToolError::InvalidParameters(format!(
+                "Page {} is out of range, result {} has {} pages",
+                page, id, pages
+            )));
+        }
+        Ok(vec![Content::text(self.render_page(
+            id,
+            &text,
+            page as usize,
+        ))])
+    }
+
+    /// Store text that the model can read with the `platform__read_result` tool, returning its id
+    pub fn store(&self, text: &str) -> std::io::Result<String> {
+        fs::create_dir_all(&self.store_dir)?;
+        let id = format!(
+            "result_{}",
+            &crate::determinism::new_uuid().simple().to_string()[..12]
+        );
+        fs::write(self.store_dir.join(format!("{}.txt", id)), text)?;
+        Ok(id)
+    }
+
+    fn render_page(&self, id: &str, text: &str, page: usize) -> String {
+        let pages = split_pages(text, self.page_chars());
+        let note = if page < pages.len() {
+            format!(
+                "[Result {} is too large to return at once, this is page {}/{}. Call {} with id \"{}\" and page {} for more.]",
+                id,
+                page,
+                pages.len(),
+                PLATFORM_READ_RESULT_TOOL_NAME,
+                id,
+                page + 1
+            )
+        } else {
+            format!(
+                "[Result {}, page {}/{}. This is the last page.]",
+                id,
+                page,
+                pages.len()
+            )
+        };
+        format!("{}\n\n{}", pages[page - 1], note)
+    }
+}
+
+/// Remove stored results last written more than `max_age` ago
+fn remove_expired_results(store_dir: &Path, max_age: Duration) {
+    let Ok(entries) = fs::read_dir(store_dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let expired = path.extension().is_some_and(|ext| ext == "txt")
+            && entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age > max_age);
+        if expired {
+            if let Err(e) = fs::remove_file(&path) {
+                tracing::debug!("Failed to remove expired result {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// Split text into pages of at most `page_chars` bytes, on character boundaries
+fn split_pages(text: &str, page_chars: usize) -> Vec<&str> {
+    let mut pages = Vec::new();
+    let mut rest = text;
+    while rest.len() > page_chars {
+        let mut end = page_chars;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        // A page always holds at least one character
+        if end == 0 {
+            end = rest.chars().next().map_or(rest.len(), char::len_utf8);
+        }
+        let (page, remainder) = rest.split_at(end);
+        pages.push(page);
+        rest = remainder;
+    }
+    pages.push(rest);
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn text_of(contents: &[Content]) -> &str {
+        contents[0].as_text().unwrap()
+    }
+
+    #[test]
+    fn test_small_results_pass_through() {
+        let dir = tempdir().unwrap();
+        let pager = ResultPager::new(dir.path().to_path_buf(), 10);
+        let contents = pager.paginate(vec![Content::text("short")]);
+        assert_eq!(text_of(&contents), "short");
+        assert!(fs::read_dir(dir.path()).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn test_pages_cover_the_whole_result() {
+        let dir = tempdir().unwrap();
+        let pager = ResultPager::new(dir.path().to_path_buf(), 2);
+        let text = "0123456789abcdef".repeat(2);
+
+        let first = pager.paginate(vec![Content::text(text.clone())]);
+        assert!(text_of(&first).starts_with("01234567\n\n[Result result_"));
+        assert!(text_of(&first).contains("page 1/4"));
+
+        let id = fs::read_dir(dir.path())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path()
+            .file_stem()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let mut pages = vec![text_of(&first).split("\n\n").next().unwrap().to_string()];
+        for page in 2..=4 {
+            let contents = pager.read_result(&json!({"id": id, "page": page})).unwrap();
+            pages.push(text_of(&contents).split("\n\n").next().unwrap().to_string());
+        }
+        assert_eq!(pages.concat(), text);
+
+        assert!(pager.read_result(&json!({"id": id, "page": 5})).is_err());
+        assert!(pager
+            .read_result(&json!({"id": "../secrets", "page": 1}))
+            .is_err());
+    }
+
+    #[test]
+    fn test_split_pages_respects_char_boundaries() {
+        let pages = split_pages("héllo wörld", 2);
+        assert_eq!(pages.concat(), "héllo wörld");
+        assert!(pages.iter().all(|page| !page.is_empty()));
+    }
+
+    #[test]
+    fn test_default_page_keeps_results_below_the_old_threshold() {
+        let dir = tempdir().unwrap();
+        let pager = ResultPager::new(dir.path().to_path_buf(), DEFAULT_RESULT_PAGE_TOKENS);
+
+        let whole = pager.paginate(vec![Content::text("a".repeat(200_000))]);
+        assert_eq!(text_of(&whole).len(), 200_000);
+
+        let paged = pager.paginate(vec![Content::text("a".repeat(200_001))]);
+        assert!(text_of(&paged).contains("page 1/2"));
+    }
+
+    #[test]
+    fn test_expired_results_are_removed() {
+        let dir = tempdir().unwrap();
+        let pager = ResultPager::new(dir.path().to_path_buf(), 10);
+        let id = pager.store("stored").unwrap();
+        let path = dir.path().join(format!("{}.txt", id));
+        std::thread::sleep(Duration::from_millis(10));
+
+        remove_expired_results(dir.path(), TEMP_RESULT_MAX_AGE);
+        assert!(path.exists());
+
+        remove_expired_results(dir.path(), Duration::ZERO);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,180 @@
+//! Stopping a reply softly, as opposed to cancelling it
+//!
+//! A hard cancel drops the reply stream, losing the tools that were running and any answer to
+//! them. A soft stop lets the tools of the current round finish, then sends their results
+//! with an instruction to summarize the progress, in a request that forbids tool calls. The
+//! reply ends with that summary.
+
+use std::sync::atomic::Ordering;
+
+use super::Agent;
+use crate::message::{Message, MessageContent};
+use mcp_core::role::Role;
+
+/// Added to the last request after a soft stop
+const STOP_INSTRUCTION: &str = "The user asked you to stop. Do not call any more tools. \
+Summarize what you did so far, what is left to do, and anything the user should check.";
+
+impl Agent {
+    /// Ask the reply in progress to stop once its running tools finish
+    ///
+    /// The model is then asked for a summary instead of more tool calls, and the reply emits
+    /// `AgentEvent::SoftStop` before it. A request made while no reply runs is dropped when
+    /// the next one starts.
+    pub fn request_stop(&self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether a stop was requested, clearing the request
+    pub(super) fn take_stop_request(&self) -> bool {
+        self.stop_requested.swap(false, Ordering::SeqCst)
+    }
+}
+
+/// The messages of the request after a soft stop, with the instruction to stop added to the
+/// last user message, as some APIs reject two user messages in a row
+pub(super) fn with_stop_instruction(mut messages: Vec<Message>) -> Vec<Message> {
+    match messages.last_mut() {
+        Some(last) if last.role == Role::User => {
+            last.content.push(MessageContent::text(STOP_INSTRUCTION));
+        }
+        _ => messages.push(Message::user().with_text(STOP_INSTRUCTION)),
+    }
+    messages
+}
+
+/// The summary without tool calls, which providers that can't forbid them may still return
+pub(super) fn without_tool_requests(mut message: Message) -> Message {
+    message
+        .content
+        .retain(|content| !matches!(content, MessageContent::ToolRequest(_)));
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::AgentEvent;
+    use crate::model::ModelConfig;
+    use crate::providers::base::{
+        CompletionOptions, Provider, ProviderMetadata, ProviderUsage, Usage,
+    };
+    use crate::providers::errors::ProviderError;
+    use futures::StreamExt;
+    use mcp_core::tool::{Tool, ToolCall};
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    /// Calls a tool on every request, unless the request forbids tool calls
+    #[derive(Default)]
+    struct ScriptedProvider {
+        requests: Mutex<Vec<(Vec<Message>, CompletionOptions)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for ScriptedProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("test-model".to_string())
+        }
+
+        async fn complete(
+            &self,
+            system: &str,
+            messages: &[Message],
+            tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            self.complete_with_options(system, messages, tools, &CompletionOptions::default())
+                .await
+        }
+
+        async fn complete_with_options(
+            &self,
+            _system: &str,
+            messages: &[Message],
+            _tools: &[Tool],
+            options: &CompletionOptions,
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            let mut requests = self.requests.lock().unwrap();
+            requests.push((messages.to_vec(), options.clone()));
+            let response = if options.no_tool_calls {
+                Message::assistant().with_text("I listed the files, nothing else is left")
+            } else {
+                Message::assistant().with_tool_request(
+                    format!("call_{}", requests.len()),
+                    Ok(ToolCall::new("files__list", json!({}))),
+                )
+            };
+            Ok((
+                response,
+                ProviderUsage::new("test-model".to_string(), Usage::default()),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_soft_stop_ends_after_one_more_request() {
+        let provider = Arc::new(ScriptedProvider::default());
+        let agent = Agent::new();
+        agent.update_provider(provider.clone()).await.unwrap();
+
+        // A stop from before the reply is dropped
+        agent.request_stop();
+        let messages = vec![Message::user().with_text("list the files")];
+        let mut stream = agent.reply(&messages, None).await.unwrap();
+
+        let mut events = Vec::new();
+        while let Some(event) = stream.next().await {
+            let event = event.unwrap();
+            // Stop once the model called a tool the first time
+            if matches!(event, AgentEvent::ToolCall { .. }) && events.is_empty() {
+                agent.request_stop();
+            }
+            if matches!(event, AgentEvent::ToolCall { .. } | AgentEvent::SoftStop) {
+                events.push(event);
+            } else if let AgentEvent::Message(message) = event {
+                events.push(AgentEvent::Message(message));
+            }
+        }
+        drop(stream);
+
+        // The running call finished, then one request forbade more tool calls
+        let requests = provider.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(!requests[0].1.no_tool_calls);
+        let (last_messages, options) = &requests[1];
+        assert!(options.no_tool_calls);
+        let instruction = last_messages.last().unwrap();
+        assert_eq!(instruction.role, Role::User);
+        assert!(instruction.content[0].as_tool_response().is_some());
+        assert_eq!(instruction.content[1].as_text(), Some(STOP_INSTRUCTION));
+
+        assert!(matches!(events[0], AgentEvent::ToolCall { .. }));
+        assert!(matches!(events[3], AgentEvent::SoftStop));
+        let AgentEvent::Message(summary) = events.last().unwrap() else {
+            panic!("the reply should end with a message");
+        };
+        assert_eq!(
+            summary.as_concat_text(),
+            "I listed the files, nothing else is left"
+        );
+        assert!(!agent.take_stop_request());
+    }
+
+    #[test]
+    fn test_with_stop_instruction() {
+        let messages = with_stop_instruction(vec![Message::assistant().with_text("working")]);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].as_concat_text(), STOP_INSTRUCTION);
+
+        let summary = without_tool_requests(
+            Message::assistant()
+                .with_text("done")
+                .with_tool_request("call_1", Ok(ToolCall::new("files__list", json!({})))),
+        );
+        assert_eq!(summary.content.len(), 1);
+    }
+}
@@ -2,7 +2,7 @@ use crate::{
     agents::{extension_manager::ExtensionManager, Agent},
     message::{Message, MessageContent, ToolRequest},
     prompt_template::render_global_file,
-    providers::base::Provider,
+    providers::base::{CompletionOptions, Provider},
     providers::errors::ProviderError,
     recipe::Recipe,
 };
@@ -15,7 +15,6 @@ use serde_json::{self, json};
 use std::{collections::HashMap, sync::Arc};
 use tokio::sync::{mpsc, Mutex, RwLock};
 use tracing::{debug, error, instrument};
-use uuid::Uuid;
 
 use crate::agents::platform_tools::{
     self, PLATFORM_LIST_RESOURCES_TOOL_NAME, PLATFORM_READ_RESOURCE_TOOL_NAME,
@@ -45,7 +44,7 @@ pub struct SubAgentConfig {
 impl SubAgentConfig {
     pub fn new_with_recipe(recipe: Recipe) -> Self {
         Self {
-            id: Uuid::new_v4().to_string(),
+            id: crate::determinism::new_uuid().to_string(),
             recipe: Some(recipe),
             instructions: None,
             max_turns: None,
@@ -55,7 +54,7 @@ impl SubAgentConfig {
 
     pub fn new_with_instructions(instructions: String) -> Self {
         Self {
-            id: Uuid::new_v4().to_string(),
+            id: crate::determinism::new_uuid().to_string(),
             recipe: None,
             instructions: Some(instructions),
             max_turns: None,
@@ -376,6 +375,8 @@ impl SubAgent {
                 &messages,
                 &tools,
                 &toolshim_tools,
+                None,
+                &CompletionOptions::default(),
             )
             .await
             {
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use mcp_core::tool::ToolCall;
+
+use super::guardrail::{Guardrail, GuardrailDecision};
+use super::Agent;
+use crate::config::workspace::tool_allowed;
+
+/// Refuses calls to tools outside an allowlist, such as the `allowed_tools` of a workspace
+pub struct ToolAllowlistGuardrail {
+    patterns: Vec<String>,
+}
+
+impl ToolAllowlistGuardrail {
+    pub fn new(patterns: Vec<String>) -> Self {
+        Self { patterns }
+    }
+}
+
+#[async_trait]
+impl Guardrail for ToolAllowlistGuardrail {
+    fn name(&self) -> &str {
+        "tool_allowlist"
+    }
+
+    async fn before_tool_dispatch(&self, tool_call: &ToolCall) -> GuardrailDecision {
+        if tool_allowed(Some(&self.patterns), &tool_call.name) {
+            return GuardrailDecision::Allow;
+        }
+        GuardrailDecision::Block {
+            reason: format!(
+                "`{}` is not one of the tools allowed in this workspace",
+                tool_call.name
+            ),
+        }
+    }
+}
+
+impl Agent {
+    /// Only let the model see and call the tools matching `patterns`
+    ///
+    /// A pattern is a tool name or a prefix ending in `*`. Platform tools are always allowed.
+    pub async fn set_tool_allowlist(&self, patterns: Vec<String>) {
+        *self.tool_allowlist.lock().await = Some(patterns.clone());
+        self.add_guardrail(Arc::new(ToolAllowlistGuardrail::new(patterns)))
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_tool_allowlist() {
+        let agent = Agent::new();
+        agent
+            .set_tool_allowlist(vec!["developer__*".to_string()])
+            .await;
+        let tools = agent.list_tools(None).await;
+        assert!(!tools.is_empty());
+        assert!(tools.iter().all(|tool| tool.name.starts_with("platform__")));
+
+        let guardrail = ToolAllowlistGuardrail::new(vec!["developer__*".to_string()]);
+        let shell = ToolCall::new("developer__shell", json!({"command": "ls"}));
+        assert_eq!(
+            guardrail.before_tool_dispatch(&shell).await,
+            GuardrailDecision::Allow
+        );
+        let fetch = ToolCall::new("fetch__get", json!({"url": "https://example.com"}));
+        assert_eq!(
+            guardrail.before_tool_dispatch(&fetch).await,
+            GuardrailDecision::Block {
+                reason: "`fetch__get` is not one of the tools allowed in this workspace"
+                    .to_string()
+            }
+        );
+    }
+}
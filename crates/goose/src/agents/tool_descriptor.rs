@@ -0,0 +1,240 @@
+use anyhow::Result;
+use mcp_core::tool::{Tool, ToolAnnotations};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::providers::formats::openai::format_tools;
+
+/// A tool as shown to people and tool pickers, with the extension that provides it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolDescriptor {
+    /// The prefixed name the model calls the tool by, such as `developer__shell`
+    pub name: String,
+    /// The extension of a prefixed tool, none for tools without a prefix
+    pub extension: Option<String>,
+    pub description: String,
+    pub input_schema: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+}
+
+impl ToolDescriptor {
+    pub fn from_tool(tool: &Tool) -> Self {
+        let extension = tool
+            .name
+            .split_once("__")
+            .map(|(extension, _)| extension.to_string());
+        Self {
+            name: tool.name.clone(),
+            extension,
+            description: tool.description.clone(),
+            input_schema: tool.input_schema.clone(),
+            annotations: tool.annotations.clone(),
+        }
+    }
+
+    pub fn to_tool(&self) -> Tool {
+        Tool::new(
+            self.name.clone(),
+            self.description.clone(),
+            self.input_schema.clone(),
+            self.annotations.clone(),
+        )
+    }
+
+    /// The tool in OpenAI's function calling format
+    pub fn to_openai_spec(&self) -> Result<Value> {
+        Ok(format_tools(&[self.to_tool()])?.remove(0))
+    }
+
+    /// A markdown section with the description and a table of the parameters
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = format!("### `{}`\n", self.name);
+        if let Some(title) = self
+            .annotations
+            .as_ref()
+            .and_then(|annotations| annotations.title.as_ref())
+        {
+            markdown.push_str(&format!("\n**{}**\n", title));
+        }
+        if !self.description.trim().is_empty() {
+            markdown.push_str(&format!("\n{}\n", self.description.trim()));
+        }
+
+        let parameters = self.parameters();
+        if parameters.is_empty() {
+            markdown.push_str("\nNo parameters.\n");
+            return markdown;
+        }
+        markdown.push_str("\n| Parameter | Type | Required | Description |\n");
+        markdown.push_str("|---|---|---|---|\n");
+        for parameter in parameters {
+            markdown.push_str(&format!(
+                "| `{}` | {} | {} | {} |\n",
+                parameter.name,
+                parameter.kind,
+                if parameter.required { "yes" } else { "no" },
+                escape_table_cell(&parameter.description)
+            ));
+        }
+        markdown
+    }
+
+    /// The top level properties of the input schema, by name
+    fn parameters(&self) -> Vec<Parameter> {
+        let required: Vec<&str> = self.input_schema["required"]
+            .as_array()
+            .map(|names| names.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        let Some(properties) = self.input_schema["properties"].as_object() else {
+            return Vec::new();
+        };
+
+        properties
+            .iter()
+            .map(|(name, schema)| Parameter {
+                name: name.clone(),
+                kind: schema_type(schema),
+                required: required.contains(&name.as_str()),
+                description: schema["description"].as_str().unwrap_or("").to_string(),
+            })
+            .collect()
+    }
+}
+
+struct Parameter {
+    name: String,
+    kind: String,
+    required: bool,
+    description: String,
+}
+
+/// A short name for the type of a schema, such as `string`, `array of string` or `a | b`
+fn schema_type(schema: &Value) -> String {
+    if let Some(values) = schema["enum"].as_array() {
+        return values
+            .iter()
+            .map(|value| format!("`{}`", value))
+            .collect::<Vec<_>>()
+            .join(" \\| ");
+    }
+    match &schema["type"] {
+        Value::String(kind) if kind == "array" => match schema.get("items") {
+            Some(items) => format!("array of {}", schema_type(items)),
+            None => "array".to_string(),
+        },
+        Value::String(kind) => kind.clone(),
+        Value::Array(kinds) => kinds
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(" \\| "),
+        _ => "any".to_string(),
+    }
+}
+
+/// Text that fits in one cell of a markdown table
+fn escape_table_cell(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('|', "\\|")
+}
+
+/// Describe tools, keeping their order
+pub fn describe_tools(tools: &[Tool]) -> Vec<ToolDescriptor> {
+    tools.iter().map(ToolDescriptor::from_tool).collect()
+}
+
+/// The tools in OpenAI's function calling format, failing on duplicate names
+pub fn descriptors_to_openai_spec(descriptors: &[ToolDescriptor]) -> Result<Vec<Value>> {
+    let tools: Vec<Tool> = descriptors.iter().map(ToolDescriptor::to_tool).collect();
+    format_tools(&tools)
+}
+
+/// A markdown document with a section per tool
+pub fn descriptors_to_markdown(descriptors: &[ToolDescriptor]) -> String {
+    descriptors
+        .iter()
+        .map(ToolDescriptor::to_markdown)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn shell_tool() -> Tool {
+        Tool::new(
+            "developer__shell",
+            "Run a shell command.\nThe output is returned as text.",
+            json!({
+                "type": "object",
+                "required": ["command"],
+                "properties": {
+                    "command": {"type": "string", "description": "The command | pipeline to run"},
+                    "timeout": {"type": "integer"},
+                    "env": {"type": "array", "items": {"type": "string"}, "description": "KEY=VALUE pairs"},
+                    "shell": {"enum": ["bash", "zsh"]}
+                }
+            }),
+            Some(ToolAnnotations::new().with_title("Shell")),
+        )
+    }
+
+    #[test]
+    fn test_tool_descriptor_to_markdown() {
+        let descriptor = ToolDescriptor::from_tool(&shell_tool());
+        assert_eq!(descriptor.extension.as_deref(), Some("developer"));
+
+        assert_eq!(
+            descriptor.to_markdown(),
+            "### `developer__shell`\n\
+             \n\
+             **Shell**\n\
+             \n\
+             Run a shell command.\nThe output is returned as text.\n\
+             \n\
+             | Parameter | Type | Required | Description |\n\
+             |---|---|---|---|\n\
+             | `command` | string | yes | The command \\| pipeline to run |\n\
+             | `env` | array of string | no | KEY=VALUE pairs |\n\
+             | `shell` | `\"bash\"` \\| `\"zsh\"` | no |  |\n\
+             | `timeout` | integer | no |  |\n"
+        );
+
+        let bare = ToolDescriptor::from_tool(&Tool::new(
+            "list_resources",
+            "",
+            json!({"type": "object"}),
+            None,
+        ));
+        assert_eq!(bare.extension, None);
+        assert_eq!(
+            bare.to_markdown(),
+            "### `list_resources`\n\nNo parameters.\n"
+        );
+    }
+
+    #[test]
+    fn test_tool_descriptor_to_openai_spec() {
+        let tool = shell_tool();
+        let descriptor = ToolDescriptor::from_tool(&tool);
+
+        let spec = descriptor.to_openai_spec().unwrap();
+        assert_eq!(spec["type"], "function");
+        assert_eq!(spec["function"]["name"], "developer__shell");
+        assert_eq!(spec["function"]["description"], tool.description);
+        assert_eq!(spec["function"]["parameters"], tool.input_schema);
+        assert_eq!(spec, format_tools(&[tool]).unwrap()[0]);
+
+        let descriptors = vec![descriptor.clone(), descriptor];
+        assert!(descriptors_to_openai_spec(&descriptors).is_err());
+        assert_eq!(
+            descriptors_to_openai_spec(&descriptors[..1]).unwrap().len(),
+            1
+        );
+    }
+}
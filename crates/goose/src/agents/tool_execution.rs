@@ -1,18 +1,22 @@
+use std::collections::HashSet;
 use std::future::Future;
 use std::sync::Arc;
 
 use async_stream::try_stream;
 use futures::stream::{self, BoxStream};
 use futures::{Stream, StreamExt};
-use mcp_core::protocol::JsonRpcMessage;
+use mcp_core::protocol::{JsonRpcMessage, JsonRpcNotification};
+use serde_json::Value;
 use tokio::sync::Mutex;
 
 use crate::config::permission::PermissionLevel;
 use crate::config::PermissionManager;
-use crate::message::{Message, ToolRequest};
-use crate::permission::Permission;
+use crate::message::{Message, MessageContent, ToolConfirmationRequest, ToolRequest};
+use crate::permission::{Permission, RiskLevel};
 use mcp_core::{Content, ToolResult};
 
+use super::approval::{ApprovalOutcome, APPROVAL_TIMED_OUT_REASON};
+
 // ToolCallResult combines the result of a tool call with an optional notification stream that
 // can be used to receive notifications from the tool.
 pub struct ToolCallResult {
@@ -29,8 +33,48 @@ impl From<ToolResult<Vec<Content>>> for ToolCallResult {
     }
 }
 
-use super::agent::{tool_stream, ToolStream};
+use super::agent::{tool_stream, AgentEvent, ToolStream};
 use crate::agents::Agent;
+use mcp_core::Role;
+
+/// The status message and the fraction of the work done of a `notifications/progress`
+/// notification, none for other messages
+pub(crate) fn tool_progress(message: &JsonRpcMessage) -> Option<(Option<String>, Option<f64>)> {
+    let JsonRpcMessage::Notification(JsonRpcNotification {
+        method,
+        params: Some(params),
+        ..
+    }) = message
+    else {
+        return None;
+    };
+    if method != "notifications/progress" {
+        return None;
+    }
+    let status = params.get("message").and_then(Value::as_str);
+    let progress = params.get("progress").and_then(Value::as_f64);
+    let fraction = match (progress, params.get("total").and_then(Value::as_f64)) {
+        (Some(progress), Some(total)) if total > 0.0 => Some((progress / total).clamp(0.0, 1.0)),
+        _ => None,
+    };
+    Some((status.map(str::to_string), fraction))
+}
+
+/// The parts of a tool result whose audience is only the user
+///
+/// Providers send content without an audience or with the assistant in it, so these
+/// parts would otherwise never be seen by anyone.
+pub(crate) fn user_facing_content(contents: &[Content]) -> Vec<Content> {
+    contents
+        .iter()
+        .filter(|content| {
+            content.audience().is_some_and(|audience| {
+                audience.contains(&Role::User) && !audience.contains(&Role::Assistant)
+            })
+        })
+        .cloned()
+        .collect()
+}
 
 pub const DECLINED_RESPONSE: &str = "The user has declined to run this tool. \
     DO NOT attempt to call this tool again. \
@@ -49,52 +93,77 @@ impl Agent {
     pub(crate) fn handle_approval_tool_requests<'a>(
         &'a self,
         tool_requests: &'a [ToolRequest],
+        read_only_tools: &'a HashSet<String>,
         tool_futures: Arc<Mutex<Vec<(String, ToolStream)>>>,
         permission_manager: &'a mut PermissionManager,
         message_tool_response: Arc<Mutex<Message>>,
-    ) -> BoxStream<'a, anyhow::Result<Message>> {
+    ) -> BoxStream<'a, anyhow::Result<AgentEvent>> {
         try_stream! {
             for request in tool_requests {
                 if let Ok(tool_call) = request.tool_call.clone() {
-                    let confirmation = Message::user().with_tool_confirmation_request(
-                        request.id.clone(),
-                        tool_call.name.clone(),
-                        tool_call.arguments.clone(),
-                        Some("Goose would like to call the above tool. Allow? (y/n):".to_string()),
+                    let risk = self.command_risk.classifier.assess_tool_call(&tool_call);
+                    let prompt = match risk.as_ref().filter(|risk| risk.level > RiskLevel::Low) {
+                        Some(risk) => format!(
+                            "Goose would like to call the above tool, which is {} risk: {}. Allow? (y/n):",
+                            risk.level, risk.explanation
+                        ),
+                        None => "Goose would like to call the above tool. Allow? (y/n):".to_string(),
+                    };
+                    let confirmation = Message::user().with_content(
+                        MessageContent::ToolConfirmationRequest(ToolConfirmationRequest {
+                            id: request.id.clone(),
+                            tool_name: tool_call.name.clone(),
+                            arguments: tool_call.arguments.clone(),
+                            prompt: Some(prompt),
+                            risk,
+                        }),
                     );
-                    yield confirmation;
-
-                    let mut rx = self.confirmation_rx.lock().await;
-                    while let Some((req_id, confirmation)) = rx.recv().await {
-                        if req_id == request.id {
-                            if confirmation.permission == Permission::AllowOnce || confirmation.permission == Permission::AlwaysAllow {
-                                let (req_id, tool_result) = self.dispatch_tool_call(tool_call.clone(), request.id.clone()).await;
-                                let mut futures = tool_futures.lock().await;
-
-                                futures.push((req_id, match tool_result {
-                                    Ok(result) => tool_stream(
-                                        result.notification_stream.unwrap_or_else(|| Box::new(stream::empty())),
-                                        result.result,
-                                    ),
-                                    Err(e) => tool_stream(
-                                        Box::new(stream::empty()),
-                                        futures::future::ready(Err(e)),
-                                    ),
-                                }));
-
-                                if confirmation.permission == Permission::AlwaysAllow {
-                                    permission_manager.update_user_permission(&tool_call.name, PermissionLevel::AlwaysAllow);
-                                }
-                            } else {
-                                // User declined - add declined response
-                                let mut response = message_tool_response.lock().await;
-                                *response = response.clone().with_tool_response(
-                                    request.id.clone(),
-                                    Ok(vec![Content::text(DECLINED_RESPONSE)]),
-                                );
+                    yield AgentEvent::Message(confirmation);
+
+                    let read_only = read_only_tools.contains(&tool_call.name);
+                    let outcome = self.approval_monitor.wait_for(&request.id, read_only).await;
+
+                    if let ApprovalOutcome::TimedOut { approved } = outcome {
+                        yield AgentEvent::ApprovalTimedOut {
+                            request_id: request.id.clone(),
+                            tool_name: tool_call.name.clone(),
+                            approved,
+                        };
+                    }
+
+                    if outcome.is_approved() {
+                        let (req_id, tool_result) = self.dispatch_tool_call(tool_call.clone(), request.id.clone()).await;
+                        let mut futures = tool_futures.lock().await;
+
+                        futures.push((req_id, match tool_result {
+                            Ok(result) => tool_stream(
+                                result.notification_stream.unwrap_or_else(|| Box::new(stream::empty())),
+                                result.result,
+                            ),
+                            Err(e) => tool_stream(
+                                Box::new(stream::empty()),
+                                futures::future::ready(Err(e)),
+                            ),
+                        }));
+
+                        if let ApprovalOutcome::Decided(confirmation) = &outcome {
+                            if confirmation.permission == Permission::AlwaysAllow {
+                                permission_manager.update_user_permission(&tool_call.name, PermissionLevel::AlwaysAllow);
                             }
-                            break; // Exit the loop once the matching `req_id` is found
                         }
+                    } else {
+                        let text = match outcome {
+                            ApprovalOutcome::TimedOut { .. } => format!(
+                                "The tool call was denied: {}. DO NOT attempt to call this tool again.",
+                                APPROVAL_TIMED_OUT_REASON
+                            ),
+                            ApprovalOutcome::Decided(_) => DECLINED_RESPONSE.to_string(),
+                        };
+                        let mut response = message_tool_response.lock().await;
+                        *response = response.clone().with_tool_response(
+                            request.id.clone(),
+                            Ok(vec![Content::text(text)]),
+                        );
                     }
                 }
             }
@@ -105,18 +174,29 @@ impl Agent {
         &'a self,
         tool_requests: &'a [ToolRequest],
         message_tool_response: Arc<Mutex<Message>>,
-    ) -> BoxStream<'a, anyhow::Result<Message>> {
+    ) -> BoxStream<'a, anyhow::Result<AgentEvent>> {
         try_stream! {
             for request in tool_requests {
                 if let Ok(tool_call) = request.tool_call.clone() {
                     if self.is_frontend_tool(&tool_call.name).await {
                         // Send frontend tool request and wait for response
-                        yield Message::assistant().with_frontend_tool_request(
+                        yield AgentEvent::Message(Message::assistant().with_frontend_tool_request(
                             request.id.clone(),
                             Ok(tool_call.clone())
-                        );
+                        ));
 
                         if let Some((id, result)) = self.tool_result_rx.lock().await.recv().await {
+                            // Providers drop user-only content, so hand it to the frontend directly
+                            let content = result.as_ref().map(|contents| user_facing_content(contents)).unwrap_or_default();
+                            if !content.is_empty() {
+                                yield AgentEvent::UserFacingToolOutput {
+                                    tool_name: tool_call.name.clone(),
+                                    content,
+                                };
+                            }
+
+                            let pager = self.result_pager.lock().await.clone();
+                            let result = result.map(|contents| pager.paginate(contents));
                             let mut response = message_tool_response.lock().await;
                             *response = response.clone().with_tool_response(id, result);
                         }
@@ -127,3 +207,33 @@ impl Agent {
         .boxed()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::formats::openai::format_messages;
+    use crate::providers::utils::ImageFormat;
+
+    #[test]
+    fn test_mixed_audience_result_splits_between_payload_and_display() {
+        let contents = vec![
+            Content::text("for everyone"),
+            Content::text("for the model").with_audience(vec![Role::Assistant]),
+            Content::text("here's a chart for you").with_audience(vec![Role::User]),
+            Content::image("aGVsbG8=", "image/png").with_audience(vec![Role::User]),
+            Content::text("for both").with_audience(vec![Role::User, Role::Assistant]),
+        ];
+
+        let display = user_facing_content(&contents);
+        assert_eq!(display, contents[2..4].to_vec());
+
+        let message = Message::user().with_tool_response("call_1", Ok(contents));
+        let payload = format_messages(&[message], &ImageFormat::OpenAi);
+        let sent = payload[0]["content"].as_str().unwrap();
+        assert!(sent.contains("for everyone"));
+        assert!(sent.contains("for the model"));
+        assert!(sent.contains("for both"));
+        assert!(!sent.contains("chart"));
+        assert_eq!(payload.len(), 1);
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use mcp_core::Content;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::utils::truncate_to_char_boundary;
+
+/// Config key of the limits users set per extension, a map from extension name to limits
+pub const TOOL_LIMITS_CONFIG_KEY: &str = "GOOSE_TOOL_LIMITS";
+
+/// Limits on the tool calls of one extension
+///
+/// An extension declares its defaults when it is added, and limits a user configures under
+/// `GOOSE_TOOL_LIMITS` take precedence over them field by field.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolLimits {
+    /// Seconds a call may run before it fails with a timeout error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// Timeouts in seconds of single tools, by tool name without the extension prefix
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tool_timeouts: HashMap<String, u64>,
+    /// Bytes of text a call may return, longer output is truncated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_bytes: Option<usize>,
+    /// Calls that may run at once, further calls wait for one to finish
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_calls: Option<usize>,
+}
+
+impl ToolLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_timeout(mut self, seconds: u64) -> Self {
+        self.timeout = Some(seconds);
+        self
+    }
+
+    pub fn with_tool_timeout(mut self, tool: impl Into<String>, seconds: u64) -> Self {
+        self.tool_timeouts.insert(tool.into(), seconds);
+        self
+    }
+
+    pub fn with_max_output_bytes(mut self, bytes: usize) -> Self {
+        self.max_output_bytes = Some(bytes);
+        self
+    }
+
+    pub fn with_max_concurrent_calls(mut self, calls: usize) -> Self {
+        self.max_concurrent_calls = Some(calls);
+        self
+    }
+
+    /// The limits a user configured for an extension
+    pub fn configured(extension: &str) -> Option<Self> {
+        Config::global()
+            .get_param::<HashMap<String, ToolLimits>>(TOOL_LIMITS_CONFIG_KEY)
+            .ok()?
+            .remove(extension)
+    }
+
+    /// These limits, with the ones set in `overrides` taking precedence
+    pub fn overridden_by(mut self, overrides: ToolLimits) -> Self {
+        self.timeout = overrides.timeout.or(self.timeout);
+        self.tool_timeouts.extend(overrides.tool_timeouts);
+        self.max_output_bytes = overrides.max_output_bytes.or(self.max_output_bytes);
+        self.max_concurrent_calls = overrides.max_concurrent_calls.or(self.max_concurrent_calls);
+        self
+    }
+
+    /// The timeout of a tool, which is its own if set and the extension's otherwise
+    pub fn timeout_for(&self, tool: &str) -> Option<Duration> {
+        self.tool_timeouts
+            .get(tool)
+            .copied()
+            .or(self.timeout)
+            .map(Duration::from_secs)
+    }
+}
+
+/// Truncate text content so the output stays within `max_bytes`, on character boundaries
+pub fn limit_output(contents: Vec<Content>, max_bytes: usize) -> Vec<Content> {
+    let mut remaining = max_bytes;
+    contents
+        .into_iter()
+        .map(|content| {
+            let Content::Text(mut text_content) = content else {
+                return content;
+            };
+            let text = &text_content.text;
+            if text.len() <= remaining {
+                remaining -= text.len();
+                return Content::Text(text_content);
+            }
+            // Only the text is cut, the annotations such as the audience are kept
+            let kept = truncate_to_char_boundary(text, remaining);
+            remaining = 0;
+            text_content.text = format!(
+                "{}\n\n[Output truncated after {} of {} bytes]",
+                kept,
+                kept.len(),
+                text.len()
+            );
+            Content::Text(text_content)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::role::Role;
+
+    #[test]
+    fn test_overridden_by() {
+        let declared = ToolLimits::new()
+            .with_timeout(60)
+            .with_tool_timeout("shell", 600)
+            .with_max_concurrent_calls(2);
+        let configured = ToolLimits::new()
+            .with_tool_timeout("shell", 30)
+            .with_max_output_bytes(1024);
+
+        let limits = declared.overridden_by(configured);
+        assert_eq!(limits.timeout_for("shell"), Some(Duration::from_secs(30)));
+        assert_eq!(limits.timeout_for("edit"), Some(Duration::from_secs(60)));
+        assert_eq!(limits.max_output_bytes, Some(1024));
+        assert_eq!(limits.max_concurrent_calls, Some(2));
+        assert_eq!(ToolLimits::new().timeout_for("shell"), None);
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let limits: HashMap<String, ToolLimits> = serde_yaml::from_str(
+            "developer:\n  timeout: 120\n  tool_timeouts:\n    shell: 900\n  max_output_bytes: 65536\n",
+        )
+        .unwrap();
+        assert_eq!(
+            limits["developer"],
+            ToolLimits::new()
+                .with_timeout(120)
+                .with_tool_timeout("shell", 900)
+                .with_max_output_bytes(65536)
+        );
+    }
+
+    #[test]
+    fn test_limit_output() {
+        let contents = vec![
+            Content::text("héllo"),
+            Content::image("abc", "image/png"),
+            Content::text("world"),
+        ];
+        let limited = limit_output(contents, 8);
+        assert_eq!(limited[0].as_text(), Some("héllo"));
+        assert!(limited[1].as_image().is_some());
+        assert_eq!(
+            limited[2].as_text(),
+            Some("wo\n\n[Output truncated after 2 of 5 bytes]")
+        );
+
+        // The annotations of truncated content are kept
+        let limited = limit_output(
+            vec![Content::text("hello")
+                .with_audience(vec![Role::User])
+                .with_priority(0.5)],
+            2,
+        );
+        assert_eq!(limited[0].audience(), Some(&vec![Role::User]));
+        assert_eq!(limited[0].priority(), Some(0.5));
+
+        // A cut inside a character moves back to the boundary before it
+        let limited = limit_output(vec![Content::text("héllo")], 2);
+        assert!(limited[0].as_text().unwrap().starts_with("h\n\n"));
+    }
+}
@@ -0,0 +1,400 @@
+//! Write-ahead journal of the turn in progress
+//!
+//! While tools run, the session file holds the assistant message with their requests, and
+//! their responses are only saved once every call finished. The journal keeps the responses
+//! that arrived in between, so after a crash the finished work is recovered and only the calls
+//! that never completed are reported to the model as interrupted.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use mcp_core::role::Role;
+use mcp_core::{Content, ToolError, ToolResult};
+
+use crate::message::{Message, MessageContent};
+use crate::session::storage::turn_journal_path;
+use crate::session::FsyncPolicy;
+
+/// The error given to the model for tool calls that did not finish before goose stopped
+pub const INTERRUPTED_TOOL_CALL: &str = "This tool call was interrupted because goose stopped before it finished. It may not have run, or may have run only in part.";
+
+/// Journal of the tool calls of a turn, removed once the turn is saved to the session
+///
+/// The first line is the assistant message with the tool requests, and every line after it a
+/// user message with one tool response.
+#[derive(Debug, Clone)]
+pub struct TurnJournal {
+    path: PathBuf,
+    policy: FsyncPolicy,
+}
+
+impl TurnJournal {
+    pub fn for_session(session_file: &Path) -> Self {
+        Self {
+            path: turn_journal_path(session_file),
+            policy: FsyncPolicy::from_config(),
+        }
+    }
+
+    /// Start the journal with the assistant message whose tool calls are about to run
+    pub fn begin(&self, request: &Message) -> Result<()> {
+        let mut line = serde_json::to_vec(request)?;
+        line.push(b'\n');
+        let mut file = fs::File::create(&self.path)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&self.path, fs::Permissions::from_mode(0o600))?;
+        }
+        file.write_all(&line)?;
+        if self.policy.should_sync() {
+            file.sync_data()?;
+        }
+        Ok(())
+    }
+
+    /// Add the result of a tool call that finished
+    pub fn record(&self, id: &str, result: &ToolResult<Vec<Content>>) -> Result<()> {
+        let response = Message::user().with_tool_response(id, result.clone());
+        let mut line = serde_json::to_vec(&response)?;
+        line.push(b'\n');
+        let mut file = fs::OpenOptions::new().append(true).open(&self.path)?;
+        file.write_all(&line)?;
+        if self.policy.should_sync() {
+            file.sync_data()?;
+        }
+        Ok(())
+    }
+
+    /// Remove the journal once the responses of the turn are part of the session
+    pub fn finish(&self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Tool calls of a turn that was cut short, by name
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecoveredTurn {
+    /// Calls whose results were recovered from the journal
+    pub completed: Vec<String>,
+    /// Calls that did not finish, which are answered with an interrupted error
+    pub interrupted: Vec<String>,
+}
+
+fn tool_request_ids(message: &Message) -> Vec<&str> {
+    message
+        .content
+        .iter()
+        .filter_map(|content| content.as_tool_request())
+        .map(|request| request.id.as_str())
+        .collect()
+}
+
+/// Complete the history of a session whose last turn stopped while its tools ran
+///
+/// When the session ends with the tool requests the journal was started with, the responses
+/// from the journal are added in a user message, along with an interrupted error for every
+/// call without one. The journal is removed afterwards, as is one the session is already past.
+pub fn recover_interrupted_turn(
+    session_file: &Path,
+    messages: &mut Vec<Message>,
+) -> Result<Option<RecoveredTurn>> {
+    let path = turn_journal_path(session_file);
+    let Ok(contents) = fs::read_to_string(&path) else {
+        return Ok(None);
+    };
+    let recovered = recover_from_journal(&contents, messages);
+    fs::remove_file(&path)?;
+    Ok(recovered)
+}
+
+fn recover_from_journal(contents: &str, messages: &mut Vec<Message>) -> Option<RecoveredTurn> {
+    let mut lines = contents.lines();
+    let request: Message = match serde_json::from_str(lines.next()?) {
+        Ok(request) => request,
+        Err(e) => {
+            tracing::warn!("Discarding unreadable turn journal: {}", e);
+            return None;
+        }
+    };
+
+    let last = messages.last()?;
+    let ids = tool_request_ids(&request);
+    if last.role != Role::Assistant || ids.is_empty() || tool_request_ids(last) != ids {
+        return None;
+    }
+
+    // A crash while appending leaves a torn last line, which is skipped
+    let mut results: HashMap<String, ToolResult<Vec<Content>>> = lines
+        .filter_map(|line| serde_json::from_str::<Message>(line).ok())
+        .flat_map(|message| message.content)
+        .filter_map(|content| match content {
+            MessageContent::ToolResponse(response) => Some((response.id, response.tool_result)),
+            _ => None,
+        })
+        .collect();
+
+    let mut turn = RecoveredTurn::default();
+    let mut responses = Message::user();
+    for request in request
+        .content
+        .iter()
+        .filter_map(|content| content.as_tool_request())
+    {
+        let name = request
+            .tool_call
+            .as_ref()
+            .map(|call| call.name.clone())
+            .unwrap_or_else(|_| "tool".to_string());
+        let result = match results.remove(&request.id) {
+            Some(result) => {
+                turn.completed.push(name);
+                result
+            }
+            None => {
+                turn.interrupted.push(name);
+                Err(ToolError::ExecutionError(INTERRUPTED_TOOL_CALL.to_string()))
+            }
+        };
+        responses = responses.with_tool_response(request.id.clone(), result);
+    }
+    messages.push(responses);
+    Some(turn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::{Agent, AgentEvent, SessionConfig, ToolLimits};
+    use crate::model::ModelConfig;
+    use crate::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+    use crate::providers::errors::ProviderError;
+    use crate::session::{persist_messages, read_messages, Identifier};
+    use futures::StreamExt;
+    use mcp_client::client::{ClientCapabilities, ClientInfo, Error, McpClientTrait};
+    use mcp_core::protocol::{
+        CallToolResult, GetPromptResult, InitializeResult, JsonRpcMessage, ListPromptsResult,
+        ListResourcesResult, ListToolsResult, ReadResourceResult,
+    };
+    use mcp_core::tool::{Tool, ToolCall};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tempfile::tempdir;
+    use tokio::sync::mpsc;
+
+    fn tool_request_message() -> Message {
+        Message::assistant()
+            .with_text("Checking both")
+            .with_tool_request("call_1", Ok(ToolCall::new("work__quick", json!({}))))
+            .with_tool_request("call_2", Ok(ToolCall::new("work__stuck", json!({}))))
+    }
+
+    /// Answers the first request with two tool calls, and any later one with text
+    struct TwoCallsProvider;
+
+    #[async_trait::async_trait]
+    impl Provider for TwoCallsProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("test-model".to_string())
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            let response = if messages.iter().any(|m| m.role == Role::Assistant) {
+                Message::assistant().with_text("done")
+            } else {
+                tool_request_message()
+            };
+            Ok((
+                response,
+                ProviderUsage::new("mock".to_string(), Usage::default()),
+            ))
+        }
+    }
+
+    /// `quick` returns at once and `stuck` never does
+    struct WorkClient;
+
+    #[async_trait::async_trait]
+    impl McpClientTrait for WorkClient {
+        async fn initialize(
+            &mut self,
+            _info: ClientInfo,
+            _capabilities: ClientCapabilities,
+        ) -> Result<InitializeResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn list_resources(
+            &self,
+            _next_cursor: Option<String>,
+        ) -> Result<ListResourcesResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn read_resource(&self, _uri: &str) -> Result<ReadResourceResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn list_tools(&self, _next_cursor: Option<String>) -> Result<ListToolsResult, Error> {
+            Ok(ListToolsResult {
+                tools: vec![],
+                next_cursor: None,
+            })
+        }
+
+        async fn call_tool(&self, name: &str, _arguments: Value) -> Result<CallToolResult, Error> {
+            if name == "stuck" {
+                std::future::pending::<()>().await;
+            }
+            Ok(CallToolResult {
+                content: vec![Content::text("quick result")],
+                is_error: None,
+            })
+        }
+
+        async fn list_prompts(
+            &self,
+            _next_cursor: Option<String>,
+        ) -> Result<ListPromptsResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn get_prompt(
+            &self,
+            _name: &str,
+            _arguments: Value,
+        ) -> Result<GetPromptResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn subscribe(&self) -> mpsc::Receiver<JsonRpcMessage> {
+            mpsc::channel(1).1
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recover_turn_after_crash_between_dispatches() -> Result<()> {
+        let dir = tempdir()?;
+        let session_file = dir.path().join("crash.jsonl");
+        let mut messages = vec![Message::user().with_text("check both")];
+        persist_messages(&session_file, &messages, None).await?;
+
+        let agent = Agent::new();
+        agent.update_provider(Arc::new(TwoCallsProvider)).await?;
+        agent
+            .add_client("work", Box::new(WorkClient), ToolLimits::new())
+            .await;
+        let session = SessionConfig {
+            id: Identifier::Path(session_file.clone()),
+            working_dir: dir.path().to_path_buf(),
+            schedule_id: None,
+            execution_mode: None,
+        };
+
+        // Save messages as a frontend would, until the quick call finished and the stuck one
+        // keeps the turn from going on
+        {
+            let mut stream = agent.reply(&messages, Some(session)).await?;
+            while let Ok(Some(event)) =
+                tokio::time::timeout(Duration::from_millis(500), stream.next()).await
+            {
+                if let AgentEvent::Message(message) = event? {
+                    messages.push(message);
+                    persist_messages(&session_file, &messages, None).await?;
+                }
+            }
+        }
+        // The crash: the agent goes away with the journal still in place
+        drop(agent);
+        assert!(turn_journal_path(&session_file).exists());
+
+        let mut messages = read_messages(&session_file)?;
+        assert_eq!(messages.last().unwrap().role, Role::Assistant);
+        let turn = recover_interrupted_turn(&session_file, &mut messages)?.unwrap();
+        assert_eq!(turn.completed, vec!["work__quick"]);
+        assert_eq!(turn.interrupted, vec!["work__stuck"]);
+        assert!(!turn_journal_path(&session_file).exists());
+
+        let responses: Vec<_> = messages
+            .last()
+            .unwrap()
+            .content
+            .iter()
+            .filter_map(|content| content.as_tool_response())
+            .collect();
+        assert_eq!(responses.len(), 2);
+        let quick = responses[0].tool_result.as_ref().unwrap();
+        assert_eq!(quick[0].as_text(), Some("quick result"));
+        assert!(matches!(
+            &responses[1].tool_result,
+            Err(ToolError::ExecutionError(message)) if message == INTERRUPTED_TOOL_CALL
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_journal_of_finished_turn_is_discarded() -> Result<()> {
+        let dir = tempdir()?;
+        let session_file = dir.path().join("done.jsonl");
+        let journal = TurnJournal::for_session(&session_file);
+        journal.begin(&tool_request_message())?;
+        journal.record("call_1", &Ok(vec![Content::text("quick result")]))?;
+
+        // The responses were saved, so the session is already past the journaled turn
+        let mut messages = vec![
+            tool_request_message(),
+            Message::user()
+                .with_tool_response("call_1", Ok(vec![]))
+                .with_tool_response("call_2", Ok(vec![])),
+        ];
+        let expected = messages.clone();
+        assert_eq!(
+            recover_interrupted_turn(&session_file, &mut messages)?,
+            None
+        );
+        assert_eq!(messages, expected);
+        assert!(!turn_journal_path(&session_file).exists());
+
+        journal.begin(&tool_request_message())?;
+        journal.finish()?;
+        assert!(!turn_journal_path(&session_file).exists());
+        journal.finish()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_torn_journal_line_is_skipped() {
+        let request = serde_json::to_string(&tool_request_message()).unwrap();
+        let response = serde_json::to_string(
+            &Message::user().with_tool_response("call_2", Ok(vec![Content::text("late")])),
+        )
+        .unwrap();
+        let contents = format!("{}\n{}\n{{\"role\":\"user\",\"cont", request, response);
+
+        let mut messages = vec![tool_request_message()];
+        let turn = recover_from_journal(&contents, &mut messages).unwrap();
+        assert_eq!(turn.completed, vec!["work__stuck"]);
+        assert_eq!(turn.interrupted, vec!["work__quick"]);
+        assert_eq!(messages.len(), 2);
+
+        let mut messages = vec![tool_request_message()];
+        assert_eq!(recover_from_journal("not json\n", &mut messages), None);
+        assert_eq!(messages.len(), 1);
+    }
+}
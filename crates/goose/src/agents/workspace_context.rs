@@ -0,0 +1,639 @@
+//! A summary of the workspace for the system prompt
+//!
+//! Models otherwise spend their first turns running `pwd`, `ls` and `git status` to find
+//! their way around. The summary is collected once per session and again when the model calls
+//! the `platform__refresh_context` tool. It is kept within a token budget: the directory tree
+//! loses its deepest levels first, and huge directories are sampled rather than walked.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::utils::truncate_to_char_boundary;
+
+/// Default token budget of the workspace context
+pub const DEFAULT_WORKSPACE_CONTEXT_TOKENS: usize = 1_000;
+
+// Rough approximation: 1 token per 4 characters for English text
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Changed files listed from git status, the rest are only counted
+const MAX_CHANGED_FILES: usize = 20;
+
+/// Build files listed, the rest are only counted
+const MAX_BUILD_FILES: usize = 10;
+
+/// Languages listed, by number of files
+const MAX_LANGUAGES: usize = 5;
+
+const BUILD_FILES: &[&str] = &[
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "setup.py",
+    "requirements.txt",
+    "go.mod",
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+    "Gemfile",
+    "composer.json",
+    "Package.swift",
+    "CMakeLists.txt",
+    "Makefile",
+    "justfile",
+    "Justfile",
+];
+
+/// What the workspace context contains, configured with GOOSE_WORKSPACE_CONTEXT
+///
+/// The setting is either `false` to turn the context off, or an object that changes single
+/// fields, such as `{"git": false, "max_tokens": 500}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkspaceContextConfig {
+    pub enabled: bool,
+    /// The directory tree, gitignore-aware
+    pub tree: bool,
+    /// The branch and the changed files
+    pub git: bool,
+    /// Languages by file extension, and build files
+    pub languages: bool,
+    /// Operating system, architecture and shell
+    pub os: bool,
+    /// Directory levels below the workspace root that are walked
+    pub max_depth: usize,
+    /// Entries listed per directory, the rest are only counted
+    pub max_entries_per_dir: usize,
+    /// Entries listed in total, after which the walk stops
+    pub max_entries: usize,
+    pub max_tokens: usize,
+}
+
+impl Default for WorkspaceContextConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            tree: true,
+            git: true,
+            languages: true,
+            os: true,
+            max_depth: 3,
+            max_entries_per_dir: 20,
+            max_entries: 2_000,
+            max_tokens: DEFAULT_WORKSPACE_CONTEXT_TOKENS,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WorkspaceContextSetting {
+    Enabled(bool),
+    Config(WorkspaceContextConfig),
+}
+
+impl WorkspaceContextConfig {
+    pub fn from_config() -> Self {
+        match Config::global().get_param::<WorkspaceContextSetting>("GOOSE_WORKSPACE_CONTEXT") {
+            Ok(WorkspaceContextSetting::Enabled(enabled)) => Self {
+                enabled,
+                ..Self::default()
+            },
+            Ok(WorkspaceContextSetting::Config(config)) => config,
+            Err(_) => Self::default(),
+        }
+    }
+
+    fn max_chars(&self) -> usize {
+        self.max_tokens.saturating_mul(CHARS_PER_TOKEN)
+    }
+}
+
+/// Collect the workspace context of `root`, or `None` when it is disabled or empty
+///
+/// This reads the file system and runs git, so call it off the async runtime.
+pub fn collect_workspace_context(root: &Path, config: &WorkspaceContextConfig) -> Option<String> {
+    if !config.enabled {
+        return None;
+    }
+    // Symlinks are never followed, so resolve the root itself once
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+
+    let mut sections = vec![format!("Working directory: {}", root.display())];
+    if config.os {
+        sections.push(os_section());
+    }
+    if config.git {
+        sections.extend(git_section(&root));
+    }
+    let walked = (config.tree || config.languages).then(|| walk(&root, config));
+    if let Some(walked) = walked.as_ref().filter(|_| config.languages) {
+        sections.extend(languages_section(walked));
+    }
+
+    // The tree goes last and gets what is left of the budget
+    let mut context = sections.join("\n");
+    if let Some(walked) = walked.as_ref().filter(|_| config.tree) {
+        let budget = config.max_chars().saturating_sub(context.len() + 1);
+        if let Some(tree) = tree_section(walked, config.max_depth, budget) {
+            context.push('\n');
+            context.push_str(&tree);
+        }
+    }
+    Some(truncate(context, config.max_chars()))
+}
+
+fn os_section() -> String {
+    let mut os = format!("OS: {} ({})", std::env::consts::OS, std::env::consts::ARCH);
+    if let Ok(shell) = std::env::var("SHELL") {
+        os.push_str(&format!(", shell {}", shell));
+    }
+    os
+}
+
+fn git(root: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The branch and the changed files, or `None` outside of a git repository
+fn git_section(root: &Path) -> Option<String> {
+    let branch = git(root, &["rev-parse", "--abbrev-ref", "HEAD"])
+        // A repository without commits has no HEAD to resolve yet
+        .or_else(|| git(root, &["symbolic-ref", "--short", "HEAD"]))?;
+    let branch = branch.trim();
+    let status = git(root, &["status", "--porcelain"]).unwrap_or_default();
+    let changed: Vec<&str> = status.lines().filter(|line| !line.is_empty()).collect();
+
+    let mut section = match changed.len() {
+        0 => format!("Git: branch {}, no changes", branch),
+        1 => format!("Git: branch {}, 1 changed file", branch),
+        count => format!("Git: branch {}, {} changed files", branch, count),
+    };
+    for line in changed.iter().take(MAX_CHANGED_FILES) {
+        section.push_str(&format!("\n  {}", line));
+    }
+    if changed.len() > MAX_CHANGED_FILES {
+        section.push_str(&format!("\n  … {} more", changed.len() - MAX_CHANGED_FILES));
+    }
+    Some(section)
+}
+
+fn language(extension: &str) -> Option<&'static str> {
+    Some(match extension {
+        "rs" => "Rust",
+        "py" => "Python",
+        "js" | "jsx" | "mjs" | "cjs" => "JavaScript",
+        "ts" | "tsx" => "TypeScript",
+        "go" => "Go",
+        "java" => "Java",
+        "kt" | "kts" => "Kotlin",
+        "rb" => "Ruby",
+        "c" | "h" => "C",
+        "cc" | "cpp" | "cxx" | "hpp" => "C++",
+        "cs" => "C#",
+        "swift" => "Swift",
+        "php" => "PHP",
+        "scala" => "Scala",
+        "sh" | "bash" => "Shell",
+        _ => return None,
+    })
+}
+
+fn languages_section(walked: &Walked) -> Option<String> {
+    let mut lines = Vec::new();
+
+    let mut languages: Vec<(&str, usize)> = walked
+        .languages
+        .iter()
+        .map(|(language, count)| (*language, *count))
+        .collect();
+    languages.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    if !languages.is_empty() {
+        let listed: Vec<String> = languages
+            .iter()
+            .take(MAX_LANGUAGES)
+            .map(|(language, count)| match count {
+                1 => format!("{} (1 file)", language),
+                count => format!("{} ({} files)", language, count),
+            })
+            .collect();
+        lines.push(format!("Languages: {}", listed.join(", ")));
+    }
+
+    if !walked.build_files.is_empty() {
+        let mut build_files = walked
+            .build_files
+            .iter()
+            .take(MAX_BUILD_FILES)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ");
+        if walked.build_files.len() > MAX_BUILD_FILES {
+            build_files.push_str(&format!(
+                " and {} more",
+                walked.build_files.len() - MAX_BUILD_FILES
+            ));
+        }
+        lines.push(format!("Build files: {}", build_files));
+    }
+
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// The directory tree at the deepest level that fits the budget
+fn tree_section(walked: &Walked, max_depth: usize, budget: usize) -> Option<String> {
+    if walked.tree.children.is_empty() {
+        return None;
+    }
+    for depth in (1..=max_depth.max(1)).rev() {
+        let mut lines = Vec::new();
+        render(&walked.tree, 0, depth, &mut lines);
+        if walked.truncated {
+            lines.push("… (more entries not listed)".to_string());
+        }
+        let tree = format!("Directory tree (depth {}):\n{}", depth, lines.join("\n"));
+        if tree.len() <= budget || depth == 1 {
+            return Some(tree);
+        }
+    }
+    None
+}
+
+fn render(node: &Node, level: usize, depth: usize, lines: &mut Vec<String>) {
+    let indent = "  ".repeat(level);
+    for (name, child) in &node.children {
+        match child.kind {
+            Kind::Dir => {
+                lines.push(format!("{}{}/", indent, name));
+                if level + 1 < depth {
+                    render(child, level + 1, depth, lines);
+                }
+            }
+            Kind::Symlink => lines.push(format!("{}{}@", indent, name)),
+            Kind::File => lines.push(format!("{}{}", indent, name)),
+        }
+    }
+    if node.omitted > 0 {
+        lines.push(format!("{}… {} more", indent, node.omitted));
+    }
+}
+
+fn truncate(mut text: String, max_chars: usize) -> String {
+    if text.len() <= max_chars {
+        return text;
+    }
+    let kept = truncate_to_char_boundary(&text, max_chars);
+    // Cut at a line, so no entry is shown half
+    let end = kept.rfind('\n').unwrap_or(kept.len());
+    text.truncate(end);
+    text.push_str("\n… (truncated)");
+    text
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Kind {
+    #[default]
+    File,
+    Dir,
+    Symlink,
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    kind: Kind,
+    children: BTreeMap<String, Node>,
+    /// Entries of the directory that were counted but not listed
+    omitted: usize,
+}
+
+impl Node {
+    fn get_mut(&mut self, path: &Path) -> Option<&mut Node> {
+        path.iter().try_fold(self, |node, name| {
+            node.children.get_mut(name.to_string_lossy().as_ref())
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct Walked {
+    tree: Node,
+    languages: HashMap<&'static str, usize>,
+    build_files: Vec<String>,
+    /// The walk stopped at `max_entries`
+    truncated: bool,
+}
+
+/// Walk the workspace without following symlinks, skipping what git ignores and hidden files
+///
+/// Each directory lists at most `max_entries_per_dir` entries and is not descended into past
+/// that, so a huge directory costs one read of its listing.
+fn walk(root: &Path, config: &WorkspaceContextConfig) -> Walked {
+    let per_dir = config.max_entries_per_dir.max(1);
+    let counts: Arc<Mutex<HashMap<PathBuf, usize>>> = Arc::default();
+    let filter_counts = Arc::clone(&counts);
+    let walker = WalkBuilder::new(root)
+        .max_depth(Some(config.max_depth.max(1)))
+        .follow_links(false)
+        .require_git(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(move |entry| {
+            let Some(parent) = entry.path().parent() else {
+                return true;
+            };
+            let mut counts = filter_counts.lock().unwrap();
+            let count = counts.entry(parent.to_path_buf()).or_default();
+            *count += 1;
+            *count <= per_dir
+        })
+        .build();
+
+    let mut walked = Walked::default();
+    let mut listed = 0;
+    for entry in walker.flatten() {
+        if entry.depth() == 0 {
+            continue;
+        }
+        if listed == config.max_entries {
+            walked.truncated = true;
+            break;
+        }
+        let Ok(relative) = entry.path().strip_prefix(root) else {
+            continue;
+        };
+        let kind = match entry.file_type() {
+            _ if entry.path_is_symlink() => Kind::Symlink,
+            Some(file_type) if file_type.is_dir() => Kind::Dir,
+            _ => Kind::File,
+        };
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let parent = relative.parent().unwrap_or(Path::new(""));
+        let Some(node) = walked.tree.get_mut(parent) else {
+            continue;
+        };
+        node.children.insert(
+            name.clone(),
+            Node {
+                kind,
+                ..Node::default()
+            },
+        );
+        listed += 1;
+
+        if kind == Kind::File {
+            if BUILD_FILES.contains(&name.as_str()) {
+                walked
+                    .build_files
+                    .push(relative.to_string_lossy().into_owned());
+            }
+            if let Some(language) = relative
+                .extension()
+                .and_then(|extension| language(&extension.to_string_lossy()))
+            {
+                *walked.languages.entry(language).or_default() += 1;
+            }
+        }
+    }
+
+    for (dir, count) in counts.lock().unwrap().iter() {
+        if *count > per_dir {
+            let relative = dir.strip_prefix(root).unwrap_or(dir);
+            if let Some(node) = walked.tree.get_mut(relative) {
+                node.omitted = count - per_dir;
+            }
+        }
+    }
+    walked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn only(component: &str) -> WorkspaceContextConfig {
+        WorkspaceContextConfig {
+            tree: component == "tree",
+            git: component == "git",
+            languages: component == "languages",
+            os: component == "os",
+            ..WorkspaceContextConfig::default()
+        }
+    }
+
+    /// A small repository layout: a Rust crate, a Python script, ignored build output and a
+    /// hidden directory
+    fn fixture() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        for (path, content) in [
+            ("Cargo.toml", "[package]\nname = \"fixture\"\n"),
+            (".gitignore", "target/\n*.log\n"),
+            ("src/main.rs", "fn main() {}\n"),
+            ("src/lib.rs", "pub fn lib() {}\n"),
+            ("src/util/mod.rs", "\n"),
+            ("scripts/build.py", "print('hi')\n"),
+            ("scripts/pyproject.toml", "[project]\n"),
+            ("target/debug/fixture", "binary"),
+            ("run.log", "log"),
+            (".hidden/secret", "secret"),
+        ] {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    fn has_git() -> bool {
+        Command::new("git").arg("--version").output().is_ok()
+    }
+
+    #[test]
+    fn test_tree_respects_gitignore_and_hidden_files() {
+        let dir = fixture();
+        let context = collect_workspace_context(dir.path(), &only("tree")).unwrap();
+        let tree = context.split_once("Directory tree (depth 3):\n").unwrap().1;
+        assert_eq!(
+            tree,
+            [
+                "Cargo.toml",
+                "scripts/",
+                "  build.py",
+                "  pyproject.toml",
+                "src/",
+                "  lib.rs",
+                "  main.rs",
+                "  util/",
+                "    mod.rs",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_languages_and_build_files() {
+        let dir = fixture();
+        let context = collect_workspace_context(dir.path(), &only("languages")).unwrap();
+        assert!(context.contains("Languages: Rust (3 files), Python (1 file)"));
+        assert!(context.contains("Build files: Cargo.toml, scripts/pyproject.toml"));
+        assert!(!context.contains("Directory tree"));
+    }
+
+    #[test]
+    fn test_components_can_be_disabled() {
+        let dir = fixture();
+        let context = collect_workspace_context(dir.path(), &only("os")).unwrap();
+        let lines: Vec<&str> = context.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("Working directory: "));
+        assert!(lines[1].starts_with(&format!("OS: {}", std::env::consts::OS)));
+
+        let disabled = WorkspaceContextConfig {
+            enabled: false,
+            ..WorkspaceContextConfig::default()
+        };
+        assert_eq!(collect_workspace_context(dir.path(), &disabled), None);
+    }
+
+    #[test]
+    fn test_setting_is_a_flag_or_an_object() {
+        let setting: WorkspaceContextSetting = serde_json::from_str("false").unwrap();
+        assert!(matches!(setting, WorkspaceContextSetting::Enabled(false)));
+        let setting: WorkspaceContextSetting =
+            serde_json::from_str(r#"{"git": false, "max_tokens": 500}"#).unwrap();
+        let WorkspaceContextSetting::Config(config) = setting else {
+            panic!("expected an object setting");
+        };
+        assert!(config.enabled && config.tree && !config.git);
+        assert_eq!(config.max_tokens, 500);
+    }
+
+    #[test]
+    fn test_git_branch_and_changes() {
+        if !has_git() {
+            return;
+        }
+        let dir = fixture();
+        let run = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?}", args);
+        };
+        run(&["init", "-q", "-b", "main"]);
+        let context = collect_workspace_context(dir.path(), &only("git")).unwrap();
+        assert!(
+            context.contains("Git: branch main, 5 changed files"),
+            "{}",
+            context
+        );
+        assert!(context.contains("  ?? Cargo.toml"));
+
+        // Outside of a repository there is no git section
+        let plain = TempDir::new().unwrap();
+        let context = collect_workspace_context(plain.path(), &only("git")).unwrap();
+        assert!(!context.contains("Git:"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_are_not_followed() {
+        let outside = TempDir::new().unwrap();
+        fs::create_dir(outside.path().join("elsewhere")).unwrap();
+        fs::write(outside.path().join("elsewhere/outside.rs"), "").unwrap();
+
+        let dir = fixture();
+        std::os::unix::fs::symlink(outside.path().join("elsewhere"), dir.path().join("link"))
+            .unwrap();
+        let context =
+            collect_workspace_context(dir.path(), &WorkspaceContextConfig::default()).unwrap();
+        assert!(context.contains("\nlink@\n"), "{}", context);
+        assert!(!context.contains("outside.rs"));
+        assert!(context.contains("Languages: Rust (3 files), Python (1 file)"));
+    }
+
+    #[test]
+    fn test_huge_directories_are_sampled() {
+        let dir = TempDir::new().unwrap();
+        for i in 0..50 {
+            let sub = dir.path().join(format!("dir{:02}", i));
+            fs::create_dir(&sub).unwrap();
+            for j in 0..50 {
+                fs::write(sub.join(format!("file{:02}.rs", j)), "").unwrap();
+            }
+        }
+        let config = WorkspaceContextConfig {
+            max_entries_per_dir: 3,
+            max_tokens: 10_000,
+            ..only("tree")
+        };
+        let context = collect_workspace_context(dir.path(), &config).unwrap();
+        let tree = context.split_once("Directory tree (depth 3):\n").unwrap().1;
+        assert_eq!(
+            tree.lines().take(6).collect::<Vec<_>>(),
+            [
+                "dir00/",
+                "  file00.rs",
+                "  file01.rs",
+                "  file02.rs",
+                "  … 47 more",
+                "dir01/"
+            ]
+        );
+        assert!(tree.ends_with("… 47 more"));
+        assert!(!tree.contains("dir03/"));
+
+        // The walk stops at the total limit
+        let config = WorkspaceContextConfig {
+            max_entries: 5,
+            ..config
+        };
+        let context = collect_workspace_context(dir.path(), &config).unwrap();
+        assert!(
+            context.ends_with("… (more entries not listed)"),
+            "{}",
+            context
+        );
+    }
+
+    #[test]
+    fn test_budget_drops_deep_levels_first() {
+        let dir = fixture();
+        let config = WorkspaceContextConfig {
+            max_tokens: 25,
+            ..only("tree")
+        };
+        let context = collect_workspace_context(dir.path(), &config).unwrap();
+        assert!(context.len() <= 100, "{}", context);
+        assert!(context.contains("Directory tree (depth 1):\nCargo.toml\nscripts/\nsrc/"));
+
+        // Even the top level is cut once nothing else fits
+        let config = WorkspaceContextConfig {
+            max_tokens: 5,
+            ..only("tree")
+        };
+        let context = collect_workspace_context(dir.path(), &config).unwrap();
+        assert!(context.ends_with("… (truncated)"));
+        assert!(!context.contains("Cargo.toml"));
+    }
+}
@@ -11,6 +11,8 @@ use std::io::Write;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
+use super::workspace::{TrustStore, Workspace, WorkspaceConfigError};
+
 pub static APP_STRATEGY: Lazy<AppStrategyArgs> = Lazy::new(|| AppStrategyArgs {
     top_level_domain: "Block".to_string(),
     author: "Block".to_string(),
@@ -37,6 +39,8 @@ pub enum ConfigError {
     KeyringError(String),
     #[error("Failed to lock config file: {0}")]
     LockError(String),
+    #[error("Invalid workspace config {0}")]
+    WorkspaceError(#[from] WorkspaceConfigError),
 }
 
 impl From<serde_json::Error> for ConfigError {
@@ -69,7 +73,8 @@ impl From<keyring::Error> for ConfigError {
 ///
 /// Configuration values are loaded with the following precedence:
 /// 1. Environment variables (exact key match)
-/// 2. Configuration file (~/.config/goose/config.yaml by default)
+/// 2. The config of the workspace, in a `.goose/config.yaml` at or above the working directory
+/// 3. Configuration file (~/.config/goose/config.yaml by default)
 ///
 /// Secrets are loaded with the following precedence:
 /// 1. Environment variables (exact key match)
@@ -106,6 +111,14 @@ impl From<keyring::Error> for ConfigError {
 pub struct Config {
     config_path: PathBuf,
     secrets: SecretStorage,
+    workspace: WorkspaceSource,
+}
+
+/// Where the workspace config is looked up from
+enum WorkspaceSource {
+    None,
+    CurrentDir,
+    Dir(PathBuf),
 }
 
 enum SecretStorage {
@@ -140,6 +153,7 @@ impl Default for Config {
         Config {
             config_path,
             secrets,
+            workspace: WorkspaceSource::CurrentDir,
         }
     }
 }
@@ -163,6 +177,7 @@ impl Config {
             secrets: SecretStorage::Keyring {
                 service: service.to_string(),
             },
+            workspace: WorkspaceSource::None,
         })
     }
 
@@ -179,9 +194,56 @@ impl Config {
             secrets: SecretStorage::File {
                 path: secrets_path.as_ref().to_path_buf(),
             },
+            workspace: WorkspaceSource::None,
         })
     }
 
+    /// Overlay the config of the workspace at or above `dir`
+    ///
+    /// The global config looks it up from the current directory, configs created with a
+    /// custom path have no workspace unless it is set here.
+    pub fn with_workspace<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.workspace = WorkspaceSource::Dir(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// The workspace whose config overlays this one, if there is one
+    pub fn workspace(&self) -> Result<Option<Workspace>, ConfigError> {
+        let dir = match &self.workspace {
+            WorkspaceSource::None => return Ok(None),
+            WorkspaceSource::CurrentDir => env::current_dir()?,
+            WorkspaceSource::Dir(dir) => dir.clone(),
+        };
+        Ok(Workspace::load(&dir)?)
+    }
+
+    /// Whether the user trusted the workspace at `root` to load privileged settings
+    pub fn is_workspace_trusted(&self, root: &Path) -> bool {
+        TrustStore::beside(&self.config_path).is_trusted(root)
+    }
+
+    /// Trust the workspace at `root` to load privileged settings, such as commands
+    pub fn trust_workspace(&self, root: &Path) -> Result<(), ConfigError> {
+        Ok(TrustStore::beside(&self.config_path).trust(root)?)
+    }
+
+    /// The values the workspace overlays, without privileged ones unless it is trusted
+    fn workspace_values(&self) -> HashMap<String, Value> {
+        let workspace = match self.workspace() {
+            Ok(Some(workspace)) => workspace,
+            Ok(None) => return HashMap::new(),
+            Err(e) => {
+                tracing::warn!("Ignoring the workspace config: {}", e);
+                return HashMap::new();
+            }
+        };
+        let mut values = workspace.values();
+        if !self.is_workspace_trusted(&workspace.root) {
+            values.retain(|key, _| !super::workspace::is_privileged(key));
+        }
+        values
+    }
+
     /// Check if this config already exists
     pub fn exists(&self) -> bool {
         self.config_path.exists()
@@ -529,7 +591,8 @@ impl Config {
     ///
     /// This will attempt to get the value from:
     /// 1. Environment variable with the exact key name
-    /// 2. Configuration file
+    /// 2. Workspace config
+    /// 3. Configuration file
     ///
     /// The value will be deserialized into the requested type. This works with
     /// both simple types (String, i32, etc.) and complex types that implement
@@ -550,6 +613,10 @@ impl Config {
             return Ok(serde_json::from_value(value)?);
         }
 
+        if let Some(value) = self.workspace_values().remove(key) {
+            return Ok(serde_json::from_value(value)?);
+        }
+
         // Load current values from file
         let values = self.load_values()?;
 
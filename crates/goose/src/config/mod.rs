@@ -2,12 +2,14 @@ pub mod base;
 mod experiments;
 pub mod extensions;
 pub mod permission;
+pub mod workspace;
 
 pub use crate::agents::ExtensionConfig;
 pub use base::{Config, ConfigError, APP_STRATEGY};
 pub use experiments::ExperimentManager;
 pub use extensions::{ExtensionConfigManager, ExtensionEntry};
 pub use permission::PermissionManager;
+pub use workspace::{Workspace, WorkspaceConfig};
 
 pub use extensions::DEFAULT_DISPLAY_NAME;
 pub use extensions::DEFAULT_EXTENSION;
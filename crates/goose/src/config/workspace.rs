@@ -0,0 +1,330 @@
+//! Configuration of a workspace, from a `.goose/config.yaml` in its root
+//!
+//! The file is found by walking up from the working directory, and overlays the global
+//! config: environment variables still win, then the workspace, then the config file. Settings
+//! that run commands or decide where requests go are only loaded from a workspace the user
+//! trusted, as anyone who can commit to a repository can write its config.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+/// The file of a workspace config, relative to the workspace root
+pub const WORKSPACE_CONFIG_PATH: &str = ".goose/config.yaml";
+
+/// The file next to the global config that lists trusted workspaces
+const TRUST_STORE_FILE: &str = "trusted_workspaces.yaml";
+
+/// What a workspace changes about goose
+///
+/// ```yaml
+/// model: gpt-4o
+/// config:
+///   GOOSE_MAX_TURNS: 50
+/// instructions:
+///   - Run `just test` before you commit.
+/// allowed_tools:
+///   - developer__*
+///   - memory__retrieve_memories
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkspaceConfig {
+    /// The default model in this workspace, overlaid as GOOSE_MODEL
+    pub model: Option<String>,
+    /// Config keys that overlay the global config
+    pub config: BTreeMap<String, Value>,
+    /// Sections added to the system prompt
+    pub instructions: Vec<String>,
+    /// The extension tools the agent may use, by name or by a prefix ending in `*`
+    pub allowed_tools: Option<Vec<String>>,
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum WorkspaceConfigError {
+    #[error("{}: {message}", path.display())]
+    Parse { path: PathBuf, message: String },
+    #[error("{}: `{field}` {message}", path.display())]
+    Invalid {
+        path: PathBuf,
+        field: String,
+        message: String,
+    },
+}
+
+/// A workspace with a config
+#[derive(Debug, Clone, PartialEq)]
+pub struct Workspace {
+    pub root: PathBuf,
+    pub config: WorkspaceConfig,
+}
+
+impl Workspace {
+    /// The nearest workspace root at or above `dir`
+    pub fn discover(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .find(|ancestor| ancestor.join(WORKSPACE_CONFIG_PATH).is_file())
+            .map(Path::to_path_buf)
+    }
+
+    /// Load the nearest workspace at or above `dir`, if there is one
+    pub fn load(dir: &Path) -> Result<Option<Self>, WorkspaceConfigError> {
+        let Some(root) = Self::discover(dir) else {
+            return Ok(None);
+        };
+        let path = root.join(WORKSPACE_CONFIG_PATH);
+        let content = std::fs::read_to_string(&path).map_err(|e| WorkspaceConfigError::Parse {
+            path: path.clone(),
+            message: e.to_string(),
+        })?;
+        let config = if content.trim().is_empty() {
+            WorkspaceConfig::default()
+        } else {
+            serde_yaml::from_str(&content).map_err(|e| WorkspaceConfigError::Parse {
+                path: path.clone(),
+                message: e.to_string(),
+            })?
+        };
+        validate(&path, &config)?;
+        Ok(Some(Self { root, config }))
+    }
+
+    /// The config keys this workspace overlays
+    pub fn values(&self) -> HashMap<String, Value> {
+        let mut values: HashMap<String, Value> = self.config.config.clone().into_iter().collect();
+        if let Some(model) = &self.config.model {
+            values.insert("GOOSE_MODEL".to_string(), Value::String(model.clone()));
+        }
+        values
+    }
+
+    /// The overlaid keys that are only loaded once the workspace is trusted
+    pub fn privileged_keys(&self) -> Vec<String> {
+        self.config
+            .config
+            .keys()
+            .filter(|key| is_privileged(key))
+            .cloned()
+            .collect()
+    }
+
+    /// Whether the agent may use a tool under the `allowed_tools` of this workspace
+    ///
+    /// Platform tools are always allowed, as the agent needs them to work.
+    pub fn allows_tool(&self, name: &str) -> bool {
+        tool_allowed(self.config.allowed_tools.as_deref(), name)
+    }
+}
+
+/// Whether a tool matches one of `patterns`, which allow every tool when there are none
+pub fn tool_allowed(patterns: Option<&[String]>, name: &str) -> bool {
+    let Some(patterns) = patterns else {
+        return true;
+    };
+    name.starts_with("platform__")
+        || patterns
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == pattern,
+            })
+}
+
+/// Settings that run commands, or that decide where requests and their keys are sent
+pub fn is_privileged(key: &str) -> bool {
+    let key = key.to_lowercase();
+    key == "extensions"
+        || ["_command", "_cmd", "_host", "_url", "_endpoint"]
+            .iter()
+            .any(|suffix| key.ends_with(suffix))
+}
+
+fn validate(path: &Path, config: &WorkspaceConfig) -> Result<(), WorkspaceConfigError> {
+    let invalid = |field: String, message: &str| WorkspaceConfigError::Invalid {
+        path: path.to_path_buf(),
+        field,
+        message: message.to_string(),
+    };
+    if config
+        .model
+        .as_deref()
+        .is_some_and(|model| model.trim().is_empty())
+    {
+        return Err(invalid("model".to_string(), "must not be empty"));
+    }
+    if config.model.is_some() && config.config.contains_key("GOOSE_MODEL") {
+        return Err(invalid(
+            "config.GOOSE_MODEL".to_string(),
+            "is also set by `model`, set only one of them",
+        ));
+    }
+    for key in config.config.keys() {
+        if key.trim().is_empty() {
+            return Err(invalid("config".to_string(), "has an empty key"));
+        }
+    }
+    for (index, instruction) in config.instructions.iter().enumerate() {
+        if instruction.trim().is_empty() {
+            return Err(invalid(format!("instructions[{}]", index), "is empty"));
+        }
+    }
+    for (index, pattern) in config.allowed_tools.iter().flatten().enumerate() {
+        let field = format!("allowed_tools[{}]", index);
+        if pattern.is_empty() || pattern == "*" {
+            return Err(invalid(field, "must name a tool or a tool prefix"));
+        }
+        if pattern.trim_end_matches('*').contains('*') {
+            return Err(invalid(field, "may only have `*` at its end"));
+        }
+    }
+    Ok(())
+}
+
+/// Workspaces the user trusted to load privileged settings, by the hash of their path
+pub struct TrustStore {
+    path: PathBuf,
+}
+
+impl TrustStore {
+    /// The trust store next to the global config at `config_path`
+    pub fn beside(config_path: &Path) -> Self {
+        Self {
+            path: config_path.with_file_name(TRUST_STORE_FILE),
+        }
+    }
+
+    fn load(&self) -> BTreeMap<String, String> {
+        std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|content| serde_yaml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn is_trusted(&self, root: &Path) -> bool {
+        self.load().contains_key(&path_hash(root))
+    }
+
+    pub fn trust(&self, root: &Path) -> std::io::Result<()> {
+        let mut trusted = self.load();
+        trusted.insert(path_hash(root), root.display().to_string());
+        let content = serde_yaml::to_string(&trusted)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        std::fs::write(&self.path, content)
+    }
+}
+
+fn path_hash(root: &Path) -> String {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    format!("{:x}", Sha256::digest(root.to_string_lossy().as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use serial_test::serial;
+    use tempfile::TempDir;
+
+    /// A global config, and a repository with a workspace config and a subdirectory
+    fn setup(workspace_config: &str) -> (TempDir, Config, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        std::fs::create_dir_all(repo.join(".goose")).unwrap();
+        std::fs::create_dir_all(repo.join("src")).unwrap();
+        std::fs::write(repo.join(WORKSPACE_CONFIG_PATH), workspace_config).unwrap();
+        let config = Config::new_with_file_secrets(
+            dir.path().join("config.yaml"),
+            dir.path().join("secrets.yaml"),
+        )
+        .unwrap()
+        .with_workspace(repo.join("src"));
+        (dir, config, repo)
+    }
+
+    #[test]
+    #[serial]
+    fn test_workspace_merge_precedence() {
+        let (_dir, config, repo) = setup(
+            "model: workspace-model\nconfig:\n  GOOSE_MAX_TURNS: 50\n  GOOSE_LEAD_TURNS: 2\n",
+        );
+        config
+            .set_param("GOOSE_MODEL", Value::String("global-model".into()))
+            .unwrap();
+        config
+            .set_param("GOOSE_MAX_TURNS", Value::from(10))
+            .unwrap();
+        config
+            .set_param("GOOSE_PROVIDER", Value::from("openai"))
+            .unwrap();
+
+        // The workspace overlays the config file, which fills in the rest
+        assert_eq!(config.workspace().unwrap().unwrap().root, repo);
+        assert_eq!(
+            config.get_param::<String>("GOOSE_MODEL").unwrap(),
+            "workspace-model"
+        );
+        assert_eq!(config.get_param::<u32>("GOOSE_MAX_TURNS").unwrap(), 50);
+        assert_eq!(
+            config.get_param::<String>("GOOSE_PROVIDER").unwrap(),
+            "openai"
+        );
+        // Environment variables still win
+        temp_env::with_var("GOOSE_LEAD_TURNS", Some("7"), || {
+            assert_eq!(config.get_param::<u32>("GOOSE_LEAD_TURNS").unwrap(), 7);
+        });
+        // The workspace is never written to the config file
+        assert_eq!(
+            config.load_values().unwrap().get("GOOSE_MODEL"),
+            Some(&Value::String("global-model".into()))
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_workspace_trust() {
+        let (_dir, config, repo) =
+            setup("config:\n  OPENAI_HOST: https://example.com\n  GOOSE_MAX_TURNS: 5\n");
+        let workspace = config.workspace().unwrap().unwrap();
+        assert_eq!(workspace.privileged_keys(), vec!["OPENAI_HOST"]);
+
+        // Until the workspace is trusted, only its plain settings load
+        assert!(!config.is_workspace_trusted(&repo));
+        assert_eq!(config.get_param::<u32>("GOOSE_MAX_TURNS").unwrap(), 5);
+        assert!(config.get_param::<String>("OPENAI_HOST").is_err());
+
+        config.trust_workspace(&repo).unwrap();
+        assert!(config.is_workspace_trusted(&repo));
+        assert_eq!(
+            config.get_param::<String>("OPENAI_HOST").unwrap(),
+            "https://example.com"
+        );
+        // Trust is kept by the hash of the path, for that workspace only
+        assert!(!config.is_workspace_trusted(&repo.join("src")));
+    }
+
+    #[test]
+    fn test_workspace_validation() {
+        let error = |content: &str| {
+            let (_dir, _config, repo) = setup(content);
+            Workspace::load(&repo).unwrap_err().to_string()
+        };
+        assert!(
+            error("allowed_tools:\n  - developer__shell\n  - dev*eloper\n")
+                .ends_with(".goose/config.yaml: `allowed_tools[1]` may only have `*` at its end")
+        );
+        assert!(error("model: gpt-4o\nconfig:\n  GOOSE_MODEL: o3\n")
+            .contains("`config.GOOSE_MODEL` is also set by `model`"));
+        assert!(error("modle: gpt-4o\n").contains("unknown field `modle`"));
+
+        let (_dir, _config, repo) = setup("allowed_tools: [developer__*, memory__remember]\n");
+        let workspace = Workspace::load(&repo).unwrap().unwrap();
+        assert!(workspace.allows_tool("developer__shell"));
+        assert!(workspace.allows_tool("memory__remember"));
+        assert!(workspace.allows_tool("platform__manage_extensions"));
+        assert!(!workspace.allows_tool("memory__remove_memory_category"));
+    }
+}
@@ -0,0 +1,653 @@
+//! Strategies that make a conversation fit in the context window of a model
+//!
+//! Each way of shrinking the history, such as dropping the oldest messages or summarizing
+//! them, is a [`CompactionStrategy`]. The agent applies the configured strategy before a
+//! provider call when the conversation gets close to the context limit, and again when the
+//! provider reports that the context length was exceeded.
+
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use mcp_core::{Content, Role};
+use serde::{Deserialize, Serialize};
+
+use super::common::estimate_target_context_limit;
+use super::summarize::summarize_messages;
+use super::truncate::{estimate_message_tokens, truncate_messages, OldestFirstTruncation};
+use crate::config::Config;
+use crate::message::{Message, MessageContent};
+use crate::providers::base::Provider;
+use crate::token_counter::{estimate_tokens, TokenCounter, TokenizerFamily};
+
+/// Fraction of the context limit above which the agent compacts before calling the provider
+pub const DEFAULT_COMPACTION_THRESHOLD: f32 = 0.8;
+
+/// Fraction of the context limit a single user message may take before it is rejected or spilled
+pub const DEFAULT_MAX_MESSAGE_FRACTION: f32 = 0.5;
+
+/// Text that replaces a tool result the conversation has a later copy of
+const DUPLICATE_STUB: &str = "[This tool result was removed, a later tool call returned the same]";
+
+/// Tool results shorter than this are left alone, stubbing them would save next to nothing
+const MIN_STUBBED_BYTES: usize = 200;
+
+/// Counts the tokens of messages, exactly with a tokenizer or estimated from their text
+pub trait MessageTokenCounter: Send + Sync {
+    fn count_text(&self, text: &str) -> usize;
+
+    fn count_message(&self, message: &Message) -> usize;
+
+    fn count_messages(&self, messages: &[Message]) -> Vec<usize> {
+        messages
+            .iter()
+            .map(|message| self.count_message(message))
+            .collect()
+    }
+}
+
+impl MessageTokenCounter for TokenCounter {
+    fn count_text(&self, text: &str) -> usize {
+        self.count_tokens(text)
+    }
+
+    fn count_message(&self, message: &Message) -> usize {
+        self.count_chat_tokens("", std::slice::from_ref(message), &[])
+    }
+}
+
+/// Estimates tokens without loading a tokenizer, cheap enough to run before every provider call
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenEstimator {
+    family: TokenizerFamily,
+}
+
+impl TokenEstimator {
+    pub fn new(family: TokenizerFamily) -> Self {
+        Self { family }
+    }
+}
+
+impl MessageTokenCounter for TokenEstimator {
+    fn count_text(&self, text: &str) -> usize {
+        estimate_tokens(text, self.family)
+    }
+
+    fn count_message(&self, message: &Message) -> usize {
+        estimate_message_tokens(message, &|text: &str| estimate_tokens(text, self.family))
+    }
+}
+
+/// How many tokens the messages of a conversation may use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenBudget {
+    pub limit: usize,
+}
+
+impl TokenBudget {
+    pub fn new(limit: usize) -> Self {
+        Self { limit }
+    }
+
+    /// The budget left for messages by the model of `provider`, after the system prompt and tools
+    pub fn for_provider(provider: &Arc<dyn Provider>) -> Self {
+        Self::new(estimate_target_context_limit(Arc::clone(provider)))
+    }
+
+    pub fn fits(&self, token_counts: &[usize]) -> bool {
+        token_counts.iter().sum::<usize>() <= self.limit
+    }
+}
+
+/// Why the agent compacted the conversation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompactionTrigger {
+    /// The conversation grew past the threshold fraction of the context limit
+    Threshold,
+    /// The provider rejected the conversation as too long
+    ContextLengthExceeded,
+}
+
+/// What one strategy did to the conversation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactionStep {
+    /// The name of the strategy, such as `drop_oldest`
+    pub strategy: String,
+    pub messages_before: usize,
+    pub messages_after: usize,
+    pub tokens_before: usize,
+    pub tokens_after: usize,
+}
+
+impl CompactionStep {
+    pub fn messages_removed(&self) -> usize {
+        self.messages_before.saturating_sub(self.messages_after)
+    }
+
+    pub fn tokens_removed(&self) -> usize {
+        self.tokens_before.saturating_sub(self.tokens_after)
+    }
+}
+
+/// The compacted conversation, with what each strategy removed from it
+#[derive(Debug, Clone)]
+pub struct CompactionResult {
+    pub messages: Vec<Message>,
+    /// The tokens of each message, as counted by the counter the strategy was given
+    pub token_counts: Vec<usize>,
+    pub steps: Vec<CompactionStep>,
+}
+
+impl CompactionResult {
+    /// The result of a single strategy that turned `before` into `messages`
+    pub fn new(
+        strategy: impl Into<String>,
+        before: &[usize],
+        messages: Vec<Message>,
+        token_counts: Vec<usize>,
+    ) -> Self {
+        let step = CompactionStep {
+            strategy: strategy.into(),
+            messages_before: before.len(),
+            messages_after: messages.len(),
+            tokens_before: before.iter().sum(),
+            tokens_after: token_counts.iter().sum(),
+        };
+        Self {
+            messages,
+            token_counts,
+            steps: vec![step],
+        }
+    }
+
+    /// The conversation left as it is
+    pub fn unchanged(messages: &[Message], token_counts: Vec<usize>) -> Self {
+        Self {
+            messages: messages.to_vec(),
+            token_counts,
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn total_tokens(&self) -> usize {
+        self.token_counts.iter().sum()
+    }
+}
+
+/// A way to make the messages of a conversation fit in a token budget
+#[async_trait]
+pub trait CompactionStrategy: Send + Sync {
+    /// The name the strategy is reported and configured with
+    fn name(&self) -> String;
+
+    /// The messages shrunk towards `budget`
+    ///
+    /// A strategy that cannot reach the budget on its own returns what it managed, so a
+    /// [`Composite`] can continue with the next strategy. The messages after the last user
+    /// text message belong to the turn in progress and are kept.
+    async fn compact(
+        &self,
+        messages: &[Message],
+        budget: TokenBudget,
+        counter: &dyn MessageTokenCounter,
+    ) -> Result<CompactionResult>;
+}
+
+/// The index of the last message the user typed, which starts the turn in progress
+fn last_user_text(messages: &[Message]) -> Option<usize> {
+    messages
+        .iter()
+        .rposition(|message| message.role == Role::User && message.has_only_text_content())
+}
+
+/// Drops the oldest messages, with the other half of their tool calls
+pub struct DropOldest {
+    /// Used to estimate messages whose content had to be cut
+    pub family: TokenizerFamily,
+}
+
+#[async_trait]
+impl CompactionStrategy for DropOldest {
+    fn name(&self) -> String {
+        "drop_oldest".to_string()
+    }
+
+    async fn compact(
+        &self,
+        messages: &[Message],
+        budget: TokenBudget,
+        counter: &dyn MessageTokenCounter,
+    ) -> Result<CompactionResult> {
+        let token_counts = counter.count_messages(messages);
+        if budget.fits(&token_counts) {
+            return Ok(CompactionResult::unchanged(messages, token_counts));
+        }
+        let split = last_user_text(messages)
+            .ok_or_else(|| anyhow!("No user message to keep while dropping old messages"))?;
+        let tail_tokens: usize = token_counts[split + 1..].iter().sum();
+        let head_limit = budget.limit.checked_sub(tail_tokens).ok_or_else(|| {
+            anyhow!("The turn in progress alone does not fit in the context limit")
+        })?;
+
+        let (mut kept, mut kept_counts) = truncate_messages(
+            &messages[..=split],
+            &token_counts[..=split],
+            head_limit,
+            &OldestFirstTruncation,
+            self.family,
+        )?;
+        kept.extend_from_slice(&messages[split + 1..]);
+        kept_counts.extend_from_slice(&token_counts[split + 1..]);
+        Ok(CompactionResult::new(
+            self.name(),
+            &token_counts,
+            kept,
+            kept_counts,
+        ))
+    }
+}
+
+/// Replaces the oldest messages with a summary written by the model
+pub struct SummarizeOldest {
+    pub provider: Arc<dyn Provider>,
+}
+
+#[async_trait]
+impl CompactionStrategy for SummarizeOldest {
+    fn name(&self) -> String {
+        "summarize_oldest".to_string()
+    }
+
+    async fn compact(
+        &self,
+        messages: &[Message],
+        budget: TokenBudget,
+        counter: &dyn MessageTokenCounter,
+    ) -> Result<CompactionResult> {
+        let token_counts = counter.count_messages(messages);
+        if budget.fits(&token_counts) {
+            return Ok(CompactionResult::unchanged(messages, token_counts));
+        }
+
+        // Keep the newest messages that fit in half the budget, starting at a user text
+        // message so no tool call is separated from its result
+        let mut split = messages.len();
+        let mut recent_tokens = 0;
+        for index in (0..messages.len()).rev() {
+            recent_tokens += token_counts[index];
+            if recent_tokens > budget.limit / 2 {
+                break;
+            }
+            if messages[index].role == Role::User && messages[index].has_only_text_content() {
+                split = index;
+            }
+        }
+        let split = split.min(last_user_text(messages).unwrap_or(messages.len()));
+        if split == 0 {
+            return Ok(CompactionResult::unchanged(messages, token_counts));
+        }
+
+        let (mut compacted, _) = summarize_messages(
+            Arc::clone(&self.provider),
+            &messages[..split],
+            counter,
+            budget.limit,
+        )
+        .await?;
+        // The summary is a user message, so the model acknowledges it before the next one
+        compacted.push(
+            Message::assistant().with_text("I summarized the earlier part of our conversation."),
+        );
+        compacted.extend_from_slice(&messages[split..]);
+        let compacted_counts = counter.count_messages(&compacted);
+        Ok(CompactionResult::new(
+            self.name(),
+            &token_counts,
+            compacted,
+            compacted_counts,
+        ))
+    }
+}
+
+/// Replaces tool results with a stub when a later tool call returned the same text
+pub struct StubDuplicates;
+
+#[async_trait]
+impl CompactionStrategy for StubDuplicates {
+    fn name(&self) -> String {
+        "stub_duplicates".to_string()
+    }
+
+    async fn compact(
+        &self,
+        messages: &[Message],
+        _budget: TokenBudget,
+        counter: &dyn MessageTokenCounter,
+    ) -> Result<CompactionResult> {
+        let token_counts = counter.count_messages(messages);
+        let mut compacted = messages.to_vec();
+        let mut compacted_counts = token_counts.clone();
+        let mut seen = HashSet::new();
+
+        // Walk from the newest message, so the latest copy of a result is the one kept
+        for (message, tokens) in compacted.iter_mut().zip(&mut compacted_counts).rev() {
+            let mut stubbed = false;
+            for content in &mut message.content {
+                let MessageContent::ToolResponse(response) = content else {
+                    continue;
+                };
+                let Ok(result) = &mut response.tool_result else {
+                    continue;
+                };
+                let text: String = result.iter().filter_map(Content::as_text).collect();
+                if text.len() < MIN_STUBBED_BYTES || text == DUPLICATE_STUB {
+                    continue;
+                }
+                if !seen.insert(text) {
+                    *result = vec![Content::text(DUPLICATE_STUB)];
+                    stubbed = true;
+                }
+            }
+            if stubbed {
+                *tokens = counter.count_message(message);
+            }
+        }
+
+        if compacted_counts == token_counts {
+            return Ok(CompactionResult::unchanged(messages, token_counts));
+        }
+        Ok(CompactionResult::new(
+            self.name(),
+            &token_counts,
+            compacted,
+            compacted_counts,
+        ))
+    }
+}
+
+/// Applies strategies in order until the conversation fits
+pub struct Composite(pub Vec<Arc<dyn CompactionStrategy>>);
+
+#[async_trait]
+impl CompactionStrategy for Composite {
+    fn name(&self) -> String {
+        let names: Vec<String> = self.0.iter().map(|strategy| strategy.name()).collect();
+        names.join(",")
+    }
+
+    async fn compact(
+        &self,
+        messages: &[Message],
+        budget: TokenBudget,
+        counter: &dyn MessageTokenCounter,
+    ) -> Result<CompactionResult> {
+        let mut result = CompactionResult::unchanged(messages, counter.count_messages(messages));
+        let mut last_error = None;
+        for strategy in &self.0 {
+            if budget.fits(&result.token_counts) {
+                return Ok(result);
+            }
+            match strategy.compact(&result.messages, budget, counter).await {
+                Ok(next) => {
+                    result.messages = next.messages;
+                    result.token_counts = next.token_counts;
+                    result.steps.extend(next.steps);
+                }
+                Err(error) => {
+                    tracing::warn!("Compaction with {} failed: {}", strategy.name(), error);
+                    last_error = Some(error);
+                }
+            }
+        }
+        match last_error {
+            Some(error) if result.steps.is_empty() => Err(error),
+            _ => Ok(result),
+        }
+    }
+}
+
+/// A built-in strategy, by the name it is configured with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompactionKind {
+    DropOldest,
+    SummarizeOldest,
+    StubDuplicates,
+}
+
+impl FromStr for CompactionKind {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name.trim() {
+            "drop_oldest" => Ok(Self::DropOldest),
+            "summarize_oldest" => Ok(Self::SummarizeOldest),
+            "stub_duplicates" => Ok(Self::StubDuplicates),
+            other => Err(anyhow!("Unknown compaction strategy '{}'", other)),
+        }
+    }
+}
+
+/// Which strategies the agent compacts with, and when
+///
+/// Set with `GOOSE_COMPACTION_STRATEGY`, a comma separated list such as
+/// `stub_duplicates,drop_oldest`, and `GOOSE_COMPACTION_THRESHOLD`, a fraction of the context
+/// limit. Without strategies the agent leaves the conversation to the user, as before.
+///
+/// No strategy helps with a single user message too large for the context, such as a pasted
+/// file. One above `GOOSE_MAX_MESSAGE_FRACTION` of the context limit is rejected, or with
+/// `GOOSE_SPILL_OVERSIZED_MESSAGES` stored for the model to read in pages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompactionConfig {
+    pub strategies: Vec<CompactionKind>,
+    pub threshold: f32,
+    pub max_message_fraction: f32,
+    pub spill_oversized_messages: bool,
+}
+
+impl Default for CompactionConfig {
+    fn default() -> Self {
+        Self {
+            strategies: Vec::new(),
+            threshold: DEFAULT_COMPACTION_THRESHOLD,
+            max_message_fraction: DEFAULT_MAX_MESSAGE_FRACTION,
+            spill_oversized_messages: false,
+        }
+    }
+}
+
+impl CompactionConfig {
+    pub fn from_config() -> Self {
+        let config = Config::global();
+        let strategies = config
+            .get_param::<String>("GOOSE_COMPACTION_STRATEGY")
+            .map(|names| Self::parse_strategies(&names))
+            .unwrap_or_default();
+        let threshold = config
+            .get_param::<f32>("GOOSE_COMPACTION_THRESHOLD")
+            .ok()
+            .filter(|threshold| *threshold > 0.0 && *threshold <= 1.0)
+            .unwrap_or(DEFAULT_COMPACTION_THRESHOLD);
+        let max_message_fraction = config
+            .get_param::<f32>("GOOSE_MAX_MESSAGE_FRACTION")
+            .ok()
+            .filter(|fraction| *fraction > 0.0 && *fraction <= 1.0)
+            .unwrap_or(DEFAULT_MAX_MESSAGE_FRACTION);
+        let spill_oversized_messages = config
+            .get_param::<bool>("GOOSE_SPILL_OVERSIZED_MESSAGES")
+            .unwrap_or(false);
+        Self {
+            strategies,
+            threshold,
+            max_message_fraction,
+            spill_oversized_messages,
+        }
+    }
+
+    fn parse_strategies(names: &str) -> Vec<CompactionKind> {
+        names
+            .split(',')
+            .filter(|name| !name.trim().is_empty())
+            .filter_map(|name| match name.parse() {
+                Ok(kind) => Some(kind),
+                Err(error) => {
+                    tracing::warn!("{}", error);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// The configured strategy for the model of `provider`, none when compaction is off
+    pub fn strategy(&self, provider: Arc<dyn Provider>) -> Option<Arc<dyn CompactionStrategy>> {
+        let family = provider.get_model_config().tokenizer_family();
+        let mut strategies: Vec<Arc<dyn CompactionStrategy>> = self
+            .strategies
+            .iter()
+            .map(|kind| -> Arc<dyn CompactionStrategy> {
+                match kind {
+                    CompactionKind::DropOldest => Arc::new(DropOldest { family }),
+                    CompactionKind::SummarizeOldest => Arc::new(SummarizeOldest {
+                        provider: Arc::clone(&provider),
+                    }),
+                    CompactionKind::StubDuplicates => Arc::new(StubDuplicates),
+                }
+            })
+            .collect();
+        match strategies.len() {
+            0 => None,
+            1 => strategies.pop(),
+            _ => Some(Arc::new(Composite(strategies))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::tool::ToolCall;
+    use serde_json::json;
+
+    /// Counts a token per byte of text, so tests can size messages exactly
+    struct ByteCounter;
+
+    impl MessageTokenCounter for ByteCounter {
+        fn count_text(&self, text: &str) -> usize {
+            text.len()
+        }
+
+        fn count_message(&self, message: &Message) -> usize {
+            estimate_message_tokens(message, &|text: &str| text.len())
+        }
+    }
+
+    fn tool_pair(id: &str, output: &str) -> [Message; 2] {
+        [
+            Message::assistant()
+                .with_tool_request(id, Ok(ToolCall::new("read_file", json!({"path": "a"})))),
+            Message::user().with_tool_response(id, Ok(vec![Content::text(output)])),
+        ]
+    }
+
+    fn conversation() -> Vec<Message> {
+        let mut messages = vec![
+            Message::user().with_text("first question"),
+            Message::assistant().with_text("a".repeat(300)),
+            Message::user().with_text("second question"),
+        ];
+        messages.extend(tool_pair("1", &"x".repeat(300)));
+        messages.push(Message::assistant().with_text("done"));
+        messages.push(Message::user().with_text("third question"));
+        messages.extend(tool_pair("2", &"x".repeat(300)));
+        messages
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest_keeps_the_turn_in_progress() {
+        let messages = conversation();
+        let result = DropOldest {
+            family: TokenizerFamily::Generic,
+        }
+        .compact(&messages, TokenBudget::new(400), &ByteCounter)
+        .await
+        .unwrap();
+
+        assert!(result.total_tokens() <= 400);
+        assert_eq!(
+            result.messages.first().unwrap().as_concat_text(),
+            "third question"
+        );
+        assert_eq!(result.messages.last(), messages.last());
+        assert_eq!(result.steps.len(), 1);
+        assert_eq!(result.steps[0].strategy, "drop_oldest");
+        assert_eq!(result.steps[0].messages_removed(), 6);
+
+        let unchanged = DropOldest {
+            family: TokenizerFamily::Generic,
+        }
+        .compact(&messages, TokenBudget::new(10_000), &ByteCounter)
+        .await
+        .unwrap();
+        assert_eq!(unchanged.messages, messages);
+        assert!(unchanged.steps.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stub_duplicates_keeps_the_latest_copy() {
+        let messages = conversation();
+        let result = StubDuplicates
+            .compact(&messages, TokenBudget::new(0), &ByteCounter)
+            .await
+            .unwrap();
+
+        assert_eq!(result.messages.len(), messages.len());
+        let stubbed = match &result.messages[4].content[0] {
+            MessageContent::ToolResponse(response) => response.tool_result.clone().unwrap(),
+            other => panic!("expected a tool response, got {:?}", other),
+        };
+        assert_eq!(stubbed, vec![Content::text(DUPLICATE_STUB)]);
+        assert_eq!(result.messages[8], messages[8]);
+        assert_eq!(result.steps[0].tokens_removed(), 300 - DUPLICATE_STUB.len());
+    }
+
+    #[tokio::test]
+    async fn test_composite_stops_once_it_fits() {
+        let messages = conversation();
+        let composite = Composite(vec![
+            Arc::new(StubDuplicates),
+            Arc::new(DropOldest {
+                family: TokenizerFamily::Generic,
+            }),
+        ]);
+        assert_eq!(composite.name(), "stub_duplicates,drop_oldest");
+
+        let total: usize = ByteCounter.count_messages(&messages).iter().sum();
+        let stubbed_total = total - (300 - DUPLICATE_STUB.len());
+        let result = composite
+            .compact(&messages, TokenBudget::new(stubbed_total), &ByteCounter)
+            .await
+            .unwrap();
+        assert_eq!(result.steps.len(), 1);
+        assert_eq!(result.total_tokens(), stubbed_total);
+
+        let result = composite
+            .compact(&messages, TokenBudget::new(400), &ByteCounter)
+            .await
+            .unwrap();
+        let strategies: Vec<&str> = result.steps.iter().map(|s| s.strategy.as_str()).collect();
+        assert_eq!(strategies, ["stub_duplicates", "drop_oldest"]);
+        assert!(result.total_tokens() <= 400);
+    }
+
+    #[test]
+    fn test_parse_strategies() {
+        assert_eq!(
+            CompactionConfig::parse_strategies("stub_duplicates, drop_oldest,unknown,"),
+            vec![CompactionKind::StubDuplicates, CompactionKind::DropOldest]
+        );
+        assert!(CompactionConfig::parse_strategies("").is_empty());
+    }
+}
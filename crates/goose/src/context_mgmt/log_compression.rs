@@ -0,0 +1,355 @@
+//! Compression of logs pasted into user messages.
+//!
+//! Long CI logs and stack traces are mostly noise to the model. Runs of repeated lines are
+//! folded into one line with a count, and of the rest only the head, the tail and the lines
+//! that look like errors are kept, with the few lines after each error. Fenced code blocks are
+//! never changed, nor is text that doesn't look like a log.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::config::Config;
+
+/// Text shorter than this, in bytes, is not compressed unless configured otherwise
+pub const DEFAULT_LOG_COMPRESSION_THRESHOLD: usize = 20_000;
+
+/// Lines kept after each error line, which often hold its stack trace or cause
+const ERROR_CONTEXT_LINES: usize = 3;
+
+/// The share of lines that must look like log lines for a block of text to be compressed
+const MIN_LOG_LINE_SHARE: f64 = 0.3;
+
+static LOG_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        // Timestamps, at the start of the line or after a bracket
+        r"^\s*\[?(\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}|\d{2}:\d{2}:\d{2})",
+        // Log levels
+        r"|\b(TRACE|DEBUG|INFO|WARN|WARNING|ERROR|FATAL)\b",
+        // Stack frames of Java and JavaScript, Python, and Rust
+        r#"|^\s+at |^\s+File ".*", line \d+|^\s*\d+: \S+::"#,
+        // CI runner markers
+        r"|^##\[|^::(group|endgroup|error|warning)",
+        // Build tool progress
+        r"|^\s*(Compiling|Downloading|Downloaded|Running|Installing|Collecting|npm (WARN|ERR!))\b",
+    ))
+    .expect("valid log line regex")
+});
+
+static ERROR_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\b(error|errors|fatal|panic|panicked|exception|traceback|failed|failure|segmentation fault)\b|^error(\[E\d+\])?:|npm ERR!",
+    )
+    .expect("valid error line regex")
+});
+
+static DIGITS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d+").expect("valid digits regex"));
+
+/// How logs in user messages are compressed, configured with GOOSE_LOG_COMPRESSION
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogCompressionConfig {
+    /// Messages with less text than this, in bytes, are left alone
+    pub threshold: usize,
+    /// Lines kept from the start of each log
+    pub head_lines: usize,
+    /// Lines kept from the end of each log
+    pub tail_lines: usize,
+}
+
+impl Default for LogCompressionConfig {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_LOG_COMPRESSION_THRESHOLD,
+            head_lines: 20,
+            tail_lines: 40,
+        }
+    }
+}
+
+impl LogCompressionConfig {
+    /// The configuration when GOOSE_LOG_COMPRESSION is on, with the threshold from
+    /// GOOSE_LOG_COMPRESSION_THRESHOLD. Compression is off by default.
+    pub fn from_config() -> Option<Self> {
+        let config = Config::global();
+        if !config
+            .get_param::<bool>("GOOSE_LOG_COMPRESSION")
+            .unwrap_or(false)
+        {
+            return None;
+        }
+        Some(Self {
+            threshold: config
+                .get_param("GOOSE_LOG_COMPRESSION_THRESHOLD")
+                .unwrap_or(DEFAULT_LOG_COMPRESSION_THRESHOLD),
+            ..Default::default()
+        })
+    }
+}
+
+/// Text with its logs compressed, and what was removed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressedLogs {
+    pub text: String,
+    /// Lines of the logs before compression
+    pub original_lines: usize,
+    /// Lines of the logs after compression, including the notes of what was left out
+    pub kept_lines: usize,
+    /// Lines folded into the line they repeat
+    pub repeated_lines: usize,
+    /// Error lines, which are always kept
+    pub error_lines: usize,
+}
+
+impl CompressedLogs {
+    /// A one line description of the compression, for the note added to the message
+    pub fn summary(&self) -> String {
+        format!(
+            "compressed pasted logs from {} to {} lines, folding {} repeated lines and keeping {} error lines",
+            self.original_lines, self.kept_lines, self.repeated_lines, self.error_lines
+        )
+    }
+}
+
+/// A block of lines of the text, fenced ones are kept as they are
+struct Block<'a> {
+    lines: Vec<&'a str>,
+    fenced: bool,
+}
+
+fn blocks(text: &str) -> Vec<Block<'_>> {
+    let mut blocks = Vec::new();
+    let mut current = Block {
+        lines: Vec::new(),
+        fenced: false,
+    };
+    let mut fence = None;
+    for line in text.split('\n') {
+        let trimmed = line.trim_start();
+        let marker = ["```", "~~~"]
+            .into_iter()
+            .find(|marker| trimmed.starts_with(marker));
+        match (fence, marker) {
+            (None, Some(marker)) => {
+                blocks.push(std::mem::replace(
+                    &mut current,
+                    Block {
+                        lines: vec![line],
+                        fenced: true,
+                    },
+                ));
+                fence = Some(marker);
+            }
+            (Some(open), Some(marker)) if open == marker => {
+                current.lines.push(line);
+                blocks.push(std::mem::replace(
+                    &mut current,
+                    Block {
+                        lines: Vec::new(),
+                        fenced: false,
+                    },
+                ));
+                fence = None;
+            }
+            _ => current.lines.push(line),
+        }
+    }
+    blocks.push(current);
+    blocks.retain(|block| !block.lines.is_empty());
+    blocks
+}
+
+fn is_log(lines: &[&str]) -> bool {
+    let non_empty: Vec<&&str> = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    if non_empty.is_empty() {
+        return false;
+    }
+    let log_lines = non_empty
+        .iter()
+        .filter(|line| LOG_LINE.is_match(line))
+        .count();
+    log_lines as f64 / non_empty.len() as f64 >= MIN_LOG_LINE_SHARE
+}
+
+/// A line of a log with the number of times it repeated right after itself
+struct Run<'a> {
+    line: &'a str,
+    repeats: usize,
+}
+
+/// Fold runs of lines that only differ in their numbers, such as timestamps and counters
+fn fold_repeats<'a>(lines: &[&'a str]) -> Vec<Run<'a>> {
+    let mut runs: Vec<Run> = Vec::new();
+    let mut last_key = None;
+    for line in lines {
+        let key = DIGITS.replace_all(line.trim_end(), "#");
+        match runs.last_mut() {
+            Some(run) if last_key.as_ref() == Some(&key) && !line.trim().is_empty() => {
+                run.repeats += 1
+            }
+            _ => runs.push(Run { line, repeats: 0 }),
+        }
+        last_key = Some(key);
+    }
+    runs
+}
+
+/// Compress one log, returning its lines and adding to the counts of `stats`
+fn compress_log(
+    lines: &[&str],
+    config: &LogCompressionConfig,
+    stats: &mut CompressedLogs,
+) -> Vec<String> {
+    let runs = fold_repeats(lines);
+    let mut keep = vec![false; runs.len()];
+    for (i, run) in runs.iter().enumerate() {
+        if i < config.head_lines || i + config.tail_lines >= runs.len() {
+            keep[i] = true;
+        }
+        if ERROR_LINE.is_match(run.line) {
+            stats.error_lines += 1;
+            let end = (i + ERROR_CONTEXT_LINES + 1).min(runs.len());
+            keep[i..end].iter_mut().for_each(|kept| *kept = true);
+        }
+    }
+
+    let mut output = Vec::new();
+    let mut omitted = 0;
+    for (run, kept) in runs.iter().zip(keep) {
+        if !kept {
+            omitted += 1 + run.repeats;
+            continue;
+        }
+        if omitted > 0 {
+            output.push(format!("[... {} lines omitted ...]", omitted));
+            omitted = 0;
+        }
+        output.push(run.line.to_string());
+        if run.repeats > 0 {
+            stats.repeated_lines += run.repeats;
+            output.push(format!("[... repeated {} more times ...]", run.repeats));
+        }
+    }
+    if omitted > 0 {
+        output.push(format!("[... {} lines omitted ...]", omitted));
+    }
+
+    stats.original_lines += lines.len();
+    stats.kept_lines += output.len();
+    output
+}
+
+/// Compress the logs in text a user pasted, none when the text is too short or has no logs
+/// that get any shorter
+///
+/// Text in fenced code blocks is kept exactly as it is.
+pub fn compress_logs(text: &str, config: &LogCompressionConfig) -> Option<CompressedLogs> {
+    if text.len() < config.threshold {
+        return None;
+    }
+
+    let mut compressed = CompressedLogs {
+        text: String::new(),
+        original_lines: 0,
+        kept_lines: 0,
+        repeated_lines: 0,
+        error_lines: 0,
+    };
+    let mut lines = Vec::new();
+    for block in blocks(text) {
+        if block.fenced || !is_log(&block.lines) {
+            lines.extend(block.lines.iter().map(|line| line.to_string()));
+            continue;
+        }
+        lines.extend(compress_log(&block.lines, config, &mut compressed));
+    }
+    if compressed.kept_lines >= compressed.original_lines {
+        return None;
+    }
+    compressed.text = lines.join("\n");
+    Some(compressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARGO_LOG: &str = include_str!("testdata/cargo_test.log");
+    const NPM_LOG: &str = include_str!("testdata/npm_ci.log");
+
+    fn config() -> LogCompressionConfig {
+        LogCompressionConfig {
+            threshold: 1_000,
+            head_lines: 5,
+            tail_lines: 5,
+        }
+    }
+
+    #[test]
+    fn test_error_lines_survive() {
+        let compressed = compress_logs(CARGO_LOG, &config()).unwrap();
+        assert!(compressed.kept_lines < compressed.original_lines);
+        for line in CARGO_LOG.lines().filter(|line| ERROR_LINE.is_match(line)) {
+            assert!(
+                compressed.text.contains(line),
+                "error line was dropped: {}",
+                line
+            );
+        }
+        // The lines after the panic explain it
+        assert!(compressed.text.contains("  left: 3"));
+
+        let compressed = compress_logs(NPM_LOG, &config()).unwrap();
+        assert!(compressed.text.contains("npm ERR! code ERESOLVE"));
+        assert!(compressed
+            .text
+            .contains("npm ERR! Could not resolve dependency:"));
+        assert!(compressed.text.contains("[... repeated 39 more times ...]"));
+        assert_eq!(compressed.repeated_lines, 39);
+    }
+
+    #[test]
+    fn test_repeated_lines_are_folded() {
+        let log: String = (0..500)
+            .map(|i| {
+                format!(
+                    "2024-05-01T10:00:{:02} INFO waiting for service, attempt {}\n",
+                    i % 60,
+                    i
+                )
+            })
+            .collect();
+        let compressed = compress_logs(&log, &config()).unwrap();
+        assert_eq!(compressed.original_lines, 501);
+        assert_eq!(compressed.repeated_lines, 499);
+        assert_eq!(
+            compressed.text,
+            "2024-05-01T10:00:00 INFO waiting for service, attempt 0\n[... repeated 499 more times ...]\n"
+        );
+        assert_eq!(
+            compressed.summary(),
+            "compressed pasted logs from 501 to 3 lines, folding 499 repeated lines and keeping 0 error lines"
+        );
+    }
+
+    #[test]
+    fn test_fenced_code_and_prose_are_kept() {
+        let fenced = format!("Why does this fail?\n```\n{}```\nThanks", CARGO_LOG);
+        assert_eq!(compress_logs(&fenced, &config()), None);
+
+        let prose = "This is a long description of the problem. ".repeat(100);
+        assert_eq!(compress_logs(&prose, &config()), None);
+
+        // Only the unfenced log is compressed
+        let mixed = format!("{}\n~~~rust\nfn main() {{}}\n~~~\n", CARGO_LOG);
+        let compressed = compress_logs(&mixed, &config()).unwrap();
+        assert!(compressed.text.ends_with("~~~rust\nfn main() {}\n~~~\n"));
+
+        assert_eq!(
+            compress_logs(CARGO_LOG, &LogCompressionConfig::default()),
+            None
+        );
+    }
+}
@@ -1,4 +1,6 @@
 mod common;
+pub mod compaction;
+pub mod log_compression;
 pub mod summarize;
 pub mod truncate;
 
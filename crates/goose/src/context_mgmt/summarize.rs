@@ -1,7 +1,6 @@
-use super::common::get_messages_token_counts;
+use super::compaction::MessageTokenCounter;
 use crate::message::{Message, MessageContent};
 use crate::providers::base::Provider;
-use crate::token_counter::TokenCounter;
 use anyhow::Result;
 use mcp_core::Role;
 use std::sync::Arc;
@@ -110,18 +109,18 @@ fn reintegrate_removed_messages(
 pub async fn summarize_messages(
     provider: Arc<dyn Provider>,
     messages: &[Message],
-    token_counter: &TokenCounter,
+    token_counter: &dyn MessageTokenCounter,
     context_limit: usize,
 ) -> Result<(Vec<Message>, Vec<usize>), anyhow::Error> {
     let chunk_size = context_limit / 3; // 33% of the context window.
-    let summary_prompt_tokens = token_counter.count_tokens(SUMMARY_PROMPT);
+    let summary_prompt_tokens = token_counter.count_text(SUMMARY_PROMPT);
     let mut accumulated_summary = Vec::new();
 
     // Preprocess messages to handle tool response edge case.
     let (preprocessed_messages, removed_messages) = preprocess_messages(messages);
 
     // Get token counts for each message.
-    let token_counts = get_messages_token_counts(token_counter, &preprocessed_messages);
+    let token_counts = token_counter.count_messages(&preprocessed_messages);
 
     // Tokenize and break messages into chunks.
     let mut current_chunk: Vec<Message> = Vec::new();
@@ -155,7 +154,7 @@ pub async fn summarize_messages(
 
     Ok((
         final_summary.clone(),
-        get_messages_token_counts(token_counter, &final_summary),
+        token_counter.count_messages(&final_summary),
     ))
 }
 
@@ -166,6 +165,7 @@ mod tests {
     use crate::model::{ModelConfig, GPT_4O_TOKENIZER};
     use crate::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
     use crate::providers::errors::ProviderError;
+    use crate::token_counter::TokenCounter;
     use chrono::Utc;
     use mcp_core::{tool::Tool, Role};
     use mcp_core::{Content, TextContent, ToolCall};
@@ -201,6 +201,7 @@ mod tests {
                         text: "Summarized content".to_string(),
                         annotations: None,
                     })],
+                    extra: Default::default(),
                 },
                 ProviderUsage::new("mock".to_string(), Usage::default()),
             ))
@@ -228,6 +229,7 @@ mod tests {
             role,
             created: 0,
             content: vec![MessageContent::text(text.to_string())],
+            extra: Default::default(),
         }
     }
 
@@ -236,6 +238,7 @@ mod tests {
             role: Role::Assistant,
             created: 0,
             content: vec![MessageContent::tool_request(id.to_string(), Ok(tool_call))],
+            extra: Default::default(),
         }
     }
 
@@ -247,6 +250,7 @@ mod tests {
                 id.to_string(),
                 Ok(tool_response),
             )],
+            extra: Default::default(),
         }
     }
 
@@ -402,6 +406,7 @@ mod tests {
                 text: "Summary".to_string(),
                 annotations: None,
             })],
+            extra: Default::default(),
         }];
         let arguments = json!({
             "param1": "value1"
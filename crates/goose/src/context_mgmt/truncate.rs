@@ -1,4 +1,6 @@
 use crate::message::{Message, MessageContent};
+use crate::token_counter::{estimate_tokens, TokenizerFamily};
+use crate::utils::truncate_to_char_boundary;
 use anyhow::{anyhow, Result};
 use mcp_core::{Content, ResourceContents, Role};
 use std::collections::HashSet;
@@ -14,17 +16,14 @@ fn handle_oversized_messages(
     token_counts: &[usize],
     context_limit: usize,
     strategy: &dyn TruncationStrategy,
+    family: TokenizerFamily,
 ) -> Result<(Vec<Message>, Vec<usize>), anyhow::Error> {
     let mut truncated_messages = Vec::new();
     let mut truncated_token_counts = Vec::new();
     let mut any_truncated = false;
 
-    // Create a basic token counter for re-estimating truncated content
-    // Note: This is a rough approximation since we don't have access to the actual tokenizer here
-    let estimate_tokens = |text: &str| -> usize {
-        // Rough approximation: 1 token per 4 characters for English text
-        (text.len() / 4).max(1)
-    };
+    // Re-estimate truncated content, we don't have access to the actual tokenizer here
+    let estimate = |text: &str| -> usize { estimate_tokens(text, family).max(1) };
 
     for (i, (message, &original_tokens)) in messages.iter().zip(token_counts.iter()).enumerate() {
         if original_tokens > context_limit {
@@ -35,8 +34,7 @@ fn handle_oversized_messages(
 
             // Try to truncate the message content
             let truncated_message = truncate_message_content(message, MAX_TRUNCATED_CONTENT_SIZE)?;
-            let estimated_new_tokens =
-                estimate_message_tokens(&truncated_message, &estimate_tokens);
+            let estimated_new_tokens = estimate_message_tokens(&truncated_message, &estimate);
 
             if estimated_new_tokens > context_limit {
                 // Even truncated message is too large, skip it entirely
@@ -62,6 +60,7 @@ fn handle_oversized_messages(
             &truncated_token_counts,
             context_limit,
             strategy,
+            family,
         );
     }
 
@@ -78,7 +77,7 @@ fn truncate_message_content(message: &Message, max_content_size: usize) -> Resul
                 if text_content.text.len() > max_content_size {
                     let truncated = format!(
                         "{}\n\n[... content truncated from {} to {} characters ...]",
-                        &text_content.text[..max_content_size.min(text_content.text.len())],
+                        truncate_to_char_boundary(&text_content.text, max_content_size),
                         text_content.text.len(),
                         max_content_size
                     );
@@ -88,26 +87,18 @@ fn truncate_message_content(message: &Message, max_content_size: usize) -> Resul
             MessageContent::ToolResponse(tool_response) => {
                 if let Ok(ref mut result) = tool_response.tool_result {
                     for content_item in result {
-                        if let Content::Text(ref mut text_content) = content_item {
-                            if text_content.text.len() > max_content_size {
-                                let truncated = format!(
-                                    "{}\n\n[... tool response truncated from {} to {} characters ...]",
-                                    &text_content.text[..max_content_size.min(text_content.text.len())],
-                                    text_content.text.len(),
-                                    max_content_size
-                                );
-                                text_content.text = truncated;
-                            }
+                        if content_item.truncate_text(max_content_size) {
+                            continue;
                         }
                         // Handle Resource content which might contain large text
-                        else if let Content::Resource(ref mut resource_content) = content_item {
+                        if let Content::Resource(ref mut resource_content) = content_item {
                             if let ResourceContents::TextResourceContents { text, .. } =
                                 &mut resource_content.resource
                             {
                                 if text.len() > max_content_size {
                                     let truncated = format!(
                                         "{}\n\n[... resource content truncated from {} to {} characters ...]",
-                                        &text[..max_content_size.min(text.len())],
+                                        truncate_to_char_boundary(text, max_content_size),
                                         text.len(),
                                         max_content_size
                                     );
@@ -127,7 +118,10 @@ fn truncate_message_content(message: &Message, max_content_size: usize) -> Resul
 }
 
 /// Estimates token count for a message using a simple heuristic
-fn estimate_message_tokens(message: &Message, estimate_fn: &dyn Fn(&str) -> usize) -> usize {
+pub(super) fn estimate_message_tokens(
+    message: &Message,
+    estimate_fn: &dyn Fn(&str) -> usize,
+) -> usize {
     let mut total_tokens = 10; // Base overhead for message structure
 
     for content in &message.content {
@@ -142,6 +136,9 @@ fn estimate_message_tokens(message: &Message, estimate_fn: &dyn Fn(&str) -> usiz
                             Content::Text(text_content) => {
                                 total_tokens += estimate_fn(&text_content.text);
                             }
+                            Content::Result(result) => {
+                                total_tokens += estimate_fn(&result.text);
+                            }
                             Content::Resource(resource_content) => {
                                 match &resource_content.resource {
                                     ResourceContents::TextResourceContents { text, .. } => {
@@ -169,11 +166,13 @@ fn estimate_message_tokens(message: &Message, estimate_fn: &dyn Fn(&str) -> usiz
 /// - token_counts: A parallel vector containing the token count for each message.
 /// - context_limit: The maximum allowed context length in tokens.
 /// - strategy: The truncation strategy to use. Only option is OldestFirstTruncation.
+/// - family: The tokenizer family used to estimate the tokens of truncated content.
 pub fn truncate_messages(
     messages: &[Message],
     token_counts: &[usize],
     context_limit: usize,
     strategy: &dyn TruncationStrategy,
+    family: TokenizerFamily,
 ) -> Result<(Vec<Message>, Vec<usize>), anyhow::Error> {
     let mut messages = messages.to_owned();
     let mut token_counts = token_counts.to_owned();
@@ -197,7 +196,13 @@ pub fn truncate_messages(
             "Found oversized message with {} tokens, attempting content truncation",
             max_message_tokens
         );
-        return handle_oversized_messages(&messages, &token_counts, context_limit, strategy);
+        return handle_oversized_messages(
+            &messages,
+            &token_counts,
+            context_limit,
+            strategy,
+            family,
+        );
     }
 
     let min_user_msg_tokens = messages
@@ -235,7 +240,13 @@ pub fn truncate_messages(
             tokens_to_remove
         );
         // Try more aggressive truncation or content truncation
-        return handle_oversized_messages(&messages, &token_counts, context_limit, strategy);
+        return handle_oversized_messages(
+            &messages,
+            &token_counts,
+            context_limit,
+            strategy,
+            family,
+        );
     }
 
     if indices_to_remove.is_empty() && total_tokens > context_limit {
@@ -459,6 +470,7 @@ mod tests {
             &token_counts,
             context_limit,
             &OldestFirstTruncation,
+            TokenizerFamily::default(),
         );
 
         // Should succeed by truncating the large content
@@ -496,6 +508,7 @@ mod tests {
             &token_counts,
             context_limit,
             &OldestFirstTruncation,
+            TokenizerFamily::default(),
         )?;
 
         assert_eq!(result.0, messages);
@@ -539,6 +552,7 @@ mod tests {
             &token_counts,
             context_limit,
             &OldestFirstTruncation,
+            TokenizerFamily::default(),
         )?;
         let (truncated_messages, truncated_counts) = result;
 
@@ -580,6 +594,7 @@ mod tests {
             &token_counts,
             context_limit,
             &OldestFirstTruncation,
+            TokenizerFamily::default(),
         )?;
         let (mut messages, mut token_counts) = result;
 
@@ -595,6 +610,7 @@ mod tests {
             &token_counts,
             context_limit,
             &OldestFirstTruncation,
+            TokenizerFamily::default(),
         )?;
         let (messages, token_counts) = result;
 
@@ -634,6 +650,7 @@ mod tests {
             &token_counts,
             context_limit,
             &OldestFirstTruncation,
+            TokenizerFamily::default(),
         )?;
         let (truncated_messages, _) = result;
 
@@ -680,6 +697,7 @@ mod tests {
             &token_counts,
             context_limit,
             &OldestFirstTruncation,
+            TokenizerFamily::default(),
         )?;
         let (messages, token_counts) = result;
 
@@ -700,15 +718,36 @@ mod tests {
             &token_counts,
             5, // Impossibly small context
             &OldestFirstTruncation,
+            TokenizerFamily::default(),
         );
         assert!(result.is_err());
 
         // Test unmatched token counts
         let messages = vec![user_text(1, 10).0];
         let token_counts = vec![10, 10]; // Mismatched length
-        let result = truncate_messages(&messages, &token_counts, 100, &OldestFirstTruncation);
+        let result = truncate_messages(
+            &messages,
+            &token_counts,
+            100,
+            &OldestFirstTruncation,
+            TokenizerFamily::default(),
+        );
         assert!(result.is_err());
 
         Ok(())
     }
+
+    #[test]
+    fn test_truncate_message_content_multibyte() -> Result<()> {
+        // Each duck is 4 bytes, so most sizes fall inside one
+        let text = "🦆".repeat(10);
+        for max_content_size in 0..=text.len() {
+            let message = Message::user().with_text(&text);
+            let truncated = truncate_message_content(&message, max_content_size)?;
+            let truncated_text = truncated.as_concat_text();
+            let kept = &truncated_text[..truncated_text.find('\n').unwrap_or(truncated_text.len())];
+            assert_eq!(kept, "🦆".repeat(max_content_size / 4));
+        }
+        Ok(())
+    }
 }
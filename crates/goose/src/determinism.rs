@@ -0,0 +1,216 @@
+//! Where goose takes the time and new ids from, so tests can reproduce them
+//!
+//! Messages, tool calls without a provider id, and sessions get their timestamps and ids from
+//! [`now`] and [`new_uuid`]. These are the system clock and random UUIDs, unless a
+//! [`Deterministic`] guard replaced them with a [`Clock`] and an [`IdGenerator`] of its own.
+//! The replacement holds for the current thread, so each test sets its own, and tasks spawned
+//! onto other threads see the defaults.
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use chrono::{DateTime, TimeZone, Utc};
+use uuid::Uuid;
+
+/// The source of the current time
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The time of the system, the default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that always reads the same time
+#[derive(Debug, Clone, Copy)]
+pub struct FrozenClock {
+    at: DateTime<Utc>,
+}
+
+impl FrozenClock {
+    pub fn new(at: DateTime<Utc>) -> Self {
+        Self { at }
+    }
+}
+
+impl Default for FrozenClock {
+    /// 2025-01-01T00:00:00Z
+    fn default() -> Self {
+        Self::new(Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap())
+    }
+}
+
+impl Clock for FrozenClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.at
+    }
+}
+
+/// The source of new ids
+pub trait IdGenerator: Send + Sync {
+    fn next_uuid(&self) -> Uuid;
+}
+
+/// Random v4 UUIDs, the default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomIds;
+
+impl IdGenerator for RandomIds {
+    fn next_uuid(&self) -> Uuid {
+        Uuid::new_v4()
+    }
+}
+
+/// Random-looking v4 UUIDs from a seed, the same sequence for the same seed
+///
+/// The bits come from a SplitMix64 stream, which is simple enough to stay the same across
+/// versions of goose and its dependencies.
+#[derive(Debug, Default)]
+pub struct SeededIds {
+    seed: u64,
+    next: AtomicU64,
+}
+
+impl SeededIds {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            next: AtomicU64::new(0),
+        }
+    }
+
+    fn output(&self, index: u64) -> u64 {
+        let mut z = self
+            .seed
+            .wrapping_add(index.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl IdGenerator for SeededIds {
+    fn next_uuid(&self) -> Uuid {
+        let index = self.next.fetch_add(1, Ordering::Relaxed);
+        let bits =
+            (u128::from(self.output(2 * index)) << 64) | u128::from(self.output(2 * index + 1));
+        // Mark the bits as a random UUID, version 4 of the RFC 4122 variant
+        let bits = (bits & !(0xf << 76) & !(0x3 << 62)) | (0x4 << 76) | (0x2 << 62);
+        Uuid::from_u128(bits)
+    }
+}
+
+type Sources = (Arc<dyn Clock>, Arc<dyn IdGenerator>);
+
+thread_local! {
+    static SOURCES: RefCell<Option<Sources>> = const { RefCell::new(None) };
+}
+
+/// The current time
+pub fn now() -> DateTime<Utc> {
+    SOURCES.with(|sources| match &*sources.borrow() {
+        Some((clock, _)) => clock.now(),
+        None => Utc::now(),
+    })
+}
+
+/// A new id
+pub fn new_uuid() -> Uuid {
+    SOURCES.with(|sources| match &*sources.borrow() {
+        Some((_, ids)) => ids.next_uuid(),
+        None => Uuid::new_v4(),
+    })
+}
+
+/// Replaces the clock and ids of the current thread until it is dropped
+///
+/// ```
+/// use goose::determinism::{self, Deterministic};
+///
+/// let _deterministic = Deterministic::seeded(7);
+/// assert_eq!(determinism::now().timestamp(), 1_735_689_600);
+/// assert_eq!(
+///     determinism::new_uuid().to_string(),
+///     "63cbe1e4-5932-4dd7-844c-3cd7f43c661c"
+/// );
+/// ```
+#[must_use = "the clock and ids are only replaced until the guard is dropped"]
+pub struct Deterministic {
+    previous: Option<Sources>,
+}
+
+impl Deterministic {
+    pub fn new(clock: Arc<dyn Clock>, ids: Arc<dyn IdGenerator>) -> Self {
+        let previous = SOURCES.with(|sources| sources.borrow_mut().replace((clock, ids)));
+        Self { previous }
+    }
+
+    /// A [`FrozenClock`] at its default time and [`SeededIds`] of `seed`
+    pub fn seeded(seed: u64) -> Self {
+        Self::new(
+            Arc::new(FrozenClock::default()),
+            Arc::new(SeededIds::new(seed)),
+        )
+    }
+}
+
+impl Drop for Deterministic {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        SOURCES.with(|sources| *sources.borrow_mut() = previous);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+    use crate::providers::formats::openai::response_to_message;
+    use crate::session::storage::generate_session_id;
+    use serde_json::json;
+
+    #[test]
+    fn test_deterministic_messages() {
+        let response = json!({
+            "choices": [{"message": {"function_call": {"name": "developer__shell", "arguments": "{}"}}}]
+        });
+        let run = || {
+            let _deterministic = Deterministic::seeded(42);
+            (
+                Message::user().with_text("hello"),
+                response_to_message(response.clone()).unwrap(),
+                generate_session_id(),
+            )
+        };
+
+        let (first, second) = (run(), run());
+        assert_eq!(first, second);
+        assert_eq!(first.0.created, 1_735_689_600);
+        assert_eq!(
+            first.1.content[0].as_tool_request().unwrap().id,
+            "bdd73226-2feb-4e95-a8ef-e333b266f103"
+        );
+
+        // Dropping the guard restores the defaults, and guards nest
+        assert_ne!(new_uuid(), new_uuid());
+        let _outer = Deterministic::seeded(1);
+        {
+            let _inner = Deterministic::new(Arc::new(SystemClock), Arc::new(SeededIds::new(2)));
+            assert_eq!(
+                new_uuid().to_string(),
+                "975835de-1c97-46ce-bfc8-46100bfc1e42"
+            );
+        }
+        assert_eq!(
+            new_uuid().to_string(),
+            "910a2dec-8902-4cc1-beeb-8da1658eec67"
+        );
+    }
+}
@@ -1,12 +1,16 @@
 pub mod agents;
 pub mod config;
 pub mod context_mgmt;
+pub mod determinism;
 pub mod message;
 pub mod model;
 pub mod permission;
 pub mod prompt_template;
+pub mod prompts;
 pub mod providers;
 pub mod recipe;
+pub mod rpc;
+pub mod sandbox;
 pub mod scheduler;
 pub mod scheduler_factory;
 pub mod scheduler_trait;
@@ -15,6 +19,7 @@ pub mod temporal_scheduler;
 pub mod token_counter;
 pub mod tool_monitor;
 pub mod tracing;
+pub mod utils;
 
 #[cfg(test)]
 mod cron_test;
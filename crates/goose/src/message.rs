@@ -1,4 +1,5 @@
 use std::collections::HashSet;
+use std::time::Duration;
 
 /// Messages which represent the content sent back and forth to LLM provider
 ///
@@ -7,7 +8,9 @@ use std::collections::HashSet;
 ///
 /// The content of the messages uses MCP types to avoid additional conversions
 /// when interacting with MCP servers.
-use chrono::Utc;
+use crate::determinism;
+use crate::permission::CommandRisk;
+use crate::providers::utils::format_tool_arguments;
 use mcp_core::content::{Content, ImageContent, TextContent};
 use mcp_core::handler::ToolResult;
 use mcp_core::prompt::{PromptMessage, PromptMessageContent, PromptMessageRole};
@@ -15,7 +18,7 @@ use mcp_core::resource::ResourceContents;
 use mcp_core::role::Role;
 use mcp_core::tool::ToolCall;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 use utoipa::ToSchema;
 
 mod tool_result_serde;
@@ -37,8 +40,7 @@ impl ToolRequest {
                 format!(
                     "Tool: {}, Args: {}",
                     tool_call.name,
-                    serde_json::to_string_pretty(&tool_call.arguments)
-                        .unwrap_or_else(|_| "<<invalid json>>".to_string())
+                    format_tool_arguments(&tool_call.arguments)
                 )
             }
             Err(e) => format!("Invalid tool call: {}", e),
@@ -54,6 +56,41 @@ pub struct ToolResponse {
     #[serde(with = "tool_result_serde")]
     #[schema(value_type = Object)]
     pub tool_result: ToolResult<Vec<Content>>,
+    /// Where the result came from, for calls the agent dispatched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<ToolProvenance>,
+}
+
+/// How a tool call was handled, kept with its response for debugging sessions
+///
+/// This is never sent to providers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolProvenance {
+    /// Index in the conversation of the assistant message that requested the call
+    pub message_index: usize,
+    /// The extension that handled the call, `platform` for the tools of the agent itself
+    pub extension: String,
+    /// When the call was dispatched, in milliseconds since the epoch
+    pub started_at: i64,
+    /// When its result arrived, in milliseconds since the epoch
+    pub finished_at: i64,
+}
+
+impl ToolProvenance {
+    pub fn duration_ms(&self) -> i64 {
+        self.finished_at - self.started_at
+    }
+}
+
+impl ToolResponse {
+    /// How long the tool took, known for the calls the agent dispatched. Like the rest of the
+    /// provenance it is never sent to providers.
+    pub fn duration(&self) -> Option<Duration> {
+        self.provenance
+            .as_ref()
+            .map(|provenance| Duration::from_millis(provenance.duration_ms().max(0) as u64))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -64,6 +101,9 @@ pub struct ToolConfirmationRequest {
     pub tool_name: String,
     pub arguments: Value,
     pub prompt: Option<String>,
+    /// The risk of the command, for calls to shell tools
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub risk: Option<CommandRisk>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
@@ -96,6 +136,35 @@ pub struct SummarizationRequested {
     pub msg: String,
 }
 
+/// A source that text in a response was taken from
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CitationSource {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The passage of the source that was cited, when the provider returns it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cited_text: Option<String>,
+}
+
+/// A range of response text that cites a source
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Citation {
+    /// Byte offset of the start of the range
+    pub start: usize,
+    /// Byte offset of the end of the range, exclusive
+    pub end: usize,
+    pub source: CitationSource,
+}
+
+/// Citations of the text content directly before them in a message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CitationsContent {
+    pub citations: Vec<Citation>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 /// Content passed inside a message, which can be both simple content and tool content
 #[serde(tag = "type", rename_all = "camelCase")]
@@ -110,6 +179,7 @@ pub enum MessageContent {
     RedactedThinking(RedactedThinkingContent),
     ContextLengthExceeded(ContextLengthExceeded),
     SummarizationRequested(SummarizationRequested),
+    Citations(CitationsContent),
 }
 
 impl MessageContent {
@@ -139,6 +209,7 @@ impl MessageContent {
         MessageContent::ToolResponse(ToolResponse {
             id: id.into(),
             tool_result,
+            provenance: None,
         })
     }
 
@@ -153,6 +224,7 @@ impl MessageContent {
             tool_name,
             arguments,
             prompt,
+            risk: None,
         })
     }
 
@@ -182,6 +254,18 @@ impl MessageContent {
         MessageContent::SummarizationRequested(SummarizationRequested { msg: msg.into() })
     }
 
+    pub fn citations(citations: Vec<Citation>) -> Self {
+        MessageContent::Citations(CitationsContent { citations })
+    }
+
+    pub fn as_citations(&self) -> Option<&CitationsContent> {
+        if let MessageContent::Citations(ref citations) = self {
+            Some(citations)
+        } else {
+            None
+        }
+    }
+
     // Add this new method to check for summarization requested content
     pub fn as_summarization_requested(&self) -> Option<&SummarizationRequested> {
         if let MessageContent::SummarizationRequested(ref summarization_requested) = self {
@@ -238,6 +322,26 @@ impl MessageContent {
         }
     }
 
+    /// Whether the content is only for display, see [`Content::with_ephemeral`]
+    pub fn is_ephemeral(&self) -> bool {
+        let annotations = match self {
+            MessageContent::Text(text) => &text.annotations,
+            MessageContent::Image(image) => &image.annotations,
+            _ => return false,
+        };
+        annotations.as_ref().and_then(|a| a.ephemeral) == Some(true)
+    }
+
+    /// Get the audience of text and images, if set
+    pub fn audience(&self) -> Option<&Vec<Role>> {
+        let annotations = match self {
+            MessageContent::Text(text) => &text.annotations,
+            MessageContent::Image(image) => &image.annotations,
+            _ => return None,
+        };
+        annotations.as_ref().and_then(|a| a.audience.as_ref())
+    }
+
     /// Get the thinking content if this is a ThinkingContent variant
     pub fn as_thinking(&self) -> Option<&ThinkingContent> {
         match self {
@@ -264,6 +368,10 @@ impl From<Content> for MessageContent {
                 text: resource.get_text(),
                 annotations: None,
             }),
+            Content::Result(result) => MessageContent::Text(TextContent {
+                text: result.text,
+                annotations: result.annotations,
+            }),
         }
     }
 }
@@ -306,6 +414,11 @@ pub struct Message {
     pub role: Role,
     pub created: i64,
     pub content: Vec<MessageContent>,
+    /// Fields of a provider response that goose doesn't model, by the API format that
+    /// produced them, so the message goes back to that format as it was received
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    #[schema(value_type = Object)]
+    pub extra: Map<String, Value>,
 }
 
 impl Message {
@@ -313,8 +426,9 @@ impl Message {
     pub fn user() -> Self {
         Message {
             role: Role::User,
-            created: Utc::now().timestamp(),
+            created: determinism::now().timestamp(),
             content: Vec::new(),
+            extra: Map::new(),
         }
     }
 
@@ -322,8 +436,9 @@ impl Message {
     pub fn assistant() -> Self {
         Message {
             role: Role::Assistant,
-            created: Utc::now().timestamp(),
+            created: determinism::now().timestamp(),
             content: Vec::new(),
+            extra: Map::new(),
         }
     }
 
@@ -343,6 +458,11 @@ impl Message {
         self.with_content(MessageContent::image(data, mime_type))
     }
 
+    /// Add text that is shown to the user but never sent to models
+    pub fn with_ephemeral_text<S: Into<String>>(self, text: S) -> Self {
+        self.with_content(Content::text(text).with_ephemeral().into())
+    }
+
     /// Add a tool request to the message
     pub fn with_tool_request<S: Into<String>>(
         self,
@@ -361,6 +481,20 @@ impl Message {
         self.with_content(MessageContent::tool_response(id, result))
     }
 
+    /// Add a tool response, with where it came from
+    pub fn with_traced_tool_response<S: Into<String>>(
+        self,
+        id: S,
+        result: ToolResult<Vec<Content>>,
+        provenance: ToolProvenance,
+    ) -> Self {
+        self.with_content(MessageContent::ToolResponse(ToolResponse {
+            id: id.into(),
+            tool_result: result,
+            provenance: Some(provenance),
+        }))
+    }
+
     /// Add a tool confirmation request to the message
     pub fn with_tool_confirmation_request<S: Into<String>>(
         self,
@@ -401,6 +535,60 @@ impl Message {
         self.with_content(MessageContent::context_length_exceeded(msg))
     }
 
+    /// Keep response fields that goose doesn't model for the API format that produced them
+    pub fn with_extra<S: Into<String>>(mut self, format: S, fields: Map<String, Value>) -> Self {
+        if !fields.is_empty() {
+            self.extra.insert(format.into(), Value::Object(fields));
+        }
+        self
+    }
+
+    /// The response fields kept for the API `format`, to send back in requests of that format
+    pub fn extra_for(&self, format: &str) -> Option<&Map<String, Value>> {
+        self.extra.get(format).and_then(Value::as_object)
+    }
+
+    /// The content of the message meant for `role`, including the content of tool results
+    ///
+    /// Content without an audience is meant for everyone, and ephemeral content for the user.
+    pub fn for_audience(&self, role: Role) -> Message {
+        self.retain_content(|audience, ephemeral| {
+            audience.is_none_or(|audience| audience.contains(&role))
+                && !(ephemeral && role == Role::Assistant)
+        })
+    }
+
+    /// The message without its ephemeral content, as its history is sent to models
+    pub fn without_ephemeral(&self) -> Message {
+        self.retain_content(|_, ephemeral| !ephemeral)
+    }
+
+    /// The message with the content, and the content of tool results, that `keep` accepts
+    /// by its audience and whether it is ephemeral
+    fn retain_content(&self, keep: impl Fn(Option<&Vec<Role>>, bool) -> bool) -> Message {
+        let content = self
+            .content
+            .iter()
+            .filter_map(|content| match content {
+                MessageContent::ToolResponse(response) => {
+                    let mut response = response.clone();
+                    if let Ok(contents) = &mut response.tool_result {
+                        contents.retain(|content| keep(content.audience(), content.is_ephemeral()));
+                    }
+                    Some(MessageContent::ToolResponse(response))
+                }
+                _ if keep(content.audience(), content.is_ephemeral()) => Some(content.clone()),
+                _ => None,
+            })
+            .collect();
+        Message {
+            role: self.role.clone(),
+            created: self.created,
+            content,
+            extra: self.extra.clone(),
+        }
+    }
+
     /// Get the concatenated text content of the message, separated by newlines
     pub fn as_concat_text(&self) -> String {
         self.content
@@ -475,6 +663,121 @@ impl Message {
     pub fn with_summarization_requested<S: Into<String>>(self, msg: S) -> Self {
         self.with_content(MessageContent::summarization_requested(msg))
     }
+
+    /// Add citations of the text content added last
+    pub fn with_citations(self, citations: Vec<Citation>) -> Self {
+        self.with_content(MessageContent::citations(citations))
+    }
+
+    /// Merge adjacent text content into one block, as streamed responses arrive in pieces
+    ///
+    /// Text is joined as is, and only across items with the same annotations. Citations of the
+    /// merged text are moved to the merged block, with their ranges shifted to match. Other
+    /// content keeps its position between the merged blocks.
+    pub fn coalesce_text(mut self) -> Self {
+        let mut content: Vec<MessageContent> = Vec::with_capacity(self.content.len());
+        // Where the text last added to the trailing text block starts in it
+        let mut offset = 0;
+        for item in self.content.drain(..) {
+            match item {
+                MessageContent::Text(next) => {
+                    let merge_at = match content.as_slice() {
+                        [.., MessageContent::Text(_)] => Some(content.len() - 1),
+                        [.., MessageContent::Text(_), MessageContent::Citations(_)] => {
+                            Some(content.len() - 2)
+                        }
+                        _ => None,
+                    };
+                    if let Some(MessageContent::Text(last)) = merge_at.map(|i| &mut content[i]) {
+                        if last.annotations == next.annotations {
+                            offset = last.text.len();
+                            last.text.push_str(&next.text);
+                            continue;
+                        }
+                    }
+                    offset = 0;
+                    content.push(MessageContent::Text(next));
+                }
+                MessageContent::Citations(mut next) => {
+                    for citation in &mut next.citations {
+                        citation.start += offset;
+                        citation.end += offset;
+                    }
+                    offset = 0;
+                    match content.last_mut() {
+                        Some(MessageContent::Citations(last)) => {
+                            last.citations.append(&mut next.citations)
+                        }
+                        _ => content.push(MessageContent::Citations(next)),
+                    }
+                }
+                item => {
+                    offset = 0;
+                    content.push(item);
+                }
+            }
+        }
+        self.content = content;
+        self
+    }
+
+    /// The sources cited in the message, each once, in order of their first citation
+    pub fn citation_sources(&self) -> Vec<&CitationSource> {
+        let mut sources: Vec<&CitationSource> = Vec::new();
+        for citation in self
+            .content
+            .iter()
+            .filter_map(|c| c.as_citations())
+            .flat_map(|c| &c.citations)
+        {
+            if !sources.contains(&&citation.source) {
+                sources.push(&citation.source);
+            }
+        }
+        sources
+    }
+
+    /// Text content with a marker after each cited range, or None if the item is not text
+    ///
+    /// Markers are made by `marker` from the 1-based number of the source in
+    /// [`Message::citation_sources`], so they match a list of the sources after the message.
+    pub fn text_with_citation_markers(
+        &self,
+        index: usize,
+        marker: impl Fn(usize) -> String,
+    ) -> Option<String> {
+        let text = self.content.get(index)?.as_text()?;
+        let Some(citations) = self.content.get(index + 1).and_then(|c| c.as_citations()) else {
+            return Some(text.to_string());
+        };
+
+        let sources = self.citation_sources();
+        let mut ends: Vec<(usize, usize)> = citations
+            .citations
+            .iter()
+            .filter_map(|citation| {
+                let number = sources.iter().position(|s| *s == &citation.source)? + 1;
+                // Ranges from an edited or foreign history may not fit the text
+                let mut end = citation.end.min(text.len());
+                while !text.is_char_boundary(end) {
+                    end -= 1;
+                }
+                Some((end, number))
+            })
+            .collect();
+        ends.sort();
+        ends.dedup();
+
+        let mut marked = String::with_capacity(text.len());
+        let mut copied = 0;
+        for (end, number) in ends {
+            marked.push_str(&text[copied..end]);
+            marked.push_str(&marker(number));
+            copied = end;
+        }
+        marked.push_str(&text[copied..]);
+        Some(marked)
+    }
 }
 
 #[cfg(test)]
@@ -752,4 +1055,114 @@ mod tests {
         assert_eq!(ids.len(), 1);
         assert!(ids.contains("req1"));
     }
+
+    #[test]
+    fn test_tool_request_readable_string() {
+        let request = ToolRequest {
+            id: "req1".to_string(),
+            tool_call: Ok(ToolCall::new("shell", json!({"command": "echo one\ntwo"}))),
+        };
+        assert_eq!(
+            request.to_readable_string(),
+            "Tool: shell, Args: {\n  \"command\": \"echo one\ntwo\"\n}"
+        );
+    }
+
+    #[test]
+    fn test_coalesce_text() {
+        let tool_call = Ok(ToolCall::new("test_tool", serde_json::json!({})));
+        let message = Message::assistant()
+            .with_text("Let me ")
+            .with_text("check that ")
+            .with_text("for you.")
+            .with_tool_request("req1", tool_call)
+            .coalesce_text();
+
+        assert_eq!(message.content.len(), 2);
+        assert_eq!(
+            message.content[0].as_text(),
+            Some("Let me check that for you.")
+        );
+        assert_eq!(message.content[1].as_tool_request().unwrap().id, "req1");
+
+        // Text on either side of other content stays apart
+        let message = Message::assistant()
+            .with_text("before")
+            .with_image("abc", "image/png")
+            .with_text("after")
+            .coalesce_text();
+        assert_eq!(message.content.len(), 3);
+    }
+
+    fn source(url: &str) -> CitationSource {
+        CitationSource {
+            url: Some(url.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn citation(start: usize, end: usize, url: &str) -> Citation {
+        Citation {
+            start,
+            end,
+            source: source(url),
+        }
+    }
+
+    #[test]
+    fn test_coalesce_text_shifts_citations() {
+        let message = Message::assistant()
+            .with_text("Paris is the capital. ")
+            .with_text("It has 2.1M people.")
+            .with_citations(vec![citation(7, 18, "https://a.example")])
+            .with_text(" Café culture is big.")
+            .with_citations(vec![citation(1, 14, "https://b.example")])
+            .coalesce_text();
+
+        assert_eq!(message.content.len(), 2);
+        let text = message.content[0].as_text().unwrap();
+        let citations = &message.content[1].as_citations().unwrap().citations;
+        assert_eq!(&text[citations[0].start..citations[0].end], "2.1M people");
+        assert_eq!(&text[citations[1].start..citations[1].end], "Café culture");
+    }
+
+    #[test]
+    fn test_text_with_citation_markers() {
+        let message = Message::assistant()
+            .with_text("Paris is the capital. Café culture is big.")
+            .with_citations(vec![
+                citation(0, 21, "https://a.example"),
+                citation(22, 35, "https://b.example"),
+                citation(22, 35, "https://a.example"),
+                // Out of range, as in a history edited by hand
+                citation(0, 100, "https://b.example"),
+            ]);
+
+        let marked = message
+            .text_with_citation_markers(0, |n| format!("[{}]", n))
+            .unwrap();
+        assert_eq!(
+            marked,
+            "Paris is the capital.[1] Café culture[1][2] is big.[2]"
+        );
+        assert_eq!(
+            message.citation_sources(),
+            vec![&source("https://a.example"), &source("https://b.example")]
+        );
+        assert_eq!(
+            message.text_with_citation_markers(1, |n| n.to_string()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_citations_serialization_roundtrip() {
+        let message = Message::assistant()
+            .with_text("Café culture is big.")
+            .with_citations(vec![citation(0, 13, "https://a.example")]);
+        let json = serde_json::to_string(&message).unwrap();
+        assert!(json.contains(r#""type":"citations""#));
+        let parsed: Message = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, message);
+    }
 }
@@ -1,7 +1,13 @@
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::collections::HashMap;
 
+use crate::providers::capabilities::PayloadLimits;
+use crate::providers::formats::tool_schema::ToolSchemaVerbosity;
+use crate::providers::utils::{TemperaturePolicy, ToolContentSeparator, ToolImagePolicy};
+use crate::token_counter::TokenizerFamily;
+
 const DEFAULT_CONTEXT_LIMIT: usize = 128_000;
 
 // Tokenizer names, used to infer from model name
@@ -33,9 +39,27 @@ static MODEL_SPECIFIC_LIMITS: Lazy<HashMap<&'static str, usize>> = Lazy::new(||
 
     // x.ai Grok models, https://docs.x.ai/docs/overview
     map.insert("grok", 131_072);
+
+    // Fireworks models, https://fireworks.ai/models
+    map.insert("llama-v3p1", 131_072);
+    map.insert("llama-v3p3", 131_072);
+    map.insert("qwen2p5", 32_768);
+    map.insert("deepseek-v3", 131_072);
+
+    // Together models, https://docs.together.ai/docs/serverless-models
+    map.insert("Meta-Llama-3.1", 131_072);
+    map.insert("Llama-3.3", 131_072);
+    map.insert("Qwen2.5", 32_768);
+    map.insert("DeepSeek-V3", 131_072);
     map
 });
 
+// Models that take tool schemas in OpenAI's strict form, matched as prefixes of the model name,
+// https://platform.openai.com/docs/guides/function-calling#strict-mode
+const STRICT_TOOL_MODELS: &[&str] = &[
+    "gpt-4o", "gpt-4.1", "gpt-4-1", "gpt-5", "o1", "o3", "o4-mini",
+];
+
 /// Configuration for model-specific settings and limits
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelConfig {
@@ -47,14 +71,64 @@ pub struct ModelConfig {
     pub tokenizer_name: String,
     /// Optional explicit context limit that overrides any defaults
     pub context_limit: Option<usize>,
-    /// Optional temperature setting (0.0 - 1.0)
+    /// Optional temperature setting, the accepted range depends on the provider
     pub temperature: Option<f32>,
+    /// Whether a temperature outside of the provider's range is clamped or rejected
+    #[serde(default)]
+    pub temperature_policy: TemperaturePolicy,
     /// Optional maximum tokens to generate
     pub max_tokens: Option<i32>,
     /// Whether to interpret tool calls with toolshim
     pub toolshim: bool,
     /// Model to use for toolshim (optional as a default exists)
     pub toolshim_model: Option<String>,
+    /// Whether to call tools through the legacy `functions` and `function_call` fields, for
+    /// OpenAI compatible APIs that don't support `tools`
+    #[serde(default)]
+    pub legacy_function_calling: bool,
+    /// Whether a request may carry only the system prompt, for APIs that accept one without
+    /// messages. Others answer such a request with an unhelpful error, so it is refused early.
+    #[serde(default)]
+    pub allow_system_only: bool,
+    /// Whether tool schemas are sent in OpenAI's strict form, decided by the model registry
+    /// when not set
+    #[serde(default)]
+    pub strict_tools: Option<bool>,
+    /// How much documentation tool schemas carry in requests
+    #[serde(default)]
+    pub tool_schema_verbosity: ToolSchemaVerbosity,
+    /// How the text blocks of tool results are joined for providers that take one string
+    #[serde(default)]
+    pub tool_content_separator: ToolContentSeparator,
+    /// Where images in tool results go for providers that take tool results as text
+    #[serde(default)]
+    pub tool_image_policy: ToolImagePolicy,
+    /// Optional maximum number of tools sent with a request, for providers that cap it
+    #[serde(default)]
+    pub max_tools: Option<usize>,
+    /// Optional maximum size in bytes of a text in one message, larger texts are split across
+    /// messages for providers that cap the size of each message
+    #[serde(default)]
+    pub max_message_bytes: Option<usize>,
+    /// Optional maximum number of images in a request, older images beyond it are replaced
+    /// with a note. Known models default to the limit of their provider.
+    #[serde(default)]
+    pub max_images: Option<usize>,
+    /// Optional maximum size in bytes of a request body. Known models default to the limit of
+    /// their provider.
+    #[serde(default)]
+    pub max_request_bytes: Option<usize>,
+    /// Optional seed for sampling, so repeated requests give the same response where the
+    /// provider supports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Optional request metadata for tracing, sent under the provider's metadata key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Map<String, Value>>,
+    /// Whether requests skip the checks against known model capabilities, for models that
+    /// differ from the registry
+    #[serde(default)]
+    pub no_validate: bool,
 }
 
 /// Struct to represent model pattern matches and their limits
@@ -81,18 +155,90 @@ impl ModelConfig {
 
         let toolshim_model = std::env::var("GOOSE_TOOLSHIM_OLLAMA_MODEL").ok();
 
+        let legacy_function_calling = std::env::var("GOOSE_LEGACY_FUNCTION_CALLING")
+            .map(|val| val == "1" || val.to_lowercase() == "true")
+            .unwrap_or(false);
+
+        let strict_tools = std::env::var("GOOSE_STRICT_TOOLS").ok().and_then(|val| {
+            match val.to_lowercase().as_str() {
+                "1" | "true" => Some(true),
+                "0" | "false" => Some(false),
+                _ => None,
+            }
+        });
+
+        let tool_schema_verbosity = std::env::var("GOOSE_TOOL_SCHEMA_VERBOSITY")
+            .ok()
+            .and_then(|val| val.parse().ok())
+            .unwrap_or_default();
+
+        let tool_content_separator = std::env::var("GOOSE_TOOL_CONTENT_SEPARATOR")
+            .ok()
+            .and_then(|val| val.parse().ok())
+            .unwrap_or_default();
+
+        let tool_image_policy = std::env::var("GOOSE_TOOL_IMAGE_POLICY")
+            .ok()
+            .and_then(|val| val.parse().ok())
+            .unwrap_or_default();
+
+        let max_tools = std::env::var("GOOSE_MAX_TOOLS")
+            .ok()
+            .and_then(|val| val.parse::<usize>().ok());
+
+        let max_message_bytes = std::env::var("GOOSE_MAX_MESSAGE_BYTES")
+            .ok()
+            .and_then(|val| val.parse::<usize>().ok())
+            .filter(|bytes| *bytes > 0);
+
+        let max_images = std::env::var("GOOSE_MAX_IMAGES")
+            .ok()
+            .and_then(|val| val.parse::<usize>().ok());
+
+        let max_request_bytes = std::env::var("GOOSE_MAX_REQUEST_BYTES")
+            .ok()
+            .and_then(|val| val.parse::<usize>().ok())
+            .filter(|bytes| *bytes > 0);
+
+        let seed = std::env::var("GOOSE_SEED")
+            .ok()
+            .and_then(|val| val.parse::<u64>().ok());
+
         let temperature = std::env::var("GOOSE_TEMPERATURE")
             .ok()
             .and_then(|val| val.parse::<f32>().ok());
 
+        let temperature_policy = std::env::var("GOOSE_TEMPERATURE_POLICY")
+            .ok()
+            .and_then(|val| val.parse().ok())
+            .unwrap_or_default();
+
+        let no_validate = std::env::var("GOOSE_NO_VALIDATE")
+            .map(|val| val == "1" || val.to_lowercase() == "true")
+            .unwrap_or(false);
+
         Self {
             model_name,
             tokenizer_name: tokenizer_name.to_string(),
             context_limit,
             temperature,
+            temperature_policy,
             max_tokens: None,
             toolshim,
             toolshim_model,
+            legacy_function_calling,
+            allow_system_only: false,
+            strict_tools,
+            tool_schema_verbosity,
+            tool_content_separator,
+            tool_image_policy,
+            max_tools,
+            max_message_bytes,
+            max_images,
+            max_request_bytes,
+            seed,
+            metadata: None,
+            no_validate,
         }
     }
 
@@ -143,6 +289,12 @@ impl ModelConfig {
         self
     }
 
+    /// Set whether a temperature outside of the provider's range is clamped or rejected
+    pub fn with_temperature_policy(mut self, policy: TemperaturePolicy) -> Self {
+        self.temperature_policy = policy;
+        self
+    }
+
     /// Set the max tokens
     pub fn with_max_tokens(mut self, tokens: Option<i32>) -> Self {
         self.max_tokens = tokens;
@@ -161,11 +313,113 @@ impl ModelConfig {
         self
     }
 
+    /// Set whether to call tools through the legacy `functions` fields
+    pub fn with_legacy_function_calling(mut self, legacy_function_calling: bool) -> Self {
+        self.legacy_function_calling = legacy_function_calling;
+        self
+    }
+
+    /// Set whether a request may carry only the system prompt
+    pub fn with_allow_system_only(mut self, allow_system_only: bool) -> Self {
+        self.allow_system_only = allow_system_only;
+        self
+    }
+
+    /// Set whether tool schemas are sent in strict form, `None` leaves it to the model registry
+    pub fn with_strict_tools(mut self, strict_tools: Option<bool>) -> Self {
+        self.strict_tools = strict_tools;
+        self
+    }
+
+    /// Whether tool schemas are sent in strict form, set explicitly or known for the model
+    pub fn strict_tools(&self) -> bool {
+        self.strict_tools.unwrap_or_else(|| {
+            let model = self.model_name.rsplit('/').next().unwrap_or_default();
+            STRICT_TOOL_MODELS
+                .iter()
+                .any(|pattern| model.starts_with(pattern))
+        })
+    }
+
+    /// Set how much documentation tool schemas carry in requests
+    pub fn with_tool_schema_verbosity(mut self, verbosity: ToolSchemaVerbosity) -> Self {
+        self.tool_schema_verbosity = verbosity;
+        self
+    }
+
+    /// Set how the text blocks of tool results are joined
+    pub fn with_tool_content_separator(mut self, separator: ToolContentSeparator) -> Self {
+        self.tool_content_separator = separator;
+        self
+    }
+
+    /// Set where images in tool results go
+    pub fn with_tool_image_policy(mut self, policy: ToolImagePolicy) -> Self {
+        self.tool_image_policy = policy;
+        self
+    }
+
+    /// Set the maximum number of tools sent with a request
+    pub fn with_max_tools(mut self, max_tools: Option<usize>) -> Self {
+        self.max_tools = max_tools;
+        self
+    }
+
+    /// Set the maximum size in bytes of a text in one message
+    pub fn with_max_message_bytes(mut self, max_message_bytes: Option<usize>) -> Self {
+        self.max_message_bytes = max_message_bytes;
+        self
+    }
+
+    /// Set the maximum number of images in a request
+    pub fn with_max_images(mut self, max_images: Option<usize>) -> Self {
+        self.max_images = max_images;
+        self
+    }
+
+    /// Set the maximum size in bytes of a request body
+    pub fn with_max_request_bytes(mut self, max_request_bytes: Option<usize>) -> Self {
+        self.max_request_bytes = max_request_bytes;
+        self
+    }
+
+    /// The limits of a request, set explicitly or known for the provider of the model
+    pub fn payload_limits(&self) -> PayloadLimits {
+        let known = PayloadLimits::for_model(&self.model_name);
+        PayloadLimits {
+            max_images: self.max_images.or(known.max_images),
+            max_request_bytes: self.max_request_bytes.or(known.max_request_bytes),
+        }
+    }
+
+    /// Set the seed for sampling
+    pub fn with_seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Set the request metadata
+    pub fn with_metadata(mut self, metadata: Option<Map<String, Value>>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Set whether requests skip the checks against known model capabilities
+    pub fn with_no_validate(mut self, no_validate: bool) -> Self {
+        self.no_validate = no_validate;
+        self
+    }
+
     /// Get the tokenizer name
     pub fn tokenizer_name(&self) -> &str {
         &self.tokenizer_name
     }
 
+    /// Get the tokenizer family used to estimate token counts
+    pub fn tokenizer_family(&self) -> TokenizerFamily {
+        TokenizerFamily::for_model(&self.model_name)
+    }
+
     /// Get the context_limit for the current model
     /// If none are defined, use the DEFAULT_CONTEXT_LIMIT
     pub fn context_limit(&self) -> usize {
@@ -208,6 +462,19 @@ mod tests {
         assert_eq!(config.context_limit, Some(50_000));
     }
 
+    #[test]
+    fn test_strict_tools() {
+        assert!(ModelConfig::new("gpt-4o-mini".to_string()).strict_tools());
+        assert!(ModelConfig::new("openai/gpt-4.1".to_string()).strict_tools());
+        assert!(!ModelConfig::new("claude-3-5-sonnet".to_string()).strict_tools());
+        assert!(!ModelConfig::new("llama3.3".to_string()).strict_tools());
+
+        let config = ModelConfig::new("gpt-4o".to_string()).with_strict_tools(Some(false));
+        assert!(!config.strict_tools());
+        let config = ModelConfig::new("llama3.3".to_string()).with_strict_tools(Some(true));
+        assert!(config.strict_tools());
+    }
+
     #[test]
     fn test_model_config_tool_interpretation() {
         // Test without env vars - should be false
@@ -1,7 +1,9 @@
+pub mod command_risk;
 pub mod permission_confirmation;
 pub mod permission_judge;
 pub mod permission_store;
 
+pub use command_risk::{CommandRisk, CommandRiskClassifier, CommandRiskPolicy, RiskLevel};
 pub use permission_confirmation::{Permission, PermissionConfirmation};
 pub use permission_judge::detect_read_only_tools;
 pub use permission_store::ToolPermissionStore;
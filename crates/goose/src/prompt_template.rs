@@ -2,6 +2,7 @@ use include_dir::{include_dir, Dir};
 use minijinja::{Environment, Error as MiniJinjaError, Value as MJValue};
 use once_cell::sync::Lazy;
 use serde::Serialize;
+use std::collections::HashSet;
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 
@@ -98,6 +99,117 @@ pub fn render_inline_once<T: Serialize>(
     Ok(rendered.trim().to_string())
 }
 
+/// The parts of a system prompt that several sources contributed, trimmed, in the order they
+/// were given. Empty parts and repeats of an earlier part are left out.
+pub fn system_prompt_parts(parts: &[String]) -> Vec<&str> {
+    let mut seen = HashSet::new();
+    parts
+        .iter()
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty() && seen.insert(*part))
+        .collect()
+}
+
+/// Join the parts of a system prompt into the single string providers take
+pub fn compose_system_prompt(parts: &[String], separator: &str) -> String {
+    system_prompt_parts(parts).join(separator)
+}
+
+/// Starts each block of an encoded system prompt, followed by the block's kind and a newline
+const SYSTEM_BLOCK_MARKER: char = '\u{1e}';
+
+/// Whether a block of the system prompt stays the same from turn to turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SystemBlockKind {
+    /// Instructions that only change between sessions, which providers can cache
+    Stable,
+    /// Context that can change during a session, such as the state of the workspace
+    Dynamic,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemBlock {
+    pub kind: SystemBlockKind,
+    pub text: String,
+}
+
+/// A system prompt made of ordered blocks. Stable blocks go first, so that they form a prefix
+/// providers can cache.
+///
+/// Providers take the system prompt as a string, so the blocks travel in `encode`d form to
+/// providers that send them separately, and `render`ed into one string to all others.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SystemPrompt {
+    blocks: Vec<SystemBlock>,
+}
+
+impl SystemPrompt {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a block that only changes between sessions. Empty text is left out.
+    pub fn push_stable(&mut self, text: impl Into<String>) {
+        self.push(SystemBlockKind::Stable, text.into());
+    }
+
+    /// Add a block that can change during a session. Empty text is left out.
+    pub fn push_dynamic(&mut self, text: impl Into<String>) {
+        self.push(SystemBlockKind::Dynamic, text.into());
+    }
+
+    fn push(&mut self, kind: SystemBlockKind, text: String) {
+        if !text.trim().is_empty() {
+            self.blocks.push(SystemBlock { kind, text });
+        }
+    }
+
+    pub fn blocks(&self) -> &[SystemBlock] {
+        &self.blocks
+    }
+
+    /// The whole prompt as one string, for providers that take a single system message
+    pub fn render(&self) -> String {
+        self.blocks
+            .iter()
+            .map(|block| block.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// The prompt as one string that keeps the block boundaries, see `decode`
+    pub fn encode(&self) -> String {
+        self.blocks
+            .iter()
+            .map(|block| {
+                let kind = match block.kind {
+                    SystemBlockKind::Stable => "stable",
+                    SystemBlockKind::Dynamic => "dynamic",
+                };
+                format!("{}{}\n{}", SYSTEM_BLOCK_MARKER, kind, block.text)
+            })
+            .collect()
+    }
+
+    /// Read the blocks of an `encode`d prompt. Any other string is a single stable block.
+    pub fn decode(system: &str) -> Self {
+        let mut prompt = Self::new();
+        if !system.starts_with(SYSTEM_BLOCK_MARKER) {
+            prompt.push_stable(system);
+            return prompt;
+        }
+        for block in system.split(SYSTEM_BLOCK_MARKER).skip(1) {
+            let (kind, text) = block.split_once('\n').unwrap_or((block, ""));
+            match kind {
+                "dynamic" => prompt.push_dynamic(text),
+                _ => prompt.push_stable(text),
+            }
+        }
+        prompt
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -235,4 +347,56 @@ mod tests {
         let expected = "### Tool Descriptions";
         assert_eq!(rendered, expected);
     }
+
+    #[test]
+    fn test_compose_system_prompt() {
+        let parts = vec![
+            "You are a helpful assistant.".to_string(),
+            "Use the developer tools.\n".to_string(),
+            "  ".to_string(),
+            "You are a helpful assistant.".to_string(),
+            "Answer in English.".to_string(),
+        ];
+        assert_eq!(
+            compose_system_prompt(&parts, "\n\n"),
+            "You are a helpful assistant.\n\nUse the developer tools.\n\nAnswer in English."
+        );
+        assert_eq!(compose_system_prompt(&parts[..1], "\n\n"), parts[0]);
+        assert_eq!(compose_system_prompt(&[], "\n\n"), "");
+    }
+
+    #[test]
+    fn test_system_prompt_blocks() {
+        let mut prompt = SystemPrompt::new();
+        prompt.push_stable("You are goose.");
+        prompt.push_stable("  ");
+        prompt.push_dynamic("# Workspace\n\nOn branch main");
+        prompt.push_stable("Be brief.");
+
+        assert_eq!(
+            prompt.render(),
+            "You are goose.\n\n# Workspace\n\nOn branch main\n\nBe brief."
+        );
+        let decoded = SystemPrompt::decode(&prompt.encode());
+        assert_eq!(decoded, prompt);
+        assert_eq!(
+            decoded
+                .blocks()
+                .iter()
+                .map(|block| block.kind)
+                .collect::<Vec<_>>(),
+            vec![
+                SystemBlockKind::Stable,
+                SystemBlockKind::Dynamic,
+                SystemBlockKind::Stable
+            ]
+        );
+
+        // Plain strings are one stable block, and render back unchanged
+        let plain = SystemPrompt::decode("You are goose.\n\nBe brief.\n");
+        assert_eq!(plain.blocks().len(), 1);
+        assert_eq!(plain.blocks()[0].kind, SystemBlockKind::Stable);
+        assert_eq!(plain.render(), "You are goose.\n\nBe brief.\n");
+        assert_eq!(SystemPrompt::decode("").render(), "");
+    }
 }
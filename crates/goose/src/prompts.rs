@@ -0,0 +1,528 @@
+//! Composable sections of a system prompt
+//!
+//! A [`PromptSection`] is a piece of a system prompt, such as the instructions for a coding
+//! agent or a terse answering style. Goose ships a few built-in sections, and users can add
+//! their own as markdown files with a front-matter header in `~/.config/goose/prompts`:
+//!
+//! ```markdown
+//! ---
+//! name: rust
+//! priority: 60
+//! when:
+//!   - git_repo
+//!   - file_exists: Cargo.toml
+//! ---
+//! Run `cargo clippy` before you are done with a change in {{ working_dir }}.
+//! ```
+//!
+//! A [`SystemPrompt`] puts the enabled sections together, highest priority first, and leaves
+//! out the sections that don't fit in its token budget.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use etcetera::{choose_app_strategy, AppStrategy};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::prompt_template::render_inline_once;
+use crate::token_counter::{estimate_tokens, TokenizerFamily};
+
+/// The default priority of a section, when it doesn't set one
+pub const DEFAULT_PRIORITY: i32 = 50;
+
+/// A condition that must hold for a section to be included
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SectionCondition {
+    /// The working directory is inside a git repository
+    GitRepo,
+    /// A file exists, relative to the working directory
+    FileExists(String),
+    /// An environment variable is set to a non-empty value
+    EnvSet(String),
+}
+
+impl SectionCondition {
+    pub fn holds(&self, context: &SectionContext) -> bool {
+        match self {
+            SectionCondition::GitRepo => context
+                .working_dir
+                .ancestors()
+                .any(|dir| dir.join(".git").exists()),
+            SectionCondition::FileExists(path) => context.working_dir.join(path).exists(),
+            SectionCondition::EnvSet(name) => {
+                std::env::var(name).is_ok_and(|value| !value.is_empty())
+            }
+        }
+    }
+}
+
+/// What the conditions of sections are checked against
+#[derive(Debug, Clone)]
+pub struct SectionContext {
+    pub working_dir: PathBuf,
+}
+
+impl SectionContext {
+    pub fn new(working_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            working_dir: working_dir.into(),
+        }
+    }
+
+    pub fn current_dir() -> Self {
+        Self::new(std::env::current_dir().unwrap_or_default())
+    }
+}
+
+/// A named piece of a system prompt
+///
+/// The template is rendered with the variables of the [`SystemPrompt`] it is added to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptSection {
+    pub name: String,
+    /// Sections with a higher priority come first, and are the last to be left out
+    pub priority: i32,
+    pub enabled: bool,
+    /// The section is only included when all of these hold
+    pub when: Vec<SectionCondition>,
+    pub template: String,
+}
+
+impl PromptSection {
+    pub fn new(name: impl Into<String>, template: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            priority: DEFAULT_PRIORITY,
+            enabled: true,
+            when: Vec::new(),
+            template: template.into(),
+        }
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    pub fn when(mut self, condition: SectionCondition) -> Self {
+        self.when.push(condition);
+        self
+    }
+
+    /// An estimate of the tokens of the template, before rendering
+    pub fn estimated_tokens(&self) -> usize {
+        estimate_tokens(&self.template, TokenizerFamily::default())
+    }
+
+    /// Whether the section is enabled and all of its conditions hold
+    pub fn is_active(&self, context: &SectionContext) -> bool {
+        self.enabled && self.when.iter().all(|condition| condition.holds(context))
+    }
+
+    /// Parse a section from a markdown file with a front-matter header
+    ///
+    /// The header is a YAML block between `---` lines with the `name` of the section, and
+    /// optionally its `priority`, `enabled` and `when` conditions. Without a name in the header,
+    /// `default_name` is used.
+    pub fn parse(source: &str, default_name: &str) -> Result<Self> {
+        let (front_matter, body) = split_front_matter(source)?;
+        let front_matter: FrontMatter = match front_matter {
+            Some(yaml) => serde_yaml::from_str(yaml).context("Invalid front matter")?,
+            None => FrontMatter::default(),
+        };
+        let name = front_matter
+            .name
+            .unwrap_or_else(|| default_name.to_string());
+        if name.trim().is_empty() {
+            return Err(anyhow!("Prompt section has no name"));
+        }
+
+        Ok(Self {
+            name,
+            priority: front_matter.priority.unwrap_or(DEFAULT_PRIORITY),
+            enabled: front_matter.enabled.unwrap_or(true),
+            when: front_matter.when,
+            template: body.trim().to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FrontMatter {
+    name: Option<String>,
+    priority: Option<i32>,
+    enabled: Option<bool>,
+    // Lets conditions with a value be written as `file_exists: Cargo.toml` instead of a YAML tag
+    #[serde(default, with = "serde_yaml::with::singleton_map_recursive")]
+    when: Vec<SectionCondition>,
+}
+
+/// Split a `---` delimited header from the rest of the source
+fn split_front_matter(source: &str) -> Result<(Option<&str>, &str)> {
+    let source = source.trim_start_matches('\u{feff}');
+    let Some(rest) = source
+        .strip_prefix("---\n")
+        .or_else(|| source.strip_prefix("---\r\n"))
+    else {
+        return Ok((None, source));
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return Ok((Some(&rest[..offset]), &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    Err(anyhow!("Front matter is not closed with ---"))
+}
+
+/// Instructions for working on code in an existing project
+pub fn coding_agent() -> PromptSection {
+    PromptSection::new(
+        "coding_agent",
+        include_str!("prompts/sections/coding_agent.md"),
+    )
+    .with_priority(80)
+}
+
+/// Instructions for reviewing changes
+pub fn code_reviewer() -> PromptSection {
+    PromptSection::new(
+        "code_reviewer",
+        include_str!("prompts/sections/code_reviewer.md"),
+    )
+    .with_priority(80)
+}
+
+/// Instructions for exploring and reporting on data
+pub fn data_analyst() -> PromptSection {
+    PromptSection::new(
+        "data_analyst",
+        include_str!("prompts/sections/data_analyst.md"),
+    )
+    .with_priority(80)
+}
+
+/// Short answers without preamble
+pub fn terse() -> PromptSection {
+    PromptSection::new("terse", include_str!("prompts/sections/terse.md")).with_priority(20)
+}
+
+/// Step by step explanations for newcomers
+pub fn verbose_explainer() -> PromptSection {
+    PromptSection::new(
+        "verbose_explainer",
+        include_str!("prompts/sections/verbose_explainer.md"),
+    )
+    .with_priority(20)
+}
+
+/// The built-in sections, by name
+pub fn builtin_section(name: &str) -> Option<PromptSection> {
+    match name {
+        "coding_agent" => Some(coding_agent()),
+        "code_reviewer" => Some(code_reviewer()),
+        "data_analyst" => Some(data_analyst()),
+        "terse" => Some(terse()),
+        "verbose_explainer" => Some(verbose_explainer()),
+        _ => None,
+    }
+}
+
+/// The directory user sections are loaded from
+pub fn user_sections_dir() -> Option<PathBuf> {
+    // - macOS/Linux: ~/.config/goose/prompts
+    // - Windows:     ~\AppData\Roaming\Block\goose\config\prompts\
+    choose_app_strategy(crate::config::APP_STRATEGY.clone())
+        .ok()
+        .map(|strategy| strategy.in_config_dir("prompts"))
+}
+
+/// Load every `.md` file of a directory as a section, named after the file by default
+///
+/// Files that can't be read or parsed are skipped with a warning. A missing directory has no
+/// sections.
+pub fn load_user_sections(dir: &Path) -> Vec<PromptSection> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let default_name = path.file_stem()?.to_string_lossy().to_string();
+            let section = std::fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|source| PromptSection::parse(&source, &default_name));
+            match section {
+                Ok(section) => Some(section),
+                Err(e) => {
+                    tracing::warn!("Skipping prompt section {}: {:#}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// A system prompt assembled from sections
+#[derive(Debug, Clone, Default)]
+pub struct SystemPrompt {
+    sections: Vec<PromptSection>,
+    variables: HashMap<String, Value>,
+    max_tokens: Option<usize>,
+}
+
+/// The result of [`SystemPrompt::build`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuiltPrompt {
+    pub text: String,
+    /// The estimated tokens of `text`
+    pub tokens: usize,
+    /// The names of the sections included, in order
+    pub included: Vec<String>,
+    /// The names of the active sections left out to stay within the token budget
+    pub dropped: Vec<String>,
+}
+
+impl SystemPrompt {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a section, replacing any section with the same name
+    pub fn with_section(mut self, section: PromptSection) -> Self {
+        self.sections
+            .retain(|existing| existing.name != section.name);
+        self.sections.push(section);
+        self
+    }
+
+    /// Add the sections of a directory, see [`load_user_sections`]
+    pub fn with_user_sections(self, dir: &Path) -> Self {
+        load_user_sections(dir)
+            .into_iter()
+            .fold(self, |prompt, section| prompt.with_section(section))
+    }
+
+    /// Leave out the lowest priority sections until the prompt fits in `max_tokens`
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Set a variable the section templates can use
+    pub fn with_variable(mut self, name: impl Into<String>, value: impl Serialize) -> Self {
+        let value = serde_json::to_value(value).unwrap_or(Value::Null);
+        self.variables.insert(name.into(), value);
+        self
+    }
+
+    /// Render the active sections, highest priority first, within the token budget
+    ///
+    /// Sections with the same priority keep the order they were added in. When a section
+    /// doesn't fit, it is left out and lower priority sections are still tried.
+    pub fn build(&self, context: &SectionContext) -> Result<BuiltPrompt> {
+        let mut variables = self.variables.clone();
+        variables
+            .entry("working_dir".to_string())
+            .or_insert_with(|| Value::String(context.working_dir.display().to_string()));
+
+        let mut active: Vec<&PromptSection> = self
+            .sections
+            .iter()
+            .filter(|section| section.is_active(context))
+            .collect();
+        active.sort_by_key(|section| std::cmp::Reverse(section.priority));
+
+        let mut rendered = Vec::new();
+        let mut included = Vec::new();
+        let mut dropped = Vec::new();
+        let mut tokens = 0;
+        for section in active {
+            let text = render_inline_once(&section.template, &variables)
+                .with_context(|| format!("Failed to render prompt section {}", section.name))?;
+            if text.is_empty() {
+                continue;
+            }
+            // Sections are joined with a blank line
+            let separator = if rendered.is_empty() { 0 } else { 1 };
+            let section_tokens = estimate_tokens(&text, TokenizerFamily::default()) + separator;
+            if self
+                .max_tokens
+                .is_some_and(|max_tokens| tokens + section_tokens > max_tokens)
+            {
+                dropped.push(section.name.clone());
+                continue;
+            }
+            tokens += section_tokens;
+            rendered.push(text);
+            included.push(section.name.clone());
+        }
+
+        Ok(BuiltPrompt {
+            text: rendered.join("\n\n"),
+            tokens,
+            included,
+            dropped,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_builtin_sections() {
+        for name in [
+            "coding_agent",
+            "code_reviewer",
+            "data_analyst",
+            "terse",
+            "verbose_explainer",
+        ] {
+            let section = builtin_section(name).unwrap();
+            assert_eq!(section.name, name);
+            assert!(section.estimated_tokens() > 0);
+        }
+        assert!(builtin_section("pirate").is_none());
+
+        let prompt = SystemPrompt::new()
+            .with_section(coding_agent())
+            .with_variable("language", "Rust")
+            .build(&SectionContext::new("/"))
+            .unwrap();
+        assert!(prompt.text.contains("mostly written in Rust"));
+    }
+
+    #[test]
+    fn test_parse_front_matter() {
+        let source = "---\nname: rust\npriority: 60\nwhen:\n  - git_repo\n  - file_exists: Cargo.toml\n---\nUse clippy in {{ working_dir }}.\n";
+        let section = PromptSection::parse(source, "file").unwrap();
+        assert_eq!(section.name, "rust");
+        assert_eq!(section.priority, 60);
+        assert!(section.enabled);
+        assert_eq!(
+            section.when,
+            vec![
+                SectionCondition::GitRepo,
+                SectionCondition::FileExists("Cargo.toml".to_string())
+            ]
+        );
+        assert_eq!(section.template, "Use clippy in {{ working_dir }}.");
+
+        let section = PromptSection::parse("No header here", "notes").unwrap();
+        assert_eq!(section.name, "notes");
+        assert_eq!(section.priority, DEFAULT_PRIORITY);
+        assert_eq!(section.template, "No header here");
+
+        assert!(PromptSection::parse("---\nname: open\nbody", "x").is_err());
+        assert!(PromptSection::parse("---\nnmae: typo\n---\nbody", "x").is_err());
+    }
+
+    #[test]
+    fn test_conditional_sections() {
+        let repo = TempDir::new().unwrap();
+        std::fs::create_dir(repo.path().join(".git")).unwrap();
+        std::fs::create_dir(repo.path().join("src")).unwrap();
+        let plain = TempDir::new().unwrap();
+
+        let prompt = SystemPrompt::new()
+            .with_section(PromptSection::new("always", "Always."))
+            .with_section(PromptSection::new("git", "In git.").when(SectionCondition::GitRepo))
+            .with_section(
+                PromptSection::new("cargo", "Cargo.")
+                    .when(SectionCondition::FileExists("Cargo.toml".to_string())),
+            )
+            .with_section(PromptSection::new("off", "Off.").with_enabled(false));
+
+        // The repository is found from a subdirectory too
+        let built = prompt
+            .build(&SectionContext::new(repo.path().join("src")))
+            .unwrap();
+        assert_eq!(built.included, vec!["always", "git"]);
+        assert!(built.dropped.is_empty());
+
+        let built = prompt.build(&SectionContext::new(plain.path())).unwrap();
+        assert_eq!(built.included, vec!["always"]);
+
+        std::fs::write(plain.path().join("Cargo.toml"), "").unwrap();
+        let built = prompt.build(&SectionContext::new(plain.path())).unwrap();
+        assert_eq!(built.included, vec!["always", "cargo"]);
+        assert_eq!(built.text, "Always.\n\nCargo.");
+    }
+
+    #[test]
+    fn test_budget_drops_lowest_priority() {
+        let words = |count: usize| vec!["word"; count].join(" ");
+        let prompt = SystemPrompt::new()
+            .with_section(PromptSection::new("low", words(20)).with_priority(10))
+            .with_section(PromptSection::new("high", words(20)).with_priority(90))
+            .with_section(PromptSection::new("small", "Tiny.").with_priority(5));
+        let context = SectionContext::new("/");
+
+        let full = prompt.build(&context).unwrap();
+        assert_eq!(full.included, vec!["high", "low", "small"]);
+        assert!(full.dropped.is_empty());
+
+        // Only one of the large sections fits, but the small one still does after it
+        let high = SystemPrompt::new()
+            .with_section(PromptSection::new("high", words(20)))
+            .build(&context)
+            .unwrap();
+        let budgeted = prompt
+            .clone()
+            .with_max_tokens(high.tokens + 5)
+            .build(&context)
+            .unwrap();
+        assert_eq!(budgeted.included, vec!["high", "small"]);
+        assert_eq!(budgeted.dropped, vec!["low"]);
+        assert!(budgeted.tokens <= high.tokens + 5);
+
+        let none = prompt.with_max_tokens(0).build(&context).unwrap();
+        assert!(none.included.is_empty());
+        assert_eq!(none.text, "");
+    }
+
+    #[test]
+    fn test_user_sections_override_builtins() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("terse.md"),
+            "---\npriority: 99\n---\nOne line answers in {{ working_dir }}.",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("broken.md"), "---\npriority: high\n---\n").unwrap();
+        std::fs::write(dir.path().join("ignored.txt"), "Not a section").unwrap();
+
+        let sections = load_user_sections(dir.path());
+        assert_eq!(sections.len(), 1);
+
+        let built = SystemPrompt::new()
+            .with_section(coding_agent())
+            .with_section(terse())
+            .with_user_sections(dir.path())
+            .build(&SectionContext::new("/work"))
+            .unwrap();
+        assert_eq!(built.included, vec!["terse", "coding_agent"]);
+        assert!(built.text.starts_with("One line answers in /work."));
+    }
+}
@@ -1,14 +1,26 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use axum::http::HeaderMap;
 use reqwest::{Client, StatusCode};
 use serde_json::Value;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use super::base::{ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage};
+use super::base::{
+    CompletionOptions, ConfigKey, DryRunRequest, ModelInfo, Provider, ProviderMetadata,
+    ProviderUsage,
+};
+use super::diagnosis::{diagnose, SetupFailure};
 use super::errors::ProviderError;
-use super::formats::anthropic::{create_request, get_usage, response_to_message};
-use super::utils::{emit_debug_trace, get_model};
+use super::formats::alternation::enforce_alternation;
+use super::formats::anthropic::{
+    add_prefill, add_tool_choice, create_request, get_finish_reason, get_usage, prepend_prefill,
+    response_to_message, thinking_enabled,
+};
+use super::formats::tool_schema::ToolSchemaTokens;
+use super::headers::{
+    client_builder, client_headers, new_request_id, RequestHeaders, REQUEST_ID_HEADER,
+};
+use super::utils::{emit_debug_trace, get_model, is_retryable_error, log_provider_response};
+use super::validation::{validate_conversation_with_rules, ConversationRules};
 use crate::message::Message;
 use crate::model::ModelConfig;
 use mcp_core::tool::Tool;
@@ -28,6 +40,82 @@ pub const ANTHROPIC_KNOWN_MODELS: &[&str] = &[
 
 pub const ANTHROPIC_DOC_URL: &str = "https://docs.anthropic.com/en/docs/about-claude/models";
 pub const ANTHROPIC_API_VERSION: &str = "2023-06-01";
+pub const ANTHROPIC_BETA_HEADER: &str = "anthropic-beta";
+
+/// Features that Anthropic gates behind values of the `anthropic-beta` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnthropicBeta {
+    /// https://docs.anthropic.com/en/docs/build-with-claude/prompt-caching
+    PromptCaching,
+    /// https://docs.anthropic.com/en/docs/build-with-claude/extended-thinking#extended-output-capabilities-beta
+    Output128k,
+    /// https://docs.anthropic.com/en/docs/build-with-claude/tool-use/token-efficient-tool-use
+    TokenEfficientTools,
+    /// https://docs.anthropic.com/en/docs/build-with-claude/extended-thinking#interleaved-thinking
+    InterleavedThinking,
+}
+
+impl AnthropicBeta {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnthropicBeta::PromptCaching => "prompt-caching-2024-07-31",
+            AnthropicBeta::Output128k => "output-128k-2025-02-19",
+            AnthropicBeta::TokenEfficientTools => "token-efficient-tools-2025-02-19",
+            AnthropicBeta::InterleavedThinking => "interleaved-thinking-2025-05-14",
+        }
+    }
+}
+
+impl std::fmt::Display for AnthropicBeta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Combines beta features into the comma separated `anthropic-beta` header value
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnthropicBetas {
+    betas: Vec<AnthropicBeta>,
+}
+
+impl AnthropicBetas {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable a beta feature, ignoring features that are already enabled
+    pub fn with(mut self, beta: AnthropicBeta) -> Self {
+        self.insert(beta);
+        self
+    }
+
+    pub fn insert(&mut self, beta: AnthropicBeta) {
+        if !self.betas.contains(&beta) {
+            self.betas.push(beta);
+        }
+    }
+
+    /// The header value, or None when no beta features are enabled
+    pub fn header_value(&self) -> Option<String> {
+        if self.betas.is_empty() {
+            return None;
+        }
+        Some(
+            self.betas
+                .iter()
+                .map(AnthropicBeta::as_str)
+                .collect::<Vec<_>>()
+                .join(","),
+        )
+    }
+
+    /// Add the enabled beta features to the `anthropic-beta` header
+    pub fn apply(&self, headers: &mut RequestHeaders) {
+        for beta in &self.betas {
+            headers.append_to_list(ANTHROPIC_BETA_HEADER, beta.as_str());
+        }
+    }
+}
 
 #[derive(serde::Serialize)]
 pub struct AnthropicProvider {
@@ -53,9 +141,7 @@ impl AnthropicProvider {
             .get_param("ANTHROPIC_HOST")
             .unwrap_or_else(|_| "https://api.anthropic.com".to_string());
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(600))
-            .build()?;
+        let client = client_builder().timeout(Duration::from_secs(600)).build()?;
 
         Ok(Self {
             client,
@@ -65,45 +151,121 @@ impl AnthropicProvider {
         })
     }
 
-    async fn post(&self, headers: HeaderMap, payload: Value) -> Result<Value, ProviderError> {
+    fn endpoint(&self) -> Result<url::Url, ProviderError> {
         let base_url = url::Url::parse(&self.host)
             .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
-        let url = base_url.join("v1/messages").map_err(|e| {
+        base_url.join("v1/messages").map_err(|e| {
             ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
-        })?;
+        })
+    }
 
-        let response = self
-            .client
-            .post(url)
-            .headers(headers)
+    /// Build the headers and payload of a messages request
+    fn build_request(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        options: &CompletionOptions,
+    ) -> Result<(RequestHeaders, Value), ProviderError> {
+        let rules = ConversationRules::for_provider(&Self::metadata().name);
+        validate_conversation_with_rules(messages, rules)?;
+        let mut payload = create_request(&self.model, system, messages, tools)?;
+        if rules.strict_alternation {
+            if let Some(messages) = payload["messages"].as_array_mut() {
+                enforce_alternation(messages);
+            }
+        }
+        if let Some(prefill) = &options.prefill {
+            add_prefill(&mut payload, prefill);
+        }
+        add_tool_choice(&mut payload, options);
+
+        let mut headers = RequestHeaders::new()
+            .with("x-api-key", &self.api_key)
+            .with("anthropic-version", ANTHROPIC_API_VERSION);
+
+        // The request always marks cache_control breakpoints
+        let mut betas = AnthropicBetas::new().with(AnthropicBeta::PromptCaching);
+
+        if thinking_enabled(&self.model) {
+            betas.insert(AnthropicBeta::Output128k);
+        }
+
+        if self.model.model_name.starts_with("claude-3-7-sonnet-") {
+            betas.insert(AnthropicBeta::TokenEfficientTools);
+        }
+        betas.apply(&mut headers);
+
+        Ok((headers, payload))
+    }
+
+    /// Send a request and return the response along with the time until its headers arrived
+    async fn post(
+        &self,
+        headers: &RequestHeaders,
+        payload: Value,
+    ) -> Result<(Value, Duration), ProviderError> {
+        let url = self.endpoint()?;
+
+        let start = Instant::now();
+        let response = headers
+            .apply(self.client.post(url))
             .json(&payload)
             .send()
             .await?;
+        let time_to_first_byte = start.elapsed();
 
         let status = response.status();
+        let url = response.url().to_string();
+        log_provider_response(status, Some(time_to_first_byte));
         let payload: Option<Value> = response.json().await.ok();
 
+        // A wrong key, host or model is reported with how to fix it
+        if let Some(error) = diagnose(SetupFailure::Response {
+            url: &url,
+            status,
+            body: payload.as_ref().unwrap_or(&Value::Null),
+        }) {
+            return Err(error.into());
+        }
+
         // https://docs.anthropic.com/en/api/errors
         match status {
-            StatusCode::OK => payload.ok_or_else( || ProviderError::RequestFailed("Response body is not valid JSON".to_string()) ),
-            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
-                Err(ProviderError::Authentication(format!("Authentication failed. Please ensure your API keys are valid and have the required permissions. \
-                    Status: {}. Response: {:?}", status, payload)))
-            }
+            StatusCode::OK => payload
+                .map(|payload| (payload, time_to_first_byte))
+                .ok_or_else(|| {
+                    ProviderError::RequestFailed("Response body is not valid JSON".to_string())
+                }),
             StatusCode::BAD_REQUEST => {
                 let mut error_msg = "Unknown error".to_string();
                 if let Some(payload) = &payload {
                     if let Some(error) = payload.get("error") {
-                    tracing::debug!("Bad Request Error: {error:?}");
-                    error_msg = error.get("message").and_then(|m| m.as_str()).unwrap_or("Unknown error").to_string();
-                    if error_msg.to_lowercase().contains("too long") || error_msg.to_lowercase().contains("too many") {
-                        return Err(ProviderError::ContextLengthExceeded(error_msg.to_string()));
+                        tracing::debug!("Bad Request Error: {error:?}");
+                        error_msg = error
+                            .get("message")
+                            .and_then(|m| m.as_str())
+                            .unwrap_or("Unknown error")
+                            .to_string();
+                        if error_msg.to_lowercase().contains("too long")
+                            || error_msg.to_lowercase().contains("too many")
+                        {
+                            return Err(ProviderError::ContextLengthExceeded(
+                                error_msg.to_string(),
+                            ));
+                        }
                     }
-                }}
+                }
                 tracing::debug!(
-                    "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, payload)
+                    "{}",
+                    format!(
+                        "Provider request failed with status: {}. Payload: {:?}",
+                        status, payload
+                    )
                 );
-                Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", status, error_msg)))
+                Err(ProviderError::RequestFailed(format!(
+                    "Request failed with status: {}. Message: {}",
+                    status, error_msg
+                )))
             }
             StatusCode::TOO_MANY_REQUESTS => {
                 Err(ProviderError::RateLimitExceeded(format!("{:?}", payload)))
@@ -111,11 +273,22 @@ impl AnthropicProvider {
             StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
                 Err(ProviderError::ServerError(format!("{:?}", payload)))
             }
+            // Such as 529 when the API is overloaded
+            _ if is_retryable_error(status, payload.as_ref().unwrap_or(&Value::Null)) => {
+                Err(ProviderError::ServerError(format!("{:?}", payload)))
+            }
             _ => {
                 tracing::debug!(
-                    "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, payload)
+                    "{}",
+                    format!(
+                        "Provider request failed with status: {}. Payload: {:?}",
+                        status, payload
+                    )
                 );
-                Err(ProviderError::RequestFailed(format!("Request failed with status: {}", status)))
+                Err(ProviderError::RequestFailed(format!(
+                    "Request failed with status: {}",
+                    status
+                )))
             }
         }
     }
@@ -159,46 +332,79 @@ impl Provider for AnthropicProvider {
         self.model.clone()
     }
 
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_with_options(system, messages, tools, &CompletionOptions::default())
+            .await
+    }
+
     #[tracing::instrument(
-        skip(self, system, messages, tools),
+        skip(self, system, messages, tools, options),
         fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
     )]
-    async fn complete(
+    async fn complete_with_options(
         &self,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
+        options: &CompletionOptions,
     ) -> Result<(Message, ProviderUsage), ProviderError> {
-        let payload = create_request(&self.model, system, messages, tools)?;
+        let (headers, payload) = self.build_request(system, messages, tools, options)?;
+        let request_id = new_request_id();
+        let headers = headers.with(REQUEST_ID_HEADER, &request_id);
 
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert("x-api-key", self.api_key.parse().unwrap());
-        headers.insert("anthropic-version", ANTHROPIC_API_VERSION.parse().unwrap());
+        // Make request
+        let (response, time_to_first_byte) = self.post(&headers, payload.clone()).await?;
 
-        let is_thinking_enabled = std::env::var("CLAUDE_THINKING_ENABLED").is_ok();
-        if self.model.model_name.starts_with("claude-3-7-sonnet-") && is_thinking_enabled {
-            // https://docs.anthropic.com/en/docs/build-with-claude/extended-thinking#extended-output-capabilities-beta
-            headers.insert("anthropic-beta", "output-128k-2025-02-19".parse().unwrap());
+        // Parse response, the model only returns what it wrote after the prefill. A final
+        // assistant message in `messages` is already part of the conversation, so only the
+        // prefill of the options is added back.
+        let mut message = response_to_message(response.clone())?;
+        if let Some(prefill) = &options.prefill {
+            message = prepend_prefill(message, prefill);
         }
+        let usage = get_usage(&response)?;
 
-        if self.model.model_name.starts_with("claude-3-7-sonnet-") {
-            // https://docs.anthropic.com/en/docs/build-with-claude/tool-use/token-efficient-tool-use
-            headers.insert(
-                "anthropic-beta",
-                "token-efficient-tools-2025-02-19".parse().unwrap(),
-            );
-        }
+        let model = get_model(&response);
+        emit_debug_trace(&self.model, &request_id, &payload, &response, &usage);
+        Ok((
+            message,
+            ProviderUsage::new(model, usage)
+                .with_time_to_first_byte(time_to_first_byte)
+                .with_finish_reason(get_finish_reason(&response)),
+        ))
+    }
 
-        // Make request
-        let response = self.post(headers, payload.clone()).await?;
+    /// Anthropic rejects an assistant message at the end of the conversation when extended
+    /// thinking is on
+    fn supports_prefill(&self) -> bool {
+        !thinking_enabled(&self.model)
+    }
 
-        // Parse response
-        let message = response_to_message(response.clone())?;
-        let usage = get_usage(&response)?;
+    fn supports_system_blocks(&self) -> bool {
+        true
+    }
 
-        let model = get_model(&response);
-        emit_debug_trace(&self.model, &payload, &response, &usage);
-        Ok((message, ProviderUsage::new(model, usage)))
+    async fn dry_run(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<DryRunRequest, ProviderError> {
+        let (headers, payload) =
+            self.build_request(system, messages, tools, &CompletionOptions::default())?;
+        Ok(DryRunRequest {
+            url: self.endpoint()?.to_string(),
+            headers: client_headers().with_all(headers).redacted(),
+            payload,
+            tool_schema_tokens: Some(ToolSchemaTokens::for_request(&self.model, messages, tools)),
+            system_blocks: None,
+            payload_size: None,
+        })
     }
 
     /// Fetch supported models from Anthropic; returns Err on failure, Ok(None) if not present
@@ -233,3 +439,249 @@ impl Provider for AnthropicProvider {
         Ok(Some(models))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::{Event, Subscriber};
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::Layer;
+    use wiremock::matchers::{header, header_regex, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_single_beta_header() {
+        let betas = AnthropicBetas::new().with(AnthropicBeta::PromptCaching);
+        assert_eq!(
+            betas.header_value().as_deref(),
+            Some("prompt-caching-2024-07-31")
+        );
+        assert_eq!(AnthropicBetas::new().header_value(), None);
+    }
+
+    #[test]
+    fn test_combined_beta_headers() {
+        let betas = AnthropicBetas::new()
+            .with(AnthropicBeta::PromptCaching)
+            .with(AnthropicBeta::Output128k)
+            .with(AnthropicBeta::PromptCaching);
+        assert_eq!(
+            betas.header_value().as_deref(),
+            Some("prompt-caching-2024-07-31,output-128k-2025-02-19")
+        );
+
+        let mut headers = RequestHeaders::new();
+        headers.append_to_list(ANTHROPIC_BETA_HEADER, "output-128k-2025-02-19");
+        betas.apply(&mut headers);
+        assert_eq!(
+            headers.get(ANTHROPIC_BETA_HEADER),
+            Some("output-128k-2025-02-19,prompt-caching-2024-07-31")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_complete_sends_beta_headers() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(header("x-api-key", "test-key"))
+            .and(header("anthropic-version", ANTHROPIC_API_VERSION))
+            .and(header_regex(
+                ANTHROPIC_BETA_HEADER,
+                "^prompt-caching-2024-07-31,.*token-efficient-tools-2025-02-19$",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "msg_1",
+                "type": "message",
+                "role": "assistant",
+                "model": "claude-3-7-sonnet-20250219",
+                "content": [{"type": "text", "text": "Hello"}],
+                "usage": {"input_tokens": 10, "output_tokens": 2}
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = AnthropicProvider {
+            client: Client::new(),
+            host: server.uri(),
+            api_key: "test-key".to_string(),
+            model: ModelConfig::new("claude-3-7-sonnet-20250219".to_string()),
+        };
+        let (message, _) = provider
+            .complete("system", &[Message::user().with_text("Hi")], &[])
+            .await
+            .unwrap();
+        assert_eq!(message.as_concat_text(), "Hello");
+    }
+
+    #[tokio::test]
+    async fn test_complete_with_prefill() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "msg_1",
+                "type": "message",
+                "role": "assistant",
+                "model": "claude-3-5-sonnet-20241022",
+                "content": [{"type": "text", "text": "\"answer\": 42}"}],
+                "usage": {"input_tokens": 10, "output_tokens": 5}
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = AnthropicProvider {
+            client: Client::new(),
+            host: server.uri(),
+            api_key: "test-key".to_string(),
+            model: ModelConfig::new("claude-3-5-sonnet-latest".to_string()),
+        };
+        assert!(provider.supports_prefill());
+        let (message, _) = provider
+            .complete_with_options(
+                "system",
+                &[Message::user().with_text("Answer in JSON")],
+                &[],
+                &CompletionOptions::default().with_prefill("{"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(message.as_concat_text(), "{\"answer\": 42}");
+
+        let requests = server.received_requests().await.unwrap();
+        let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+        let last = body["messages"].as_array().unwrap().last().unwrap();
+        assert_eq!(last["role"], "assistant");
+        assert_eq!(last["content"][0]["text"], "{");
+    }
+
+    #[test]
+    fn test_no_prefill_with_thinking() {
+        let provider = |model: &str| AnthropicProvider {
+            client: Client::new(),
+            host: "http://localhost".to_string(),
+            api_key: "test-key".to_string(),
+            model: ModelConfig::new(model.to_string()),
+        };
+        let original_value = std::env::var("CLAUDE_THINKING_ENABLED").ok();
+        std::env::set_var("CLAUDE_THINKING_ENABLED", "true");
+
+        let thinking = provider("claude-3-7-sonnet-20250219").supports_prefill();
+        let without_thinking = provider("claude-3-5-sonnet-latest").supports_prefill();
+
+        match original_value {
+            Some(val) => std::env::set_var("CLAUDE_THINKING_ENABLED", val),
+            None => std::env::remove_var("CLAUDE_THINKING_ENABLED"),
+        }
+        assert!(!thinking);
+        assert!(without_thinking);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_does_not_send() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let model = ModelConfig::new("claude-3-5-sonnet-latest".to_string());
+        let provider = AnthropicProvider {
+            client: Client::new(),
+            host: server.uri(),
+            api_key: "test-key".to_string(),
+            model: model.clone(),
+        };
+        let messages = [Message::user().with_text("Hi")];
+        let request = provider.dry_run("system", &messages, &[]).await.unwrap();
+
+        assert_eq!(request.url, format!("{}/v1/messages", server.uri()));
+        assert_eq!(
+            request.payload,
+            create_request(&model, "system", &messages, &[]).unwrap()
+        );
+        let headers: HashMap<_, _> = request.headers.iter().collect();
+        assert_eq!(headers["x-api-key"], "[redacted]");
+        assert_eq!(headers["anthropic-version"], ANTHROPIC_API_VERSION);
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+
+    /// Records the fields of every event, formatted with Debug
+    #[derive(Clone, Default)]
+    struct CapturedEvents(Arc<Mutex<Vec<HashMap<String, String>>>>);
+
+    struct FieldMap<'a>(&'a mut HashMap<String, String>);
+
+    impl Visit for FieldMap<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for CapturedEvents {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            let mut fields = HashMap::new();
+            event.record(&mut FieldMap(&mut fields));
+            self.0.lock().unwrap().push(fields);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_complete_emits_usage_event() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "msg_1",
+                "type": "message",
+                "role": "assistant",
+                "model": "claude-3-5-sonnet-20241022",
+                "content": [{"type": "text", "text": "A duck"}],
+                "usage": {"input_tokens": 1500, "output_tokens": 3}
+            })))
+            .mount(&server)
+            .await;
+
+        let provider = AnthropicProvider {
+            client: Client::new(),
+            host: server.uri(),
+            api_key: "test-key".to_string(),
+            model: ModelConfig::new("claude-3-5-sonnet-latest".to_string()),
+        };
+        let messages = [Message::user().with_text("What is this?")];
+
+        let captured = CapturedEvents::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(captured.clone()));
+        provider.complete("system", &messages, &[]).await.unwrap();
+
+        let events = captured.0.lock().unwrap();
+        let response = events
+            .iter()
+            .find(|fields| {
+                fields
+                    .get("message")
+                    .is_some_and(|m| m == "provider response")
+            })
+            .expect("no response event");
+        assert_eq!(response["status"], "200");
+        assert!(response.contains_key("latency_ms"));
+
+        let usage = events
+            .iter()
+            .find(|fields| fields.get("message").is_some_and(|m| m == "provider usage"))
+            .expect("no usage event");
+        assert_eq!(usage["model"], "claude-3-5-sonnet-latest");
+        assert_eq!(usage["message_count"], "1");
+        assert_eq!(usage["input_tokens"], "1500");
+        assert_eq!(usage["output_tokens"], "3");
+    }
+}
@@ -9,7 +9,10 @@ use tokio::time::sleep;
 use super::azureauth::AzureAuth;
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
-use super::formats::openai::{create_request, get_usage, response_to_message};
+use super::formats::openai::{
+    create_request_for_target, get_usage, response_to_message, RequestTarget,
+};
+use super::headers::{client_builder, new_request_id, REQUEST_ID_HEADER};
 use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat, ImageFormat};
 use crate::message::Message;
 use crate::model::ModelConfig;
@@ -20,6 +23,10 @@ pub const AZURE_DOC_URL: &str =
     "https://learn.microsoft.com/en-us/azure/ai-services/openai/concepts/models";
 pub const AZURE_DEFAULT_API_VERSION: &str = "2024-10-21";
 pub const AZURE_OPENAI_KNOWN_MODELS: &[&str] = &["gpt-4o", "gpt-4o-mini", "gpt-4"];
+/// Header that carries the API key, Azure doesn't use a bearer token for keys
+pub const AZURE_API_KEY_HEADER: &str = "api-key";
+/// Query parameter that selects the API version
+pub const AZURE_API_VERSION_PARAM: &str = "api-version";
 
 // Default retry configuration
 const DEFAULT_MAX_RETRIES: usize = 5;
@@ -71,9 +78,7 @@ impl AzureProvider {
         let api_key = config.get_secret("AZURE_OPENAI_API_KEY").ok();
         let auth = AzureAuth::new(api_key)?;
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(600))
-            .build()?;
+        let client = client_builder().timeout(Duration::from_secs(600)).build()?;
 
         Ok(Self {
             client,
@@ -85,7 +90,7 @@ impl AzureProvider {
         })
     }
 
-    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+    async fn post(&self, request_id: &str, payload: Value) -> Result<Value, ProviderError> {
         let mut base_url = url::Url::parse(&self.endpoint)
             .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
 
@@ -104,7 +109,10 @@ impl AzureProvider {
         };
 
         base_url.set_path(&new_path);
-        base_url.set_query(Some(&format!("api-version={}", self.api_version)));
+        base_url.set_query(Some(&format!(
+            "{}={}",
+            AZURE_API_VERSION_PARAM, self.api_version
+        )));
 
         let mut attempts = 0;
         let mut last_error = None;
@@ -127,13 +135,17 @@ impl AzureProvider {
                 ProviderError::RequestFailed(format!("Failed to get authentication token: {}", e))
             })?;
 
-            let mut request_builder = self.client.post(base_url.clone());
+            let mut request_builder = self
+                .client
+                .post(base_url.clone())
+                .header(REQUEST_ID_HEADER, request_id);
             let token_value = auth_token.token_value.clone();
 
             // Set the correct header based on authentication type
             match self.auth.credential_type() {
                 super::azureauth::AzureCredentials::ApiKey(_) => {
-                    request_builder = request_builder.header("api-key", token_value.clone());
+                    request_builder =
+                        request_builder.header(AZURE_API_KEY_HEADER, token_value.clone());
                 }
                 super::azureauth::AzureCredentials::DefaultCredential => {
                     request_builder = request_builder
@@ -245,8 +257,16 @@ impl Provider for AzureProvider {
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
-        let payload = create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
-        let response = self.post(payload.clone()).await?;
+        let payload = create_request_for_target(
+            &self.model,
+            system,
+            messages,
+            tools,
+            &ImageFormat::OpenAi,
+            RequestTarget::AzureDeployment,
+        )?;
+        let request_id = new_request_id();
+        let response = self.post(&request_id, payload.clone()).await?;
 
         let message = response_to_message(response.clone())?;
         let usage = match get_usage(&response) {
@@ -258,7 +278,7 @@ impl Provider for AzureProvider {
             Err(e) => return Err(e),
         };
         let model = get_model(&response);
-        emit_debug_trace(&self.model, &payload, &response, &usage);
+        emit_debug_trace(&self.model, &request_id, &payload, &response, &usage);
         Ok((message, ProviderUsage::new(model, usage)))
     }
 }
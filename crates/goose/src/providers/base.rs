@@ -2,13 +2,22 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 use super::errors::ProviderError;
+use super::formats::payload_limits::{count_images, request_bytes};
+use super::formats::tool_schema::ToolSchemaTokens;
+use super::headers::RequestHeaders;
 use crate::message::Message;
 use crate::model::ModelConfig;
+use crate::prompt_template::{SystemBlockKind, SystemPrompt};
 use mcp_core::tool::Tool;
+use serde_json::Value;
 use utoipa::ToSchema;
 
 use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
 use std::sync::Mutex;
+use std::time::Duration;
 
 /// A global store for the current model being used, we use this as when a provider returns, it tells us the real model, not an alias
 pub static CURRENT_MODEL: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
@@ -176,15 +185,148 @@ impl ConfigKey {
 pub struct ProviderUsage {
     pub model: String,
     pub usage: Usage,
+    /// Time until the response headers arrived, for providers that measure it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_to_first_byte_ms: Option<u64>,
+    /// Why the model stopped, normalized from the reason the provider reports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<FinishReason>,
+    /// The follow-up requests made to finish a response that hit the output limit
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub continuations: u32,
+    /// The log probabilities of the generated tokens, when they were asked for and the
+    /// provider supports them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<Logprobs>,
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
 }
 
 impl ProviderUsage {
     pub fn new(model: String, usage: Usage) -> Self {
-        Self { model, usage }
+        Self {
+            model,
+            usage,
+            time_to_first_byte_ms: None,
+            finish_reason: None,
+            continuations: 0,
+            logprobs: None,
+        }
+    }
+
+    pub fn with_time_to_first_byte(mut self, time_to_first_byte: Duration) -> Self {
+        self.time_to_first_byte_ms = time_to_first_byte.as_millis().try_into().ok();
+        self
+    }
+
+    pub fn with_finish_reason(mut self, finish_reason: Option<FinishReason>) -> Self {
+        self.finish_reason = finish_reason;
+        self
+    }
+
+    pub fn with_logprobs(mut self, logprobs: Option<Logprobs>) -> Self {
+        self.logprobs = logprobs;
+        self
+    }
+
+    /// Whether the response was cut off by the output token limit
+    pub fn is_truncated(&self) -> bool {
+        matches!(self.finish_reason, Some(FinishReason::Length))
+    }
+}
+
+/// Why the model stopped generating, the same for every provider
+///
+/// Each provider names its reasons differently, so the response parsers in
+/// [`super::formats`] map them into this. It is stored with the names OpenAI uses.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum FinishReason {
+    /// The model finished on its own or hit a stop sequence
+    Stop,
+    /// The output token limit cut the response off
+    Length,
+    /// The model stopped to call tools
+    ToolCalls,
+    /// The provider withheld or cut off the response for safety
+    ContentFilter,
+    /// A reason that has no equivalent here, as the provider reports it
+    Other(String),
+}
+
+impl FinishReason {
+    pub fn as_str(&self) -> &str {
+        match self {
+            FinishReason::Stop => "stop",
+            FinishReason::Length => "length",
+            FinishReason::ToolCalls => "tool_calls",
+            FinishReason::ContentFilter => "content_filter",
+            FinishReason::Other(reason) => reason,
+        }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+impl From<String> for FinishReason {
+    fn from(reason: String) -> Self {
+        match reason.as_str() {
+            "stop" => FinishReason::Stop,
+            "length" => FinishReason::Length,
+            "tool_calls" => FinishReason::ToolCalls,
+            "content_filter" => FinishReason::ContentFilter,
+            _ => FinishReason::Other(reason),
+        }
+    }
+}
+
+impl From<FinishReason> for String {
+    fn from(reason: FinishReason) -> Self {
+        reason.as_str().to_string()
+    }
+}
+
+impl std::fmt::Display for FinishReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The log probabilities of the tokens of a completion, for evaluation
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Logprobs {
+    pub tokens: Vec<TokenLogprob>,
+}
+
+impl Logprobs {
+    /// The perplexity of the generated tokens, the exponent of their mean negative log
+    /// probability. None when there are no tokens.
+    pub fn perplexity(&self) -> Option<f64> {
+        if self.tokens.is_empty() {
+            return None;
+        }
+        let total: f64 = self.tokens.iter().map(|token| token.logprob).sum();
+        Some((-total / self.tokens.len() as f64).exp())
+    }
+}
+
+/// A generated token with its log probability and the likeliest tokens at its position
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenLogprob {
+    pub token: String,
+    pub logprob: f64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub top_logprobs: Vec<TopLogprob>,
+}
+
+/// An alternative token at a position of the completion
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopLogprob {
+    pub token: String,
+    pub logprob: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
 pub struct Usage {
     pub input_tokens: Option<i32>,
     pub output_tokens: Option<i32>,
@@ -205,6 +347,232 @@ impl Usage {
     }
 }
 
+/// Counts a provider did not report don't count as zero, so they are only missing from a sum
+/// when no part of it reported them
+fn add_tokens(a: Option<i32>, b: Option<i32>) -> Option<i32> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.saturating_add(b)),
+        _ => a.or(b),
+    }
+}
+
+impl Add for Usage {
+    type Output = Usage;
+
+    fn add(self, other: Usage) -> Usage {
+        Usage::new(
+            add_tokens(self.input_tokens, other.input_tokens),
+            add_tokens(self.output_tokens, other.output_tokens),
+            add_tokens(self.total_tokens, other.total_tokens),
+        )
+    }
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, other: Usage) {
+        *self = self.clone() + other;
+    }
+}
+
+impl Sum for Usage {
+    fn sum<I: Iterator<Item = Usage>>(iter: I) -> Usage {
+        iter.fold(Usage::default(), Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Usage> for Usage {
+    fn sum<I: Iterator<Item = &'a Usage>>(iter: I) -> Usage {
+        iter.cloned().sum()
+    }
+}
+
+/// Usage summed per model, so sessions that switch models can report a breakdown
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UsageTotals {
+    by_model: BTreeMap<String, Usage>,
+}
+
+impl UsageTotals {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the usage of a provider call to the model that served it
+    pub fn record(&mut self, usage: &ProviderUsage) {
+        self.add(&usage.model, usage.usage.clone());
+    }
+
+    pub fn add(&mut self, model: &str, usage: Usage) {
+        *self.by_model.entry(model.to_string()).or_default() += usage;
+    }
+
+    /// Add all usage of other totals, such as those of a subagent
+    pub fn merge(&mut self, other: &UsageTotals) {
+        for (model, usage) in &other.by_model {
+            self.add(model, usage.clone());
+        }
+    }
+
+    pub fn get(&self, model: &str) -> Option<&Usage> {
+        self.by_model.get(model)
+    }
+
+    /// The usage of each model, ordered by model name
+    pub fn by_model(&self) -> &BTreeMap<String, Usage> {
+        &self.by_model
+    }
+
+    /// The usage of all models together
+    pub fn total(&self) -> Usage {
+        self.by_model.values().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_model.is_empty()
+    }
+}
+
+/// Options for a single completion
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompletionOptions {
+    /// The start of the assistant response, which the model continues from. Use it to force
+    /// a format, such as "{" for JSON. The returned message includes the prefill.
+    pub prefill: Option<String>,
+    /// Whether to return the log probability of each generated token in the usage. Providers
+    /// that don't support it return none.
+    pub logprobs: bool,
+    /// How many of the likeliest alternatives to return for each token, with `logprobs`
+    pub top_logprobs: u8,
+    /// Whether to forbid tool calls in the response, as tool_choice "none". The tools are
+    /// still sent, so the tool calls in the history stay valid. Providers that can't forbid
+    /// them may still return some.
+    pub no_tool_calls: bool,
+}
+
+impl CompletionOptions {
+    pub fn with_prefill(mut self, prefill: impl Into<String>) -> Self {
+        self.prefill = Some(prefill.into());
+        self
+    }
+
+    pub fn with_logprobs(mut self, top_logprobs: u8) -> Self {
+        self.logprobs = true;
+        self.top_logprobs = top_logprobs;
+        self
+    }
+
+    pub fn with_no_tool_calls(mut self) -> Self {
+        self.no_tool_calls = true;
+        self
+    }
+}
+
+/// The arguments of a tool call known while it is still streaming in
+///
+/// Only for showing progress, such as the file a call is about to edit. The call itself runs
+/// with the arguments of the finished response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCallProgress {
+    pub id: String,
+    pub name: String,
+    /// The top level arguments whose values have fully arrived
+    pub arguments: Value,
+}
+
+pub type ToolCallProgressSender = tokio::sync::mpsc::UnboundedSender<ToolCallProgress>;
+
+/// The HTTP request a completion would send, built without sending it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DryRunRequest {
+    pub url: String,
+    /// Request headers, with credentials redacted
+    #[serde(serialize_with = "serialize_headers")]
+    pub headers: RequestHeaders,
+    pub payload: Value,
+    /// Estimated prompt tokens of the tool schemas, in full and as sent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_schema_tokens: Option<ToolSchemaTokens>,
+    /// The blocks of the system prompt, in order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_blocks: Option<Vec<SystemBlockInfo>>,
+    /// The images and size of the request, against the limits of the provider
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload_size: Option<PayloadSize>,
+}
+
+/// What a request carries, as a dry run shows it, see `ModelConfig::payload_limits`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PayloadSize {
+    /// Images sent, after older ones beyond `max_images` were removed
+    pub images: usize,
+    pub images_removed: usize,
+    pub bytes: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_images: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_request_bytes: Option<usize>,
+}
+
+impl PayloadSize {
+    pub fn for_request(model_config: &ModelConfig, messages: &[Message], payload: &Value) -> Self {
+        let limits = model_config.payload_limits();
+        let images = count_images(messages);
+        let sent = limits.max_images.map_or(images, |max| images.min(max));
+        PayloadSize {
+            images: sent,
+            images_removed: images - sent,
+            bytes: request_bytes(payload),
+            max_images: limits.max_images,
+            max_request_bytes: limits.max_request_bytes,
+        }
+    }
+}
+
+/// A block of the system prompt, as a dry run shows it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SystemBlockInfo {
+    pub kind: SystemBlockKind,
+    pub chars: usize,
+    /// Whether the provider marks the block for its prompt cache
+    pub cached: bool,
+}
+
+impl SystemBlockInfo {
+    /// The blocks of a prompt. With `cache`, the stable blocks before the first dynamic one
+    /// are cached, as that is the prefix that stays the same.
+    pub fn for_prompt(prompt: &SystemPrompt, cache: bool) -> Vec<Self> {
+        let mut cached = cache;
+        prompt
+            .blocks()
+            .iter()
+            .map(|block| {
+                cached &= block.kind == SystemBlockKind::Stable;
+                SystemBlockInfo {
+                    kind: block.kind,
+                    chars: block.text.chars().count(),
+                    cached,
+                }
+            })
+            .collect()
+    }
+}
+
+fn serialize_headers<S: serde::Serializer>(
+    headers: &RequestHeaders,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(headers.iter())
+}
+
+/// The system prompt instruction for providers that can't prefill the response
+pub fn prefill_instruction(prefill: &str) -> String {
+    format!(
+        "Begin your response with exactly the following text, and continue from it:\n{}",
+        prefill
+    )
+}
+
 use async_trait::async_trait;
 
 /// Trait for LeadWorkerProvider-specific functionality
@@ -244,6 +612,62 @@ pub trait Provider: Send + Sync {
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError>;
 
+    /// Generate the next message, reporting the arguments of tool calls as they stream in
+    ///
+    /// Providers that return whole responses report nothing and behave like `complete`.
+    async fn complete_with_progress(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        _progress: ToolCallProgressSender,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete(system, messages, tools).await
+    }
+
+    /// Generate the next message with per-call options
+    ///
+    /// Providers that can't prefill the assistant response ask for it in the system prompt
+    /// instead, so the model is not guaranteed to follow it.
+    async fn complete_with_options(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        options: &CompletionOptions,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        match &options.prefill {
+            Some(prefill) => {
+                let system = format!("{}\n\n{}", system, prefill_instruction(prefill));
+                self.complete(&system, messages, tools).await
+            }
+            None => self.complete(system, messages, tools).await,
+        }
+    }
+
+    /// Build the request `complete` would send for these arguments, without sending it
+    async fn dry_run(
+        &self,
+        _system: &str,
+        _messages: &[Message],
+        _tools: &[Tool],
+    ) -> Result<DryRunRequest, ProviderError> {
+        Err(ProviderError::ExecutionError(
+            "This provider does not support dry runs".to_string(),
+        ))
+    }
+
+    /// Whether `complete_with_options` prefills the assistant response natively
+    fn supports_prefill(&self) -> bool {
+        false
+    }
+
+    /// Whether the provider takes the system prompt in `SystemPrompt::encode`d form, to send
+    /// its blocks separately. Other providers get the blocks joined into one string.
+    fn supports_system_blocks(&self) -> bool {
+        false
+    }
+
     /// Get the model config from the provider
     fn get_model_config(&self) -> ModelConfig;
 
@@ -316,6 +740,122 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_finish_reason_serialization() -> Result<()> {
+        let usage = ProviderUsage::new("model".to_string(), Usage::default())
+            .with_finish_reason(Some(FinishReason::Length));
+        let value = serde_json::to_value(&usage)?;
+        assert_eq!(value["finish_reason"], json!("length"));
+        let deserialized: ProviderUsage = serde_json::from_value(value)?;
+        assert!(deserialized.is_truncated());
+
+        let other: FinishReason = serde_json::from_value(json!("pause_turn"))?;
+        assert_eq!(other, FinishReason::Other("pause_turn".to_string()));
+        assert_eq!(serde_json::to_value(&other)?, json!("pause_turn"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_usage_add_option_semantics() {
+        let counts = [None, Some(0), Some(1), Some(7), Some(i32::MAX)];
+        let usages: Vec<Usage> = counts
+            .iter()
+            .flat_map(|&input| counts.iter().map(move |&output| (input, output)))
+            .flat_map(|(input, output)| {
+                counts
+                    .iter()
+                    .map(move |&total| Usage::new(input, output, total))
+            })
+            .collect();
+
+        for a in &usages {
+            // Default is the identity
+            assert_eq!(a.clone() + Usage::default(), *a);
+            assert_eq!(Usage::default() + a.clone(), *a);
+            for b in &usages {
+                let sum = a.clone() + b.clone();
+                assert_eq!(sum, b.clone() + a.clone());
+
+                // A count is only missing when both sides are missing it
+                assert_eq!(
+                    sum.input_tokens.is_none(),
+                    a.input_tokens.is_none() && b.input_tokens.is_none()
+                );
+                if b.output_tokens.is_none() {
+                    assert_eq!(sum.output_tokens, a.output_tokens);
+                }
+                if let (Some(x), Some(y)) = (a.total_tokens, b.total_tokens) {
+                    assert_eq!(sum.total_tokens, Some(x.saturating_add(y)));
+                }
+
+                let mut assigned = a.clone();
+                assigned += b.clone();
+                assert_eq!(assigned, sum);
+            }
+        }
+
+        let a = Usage::new(Some(1), None, Some(3));
+        let b = Usage::new(None, Some(2), None);
+        let c = Usage::new(Some(4), Some(5), None);
+        assert_eq!(
+            (a.clone() + b.clone()) + c.clone(),
+            a.clone() + (b.clone() + c.clone())
+        );
+        let total: Usage = [a.clone(), b.clone(), c.clone()].iter().sum();
+        assert_eq!(total, Usage::new(Some(5), Some(7), Some(3)));
+        assert_eq!(vec![a, b, c].into_iter().sum::<Usage>(), total);
+        assert_eq!(
+            Vec::<Usage>::new().into_iter().sum::<Usage>(),
+            Usage::default()
+        );
+    }
+
+    #[test]
+    fn test_usage_totals_across_models() -> Result<()> {
+        let mut totals = UsageTotals::new();
+        totals.record(&ProviderUsage::new(
+            "gpt-4o".to_string(),
+            Usage::new(Some(100), Some(20), Some(120)),
+        ));
+        totals.record(&ProviderUsage::new(
+            "claude-3-5-sonnet".to_string(),
+            Usage::new(Some(50), Some(10), None),
+        ));
+        totals.record(&ProviderUsage::new(
+            "gpt-4o".to_string(),
+            Usage::new(Some(200), Some(30), Some(230)),
+        ));
+
+        let mut subagent = UsageTotals::new();
+        subagent.add("gemini-2.0-flash", Usage::new(Some(10), Some(5), Some(15)));
+        subagent.add("claude-3-5-sonnet", Usage::new(Some(5), None, Some(5)));
+        totals.merge(&subagent);
+
+        assert_eq!(
+            totals.get("gpt-4o"),
+            Some(&Usage::new(Some(300), Some(50), Some(350)))
+        );
+        assert_eq!(
+            totals.get("claude-3-5-sonnet"),
+            Some(&Usage::new(Some(55), Some(10), Some(5)))
+        );
+        assert_eq!(
+            totals.get("gemini-2.0-flash"),
+            Some(&Usage::new(Some(10), Some(5), Some(15)))
+        );
+        assert_eq!(
+            totals.by_model().keys().collect::<Vec<_>>(),
+            vec!["claude-3-5-sonnet", "gemini-2.0-flash", "gpt-4o"]
+        );
+        assert_eq!(totals.total(), Usage::new(Some(365), Some(65), Some(370)));
+
+        let serialized = serde_json::to_value(&totals)?;
+        assert_eq!(serialized["gpt-4o"]["input_tokens"], json!(300));
+        let deserialized: UsageTotals = serde_json::from_value(serialized)?;
+        assert_eq!(deserialized, totals);
+        Ok(())
+    }
+
     #[test]
     fn test_set_and_get_current_model() {
         // Set the model
@@ -408,4 +948,25 @@ mod tests {
         assert_eq!(info.output_token_cost, Some(0.00001));
         assert_eq!(info.currency, Some("$".to_string()));
     }
+
+    #[test]
+    fn test_system_block_info() {
+        let mut prompt = SystemPrompt::new();
+        prompt.push_stable("You are goose.");
+        prompt.push_dynamic("On branch main");
+        prompt.push_stable("Be brief.");
+
+        let blocks = SystemBlockInfo::for_prompt(&prompt, true);
+        assert_eq!(
+            serde_json::to_value(&blocks).unwrap(),
+            json!([
+                {"kind": "stable", "chars": 14, "cached": true},
+                {"kind": "dynamic", "chars": 14, "cached": false},
+                {"kind": "stable", "chars": 9, "cached": false},
+            ])
+        );
+        assert!(SystemBlockInfo::for_prompt(&prompt, false)
+            .iter()
+            .all(|block| !block.cached));
+    }
 }
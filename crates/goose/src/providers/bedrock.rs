@@ -12,13 +12,17 @@ use tokio::time::sleep;
 
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
 use super::errors::ProviderError;
+use super::headers::new_request_id;
 use crate::message::Message;
 use crate::model::ModelConfig;
+use crate::providers::formats::alternation::alternate_messages;
 use crate::providers::utils::emit_debug_trace;
+use crate::providers::validation::validate_conversation;
 
 // Import the migrated helper functions from providers/formats/bedrock.rs
 use super::formats::bedrock::{
-    from_bedrock_message, from_bedrock_usage, to_bedrock_message, to_bedrock_tool_config,
+    from_bedrock_message, from_bedrock_stop_reason, from_bedrock_usage, to_bedrock_message,
+    to_bedrock_tool_config,
 };
 
 pub const BEDROCK_DOC_LINK: &str =
@@ -105,6 +109,9 @@ impl Provider for BedrockProvider {
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
+        validate_conversation(messages, &Self::metadata().name)?;
+        // Converse rejects repeated roles, such as an interjection after tool results
+        let alternated = alternate_messages(messages);
         let model_name = &self.model.model_name;
 
         let mut request = self
@@ -113,7 +120,7 @@ impl Provider for BedrockProvider {
             .system(bedrock::SystemContentBlock::Text(system.to_string()))
             .model_id(model_name.to_string())
             .set_messages(Some(
-                messages
+                alternated
                     .iter()
                     .map(to_bedrock_message)
                     .collect::<Result<_>>()?,
@@ -155,12 +162,16 @@ impl Provider for BedrockProvider {
                             });
                             emit_debug_trace(
                                 &self.model,
+                                &new_request_id(),
                                 &debug_payload,
                                 &serde_json::to_value(&message).unwrap_or_default(),
                                 &usage,
                             );
 
-                            let provider_usage = ProviderUsage::new(model_name.to_string(), usage);
+                            let provider_usage = ProviderUsage::new(model_name.to_string(), usage)
+                                .with_finish_reason(Some(from_bedrock_stop_reason(
+                                    &response.stop_reason,
+                                )));
                             Ok((message, provider_usage))
                         }
                         _ => Err(ProviderError::RequestFailed(
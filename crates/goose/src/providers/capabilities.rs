@@ -0,0 +1,290 @@
+use mcp_core::tool::Tool;
+use mcp_core::Content;
+use thiserror::Error;
+
+use super::errors::ProviderError;
+use super::utils::ToolImagePolicy;
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+
+/// What a model accepts, for checking requests before they are sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelCapabilities {
+    pub max_output_tokens: i32,
+    pub vision: bool,
+    pub tools: bool,
+    /// Whether the model uses the temperature, reasoning models ignore it
+    pub temperature: bool,
+}
+
+const fn capabilities(
+    max_output_tokens: i32,
+    vision: bool,
+    tools: bool,
+    temperature: bool,
+) -> ModelCapabilities {
+    ModelCapabilities {
+        max_output_tokens,
+        vision,
+        tools,
+        temperature,
+    }
+}
+
+// Known models, matched as prefixes of the model name, where the longest match wins
+// https://platform.openai.com/docs/models
+// https://docs.anthropic.com/en/docs/about-claude/models/all-models#model-comparison-table
+const MODEL_CAPABILITIES: &[(&str, ModelCapabilities)] = &[
+    ("gpt-3.5-turbo", capabilities(4_096, false, true, true)),
+    ("gpt-4-turbo", capabilities(4_096, true, true, true)),
+    ("gpt-4o", capabilities(16_384, true, true, true)),
+    ("gpt-4.1", capabilities(32_768, true, true, true)),
+    ("gpt-4-1", capabilities(32_768, true, true, true)),
+    ("o1", capabilities(100_000, true, true, false)),
+    ("o1-mini", capabilities(65_536, false, false, false)),
+    ("o3", capabilities(100_000, true, true, false)),
+    ("o3-mini", capabilities(100_000, false, true, false)),
+    ("o4-mini", capabilities(100_000, true, true, false)),
+    ("claude-3-haiku", capabilities(4_096, true, true, true)),
+    ("claude-3-5-haiku", capabilities(8_192, true, true, true)),
+    ("claude-3-5-sonnet", capabilities(8_192, true, true, true)),
+    ("claude-3-7-sonnet", capabilities(64_000, true, true, true)),
+    ("claude-sonnet-4", capabilities(64_000, true, true, true)),
+    ("claude-opus-4", capabilities(32_000, true, true, true)),
+];
+
+/// How much one request to the provider of a model may carry, beyond its context
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PayloadLimits {
+    pub max_images: Option<usize>,
+    pub max_request_bytes: Option<usize>,
+}
+
+const MB: usize = 1024 * 1024;
+
+// https://platform.openai.com/docs/guides/images-vision#image-input-requirements
+const OPENAI: PayloadLimits = PayloadLimits {
+    max_images: Some(500),
+    max_request_bytes: Some(50 * MB),
+};
+
+// https://docs.anthropic.com/en/docs/build-with-claude/vision
+// https://docs.anthropic.com/en/api/overview#request-size-limits
+const ANTHROPIC: PayloadLimits = PayloadLimits {
+    max_images: Some(100),
+    max_request_bytes: Some(32 * MB),
+};
+
+// The limits of provider APIs, matched as prefixes of the model name
+const MODEL_PAYLOAD_LIMITS: &[(&str, PayloadLimits)] = &[
+    ("gpt-", OPENAI),
+    ("o1", OPENAI),
+    ("o3", OPENAI),
+    ("o4", OPENAI),
+    ("claude", ANTHROPIC),
+];
+
+impl ModelCapabilities {
+    /// The capabilities of a known model. Provider prefixes such as `openai/` are ignored.
+    pub fn for_model(model_name: &str) -> Option<Self> {
+        let name = model_name.rsplit('/').next().unwrap_or(model_name);
+        MODEL_CAPABILITIES
+            .iter()
+            .filter(|(prefix, _)| name.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, capabilities)| *capabilities)
+    }
+}
+
+impl PayloadLimits {
+    /// The limits of the provider of a known model. Provider prefixes such as `openai/` are
+    /// ignored.
+    pub fn for_model(model_name: &str) -> Self {
+        let name = model_name.rsplit('/').next().unwrap_or(model_name);
+        MODEL_PAYLOAD_LIMITS
+            .iter()
+            .find(|(prefix, _)| name.starts_with(prefix))
+            .map(|(_, limits)| *limits)
+            .unwrap_or_default()
+    }
+}
+
+/// A request that the model would reject
+#[derive(Debug, Error, PartialEq)]
+pub enum CapabilityError {
+    #[error("Parameter max_tokens is {value}, but {model} accepts 1 to {max}")]
+    MaxTokensOutOfRange { model: String, value: i32, max: i32 },
+
+    #[error("Message {index} contains an image, but {model} does not accept images")]
+    ImagesNotSupported { model: String, index: usize },
+
+    #[error("{model} does not support tools, set GOOSE_TOOLSHIM=true to call them through text")]
+    ToolsNotSupported { model: String },
+}
+
+impl From<CapabilityError> for ProviderError {
+    fn from(error: CapabilityError) -> Self {
+        ProviderError::RequestFailed(format!("Invalid request: {}", error))
+    }
+}
+
+/// Check a request against what the model accepts, before it is sent
+///
+/// Models that are not known, and configs with `no_validate`, are not checked. A temperature
+/// for a model that ignores it is only a warning.
+pub fn validate_capabilities(
+    model_config: &ModelConfig,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<(), CapabilityError> {
+    if model_config.no_validate {
+        return Ok(());
+    }
+    let Some(capabilities) = ModelCapabilities::for_model(&model_config.model_name) else {
+        return Ok(());
+    };
+    let model = || model_config.model_name.clone();
+
+    if let Some(value) = model_config.max_tokens {
+        if !(1..=capabilities.max_output_tokens).contains(&value) {
+            return Err(CapabilityError::MaxTokensOutOfRange {
+                model: model(),
+                value,
+                max: capabilities.max_output_tokens,
+            });
+        }
+    }
+
+    if !capabilities.vision {
+        let sends_tool_images = model_config.tool_image_policy != ToolImagePolicy::Drop;
+        if let Some(index) = messages
+            .iter()
+            .position(|message| has_image(message, sends_tool_images))
+        {
+            return Err(CapabilityError::ImagesNotSupported {
+                model: model(),
+                index,
+            });
+        }
+    }
+
+    if !capabilities.tools && !tools.is_empty() {
+        return Err(CapabilityError::ToolsNotSupported { model: model() });
+    }
+
+    if !capabilities.temperature && model_config.temperature.is_some() {
+        tracing::warn!(
+            "{} ignores the temperature, so it is not sent",
+            model_config.model_name
+        );
+    }
+    Ok(())
+}
+
+fn has_image(message: &Message, tool_images: bool) -> bool {
+    message.content.iter().any(|content| match content {
+        MessageContent::Image(_) => true,
+        MessageContent::ToolResponse(response) => {
+            tool_images
+                && response.tool_result.as_ref().is_ok_and(|result| {
+                    result
+                        .iter()
+                        .any(|content| matches!(content, Content::Image(_)))
+                })
+        }
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const PNG_DATA: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=";
+
+    #[test]
+    fn test_model_capabilities() {
+        let o3 = ModelCapabilities::for_model("o3").unwrap();
+        assert!(o3.vision);
+        assert!(!o3.temperature);
+        // The longest prefix wins
+        assert!(!ModelCapabilities::for_model("o3-mini").unwrap().vision);
+        assert_eq!(
+            ModelCapabilities::for_model("openai/gpt-4o-2024-08-06")
+                .unwrap()
+                .max_output_tokens,
+            16_384
+        );
+        assert_eq!(ModelCapabilities::for_model("llama3.3"), None);
+    }
+
+    #[test]
+    fn test_validate_max_tokens() {
+        let hello = [Message::user().with_text("Hello")];
+        let model_config =
+            ModelConfig::new("claude-3-5-sonnet-latest".to_string()).with_max_tokens(Some(100_000));
+        assert_eq!(
+            validate_capabilities(&model_config, &hello, &[]),
+            Err(CapabilityError::MaxTokensOutOfRange {
+                model: "claude-3-5-sonnet-latest".to_string(),
+                value: 100_000,
+                max: 8_192,
+            })
+        );
+        assert_eq!(
+            validate_capabilities(&model_config, &hello, &[])
+                .unwrap_err()
+                .to_string(),
+            "Parameter max_tokens is 100000, but claude-3-5-sonnet-latest accepts 1 to 8192"
+        );
+
+        let model_config = model_config.with_max_tokens(Some(8_192));
+        assert!(validate_capabilities(&model_config, &hello, &[]).is_ok());
+
+        // Unknown models and the escape hatch aren't checked
+        let model_config =
+            ModelConfig::new("my-finetune".to_string()).with_max_tokens(Some(100_000));
+        assert!(validate_capabilities(&model_config, &hello, &[]).is_ok());
+        let model_config = ModelConfig::new("claude-3-5-sonnet-latest".to_string())
+            .with_max_tokens(Some(100_000))
+            .with_no_validate(true);
+        assert!(validate_capabilities(&model_config, &hello, &[]).is_ok());
+    }
+
+    #[test]
+    fn test_validate_images_and_tools() {
+        let messages = [
+            Message::user().with_text("Hello"),
+            Message::assistant().with_text("Hi"),
+            Message::user().with_image(PNG_DATA, "image/png"),
+        ];
+        let model_config = ModelConfig::new("o3-mini".to_string());
+        assert_eq!(
+            validate_capabilities(&model_config, &messages, &[]),
+            Err(CapabilityError::ImagesNotSupported {
+                model: "o3-mini".to_string(),
+                index: 2,
+            })
+        );
+        let model_config = ModelConfig::new("gpt-4o".to_string());
+        assert!(validate_capabilities(&model_config, &messages, &[]).is_ok());
+
+        // Images in tool results count unless they are dropped
+        let messages = [Message::user()
+            .with_tool_response("1", Ok(vec![Content::image(PNG_DATA, "image/png")]))];
+        let model_config = ModelConfig::new("o3-mini".to_string());
+        assert!(validate_capabilities(&model_config, &messages, &[]).is_err());
+        let model_config = model_config.with_tool_image_policy(ToolImagePolicy::Drop);
+        assert!(validate_capabilities(&model_config, &messages, &[]).is_ok());
+
+        let tool = Tool::new("shell", "Run a command", json!({"type": "object"}), None);
+        let model_config = ModelConfig::new("o1-mini".to_string());
+        assert_eq!(
+            validate_capabilities(&model_config, &[], &[tool]),
+            Err(CapabilityError::ToolsNotSupported {
+                model: "o1-mini".to_string()
+            })
+        );
+    }
+}
@@ -7,6 +7,7 @@ use tokio::process::Command;
 
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
+use super::headers::new_request_id;
 use super::utils::emit_debug_trace;
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
@@ -221,8 +222,9 @@ impl ClaudeCodeProvider {
 
         let response_message = Message {
             role: Role::Assistant,
-            created: chrono::Utc::now().timestamp(),
+            created: crate::determinism::now().timestamp(),
             content: message_content,
+            extra: Default::default(),
         };
 
         Ok((response_message, usage))
@@ -355,11 +357,12 @@ impl ClaudeCodeProvider {
 
         let message = Message {
             role: mcp_core::Role::Assistant,
-            created: chrono::Utc::now().timestamp(),
+            created: crate::determinism::now().timestamp(),
             content: vec![MessageContent::Text(mcp_core::content::TextContent {
                 text: description.clone(),
                 annotations: None,
             })],
+            extra: Default::default(),
         };
 
         let usage = Usage::default();
@@ -427,7 +430,7 @@ impl Provider for ClaudeCodeProvider {
             "usage": usage
         });
 
-        emit_debug_trace(&self.model, &payload, &response, &usage);
+        emit_debug_trace(&self.model, &new_request_id(), &payload, &response, &usage);
 
         Ok((
             message,
@@ -0,0 +1,445 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use super::base::{
+    CompletionOptions, DryRunRequest, LeadWorkerProviderTrait, Provider, ProviderMetadata,
+    ProviderUsage, ToolCallProgressSender,
+};
+use super::errors::ProviderError;
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+/// The instruction sent after a response that was cut off by the output limit
+pub const CONTINUE_INSTRUCTION: &str = "Your previous response was cut off by the output limit. \
+Continue exactly from where you stopped. Do not repeat anything you already wrote, and do not add \
+any preamble.";
+
+/// The shortest overlap removed when stitching, so a few characters that happen to match are kept
+const MIN_OVERLAP_CHARS: usize = 5;
+
+/// The longest overlap looked for when stitching
+const MAX_OVERLAP_BYTES: usize = 2000;
+
+/// A provider that asks for the rest of responses that were cut off by the output limit
+///
+/// The pieces are stitched into one message, and their usage is summed. Responses cut off in
+/// the middle of a tool call are returned as they are, as the call can't be continued.
+pub struct ContinuationProvider {
+    inner: Arc<dyn Provider>,
+    max_continuations: u32,
+}
+
+impl ContinuationProvider {
+    pub fn new(inner: Arc<dyn Provider>, max_continuations: u32) -> Self {
+        Self {
+            inner,
+            max_continuations,
+        }
+    }
+
+    /// Ask for the rest of a response until it is complete or the continuations run out
+    async fn continue_truncated(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        mut message: Message,
+        mut usage: ProviderUsage,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        while usage.is_truncated()
+            && usage.continuations < self.max_continuations
+            && !message.is_tool_call()
+        {
+            let mut conversation = messages.to_vec();
+            conversation.push(Message::assistant().with_text(text_of(&message)));
+            conversation.push(Message::user().with_text(CONTINUE_INSTRUCTION));
+
+            let (next, next_usage) = self.inner.complete(system, &conversation, tools).await?;
+            tracing::debug!(
+                "Continued a response cut off by the output limit ({} of {})",
+                usage.continuations + 1,
+                self.max_continuations
+            );
+            let progressed = !text_of(&next).is_empty() || next.is_tool_call();
+
+            message = stitch_messages(message, next);
+            usage.usage += next_usage.usage;
+            usage.model = next_usage.model;
+            usage.finish_reason = next_usage.finish_reason;
+            usage.continuations += 1;
+            if !progressed {
+                break;
+            }
+        }
+        Ok((message, usage))
+    }
+}
+
+/// The text of a message, with the pieces of a streamed response joined as they arrived
+fn text_of(message: &Message) -> String {
+    message
+        .content
+        .iter()
+        .filter_map(|content| content.as_text())
+        .collect()
+}
+
+/// Join a response with its continuation
+///
+/// The text of both is stitched into one block after the other content of the first message,
+/// followed by the other content of the continuation, such as tool calls made once the text
+/// was done.
+pub fn stitch_messages(previous: Message, next: Message) -> Message {
+    let text = stitch_text(&text_of(&previous), &text_of(&next));
+    let mut content: Vec<MessageContent> = previous
+        .content
+        .into_iter()
+        .filter(|content| content.as_text().is_none())
+        .collect();
+    content.push(MessageContent::text(text));
+    content.extend(
+        next.content
+            .into_iter()
+            .filter(|content| content.as_text().is_none()),
+    );
+    Message {
+        content,
+        ..previous
+    }
+}
+
+/// Join text with its continuation, dropping what the continuation repeats
+///
+/// Models asked to continue sometimes start with the end of what they already wrote, or open
+/// the code block they were in again.
+pub fn stitch_text(previous: &str, next: &str) -> String {
+    let next = if in_code_block(previous) {
+        strip_code_fence_line(next)
+    } else {
+        next
+    };
+    let overlap = overlap_len(previous, next);
+    format!("{}{}", previous, &next[overlap..])
+}
+
+/// Whether text ends inside a ``` fenced code block
+fn in_code_block(text: &str) -> bool {
+    text.lines()
+        .filter(|line| line.trim_start().starts_with("```"))
+        .count()
+        % 2
+        == 1
+}
+
+/// `text` without its first line, when that line opens a code block
+///
+/// Only fences with an info string, such as the language, are dropped, as a bare fence may
+/// close the block instead.
+fn strip_code_fence_line(text: &str) -> &str {
+    let trimmed = text.trim_start();
+    let Some(info) = trimmed.strip_prefix("```") else {
+        return text;
+    };
+    match info.split_once('\n') {
+        Some((info, rest)) if !info.trim().is_empty() && !info.trim().contains(' ') => rest,
+        _ => text,
+    }
+}
+
+/// The length in bytes of the longest start of `next` that `previous` ends with
+///
+/// Overlaps shorter than [`MIN_OVERLAP_CHARS`] or made only of whitespace are not counted.
+fn overlap_len(previous: &str, next: &str) -> usize {
+    let max_len = previous.len().min(next.len()).min(MAX_OVERLAP_BYTES);
+    next.char_indices()
+        .map(|(index, c)| index + c.len_utf8())
+        .take_while(|&end| end <= max_len)
+        .filter(|&end| {
+            let candidate = &next[..end];
+            candidate.chars().count() >= MIN_OVERLAP_CHARS
+                && !candidate.trim().is_empty()
+                && previous.ends_with(candidate)
+        })
+        .last()
+        .unwrap_or(0)
+}
+
+#[async_trait]
+impl Provider for ContinuationProvider {
+    fn metadata() -> ProviderMetadata {
+        // This is a wrapper provider, so we return minimal metadata
+        ProviderMetadata::new(
+            "continuation",
+            "Continuation Provider",
+            "A provider that continues responses cut off by the output limit",
+            "",     // No default model as this is determined by the wrapped provider
+            vec![], // No known models as this depends on the wrapped provider
+            "",     // No doc link
+            vec![], // No config keys as configuration is done through the wrapped provider
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let (message, usage) = self.inner.complete(system, messages, tools).await?;
+        self.continue_truncated(system, messages, tools, message, usage)
+            .await
+    }
+
+    async fn complete_with_progress(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        progress: ToolCallProgressSender,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let (message, usage) = self
+            .inner
+            .complete_with_progress(system, messages, tools, progress)
+            .await?;
+        self.continue_truncated(system, messages, tools, message, usage)
+            .await
+    }
+
+    async fn complete_with_options(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        options: &CompletionOptions,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let (message, usage) = self
+            .inner
+            .complete_with_options(system, messages, tools, options)
+            .await?;
+        self.continue_truncated(system, messages, tools, message, usage)
+            .await
+    }
+
+    async fn dry_run(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<DryRunRequest, ProviderError> {
+        self.inner.dry_run(system, messages, tools).await
+    }
+
+    fn supports_prefill(&self) -> bool {
+        self.inner.supports_prefill()
+    }
+
+    fn supports_system_blocks(&self) -> bool {
+        self.inner.supports_system_blocks()
+    }
+
+    async fn fetch_supported_models_async(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        self.inner.fetch_supported_models_async().await
+    }
+
+    fn supports_embeddings(&self) -> bool {
+        self.inner.supports_embeddings()
+    }
+
+    async fn create_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, ProviderError> {
+        self.inner.create_embeddings(texts).await
+    }
+
+    fn as_lead_worker(&self) -> Option<&dyn LeadWorkerProviderTrait> {
+        self.inner.as_lead_worker()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::{FinishReason, Usage};
+    use mcp_core::ToolCall;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    /// Returns the scripted responses in order, and records the conversations it was sent
+    struct ScriptedProvider {
+        responses: Mutex<Vec<(Message, &'static str)>>,
+        requests: Mutex<Vec<Vec<Message>>>,
+    }
+
+    impl ScriptedProvider {
+        fn new(responses: Vec<(Message, &'static str)>) -> Arc<Self> {
+            Arc::new(Self {
+                responses: Mutex::new(responses.into_iter().rev().collect()),
+                requests: Mutex::new(Vec::new()),
+            })
+        }
+
+        fn text(pieces: &[(&str, &'static str)]) -> Arc<Self> {
+            Self::new(
+                pieces
+                    .iter()
+                    .map(|(text, reason)| (Message::assistant().with_text(*text), *reason))
+                    .collect(),
+            )
+        }
+    }
+
+    #[async_trait]
+    impl Provider for ScriptedProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("scripted".to_string())
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            self.requests.lock().unwrap().push(messages.to_vec());
+            let (message, reason) = self.responses.lock().unwrap().pop().unwrap();
+            let usage = Usage::new(Some(100), Some(10), Some(110));
+            Ok((
+                message,
+                ProviderUsage::new("scripted".to_string(), usage)
+                    .with_finish_reason(Some(FinishReason::from(reason.to_string()))),
+            ))
+        }
+    }
+
+    async fn complete(inner: Arc<ScriptedProvider>, max: u32) -> (Message, ProviderUsage) {
+        let provider = ContinuationProvider::new(inner, max);
+        let messages = vec![Message::user().with_text("Write main")];
+        provider.complete("system", &messages, &[]).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_continue_mid_word() {
+        let inner = ScriptedProvider::text(&[
+            ("fn mai", "length"),
+            ("n() {\n    println!(\"hel", "length"),
+            ("lo\");\n}", "stop"),
+        ]);
+        let (message, usage) = complete(inner.clone(), 3).await;
+
+        assert_eq!(
+            message.as_concat_text(),
+            "fn main() {\n    println!(\"hello\");\n}"
+        );
+        assert_eq!(message.content.len(), 1);
+        assert_eq!(usage.continuations, 2);
+        assert_eq!(usage.finish_reason, Some(FinishReason::Stop));
+        assert_eq!(usage.usage, Usage::new(Some(300), Some(30), Some(330)));
+
+        // Each follow-up carries everything written so far and asks to continue
+        let requests = inner.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        let last = &requests[2];
+        assert_eq!(last.len(), 3);
+        assert_eq!(last[1].as_concat_text(), "fn main() {\n    println!(\"hel");
+        assert_eq!(last[2].as_concat_text(), CONTINUE_INSTRUCTION);
+    }
+
+    #[tokio::test]
+    async fn test_continue_mid_code_fence() {
+        // Cut off in the fence line itself
+        let inner = ScriptedProvider::text(&[
+            ("Here it is:\n```ru", "length"),
+            ("st\nfn main() {}\n```", "stop"),
+        ]);
+        let (message, _) = complete(inner, 3).await;
+        assert_eq!(
+            message.as_concat_text(),
+            "Here it is:\n```rust\nfn main() {}\n```"
+        );
+
+        // Cut off inside the block, and the continuation opens it again and repeats a line
+        let inner = ScriptedProvider::text(&[
+            ("```rust\nfn main() {\n    let x = 1;\n", "length"),
+            (
+                "```rust\n    let x = 1;\n    println!(\"{}\", x);\n}\n```",
+                "stop",
+            ),
+        ]);
+        let (message, usage) = complete(inner, 3).await;
+        assert_eq!(
+            message.as_concat_text(),
+            "```rust\nfn main() {\n    let x = 1;\n    println!(\"{}\", x);\n}\n```"
+        );
+        assert_eq!(usage.continuations, 1);
+    }
+
+    #[tokio::test]
+    async fn test_continuations_are_limited() {
+        let inner =
+            ScriptedProvider::text(&[("one ", "length"), ("two ", "length"), ("three ", "length")]);
+        let (message, usage) = complete(inner.clone(), 2).await;
+
+        assert_eq!(message.as_concat_text(), "one two three ");
+        assert_eq!(usage.continuations, 2);
+        assert!(usage.is_truncated());
+        assert_eq!(inner.requests.lock().unwrap().len(), 3);
+
+        // Disabled with no continuations
+        let inner = ScriptedProvider::text(&[("one ", "length")]);
+        let (_, usage) = complete(inner, 0).await;
+        assert_eq!(usage.continuations, 0);
+    }
+
+    #[tokio::test]
+    async fn test_no_continuation_mid_tool_call() {
+        let truncated = Message::assistant()
+            .with_text("Let me write that file.")
+            .with_tool_request(
+                "1",
+                Ok(ToolCall::new(
+                    "developer__text_editor",
+                    json!({"command": "write"}),
+                )),
+            );
+        let inner = ScriptedProvider::new(vec![(truncated.clone(), "length")]);
+        let (message, usage) = complete(inner.clone(), 3).await;
+
+        assert_eq!(message, truncated);
+        assert_eq!(usage.continuations, 0);
+        assert_eq!(inner.requests.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_stitch_text() {
+        // Short or blank overlaps are kept, as they are likely a coincidence
+        assert_eq!(stitch_text("fo", "o bar"), "foo bar");
+        assert_eq!(stitch_text("a\n    ", "    b"), "a\n        b");
+        // Repeated text is dropped
+        assert_eq!(
+            stitch_text("The quick brown", "quick brown fox"),
+            "The quick brown fox"
+        );
+        assert_eq!(
+            stitch_text("日本語のテキスト", "のテキストです"),
+            "日本語のテキストです"
+        );
+        // A fence is only dropped when it reopens a block
+        assert_eq!(
+            stitch_text("text\n", "```rust\ncode"),
+            "text\n```rust\ncode"
+        );
+        assert_eq!(stitch_text("```\ncode\n", "```sh\nmore"), "```\ncode\nmore");
+        assert_eq!(
+            stitch_text("```\ncode\n", "```\nDone."),
+            "```\ncode\n```\nDone."
+        );
+    }
+}
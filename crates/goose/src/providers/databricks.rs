@@ -2,8 +2,10 @@ use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::embedding::EmbeddingCapable;
 use super::errors::ProviderError;
 use super::formats::databricks::{create_request, get_usage, response_to_message};
+use super::formats::openai::get_finish_reason;
+use super::headers::{client_builder, new_request_id, REQUEST_ID_HEADER};
 use super::oauth;
-use super::utils::{get_model, ImageFormat};
+use super::utils::{get_model, is_retryable_error, ImageFormat};
 use crate::config::ConfigError;
 use crate::message::Message;
 use crate::model::ModelConfig;
@@ -160,7 +162,7 @@ impl DatabricksProvider {
 
         let host = host?;
 
-        let client = Client::builder()
+        let client = client_builder()
             .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
             .build()?;
 
@@ -235,9 +237,7 @@ impl DatabricksProvider {
     ///
     /// Returns a Result containing the new DatabricksProvider instance
     pub fn from_params(host: String, api_key: String, model: ModelConfig) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(600))
-            .build()?;
+        let client = client_builder().timeout(Duration::from_secs(600)).build()?;
 
         Ok(Self {
             client,
@@ -265,7 +265,7 @@ impl DatabricksProvider {
         }
     }
 
-    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+    async fn post(&self, request_id: &str, payload: Value) -> Result<Value, ProviderError> {
         let base_url = Url::parse(&self.host)
             .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
 
@@ -303,6 +303,7 @@ impl DatabricksProvider {
                 .client
                 .post(url.clone())
                 .header("Authorization", auth_header)
+                .header(REQUEST_ID_HEADER, request_id)
                 .json(&payload)
                 .send()
                 .await?;
@@ -393,7 +394,8 @@ impl DatabricksProvider {
                     // Continue to the next retry attempt
                     continue;
                 }
-                StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
+                // Server errors, and overloaded models behind the serving endpoint
+                _ if is_retryable_error(status, payload.as_ref().unwrap_or(&Value::Null)) => {
                     attempts += 1;
                     let error_msg = format!(
                         "Server error (attempt {}/{}): {:?}",
@@ -468,7 +470,9 @@ impl Provider for DatabricksProvider {
             .expect("payload should have model key")
             .remove("model");
 
-        let response = self.post(payload.clone()).await?;
+        let request_id = new_request_id();
+
+        let response = self.post(&request_id, payload.clone()).await?;
 
         // Parse response
         let message = response_to_message(response.clone())?;
@@ -481,9 +485,12 @@ impl Provider for DatabricksProvider {
             Err(e) => return Err(e),
         };
         let model = get_model(&response);
-        super::utils::emit_debug_trace(&self.model, &payload, &response, &usage);
+        super::utils::emit_debug_trace(&self.model, &request_id, &payload, &response, &usage);
 
-        Ok((message, ProviderUsage::new(model, usage)))
+        Ok((
+            message,
+            ProviderUsage::new(model, usage).with_finish_reason(get_finish_reason(&response)),
+        ))
     }
 
     fn supports_embeddings(&self) -> bool {
@@ -592,7 +599,7 @@ impl EmbeddingCapable for DatabricksProvider {
             "input": texts,
         });
 
-        let response = self.post(request).await?;
+        let response = self.post(&new_request_id(), request).await?;
 
         let embeddings = response["data"]
             .as_array()
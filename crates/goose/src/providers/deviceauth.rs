@@ -0,0 +1,466 @@
+//! Signing in with the OAuth device flow, for providers without a static key
+//!
+//! The user is shown a code and a URL to enter it at, in a browser that can be on another
+//! machine, while goose polls for the token as RFC 8628 describes. The access and refresh
+//! tokens are kept with the other secrets, in the keyring unless it is disabled, and the access
+//! token is refreshed once it expires. Providers take it through [`TokenProvider`], as they
+//! would a static key.
+
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use super::headers::client_builder;
+use crate::config::{Config, ConfigError};
+
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// How much longer to wait between polls after the server asks to slow down
+const SLOW_DOWN_STEP: Duration = Duration::from_secs(5);
+
+/// Tokens that expire within this many seconds are refreshed before they are used
+const EXPIRY_MARGIN_SECONDS: i64 = 60;
+
+/// A source of the bearer token of provider requests
+#[async_trait]
+pub trait TokenProvider: Send + Sync {
+    /// A token that is valid now, refreshed or signed in for again as needed
+    async fn token(&self) -> Result<String>;
+}
+
+/// A key that does not expire, such as a personal access token
+pub struct StaticToken(pub String);
+
+#[async_trait]
+impl TokenProvider for StaticToken {
+    async fn token(&self) -> Result<String> {
+        Ok(self.0.clone())
+    }
+}
+
+/// What the user needs to authorize goose
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceCode {
+    device_code: String,
+    /// The code to enter
+    pub user_code: String,
+    /// Where to enter it
+    pub verification_uri: String,
+    /// How long the code can be entered, in seconds
+    pub expires_in: u64,
+    /// The seconds to wait between polls
+    #[serde(default = "default_interval")]
+    interval: u64,
+}
+
+fn default_interval() -> u64 {
+    5
+}
+
+type DevicePrompt = Box<dyn Fn(&DeviceCode) + Send + Sync>;
+
+static DEVICE_PROMPT: OnceLock<DevicePrompt> = OnceLock::new();
+
+/// Set how the code to enter is shown to the user
+///
+/// Without one, it is printed to stderr.
+pub fn set_device_prompt(prompt: impl Fn(&DeviceCode) + Send + Sync + 'static) {
+    let _ = DEVICE_PROMPT.set(Box::new(prompt));
+}
+
+fn show_device_code(code: &DeviceCode) {
+    match DEVICE_PROMPT.get() {
+        Some(prompt) => prompt(code),
+        None => eprintln!(
+            "To sign in, open {} and enter the code {}",
+            code.verification_uri, code.user_code
+        ),
+    }
+}
+
+/// The OAuth client to sign in with, and where its tokens are kept
+#[derive(Debug, Clone)]
+pub struct DeviceFlowConfig {
+    pub client_id: String,
+    pub device_code_url: String,
+    pub token_url: String,
+    pub scopes: Vec<String>,
+    /// The secret the tokens are stored under
+    pub secret_key: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct StoredTokens {
+    access_token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    refresh_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl StoredTokens {
+    fn is_fresh(&self) -> bool {
+        self.expires_at.is_none_or(|expires_at| {
+            expires_at > Utc::now() + chrono::Duration::seconds(EXPIRY_MARGIN_SECONDS)
+        })
+    }
+}
+
+/// A response of the token endpoint, a token or an error, which some servers send with a
+/// success status
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+    expires_in: Option<i64>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+impl TokenResponse {
+    /// The tokens, keeping `refresh_token` when the server did not send a new one
+    fn into_tokens(self, refresh_token: Option<&str>) -> Result<StoredTokens> {
+        let access_token = self
+            .access_token
+            .ok_or_else(|| anyhow!("The token response has no access token"))?;
+        Ok(StoredTokens {
+            access_token,
+            refresh_token: self
+                .refresh_token
+                .or_else(|| refresh_token.map(str::to_string)),
+            expires_at: self
+                .expires_in
+                .map(|seconds| Utc::now() + chrono::Duration::seconds(seconds)),
+        })
+    }
+
+    fn error_message(&self, error: &str) -> String {
+        match &self.error_description {
+            Some(description) => format!("{}: {}", error, description),
+            None => error.to_string(),
+        }
+    }
+}
+
+/// Tokens from the OAuth device flow, signed in for the first time one is needed
+pub struct DeviceFlowAuth {
+    client: Client,
+    flow: DeviceFlowConfig,
+    config: Option<Config>,
+    tokens: Mutex<Option<StoredTokens>>,
+    slow_down_step: Duration,
+}
+
+impl DeviceFlowAuth {
+    pub fn new(flow: DeviceFlowConfig) -> Result<Self> {
+        Ok(Self {
+            client: client_builder().timeout(Duration::from_secs(30)).build()?,
+            flow,
+            config: None,
+            tokens: Mutex::new(None),
+            slow_down_step: SLOW_DOWN_STEP,
+        })
+    }
+
+    /// Keep the tokens in `config` instead of the global config
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    fn config(&self) -> &Config {
+        match &self.config {
+            Some(config) => config,
+            None => Config::global(),
+        }
+    }
+
+    fn load(&self) -> Result<Option<StoredTokens>> {
+        match self
+            .config()
+            .get_secret::<StoredTokens>(&self.flow.secret_key)
+        {
+            Ok(tokens) => Ok(Some(tokens)),
+            Err(ConfigError::NotFound(_)) => Ok(None),
+            Err(ConfigError::DeserializeError(e)) => {
+                tracing::warn!("Ignoring the stored {} tokens: {}", self.flow.secret_key, e);
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, tokens: &StoredTokens) -> Result<()> {
+        self.config()
+            .set_secret(&self.flow.secret_key, serde_json::to_value(tokens)?)
+            .context("Failed to store the signed in tokens")
+    }
+
+    async fn request_token(&self, form: &[(&str, &str)]) -> Result<TokenResponse> {
+        self.client
+            .post(&self.flow.token_url)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .form(form)
+            .send()
+            .await
+            .context("Failed to reach the token endpoint")?
+            .json::<TokenResponse>()
+            .await
+            .context("Failed to parse the token response")
+    }
+
+    async fn refresh(&self, refresh_token: &str) -> Result<StoredTokens> {
+        let response = self
+            .request_token(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token),
+                ("client_id", self.flow.client_id.as_str()),
+            ])
+            .await?;
+        if let Some(error) = &response.error {
+            return Err(anyhow!(
+                "Failed to refresh the token, {}",
+                response.error_message(error)
+            ));
+        }
+        response.into_tokens(Some(refresh_token))
+    }
+
+    async fn sign_in(&self) -> Result<StoredTokens> {
+        let scope = self.flow.scopes.join(" ");
+        let code = self
+            .client
+            .post(&self.flow.device_code_url)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .form(&[
+                ("client_id", self.flow.client_id.as_str()),
+                ("scope", scope.as_str()),
+            ])
+            .send()
+            .await
+            .context("Failed to request a device code")?
+            .error_for_status()
+            .context("Failed to request a device code")?
+            .json::<DeviceCode>()
+            .await
+            .context("Failed to parse the device code response")?;
+        show_device_code(&code);
+        self.poll(&code).await
+    }
+
+    /// Wait for the user to enter the code, at the pace the server asks for
+    async fn poll(&self, code: &DeviceCode) -> Result<StoredTokens> {
+        let deadline = Instant::now() + Duration::from_secs(code.expires_in);
+        let mut interval = Duration::from_secs(code.interval);
+        loop {
+            tokio::time::sleep(interval).await;
+            let response = self
+                .request_token(&[
+                    ("grant_type", DEVICE_CODE_GRANT),
+                    ("device_code", code.device_code.as_str()),
+                    ("client_id", self.flow.client_id.as_str()),
+                ])
+                .await?;
+            match response.error.as_deref() {
+                None => return response.into_tokens(None),
+                Some("authorization_pending") => {}
+                Some("slow_down") => interval += self.slow_down_step,
+                Some("expired_token") => {
+                    return Err(anyhow!(
+                        "The code {} expired before it was entered, sign in again",
+                        code.user_code
+                    ))
+                }
+                Some("access_denied") => return Err(anyhow!("The sign in was denied")),
+                Some(error) => {
+                    return Err(anyhow!(
+                        "Failed to sign in, {}",
+                        response.error_message(error)
+                    ))
+                }
+            }
+            if Instant::now() >= deadline {
+                return Err(anyhow!(
+                    "The code {} expired before it was entered, sign in again",
+                    code.user_code
+                ));
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl TokenProvider for DeviceFlowAuth {
+    async fn token(&self) -> Result<String> {
+        let mut cached = self.tokens.lock().await;
+        if cached.is_none() {
+            *cached = self.load()?;
+        }
+        if let Some(tokens) = cached.as_ref().filter(|tokens| tokens.is_fresh()) {
+            return Ok(tokens.access_token.clone());
+        }
+
+        let refreshed = match cached
+            .as_ref()
+            .and_then(|tokens| tokens.refresh_token.clone())
+        {
+            Some(refresh_token) => match self.refresh(&refresh_token).await {
+                Ok(tokens) => Some(tokens),
+                Err(e) => {
+                    tracing::warn!("{}, signing in again", e);
+                    None
+                }
+            },
+            None => None,
+        };
+        let tokens = match refreshed {
+            Some(tokens) => tokens,
+            None => self.sign_in().await?,
+        };
+        self.save(&tokens)?;
+        let access_token = tokens.access_token.clone();
+        *cached = Some(tokens);
+        Ok(access_token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+    use wiremock::matchers::{body_string_contains, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn auth(server: &MockServer, dir: &TempDir) -> DeviceFlowAuth {
+        let mut auth = DeviceFlowAuth::new(DeviceFlowConfig {
+            client_id: "test-client".to_string(),
+            device_code_url: format!("{}/login/device/code", server.uri()),
+            token_url: format!("{}/login/oauth/access_token", server.uri()),
+            scopes: vec!["read:user".to_string()],
+            secret_key: "TEST_DEVICE_FLOW_TOKENS".to_string(),
+        })
+        .unwrap()
+        .with_config(
+            Config::new_with_file_secrets(
+                dir.path().join("config.yaml"),
+                dir.path().join("secrets.yaml"),
+            )
+            .unwrap(),
+        );
+        auth.slow_down_step = Duration::from_millis(10);
+        auth
+    }
+
+    /// Answer the token endpoint with `error` the next time it is polled
+    async fn mount_poll_error(server: &MockServer, error: &str) {
+        Mock::given(method("POST"))
+            .and(path("/login/oauth/access_token"))
+            .and(body_string_contains("device_code=device-123"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({"error": error})))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    async fn mount_device_code(server: &MockServer) {
+        Mock::given(method("POST"))
+            .and(path("/login/device/code"))
+            .and(body_string_contains("client_id=test-client"))
+            .and(body_string_contains("scope=read%3Auser"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "device_code": "device-123",
+                "user_code": "ABCD-1234",
+                "verification_uri": "https://github.com/login/device",
+                "expires_in": 900,
+                "interval": 0
+            })))
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_sign_in_polls_until_authorized() {
+        let server = MockServer::start().await;
+        let dir = TempDir::new().unwrap();
+        mount_device_code(&server).await;
+        mount_poll_error(&server, "authorization_pending").await;
+        mount_poll_error(&server, "slow_down").await;
+        Mock::given(method("POST"))
+            .and(path("/login/oauth/access_token"))
+            .and(body_string_contains(
+                "grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Adevice_code",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "access-1",
+                "refresh_token": "refresh-1",
+                "expires_in": 28800,
+                "token_type": "bearer"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let auth = auth(&server, &dir);
+        assert_eq!(auth.token().await.unwrap(), "access-1");
+        // Cached after the first time, and kept for the next process
+        assert_eq!(auth.token().await.unwrap(), "access-1");
+        let stored = auth.load().unwrap().unwrap();
+        assert_eq!(stored.refresh_token.as_deref(), Some("refresh-1"));
+        assert!(stored.is_fresh());
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_refreshed() {
+        let server = MockServer::start().await;
+        let dir = TempDir::new().unwrap();
+        Mock::given(method("POST"))
+            .and(path("/login/oauth/access_token"))
+            .and(body_string_contains("grant_type=refresh_token"))
+            .and(body_string_contains("refresh_token=refresh-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "access-2",
+                "expires_in": 28800
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let auth = auth(&server, &dir);
+        auth.save(&StoredTokens {
+            access_token: "access-1".to_string(),
+            refresh_token: Some("refresh-1".to_string()),
+            expires_at: Some(Utc::now() - chrono::Duration::minutes(1)),
+        })
+        .unwrap();
+
+        assert_eq!(auth.token().await.unwrap(), "access-2");
+        // The refresh token is kept when the server does not rotate it
+        assert_eq!(
+            auth.load().unwrap().unwrap().refresh_token.as_deref(),
+            Some("refresh-1")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_device_code() {
+        let server = MockServer::start().await;
+        let dir = TempDir::new().unwrap();
+        mount_device_code(&server).await;
+        mount_poll_error(&server, "expired_token").await;
+
+        let error = auth(&server, &dir).token().await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The code ABCD-1234 expired before it was entered, sign in again"
+        );
+    }
+}
@@ -0,0 +1,329 @@
+use std::error::Error as _;
+
+use reqwest::StatusCode;
+use serde_json::Value;
+use thiserror::Error;
+
+/// A failed provider request, as it is diagnosed
+#[derive(Debug, Clone, Copy)]
+pub enum SetupFailure<'a> {
+    /// The request could not be sent, or got no response
+    Send(&'a reqwest::Error),
+    /// The provider answered with an error
+    Response {
+        url: &'a str,
+        status: StatusCode,
+        body: &'a Value,
+    },
+}
+
+/// A provider that is set up wrong, such as with a bad base URL, key or model name
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum ProviderSetupError {
+    #[error("The URL {url} is not valid: {detail}")]
+    InvalidUrl { url: String, detail: String },
+
+    #[error("Could not resolve the host {host}")]
+    Dns { host: String },
+
+    #[error("Could not connect to {url}")]
+    Connect { url: String },
+
+    #[error("The TLS connection to {host} failed: {detail}")]
+    Tls { host: String, detail: String },
+
+    #[error("The API key was rejected (status 401)")]
+    InvalidKey,
+
+    #[error("The API key does not have access to this model (status 403)")]
+    NoModelAccess,
+
+    #[error("The API has no endpoint {url} (status 404)")]
+    WrongPath { url: String },
+
+    #[error("The model was not found: {detail}")]
+    ModelNotFound { detail: String },
+}
+
+impl ProviderSetupError {
+    /// A short suggestion of how to fix the setup
+    pub fn suggestion(&self) -> &'static str {
+        match self {
+            Self::InvalidUrl { .. } => "Set the host to a full URL, such as https://api.openai.com",
+            Self::Dns { .. } => "Check the host for typos, and that you are online",
+            Self::Connect { .. } => "Check that the server is running and listening on that port",
+            Self::Tls { .. } => {
+                "If the server has a certificate of a private CA, set SSL_CERT_FILE to a CA bundle with it"
+            }
+            Self::InvalidKey => "Check the API key, it may be mistyped or revoked",
+            Self::NoModelAccess => "The key can't use this model, pick another or ask for access",
+            Self::WrongPath { url } if url.contains("/v1/v1/") => {
+                "The path has /v1 twice, remove /v1 from the end of the host"
+            }
+            Self::WrongPath { url } if !url.contains("/v1/") => {
+                "The host probably needs /v1 at the end"
+            }
+            Self::WrongPath { .. } => "Check the host, this server may not serve the API under /v1",
+            Self::ModelNotFound { .. } => {
+                "Check the model name, `goose configure` lists the models of the provider"
+            }
+        }
+    }
+}
+
+/// What a failed provider request says about the setup of the provider
+///
+/// Shared by all providers and the configuration check. Failures that aren't caused by the
+/// setup, such as rate limits and server errors, are `None`.
+pub fn diagnose(failure: SetupFailure) -> Option<ProviderSetupError> {
+    match failure {
+        SetupFailure::Send(error) => diagnose_send(error),
+        SetupFailure::Response { url, status, body } => diagnose_response(url, status, body),
+    }
+}
+
+fn diagnose_send(error: &reqwest::Error) -> Option<ProviderSetupError> {
+    let url = error.url().map(|url| url.to_string()).unwrap_or_default();
+    let host = error
+        .url()
+        .and_then(|url| url.host_str())
+        .unwrap_or_default()
+        .to_string();
+    let mut causes = Vec::new();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        causes.push(cause);
+        source = cause.source();
+    }
+    let detail = causes
+        .last()
+        .map_or_else(|| error.to_string(), |cause| cause.to_string());
+
+    if error.is_builder() {
+        return Some(ProviderSetupError::InvalidUrl { url, detail });
+    }
+    if !error.is_connect() {
+        return None;
+    }
+
+    let messages = causes
+        .iter()
+        .map(|cause| cause.to_string().to_lowercase())
+        .collect::<Vec<_>>()
+        .join(": ");
+    if [
+        "dns error",
+        "failed to lookup address",
+        "name or service not known",
+    ]
+    .iter()
+    .any(|pattern| messages.contains(pattern))
+    {
+        return Some(ProviderSetupError::Dns { host });
+    }
+    // rustls reports its errors as invalid data, which hyper may wrap in another io error
+    let invalid_data = causes.iter().any(|cause| {
+        let mut io_error = cause.downcast_ref::<std::io::Error>();
+        while let Some(error) = io_error {
+            if error.kind() == std::io::ErrorKind::InvalidData {
+                return true;
+            }
+            io_error = error
+                .get_ref()
+                .and_then(|inner| inner.downcast_ref::<std::io::Error>());
+        }
+        false
+    });
+    if invalid_data
+        || ["certificate", "tls", "handshake"]
+            .iter()
+            .any(|pattern| messages.contains(pattern))
+    {
+        return Some(ProviderSetupError::Tls { host, detail });
+    }
+    Some(ProviderSetupError::Connect { url })
+}
+
+fn diagnose_response(url: &str, status: StatusCode, body: &Value) -> Option<ProviderSetupError> {
+    let error = &body["error"];
+    let message = error["message"]
+        .as_str()
+        .or_else(|| error.as_str())
+        .unwrap_or_default();
+    let codes = [&error["code"], &error["type"]];
+    let model_not_found = codes
+        .iter()
+        .any(|code| code.as_str() == Some("model_not_found"))
+        || {
+            let message = message.to_lowercase();
+            // Anthropic names the missing model as `model: <name>`
+            message.starts_with("model:")
+                || (message.contains("model")
+                    && ["not found", "does not exist", "not a valid model"]
+                        .iter()
+                        .any(|pattern| message.contains(pattern)))
+        };
+    // An API reports its own 404s with an error code, a wrong path gets the server's page
+    let api_error = codes.iter().any(|code| code.is_string());
+
+    match status {
+        StatusCode::BAD_REQUEST | StatusCode::NOT_FOUND if model_not_found => {
+            Some(ProviderSetupError::ModelNotFound {
+                detail: message.to_string(),
+            })
+        }
+        StatusCode::NOT_FOUND if !api_error => Some(ProviderSetupError::WrongPath {
+            url: url.to_string(),
+        }),
+        StatusCode::UNAUTHORIZED => Some(ProviderSetupError::InvalidKey),
+        StatusCode::FORBIDDEN => Some(ProviderSetupError::NoModelAccess),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// Post to `path` of a server that answers with `status` and `body`, and diagnose it
+    async fn diagnose_server(path: &str, status: u16, body: Value) -> Option<ProviderSetupError> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(status).set_body_json(body))
+            .mount(&server)
+            .await;
+        let url = format!("{}{}", server.uri(), path);
+        let response = reqwest::Client::new().post(&url).send().await.unwrap();
+        let status = response.status();
+        let body = response.json::<Value>().await.unwrap_or_default();
+        diagnose(SetupFailure::Response {
+            url: &url,
+            status,
+            body: &body,
+        })
+    }
+
+    async fn diagnose_send_to(url: &str) -> Option<ProviderSetupError> {
+        let error = reqwest::Client::new().post(url).send().await.unwrap_err();
+        diagnose(SetupFailure::Send(&error))
+    }
+
+    #[tokio::test]
+    async fn test_diagnose_dns() {
+        assert_eq!(
+            diagnose_send_to("http://api.goose-provider.invalid/v1/chat/completions").await,
+            Some(ProviderSetupError::Dns {
+                host: "api.goose-provider.invalid".to_string()
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_diagnose_connect_and_url() {
+        // A port that nothing listens on anymore
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let diagnosis = diagnose_send_to(&format!("http://127.0.0.1:{}/v1", port)).await;
+        assert!(matches!(
+            diagnosis,
+            Some(ProviderSetupError::Connect { .. })
+        ));
+
+        let diagnosis = diagnose_send_to("api.openai.com/v1").await;
+        assert!(matches!(
+            diagnosis,
+            Some(ProviderSetupError::InvalidUrl { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_diagnose_tls() {
+        // A TLS handshake with a server that only speaks HTTP fails
+        let server = MockServer::start().await;
+        let url = server.uri().replace("http://", "https://");
+        let diagnosis = diagnose_send_to(&url).await.unwrap();
+        assert!(
+            matches!(diagnosis, ProviderSetupError::Tls { ref host, .. } if host == "127.0.0.1")
+        );
+        assert!(diagnosis.suggestion().contains("SSL_CERT_FILE"));
+    }
+
+    #[tokio::test]
+    async fn test_diagnose_keys() {
+        let invalid =
+            json!({"error": {"message": "Incorrect API key provided", "code": "invalid_api_key"}});
+        assert_eq!(
+            diagnose_server("/v1/chat/completions", 401, invalid).await,
+            Some(ProviderSetupError::InvalidKey)
+        );
+        let no_access = json!({"error": {"type": "permission_error", "message": "Your key can't use this model"}});
+        assert_eq!(
+            diagnose_server("/v1/chat/completions", 403, no_access).await,
+            Some(ProviderSetupError::NoModelAccess)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_diagnose_wrong_path() {
+        let not_found = json!({"error": "404 page not found"});
+        let diagnosis = diagnose_server("/chat/completions", 404, not_found.clone())
+            .await
+            .unwrap();
+        assert!(matches!(diagnosis, ProviderSetupError::WrongPath { .. }));
+        assert_eq!(
+            diagnosis.suggestion(),
+            "The host probably needs /v1 at the end"
+        );
+
+        let diagnosis = diagnose_server("/v1/v1/chat/completions", 404, not_found)
+            .await
+            .unwrap();
+        assert_eq!(
+            diagnosis.suggestion(),
+            "The path has /v1 twice, remove /v1 from the end of the host"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_diagnose_model_not_found() {
+        let openai = json!({"error": {
+            "message": "The model `gpt-5o` does not exist or you do not have access to it.",
+            "type": "invalid_request_error",
+            "code": "model_not_found"
+        }});
+        assert_eq!(
+            diagnose_server("/v1/chat/completions", 404, openai).await,
+            Some(ProviderSetupError::ModelNotFound {
+                detail: "The model `gpt-5o` does not exist or you do not have access to it."
+                    .to_string()
+            })
+        );
+        let anthropic = json!({"type": "error", "error": {"type": "not_found_error", "message": "model: claude-4"}});
+        assert!(matches!(
+            diagnose_server("/v1/messages", 404, anthropic).await,
+            Some(ProviderSetupError::ModelNotFound { .. })
+        ));
+        let ollama = json!({"error": "model \"llama9\" not found, try pulling it first"});
+        assert!(matches!(
+            diagnose_server("/v1/chat/completions", 404, ollama).await,
+            Some(ProviderSetupError::ModelNotFound { .. })
+        ));
+
+        // Other failures aren't about the setup
+        let overloaded = json!({"error": {"type": "overloaded_error", "message": "Overloaded"}});
+        assert_eq!(diagnose_server("/v1/messages", 529, overloaded).await, None);
+        let deployment = json!({"error": {"code": "DeploymentNotFound", "message": "The deployment does not exist"}});
+        assert_eq!(
+            diagnose_server("/openai/deployments/gpt/chat/completions", 404, deployment).await,
+            None
+        );
+        let invalid = json!({"error": {"message": "messages: field required"}});
+        assert_eq!(diagnose_server("/v1/messages", 400, invalid).await, None);
+    }
+}
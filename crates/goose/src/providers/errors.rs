@@ -1,6 +1,8 @@
 use reqwest::StatusCode;
 use thiserror::Error;
 
+use super::diagnosis::{diagnose, ProviderSetupError, SetupFailure};
+
 #[derive(Error, Debug)]
 pub enum ProviderError {
     #[error("Authentication error: {0}")]
@@ -18,22 +20,51 @@ pub enum ProviderError {
     #[error("Request failed: {0}")]
     RequestFailed(String),
 
+    #[error("Request failed with status {status}: {message}")]
+    Status { status: u16, message: String },
+
     #[error("Execution error: {0}")]
     ExecutionError(String),
 
     #[error("Usage data error: {0}")]
     UsageError(String),
+
+    #[error("{0}. {}", .0.suggestion())]
+    Setup(ProviderSetupError),
+}
+
+impl ProviderError {
+    /// The HTTP status of the response the error was made from, if it is known
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ProviderError::Status { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
 }
 
 impl From<anyhow::Error> for ProviderError {
     fn from(error: anyhow::Error) -> Self {
-        ProviderError::ExecutionError(error.to_string())
+        // Provider errors raised while building a request keep their kind
+        match error.downcast::<ProviderError>() {
+            Ok(error) => error,
+            Err(error) => ProviderError::ExecutionError(error.to_string()),
+        }
     }
 }
 
 impl From<reqwest::Error> for ProviderError {
     fn from(error: reqwest::Error) -> Self {
-        ProviderError::ExecutionError(error.to_string())
+        match diagnose(SetupFailure::Send(&error)) {
+            Some(setup_error) => ProviderError::Setup(setup_error),
+            None => ProviderError::ExecutionError(error.to_string()),
+        }
+    }
+}
+
+impl From<ProviderSetupError> for ProviderError {
+    fn from(error: ProviderSetupError) -> Self {
+        ProviderError::Setup(error)
     }
 }
 
@@ -6,18 +6,23 @@ use super::{
     base::{Provider, ProviderMetadata},
     bedrock::BedrockProvider,
     claude_code::ClaudeCodeProvider,
+    continuation::ContinuationProvider,
     databricks::DatabricksProvider,
+    fireworks::FireworksProvider,
     gcpvertexai::GcpVertexAIProvider,
     gemini_cli::GeminiCliProvider,
     githubcopilot::GithubCopilotProvider,
+    githubmodels::GithubModelsProvider,
     google::GoogleProvider,
     groq::GroqProvider,
     lead_worker::LeadWorkerProvider,
     ollama::OllamaProvider,
     openai::OpenAiProvider,
     openrouter::OpenRouterProvider,
+    response_cache::CachingProvider,
     sagemaker_tgi::SageMakerTgiProvider,
     snowflake::SnowflakeProvider,
+    together::TogetherProvider,
     venice::VeniceProvider,
     xai::XaiProvider,
 };
@@ -46,9 +51,11 @@ pub fn providers() -> Vec<ProviderMetadata> {
         BedrockProvider::metadata(),
         ClaudeCodeProvider::metadata(),
         DatabricksProvider::metadata(),
+        FireworksProvider::metadata(),
         GcpVertexAIProvider::metadata(),
         GeminiCliProvider::metadata(),
         GithubCopilotProvider::metadata(),
+        GithubModelsProvider::metadata(),
         GoogleProvider::metadata(),
         GroqProvider::metadata(),
         OllamaProvider::metadata(),
@@ -57,6 +64,7 @@ pub fn providers() -> Vec<ProviderMetadata> {
         SageMakerTgiProvider::metadata(),
         VeniceProvider::metadata(),
         SnowflakeProvider::metadata(),
+        TogetherProvider::metadata(),
         XaiProvider::metadata(),
     ]
 }
@@ -65,14 +73,33 @@ pub fn create(name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>> {
     let config = crate::config::Config::global();
 
     // Check for lead model environment variables
-    if let Ok(lead_model_name) = config.get_param::<String>("GOOSE_LEAD_MODEL") {
+    let provider = if let Ok(lead_model_name) = config.get_param::<String>("GOOSE_LEAD_MODEL") {
         tracing::info!("Creating lead/worker provider from environment variables");
 
-        return create_lead_worker_from_env(name, &model, &lead_model_name);
+        create_lead_worker_from_env(name, &model, &lead_model_name)?
+    } else {
+        // Default: create regular provider
+        create_provider(name, model)?
+    };
+
+    // Continuing responses cut off by the output limit is off unless a maximum is set
+    let max_continuations = config
+        .get_param::<u32>("GOOSE_MAX_CONTINUATIONS")
+        .unwrap_or(0);
+    let provider: Arc<dyn Provider> = if max_continuations > 0 {
+        Arc::new(ContinuationProvider::new(provider, max_continuations))
+    } else {
+        provider
+    };
+
+    // Caching responses is off unless a capacity is set
+    let cache_size = config
+        .get_param::<usize>("GOOSE_RESPONSE_CACHE_SIZE")
+        .unwrap_or(0);
+    if cache_size > 0 {
+        return Ok(Arc::new(CachingProvider::new(provider, cache_size)));
     }
-
-    // Default: create regular provider
-    create_provider(name, model)
+    Ok(provider)
 }
 
 /// Create a lead/worker provider from environment variables
@@ -136,7 +163,10 @@ fn create_provider(name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>>
         "venice" => Ok(Arc::new(VeniceProvider::from_env(model)?)),
         "snowflake" => Ok(Arc::new(SnowflakeProvider::from_env(model)?)),
         "github_copilot" => Ok(Arc::new(GithubCopilotProvider::from_env(model)?)),
+        "github_models" => Ok(Arc::new(GithubModelsProvider::from_env(model)?)),
         "xai" => Ok(Arc::new(XaiProvider::from_env(model)?)),
+        "fireworks" => Ok(Arc::new(FireworksProvider::from_env(model)?)),
+        "together" => Ok(Arc::new(TogetherProvider::from_env(model)?)),
         _ => Err(anyhow::anyhow!("Unknown provider: {}", name)),
     }
 }
@@ -191,6 +221,7 @@ mod tests {
                         ),
                         annotations: None,
                     })],
+                    extra: Default::default(),
                 },
                 ProviderUsage::new(self.model_config.model_name.clone(), Usage::default()),
             ))
@@ -0,0 +1,88 @@
+use super::openai_compatible::{OpenAiCompatible, OpenAiCompatibleProvider};
+
+/// Fireworks names its serverless models `accounts/fireworks/models/<model>`
+const FIREWORKS_MODEL_PREFIX: &str = "accounts/fireworks/models/";
+
+/// Models that call tools natively, https://docs.fireworks.ai/guides/function-calling
+const FIREWORKS_TOOL_MODELS: &[&str] = &[
+    "firefunction",
+    "llama-v3p1-405b",
+    "llama-v3p1-70b",
+    "llama-v3p3-70b",
+    "llama4",
+    "qwen2p5-72b",
+    "qwen3",
+    "deepseek-v3",
+    "kimi-k2",
+];
+
+pub struct Fireworks;
+
+impl OpenAiCompatible for Fireworks {
+    const NAME: &'static str = "fireworks";
+    const DISPLAY_NAME: &'static str = "Fireworks";
+    const DESCRIPTION: &'static str = "Open models served by Fireworks AI";
+    const HOST: &'static str = "https://api.fireworks.ai/inference/v1";
+    const API_KEY: &'static str = "FIREWORKS_API_KEY";
+    const HOST_KEY: &'static str = "FIREWORKS_HOST";
+    const DEFAULT_MODEL: &'static str = "accounts/fireworks/models/llama-v3p3-70b-instruct";
+    const KNOWN_MODELS: &'static [&'static str] = &[
+        "accounts/fireworks/models/llama-v3p3-70b-instruct",
+        "accounts/fireworks/models/llama-v3p1-405b-instruct",
+        "accounts/fireworks/models/llama-v3p1-8b-instruct",
+        "accounts/fireworks/models/qwen2p5-72b-instruct",
+        "accounts/fireworks/models/deepseek-v3",
+        "accounts/fireworks/models/firefunction-v2",
+    ];
+    const DOC_URL: &'static str = "https://fireworks.ai/models";
+    const CONTEXT_LIMIT: usize = 32_768;
+
+    /// Short names like `llama-v3p3-70b-instruct` are the serverless models
+    fn model_name(model: &str) -> String {
+        if model.contains('/') {
+            model.to_string()
+        } else {
+            format!("{}{}", FIREWORKS_MODEL_PREFIX, model)
+        }
+    }
+
+    fn supports_tools(model: &str) -> bool {
+        FIREWORKS_TOOL_MODELS
+            .iter()
+            .any(|pattern| model.contains(pattern))
+    }
+}
+
+pub type FireworksProvider = OpenAiCompatibleProvider<Fireworks>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ModelConfig;
+
+    #[test]
+    fn test_fireworks_models() {
+        assert_eq!(
+            Fireworks::model_name("llama-v3p1-8b-instruct"),
+            "accounts/fireworks/models/llama-v3p1-8b-instruct"
+        );
+        assert_eq!(
+            Fireworks::model_name("accounts/acme/models/tuned"),
+            "accounts/acme/models/tuned"
+        );
+
+        assert!(Fireworks::supports_tools(Fireworks::DEFAULT_MODEL));
+        assert!(Fireworks::supports_tools(
+            "accounts/fireworks/models/firefunction-v2"
+        ));
+        assert!(!Fireworks::supports_tools(
+            "accounts/fireworks/models/llama-v3p1-8b-instruct"
+        ));
+
+        // Models without native tool calls fall back to the text-based tool interpreter
+        let model =
+            FireworksProvider::configure(ModelConfig::new("llama-v3p1-8b-instruct".to_string()));
+        assert!(model.toolshim);
+        assert_eq!(model.context_limit(), 131_072);
+    }
+}
@@ -0,0 +1,297 @@
+//! Role normalization for providers that require user and assistant turns to alternate
+//!
+//! Goose can produce consecutive messages from the same role, for example when a tool result
+//! image is uploaded in a follow-up user message, or when the user interjects while a tool
+//! runs. This pass works on converted payload messages in either the OpenAI or the Anthropic
+//! shape, where `content` is a string or a list of parts, or on the messages themselves for
+//! providers that don't convert them to JSON.
+
+use mcp_core::role::Role;
+use serde_json::{json, Value};
+
+use crate::message::Message;
+
+/// The user turn added before a conversation that would otherwise start with the assistant
+pub const PLACEHOLDER_USER_TEXT: &str = "Continue.";
+
+/// Merge consecutive user or assistant messages, and start the conversation with a user turn
+///
+/// Merged messages keep the content parts and tool calls of both, in order. System and tool
+/// messages are never merged, as tool messages each answer their own tool call.
+pub fn enforce_alternation(messages: &mut Vec<Value>) {
+    let mut normalized: Vec<Value> = Vec::with_capacity(messages.len());
+    for message in messages.drain(..) {
+        match normalized.last_mut() {
+            Some(previous) if mergeable(previous, &message) => merge(previous, message),
+            _ => normalized.push(message),
+        }
+    }
+
+    let first_turn = normalized
+        .iter()
+        .position(|message| !matches!(role(message), "system" | "developer"));
+    if let Some(index) = first_turn {
+        if role(&normalized[index]) == "assistant" {
+            normalized.insert(
+                index,
+                json!({ "role": "user", "content": PLACEHOLDER_USER_TEXT }),
+            );
+        }
+    }
+
+    *messages = normalized;
+}
+
+/// Merge consecutive messages of the same role, and start the conversation with a user turn,
+/// as [`enforce_alternation`] does for converted messages
+pub fn alternate_messages(messages: &[Message]) -> Vec<Message> {
+    let mut alternated: Vec<Message> = Vec::with_capacity(messages.len());
+    for message in messages {
+        match alternated.last_mut() {
+            Some(previous) if previous.role == message.role => {
+                previous.content.extend(message.content.iter().cloned());
+            }
+            _ => alternated.push(message.clone()),
+        }
+    }
+    if alternated
+        .first()
+        .is_some_and(|message| message.role == Role::Assistant)
+    {
+        alternated.insert(0, Message::user().with_text(PLACEHOLDER_USER_TEXT));
+    }
+    alternated
+}
+
+fn role(message: &Value) -> &str {
+    message.get("role").and_then(Value::as_str).unwrap_or("")
+}
+
+fn mergeable(previous: &Value, message: &Value) -> bool {
+    matches!(role(message), "user" | "assistant") && role(message) == role(previous)
+}
+
+fn merge(into: &mut Value, from: Value) {
+    let Value::Object(mut from) = from else {
+        return;
+    };
+
+    let content = match (
+        into.get_mut("content").map(Value::take),
+        from.remove("content"),
+    ) {
+        (Some(Value::String(first)), Some(Value::String(second))) => {
+            Some(Value::String(format!("{}\n\n{}", first, second)))
+        }
+        (Some(first), Some(second)) => {
+            let mut parts = content_parts(first);
+            parts.extend(content_parts(second));
+            Some(Value::Array(parts))
+        }
+        (first, second) => first.or(second),
+    };
+    if let Some(content) = content {
+        into["content"] = content;
+    }
+
+    if let Some(Value::Array(calls)) = from.remove("tool_calls") {
+        match into.get_mut("tool_calls").and_then(Value::as_array_mut) {
+            Some(existing) => existing.extend(calls),
+            None => into["tool_calls"] = Value::Array(calls),
+        }
+    }
+}
+
+fn content_parts(content: Value) -> Vec<Value> {
+    match content {
+        Value::Array(parts) => parts,
+        Value::String(text) => vec![json!({ "type": "text", "text": text })],
+        Value::Null => vec![],
+        other => vec![other],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+    use crate::model::ModelConfig;
+    use crate::providers::formats::{anthropic, openai};
+    use crate::providers::utils::ImageFormat;
+    use mcp_core::tool::ToolCall;
+    use mcp_core::Content;
+
+    const PNG_DATA: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=";
+
+    fn roles(messages: &[Value]) -> Vec<&str> {
+        messages.iter().map(role).collect()
+    }
+
+    fn screenshot_then_interjection() -> Vec<Message> {
+        vec![
+            Message::user().with_text("What is on my screen?"),
+            Message::assistant().with_tool_request(
+                "call_1",
+                Ok(ToolCall::new("developer__screen_capture", json!({}))),
+            ),
+            Message::user().with_tool_response(
+                "call_1",
+                Ok(vec![
+                    Content::text("Captured the screen"),
+                    Content::image(PNG_DATA, "image/png"),
+                ]),
+            ),
+            Message::user().with_text("Focus on the left half"),
+        ]
+    }
+
+    #[test]
+    fn test_merges_split_tool_image_with_next_user_message() {
+        let mut messages =
+            openai::format_messages(&screenshot_then_interjection(), &ImageFormat::OpenAi);
+        assert_eq!(
+            roles(&messages),
+            vec!["user", "assistant", "tool", "user", "user"]
+        );
+
+        enforce_alternation(&mut messages);
+        assert_eq!(roles(&messages), vec!["user", "assistant", "tool", "user"]);
+
+        let parts = messages[3]["content"].as_array().unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0]["type"], "image_url");
+        assert_eq!(
+            parts[1],
+            json!({"type": "text", "text": "Focus on the left half"})
+        );
+        // The tool message still answers its call
+        assert_eq!(messages[2]["tool_call_id"], "call_1");
+    }
+
+    #[test]
+    fn test_keeps_consecutive_split_images_in_order() {
+        let messages = vec![
+            Message::user().with_text("Compare these"),
+            Message::assistant()
+                .with_tool_request("call_1", Ok(ToolCall::new("screenshot", json!({}))))
+                .with_tool_request("call_2", Ok(ToolCall::new("screenshot", json!({})))),
+            Message::user()
+                .with_tool_response("call_1", Ok(vec![Content::image(PNG_DATA, "image/png")]))
+                .with_tool_response("call_2", Ok(vec![Content::image(PNG_DATA, "image/jpeg")])),
+        ];
+        let mut messages = openai::format_messages(&messages, &ImageFormat::OpenAi);
+        assert_eq!(
+            roles(&messages),
+            vec!["user", "assistant", "tool", "user", "tool", "user"]
+        );
+
+        // Tool messages separate the image uploads, so nothing is merged
+        let expected = messages.clone();
+        enforce_alternation(&mut messages);
+        assert_eq!(messages, expected);
+    }
+
+    #[test]
+    fn test_merges_text_messages_into_one_string() {
+        let mut messages = vec![
+            json!({"role": "system", "content": "Be brief."}),
+            json!({"role": "user", "content": "hello"}),
+            json!({"role": "user", "content": "are you there?"}),
+        ];
+        enforce_alternation(&mut messages);
+        assert_eq!(
+            messages,
+            vec![
+                json!({"role": "system", "content": "Be brief."}),
+                json!({"role": "user", "content": "hello\n\nare you there?"}),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merges_assistant_tool_calls() {
+        let mut messages = vec![
+            json!({"role": "user", "content": "go"}),
+            json!({"role": "assistant", "content": "First", "tool_calls": [{"id": "1"}]}),
+            json!({"role": "assistant", "tool_calls": [{"id": "2"}]}),
+        ];
+        enforce_alternation(&mut messages);
+        assert_eq!(
+            messages[1],
+            json!({"role": "assistant", "content": "First", "tool_calls": [{"id": "1"}, {"id": "2"}]})
+        );
+    }
+
+    #[test]
+    fn test_placeholder_before_leading_assistant_message() {
+        let mut messages = vec![
+            json!({"role": "system", "content": "Be brief."}),
+            json!({"role": "assistant", "content": "How can I help?"}),
+            json!({"role": "user", "content": "list the files"}),
+        ];
+        enforce_alternation(&mut messages);
+        assert_eq!(
+            roles(&messages),
+            vec!["system", "user", "assistant", "user"]
+        );
+        assert_eq!(messages[1]["content"], PLACEHOLDER_USER_TEXT);
+
+        let mut empty = vec![];
+        enforce_alternation(&mut empty);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_anthropic_payload_alternates() {
+        let messages = vec![
+            Message::assistant().with_text("Hello, how can I help?"),
+            Message::user().with_text("list the files"),
+            Message::user().with_text("and the hidden ones"),
+        ];
+        let model = ModelConfig::new("claude-3-5-sonnet-latest".to_string());
+        let mut payload = anthropic::create_request(&model, "system", &messages, &[]).unwrap();
+        let converted = payload["messages"].as_array_mut().unwrap();
+        enforce_alternation(converted);
+
+        assert_eq!(roles(converted), vec!["user", "assistant", "user"]);
+        let texts: Vec<&str> = converted[2]["content"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|part| part["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(texts, vec!["list the files", "and the hidden ones"]);
+    }
+
+    #[test]
+    fn test_alternate_messages_merges_interjection() {
+        let messages = vec![
+            Message::assistant().with_text("How can I help?"),
+            Message::user().with_text("list the files"),
+            Message::assistant().with_tool_request("call_1", Ok(ToolCall::new("ls", json!({})))),
+            Message::user().with_tool_response("call_1", Ok(vec![Content::text("a.txt")])),
+            Message::user().with_text("and the hidden ones"),
+        ];
+        let alternated = alternate_messages(&messages);
+
+        let roles: Vec<Role> = alternated.iter().map(|m| m.role.clone()).collect();
+        assert_eq!(
+            roles,
+            vec![
+                Role::User,
+                Role::Assistant,
+                Role::User,
+                Role::Assistant,
+                Role::User
+            ]
+        );
+        assert_eq!(alternated[0].as_concat_text(), PLACEHOLDER_USER_TEXT);
+        // The interjection follows the tool result it was sent after
+        assert_eq!(alternated[4].content.len(), 2);
+        assert!(alternated[4].content[0].as_tool_response().is_some());
+        assert_eq!(
+            alternated[4].content[1].as_text(),
+            Some("and the hidden ones")
+        );
+    }
+}
@@ -1,13 +1,23 @@
-use crate::message::{Message, MessageContent};
+use crate::message::{Citation, CitationSource, Message, MessageContent};
 use crate::model::ModelConfig;
-use crate::providers::base::Usage;
+use crate::prompt_template::{system_prompt_parts, SystemBlockKind, SystemPrompt};
+use crate::providers::base::{CompletionOptions, FinishReason, Usage};
+use crate::providers::capabilities::validate_capabilities;
 use crate::providers::errors::ProviderError;
+use crate::providers::formats::payload_limits::{check_request_size, limit_images};
+use crate::providers::formats::tool_schema::minify_tools;
+use crate::providers::utils::validate_temperature;
+use crate::providers::validation::assistant_prefill;
 use anyhow::{anyhow, Result};
 use mcp_core::content::Content;
 use mcp_core::role::Role;
 use mcp_core::tool::{Tool, ToolCall};
-use serde_json::{json, Value};
+use serde_json::{json, Map, Value};
 use std::collections::HashSet;
+use std::ops::RangeInclusive;
+
+/// https://docs.anthropic.com/en/api/messages#body-temperature
+const TEMPERATURE_RANGE: RangeInclusive<f32> = 0.0..=1.0;
 
 /// Convert internal Message format to Anthropic's API message specification
 pub fn format_messages(messages: &[Message]) -> Vec<Value> {
@@ -45,6 +55,7 @@ pub fn format_messages(messages: &[Message]) -> Vec<Value> {
                             .iter()
                             .filter_map(|c| match c {
                                 Content::Text(t) => Some(t.text.clone()),
+                                Content::Result(r) => Some(r.text.clone()),
                                 _ => None,
                             })
                             .collect::<Vec<_>>()
@@ -66,6 +77,12 @@ pub fn format_messages(messages: &[Message]) -> Vec<Value> {
                 MessageContent::SummarizationRequested(_) => {
                     // Skip
                 }
+                MessageContent::Citations(_) => {
+                    // Skip, citations are only shown to the user
+                }
+                // Thinking from other providers, such as reasoning summaries, has no signature
+                // and would be rejected
+                MessageContent::Thinking(thinking) if thinking.signature.is_empty() => {}
                 MessageContent::Thinking(thinking) => {
                     content.push(json!({
                         "type": "thinking",
@@ -168,12 +185,45 @@ pub fn format_tools(tools: &[Tool]) -> Vec<Value> {
 }
 
 /// Convert system message to Anthropic's API system specification
+///
+/// A `SystemPrompt::encode`d message becomes one block per block of the prompt, with the cache
+/// breakpoint on the last of the stable blocks it starts with, so changes to the dynamic blocks
+/// don't invalidate the cached prefix. Any other message is a single cached block.
 pub fn format_system(system: &str) -> Value {
-    json!([{
-        "type": "text",
-        "text": system,
-        "cache_control": { "type": "ephemeral" }
-    }])
+    let prompt = SystemPrompt::decode(system);
+    let mut blocks: Vec<Value> = prompt
+        .blocks()
+        .iter()
+        .map(|block| json!({"type": "text", "text": block.text}))
+        .collect();
+    let stable = prompt
+        .blocks()
+        .iter()
+        .take_while(|block| block.kind == SystemBlockKind::Stable)
+        .count();
+    if let Some(block) = stable.checked_sub(1).and_then(|last| blocks.get_mut(last)) {
+        block["cache_control"] = json!({ "type": "ephemeral" });
+    }
+    json!(blocks)
+}
+
+/// The parts of a system prompt as separate blocks of Anthropic's system array, without
+/// repeats. The cache breakpoint is on the last block, so it covers all of them.
+pub fn format_system_parts(parts: &[String]) -> Value {
+    let parts = system_prompt_parts(parts);
+    let last = parts.len().saturating_sub(1);
+    let blocks: Vec<Value> = parts
+        .into_iter()
+        .enumerate()
+        .map(|(index, text)| {
+            let mut block = json!({"type": "text", "text": text});
+            if index == last {
+                block["cache_control"] = json!({ "type": "ephemeral" });
+            }
+            block
+        })
+        .collect();
+    json!(blocks)
 }
 
 /// Convert Anthropic's API response to internal Message format
@@ -184,12 +234,24 @@ pub fn response_to_message(response: Value) -> Result<Message> {
         .ok_or_else(|| anyhow!("Invalid response format: missing content array"))?;
 
     let mut message = Message::assistant();
+    // Cited text arrives in blocks of its own, between the blocks of the text around it, so
+    // consecutive text blocks are collected and merged
+    let mut text_run = Message::assistant();
 
     for block in content_blocks {
-        match block.get("type").and_then(|t| t.as_str()) {
+        let block_type = block.get("type").and_then(|t| t.as_str());
+        if block_type != Some("text") && !text_run.content.is_empty() {
+            let run = std::mem::replace(&mut text_run, Message::assistant()).coalesce_text();
+            message.content.extend(run.content);
+        }
+        match block_type {
             Some("text") => {
                 if let Some(text) = block.get("text").and_then(|t| t.as_str()) {
-                    message = message.with_text(text.to_string());
+                    text_run = text_run.with_text(text.to_string());
+                    let citations = text_block_citations(text, block);
+                    if !citations.is_empty() {
+                        text_run = text_run.with_citations(citations);
+                    }
                 }
             }
             Some("tool_use") => {
@@ -229,10 +291,104 @@ pub fn response_to_message(response: Value) -> Result<Message> {
             _ => continue,
         }
     }
+    message.content.extend(text_run.coalesce_text().content);
 
     Ok(message)
 }
 
+/// Citations of a text block, each of which cites the whole block
+fn text_block_citations(text: &str, block: &Value) -> Vec<Citation> {
+    let citations = block.get("citations").and_then(|c| c.as_array());
+    citations
+        .into_iter()
+        .flatten()
+        .map(|citation| {
+            let field = |key: &str| citation.get(key).and_then(|v| v.as_str()).map(String::from);
+            Citation {
+                start: 0,
+                end: text.len(),
+                source: CitationSource {
+                    url: field("url"),
+                    title: field("title").or_else(|| field("document_title")),
+                    cited_text: field("cited_text"),
+                },
+            }
+        })
+        .collect()
+}
+
+/// Prefill the assistant response by ending the conversation with a partial assistant message
+/// https://docs.anthropic.com/en/docs/build-with-claude/prompt-engineering/prefill-claudes-response
+///
+/// A conversation that already ends with a prefill is extended, as the API rejects two
+/// assistant messages in a row.
+pub fn add_prefill(payload: &mut Value, prefill: &str) {
+    // The API rejects a final assistant message that ends with whitespace
+    let prefill = prefill.trim_end();
+    if prefill.is_empty() {
+        return;
+    }
+    let block = json!({"type": "text", "text": prefill});
+    if let Some(messages) = payload.get_mut("messages").and_then(Value::as_array_mut) {
+        match messages.last_mut() {
+            Some(last) if last["role"] == "assistant" => match last["content"].as_array_mut() {
+                Some(content) => content.push(block),
+                None => last["content"] = json!([block]),
+            },
+            _ => messages.push(json!({"role": "assistant", "content": [block]})),
+        }
+    }
+}
+
+/// Forbid tool calls in the response, if the options do. Without tools there is nothing to
+/// forbid.
+pub fn add_tool_choice(payload: &mut Value, options: &CompletionOptions) {
+    if options.no_tool_calls && payload.get("tools").is_some() {
+        payload["tool_choice"] = json!({"type": "none"});
+    }
+}
+
+/// Add the prefill back to a response, which only contains what the model wrote after it
+pub fn prepend_prefill(mut message: Message, prefill: &str) -> Message {
+    let prefill = prefill.trim_end();
+    if prefill.is_empty() {
+        return message;
+    }
+    let position = message
+        .content
+        .iter()
+        .position(|content| {
+            !matches!(
+                content,
+                MessageContent::Thinking(_) | MessageContent::RedactedThinking(_)
+            )
+        })
+        .unwrap_or(message.content.len());
+    match message.content.get_mut(position) {
+        Some(MessageContent::Text(text)) => text.text = format!("{}{}", prefill, text.text),
+        _ => message
+            .content
+            .insert(position, MessageContent::text(prefill)),
+    }
+    message
+}
+
+/// Why the response stopped, none if the response does not say
+pub fn get_finish_reason(response: &Value) -> Option<FinishReason> {
+    response["stop_reason"].as_str().map(parse_stop_reason)
+}
+
+/// Normalize an Anthropic `stop_reason`
+pub fn parse_stop_reason(reason: &str) -> FinishReason {
+    match reason {
+        "end_turn" | "stop_sequence" => FinishReason::Stop,
+        "max_tokens" => FinishReason::Length,
+        "tool_use" => FinishReason::ToolCalls,
+        "refusal" => FinishReason::ContentFilter,
+        other => FinishReason::Other(other.to_string()),
+    }
+}
+
 /// Extract usage information from Anthropic's API response
 pub fn get_usage(data: &Value) -> Result<Usage> {
     // Extract usage data if available
@@ -274,6 +430,32 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
     }
 }
 
+/// Anthropic only accepts a `user_id` string in request metadata, and rejects other keys
+fn validate_metadata(metadata: &Map<String, Value>) -> Result<(), ProviderError> {
+    for (key, value) in metadata {
+        if key != "user_id" {
+            return Err(ProviderError::RequestFailed(format!(
+                "Anthropic request metadata only accepts user_id, not '{}'",
+                key
+            )));
+        }
+        if !value.is_string() {
+            return Err(ProviderError::RequestFailed(format!(
+                "Anthropic request metadata user_id must be a string, got {}",
+                value
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Whether requests for the model use extended thinking, which claude-3-7-sonnet models do when
+/// CLAUDE_THINKING_ENABLED is set
+pub fn thinking_enabled(model_config: &ModelConfig) -> bool {
+    model_config.model_name.starts_with("claude-3-7-sonnet-")
+        && std::env::var("CLAUDE_THINKING_ENABLED").is_ok()
+}
+
 /// Create a complete request payload for Anthropic's API
 pub fn create_request(
     model_config: &ModelConfig,
@@ -281,12 +463,23 @@ pub fn create_request(
     messages: &[Message],
     tools: &[Tool],
 ) -> Result<Value> {
-    let anthropic_messages = format_messages(messages);
-    let tool_specs = format_tools(tools);
+    validate_capabilities(model_config, messages, tools)?;
+    let messages = &limit_images(model_config, messages)[..];
+
+    let mut anthropic_messages = format_messages(messages);
+    let verbosity = model_config.tool_schema_verbosity.for_turn(messages);
+    let tool_specs = format_tools(&minify_tools(tools, verbosity));
     let system_spec = format_system(system);
 
+    // A final assistant message that only holds text is the start of the response, which is
+    // sent as a prefill so it follows the same whitespace rule
+    let prefill = assistant_prefill(messages);
+    if prefill.is_some() {
+        anthropic_messages.pop();
+    }
+
     // Check if we have any messages to send
-    if anthropic_messages.is_empty() {
+    if anthropic_messages.is_empty() && prefill.is_none() {
         return Err(anyhow!("No valid messages to send to Anthropic API"));
     }
 
@@ -315,10 +508,10 @@ pub fn create_request(
             .insert("tools".to_string(), json!(tool_specs));
     }
 
-    // Add temperature if specified and not using extended thinking model
-    if let Some(temp) = model_config.temperature {
-        // Claude 3.7 models with thinking enabled don't support temperature
-        if !model_config.model_name.starts_with("claude-3-7-sonnet-") {
+    // Add temperature if specified and not using extended thinking model, as Claude 3.7
+    // models with thinking enabled don't support temperature
+    if !model_config.model_name.starts_with("claude-3-7-sonnet-") {
+        if let Some(temp) = validate_temperature(model_config, TEMPERATURE_RANGE)? {
             payload
                 .as_object_mut()
                 .unwrap()
@@ -326,9 +519,17 @@ pub fn create_request(
         }
     }
 
+    // Anthropic takes request metadata, such as a user_id, as a top-level object
+    if let Some(metadata) = &model_config.metadata {
+        validate_metadata(metadata)?;
+        payload
+            .as_object_mut()
+            .unwrap()
+            .insert("metadata".to_string(), json!(metadata));
+    }
+
     // Add thinking parameters for claude-3-7-sonnet model
-    let is_thinking_enabled = std::env::var("CLAUDE_THINKING_ENABLED").is_ok();
-    if model_config.model_name.starts_with("claude-3-7-sonnet-") && is_thinking_enabled {
+    if thinking_enabled(model_config) {
         // Minimum budget_tokens is 1024
         let budget_tokens = std::env::var("CLAUDE_THINKING_BUDGET")
             .unwrap_or_else(|_| "16000".to_string())
@@ -349,6 +550,11 @@ pub fn create_request(
         );
     }
 
+    if let Some(prefill) = &prefill {
+        add_prefill(&mut payload, prefill);
+    }
+
+    check_request_size(model_config, &payload)?;
     Ok(payload)
 }
 
@@ -437,6 +643,20 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_format_messages_skips_unsigned_thinking() {
+        let messages = vec![
+            Message::user().with_text("Hello"),
+            Message::assistant()
+                .with_thinking("A summary of the reasoning", "")
+                .with_text("Hi"),
+        ];
+        let formatted = format_messages(&messages);
+        let content = formatted[1]["content"].as_array().unwrap();
+        assert_eq!(content.len(), 1);
+        assert_eq!(content[0]["type"], "text");
+    }
+
     #[test]
     fn test_parse_thinking_response() -> Result<()> {
         let response = json!({
@@ -511,6 +731,55 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_thinking_round_trip() -> Result<()> {
+        let content = json!([
+            {
+                "type": "thinking",
+                "thinking": "I should list the files first.",
+                "signature": "EuYBCkQYAiJAVbJNBoH7HQiDcMwwAMhWqNyoe4G2xHRprK8ICM8g"
+            },
+            {
+                "type": "redacted_thinking",
+                "data": "EmwKAhgBEgy3va3pzix/LafPsn4aDFIT2Xlxh0L5L8rLVyIwxtE3rAFBa8cr3qpP"
+            },
+            {
+                "type": "text",
+                "text": "Let me look."
+            },
+            {
+                "type": "tool_use",
+                "id": "toolu_01",
+                "name": "developer__shell",
+                "input": {"command": "ls"}
+            }
+        ]);
+        let response = json!({
+            "id": "msg_789",
+            "type": "message",
+            "role": "assistant",
+            "content": content,
+            "model": "claude-3-7-sonnet-20250219",
+            "stop_reason": "tool_use",
+        });
+
+        // The message is stored with the session before it is sent back
+        let message = response_to_message(response)?;
+        let message: Message = serde_json::from_value(serde_json::to_value(&message)?)?;
+
+        let messages = vec![
+            Message::user().with_text("What's in this directory?"),
+            message,
+            Message::user().with_tool_response("toolu_01", Ok(vec![Content::text("README.md")])),
+        ];
+        let spec = format_messages(&messages);
+
+        assert_eq!(spec[1]["role"], "assistant");
+        assert_eq!(spec[1]["content"], content);
+        assert_eq!(spec[2]["content"][0]["tool_use_id"], "toolu_01");
+        Ok(())
+    }
+
     #[test]
     fn test_message_to_anthropic_spec() {
         let messages = vec![
@@ -589,6 +858,171 @@ mod tests {
         assert!(spec_array[0].get("cache_control").is_some());
     }
 
+    #[test]
+    fn test_system_blocks_to_anthropic_spec() {
+        let mut prompt = SystemPrompt::new();
+        prompt.push_stable("You are a helpful assistant.");
+        prompt.push_stable("# Additional Instructions:\n\nBe brief.");
+        prompt.push_dynamic("# Workspace\n\nOn branch main");
+
+        // The breakpoint is on the last stable block, the workspace can change without a miss
+        assert_eq!(
+            format_system(&prompt.encode()),
+            json!([
+                {"type": "text", "text": "You are a helpful assistant."},
+                {
+                    "type": "text",
+                    "text": "# Additional Instructions:\n\nBe brief.",
+                    "cache_control": { "type": "ephemeral" }
+                },
+                {"type": "text", "text": "# Workspace\n\nOn branch main"}
+            ])
+        );
+
+        let mut prompt = SystemPrompt::new();
+        prompt.push_dynamic("# Workspace\n\nOn branch main");
+        prompt.push_stable("You are a helpful assistant.");
+        assert!(format_system(&prompt.encode())
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|block| block.get("cache_control").is_none()));
+    }
+
+    #[test]
+    fn test_system_parts_to_anthropic_spec() {
+        let parts = vec![
+            "You are a helpful assistant.".to_string(),
+            "Use the developer tools.".to_string(),
+            "You are a helpful assistant.".to_string(),
+        ];
+        let spec = format_system_parts(&parts);
+
+        assert_eq!(
+            spec,
+            json!([
+                {"type": "text", "text": "You are a helpful assistant."},
+                {
+                    "type": "text",
+                    "text": "Use the developer tools.",
+                    "cache_control": { "type": "ephemeral" }
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_create_request_with_metadata() -> Result<()> {
+        let metadata = json!({"user_id": "user-1234"});
+        let model_config = ModelConfig::new("claude-3-5-sonnet-latest".to_string())
+            .with_metadata(metadata.as_object().cloned());
+        let messages = vec![Message::user().with_text("Hello")];
+
+        let payload = create_request(&model_config, "system", &messages, &[])?;
+        assert_eq!(payload["metadata"], metadata);
+
+        let model_config = ModelConfig::new("claude-3-5-sonnet-latest".to_string());
+        let payload = create_request(&model_config, "system", &messages, &[])?;
+        assert!(payload.get("metadata").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_request_rejects_unsupported_metadata() {
+        let messages = vec![Message::user().with_text("Hello")];
+        let rejection = |metadata: Value| {
+            let model_config = ModelConfig::new("claude-3-5-sonnet-latest".to_string())
+                .with_metadata(metadata.as_object().cloned());
+            let error = create_request(&model_config, "system", &messages, &[]).unwrap_err();
+            ProviderError::from(error).to_string()
+        };
+
+        assert_eq!(
+            rejection(json!({"session_id": "abc123"})),
+            "Request failed: Anthropic request metadata only accepts user_id, not 'session_id'"
+        );
+        assert_eq!(
+            rejection(json!({"user_id": 1234})),
+            "Request failed: Anthropic request metadata user_id must be a string, got 1234"
+        );
+    }
+
+    #[test]
+    fn test_prefill_round_trip() -> Result<()> {
+        let model_config = ModelConfig::new("claude-3-5-sonnet-latest".to_string());
+        let messages = vec![Message::user().with_text("Reply in JSON")];
+        let mut payload = create_request(&model_config, "system", &messages, &[])?;
+
+        add_prefill(&mut payload, "{\n");
+        assert_eq!(payload["messages"][1]["role"], "assistant");
+        assert_eq!(payload["messages"][1]["content"][0]["text"], "{");
+        // Tool calls are only forbidden when there are tools to call
+        let no_tool_calls = CompletionOptions::default().with_no_tool_calls();
+        add_tool_choice(&mut payload, &no_tool_calls);
+        assert!(payload.get("tool_choice").is_none());
+        payload["tools"] = json!([]);
+        add_tool_choice(&mut payload, &no_tool_calls);
+        assert_eq!(payload["tool_choice"], json!({"type": "none"}));
+
+        let response = response_to_message(json!({
+            "content": [{"type": "text", "text": "\"ok\": true}"}]
+        }))?;
+        let message = prepend_prefill(response, "{\n");
+        assert_eq!(message.as_concat_text(), "{\"ok\": true}");
+
+        // Thinking stays first, the prefill goes before the text that continues it
+        let message = prepend_prefill(
+            Message::assistant()
+                .with_thinking("Let me think", "sig")
+                .with_tool_request("1", Ok(ToolCall::new("shell", json!({})))),
+            "{",
+        );
+        assert!(matches!(message.content[0], MessageContent::Thinking(_)));
+        assert_eq!(message.content[1].as_text(), Some("{"));
+        assert_eq!(message.content.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_trailing_assistant_message_is_prefill() -> Result<()> {
+        let model_config = ModelConfig::new("claude-3-5-sonnet-latest".to_string());
+        let messages = vec![
+            Message::user().with_text("Reply in JSON"),
+            Message::assistant().with_text("Here is the JSON:\n"),
+        ];
+        let mut payload = create_request(&model_config, "system", &messages, &[])?;
+        let sent = payload["messages"].as_array().unwrap();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(
+            sent[1],
+            json!({
+                "role": "assistant",
+                "content": [{"type": "text", "text": "Here is the JSON:"}]
+            })
+        );
+
+        // A prefill of the options continues it instead of adding another assistant turn
+        add_prefill(&mut payload, "{");
+        assert_eq!(payload["messages"].as_array().unwrap().len(), 2);
+        assert_eq!(payload["messages"][1]["content"][1]["text"], "{");
+
+        // A trailing tool request is a finished turn and is sent as it is
+        let messages = vec![
+            Message::user().with_text("list the files"),
+            Message::assistant()
+                .with_text("Listing them ")
+                .with_tool_request("1", Ok(ToolCall::new("shell", json!({})))),
+        ];
+        let payload = create_request(&model_config, "system", &messages, &[])?;
+        assert_eq!(
+            payload["messages"][1]["content"][0]["text"],
+            "Listing them "
+        );
+        assert_eq!(payload["messages"][1]["content"][1]["type"], "tool_use");
+        Ok(())
+    }
+
     #[test]
     fn test_create_request_with_thinking() -> Result<()> {
         // Save the original env var value if it exists
@@ -631,4 +1065,21 @@ mod tests {
         // Return the test result
         result
     }
+
+    #[test]
+    fn test_stop_reasons() {
+        assert_eq!(parse_stop_reason("end_turn"), FinishReason::Stop);
+        assert_eq!(parse_stop_reason("stop_sequence"), FinishReason::Stop);
+        assert_eq!(parse_stop_reason("max_tokens"), FinishReason::Length);
+        assert_eq!(parse_stop_reason("tool_use"), FinishReason::ToolCalls);
+        assert_eq!(parse_stop_reason("refusal"), FinishReason::ContentFilter);
+        assert_eq!(
+            parse_stop_reason("pause_turn"),
+            FinishReason::Other("pause_turn".to_string())
+        );
+
+        let response = json!({"stop_reason": "max_tokens"});
+        assert_eq!(get_finish_reason(&response), Some(FinishReason::Length));
+        assert_eq!(get_finish_reason(&json!({})), None);
+    }
 }
@@ -4,11 +4,10 @@ use std::path::Path;
 use anyhow::{anyhow, bail, Result};
 use aws_sdk_bedrockruntime::types as bedrock;
 use aws_smithy_types::{Document, Number};
-use chrono::Utc;
 use mcp_core::{Content, ResourceContents, Role, Tool, ToolCall, ToolError, ToolResult};
 use serde_json::Value;
 
-use super::super::base::Usage;
+use super::super::base::{FinishReason, Usage};
 use crate::message::{Message, MessageContent};
 
 pub fn to_bedrock_message(message: &Message) -> Result<bedrock::Message> {
@@ -48,6 +47,10 @@ pub fn to_bedrock_message_content(content: &MessageContent) -> Result<bedrock::C
         MessageContent::SummarizationRequested(_) => {
             bail!("SummarizationRequested should not get passed to the provider")
         }
+        MessageContent::Citations(_) => {
+            // Citations are only shown to the user - skip
+            bedrock::ContentBlock::Text("".to_string())
+        }
         MessageContent::ToolRequest(tool_req) => {
             let tool_use_id = tool_req.id.to_string();
             let tool_use = if let Ok(call) = tool_req.tool_call.as_ref() {
@@ -114,6 +117,7 @@ pub fn to_bedrock_tool_result_content_block(
 ) -> Result<bedrock::ToolResultContentBlock> {
     Ok(match content {
         Content::Text(text) => bedrock::ToolResultContentBlock::Text(text.text.to_string()),
+        Content::Result(result) => bedrock::ToolResultContentBlock::Text(result.text.to_string()),
         Content::Image(_) => bail!("Image content is not supported by Bedrock provider yet"),
         Content::Resource(resource) => match &resource.resource {
             ResourceContents::TextResourceContents { text, .. } => {
@@ -227,12 +231,13 @@ pub fn from_bedrock_message(message: &bedrock::Message) -> Result<Message> {
         .iter()
         .map(from_bedrock_content_block)
         .collect::<Result<Vec<_>>>()?;
-    let created = Utc::now().timestamp();
+    let created = crate::determinism::now().timestamp();
 
     Ok(Message {
         role,
         content,
         created,
+        extra: Default::default(),
     })
 }
 
@@ -293,6 +298,16 @@ pub fn from_bedrock_usage(usage: &bedrock::TokenUsage) -> Usage {
     }
 }
 
+pub fn from_bedrock_stop_reason(reason: &bedrock::StopReason) -> FinishReason {
+    match reason.as_str() {
+        "end_turn" | "stop_sequence" => FinishReason::Stop,
+        "max_tokens" => FinishReason::Length,
+        "tool_use" => FinishReason::ToolCalls,
+        "guardrail_intervened" | "content_filtered" => FinishReason::ContentFilter,
+        other => FinishReason::Other(other.to_string()),
+    }
+}
+
 pub fn from_bedrock_json(document: &Document) -> Result<Value> {
     Ok(match document {
         Document::Null => Value::Null,
@@ -315,3 +330,69 @@ pub fn from_bedrock_json(document: &Document) -> Result<Value> {
         ),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::formats::alternation::alternate_messages;
+    use crate::providers::validation::validate_conversation;
+    use serde_json::json;
+
+    #[test]
+    fn test_stop_reasons() {
+        let reason = |reason: bedrock::StopReason| from_bedrock_stop_reason(&reason);
+        assert_eq!(reason(bedrock::StopReason::EndTurn), FinishReason::Stop);
+        assert_eq!(
+            reason(bedrock::StopReason::StopSequence),
+            FinishReason::Stop
+        );
+        assert_eq!(reason(bedrock::StopReason::MaxTokens), FinishReason::Length);
+        assert_eq!(
+            reason(bedrock::StopReason::ToolUse),
+            FinishReason::ToolCalls
+        );
+        assert_eq!(
+            reason(bedrock::StopReason::GuardrailIntervened),
+            FinishReason::ContentFilter
+        );
+        assert_eq!(
+            reason(bedrock::StopReason::ContentFiltered),
+            FinishReason::ContentFilter
+        );
+        assert_eq!(
+            reason(bedrock::StopReason::from("model_context_window_exceeded")),
+            FinishReason::Other("model_context_window_exceeded".to_string())
+        );
+    }
+
+    #[test]
+    fn test_interjection_after_tool_result() {
+        let messages = vec![
+            Message::user().with_text("list the files"),
+            Message::assistant().with_tool_request("call_1", Ok(ToolCall::new("ls", json!({})))),
+            Message::user().with_tool_response("call_1", Ok(vec![Content::text("a.txt")])),
+            Message::user().with_text("and the hidden ones"),
+        ];
+        assert_eq!(validate_conversation(&messages, "aws_bedrock"), Ok(()));
+
+        let converted = alternate_messages(&messages)
+            .iter()
+            .map(to_bedrock_message)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        let roles: Vec<&bedrock::ConversationRole> = converted.iter().map(|m| m.role()).collect();
+        assert_eq!(
+            roles,
+            vec![
+                &bedrock::ConversationRole::User,
+                &bedrock::ConversationRole::Assistant,
+                &bedrock::ConversationRole::User
+            ]
+        );
+        let last = converted[2].content();
+        assert!(matches!(last[0], bedrock::ContentBlock::ToolResult(_)));
+        assert!(
+            matches!(&last[1], bedrock::ContentBlock::Text(text) if text == "and the hidden ones")
+        );
+    }
+}
@@ -2,20 +2,36 @@ use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
 use crate::providers::base::Usage;
 use crate::providers::errors::ProviderError;
+use crate::providers::formats::message_split::limit_message_size;
+use crate::providers::tool_call_ids::UuidToolCallIds;
 use crate::providers::utils::{
     convert_image, detect_image_path, is_valid_function_name, load_image_file,
-    sanitize_function_name, ImageFormat,
+    sanitize_function_name, tool_call_id, validate_message_images, validate_temperature,
+    ImageFormat, ToolContentSeparator,
 };
 use anyhow::{anyhow, Error};
 use mcp_core::ToolError;
 use mcp_core::{Content, Role, Tool, ToolCall};
 use serde_json::{json, Value};
+use std::ops::RangeInclusive;
+
+/// Databricks serves OpenAI compatible endpoints, which accept the OpenAI range
+const TEMPERATURE_RANGE: RangeInclusive<f32> = 0.0..=2.0;
 
 /// Convert internal Message format to Databricks' API message specification
 ///   Databricks is mostly OpenAI compatible, but has some differences (reasoning type, etc)
 ///   some openai compatible endpoints use the anthropic image spec at the content level
 ///   even though the message structure is otherwise following openai, the enum switches this
 pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<Value> {
+    format_messages_with_separator(messages, image_format, ToolContentSeparator::default())
+}
+
+/// Like [`format_messages`], joining the text blocks of tool results with `separator`
+pub fn format_messages_with_separator(
+    messages: &[Message],
+    image_format: &ImageFormat,
+    separator: ToolContentSeparator,
+) -> Vec<Value> {
     let mut result = Vec::new();
     for message in messages {
         let mut converted = json!({
@@ -116,6 +132,9 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
                 MessageContent::SummarizationRequested(_) => {
                     continue;
                 }
+                MessageContent::Citations(_) => {
+                    continue;
+                }
                 MessageContent::ToolResponse(response) => {
                     match &response.tool_result {
                         Ok(contents) => {
@@ -154,14 +173,16 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
                                     }
                                 }
                             }
-                            let tool_response_content: Value = json!(tool_content
-                                .iter()
-                                .map(|content| match content {
-                                    Content::Text(text) => text.text.clone(),
-                                    _ => String::new(),
-                                })
-                                .collect::<Vec<String>>()
-                                .join(" "));
+                            let tool_response_content: Value = json!(separator.join(
+                                &tool_content
+                                    .iter()
+                                    .map(|content| match content {
+                                        Content::Text(text) => text.text.clone(),
+                                        Content::Result(result) => result.text.clone(),
+                                        _ => String::new(),
+                                    })
+                                    .collect::<Vec<String>>()
+                            ));
 
                             // Add tool response as a separate message
                             result.push(json!({
@@ -316,8 +337,8 @@ pub fn response_to_message(response: Value) -> anyhow::Result<Message> {
     // Handle tool calls
     if let Some(tool_calls) = original.get("tool_calls") {
         if let Some(tool_calls_array) = tool_calls.as_array() {
-            for tool_call in tool_calls_array {
-                let id = tool_call["id"].as_str().unwrap_or_default().to_string();
+            for (index, tool_call) in tool_calls_array.iter().enumerate() {
+                let id = tool_call_id(&response, tool_call, index, &UuidToolCallIds);
                 let function_name = tool_call["function"]["name"]
                     .as_str()
                     .unwrap_or_default()
@@ -360,8 +381,9 @@ pub fn response_to_message(response: Value) -> anyhow::Result<Message> {
 
     Ok(Message {
         role: Role::Assistant,
-        created: chrono::Utc::now().timestamp(),
+        created: crate::determinism::now().timestamp(),
         content,
+        extra: Default::default(),
     })
 }
 
@@ -451,6 +473,9 @@ pub fn create_request(
         ));
     }
 
+    validate_message_images(messages)?;
+    let messages = &limit_message_size(model_config, messages)[..];
+
     let model_name = model_config.model_name.to_string();
     let is_o1 = model_name.starts_with("o1") || model_name.starts_with("goose-o1");
     let is_o3 = model_name.starts_with("o3") || model_name.starts_with("goose-o3");
@@ -482,7 +507,8 @@ pub fn create_request(
         "content": system
     });
 
-    let messages_spec = format_messages(messages, image_format);
+    let messages_spec =
+        format_messages_with_separator(messages, image_format, model_config.tool_content_separator);
     let mut tools_spec = if !tools.is_empty() {
         format_tools(tools)?
     } else {
@@ -545,9 +571,9 @@ pub fn create_request(
             .unwrap()
             .insert("temperature".to_string(), json!(2));
     } else {
-        // o1, o3 models currently don't support temperature
+        // o1, o3 models currently don't support temperature, so it is dropped before it is checked
         if !is_o1 && !is_o3 {
-            if let Some(temp) = model_config.temperature {
+            if let Some(temp) = validate_temperature(model_config, TEMPERATURE_RANGE)? {
                 payload
                     .as_object_mut()
                     .unwrap()
@@ -777,6 +803,35 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_format_messages_tool_content_separator() {
+        let messages = vec![Message::user().with_tool_response(
+            "tool1",
+            Ok(vec![Content::text("line one"), Content::text("line two")]),
+        )];
+        let tool_content = |separator| {
+            format_messages_with_separator(&messages, &ImageFormat::OpenAi, separator)[0]["content"]
+                .clone()
+        };
+
+        assert_eq!(
+            tool_content(ToolContentSeparator::Space),
+            "line one line two"
+        );
+        assert_eq!(
+            tool_content(ToolContentSeparator::Newline),
+            "line one\nline two"
+        );
+        assert_eq!(
+            tool_content(ToolContentSeparator::JsonArray),
+            r#"["line one","line two"]"#
+        );
+        assert_eq!(
+            format_messages(&messages, &ImageFormat::OpenAi)[0]["content"],
+            "line one\nline two"
+        );
+    }
+
     #[test]
     fn test_format_tools_duplicate() -> anyhow::Result<()> {
         let tool1 = Tool::new(
@@ -978,9 +1033,23 @@ mod tests {
             tokenizer_name: "gpt-4o".to_string(),
             context_limit: Some(4096),
             temperature: None,
+            temperature_policy: Default::default(),
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            legacy_function_calling: false,
+            allow_system_only: false,
+            strict_tools: None,
+            tool_schema_verbosity: Default::default(),
+            tool_content_separator: Default::default(),
+            tool_image_policy: Default::default(),
+            max_tools: None,
+            max_message_bytes: None,
+            max_images: None,
+            max_request_bytes: None,
+            seed: None,
+            metadata: None,
+            no_validate: false,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1010,9 +1079,23 @@ mod tests {
             tokenizer_name: "o1".to_string(),
             context_limit: Some(4096),
             temperature: None,
+            temperature_policy: Default::default(),
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            legacy_function_calling: false,
+            allow_system_only: false,
+            strict_tools: None,
+            tool_schema_verbosity: Default::default(),
+            tool_content_separator: Default::default(),
+            tool_image_policy: Default::default(),
+            max_tools: None,
+            max_message_bytes: None,
+            max_images: None,
+            max_request_bytes: None,
+            seed: None,
+            metadata: None,
+            no_validate: false,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1043,9 +1126,23 @@ mod tests {
             tokenizer_name: "o3-mini".to_string(),
             context_limit: Some(4096),
             temperature: None,
+            temperature_policy: Default::default(),
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            legacy_function_calling: false,
+            allow_system_only: false,
+            strict_tools: None,
+            tool_schema_verbosity: Default::default(),
+            tool_content_separator: Default::default(),
+            tool_image_policy: Default::default(),
+            max_tools: None,
+            max_message_bytes: None,
+            max_images: None,
+            max_request_bytes: None,
+            seed: None,
+            metadata: None,
+            no_validate: false,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1,7 +1,7 @@
 use super::{anthropic, google};
 use crate::message::Message;
 use crate::model::ModelConfig;
-use crate::providers::base::Usage;
+use crate::providers::base::{FinishReason, Usage};
 use anyhow::{Context, Result};
 use mcp_core::tool::Tool;
 use serde_json::Value;
@@ -263,6 +263,10 @@ fn create_anthropic_request(
 
 /// Creates a Gemini-specific Vertex AI request payload.
 ///
+/// The messages and tools are converted with the same code as the API-key Gemini provider, then
+/// adapted to the stricter envelope Vertex AI expects: `systemInstruction` in camel case, `tools`
+/// as a list of tool objects and no contents without parts.
+///
 /// # Arguments
 /// * `model_config` - Configuration for the model
 /// * `system` - System prompt
@@ -277,7 +281,36 @@ fn create_google_request(
     messages: &[Message],
     tools: &[Tool],
 ) -> Result<Value> {
-    google::create_request(model_config, system, messages, tools)
+    let mut request = google::create_request(model_config, system, messages, tools)?;
+
+    let obj = request
+        .as_object_mut()
+        .ok_or_else(|| ModelError::InvalidRequest("Request is not a JSON object".to_string()))?;
+
+    if let Some(system_instruction) = obj.remove("system_instruction") {
+        if !system.is_empty() {
+            obj.insert("systemInstruction".to_string(), system_instruction);
+        }
+    }
+
+    if let Some(tools) = obj.remove("tools") {
+        let tools = match tools {
+            Value::Array(tools) => tools,
+            tool => vec![tool],
+        };
+        obj.insert("tools".to_string(), Value::Array(tools));
+    }
+
+    if let Some(Value::Array(contents)) = obj.get_mut("contents") {
+        contents.retain(|content| {
+            content
+                .get("parts")
+                .and_then(Value::as_array)
+                .is_some_and(|parts| !parts.is_empty())
+        });
+    }
+
+    Ok(request)
 }
 
 /// Creates a provider-specific request payload and context.
@@ -340,6 +373,17 @@ pub fn get_usage(data: &Value, request_context: &RequestContext) -> Result<Usage
     }
 }
 
+/// Why the response stopped, normalized from the reason the model's API reports
+pub fn get_finish_reason(
+    response: &Value,
+    request_context: &RequestContext,
+) -> Option<FinishReason> {
+    match request_context.provider() {
+        ModelProvider::Anthropic => anthropic::get_finish_reason(response),
+        ModelProvider::Google => google::get_finish_reason(response),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -443,4 +487,38 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_google_request_envelope() -> Result<()> {
+        let model_config = ModelConfig::new("gemini-2.0-flash-001".to_string());
+        let messages = vec![
+            Message::user().with_text("Hello"),
+            Message::assistant().with_text(""),
+            Message::user().with_text("Read the file"),
+        ];
+        let tools = vec![Tool::new(
+            "developer__shell",
+            "Run a command",
+            serde_json::json!({"type": "object", "properties": {}}),
+            None,
+        )];
+
+        let (request, context) =
+            create_request(&model_config, "You are helpful", &messages, &tools)?;
+        assert_eq!(context.provider(), ModelProvider::Google);
+
+        assert!(request.get("system_instruction").is_none());
+        assert_eq!(
+            request["systemInstruction"]["parts"][0]["text"],
+            "You are helpful"
+        );
+        let tools = request["tools"].as_array().unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(
+            tools[0]["functionDeclarations"][0]["name"],
+            "developer__shell"
+        );
+        assert_eq!(request["contents"].as_array().unwrap().len(), 2);
+        Ok(())
+    }
 }
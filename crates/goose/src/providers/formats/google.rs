@@ -1,14 +1,20 @@
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
-use crate::providers::base::Usage;
+use crate::providers::base::{FinishReason, Usage};
 use crate::providers::errors::ProviderError;
-use crate::providers::utils::{is_valid_function_name, sanitize_function_name};
+use crate::providers::utils::{
+    is_valid_function_name, normalize_image, sanitize_function_name, validate_temperature,
+};
 use anyhow::Result;
 use mcp_core::content::Content;
 use mcp_core::role::Role;
 use mcp_core::tool::{Tool, ToolCall};
 use rand::{distributions::Alphanumeric, Rng};
 use serde_json::{json, Map, Value};
+use std::ops::RangeInclusive;
+
+/// https://ai.google.dev/api/generate-content#generationconfig
+const TEMPERATURE_RANGE: RangeInclusive<f32> = 0.0..=2.0;
 
 /// Convert internal Message format to Google's API message specification
 pub fn format_messages(messages: &[Message]) -> Vec<Value> {
@@ -73,6 +79,7 @@ pub fn format_messages(messages: &[Message]) -> Vec<Value> {
                                 for content in abridged {
                                     match content {
                                         Content::Image(image) => {
+                                            let image = normalize_image(image);
                                             parts.push(json!({
                                                 "inline_data": {
                                                     "mime_type": image.mime_type,
@@ -89,6 +96,7 @@ pub fn format_messages(messages: &[Message]) -> Vec<Value> {
                                     .iter()
                                     .filter_map(|c| match c {
                                         Content::Text(t) => Some(t.text.clone()),
+                                        Content::Result(r) => Some(r.text.clone()),
                                         Content::Resource(r) => Some(r.get_text()),
                                         _ => None,
                                     })
@@ -207,12 +215,13 @@ pub fn response_to_message(response: Value) -> Result<Message> {
         .unwrap_or(&binding);
     let candidate = candidates.first();
     let role = Role::Assistant;
-    let created = chrono::Utc::now().timestamp();
+    let created = crate::determinism::now().timestamp();
     if candidate.is_none() {
         return Ok(Message {
             role,
             created,
             content,
+            extra: Default::default(),
         });
     }
     let candidate = candidate.unwrap();
@@ -256,9 +265,31 @@ pub fn response_to_message(response: Value) -> Result<Message> {
         role,
         created,
         content,
+        extra: Default::default(),
     })
 }
 
+/// Why the first candidate stopped, none if the response does not say
+pub fn get_finish_reason(response: &Value) -> Option<FinishReason> {
+    response["candidates"][0]["finishReason"]
+        .as_str()
+        .map(parse_finish_reason)
+}
+
+/// Normalize a Gemini `finishReason`
+///
+/// Gemini reports "STOP" for tool calls as well, since they are part of the content.
+pub fn parse_finish_reason(reason: &str) -> FinishReason {
+    match reason {
+        "STOP" => FinishReason::Stop,
+        "MAX_TOKENS" => FinishReason::Length,
+        "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" | "IMAGE_SAFETY" => {
+            FinishReason::ContentFilter
+        }
+        other => FinishReason::Other(other.to_string()),
+    }
+}
+
 /// Extract usage information from Google's API response
 pub fn get_usage(data: &Value) -> Result<Usage> {
     if let Some(usage_meta_data) = data.get("usageMetadata") {
@@ -305,7 +336,7 @@ pub fn create_request(
         );
     }
     let mut generation_config = Map::new();
-    if let Some(temp) = model_config.temperature {
+    if let Some(temp) = validate_temperature(model_config, TEMPERATURE_RANGE)? {
         generation_config.insert("temperature".to_string(), json!(temp));
     }
     if let Some(tokens) = model_config.max_tokens {
@@ -328,6 +359,7 @@ mod tests {
             role,
             created: 0,
             content: vec![MessageContent::text(text.to_string())],
+            extra: Default::default(),
         }
     }
 
@@ -336,6 +368,7 @@ mod tests {
             role: Role::User,
             created: 0,
             content: vec![MessageContent::tool_request(id.to_string(), Ok(tool_call))],
+            extra: Default::default(),
         }
     }
 
@@ -349,6 +382,7 @@ mod tests {
                 tool_call.arguments.clone(),
                 Some("Goose would like to call the above tool. Allow? (y/n):".to_string()),
             )],
+            extra: Default::default(),
         }
     }
 
@@ -360,6 +394,7 @@ mod tests {
                 id.to_string(),
                 Ok(tool_response),
             )],
+            extra: Default::default(),
         }
     }
 
@@ -628,4 +663,23 @@ mod tests {
 
         assert_eq!(payload, expected_payload);
     }
+
+    #[test]
+    fn test_finish_reasons() {
+        assert_eq!(parse_finish_reason("STOP"), FinishReason::Stop);
+        assert_eq!(parse_finish_reason("MAX_TOKENS"), FinishReason::Length);
+        assert_eq!(parse_finish_reason("SAFETY"), FinishReason::ContentFilter);
+        assert_eq!(
+            parse_finish_reason("RECITATION"),
+            FinishReason::ContentFilter
+        );
+        assert_eq!(
+            parse_finish_reason("MALFORMED_FUNCTION_CALL"),
+            FinishReason::Other("MALFORMED_FUNCTION_CALL".to_string())
+        );
+
+        let response = json!({"candidates": [{"finishReason": "MAX_TOKENS"}]});
+        assert_eq!(get_finish_reason(&response), Some(FinishReason::Length));
+        assert_eq!(get_finish_reason(&json!({"candidates": []})), None);
+    }
 }
@@ -0,0 +1,150 @@
+//! Splitting of large text content for providers that cap the size of each message
+//!
+//! Some APIs reject a single message above a size even when the conversation fits the
+//! context. This pass runs on goose messages before they are converted, and moves the
+//! pieces of an oversized text into sequential messages of the same role.
+
+use std::borrow::Cow;
+
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+
+/// The messages split for the `max_message_bytes` of the model, if it has one
+pub fn limit_message_size<'a>(
+    model_config: &ModelConfig,
+    messages: &'a [Message],
+) -> Cow<'a, [Message]> {
+    match model_config.max_message_bytes {
+        Some(max_bytes) => Cow::Owned(split_large_text(messages, max_bytes)),
+        None => Cow::Borrowed(messages),
+    }
+}
+
+/// Split every text content larger than `max_bytes` across sequential messages of its role
+///
+/// Text is cut after the last newline that fits, else at the last char boundary that fits.
+/// Other content stays in order, in the message of the text piece it followed.
+pub fn split_large_text(messages: &[Message], max_bytes: usize) -> Vec<Message> {
+    let mut split = Vec::with_capacity(messages.len());
+    for message in messages {
+        let oversized = message.content.iter().any(
+            |content| matches!(content, MessageContent::Text(text) if text.text.len() > max_bytes),
+        );
+        if !oversized {
+            split.push(message.clone());
+            continue;
+        }
+
+        let mut current = Message {
+            content: Vec::new(),
+            ..message.clone()
+        };
+        for content in &message.content {
+            match content {
+                MessageContent::Text(text) if text.text.len() > max_bytes => {
+                    for chunk in split_text(&text.text, max_bytes) {
+                        if !current.content.is_empty() {
+                            // Extra response fields go back once, with the first piece
+                            let next = Message {
+                                content: Vec::new(),
+                                extra: Default::default(),
+                                ..current.clone()
+                            };
+                            split.push(std::mem::replace(&mut current, next));
+                        }
+                        let mut piece = text.clone();
+                        piece.text = chunk.to_string();
+                        current.content.push(MessageContent::Text(piece));
+                    }
+                }
+                _ => current.content.push(content.clone()),
+            }
+        }
+        split.push(current);
+    }
+    split
+}
+
+/// Cut `text` into pieces of at most `max_bytes`, unless a single char is larger
+fn split_text(text: &str, max_bytes: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while rest.len() > max_bytes {
+        let mut end = max_bytes;
+        while end > 0 && !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        if end == 0 {
+            // The limit is smaller than the first char, which can't be cut
+            end = rest.chars().next().map_or(rest.len(), char::len_utf8);
+        } else if let Some(newline) = rest[..end].rfind('\n') {
+            end = newline + 1;
+        }
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    if !rest.is_empty() {
+        chunks.push(rest);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::role::Role;
+    use mcp_core::tool::ToolCall;
+    use serde_json::json;
+
+    #[test]
+    fn test_split_large_text() {
+        let line = "0123456789abcdefghi\n";
+        let large = line.repeat(50);
+        let messages = vec![
+            Message::user().with_text("short"),
+            Message::assistant()
+                .with_text(&large)
+                .with_tool_request("1", Ok(ToolCall::new("read", json!({})))),
+        ];
+
+        let split = split_large_text(&messages, 100);
+        // The first message is untouched, the text of the second splits into 10 chunks of 5 lines
+        assert_eq!(split.len(), 11);
+        assert_eq!(split[0].as_concat_text(), "short");
+        let chunks: Vec<&str> = split[1..]
+            .iter()
+            .map(|message| message.content[0].as_text().unwrap())
+            .collect();
+        assert_eq!(chunks.len(), 10);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 100));
+        assert!(chunks.iter().all(|chunk| chunk.ends_with('\n')));
+        assert_eq!(chunks.concat(), large);
+        assert!(split[1..]
+            .iter()
+            .all(|message| message.role == Role::Assistant));
+        // The tool request stays after the last chunk
+        assert!(split[10].content[1].as_tool_request().is_some());
+        assert!(split[1..10]
+            .iter()
+            .all(|message| message.content.len() == 1));
+    }
+
+    #[test]
+    fn test_split_text_boundaries() {
+        // Without newlines, text is cut at char boundaries
+        let text = "é".repeat(10);
+        let chunks = split_text(&text, 5);
+        assert_eq!(chunks, vec!["éé"; 5]);
+
+        let text = "a".repeat(25);
+        let chunks = split_text(&text, 10);
+        assert_eq!(
+            chunks.iter().map(|chunk| chunk.len()).collect::<Vec<_>>(),
+            vec![10, 10, 5]
+        );
+
+        // A char larger than the limit is kept whole
+        assert_eq!(split_text("😀a", 2), vec!["😀", "a"]);
+    }
+}
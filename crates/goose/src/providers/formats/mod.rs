@@ -1,7 +1,97 @@
+pub mod alternation;
 pub mod anthropic;
 pub mod bedrock;
 pub mod databricks;
 pub mod gcpvertexai;
 pub mod google;
+pub mod message_split;
 pub mod openai;
+pub mod openai_responses;
+pub mod payload_limits;
 pub mod snowflake;
+pub mod strict_schema;
+pub mod tool_schema;
+
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::providers::anthropic::AnthropicProvider;
+use crate::providers::base::Provider;
+use crate::providers::databricks::DatabricksProvider;
+use crate::providers::gcpvertexai::GcpVertexAIProvider;
+use crate::providers::google::GoogleProvider;
+use crate::providers::openai::OpenAiProvider;
+use crate::providers::snowflake::SnowflakeProvider;
+use crate::providers::utils::{ImageFormat, ToolContentSeparator};
+use anyhow::{anyhow, Result};
+use mcp_core::tool::Tool;
+use serde_json::Value;
+use tool_schema::ToolSchemaVerbosity;
+
+/// The adapters [`convert_roundtrip`] accepts. Bedrock builds AWS SDK types rather than JSON,
+/// so it has no serialized request to compare.
+pub const ROUNDTRIP_ADAPTERS: [&str; 7] = [
+    "anthropic",
+    "databricks",
+    "gcpvertexai",
+    "google",
+    "openai",
+    "openai_responses",
+    "snowflake",
+];
+
+/// Convert a conversation to the request an adapter would send, as it arrives on the wire
+///
+/// The payload is serialized to JSON text and parsed back, so the result only holds what
+/// survives serialization. The model is the owning provider's default, and settings that
+/// environment variables could change are pinned, which makes the result stable enough to
+/// snapshot.
+pub fn convert_roundtrip(
+    messages: &[Message],
+    tools: &[Tool],
+    system: &str,
+    provider: &str,
+) -> Result<Value> {
+    let default_model = match provider {
+        "anthropic" => AnthropicProvider::metadata().default_model,
+        "databricks" => DatabricksProvider::metadata().default_model,
+        "gcpvertexai" => GcpVertexAIProvider::metadata().default_model,
+        "google" => GoogleProvider::metadata().default_model,
+        "openai" | "openai_responses" => OpenAiProvider::metadata().default_model,
+        "snowflake" => SnowflakeProvider::metadata().default_model,
+        _ => {
+            return Err(anyhow!(
+                "No roundtrip for '{}', expected one of {}",
+                provider,
+                ROUNDTRIP_ADAPTERS.join(", ")
+            ))
+        }
+    };
+    let config = ModelConfig::new(default_model)
+        .with_temperature(None)
+        .with_max_tokens(Some(1024))
+        .with_toolshim(false)
+        .with_legacy_function_calling(false)
+        .with_strict_tools(Some(false))
+        .with_tool_schema_verbosity(ToolSchemaVerbosity::Full)
+        .with_tool_content_separator(ToolContentSeparator::default());
+
+    let request = match provider {
+        "anthropic" => anthropic::create_request(&config, system, messages, tools)?,
+        "databricks" => {
+            databricks::create_request(&config, system, messages, tools, &ImageFormat::OpenAi)?
+        }
+        "gcpvertexai" => gcpvertexai::create_request(&config, system, messages, tools)?.0,
+        "google" => google::create_request(&config, system, messages, tools)?,
+        "openai" => openai::create_request(&config, system, messages, tools, &ImageFormat::OpenAi)?,
+        "openai_responses" => openai_responses::create_request(
+            &config,
+            system,
+            messages,
+            tools,
+            &ImageFormat::OpenAi,
+        )?,
+        "snowflake" => snowflake::create_request(&config, system, messages, tools)?,
+        _ => unreachable!("the adapter was checked when picking the model"),
+    };
+    Ok(serde_json::from_str(&serde_json::to_string(&request)?)?)
+}
@@ -1,20 +1,66 @@
-use crate::message::{Message, MessageContent};
+use crate::message::{Citation, CitationSource, Message, MessageContent};
 use crate::model::ModelConfig;
-use crate::providers::base::Usage;
+use crate::prompt_template::SystemPrompt;
+use crate::providers::base::{CompletionOptions, FinishReason, Logprobs, Usage};
+use crate::providers::capabilities::validate_capabilities;
 use crate::providers::errors::ProviderError;
+use crate::providers::formats::message_split::limit_message_size;
+use crate::providers::formats::payload_limits::{check_request_size, limit_images};
+use crate::providers::formats::strict_schema::strict_schema;
+use crate::providers::formats::tool_schema::minify_tools;
+use crate::providers::tool_call_ids::{ToolCallIdSource, UuidToolCallIds};
 use crate::providers::utils::{
     convert_image, detect_image_path, is_valid_function_name, load_image_file,
-    sanitize_function_name, ImageFormat,
+    sanitize_function_name, tool_call_id, validate_message_images, validate_temperature,
+    ImageFormat, ToolContentSeparator, ToolImagePolicy,
 };
 use anyhow::{anyhow, Error};
-use mcp_core::ToolError;
 use mcp_core::{Content, Role, Tool, ToolCall};
-use serde_json::{json, Value};
+use mcp_core::{ToolError, ToolResult};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+/// The key of the fields that Chat Completions responses kept in [`Message::extra`]
+pub const EXTRA_KEY: &str = "openai";
+
+/// Shown before the refusal of a model, which OpenAI sends instead of content
+pub const REFUSAL_PREFIX: &str = "Refused: ";
+
+/// Fields of a response message that goose converts itself, the others are kept as extra
+const MODELED_FIELDS: [&str; 4] = ["role", "content", "tool_calls", "function_call"];
+
+/// https://platform.openai.com/docs/api-reference/chat/create#chat-create-temperature
+const TEMPERATURE_RANGE: RangeInclusive<f32> = 0.0..=2.0;
 
 /// Convert internal Message format to OpenAI's API message specification
 ///   some openai compatible endpoints use the anthropic image spec at the content level
 ///   even though the message structure is otherwise following openai, the enum switches this
 pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<Value> {
+    format_messages_with_separator(messages, image_format, ToolContentSeparator::default())
+}
+
+/// Like [`format_messages`], joining the text blocks of tool results with `separator`
+pub fn format_messages_with_separator(
+    messages: &[Message],
+    image_format: &ImageFormat,
+    separator: ToolContentSeparator,
+) -> Vec<Value> {
+    format_messages_with_options(
+        messages,
+        image_format,
+        separator,
+        ToolImagePolicy::default(),
+    )
+}
+
+/// Like [`format_messages_with_separator`], handling images in tool results with `image_policy`
+pub fn format_messages_with_options(
+    messages: &[Message],
+    image_format: &ImageFormat,
+    separator: ToolContentSeparator,
+    image_policy: ToolImagePolicy,
+) -> Vec<Value> {
     let mut messages_spec = Vec::new();
     for message in messages {
         let mut converted = json!({
@@ -23,8 +69,18 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
 
         let mut output = Vec::new();
 
+        // The refusal goes back as its own field, not as the text it is shown as
+        let extra = message.extra_for(EXTRA_KEY);
+        let refusal = extra
+            .and_then(|extra| extra.get("refusal"))
+            .and_then(Value::as_str)
+            .map(|refusal| format!("{}{}", REFUSAL_PREFIX, refusal));
+
         for content in &message.content {
             match content {
+                // Shown to the user only, so never part of the history sent back
+                _ if content.is_ephemeral() => continue,
+                MessageContent::Text(text) if refusal.as_ref() == Some(&text.text) => continue,
                 MessageContent::Text(text) => {
                     if !text.text.is_empty() {
                         // Check for image paths in the text
@@ -58,6 +114,9 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
                 MessageContent::SummarizationRequested(_) => {
                     continue;
                 }
+                MessageContent::Citations(_) => {
+                    continue;
+                }
                 MessageContent::ToolRequest(request) => match &request.tool_call {
                     Ok(tool_call) => {
                         let sanitized_name = sanitize_function_name(&tool_call.name);
@@ -91,29 +150,40 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
                             let abridged: Vec<_> = contents
                                 .iter()
                                 .filter(|content| {
-                                    content
-                                        .audience()
-                                        .is_none_or(|audience| audience.contains(&Role::Assistant))
+                                    !content.is_ephemeral()
+                                        && content.audience().is_none_or(|audience| {
+                                            audience.contains(&Role::Assistant)
+                                        })
                                 })
                                 .map(|content| content.unannotated())
                                 .collect();
 
                             // Process all content, replacing images with placeholder text
+                            // unless they are sent inline
                             let mut tool_content = Vec::new();
+                            let mut inline_images = Vec::new();
                             let mut image_messages = Vec::new();
 
                             for content in abridged {
                                 match content {
-                                    Content::Image(image) => {
-                                        // Add placeholder text in the tool response
-                                        tool_content.push(Content::text("This tool result included an image that is uploaded in the next message."));
-
-                                        // Create a separate image message
-                                        image_messages.push(json!({
-                                            "role": "user",
-                                            "content": [convert_image(&image, image_format)]
-                                        }));
-                                    }
+                                    Content::Image(image) => match image_policy {
+                                        ToolImagePolicy::Hoist => {
+                                            // Add placeholder text in the tool response
+                                            tool_content.push(Content::text("This tool result included an image that is uploaded in the next message."));
+
+                                            // Create a separate image message
+                                            image_messages.push(json!({
+                                                "role": "user",
+                                                "content": [convert_image(&image, image_format)]
+                                            }));
+                                        }
+                                        ToolImagePolicy::Inline => {
+                                            inline_images.push(convert_image(&image, image_format));
+                                        }
+                                        ToolImagePolicy::Drop => {
+                                            tool_content.push(Content::text("This tool result included an image that was not sent."));
+                                        }
+                                    },
                                     Content::Resource(resource) => {
                                         tool_content.push(Content::text(resource.get_text()));
                                     }
@@ -122,14 +192,26 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
                                     }
                                 }
                             }
-                            let tool_response_content: Value = json!(tool_content
-                                .iter()
-                                .map(|content| match content {
-                                    Content::Text(text) => text.text.clone(),
-                                    _ => String::new(),
-                                })
-                                .collect::<Vec<String>>()
-                                .join(" "));
+                            let tool_response_text = separator.join(
+                                &tool_content
+                                    .iter()
+                                    .map(|content| match content {
+                                        Content::Text(text) => text.text.clone(),
+                                        Content::Result(result) => result.text.clone(),
+                                        _ => String::new(),
+                                    })
+                                    .collect::<Vec<String>>(),
+                            );
+                            let tool_response_content: Value = if inline_images.is_empty() {
+                                json!(tool_response_text)
+                            } else {
+                                let mut parts = Vec::new();
+                                if !tool_response_text.is_empty() {
+                                    parts.push(json!({"type": "text", "text": tool_response_text}));
+                                }
+                                parts.extend(inline_images);
+                                json!(parts)
+                            };
 
                             // First add the tool response with all content
                             output.push(json!({
@@ -186,6 +268,16 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
             }
         }
 
+        if let Some(extra) = extra {
+            let fields = converted.as_object_mut().unwrap();
+            if refusal.is_some() && !fields.contains_key("content") {
+                fields.insert("content".to_string(), Value::Null);
+            }
+            for (key, value) in extra {
+                fields.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+        }
+
         if converted.get("content").is_some() || converted.get("tool_calls").is_some() {
             output.insert(0, converted);
         }
@@ -195,8 +287,60 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
     messages_spec
 }
 
+/// Which tools are kept when there are more than a request may carry
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToolSelection<'a> {
+    /// The first tools, in order
+    First,
+    /// The tools with the highest scores, one score per tool. Ties keep the earlier tool.
+    ByScore(&'a [f32]),
+}
+
+impl ToolSelection<'_> {
+    /// The indices of at most `max` of `count` tools, in their original order
+    fn select(&self, count: usize, max: usize) -> anyhow::Result<Vec<usize>> {
+        let mut selected: Vec<usize> = match self {
+            ToolSelection::First => (0..count).take(max).collect(),
+            ToolSelection::ByScore(scores) => {
+                if scores.len() != count {
+                    return Err(anyhow!(
+                        "Expected a score for each of the {} tools, got {}",
+                        count,
+                        scores.len()
+                    ));
+                }
+                let mut ranked: Vec<usize> = (0..count).collect();
+                ranked.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]));
+                ranked.truncate(max);
+                ranked
+            }
+        };
+        selected.sort_unstable();
+        Ok(selected)
+    }
+}
+
 /// Convert internal Tool format to OpenAI's API tool specification
 pub fn format_tools(tools: &[Tool]) -> anyhow::Result<Vec<Value>> {
+    format_tools_with_limit(tools, None, ToolSelection::First)
+}
+
+/// Convert tools to OpenAI's API tool specification, keeping at most `max_tools` of them
+///
+/// Tool names only have to be unique among the tools that are kept.
+pub fn format_tools_with_limit(
+    tools: &[Tool],
+    max_tools: Option<usize>,
+    selection: ToolSelection,
+) -> anyhow::Result<Vec<Value>> {
+    let tools: Vec<&Tool> = match max_tools {
+        Some(max) if max < tools.len() => selection
+            .select(tools.len(), max)?
+            .into_iter()
+            .map(|index| &tools[index])
+            .collect(),
+        _ => tools.iter().collect(),
+    };
     let mut tool_names = std::collections::HashSet::new();
     let mut result = Vec::new();
 
@@ -219,66 +363,276 @@ pub fn format_tools(tools: &[Tool]) -> anyhow::Result<Vec<Value>> {
     Ok(result)
 }
 
+/// Send function tools in strict form, so the model's arguments always match their schemas
+///
+/// A tool whose schema can't be made strict fails the request when strict tools were asked for
+/// explicitly, and is sent as it is when the model registry turned them on.
+pub fn make_tools_strict(tools_spec: &mut [Value], required: bool) -> anyhow::Result<()> {
+    for tool in tools_spec {
+        let function = &mut tool["function"];
+        match strict_schema(&function["parameters"]) {
+            Ok(parameters) => {
+                function["parameters"] = parameters;
+                function["strict"] = json!(true);
+            }
+            Err(e) => {
+                let name = function["name"].as_str().unwrap_or_default();
+                if required {
+                    return Err(anyhow!(
+                        "Tool '{}' can't be sent in strict mode: {}",
+                        name,
+                        e
+                    ));
+                }
+                tracing::debug!("Sending tool '{}' without strict mode: {}", name, e);
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Convert OpenAI's API response to internal Message format
 pub fn response_to_message(response: Value) -> anyhow::Result<Message> {
+    response_to_message_with_ids(response, &UuidToolCallIds)
+}
+
+/// Convert OpenAI's API response to internal Message format, taking the ids of tool calls
+/// without one from `ids`
+pub fn response_to_message_with_ids(
+    response: Value,
+    ids: &dyn ToolCallIdSource,
+) -> anyhow::Result<Message> {
     let original = response["choices"][0]["message"].clone();
     let mut content = Vec::new();
 
+    // Tool use responses have null or empty content, which isn't part of the message
     if let Some(text) = original.get("content") {
-        if let Some(text_str) = text.as_str() {
+        if let Some(text_str) = text.as_str().filter(|text| !text.is_empty()) {
             content.push(MessageContent::text(text_str));
+            let citations = annotation_citations(text_str, &original["annotations"]);
+            if !citations.is_empty() {
+                content.push(MessageContent::citations(citations));
+            }
         }
     }
 
     if let Some(tool_calls) = original.get("tool_calls") {
         if let Some(tool_calls_array) = tool_calls.as_array() {
-            for tool_call in tool_calls_array {
+            for (index, tool_call) in tool_calls_array.iter().enumerate() {
+                let id = tool_call_id(&response, tool_call, index, ids);
+                content.push(function_to_tool_request(id, &tool_call["function"]));
+            }
+        }
+    }
+
+    // The legacy form has a single call without an id
+    if let Some(function_call) = original
+        .get("function_call")
+        .filter(|call| call.is_object())
+    {
+        content.push(function_to_tool_request(ids.next_id(), function_call));
+    }
+
+    if let Some(refusal) = original["refusal"].as_str().filter(|text| !text.is_empty()) {
+        content.push(MessageContent::text(format!(
+            "{}{}",
+            REFUSAL_PREFIX, refusal
+        )));
+    }
+
+    // Fields goose doesn't model, such as audio or refusals, go back to the provider as they
+    // came. Null and empty fields carry nothing.
+    let extra = original
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(key, _)| !MODELED_FIELDS.contains(&key.as_str()))
+        .filter(|(_, value)| match value {
+            Value::Null => false,
+            Value::Array(items) => !items.is_empty(),
+            Value::Object(fields) => !fields.is_empty(),
+            _ => true,
+        })
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+
+    Ok(Message {
+        role: Role::Assistant,
+        created: crate::determinism::now().timestamp(),
+        content,
+        extra: Default::default(),
+    }
+    .with_extra(EXTRA_KEY, extra))
+}
+
+/// Convert OpenAI's API response to internal Message format, along with the raw choice it
+/// was parsed from, so that a message that looks wrong can be logged next to its original
+pub fn response_to_message_with_raw(response: Value) -> anyhow::Result<(Message, Value)> {
+    let choice = response["choices"][0].clone();
+    let message = response_to_message(response)?;
+    Ok((message, choice))
+}
+
+/// Citations from the `url_citation` annotations of a text, whose ranges count characters
+///
+/// Chat Completions nests the fields of an annotation under its type, Responses does not.
+pub(crate) fn annotation_citations(text: &str, annotations: &Value) -> Vec<Citation> {
+    let byte_offset = |chars: usize| {
+        text.char_indices()
+            .nth(chars)
+            .map_or(text.len(), |(offset, _)| offset)
+    };
+    annotations
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|annotation| annotation["type"] == "url_citation")
+        .filter_map(|annotation| {
+            let fields = annotation.get("url_citation").unwrap_or(annotation);
+            let start = fields["start_index"].as_u64()? as usize;
+            let end = fields["end_index"].as_u64()? as usize;
+            Some(Citation {
+                start: byte_offset(start),
+                end: byte_offset(end),
+                source: CitationSource {
+                    url: fields["url"].as_str().map(String::from),
+                    title: fields["title"].as_str().map(String::from),
+                    cited_text: None,
+                },
+            })
+        })
+        .collect()
+}
+
+/// The tool calls of OpenAI's API response in order, each validated as in
+/// `response_to_message`, for dispatching them without the rest of the message
+pub fn extract_tool_calls(response: &Value) -> Vec<ToolResult<ToolCall>> {
+    let ids = UuidToolCallIds;
+    let original = &response["choices"][0]["message"];
+    let mut tool_calls: Vec<_> = original["tool_calls"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(|(index, tool_call)| {
+            let id = tool_call_id(response, tool_call, index, &ids);
+            function_to_tool_call(&id, &tool_call["function"])
+        })
+        .collect();
+    if let Some(function_call) = original
+        .get("function_call")
+        .filter(|call| call.is_object())
+    {
+        tool_calls.push(function_to_tool_call(&ids.next_id(), function_call));
+    }
+    tool_calls
+}
+
+/// Convert a `{"name", "arguments"}` function call to a tool request
+pub(crate) fn function_to_tool_request(id: String, function: &Value) -> MessageContent {
+    let tool_call = function_to_tool_call(&id, function);
+    MessageContent::tool_request(id, tool_call)
+}
+
+/// Check the name and parse the arguments of a `{"name", "arguments"}` function call
+fn function_to_tool_call(id: &str, function: &Value) -> ToolResult<ToolCall> {
+    let function_name = function["name"].as_str().unwrap_or_default().to_string();
+    let mut arguments = function["arguments"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    // If arguments is empty, we will have invalid json parsing error later.
+    if arguments.is_empty() {
+        arguments = "{}".to_string();
+    }
+
+    if !is_valid_function_name(&function_name) {
+        return Err(ToolError::NotFound(format!(
+            "The provided function name '{}' had invalid characters, it must match this regex [a-zA-Z0-9_-]+",
+            function_name
+        )));
+    }
+
+    match serde_json::from_str::<Value>(&arguments) {
+        Ok(params) => Ok(ToolCall::new(&function_name, params)),
+        Err(e) => Err(ToolError::InvalidParameters(format!(
+            "Could not interpret tool use parameters for id {}: {}",
+            id, e
+        ))),
+    }
+}
+
+/// Rewrite formatted messages to the legacy `function_call` and `function` role form
+///
+/// A legacy assistant message carries a single call, so the calls of a message after the
+/// first are each sent in their own assistant message, directly before their result.
+pub fn to_legacy_function_messages(messages: Vec<Value>) -> Vec<Value> {
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut deferred: HashMap<String, Value> = HashMap::new();
+    let mut output = Vec::with_capacity(messages.len());
+
+    for mut message in messages {
+        let tool_calls = message
+            .as_object_mut()
+            .and_then(|message| message.remove("tool_calls"));
+        if let Some(Value::Array(tool_calls)) = tool_calls {
+            for (position, tool_call) in tool_calls.into_iter().enumerate() {
                 let id = tool_call["id"].as_str().unwrap_or_default().to_string();
-                let function_name = tool_call["function"]["name"]
-                    .as_str()
-                    .unwrap_or_default()
-                    .to_string();
-                let mut arguments = tool_call["function"]["arguments"]
-                    .as_str()
-                    .unwrap_or_default()
-                    .to_string();
-                // If arguments is empty, we will have invalid json parsing error later.
-                if arguments.is_empty() {
-                    arguments = "{}".to_string();
+                let function = tool_call["function"].clone();
+                if let Some(name) = function["name"].as_str() {
+                    names.insert(id.clone(), name.to_string());
                 }
-
-                if !is_valid_function_name(&function_name) {
-                    let error = ToolError::NotFound(format!(
-                        "The provided function name '{}' had invalid characters, it must match this regex [a-zA-Z0-9_-]+",
-                        function_name
-                    ));
-                    content.push(MessageContent::tool_request(id, Err(error)));
+                if position == 0 {
+                    message["function_call"] = function;
                 } else {
-                    match serde_json::from_str::<Value>(&arguments) {
-                        Ok(params) => {
-                            content.push(MessageContent::tool_request(
-                                id,
-                                Ok(ToolCall::new(&function_name, params)),
-                            ));
-                        }
-                        Err(e) => {
-                            let error = ToolError::InvalidParameters(format!(
-                                "Could not interpret tool use parameters for id {}: {}",
-                                id, e
-                            ));
-                            content.push(MessageContent::tool_request(id, Err(error)));
-                        }
-                    }
+                    deferred.insert(id, function);
                 }
             }
+            if message.get("content").is_none() {
+                message["content"] = Value::Null;
+            }
+            output.push(message);
+        } else if message["role"] == "tool" {
+            let id = message["tool_call_id"].as_str().unwrap_or_default();
+            if let Some(function) = deferred.remove(id) {
+                output.push(json!({
+                    "role": "assistant",
+                    "content": null,
+                    "function_call": function,
+                }));
+            }
+            // Calls that could not be parsed have no name, the result still explains the error
+            let name = names.get(id).map(String::as_str).unwrap_or("unknown");
+            output.push(json!({
+                "role": "function",
+                "name": name,
+                "content": message["content"],
+            }));
+        } else {
+            output.push(message);
         }
     }
 
-    Ok(Message {
-        role: Role::Assistant,
-        created: chrono::Utc::now().timestamp(),
-        content,
-    })
+    output
+}
+
+/// Why the first choice stopped, none if the response does not say
+pub fn get_finish_reason(response: &Value) -> Option<FinishReason> {
+    response["choices"][0]["finish_reason"]
+        .as_str()
+        .map(parse_finish_reason)
+}
+
+/// Normalize an OpenAI `finish_reason`
+pub fn parse_finish_reason(reason: &str) -> FinishReason {
+    match reason {
+        "stop" => FinishReason::Stop,
+        "length" => FinishReason::Length,
+        // "function_call" is what the deprecated functions API reports
+        "tool_calls" | "function_call" => FinishReason::ToolCalls,
+        "content_filter" => FinishReason::ContentFilter,
+        other => FinishReason::Other(other.to_string()),
+    }
 }
 
 pub fn get_usage(data: &Value) -> Result<Usage, ProviderError> {
@@ -286,13 +640,16 @@ pub fn get_usage(data: &Value) -> Result<Usage, ProviderError> {
         .get("usage")
         .ok_or_else(|| ProviderError::UsageError("No usage data in response".to_string()))?;
 
+    // The Responses API names the counts after input and output
     let input_tokens = usage
         .get("prompt_tokens")
+        .or_else(|| usage.get("input_tokens"))
         .and_then(|v| v.as_i64())
         .map(|v| v as i32);
 
     let output_tokens = usage
         .get("completion_tokens")
+        .or_else(|| usage.get("output_tokens"))
         .and_then(|v| v.as_i64())
         .map(|v| v as i32);
 
@@ -308,6 +665,84 @@ pub fn get_usage(data: &Value) -> Result<Usage, ProviderError> {
     Ok(Usage::new(input_tokens, output_tokens, total_tokens))
 }
 
+/// The `system_fingerprint` of a response, which names the backend configuration that
+/// served it
+pub fn get_system_fingerprint(response: &Value) -> Option<String> {
+    response["system_fingerprint"]
+        .as_str()
+        .filter(|fingerprint| !fingerprint.is_empty())
+        .map(String::from)
+}
+
+/// The seed of a request and the backend that answered it, to check reproducible runs
+///
+/// A seeded request only gets the same response again from the same backend, so when two
+/// runs of the same request differ, a changed fingerprint says the backend did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReproInfo {
+    pub seed: Option<u64>,
+    pub system_fingerprint: Option<String>,
+}
+
+impl ReproInfo {
+    /// The seed sent with `model_config` and the fingerprint of its `response`
+    pub fn new(model_config: &ModelConfig, response: &Value) -> Self {
+        Self {
+            seed: model_config.seed,
+            system_fingerprint: get_system_fingerprint(response),
+        }
+    }
+
+    /// Whether `other`, a run of the same seeded request, was answered by another backend
+    ///
+    /// Runs without a seed, or without a fingerprint to compare, are never flagged.
+    pub fn backend_changed(&self, other: &ReproInfo) -> bool {
+        self.seed.is_some()
+            && self.seed == other.seed
+            && matches!(
+                (&self.system_fingerprint, &other.system_fingerprint),
+                (Some(a), Some(b)) if a != b
+            )
+    }
+}
+
+/// Ask for the log probabilities of the generated tokens, if the options want them
+pub fn add_logprobs(payload: &mut Value, options: &CompletionOptions) {
+    if !options.logprobs {
+        return;
+    }
+    let payload = payload.as_object_mut().unwrap();
+    payload.insert("logprobs".to_string(), json!(true));
+    if options.top_logprobs > 0 {
+        payload.insert("top_logprobs".to_string(), json!(options.top_logprobs));
+    }
+}
+
+/// Forbid tool calls in the response, if the options do. The API rejects a tool_choice
+/// without tools, and there is nothing to forbid then.
+pub fn add_tool_choice(payload: &mut Value, options: &CompletionOptions) {
+    if options.no_tool_calls && payload.get("tools").is_some() {
+        payload["tool_choice"] = json!("none");
+    }
+}
+
+/// The log probabilities of the generated tokens, none if the response has no logprobs
+///
+/// A refusal has its tokens under `refusal` instead of `content`.
+pub fn response_to_logprobs(response: &Value) -> Option<Logprobs> {
+    let logprobs = &response["choices"][0]["logprobs"];
+    let tokens = ["content", "refusal"]
+        .into_iter()
+        .find_map(|key| logprobs.get(key).filter(|tokens| !tokens.is_null()))?;
+    match serde_json::from_value(tokens.clone()) {
+        Ok(tokens) => Some(Logprobs { tokens }),
+        Err(e) => {
+            tracing::debug!("Failed to parse logprobs: {}", e);
+            None
+        }
+    }
+}
+
 /// Validates and fixes tool schemas to ensure they have proper parameter structure.
 /// If parameters exist, ensures they have properties and required fields, or removes parameters entirely.
 pub fn validate_tool_schemas(tools: &mut [Value]) {
@@ -354,12 +789,73 @@ fn ensure_valid_json_schema(schema: &mut Value) {
     }
 }
 
+/// The API a request is built for, when it differs from OpenAI beyond the endpoint
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RequestTarget {
+    #[default]
+    OpenAi,
+    /// Azure OpenAI, where the deployment in the URL implies the model
+    AzureDeployment,
+}
+
+/// OpenAI only accepts string values in request metadata
+fn validate_metadata(metadata: &Map<String, Value>) -> Result<(), ProviderError> {
+    match metadata.iter().find(|(_, value)| !value.is_string()) {
+        Some((key, value)) => Err(ProviderError::RequestFailed(format!(
+            "OpenAI request metadata values must be strings, '{}' is {}",
+            key, value
+        ))),
+        None => Ok(()),
+    }
+}
+
 pub fn create_request(
     model_config: &ModelConfig,
     system: &str,
     messages: &[Message],
     tools: &[Tool],
     image_format: &ImageFormat,
+) -> anyhow::Result<Value, Error> {
+    create_request_for_target(
+        model_config,
+        system,
+        messages,
+        tools,
+        image_format,
+        RequestTarget::OpenAi,
+    )
+}
+
+pub fn create_request_for_target(
+    model_config: &ModelConfig,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+    image_format: &ImageFormat,
+    target: RequestTarget,
+) -> anyhow::Result<Value, Error> {
+    // OpenAI takes the system prompt as one message, so its blocks are joined
+    create_request_with_system_messages(
+        model_config,
+        &[SystemPrompt::decode(system).render()],
+        messages,
+        tools,
+        image_format,
+        target,
+    )
+}
+
+/// Like [`create_request_for_target`], with each of `system_messages` sent as its own message
+/// in order before the conversation, for layered instructions
+///
+/// The o-series takes them as developer messages.
+pub fn create_request_with_system_messages(
+    model_config: &ModelConfig,
+    system_messages: &[String],
+    messages: &[Message],
+    tools: &[Tool],
+    image_format: &ImageFormat,
+    target: RequestTarget,
 ) -> anyhow::Result<Value, Error> {
     if model_config.model_name.starts_with("o1-mini") {
         return Err(anyhow!(
@@ -367,6 +863,17 @@ pub fn create_request(
         ));
     }
 
+    if messages.is_empty() && !model_config.allow_system_only {
+        return Err(anyhow!(
+            "Cannot send a request without messages, only a system prompt. Add a user message, or allow system-only requests for APIs that accept them."
+        ));
+    }
+
+    validate_message_images(messages)?;
+    validate_capabilities(model_config, messages, tools)?;
+    let messages = &limit_message_size(model_config, messages)[..];
+    let messages = &limit_images(model_config, messages)[..];
+
     let is_ox_model = model_config.model_name.starts_with("o");
 
     // Only extract reasoning effort for O1/O3 models
@@ -389,28 +896,52 @@ pub fn create_request(
         (model_config.model_name.to_string(), None)
     };
 
-    let system_message = json!({
-        "role": if is_ox_model { "developer" } else { "system" },
-        "content": system
-    });
-
-    let messages_spec = format_messages(messages, image_format);
+    let system_role = if is_ox_model { "developer" } else { "system" };
+
+    // The legacy function messages only take text, so images can't be inlined there
+    let image_policy = model_config
+        .tool_image_policy
+        .for_capabilities(!model_config.legacy_function_calling);
+    let mut messages_spec = format_messages_with_options(
+        messages,
+        image_format,
+        model_config.tool_content_separator,
+        image_policy,
+    );
     let mut tools_spec = if !tools.is_empty() {
-        format_tools(tools)?
+        let verbosity = model_config.tool_schema_verbosity.for_turn(messages);
+        format_tools_with_limit(
+            &minify_tools(tools, verbosity),
+            model_config.max_tools,
+            ToolSelection::First,
+        )?
     } else {
         vec![]
     };
 
     // Validate tool schemas
     validate_tool_schemas(&mut tools_spec);
+    if model_config.strict_tools() && !model_config.legacy_function_calling {
+        make_tools_strict(&mut tools_spec, model_config.strict_tools == Some(true))?;
+    }
 
-    let mut messages_array = vec![system_message];
+    if model_config.legacy_function_calling {
+        messages_spec = to_legacy_function_messages(messages_spec);
+    }
+
+    let mut messages_array: Vec<Value> = system_messages
+        .iter()
+        .map(|content| json!({"role": system_role, "content": content}))
+        .collect();
     messages_array.extend(messages_spec);
 
     let mut payload = json!({
         "model": model_name,
         "messages": messages_array
     });
+    if target == RequestTarget::AzureDeployment {
+        payload.as_object_mut().unwrap().remove("model");
+    }
 
     if let Some(effort) = reasoning_effort {
         payload
@@ -420,14 +951,25 @@ pub fn create_request(
     }
 
     if !tools_spec.is_empty() {
-        payload
-            .as_object_mut()
-            .unwrap()
-            .insert("tools".to_string(), json!(tools_spec));
+        if model_config.legacy_function_calling {
+            let functions: Vec<Value> = tools_spec
+                .into_iter()
+                .map(|tool| tool["function"].clone())
+                .collect();
+            payload
+                .as_object_mut()
+                .unwrap()
+                .insert("functions".to_string(), json!(functions));
+        } else {
+            payload
+                .as_object_mut()
+                .unwrap()
+                .insert("tools".to_string(), json!(tools_spec));
+        }
     }
-    // o1, o3 models currently don't support temperature
+    // o1, o3 models currently don't support temperature, so it is dropped before it is checked
     if !is_ox_model {
-        if let Some(temp) = model_config.temperature {
+        if let Some(temp) = validate_temperature(model_config, TEMPERATURE_RANGE)? {
             payload
                 .as_object_mut()
                 .unwrap()
@@ -447,14 +989,40 @@ pub fn create_request(
             .unwrap()
             .insert(key.to_string(), json!(tokens));
     }
+
+    if let Some(seed) = model_config.seed {
+        payload
+            .as_object_mut()
+            .unwrap()
+            .insert("seed".to_string(), json!(seed));
+    }
+
+    // Request metadata is a string map used to tag requests
+    if let Some(metadata) = &model_config.metadata {
+        validate_metadata(metadata)?;
+        payload
+            .as_object_mut()
+            .unwrap()
+            .insert("metadata".to_string(), json!(metadata));
+    }
+
+    check_request_size(model_config, &payload)?;
     Ok(payload)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::determinism::Deterministic;
+    use crate::message::ToolProvenance;
+    use crate::providers::formats::tool_schema::ToolSchemaVerbosity;
+    use crate::providers::tool_call_ids::SequentialToolCallIds;
+    use crate::providers::utils::TemperaturePolicy;
     use mcp_core::content::Content;
     use serde_json::json;
+    use std::time::Duration;
+
+    const PNG_DATA: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=";
 
     #[test]
     fn test_validate_tool_schemas() {
@@ -563,6 +1131,99 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_format_messages_result_content() -> anyhow::Result<()> {
+        let messages = vec![
+            Message::assistant()
+                .with_tool_request("tool1", Ok(ToolCall::new("git__status", json!({})))),
+            Message::user().with_tool_response(
+                "tool1",
+                Ok(vec![Content::result(
+                    "2 files changed",
+                    Some(json!({"files": ["a.rs", "b.rs"]})),
+                )]),
+            ),
+        ];
+
+        // The model sees the text, the structured form stays in the message for callers
+        let spec = format_messages(&messages, &ImageFormat::OpenAi);
+        assert_eq!(spec[1]["role"], "tool");
+        assert_eq!(spec[1]["content"], "2 files changed");
+        let response = messages[1].content[0].as_tool_response().unwrap();
+        assert_eq!(
+            response.tool_result.as_ref().unwrap()[0].as_structured(),
+            Some(&json!({"files": ["a.rs", "b.rs"]}))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_messages_omit_tool_duration() -> anyhow::Result<()> {
+        let provenance = ToolProvenance {
+            message_index: 0,
+            extension: "developer".to_string(),
+            started_at: 1_000,
+            finished_at: 13_500,
+        };
+        let messages = vec![
+            Message::assistant()
+                .with_tool_request("tool1", Ok(ToolCall::new("developer__shell", json!({})))),
+            Message::user().with_traced_tool_response(
+                "tool1",
+                Ok(vec![Content::text("done")]),
+                provenance,
+            ),
+        ];
+
+        // The duration stays with the message, and never reaches the model
+        let response = messages[1].content[0].as_tool_response().unwrap();
+        assert_eq!(response.duration(), Some(Duration::from_millis(12_500)));
+        let spec = format_messages(&messages, &ImageFormat::OpenAi);
+        assert_eq!(
+            spec[1],
+            json!({"role": "tool", "content": "done", "tool_call_id": "tool1"})
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_messages_ephemeral_content() -> anyhow::Result<()> {
+        let messages = vec![
+            Message::assistant()
+                .with_ephemeral_text("Indexing the repository...")
+                .with_tool_request("tool1", Ok(ToolCall::new("git__status", json!({})))),
+            Message::user().with_tool_response(
+                "tool1",
+                Ok(vec![
+                    Content::text("2 files changed"),
+                    Content::text("Took 3s").with_ephemeral(),
+                ]),
+            ),
+        ];
+
+        // Ephemeral content never reaches the model
+        let spec = format_messages(&messages, &ImageFormat::OpenAi);
+        assert_eq!(spec.len(), 2);
+        assert!(spec[0].get("content").is_none());
+        assert_eq!(spec[0]["tool_calls"][0]["id"], "tool1");
+        assert_eq!(spec[1]["content"], "2 files changed");
+
+        // But stays in the messages shown to the user
+        let shown = messages[0].for_audience(Role::User);
+        assert_eq!(shown.as_concat_text(), "Indexing the repository...");
+        let shown = messages[1].for_audience(Role::User);
+        assert_eq!(
+            shown.content[0].as_tool_response_text().unwrap(),
+            "2 files changed\nTook 3s"
+        );
+        let sent = messages[1].for_audience(Role::Assistant);
+        assert_eq!(
+            sent.content[0].as_tool_response_text().unwrap(),
+            "2 files changed"
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_format_tools() -> anyhow::Result<()> {
         let tool = Tool::new(
@@ -599,16 +1260,8 @@ mod tests {
                 Ok(ToolCall::new("example", json!({"param1": "value1"}))),
             ),
         ];
-
-        // Get the ID from the tool request to use in the response
-        let tool_id = if let MessageContent::ToolRequest(request) = &messages[2].content[0] {
-            request.id.clone()
-        } else {
-            panic!("should be tool request");
-        };
-
         messages
-            .push(Message::user().with_tool_response(tool_id, Ok(vec![Content::text("Result")])));
+            .push(Message::user().with_tool_response("tool1", Ok(vec![Content::text("Result")])));
 
         let spec = format_messages(&messages, &ImageFormat::OpenAi);
 
@@ -632,16 +1285,8 @@ mod tests {
             "tool1",
             Ok(ToolCall::new("example", json!({"param1": "value1"}))),
         )];
-
-        // Get the ID from the tool request to use in the response
-        let tool_id = if let MessageContent::ToolRequest(request) = &messages[0].content[0] {
-            request.id.clone()
-        } else {
-            panic!("should be tool request");
-        };
-
         messages
-            .push(Message::user().with_tool_response(tool_id, Ok(vec![Content::text("Result")])));
+            .push(Message::user().with_tool_response("tool1", Ok(vec![Content::text("Result")])));
 
         let spec = format_messages(&messages, &ImageFormat::OpenAi);
 
@@ -655,6 +1300,119 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_format_messages_tool_content_separator() {
+        let messages = vec![Message::user().with_tool_response(
+            "tool1",
+            Ok(vec![Content::text("line one"), Content::text("line two")]),
+        )];
+        let tool_content = |separator| {
+            format_messages_with_separator(&messages, &ImageFormat::OpenAi, separator)[0]["content"]
+                .clone()
+        };
+
+        assert_eq!(
+            tool_content(ToolContentSeparator::Space),
+            "line one line two"
+        );
+        assert_eq!(
+            tool_content(ToolContentSeparator::Newline),
+            "line one\nline two"
+        );
+        assert_eq!(
+            tool_content(ToolContentSeparator::JsonArray),
+            r#"["line one","line two"]"#
+        );
+        assert_eq!(
+            format_messages(&messages, &ImageFormat::OpenAi)[0]["content"],
+            "line one\nline two"
+        );
+    }
+
+    #[test]
+    fn test_format_messages_tool_image_policy() {
+        let messages = vec![Message::user().with_tool_response(
+            "tool1",
+            Ok(vec![
+                Content::text("Screenshot taken"),
+                Content::image(PNG_DATA, "image/png"),
+            ]),
+        )];
+        let format = |policy| {
+            format_messages_with_options(
+                &messages,
+                &ImageFormat::OpenAi,
+                ToolContentSeparator::Newline,
+                policy,
+            )
+        };
+        let image_url = format!("data:image/png;base64,{}", PNG_DATA);
+
+        let hoisted = format(ToolImagePolicy::Hoist);
+        assert_eq!(hoisted, format_messages(&messages, &ImageFormat::OpenAi));
+        assert_eq!(hoisted.len(), 2);
+        assert_eq!(hoisted[0]["role"], "tool");
+        assert_eq!(
+            hoisted[0]["content"],
+            "Screenshot taken\nThis tool result included an image that is uploaded in the next message."
+        );
+        assert_eq!(hoisted[1]["role"], "user");
+        assert_eq!(hoisted[1]["content"][0]["image_url"]["url"], image_url);
+
+        let inlined = format(ToolImagePolicy::Inline);
+        assert_eq!(
+            inlined,
+            vec![json!({
+                "role": "tool",
+                "content": [
+                    {"type": "text", "text": "Screenshot taken"},
+                    {"type": "image_url", "image_url": {"url": image_url}}
+                ],
+                "tool_call_id": "tool1"
+            })]
+        );
+
+        let dropped = format(ToolImagePolicy::Drop);
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(
+            dropped[0]["content"],
+            "Screenshot taken\nThis tool result included an image that was not sent."
+        );
+    }
+
+    #[test]
+    fn test_create_request_tool_image_policy_capabilities() -> anyhow::Result<()> {
+        let messages = vec![
+            Message::assistant().with_tool_request("1", Ok(ToolCall::new("screenshot", json!({})))),
+            Message::user()
+                .with_tool_response("1", Ok(vec![Content::image(PNG_DATA, "image/png")])),
+        ];
+        let request = |model_config: ModelConfig| {
+            create_request(
+                &model_config,
+                "system",
+                &messages,
+                &[],
+                &ImageFormat::OpenAi,
+            )
+        };
+        let model_config =
+            ModelConfig::new("gpt-4o".to_string()).with_tool_image_policy(ToolImagePolicy::Inline);
+
+        let inlined = request(model_config.clone())?;
+        assert_eq!(inlined["messages"].as_array().unwrap().len(), 3);
+        assert_eq!(inlined["messages"][2]["content"][0]["type"], "image_url");
+
+        // Function messages only take text, so the image is hoisted instead
+        let legacy = request(model_config.with_legacy_function_calling(true))?;
+        assert_eq!(legacy["messages"].as_array().unwrap().len(), 4);
+        assert_eq!(legacy["messages"][2]["role"], "function");
+        assert!(legacy["messages"][2]["content"].is_string());
+        assert_eq!(legacy["messages"][3]["content"][0]["type"], "image_url");
+
+        Ok(())
+    }
+
     #[test]
     fn test_format_tools_duplicate() -> anyhow::Result<()> {
         let tool1 = Tool::new(
@@ -699,6 +1457,61 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_format_tools_with_limit() -> anyhow::Result<()> {
+        let tools: Vec<Tool> = (0..100)
+            .map(|i| {
+                Tool::new(
+                    format!("tool_{}", i),
+                    "Test tool",
+                    json!({"type": "object", "properties": {}}),
+                    None,
+                )
+            })
+            .collect();
+        let names = |spec: &[Value]| -> Vec<String> {
+            spec.iter()
+                .map(|tool| tool["function"]["name"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        let spec = format_tools_with_limit(&tools, Some(20), ToolSelection::First)?;
+        assert_eq!(spec.len(), 20);
+        assert_eq!(names(&spec)[0], "tool_0");
+        assert_eq!(names(&spec)[19], "tool_19");
+
+        // The highest scores are kept, in the order of the tools
+        let scores: Vec<f32> = (0..100).map(|i| ((i * 37) % 100) as f32).collect();
+        let spec = format_tools_with_limit(&tools, Some(20), ToolSelection::ByScore(&scores))?;
+        assert_eq!(spec.len(), 20);
+        let kept = names(&spec);
+        let expected: Vec<usize> = (0..100).filter(|i| (i * 37) % 100 >= 80).collect();
+        assert_eq!(
+            kept,
+            expected
+                .iter()
+                .map(|i| format!("tool_{}", i))
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(
+            format_tools_with_limit(&tools, Some(200), ToolSelection::First)?.len(),
+            100
+        );
+        assert!(
+            format_tools_with_limit(&tools, Some(20), ToolSelection::ByScore(&scores[..10]))
+                .is_err()
+        );
+
+        // Duplicates are only rejected among the selected tools
+        let mut duplicated = tools[..20].to_vec();
+        duplicated.push(tools[0].clone());
+        assert!(format_tools_with_limit(&duplicated, Some(20), ToolSelection::First).is_ok());
+        assert!(format_tools_with_limit(&duplicated, None, ToolSelection::First).is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_format_tools_empty() -> anyhow::Result<()> {
         let spec = format_tools(&[])?;
@@ -768,6 +1581,127 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_response_to_logprobs() -> anyhow::Result<()> {
+        let response = json!({
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hi there"},
+                "logprobs": {
+                    "content": [
+                        {
+                            "token": "Hi",
+                            "logprob": -0.5,
+                            "bytes": [72, 105],
+                            "top_logprobs": [
+                                {"token": "Hi", "logprob": -0.5, "bytes": [72, 105]},
+                                {"token": "Hello", "logprob": -1.25, "bytes": [72, 101, 108, 108, 111]}
+                            ]
+                        },
+                        {
+                            "token": " there",
+                            "logprob": -1.5,
+                            "bytes": [32, 116, 104, 101, 114, 101],
+                            "top_logprobs": []
+                        }
+                    ],
+                    "refusal": null
+                },
+                "finish_reason": "stop"
+            }]
+        });
+
+        let logprobs = response_to_logprobs(&response).unwrap();
+        assert_eq!(logprobs.tokens.len(), 2);
+        assert_eq!(logprobs.tokens[0].token, "Hi");
+        assert_eq!(logprobs.tokens[0].top_logprobs[1].token, "Hello");
+        assert_eq!(logprobs.tokens[0].top_logprobs[1].logprob, -1.25);
+        assert!(logprobs.tokens[1].top_logprobs.is_empty());
+        // exp(-(-0.5 + -1.5) / 2) = e
+        let perplexity = logprobs.perplexity().unwrap();
+        assert!((perplexity - std::f64::consts::E).abs() < 1e-9);
+
+        // Responses without logprobs have none, rather than failing
+        let plain = json!({"choices": [{"message": {"content": "Hi"}, "logprobs": null}]});
+        assert!(response_to_logprobs(&plain).is_none());
+        let empty = json!({"choices": [{"logprobs": {"content": null, "refusal": null}}]});
+        assert!(response_to_logprobs(&empty).is_none());
+
+        // A refusal has no content, its tokens are under the refusal
+        let refusal = json!({
+            "choices": [{
+                "message": {"role": "assistant", "content": null, "refusal": "No"},
+                "logprobs": {
+                    "content": null,
+                    "refusal": [{"token": "No", "logprob": -0.25, "top_logprobs": []}]
+                }
+            }]
+        });
+        let logprobs = response_to_logprobs(&refusal).unwrap();
+        assert_eq!(logprobs.tokens.len(), 1);
+        assert_eq!(logprobs.tokens[0].token, "No");
+        assert_eq!(logprobs.tokens[0].logprob, -0.25);
+        assert_eq!(Logprobs::default().perplexity(), None);
+
+        let mut payload = json!({"model": "gpt-4o"});
+        add_logprobs(&mut payload, &CompletionOptions::default());
+        assert!(payload.get("logprobs").is_none());
+        add_logprobs(&mut payload, &CompletionOptions::default().with_logprobs(3));
+        assert_eq!(payload["logprobs"], true);
+        assert_eq!(payload["top_logprobs"], 3);
+
+        // Tool calls are only forbidden when there are tools to call
+        let no_tool_calls = CompletionOptions::default().with_no_tool_calls();
+        add_tool_choice(&mut payload, &no_tool_calls);
+        assert!(payload.get("tool_choice").is_none());
+        payload["tools"] = json!([]);
+        add_tool_choice(&mut payload, &no_tool_calls);
+        assert_eq!(payload["tool_choice"], "none");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_response_extra_fields_roundtrip() -> anyhow::Result<()> {
+        let original = json!({
+            "role": "assistant",
+            "content": null,
+            "refusal": "I can't help with that.",
+            "audio": null,
+            "future_field": {"kind": "unknown", "values": [1, 2]}
+        });
+        let response = json!({
+            "choices": [{"index": 0, "message": original, "finish_reason": "stop"}]
+        });
+        let message = response_to_message(response)?;
+        assert_eq!(message.as_concat_text(), "Refused: I can't help with that.");
+
+        // Saved and loaded with the session
+        let loaded: Message = serde_json::from_str(&serde_json::to_string(&message)?)?;
+        assert_eq!(loaded, message);
+
+        // Sent back as it was received, without the null field
+        let mut expected = original.clone();
+        expected.as_object_mut().unwrap().remove("audio");
+        let spec = format_messages(&[loaded.clone()], &ImageFormat::OpenAi);
+        assert_eq!(spec.len(), 1);
+        assert_eq!(serde_json::to_string(&spec[0])?, expected.to_string());
+
+        // Other formats only see the refusal as text
+        let anthropic = super::super::anthropic::format_messages(&[loaded]);
+        assert!(anthropic[0].get("future_field").is_none());
+        assert_eq!(
+            anthropic[0]["content"][0]["text"],
+            "Refused: I can't help with that."
+        );
+
+        // Messages without extra fields are unchanged
+        let plain = Message::assistant().with_text("Hello");
+        assert!(plain.extra.is_empty());
+        assert!(!serde_json::to_string(&plain)?.contains("extra"));
+        Ok(())
+    }
+
     #[test]
     fn test_response_to_message_valid_toolrequest() -> anyhow::Result<()> {
         let response: Value = serde_json::from_str(OPENAI_TOOL_USE_RESPONSE)?;
@@ -785,6 +1719,62 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_response_to_message_empty_content_with_toolrequest() -> anyhow::Result<()> {
+        let mut response: Value = serde_json::from_str(OPENAI_TOOL_USE_RESPONSE)?;
+        response["choices"][0]["message"]["content"] = json!("");
+        let message = response_to_message(response)?;
+
+        assert_eq!(message.content.len(), 1);
+        assert!(matches!(
+            &message.content[0],
+            MessageContent::ToolRequest(request) if request.id == "1"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_response_to_message_with_raw() -> anyhow::Result<()> {
+        let response: Value = serde_json::from_str(OPENAI_TOOL_USE_RESPONSE)?;
+        let (message, raw) = response_to_message_with_raw(response.clone())?;
+
+        assert_eq!(raw, response["choices"][0]);
+        assert_eq!(message.content, response_to_message(response)?.content);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_request_rejects_corrupted_image() {
+        let config = ModelConfig::new("gpt-4o".to_string());
+        let messages = vec![Message::user().with_image("iVBORw0K*Ggo=", "image/png")];
+        let error = create_request(&config, "system", &messages, &[], &ImageFormat::OpenAi)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("offset 8"), "{}", error);
+    }
+
+    #[test]
+    fn test_response_to_message_tool_call_without_id() -> anyhow::Result<()> {
+        let mut response: Value = serde_json::from_str(OPENAI_TOOL_USE_RESPONSE)?;
+        let tool_call = &mut response["choices"][0]["message"]["tool_calls"][0];
+        tool_call.as_object_mut().unwrap().remove("id");
+
+        let message = response_to_message(response.clone())?;
+        let id = &message.content[0].as_tool_request().unwrap().id;
+        assert!(!id.is_empty());
+        assert_eq!(
+            &response_to_message(response)?.content[0]
+                .as_tool_request()
+                .unwrap()
+                .id,
+            id
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_response_to_message_invalid_func_name() -> anyhow::Result<()> {
         let mut response: Value = serde_json::from_str(OPENAI_TOOL_USE_RESPONSE)?;
@@ -807,6 +1797,94 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_repro_info() -> anyhow::Result<()> {
+        let model_config = ModelConfig::new("gpt-4o".to_string()).with_seed(Some(42));
+        let request = create_request(
+            &model_config,
+            "system",
+            &[Message::user().with_text("Hello")],
+            &[],
+            &ImageFormat::OpenAi,
+        )?;
+        assert_eq!(request["seed"], json!(42));
+
+        let response = json!({
+            "id": "chatcmpl-1",
+            "system_fingerprint": "fp_ee1d74bde0",
+            "choices": [{"message": {"role": "assistant", "content": "Hi"}}]
+        });
+        let first = ReproInfo::new(&model_config, &response);
+        assert_eq!(
+            first,
+            ReproInfo {
+                seed: Some(42),
+                system_fingerprint: Some("fp_ee1d74bde0".to_string()),
+            }
+        );
+        assert!(!first.backend_changed(&first.clone()));
+
+        let changed = ReproInfo::new(
+            &model_config,
+            &json!({"system_fingerprint": "fp_44709d6fcb", "choices": []}),
+        );
+        assert!(first.backend_changed(&changed));
+
+        // Without a fingerprint, or with another seed, there is nothing to compare
+        let unknown = ReproInfo::new(&model_config, &json!({"choices": []}));
+        assert_eq!(unknown.system_fingerprint, None);
+        assert!(!first.backend_changed(&unknown));
+        let reseeded = ReproInfo::new(&model_config.clone().with_seed(Some(7)), &response);
+        assert!(!first.backend_changed(&reseeded));
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_tool_calls() {
+        let response = json!({
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [
+                        {
+                            "id": "call_1",
+                            "type": "function",
+                            "function": {
+                                "name": "developer__shell",
+                                "arguments": "{\"command\": \"ls\"}"
+                            }
+                        },
+                        {
+                            "id": "call_2",
+                            "type": "function",
+                            "function": {"name": "bad name", "arguments": "{}"}
+                        }
+                    ]
+                }
+            }]
+        });
+
+        let tool_calls = extract_tool_calls(&response);
+        assert_eq!(tool_calls.len(), 2);
+        assert_eq!(
+            tool_calls[0].as_ref().unwrap(),
+            &ToolCall::new("developer__shell", json!({"command": "ls"}))
+        );
+        assert!(matches!(&tool_calls[1], Err(ToolError::NotFound(msg))
+            if msg.starts_with("The provided function name 'bad name'")));
+
+        // The calls are those of the message
+        let message = response_to_message(response).unwrap();
+        let requests: Vec<_> = message
+            .content
+            .iter()
+            .filter_map(|content| content.as_tool_request())
+            .map(|request| request.tool_call.clone())
+            .collect();
+        assert_eq!(requests, tool_calls);
+    }
+
     #[test]
     fn test_response_to_message_json_decode_error() -> anyhow::Result<()> {
         let mut response: Value = serde_json::from_str(OPENAI_TOOL_USE_RESPONSE)?;
@@ -856,9 +1934,23 @@ mod tests {
             tokenizer_name: "gpt-4o".to_string(),
             context_limit: Some(4096),
             temperature: None,
+            temperature_policy: Default::default(),
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            legacy_function_calling: false,
+            allow_system_only: true,
+            strict_tools: None,
+            tool_schema_verbosity: Default::default(),
+            tool_content_separator: Default::default(),
+            tool_image_policy: Default::default(),
+            max_tools: None,
+            max_message_bytes: None,
+            max_images: None,
+            max_request_bytes: None,
+            seed: None,
+            metadata: None,
+            no_validate: false,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -888,9 +1980,23 @@ mod tests {
             tokenizer_name: "o1".to_string(),
             context_limit: Some(4096),
             temperature: None,
+            temperature_policy: Default::default(),
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            legacy_function_calling: false,
+            allow_system_only: true,
+            strict_tools: None,
+            tool_schema_verbosity: Default::default(),
+            tool_content_separator: Default::default(),
+            tool_image_policy: Default::default(),
+            max_tools: None,
+            max_message_bytes: None,
+            max_images: None,
+            max_request_bytes: None,
+            seed: None,
+            metadata: None,
+            no_validate: false,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -921,9 +2027,23 @@ mod tests {
             tokenizer_name: "o3-mini".to_string(),
             context_limit: Some(4096),
             temperature: None,
+            temperature_policy: Default::default(),
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            legacy_function_calling: false,
+            allow_system_only: true,
+            strict_tools: None,
+            tool_schema_verbosity: Default::default(),
+            tool_content_separator: Default::default(),
+            tool_image_policy: Default::default(),
+            max_tools: None,
+            max_message_bytes: None,
+            max_images: None,
+            max_request_bytes: None,
+            seed: None,
+            metadata: None,
+            no_validate: false,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -945,4 +2065,432 @@ mod tests {
 
         Ok(())
     }
+
+    fn hello() -> Vec<Message> {
+        vec![Message::user().with_text("Hello")]
+    }
+
+    #[test]
+    fn test_create_request_without_messages() -> anyhow::Result<()> {
+        let model_config = ModelConfig::new("gpt-4o".to_string());
+        let error =
+            create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi).unwrap_err();
+        assert!(error.to_string().contains("without messages"));
+
+        let model_config = model_config.with_allow_system_only(true);
+        let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
+        assert_eq!(
+            request["messages"],
+            json!([{"role": "system", "content": "system"}])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_request_checks_temperature() -> anyhow::Result<()> {
+        let model_config = ModelConfig::new("gpt-4o".to_string()).with_temperature(Some(3.0));
+        let request = create_request(&model_config, "system", &hello(), &[], &ImageFormat::OpenAi)?;
+        assert_eq!(request["temperature"], json!(2.0));
+
+        let model_config = model_config.with_temperature_policy(TemperaturePolicy::Reject);
+        assert!(
+            create_request(&model_config, "system", &hello(), &[], &ImageFormat::OpenAi).is_err()
+        );
+
+        // Reasoning models don't take a temperature, so it is dropped rather than rejected
+        let model_config = ModelConfig::new("o3-mini".to_string())
+            .with_temperature(Some(3.0))
+            .with_temperature_policy(TemperaturePolicy::Reject);
+        let request = create_request(&model_config, "system", &hello(), &[], &ImageFormat::OpenAi)?;
+        assert!(request.get("temperature").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_request_with_metadata() -> anyhow::Result<()> {
+        let metadata = json!({"session_id": "abc123", "team": "goose"});
+        let model_config =
+            ModelConfig::new("gpt-4o".to_string()).with_metadata(metadata.as_object().cloned());
+        let request = create_request(&model_config, "system", &hello(), &[], &ImageFormat::OpenAi)?;
+        assert_eq!(request["metadata"], metadata);
+
+        let model_config = ModelConfig::new("gpt-4o".to_string());
+        let request = create_request(&model_config, "system", &hello(), &[], &ImageFormat::OpenAi)?;
+        assert!(request.get("metadata").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_request_rejects_non_string_metadata() {
+        let metadata = json!({"session_id": "abc123", "attempt": 2});
+        let model_config =
+            ModelConfig::new("gpt-4o".to_string()).with_metadata(metadata.as_object().cloned());
+        let error = create_request(&model_config, "system", &hello(), &[], &ImageFormat::OpenAi)
+            .unwrap_err();
+        assert_eq!(
+            ProviderError::from(error).to_string(),
+            "Request failed: OpenAI request metadata values must be strings, 'attempt' is 2"
+        );
+    }
+
+    #[test]
+    fn test_create_request_legacy_functions() -> anyhow::Result<()> {
+        let model_config =
+            ModelConfig::new("gpt-4o".to_string()).with_legacy_function_calling(true);
+        let tool = Tool::new(
+            "developer__shell",
+            "Run a shell command",
+            json!({"type": "object", "properties": {"command": {"type": "string"}}}),
+            None,
+        );
+        let messages = vec![
+            Message::user().with_text("Where am I?"),
+            Message::assistant()
+                .with_tool_request(
+                    "1",
+                    Ok(ToolCall::new("developer__shell", json!({"command": "pwd"}))),
+                )
+                .with_tool_request(
+                    "2",
+                    Ok(ToolCall::new("developer__shell", json!({"command": "ls"}))),
+                ),
+            Message::user()
+                .with_tool_response("1", Ok(vec![Content::text("/home")]))
+                .with_tool_response("2", Ok(vec![Content::text("goose")])),
+        ];
+        let request = create_request(
+            &model_config,
+            "system",
+            &messages,
+            &[tool],
+            &ImageFormat::OpenAi,
+        )?;
+
+        assert!(request.get("tools").is_none());
+        assert_eq!(request["functions"][0]["name"], "developer__shell");
+        assert_eq!(request["functions"][0]["parameters"]["required"], json!([]));
+        assert_eq!(
+            request["messages"],
+            json!([
+                {"role": "system", "content": "system"},
+                {"role": "user", "content": "Where am I?"},
+                {
+                    "role": "assistant",
+                    "content": null,
+                    "function_call": {"name": "developer__shell", "arguments": "{\"command\":\"pwd\"}"}
+                },
+                {"role": "function", "name": "developer__shell", "content": "/home"},
+                {
+                    "role": "assistant",
+                    "content": null,
+                    "function_call": {"name": "developer__shell", "arguments": "{\"command\":\"ls\"}"}
+                },
+                {"role": "function", "name": "developer__shell", "content": "goose"},
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_response_to_message_legacy_function_call() -> anyhow::Result<()> {
+        let response = json!({
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "function_call": {
+                        "name": "developer__shell",
+                        "arguments": "{\"command\": \"pwd\"}"
+                    }
+                },
+                "finish_reason": "function_call"
+            }]
+        });
+        let message = {
+            let _deterministic = Deterministic::seeded(1);
+            response_to_message(response)?
+        };
+        assert_eq!(message.content.len(), 1);
+        let MessageContent::ToolRequest(request) = &message.content[0] else {
+            panic!("Expected a tool request");
+        };
+        assert_eq!(request.id, "910a2dec-8902-4cc1-beeb-8da1658eec67");
+        let tool_call = request.tool_call.as_ref().unwrap();
+        assert_eq!(tool_call.name, "developer__shell");
+        assert_eq!(tool_call.arguments, json!({"command": "pwd"}));
+
+        // An invalid name is reported like with tool calls
+        let response = json!({
+            "choices": [{"message": {"function_call": {"name": "rm -rf", "arguments": "{}"}}}]
+        });
+        let message = response_to_message(response)?;
+        let MessageContent::ToolRequest(request) = &message.content[0] else {
+            panic!("Expected a tool request");
+        };
+        assert!(matches!(request.tool_call, Err(ToolError::NotFound(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_response_to_message_with_sequential_ids() -> anyhow::Result<()> {
+        let ids = SequentialToolCallIds::new();
+        let response = json!({
+            "choices": [{"message": {"function_call": {"name": "developer__shell", "arguments": "{}"}}}]
+        });
+        for expected in ["call_0", "call_1"] {
+            let message = response_to_message_with_ids(response.clone(), &ids)?;
+            let MessageContent::ToolRequest(request) = &message.content[0] else {
+                panic!("Expected a tool request");
+            };
+            assert_eq!(request.id, expected);
+        }
+
+        // Ids from the provider are kept
+        let response = json!({
+            "choices": [{"message": {"tool_calls": [{
+                "id": "call_abc",
+                "type": "function",
+                "function": {"name": "developer__shell", "arguments": "{}"}
+            }]}}]
+        });
+        let message = response_to_message_with_ids(response, &ids)?;
+        assert_eq!(message.content[0].as_tool_request().unwrap().id, "call_abc");
+        assert_eq!(ids.next_id(), "call_2");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_request_azure_deployment() -> anyhow::Result<()> {
+        let model_config = ModelConfig::new("o3-mini-high".to_string());
+        let request = create_request_for_target(
+            &model_config,
+            "system",
+            &hello(),
+            &[],
+            &ImageFormat::OpenAi,
+            RequestTarget::AzureDeployment,
+        )?;
+        assert!(request.get("model").is_none());
+        // Settings derived from the model name still apply
+        assert_eq!(request["reasoning_effort"], "high");
+
+        let request = create_request(&model_config, "system", &hello(), &[], &ImageFormat::OpenAi)?;
+        assert_eq!(request["model"], "o3-mini");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_request_with_system_messages() -> anyhow::Result<()> {
+        let systems = vec!["You are goose.".to_string(), "Be brief.".to_string()];
+        let request = create_request_with_system_messages(
+            &ModelConfig::new("gpt-4o".to_string()),
+            &systems,
+            &hello(),
+            &[],
+            &ImageFormat::OpenAi,
+            RequestTarget::OpenAi,
+        )?;
+        let messages = request["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(
+            messages[0],
+            json!({"role": "system", "content": "You are goose."})
+        );
+        assert_eq!(
+            messages[1],
+            json!({"role": "system", "content": "Be brief."})
+        );
+        assert_eq!(messages[2]["role"], "user");
+
+        // The o-series takes them as developer messages
+        let request = create_request_with_system_messages(
+            &ModelConfig::new("o3-mini".to_string()),
+            &systems,
+            &hello(),
+            &[],
+            &ImageFormat::OpenAi,
+            RequestTarget::OpenAi,
+        )?;
+        assert_eq!(request["messages"][0]["role"], "developer");
+        assert_eq!(request["messages"][1]["role"], "developer");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_request_joins_system_blocks() -> anyhow::Result<()> {
+        let mut prompt = SystemPrompt::new();
+        prompt.push_stable("You are a helpful assistant.");
+        prompt.push_dynamic("# Workspace\n\nOn branch main");
+        let model_config = ModelConfig::new("gpt-4o".to_string());
+
+        let payload = create_request(
+            &model_config,
+            &prompt.encode(),
+            &hello(),
+            &[],
+            &ImageFormat::OpenAi,
+        )?;
+        assert_eq!(
+            payload["messages"][0]["content"],
+            "You are a helpful assistant.\n\n# Workspace\n\nOn branch main"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_request_strict_tools() -> anyhow::Result<()> {
+        let shell = Tool::new(
+            "developer__shell",
+            "Run a command",
+            json!({
+                "type": "object",
+                "required": ["command"],
+                "properties": {
+                    "command": {"type": "string"},
+                    "timeout": {"type": "integer", "minimum": 1}
+                }
+            }),
+            None,
+        );
+        let env = Tool::new(
+            "developer__set_env",
+            "Set environment variables",
+            json!({
+                "type": "object",
+                "properties": {"vars": {"type": "object", "additionalProperties": {"type": "string"}}}
+            }),
+            None,
+        );
+        let request = |model_config: &ModelConfig, tools: &[Tool]| {
+            create_request(
+                model_config,
+                "system",
+                &hello(),
+                tools,
+                &ImageFormat::OpenAi,
+            )
+        };
+
+        // The model registry turns strict tools on, and leaves out schemas that can't be strict
+        let model_config = ModelConfig::new("gpt-4o".to_string());
+        let payload = request(&model_config, &[shell.clone(), env.clone()])?;
+        let function = &payload["tools"][0]["function"];
+        assert_eq!(function["strict"], true);
+        assert_eq!(
+            function["parameters"],
+            json!({
+                "type": "object",
+                "additionalProperties": false,
+                "required": ["command", "timeout"],
+                "properties": {
+                    "command": {"type": "string"},
+                    "timeout": {"type": ["integer", "null"]}
+                }
+            })
+        );
+        assert!(payload["tools"][1]["function"].get("strict").is_none());
+        assert_eq!(
+            payload["tools"][1]["function"]["parameters"]["properties"]["vars"]
+                ["additionalProperties"],
+            json!({"type": "string"})
+        );
+
+        // Asked for explicitly, a schema that can't be strict fails the request
+        let model_config = model_config.with_strict_tools(Some(true));
+        let error = request(&model_config, &[env.clone()]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Tool 'developer__set_env' can't be sent in strict mode: #/properties/vars allows arbitrary keys"
+        );
+
+        let model_config = ModelConfig::new("llama3.3".to_string());
+        let payload = request(&model_config, &[shell.clone()])?;
+        assert!(payload["tools"][0]["function"].get("strict").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_request_minifies_tool_schemas() -> anyhow::Result<()> {
+        let tool = Tool::new(
+            "search",
+            "Search   the\n web",
+            json!({
+                "type": "object",
+                "title": "SearchArgs",
+                "required": ["query"],
+                "properties": {"query": {"type": "string", "examples": ["rust"]}}
+            }),
+            None,
+        );
+        let model_config = ModelConfig::new("gpt-4o".to_string())
+            .with_tool_schema_verbosity(ToolSchemaVerbosity::FullFirstTurn)
+            .with_strict_tools(Some(false));
+
+        let first_turn = [Message::user().with_text("find rust docs")];
+        let request = create_request(
+            &model_config,
+            "system",
+            &first_turn,
+            &[tool.clone()],
+            &ImageFormat::OpenAi,
+        )?;
+        assert_eq!(
+            request["tools"][0]["function"]["parameters"],
+            tool.input_schema
+        );
+
+        let later_turn = [
+            Message::user().with_text("find rust docs"),
+            Message::assistant().with_text("Which ones?"),
+            Message::user().with_text("the book"),
+        ];
+        let request = create_request(
+            &model_config,
+            "system",
+            &later_turn,
+            &[tool],
+            &ImageFormat::OpenAi,
+        )?;
+        let function = &request["tools"][0]["function"];
+        assert_eq!(function["description"], "Search the web");
+        assert_eq!(
+            function["parameters"],
+            json!({
+                "type": "object",
+                "required": ["query"],
+                "properties": {"query": {"type": "string"}}
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_finish_reasons() {
+        assert_eq!(parse_finish_reason("stop"), FinishReason::Stop);
+        assert_eq!(parse_finish_reason("length"), FinishReason::Length);
+        assert_eq!(parse_finish_reason("tool_calls"), FinishReason::ToolCalls);
+        assert_eq!(
+            parse_finish_reason("function_call"),
+            FinishReason::ToolCalls
+        );
+        assert_eq!(
+            parse_finish_reason("content_filter"),
+            FinishReason::ContentFilter
+        );
+        assert_eq!(
+            parse_finish_reason("insufficient_system_resource"),
+            FinishReason::Other("insufficient_system_resource".to_string())
+        );
+
+        let response = json!({"choices": [{"finish_reason": "length"}]});
+        assert_eq!(get_finish_reason(&response), Some(FinishReason::Length));
+        assert_eq!(get_finish_reason(&json!({"choices": []})), None);
+    }
 }
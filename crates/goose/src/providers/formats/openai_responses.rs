@@ -0,0 +1,315 @@
+//! Conversion for the OpenAI Responses API
+//!
+//! Requests are built as Chat Completions payloads by [`openai::create_request`] and then
+//! rewritten, so both APIs send the same conversation. Tool calls and their results become
+//! `function_call` and `function_call_output` input items instead of fields and messages.
+
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+use crate::providers::formats::openai;
+use crate::providers::tool_call_ids::UuidToolCallIds;
+use crate::providers::utils::{tool_call_id, ImageFormat};
+use anyhow::anyhow;
+use mcp_core::{Role, Tool};
+use serde_json::{json, Map, Value};
+
+pub use openai::get_usage;
+
+pub fn create_request(
+    model_config: &ModelConfig,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+    image_format: &ImageFormat,
+) -> anyhow::Result<Value> {
+    // Tool calls are items of their own, the legacy form has no equivalent
+    let model_config = model_config.clone().with_legacy_function_calling(false);
+    let Value::Object(chat) =
+        openai::create_request(&model_config, system, messages, tools, image_format)?
+    else {
+        return Err(anyhow!("Chat Completions payload is not an object"));
+    };
+
+    let mut payload = Map::new();
+    for (key, value) in chat {
+        match key.as_str() {
+            "messages" => {
+                let mut messages = match value {
+                    Value::Array(messages) => messages.into_iter(),
+                    _ => Vec::new().into_iter(),
+                };
+                // The system or developer message always comes first
+                if let Some(system) = messages.next() {
+                    payload.insert("instructions".to_string(), system["content"].clone());
+                }
+                payload.insert("input".to_string(), json!(to_input_items(messages)));
+            }
+            "tools" => {
+                let tools: Vec<Value> = value
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(flatten_tool)
+                    .collect();
+                payload.insert(key, json!(tools));
+            }
+            "reasoning_effort" => {
+                payload.insert(
+                    "reasoning".to_string(),
+                    json!({ "effort": value, "summary": "auto" }),
+                );
+            }
+            "max_tokens" | "max_completion_tokens" => {
+                payload.insert("max_output_tokens".to_string(), value);
+            }
+            _ => {
+                payload.insert(key, value);
+            }
+        }
+    }
+    Ok(Value::Object(payload))
+}
+
+/// Rewrite formatted Chat Completions messages to Responses input items
+fn to_input_items(messages: impl Iterator<Item = Value>) -> Vec<Value> {
+    let mut items = Vec::new();
+    for message in messages {
+        let role = message["role"].as_str().unwrap_or("user").to_string();
+        if role == "tool" {
+            items.push(json!({
+                "type": "function_call_output",
+                "call_id": message["tool_call_id"],
+                "output": message["content"],
+            }));
+            continue;
+        }
+
+        if !message["content"].is_null() {
+            items.push(json!({
+                "role": role,
+                "content": input_content(&role, &message["content"]),
+            }));
+        }
+        for tool_call in message["tool_calls"].as_array().into_iter().flatten() {
+            items.push(json!({
+                "type": "function_call",
+                "call_id": tool_call["id"],
+                "name": tool_call["function"]["name"],
+                "arguments": tool_call["function"]["arguments"],
+            }));
+        }
+    }
+    items
+}
+
+/// Content parts are typed by direction, text the model wrote is output text
+fn input_content(role: &str, content: &Value) -> Value {
+    let Some(parts) = content.as_array() else {
+        return content.clone();
+    };
+    let text_type = if role == "assistant" {
+        "output_text"
+    } else {
+        "input_text"
+    };
+    let parts: Vec<Value> = parts
+        .iter()
+        .map(|part| match part["type"].as_str() {
+            Some("text") => json!({ "type": text_type, "text": part["text"] }),
+            Some("image_url") => json!({
+                "type": "input_image",
+                "image_url": part["image_url"]["url"],
+            }),
+            _ => part.clone(),
+        })
+        .collect();
+    json!(parts)
+}
+
+/// Tools have their function fields at the top level
+fn flatten_tool(tool: &Value) -> Value {
+    let mut flattened = Map::new();
+    flattened.insert("type".to_string(), json!("function"));
+    if let Some(function) = tool["function"].as_object() {
+        flattened.extend(function.clone());
+    }
+    Value::Object(flattened)
+}
+
+/// Convert the `output` items of a response to a message
+///
+/// Reasoning summaries become thinking content, which has no signature in this API, so formats
+/// that require one, such as Anthropic's, leave them out.
+pub fn response_to_message(response: Value) -> anyhow::Result<Message> {
+    let mut content = Vec::new();
+    let items = response["output"].as_array().cloned().unwrap_or_default();
+    for (index, item) in items.iter().enumerate() {
+        match item["type"].as_str() {
+            Some("message") => {
+                for part in item["content"].as_array().into_iter().flatten() {
+                    let text = match part["type"].as_str() {
+                        Some("output_text") => part["text"].as_str(),
+                        Some("refusal") => part["refusal"].as_str(),
+                        _ => None,
+                    };
+                    if let Some(text) = text {
+                        content.push(MessageContent::text(text));
+                        let citations = openai::annotation_citations(text, &part["annotations"]);
+                        if !citations.is_empty() {
+                            content.push(MessageContent::citations(citations));
+                        }
+                    }
+                }
+            }
+            Some("reasoning") => {
+                let summary: Vec<&str> = item["summary"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|part| part["text"].as_str())
+                    .collect();
+                if !summary.is_empty() {
+                    content.push(MessageContent::thinking(summary.join("\n\n"), ""));
+                }
+            }
+            Some("function_call") => {
+                let id = tool_call_id(
+                    &response,
+                    &json!({ "id": item["call_id"] }),
+                    index,
+                    &UuidToolCallIds,
+                );
+                content.push(openai::function_to_tool_request(id, item));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(Message {
+        role: Role::Assistant,
+        created: crate::determinism::now().timestamp(),
+        content,
+        extra: Default::default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::content::Content;
+    use mcp_core::ToolCall;
+
+    fn shell_tool() -> Tool {
+        Tool::new(
+            "developer__shell",
+            "Run a shell command.",
+            json!({
+                "type": "object",
+                "required": ["command"],
+                "properties": {"command": {"type": "string"}}
+            }),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_create_request() -> anyhow::Result<()> {
+        let messages = vec![
+            Message::user().with_text("List the files"),
+            Message::assistant()
+                .with_text("Let me look.")
+                .with_tool_request(
+                    "call_1",
+                    Ok(ToolCall::new("developer__shell", json!({"command": "ls"}))),
+                ),
+            Message::user().with_tool_response("call_1", Ok(vec![Content::text("README.md")])),
+        ];
+        let config = ModelConfig::new("o3-high".to_string())
+            .with_max_tokens(Some(1024))
+            .with_legacy_function_calling(true);
+        let request = create_request(
+            &config,
+            "system",
+            &messages,
+            &[shell_tool()],
+            &ImageFormat::OpenAi,
+        )?;
+
+        assert_eq!(request["model"], "o3");
+        assert_eq!(request["instructions"], "system");
+        assert_eq!(
+            request["reasoning"],
+            json!({"effort": "high", "summary": "auto"})
+        );
+        assert_eq!(request["max_output_tokens"], 1024);
+        assert!(request.get("messages").is_none());
+        assert_eq!(
+            request["input"],
+            json!([
+                {"role": "user", "content": "List the files"},
+                {"role": "assistant", "content": "Let me look."},
+                {
+                    "type": "function_call",
+                    "call_id": "call_1",
+                    "name": "developer__shell",
+                    "arguments": "{\"command\":\"ls\"}"
+                },
+                {"type": "function_call_output", "call_id": "call_1", "output": "README.md"}
+            ])
+        );
+        assert_eq!(request["tools"][0]["type"], "function");
+        assert_eq!(request["tools"][0]["name"], "developer__shell");
+        assert!(request["tools"][0].get("function").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_response_to_message() -> anyhow::Result<()> {
+        let response = json!({
+            "id": "resp_1",
+            "output": [
+                {
+                    "type": "reasoning",
+                    "id": "rs_1",
+                    "summary": [
+                        {"type": "summary_text", "text": "The user wants a listing."},
+                        {"type": "summary_text", "text": "Use the shell."}
+                    ]
+                },
+                {
+                    "type": "message",
+                    "role": "assistant",
+                    "content": [{"type": "output_text", "text": "Listing files.", "annotations": []}]
+                },
+                {
+                    "type": "function_call",
+                    "id": "fc_1",
+                    "call_id": "call_1",
+                    "name": "developer__shell",
+                    "arguments": "{\"command\":\"ls\"}"
+                }
+            ],
+            "usage": {"input_tokens": 12, "output_tokens": 34, "total_tokens": 46}
+        });
+
+        let message = response_to_message(response.clone())?;
+        assert_eq!(message.content.len(), 3);
+        assert_eq!(
+            message.content[0].as_thinking().unwrap().thinking,
+            "The user wants a listing.\n\nUse the shell."
+        );
+        assert_eq!(message.content[1].as_text(), Some("Listing files."));
+        let request = message.content[2].as_tool_request().unwrap();
+        assert_eq!(request.id, "call_1");
+        assert_eq!(
+            request.tool_call.as_ref().unwrap().arguments,
+            json!({"command": "ls"})
+        );
+
+        let usage = get_usage(&response)?;
+        assert_eq!(usage.input_tokens, Some(12));
+        assert_eq!(usage.output_tokens, Some(34));
+        assert_eq!(usage.total_tokens, Some(46));
+        Ok(())
+    }
+}
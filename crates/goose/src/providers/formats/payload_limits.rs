@@ -0,0 +1,200 @@
+//! Limits on the images and the size of one request, see `ModelConfig::payload_limits`
+//!
+//! APIs reject a request with too many images, or too large a body, even when the
+//! conversation fits the context. The oldest images are replaced first, as the model most
+//! likely needs the newest ones. A body that is still too large is reported as exceeding the
+//! context, so the agent compacts the conversation before giving up.
+
+use std::borrow::Cow;
+use std::io;
+
+use mcp_core::Content;
+use serde_json::Value;
+
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+use crate::providers::errors::ProviderError;
+
+/// The messages with their oldest images replaced, for the `max_images` of the model
+pub fn limit_images<'a>(model_config: &ModelConfig, messages: &'a [Message]) -> Cow<'a, [Message]> {
+    match model_config.payload_limits().max_images {
+        Some(max_images) if count_images(messages) > max_images => {
+            Cow::Owned(remove_old_images(messages, max_images))
+        }
+        _ => Cow::Borrowed(messages),
+    }
+}
+
+/// The number of images in the messages, including those in tool results
+pub fn count_images(messages: &[Message]) -> usize {
+    messages
+        .iter()
+        .flat_map(|message| &message.content)
+        .map(|content| match content {
+            MessageContent::Image(_) => 1,
+            MessageContent::ToolResponse(response) => {
+                response.tool_result.as_ref().map_or(0, |contents| {
+                    contents
+                        .iter()
+                        .filter(|content| matches!(content, Content::Image(_)))
+                        .count()
+                })
+            }
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Replace all but the newest `max_images` images with a note of where each one was
+pub fn remove_old_images(messages: &[Message], max_images: usize) -> Vec<Message> {
+    let mut to_remove = count_images(messages).saturating_sub(max_images);
+    let mut limited = messages.to_vec();
+    for (index, message) in limited.iter_mut().enumerate() {
+        for content in message.content.iter_mut() {
+            if to_remove == 0 {
+                return limited;
+            }
+            match content {
+                MessageContent::Image(_) => {
+                    *content = MessageContent::text(removed_image(&format!("message {}", index)));
+                    to_remove -= 1;
+                }
+                MessageContent::ToolResponse(response) => {
+                    if let Ok(contents) = &mut response.tool_result {
+                        for item in contents.iter_mut() {
+                            if to_remove > 0 && matches!(item, Content::Image(_)) {
+                                let source = format!("tool result {}", response.id);
+                                *item = Content::text(removed_image(&source));
+                                to_remove -= 1;
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    limited
+}
+
+fn removed_image(source: &str) -> String {
+    format!(
+        "[image removed: exceeded provider limit, originally from {}]",
+        source
+    )
+}
+
+/// Refuse a request body larger than the `max_request_bytes` of the model
+///
+/// This is a context length error, so the agent compacts the conversation and retries.
+pub fn check_request_size(
+    model_config: &ModelConfig,
+    payload: &Value,
+) -> Result<(), ProviderError> {
+    let Some(max_bytes) = model_config.payload_limits().max_request_bytes else {
+        return Ok(());
+    };
+    let bytes = request_bytes(payload);
+    if bytes > max_bytes {
+        return Err(ProviderError::ContextLengthExceeded(format!(
+            "The request is {} bytes, more than the {} bytes accepted for {}",
+            bytes, max_bytes, model_config.model_name
+        )));
+    }
+    Ok(())
+}
+
+/// The size of the payload as a JSON request body
+pub fn request_bytes(payload: &Value) -> usize {
+    let mut counter = ByteCounter(0);
+    // Writing to the counter can't fail
+    let _ = serde_json::to_writer(&mut counter, payload);
+    counter.0
+}
+
+/// Counts the bytes written to it, to size a body without building it
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const PNG_DATA: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=";
+
+    fn screenshot(id: usize) -> Message {
+        Message::user().with_tool_response(
+            format!("call_{}", id),
+            Ok(vec![
+                Content::text(format!("screenshot {}", id)),
+                Content::image(PNG_DATA, "image/png"),
+            ]),
+        )
+    }
+
+    #[test]
+    fn test_newest_images_survive() {
+        let mut messages = vec![Message::user()
+            .with_text("Compare these")
+            .with_image(PNG_DATA, "image/png")];
+        messages.extend((1..=30).map(screenshot));
+        assert_eq!(count_images(&messages), 31);
+
+        let model_config =
+            ModelConfig::new("claude-sonnet-4".to_string()).with_max_images(Some(20));
+        let limited = limit_images(&model_config, &messages);
+        assert_eq!(count_images(&limited), 20);
+        assert_eq!(
+            limited[0].content[1].as_text(),
+            Some("[image removed: exceeded provider limit, originally from message 0]")
+        );
+        assert_eq!(
+            limited[10].content[0].as_tool_response_text().unwrap(),
+            "screenshot 10\n[image removed: exceeded provider limit, originally from tool result call_10]"
+        );
+        // The last 20 screenshots are untouched
+        assert_eq!(&limited[11..], &messages[11..]);
+
+        // Under the limit, nothing is copied
+        let model_config = model_config.with_max_images(Some(31));
+        assert!(matches!(
+            limit_images(&model_config, &messages),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_check_request_size() {
+        let payload = json!({"messages": [{"role": "user", "content": "x".repeat(1000)}]});
+        let bytes = request_bytes(&payload);
+        assert_eq!(bytes, serde_json::to_vec(&payload).unwrap().len());
+
+        let model_config = ModelConfig::new("gpt-4o".to_string());
+        assert_eq!(model_config.payload_limits().max_images, Some(500));
+        assert!(check_request_size(&model_config, &payload).is_ok());
+
+        // A body that is too large is a context length error, which the agent compacts
+        let model_config = model_config.with_max_request_bytes(Some(bytes - 1));
+        assert!(matches!(
+            check_request_size(&model_config, &payload),
+            Err(ProviderError::ContextLengthExceeded(_))
+        ));
+        // Its kind survives the anyhow errors of request builders
+        let error = anyhow::Error::from(check_request_size(&model_config, &payload).unwrap_err());
+        assert!(matches!(
+            ProviderError::from(error),
+            ProviderError::ContextLengthExceeded(_)
+        ));
+    }
+}
@@ -41,6 +41,7 @@ pub fn format_messages(messages: &[Message]) -> Vec<Value> {
                             .iter()
                             .filter_map(|c| match c {
                                 Content::Text(t) => Some(t.text.clone()),
+                                Content::Result(r) => Some(r.text.clone()),
                                 _ => None,
                             })
                             .collect::<Vec<_>>()
@@ -63,6 +64,9 @@ pub fn format_messages(messages: &[Message]) -> Vec<Value> {
                 MessageContent::SummarizationRequested(_) => {
                     // Skip
                 }
+                MessageContent::Citations(_) => {
+                    // Skip, citations are only shown to the user
+                }
                 MessageContent::Thinking(_thinking) => {
                     // Skip thinking for now
                 }
@@ -0,0 +1,299 @@
+//! Tool schemas for OpenAI's strict function calling
+//!
+//! With `"strict": true` the model's arguments always match the schema, but only a subset of
+//! JSON schema is accepted: every object lists all of its properties as required and allows no
+//! others, optional properties are expressed as nullable instead, and validation keywords such
+//! as `minLength` or `format` are not supported.
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::{json, Map, Value};
+
+/// Keywords strict mode doesn't support, which only narrow what is valid and can be dropped
+const DROPPED_KEYWORDS: &[&str] = &[
+    "$schema",
+    "default",
+    "examples",
+    "format",
+    "minLength",
+    "maxLength",
+    "pattern",
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "multipleOf",
+    "minItems",
+    "maxItems",
+    "uniqueItems",
+    "minProperties",
+    "maxProperties",
+];
+
+/// Keywords strict mode doesn't support, which change what is valid and can't be dropped
+const UNSUPPORTED_KEYWORDS: &[&str] = &[
+    "allOf",
+    "not",
+    "if",
+    "then",
+    "else",
+    "patternProperties",
+    "dependentSchemas",
+    "dependentRequired",
+    "unevaluatedProperties",
+    "prefixItems",
+];
+
+/// The schema of a tool's parameters in the form strict mode accepts
+///
+/// Optional properties become required and nullable, `oneOf` becomes `anyOf`, and validation
+/// keywords strict mode doesn't support are dropped. Schemas that allow arbitrary keys or use
+/// keywords that can't be expressed fail with the path of the offending schema.
+pub fn strict_schema(schema: &Value) -> Result<Value> {
+    if schema.get("type").and_then(Value::as_str) != Some("object") {
+        bail!("the parameters are not an object");
+    }
+    let mut schema = schema.clone();
+    make_strict(&mut schema, "#")?;
+    Ok(schema)
+}
+
+fn make_strict(schema: &mut Value, path: &str) -> Result<()> {
+    let Value::Object(object) = schema else {
+        bail!("{} is not a schema", path);
+    };
+    for keyword in DROPPED_KEYWORDS {
+        object.remove(*keyword);
+    }
+    if let Some(keyword) = UNSUPPORTED_KEYWORDS
+        .iter()
+        .find(|keyword| object.contains_key(**keyword))
+    {
+        bail!("{} uses '{}'", path, keyword);
+    }
+    if let Some(one_of) = object.remove("oneOf") {
+        object.insert("anyOf".to_string(), one_of);
+    }
+    if !["type", "anyOf", "$ref", "enum", "const"]
+        .iter()
+        .any(|keyword| object.contains_key(*keyword))
+    {
+        bail!("{} has no type", path);
+    }
+
+    for keyword in ["$defs", "definitions"] {
+        if let Some(Value::Object(definitions)) = object.get_mut(keyword) {
+            for (name, definition) in definitions.iter_mut() {
+                make_strict(definition, &format!("{}/{}/{}", path, keyword, name))?;
+            }
+        }
+    }
+    if let Some(any_of) = object.get_mut("anyOf") {
+        let Value::Array(schemas) = any_of else {
+            bail!("{}/anyOf is not a list", path);
+        };
+        for (index, schema) in schemas.iter_mut().enumerate() {
+            make_strict(schema, &format!("{}/anyOf/{}", path, index))?;
+        }
+    }
+    if let Some(items) = object.get_mut("items") {
+        make_strict(items, &format!("{}/items", path))?;
+    }
+    if has_type(object, "object") {
+        make_object_strict(object, path)?;
+    }
+    Ok(())
+}
+
+fn make_object_strict(object: &mut Map<String, Value>, path: &str) -> Result<()> {
+    match object.get("additionalProperties") {
+        None | Some(Value::Bool(false)) => {}
+        Some(_) => bail!("{} allows arbitrary keys", path),
+    }
+    object.insert("additionalProperties".to_string(), json!(false));
+
+    let required: Vec<String> = object
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| {
+            required
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    let properties = object
+        .entry("properties")
+        .or_insert_with(|| json!({}))
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("{}/properties is not an object", path))?;
+
+    let mut names = Vec::new();
+    for (name, property) in properties.iter_mut() {
+        make_strict(property, &format!("{}/properties/{}", path, name))?;
+        if !required.contains(name) {
+            make_nullable(property);
+        }
+        names.push(name.clone());
+    }
+    names.sort();
+    object.insert("required".to_string(), json!(names));
+    Ok(())
+}
+
+fn has_type(object: &Map<String, Value>, name: &str) -> bool {
+    match object.get("type") {
+        Some(Value::String(type_name)) => type_name == name,
+        Some(Value::Array(types)) => types.iter().any(|type_name| type_name == name),
+        _ => false,
+    }
+}
+
+/// Let an optional property be null, which is how strict schemas leave a value out
+fn make_nullable(schema: &mut Value) {
+    let null = json!("null");
+    let Value::Object(object) = schema else {
+        return;
+    };
+    if let Some(Value::Array(values)) = object.get_mut("enum") {
+        if !values.contains(&Value::Null) {
+            values.push(Value::Null);
+        }
+    }
+    match object.get_mut("type") {
+        Some(Value::String(type_name)) if *type_name != "null" => {
+            let type_name = type_name.clone();
+            object.insert("type".to_string(), json!([type_name, "null"]));
+        }
+        Some(Value::Array(types)) if !types.contains(&null) => types.push(null),
+        Some(_) => {}
+        None => match object.get_mut("anyOf") {
+            Some(Value::Array(schemas)) => {
+                if !schemas.iter().any(|schema| schema["type"] == null) {
+                    schemas.push(json!({"type": "null"}));
+                }
+            }
+            // A reference or constant gets an alternative, next to its own keywords
+            _ => {
+                let inner = std::mem::take(object);
+                object.insert(
+                    "anyOf".to_string(),
+                    json!([Value::Object(inner), {"type": "null"}]),
+                );
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strict_schema() {
+        // Like the developer extension's text editor
+        let schema = json!({
+            "type": "object",
+            "required": ["command", "path"],
+            "properties": {
+                "path": {"type": "string", "description": "Absolute path", "minLength": 1},
+                "command": {"type": "string", "enum": ["view", "write"]},
+                "view_range": {"type": "array", "items": {"type": "integer", "minimum": 1}},
+                "mode": {"type": "string", "enum": ["append", "replace"], "default": "replace"},
+                "options": {
+                    "type": "object",
+                    "properties": {"force": {"type": "boolean"}},
+                },
+                "target": {"oneOf": [{"type": "string"}, {"type": "integer"}]},
+            }
+        });
+
+        let strict = strict_schema(&schema).unwrap();
+        assert_eq!(
+            strict,
+            json!({
+                "type": "object",
+                "additionalProperties": false,
+                "required": ["command", "mode", "options", "path", "target", "view_range"],
+                "properties": {
+                    "path": {"type": "string", "description": "Absolute path"},
+                    "command": {"type": "string", "enum": ["view", "write"]},
+                    "view_range": {"type": ["array", "null"], "items": {"type": "integer"}},
+                    "mode": {"type": ["string", "null"], "enum": ["append", "replace", null]},
+                    "options": {
+                        "type": ["object", "null"],
+                        "additionalProperties": false,
+                        "required": ["force"],
+                        "properties": {"force": {"type": ["boolean", "null"]}},
+                    },
+                    "target": {"anyOf": [{"type": "string"}, {"type": "integer"}, {"type": "null"}]},
+                }
+            })
+        );
+        // Strict schemas stay as they are
+        assert_eq!(strict_schema(&strict).unwrap(), strict);
+    }
+
+    #[test]
+    fn test_strict_schema_without_properties() {
+        let strict = strict_schema(&json!({"type": "object"})).unwrap();
+        assert_eq!(
+            strict,
+            json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+                "additionalProperties": false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_strict_schema_references() {
+        let schema = json!({
+            "type": "object",
+            "properties": {"point": {"$ref": "#/$defs/point"}},
+            "$defs": {
+                "point": {
+                    "type": "object",
+                    "required": ["x", "y"],
+                    "properties": {"x": {"type": "number"}, "y": {"type": "number"}},
+                }
+            }
+        });
+        let strict = strict_schema(&schema).unwrap();
+        assert_eq!(
+            strict["properties"]["point"],
+            json!({"anyOf": [{"$ref": "#/$defs/point"}, {"type": "null"}]})
+        );
+        assert_eq!(strict["$defs"]["point"]["additionalProperties"], false);
+    }
+
+    #[test]
+    fn test_strict_schema_errors() {
+        let error = |schema: Value| strict_schema(&schema).unwrap_err().to_string();
+
+        assert_eq!(
+            error(json!({
+                "type": "object",
+                "properties": {"env": {"type": "object", "additionalProperties": {"type": "string"}}}
+            })),
+            "#/properties/env allows arbitrary keys"
+        );
+        assert_eq!(
+            error(json!({"type": "object", "properties": {"value": {}}})),
+            "#/properties/value has no type"
+        );
+        assert_eq!(
+            error(json!({
+                "type": "object",
+                "properties": {"n": {"allOf": [{"type": "integer"}]}}
+            })),
+            "#/properties/n uses 'allOf'"
+        );
+        assert_eq!(
+            error(json!({"type": "string"})),
+            "the parameters are not an object"
+        );
+    }
+}
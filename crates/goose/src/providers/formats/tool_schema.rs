@@ -0,0 +1,384 @@
+//! Minification of tool schemas to save prompt tokens
+//!
+//! Extensions describe their tools generously, with long descriptions, examples and titles,
+//! which costs thousands of prompt tokens on every request. Minified schemas keep everything a
+//! model needs to call a tool correctly: names, types, required fields, enums, defaults and the
+//! shape of nested objects. Only documentation is shortened or removed.
+
+use std::str::FromStr;
+
+use mcp_core::tool::Tool;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::token_counter::{estimate_tokens, TokenizerFamily};
+use mcp_core::role::Role;
+
+/// Descriptions longer than this many characters are truncated in compact schemas
+const COMPACT_DESCRIPTION_CHARS: usize = 512;
+/// Descriptions longer than this many characters are truncated in minimal schemas
+const MINIMAL_DESCRIPTION_CHARS: usize = 128;
+
+/// Schema keywords holding documentation that models don't need to call a tool
+const DOCUMENTATION_KEYWORDS: &[&str] = &["examples", "example", "title", "$comment"];
+/// Schema keywords whose value is a map from names to schemas
+const SCHEMA_MAP_KEYWORDS: &[&str] = &["properties", "patternProperties", "$defs", "definitions"];
+/// Schema keywords whose value is a schema or a list of schemas
+const SUBSCHEMA_KEYWORDS: &[&str] = &[
+    "items",
+    "additionalProperties",
+    "additionalItems",
+    "prefixItems",
+    "contains",
+    "propertyNames",
+    "anyOf",
+    "oneOf",
+    "allOf",
+    "not",
+    "if",
+    "then",
+    "else",
+];
+
+/// How much documentation tool schemas carry in requests
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolSchemaVerbosity {
+    /// Schemas as the extensions declared them
+    #[default]
+    Full,
+    /// Without examples and titles, with whitespace collapsed and long descriptions truncated
+    Compact,
+    /// Compact, with descriptions truncated much shorter
+    Minimal,
+    /// Full on the first turn of a session and compact on the turns after it
+    FullFirstTurn,
+}
+
+impl FromStr for ToolSchemaVerbosity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "full" => Ok(Self::Full),
+            "compact" => Ok(Self::Compact),
+            "minimal" => Ok(Self::Minimal),
+            "full_first_turn" => Ok(Self::FullFirstTurn),
+            _ => Err(anyhow::anyhow!(
+                "Unknown tool schema verbosity '{}', expected full, compact, minimal or full_first_turn",
+                s
+            )),
+        }
+    }
+}
+
+impl ToolSchemaVerbosity {
+    /// The verbosity for a request with these messages
+    ///
+    /// The first turn is the one before the assistant first replied. Providers that cache by
+    /// prompt prefix lose that cache when the schemas change, which happens only once here.
+    pub fn for_turn(self, messages: &[Message]) -> Self {
+        match self {
+            Self::FullFirstTurn if messages.iter().any(|m| m.role == Role::Assistant) => {
+                Self::Compact
+            }
+            Self::FullFirstTurn => Self::Full,
+            verbosity => verbosity,
+        }
+    }
+
+    fn description_limit(self) -> Option<usize> {
+        match self {
+            Self::Full | Self::FullFirstTurn => None,
+            Self::Compact => Some(COMPACT_DESCRIPTION_CHARS),
+            Self::Minimal => Some(MINIMAL_DESCRIPTION_CHARS),
+        }
+    }
+}
+
+/// Collapse whitespace and truncate a description to `limit` characters with an ellipsis
+fn minify_description(description: &str, limit: usize) -> String {
+    let collapsed = description.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.chars().count() <= limit {
+        return collapsed;
+    }
+    let mut truncated: String = collapsed.chars().take(limit.saturating_sub(1)).collect();
+    truncated.truncate(truncated.trim_end().len());
+    truncated.push('…');
+    truncated
+}
+
+/// Minify a JSON schema in place, descending into every nested schema
+pub fn minify_schema(schema: &mut Value, description_limit: usize) {
+    match schema {
+        Value::Array(schemas) => {
+            for schema in schemas {
+                minify_schema(schema, description_limit);
+            }
+        }
+        Value::Object(object) => {
+            for keyword in DOCUMENTATION_KEYWORDS {
+                object.remove(*keyword);
+            }
+            if let Some(Value::String(description)) = object.get_mut("description") {
+                *description = minify_description(description, description_limit);
+            }
+            for keyword in SCHEMA_MAP_KEYWORDS {
+                if let Some(Value::Object(schemas)) = object.get_mut(*keyword) {
+                    for schema in schemas.values_mut() {
+                        minify_schema(schema, description_limit);
+                    }
+                }
+            }
+            for keyword in SUBSCHEMA_KEYWORDS {
+                if let Some(schema) = object.get_mut(*keyword) {
+                    minify_schema(schema, description_limit);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The tools with their descriptions and schemas minified to the verbosity
+pub fn minify_tools(tools: &[Tool], verbosity: ToolSchemaVerbosity) -> Vec<Tool> {
+    let Some(limit) = verbosity.description_limit() else {
+        return tools.to_vec();
+    };
+    tools
+        .iter()
+        .map(|tool| {
+            let mut tool = tool.clone();
+            tool.description = minify_description(&tool.description, limit);
+            minify_schema(&mut tool.input_schema, limit);
+            tool
+        })
+        .collect()
+}
+
+/// Estimated prompt tokens of the tool schemas in a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ToolSchemaTokens {
+    /// Tokens of the schemas as the extensions declared them
+    pub full: usize,
+    /// Tokens of the schemas as they are sent
+    pub sent: usize,
+}
+
+impl ToolSchemaTokens {
+    pub fn estimate(
+        tools: &[Tool],
+        verbosity: ToolSchemaVerbosity,
+        family: TokenizerFamily,
+    ) -> Self {
+        let tokens = |tools: &[Tool]| {
+            estimate_tokens(&serde_json::to_string(tools).unwrap_or_default(), family)
+        };
+        Self {
+            full: tokens(tools),
+            sent: tokens(&minify_tools(tools, verbosity)),
+        }
+    }
+
+    /// The tokens of the tool schemas in a request the model would be sent
+    pub fn for_request(model_config: &ModelConfig, messages: &[Message], tools: &[Tool]) -> Self {
+        Self::estimate(
+            tools,
+            model_config.tool_schema_verbosity.for_turn(messages),
+            model_config.tokenizer_family(),
+        )
+    }
+
+    /// Tokens saved by minifying the schemas
+    pub fn saved(&self) -> usize {
+        self.full.saturating_sub(self.sent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn verbose_tool() -> Tool {
+        Tool::new(
+            "developer__shell",
+            format!(
+                "Execute a command in the shell.\n\n    This will return the output and error.\n{}",
+                "Use it carefully. ".repeat(40)
+            ),
+            json!({
+                "type": "object",
+                "title": "ShellArguments",
+                "required": ["command"],
+                "properties": {
+                    "command": {
+                        "type": "string",
+                        "title": "Command",
+                        "description": "The   command\n to run",
+                        "examples": ["ls -la"]
+                    },
+                    "title": {
+                        "type": "string",
+                        "description": "A property that happens to be named title"
+                    },
+                    "examples": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "title": "Example",
+                            "required": ["input"],
+                            "properties": {"input": {"type": "string", "example": "x"}}
+                        }
+                    },
+                    "mode": {
+                        "anyOf": [
+                            {"type": "string", "enum": ["fast", "safe"], "title": "Mode"},
+                            {"type": "null"}
+                        ],
+                        "default": "safe"
+                    }
+                },
+                "additionalProperties": false
+            }),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_compact_removes_only_documentation() {
+        let tool = &minify_tools(&[verbose_tool()], ToolSchemaVerbosity::Compact)[0];
+        assert_eq!(
+            tool.input_schema,
+            json!({
+                "type": "object",
+                "required": ["command"],
+                "properties": {
+                    "command": {"type": "string", "description": "The command to run"},
+                    "title": {
+                        "type": "string",
+                        "description": "A property that happens to be named title"
+                    },
+                    "examples": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "required": ["input"],
+                            "properties": {"input": {"type": "string"}}
+                        }
+                    },
+                    "mode": {
+                        "anyOf": [
+                            {"type": "string", "enum": ["fast", "safe"]},
+                            {"type": "null"}
+                        ],
+                        "default": "safe"
+                    }
+                },
+                "additionalProperties": false
+            })
+        );
+        assert!(tool
+            .description
+            .starts_with("Execute a command in the shell. This will return"));
+        assert_eq!(tool.description.chars().count(), COMPACT_DESCRIPTION_CHARS);
+        assert!(tool.description.ends_with('…'));
+    }
+
+    /// Assert that a minified schema keeps every field of the original other than documentation
+    fn assert_structure_kept(full: &Value, minified: &Value, path: &str) {
+        match (full, minified) {
+            (Value::Array(full), Value::Array(minified)) => {
+                assert_eq!(full.len(), minified.len(), "{}", path);
+                for (i, (full, minified)) in full.iter().zip(minified).enumerate() {
+                    assert_structure_kept(full, minified, &format!("{}/{}", path, i));
+                }
+            }
+            (Value::Object(full), Value::Object(minified)) => {
+                for (key, value) in full {
+                    let path = format!("{}/{}", path, key);
+                    if DOCUMENTATION_KEYWORDS.contains(&key.as_str()) {
+                        continue;
+                    }
+                    let kept = minified
+                        .get(key)
+                        .unwrap_or_else(|| panic!("{} removed", path));
+                    if key == "description" {
+                        assert!(kept.is_string(), "{}", path);
+                    } else if SCHEMA_MAP_KEYWORDS.contains(&key.as_str()) {
+                        let (full, kept) = (value.as_object().unwrap(), kept.as_object().unwrap());
+                        assert_eq!(full.len(), kept.len(), "{}", path);
+                        for (name, schema) in full {
+                            assert_structure_kept(
+                                schema,
+                                &kept[name],
+                                &format!("{}/{}", path, name),
+                            );
+                        }
+                    } else if SUBSCHEMA_KEYWORDS.contains(&key.as_str()) {
+                        assert_structure_kept(value, kept, &path);
+                    } else {
+                        assert_eq!(value, kept, "{}", path);
+                    }
+                }
+            }
+            (full, minified) => assert_eq!(full, minified, "{}", path),
+        }
+    }
+
+    #[test]
+    fn test_minimal_keeps_structure() {
+        let full = verbose_tool();
+        let minimal = &minify_tools(&[full.clone()], ToolSchemaVerbosity::Minimal)[0];
+        assert_eq!(minimal.name, full.name);
+        assert!(minimal.description.chars().count() <= MINIMAL_DESCRIPTION_CHARS);
+        assert_structure_kept(&full.input_schema, &minimal.input_schema, "");
+
+        // Properties named like documentation keywords are parameters, not documentation
+        let properties = &minimal.input_schema["properties"];
+        assert!(properties["title"].is_object());
+        assert!(properties["examples"]["items"]["properties"]["input"].is_object());
+        assert_eq!(minimal.input_schema["required"], json!(["command"]));
+    }
+
+    #[test]
+    fn test_full_first_turn() {
+        let first = vec![Message::user().with_text("hi")];
+        let later = vec![
+            Message::user().with_text("hi"),
+            Message::assistant().with_text("hello"),
+            Message::user().with_text("list files"),
+        ];
+        let verbosity = ToolSchemaVerbosity::FullFirstTurn;
+        assert_eq!(verbosity.for_turn(&first), ToolSchemaVerbosity::Full);
+        assert_eq!(verbosity.for_turn(&later), ToolSchemaVerbosity::Compact);
+        assert_eq!(
+            ToolSchemaVerbosity::Minimal.for_turn(&first),
+            ToolSchemaVerbosity::Minimal
+        );
+        assert_eq!(
+            "full_first_turn".parse::<ToolSchemaVerbosity>().unwrap(),
+            verbosity
+        );
+        assert!("terse".parse::<ToolSchemaVerbosity>().is_err());
+    }
+
+    #[test]
+    fn test_token_estimate() {
+        let tools = vec![verbose_tool()];
+        let full =
+            ToolSchemaTokens::estimate(&tools, ToolSchemaVerbosity::Full, TokenizerFamily::O200k);
+        assert_eq!(full.full, full.sent);
+        assert_eq!(full.saved(), 0);
+
+        let minimal = ToolSchemaTokens::estimate(
+            &tools,
+            ToolSchemaVerbosity::Minimal,
+            TokenizerFamily::O200k,
+        );
+        assert_eq!(minimal.full, full.full);
+        assert!(minimal.saved() > minimal.sent);
+    }
+}
@@ -12,10 +12,12 @@ use crate::model::ModelConfig;
 use crate::providers::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
 
 use crate::providers::errors::ProviderError;
+use crate::providers::formats::alternation::alternate_messages;
 use crate::providers::formats::gcpvertexai::{
-    create_request, get_usage, response_to_message, ClaudeVersion, GcpVertexAIModel, GeminiVersion,
-    ModelProvider, RequestContext,
+    create_request, get_finish_reason, get_usage, response_to_message, ClaudeVersion,
+    GcpVertexAIModel, GeminiVersion, ModelProvider, RequestContext,
 };
+use crate::providers::headers::{client_builder, new_request_id, REQUEST_ID_HEADER};
 
 use crate::providers::formats::gcpvertexai::GcpLocation::Iowa;
 use crate::providers::gcpauth::GcpAuth;
@@ -94,6 +96,24 @@ impl RetryConfig {
     }
 }
 
+/// Returns the error message of a Vertex AI error response reporting an exhausted quota.
+///
+/// Quota errors are normally sent with a 429 status, but the `RESOURCE_EXHAUSTED` status
+/// in the error body is what identifies them.
+fn quota_error_message(response: &Value) -> Option<String> {
+    let error = response.get("error")?;
+    if error.get("status").and_then(Value::as_str) != Some("RESOURCE_EXHAUSTED") {
+        return None;
+    }
+    Some(
+        error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("Resource exhausted")
+            .to_string(),
+    )
+}
+
 /// Provider implementation for Google Cloud Platform's Vertex AI service.
 ///
 /// This provider enables interaction with various AI models hosted on GCP Vertex AI,
@@ -150,7 +170,7 @@ impl GcpVertexAIProvider {
         let location = Self::determine_location(config)?;
         let host = format!("https://{}-aiplatform.googleapis.com", location);
 
-        let client = Client::builder()
+        let client = client_builder()
             .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
             .build()?;
 
@@ -272,11 +292,13 @@ impl GcpVertexAIProvider {
     /// Includes retry logic for 429 Too Many Requests errors.
     ///
     /// # Arguments
+    /// * `request_id` - The id of the provider call, sent with every attempt
     /// * `payload` - The request payload to send
     /// * `context` - Request context containing model information
     /// * `location` - The GCP location for the request
     async fn post_with_location(
         &self,
+        request_id: &str,
         payload: &Value,
         context: &RequestContext,
         location: &str,
@@ -312,6 +334,7 @@ impl GcpVertexAIProvider {
                 .post(url.clone())
                 .json(payload)
                 .header("Authorization", auth_header)
+                .header(REQUEST_ID_HEADER, request_id)
                 .send()
                 .await
                 .map_err(|e| ProviderError::RequestFailed(e.to_string()))?;
@@ -324,6 +347,10 @@ impl GcpVertexAIProvider {
                     ProviderError::RequestFailed(format!("Failed to parse response: {e}"))
                 })?;
 
+                if let Some(message) = quota_error_message(&response_json) {
+                    return Err(ProviderError::RateLimitExceeded(message));
+                }
+
                 return match status {
                     StatusCode::OK => Ok(response_json),
                     StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
@@ -378,12 +405,18 @@ impl GcpVertexAIProvider {
     /// Makes an authenticated POST request to the Vertex AI API with fallback for invalid locations.
     ///
     /// # Arguments
+    /// * `request_id` - The id of the provider call, sent with every attempt
     /// * `payload` - The request payload to send
     /// * `context` - Request context containing model information
-    async fn post(&self, payload: Value, context: &RequestContext) -> Result<Value, ProviderError> {
+    async fn post(
+        &self,
+        request_id: &str,
+        payload: Value,
+        context: &RequestContext,
+    ) -> Result<Value, ProviderError> {
         // Try with user-specified location first
         let result = self
-            .post_with_location(&payload, context, &self.location)
+            .post_with_location(request_id, &payload, context, &self.location)
             .await;
 
         // If location is already the known location for the model or request succeeded, return result
@@ -402,7 +435,7 @@ impl GcpVertexAIProvider {
                     "Trying known location {known_location} for {model_name} instead of {configured_location}: {msg}"
                 );
 
-                self.post_with_location(&payload, context, &known_location)
+                self.post_with_location(request_id, &payload, context, &known_location)
                     .await
             }
             // For any other error, return the original result
@@ -500,18 +533,22 @@ impl Provider for GcpVertexAIProvider {
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
-        // Create request and context
-        let (request, context) = create_request(&self.model, system, messages, tools)?;
+        // Create request and context, with repeated roles merged as both model families require
+        let (request, context) =
+            create_request(&self.model, system, &alternate_messages(messages), tools)?;
 
         // Send request and process response
-        let response = self.post(request.clone(), &context).await?;
+        let request_id = new_request_id();
+        let response = self.post(&request_id, request.clone(), &context).await?;
         let usage = get_usage(&response, &context)?;
+        let finish_reason = get_finish_reason(&response, &context);
 
-        emit_debug_trace(&self.model, &request, &response, &usage);
+        emit_debug_trace(&self.model, &request_id, &request, &response, &usage);
 
         // Convert response to message
         let message = response_to_message(response, context)?;
-        let provider_usage = ProviderUsage::new(self.model.model_name.clone(), usage);
+        let provider_usage = ProviderUsage::new(self.model.model_name.clone(), usage)
+            .with_finish_reason(finish_reason);
 
         Ok((message, provider_usage))
     }
@@ -552,6 +589,32 @@ mod tests {
         assert!(delay10.as_millis() <= 38400); // max_interval_ms * 1.2 (max jitter)
     }
 
+    #[test]
+    fn test_quota_error_message() {
+        let response = serde_json::json!({
+            "error": {
+                "code": 429,
+                "message": "Quota exceeded for aiplatform.googleapis.com/generate_content_requests_per_minute",
+                "status": "RESOURCE_EXHAUSTED"
+            }
+        });
+        assert_eq!(
+            quota_error_message(&response).as_deref(),
+            Some(
+                "Quota exceeded for aiplatform.googleapis.com/generate_content_requests_per_minute"
+            )
+        );
+
+        let response = serde_json::json!({
+            "error": {"code": 400, "message": "Invalid argument", "status": "INVALID_ARGUMENT"}
+        });
+        assert_eq!(quota_error_message(&response), None);
+        assert_eq!(
+            quota_error_message(&serde_json::json!({"candidates": []})),
+            None
+        );
+    }
+
     #[test]
     fn test_model_provider_conversion() {
         assert_eq!(ModelProvider::Anthropic.as_str(), "anthropic");
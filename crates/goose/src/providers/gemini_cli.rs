@@ -7,6 +7,7 @@ use tokio::process::Command;
 
 use super::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
+use super::headers::new_request_id;
 use super::utils::emit_debug_trace;
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
@@ -171,11 +172,12 @@ impl GeminiCliProvider {
 
         let message = Message {
             role: Role::Assistant,
-            created: chrono::Utc::now().timestamp(),
+            created: crate::determinism::now().timestamp(),
             content: vec![MessageContent::Text(TextContent {
                 text: response_text,
                 annotations: None,
             })],
+            extra: Default::default(),
         };
 
         let usage = Usage::default(); // No usage info available for gemini CLI
@@ -216,11 +218,12 @@ impl GeminiCliProvider {
 
         let message = Message {
             role: Role::Assistant,
-            created: chrono::Utc::now().timestamp(),
+            created: crate::determinism::now().timestamp(),
             content: vec![MessageContent::Text(TextContent {
                 text: description.clone(),
                 annotations: None,
             })],
+            extra: Default::default(),
         };
 
         let usage = Usage::default();
@@ -283,7 +286,7 @@ impl Provider for GeminiCliProvider {
             "usage": usage
         });
 
-        emit_debug_trace(&self.model, &payload, &response, &usage);
+        emit_debug_trace(&self.model, &new_request_id(), &payload, &response, &usage);
 
         Ok((
             message,
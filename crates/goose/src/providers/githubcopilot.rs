@@ -11,9 +11,16 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 use std::time::Duration;
 
-use super::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{
+    prefill_instruction, CompletionOptions, Provider, ProviderMetadata, ProviderUsage,
+    ToolCallProgressSender, Usage,
+};
 use super::errors::ProviderError;
-use super::formats::openai::{create_request, get_usage, response_to_message};
+use super::formats::openai::{
+    add_logprobs, add_tool_choice, create_request, get_usage, response_to_logprobs,
+    response_to_message,
+};
+use super::headers::{client_builder, new_request_id, REQUEST_ID_HEADER};
 use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat, ImageFormat};
 
 use crate::config::{Config, ConfigError};
@@ -124,9 +131,7 @@ impl Default for GithubCopilotProvider {
 
 impl GithubCopilotProvider {
     pub fn from_env(model: ModelConfig) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(600))
-            .build()?;
+        let client = client_builder().timeout(Duration::from_secs(600)).build()?;
         let cache = DiskCache::new();
         let mu = tokio::sync::Mutex::new(RefCell::new(None));
         Ok(Self {
@@ -137,9 +142,15 @@ impl GithubCopilotProvider {
         })
     }
 
-    async fn post(&self, mut payload: Value) -> Result<Value, ProviderError> {
-        use crate::providers::utils_universal_openai_stream::{OAIStreamChunk, OAIStreamCollector};
-        use futures_util::StreamExt;
+    async fn post(
+        &self,
+        request_id: &str,
+        mut payload: Value,
+        progress: Option<ToolCallProgressSender>,
+    ) -> Result<Value, ProviderError> {
+        use crate::providers::utils_universal_openai_stream::{
+            collect_oai_stream_with_progress, oai_stream_chunks,
+        };
         // Detect gpt-4.1 and stream
         let model_name = payload.get("model").and_then(|v| v.as_str()).unwrap_or("");
         let stream_only_model = GITHUB_COPILOT_STREAM_MODELS
@@ -159,34 +170,14 @@ impl GithubCopilotProvider {
             .post(url)
             .headers(self.get_github_headers())
             .header("Authorization", format!("Bearer {}", token))
+            .header(REQUEST_ID_HEADER, request_id)
             .json(&payload)
             .send()
             .await?;
         if stream_only_model {
-            let mut collector = OAIStreamCollector::new();
-            let mut stream = response.bytes_stream();
-            while let Some(chunk) = stream.next().await {
-                let chunk = chunk.map_err(|e| ProviderError::RequestFailed(e.to_string()))?;
-                let text = String::from_utf8_lossy(&chunk);
-                for line in text.lines() {
-                    let tline = line.trim();
-                    if !tline.starts_with("data: ") {
-                        continue;
-                    }
-                    let payload = &tline[6..];
-                    if payload == "[DONE]" {
-                        break;
-                    }
-                    match serde_json::from_str::<OAIStreamChunk>(payload) {
-                        Ok(ch) => collector.add_chunk(&ch),
-                        Err(_) => continue,
-                    }
-                }
-            }
-            let final_response = collector.build_response();
-            let value = serde_json::to_value(final_response)
-                .map_err(|e| ProviderError::RequestFailed(e.to_string()))?;
-            Ok(value)
+            // Reading the body as it streams in means a cancelled reply aborts the download
+            collect_oai_stream_with_progress(oai_stream_chunks(response.bytes_stream()), progress)
+                .await
         } else {
             handle_response_openai_compat(response).await
         }
@@ -378,6 +369,56 @@ impl GithubCopilotProvider {
         headers.insert("editor-plugin-version", "copilot/1.155.0".parse().unwrap());
         headers
     }
+
+    /// Models that stream report their tool call arguments to `progress` as they arrive
+    #[tracing::instrument(
+        skip(self, system, messages, tools, progress, options),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete_streaming(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        progress: Option<ToolCallProgressSender>,
+        options: &CompletionOptions,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        // The response can't be prefilled, so the model is asked to start with the prefill
+        let system = match &options.prefill {
+            Some(prefill) => format!("{}\n\n{}", system, prefill_instruction(prefill)),
+            None => system.to_string(),
+        };
+        let mut payload =
+            create_request(&self.model, &system, messages, tools, &ImageFormat::OpenAi)?;
+        add_logprobs(&mut payload, options);
+        add_tool_choice(&mut payload, options);
+
+        // Make request
+        let request_id = new_request_id();
+        let response = self.post(&request_id, payload.clone(), progress).await?;
+
+        // Parse response
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let logprobs = if options.logprobs {
+            response_to_logprobs(&response)
+        } else {
+            None
+        };
+        let model = get_model(&response);
+        emit_debug_trace(&self.model, &request_id, &payload, &response, &usage);
+        Ok((
+            message,
+            ProviderUsage::new(model, usage).with_logprobs(logprobs),
+        ))
+    }
 }
 
 #[async_trait]
@@ -398,33 +439,41 @@ impl Provider for GithubCopilotProvider {
         self.model.clone()
     }
 
-    #[tracing::instrument(
-        skip(self, system, messages, tools),
-        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
-    )]
     async fn complete(
         &self,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
-        let payload = create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        self.complete_streaming(system, messages, tools, None, &CompletionOptions::default())
+            .await
+    }
 
-        // Make request
-        let response = self.post(payload.clone()).await?;
+    async fn complete_with_progress(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        progress: ToolCallProgressSender,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_streaming(
+            system,
+            messages,
+            tools,
+            Some(progress),
+            &CompletionOptions::default(),
+        )
+        .await
+    }
 
-        // Parse response
-        let message = response_to_message(response.clone())?;
-        let usage = match get_usage(&response) {
-            Ok(usage) => usage,
-            Err(ProviderError::UsageError(e)) => {
-                tracing::debug!("Failed to get usage data: {}", e);
-                Usage::default()
-            }
-            Err(e) => return Err(e),
-        };
-        let model = get_model(&response);
-        emit_debug_trace(&self.model, &payload, &response, &usage);
-        Ok((message, ProviderUsage::new(model, usage)))
+    async fn complete_with_options(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        options: &CompletionOptions,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_streaming(system, messages, tools, None, options)
+            .await
     }
 }
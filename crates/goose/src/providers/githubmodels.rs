@@ -0,0 +1,287 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::Value;
+use url::Url;
+
+use super::base::{
+    prefill_instruction, CompletionOptions, ConfigKey, Provider, ProviderMetadata, ProviderUsage,
+    Usage,
+};
+use super::deviceauth::{DeviceFlowAuth, DeviceFlowConfig, StaticToken, TokenProvider};
+use super::errors::ProviderError;
+use super::formats::openai::{
+    add_logprobs, add_tool_choice, create_request, get_usage, response_to_logprobs,
+    response_to_message,
+};
+use super::headers::{client_builder, new_request_id, REQUEST_ID_HEADER};
+use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat, ImageFormat};
+use crate::config::{Config, ConfigError};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+pub const GITHUB_MODELS_API_HOST: &str = "https://models.github.ai";
+pub const GITHUB_MODELS_DEFAULT_MODEL: &str = "openai/gpt-4.1";
+pub const GITHUB_MODELS_KNOWN_MODELS: &[&str] = &[
+    "openai/gpt-4.1",
+    "openai/gpt-4.1-mini",
+    "openai/gpt-4o",
+    "openai/gpt-4o-mini",
+];
+
+pub const GITHUB_MODELS_DOC_URL: &str = "https://docs.github.com/en/github-models";
+
+/// The GitHub app goose signs in with, the same as for Copilot
+const GITHUB_MODELS_CLIENT_ID: &str = "Iv1.b507a08c87ecfe98";
+const GITHUB_DEVICE_CODE_URL: &str = "https://github.com/login/device/code";
+const GITHUB_ACCESS_TOKEN_URL: &str = "https://github.com/login/oauth/access_token";
+/// The secret the signed in tokens are stored under
+const GITHUB_MODELS_OAUTH_SECRET: &str = "GITHUB_MODELS_OAUTH_TOKENS";
+
+#[derive(serde::Serialize)]
+pub struct GithubModelsProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    #[serde(skip)]
+    auth: Arc<dyn TokenProvider>,
+    model: ModelConfig,
+}
+
+impl Default for GithubModelsProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(GithubModelsProvider::metadata().default_model);
+        GithubModelsProvider::from_env(model).expect("Failed to initialize GitHub Models provider")
+    }
+}
+
+impl GithubModelsProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = Config::global();
+        let host: String = config
+            .get_param("GITHUB_MODELS_HOST")
+            .unwrap_or_else(|_| GITHUB_MODELS_API_HOST.to_string());
+
+        // A personal access token is used as is, otherwise the user signs in with GitHub
+        let auth: Arc<dyn TokenProvider> = match config.get_secret("GITHUB_MODELS_TOKEN") {
+            Ok(token) => Arc::new(StaticToken(token)),
+            Err(ConfigError::NotFound(_)) => Arc::new(DeviceFlowAuth::new(DeviceFlowConfig {
+                client_id: config
+                    .get_param("GITHUB_MODELS_CLIENT_ID")
+                    .unwrap_or_else(|_| GITHUB_MODELS_CLIENT_ID.to_string()),
+                device_code_url: GITHUB_DEVICE_CODE_URL.to_string(),
+                token_url: GITHUB_ACCESS_TOKEN_URL.to_string(),
+                scopes: Vec::new(),
+                secret_key: GITHUB_MODELS_OAUTH_SECRET.to_string(),
+            })?),
+            Err(e) => return Err(e.into()),
+        };
+        Self::new(model, host, auth)
+    }
+
+    /// A provider for the API at `host`, authenticated with the tokens of `auth`
+    pub fn new(model: ModelConfig, host: String, auth: Arc<dyn TokenProvider>) -> Result<Self> {
+        let client = client_builder().timeout(Duration::from_secs(600)).build()?;
+        Ok(Self {
+            client,
+            host,
+            auth,
+            model,
+        })
+    }
+
+    async fn post(&self, request_id: &str, payload: &Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url.join("inference/chat/completions").map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+        let token = self.auth.token().await.map_err(|e| {
+            ProviderError::Authentication(format!("Failed to sign in to GitHub: {e:#}"))
+        })?;
+
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(token)
+            .header(REQUEST_ID_HEADER, request_id)
+            .json(payload)
+            .send()
+            .await?;
+        handle_response_openai_compat(response).await
+    }
+}
+
+#[async_trait]
+impl Provider for GithubModelsProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "github_models",
+            "GitHub Models",
+            "Models from the GitHub Models catalog, signed in with GitHub",
+            GITHUB_MODELS_DEFAULT_MODEL,
+            GITHUB_MODELS_KNOWN_MODELS.to_vec(),
+            GITHUB_MODELS_DOC_URL,
+            vec![
+                ConfigKey::new("GITHUB_MODELS_TOKEN", false, true, None),
+                ConfigKey::new(
+                    "GITHUB_MODELS_HOST",
+                    false,
+                    false,
+                    Some(GITHUB_MODELS_API_HOST),
+                ),
+                ConfigKey::new(
+                    "GITHUB_MODELS_CLIENT_ID",
+                    false,
+                    false,
+                    Some(GITHUB_MODELS_CLIENT_ID),
+                ),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_with_options(system, messages, tools, &CompletionOptions::default())
+            .await
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools, options),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete_with_options(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        options: &CompletionOptions,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        // The response can't be prefilled, so the model is asked to start with the prefill
+        let system = match &options.prefill {
+            Some(prefill) => format!("{}\n\n{}", system, prefill_instruction(prefill)),
+            None => system.to_string(),
+        };
+        let mut payload =
+            create_request(&self.model, &system, messages, tools, &ImageFormat::OpenAi)?;
+        add_logprobs(&mut payload, options);
+        add_tool_choice(&mut payload, options);
+
+        let request_id = new_request_id();
+        let response = self.post(&request_id, &payload).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let logprobs = if options.logprobs {
+            response_to_logprobs(&response)
+        } else {
+            None
+        };
+        let model = get_model(&response);
+        emit_debug_trace(&self.model, &request_id, &payload, &response, &usage);
+        Ok((
+            message,
+            ProviderUsage::new(model, usage).with_logprobs(logprobs),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+    use wiremock::matchers::{body_string_contains, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_complete_signs_in_with_device_flow() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/login/device/code"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "device_code": "device-123",
+                "user_code": "ABCD-1234",
+                "verification_uri": "https://github.com/login/device",
+                "expires_in": 900,
+                "interval": 0
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/login/oauth/access_token"))
+            .and(body_string_contains("device_code=device-123"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "ghu_access",
+                "refresh_token": "ghr_refresh",
+                "expires_in": 28800
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/inference/chat/completions"))
+            .and(header("Authorization", "Bearer ghu_access"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "model": "openai/gpt-4.1",
+                "choices": [{"message": {"role": "assistant", "content": "Hello"}}],
+                "usage": {"prompt_tokens": 10, "completion_tokens": 2, "total_tokens": 12}
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let dir = TempDir::new().unwrap();
+        let auth = DeviceFlowAuth::new(DeviceFlowConfig {
+            client_id: GITHUB_MODELS_CLIENT_ID.to_string(),
+            device_code_url: format!("{}/login/device/code", server.uri()),
+            token_url: format!("{}/login/oauth/access_token", server.uri()),
+            scopes: Vec::new(),
+            secret_key: GITHUB_MODELS_OAUTH_SECRET.to_string(),
+        })
+        .unwrap()
+        .with_config(
+            Config::new_with_file_secrets(
+                dir.path().join("config.yaml"),
+                dir.path().join("secrets.yaml"),
+            )
+            .unwrap(),
+        );
+        let provider = GithubModelsProvider::new(
+            ModelConfig::new(GITHUB_MODELS_DEFAULT_MODEL.to_string()),
+            server.uri(),
+            Arc::new(auth),
+        )
+        .unwrap();
+
+        // Signed in once, the token is reused for the next request
+        for _ in 0..2 {
+            let (message, usage) = provider
+                .complete("system", &[Message::user().with_text("Hi")], &[])
+                .await
+                .unwrap();
+            assert_eq!(message.as_concat_text(), "Hello");
+            assert_eq!(usage.model, "openai/gpt-4.1");
+        }
+    }
+}
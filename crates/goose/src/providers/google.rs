@@ -1,8 +1,11 @@
 use super::errors::ProviderError;
+use super::headers::{client_builder, new_request_id, REQUEST_ID_HEADER};
 use crate::message::Message;
 use crate::model::ModelConfig;
 use crate::providers::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
-use crate::providers::formats::google::{create_request, get_usage, response_to_message};
+use crate::providers::formats::google::{
+    create_request, get_finish_reason, get_usage, response_to_message,
+};
 use crate::providers::utils::{
     emit_debug_trace, handle_response_google_compat, unescape_json_values,
 };
@@ -70,9 +73,7 @@ impl GoogleProvider {
             .get_param("GOOGLE_HOST")
             .unwrap_or_else(|_| GOOGLE_API_HOST.to_string());
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(600))
-            .build()?;
+        let client = client_builder().timeout(Duration::from_secs(600)).build()?;
 
         Ok(Self {
             client,
@@ -82,7 +83,7 @@ impl GoogleProvider {
         })
     }
 
-    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+    async fn post(&self, request_id: &str, payload: Value) -> Result<Value, ProviderError> {
         let base_url = Url::parse(&self.host)
             .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
 
@@ -104,6 +105,7 @@ impl GoogleProvider {
                 .client
                 .post(url.clone()) // Clone the URL for each retry
                 .header("CONTENT_TYPE", "application/json")
+                .header(REQUEST_ID_HEADER, request_id)
                 .json(&payload)
                 .send()
                 .await;
@@ -175,7 +177,8 @@ impl Provider for GoogleProvider {
         let payload = create_request(&self.model, system, messages, tools)?;
 
         // Make request
-        let response = self.post(payload.clone()).await?;
+        let request_id = new_request_id();
+        let response = self.post(&request_id, payload.clone()).await?;
 
         // Parse response
         let message = response_to_message(unescape_json_values(&response))?;
@@ -184,8 +187,9 @@ impl Provider for GoogleProvider {
             Some(model_version) => model_version.as_str().unwrap_or_default().to_string(),
             None => self.model.model_name.clone(),
         };
-        emit_debug_trace(&self.model, &payload, &response, &usage);
-        let provider_usage = ProviderUsage::new(model, usage);
+        emit_debug_trace(&self.model, &request_id, &payload, &response, &usage);
+        let provider_usage =
+            ProviderUsage::new(model, usage).with_finish_reason(get_finish_reason(&response));
         Ok((message, provider_usage))
     }
 
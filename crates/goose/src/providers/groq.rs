@@ -1,4 +1,5 @@
 use super::errors::ProviderError;
+use super::headers::{client_builder, new_request_id, REQUEST_ID_HEADER};
 use crate::message::Message;
 use crate::model::ModelConfig;
 use crate::providers::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
@@ -42,9 +43,7 @@ impl GroqProvider {
             .get_param("GROQ_HOST")
             .unwrap_or_else(|_| GROQ_API_HOST.to_string());
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(600))
-            .build()?;
+        let client = client_builder().timeout(Duration::from_secs(600)).build()?;
 
         Ok(Self {
             client,
@@ -54,7 +53,7 @@ impl GroqProvider {
         })
     }
 
-    async fn post(&self, payload: Value) -> anyhow::Result<Value, ProviderError> {
+    async fn post(&self, request_id: &str, payload: Value) -> anyhow::Result<Value, ProviderError> {
         let base_url = Url::parse(&self.host)
             .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
         let url = base_url.join("openai/v1/chat/completions").map_err(|e| {
@@ -65,6 +64,7 @@ impl GroqProvider {
             .client
             .post(url)
             .header("Authorization", format!("Bearer {}", self.api_key))
+            .header(REQUEST_ID_HEADER, request_id)
             .json(&payload)
             .send()
             .await?;
@@ -136,7 +136,9 @@ impl Provider for GroqProvider {
             &super::utils::ImageFormat::OpenAi,
         )?;
 
-        let response = self.post(payload.clone()).await?;
+        let request_id = new_request_id();
+
+        let response = self.post(&request_id, payload.clone()).await?;
 
         let message = response_to_message(response.clone())?;
         let usage = match get_usage(&response) {
@@ -148,7 +150,7 @@ impl Provider for GroqProvider {
             Err(e) => return Err(e),
         };
         let model = get_model(&response);
-        super::utils::emit_debug_trace(&self.model, &payload, &response, &usage);
+        super::utils::emit_debug_trace(&self.model, &request_id, &payload, &response, &usage);
         Ok((message, ProviderUsage::new(model, usage)))
     }
 
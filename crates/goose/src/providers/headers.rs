@@ -0,0 +1,359 @@
+use std::collections::BTreeMap;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{ClientBuilder, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+/// The header carrying the id of a single provider call, also logged with its trace
+pub const REQUEST_ID_HEADER: &str = "X-Client-Request-Id";
+
+/// The `User-Agent` sent with every provider request
+pub fn user_agent() -> String {
+    format!("goose/{}", env!("CARGO_PKG_VERSION"))
+}
+
+/// A new id for a provider call, shared by its retries
+pub fn new_request_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// The headers every provider client sends by default, as shown in dry runs
+pub fn client_headers() -> RequestHeaders {
+    let mut headers = RequestHeaders::new().with("User-Agent", user_agent());
+    headers.extend(RequestHeaders::from_config());
+    headers
+}
+
+/// Start building an HTTP client for a provider, with the `User-Agent` and any headers
+/// from `GOOSE_REQUEST_HEADERS` sent by default. Providers set their own timeouts.
+pub fn client_builder() -> ClientBuilder {
+    client_builder_with(&RequestHeaders::from_config())
+}
+
+fn client_builder_with(extra: &RequestHeaders) -> ClientBuilder {
+    if !extra.is_empty() {
+        tracing::debug!(headers = ?extra.redacted(), "extra provider request headers");
+    }
+    reqwest::Client::builder()
+        .user_agent(user_agent())
+        .default_headers(extra.to_header_map())
+}
+
+/// `GOOSE_REQUEST_HEADERS` is either a map of names to values, or a `name=value` list
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ConfiguredHeaders {
+    Map(BTreeMap<String, String>),
+    List(String),
+}
+
+/// Extra HTTP headers to send with a provider request, kept separate from the JSON payload.
+///
+/// Header names are matched case-insensitively and keep the order they were first added in.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RequestHeaders {
+    headers: Vec<(String, String)>,
+}
+
+impl RequestHeaders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse headers from a comma separated list of `name=value` pairs, as used by the
+    /// `*_CUSTOM_HEADERS` config keys. Entries without a `=` are ignored.
+    pub fn parse(s: &str) -> Self {
+        let mut headers = Self::new();
+        for header in s.split(',') {
+            if let Some((name, value)) = header.split_once('=') {
+                let name = name.trim();
+                if !name.is_empty() {
+                    headers.insert(name, value.trim());
+                }
+            }
+        }
+        headers
+    }
+
+    /// The extra headers configured under `GOOSE_REQUEST_HEADERS`, with `${NAME}` in values
+    /// replaced by the environment variable `NAME`
+    pub fn from_config() -> Self {
+        let configured =
+            crate::config::Config::global().get_param::<ConfiguredHeaders>("GOOSE_REQUEST_HEADERS");
+        let headers = match configured {
+            Ok(ConfiguredHeaders::Map(map)) => {
+                map.into_iter().fold(Self::new(), |headers, (name, value)| {
+                    headers.with(name, value)
+                })
+            }
+            Ok(ConfiguredHeaders::List(list)) => Self::parse(&list),
+            Err(_) => Self::new(),
+        };
+        headers.interpolated(|name| std::env::var(name).ok())
+    }
+
+    /// Replace `${NAME}` in header values with `lookup(NAME)`. Unknown names become empty,
+    /// since sending the placeholder itself is never what was meant.
+    pub fn interpolated(&self, lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let interpolate = |value: &str| {
+            let mut result = String::new();
+            let mut rest = value;
+            while let Some(start) = rest.find("${") {
+                let Some(len) = rest[start + 2..].find('}') else {
+                    break;
+                };
+                let name = &rest[start + 2..start + 2 + len];
+                result.push_str(&rest[..start]);
+                match lookup(name) {
+                    Some(value) => result.push_str(&value),
+                    None => tracing::warn!("Request header references unset variable {}", name),
+                }
+                rest = &rest[start + 3 + len..];
+            }
+            result.push_str(rest);
+            result
+        };
+        Self {
+            headers: self
+                .headers
+                .iter()
+                .map(|(name, value)| (name.clone(), interpolate(value)))
+                .collect(),
+        }
+    }
+
+    /// Add a header, replacing any existing value
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.insert(name, value);
+        self
+    }
+
+    /// Set a header, replacing any existing value
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let name = name.into();
+        let value = value.into();
+        match self.position(&name) {
+            Some(index) => self.headers[index].1 = value,
+            None => self.headers.push((name, value)),
+        }
+    }
+
+    /// Add a value to a header that holds a comma separated list, such as `anthropic-beta`.
+    /// Values already in the list are not added again.
+    pub fn append_to_list(&mut self, name: impl Into<String>, value: &str) {
+        let name = name.into();
+        match self.position(&name) {
+            Some(index) => {
+                let current = &mut self.headers[index].1;
+                if !current.split(',').any(|existing| existing.trim() == value) {
+                    current.push(',');
+                    current.push_str(value);
+                }
+            }
+            None => self.headers.push((name, value.to_string())),
+        }
+    }
+
+    /// Add all headers from `other`, which take precedence over existing values
+    pub fn with_all(mut self, other: RequestHeaders) -> Self {
+        self.extend(other);
+        self
+    }
+
+    /// Add all headers from `other`, which take precedence over existing values
+    pub fn extend(&mut self, other: RequestHeaders) {
+        for (name, value) in other.headers {
+            self.insert(name, value);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.position(name)
+            .map(|index| self.headers[index].1.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// A copy with credentials replaced, safe to print or log. Custom headers can carry
+    /// credentials under any name, so anything that looks like a key or token is redacted.
+    pub fn redacted(&self) -> Self {
+        const SENSITIVE: [&str; 5] = ["authorization", "key", "token", "secret", "cookie"];
+        Self {
+            headers: self
+                .headers
+                .iter()
+                .map(|(name, value)| {
+                    let lower = name.to_lowercase();
+                    if SENSITIVE.iter().any(|word| lower.contains(word)) {
+                        (name.clone(), "[redacted]".to_string())
+                    } else {
+                        (name.clone(), value.clone())
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Attach the headers to an outgoing request
+    pub fn apply(&self, mut request: RequestBuilder) -> RequestBuilder {
+        for (name, value) in self.iter() {
+            request = request.header(name, value);
+        }
+        request
+    }
+
+    /// Convert to the default headers of a client, skipping any that are not valid HTTP
+    fn to_header_map(&self) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in self.iter() {
+            match (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                (Ok(name), Ok(value)) => {
+                    map.insert(name, value);
+                }
+                _ => tracing::warn!("Skipping invalid request header {}", name),
+            }
+        }
+        map
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.headers
+            .iter()
+            .position(|(existing, _)| existing.eq_ignore_ascii_case(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_replaces_case_insensitively() {
+        let mut headers = RequestHeaders::new().with("X-Title", "Goose");
+        headers.insert("x-title", "Other");
+        assert_eq!(headers.get("X-TITLE"), Some("Other"));
+        assert_eq!(headers.iter().count(), 1);
+    }
+
+    #[test]
+    fn test_append_to_list() {
+        let mut headers = RequestHeaders::new();
+        headers.append_to_list("anthropic-beta", "output-128k-2025-02-19");
+        headers.append_to_list("anthropic-beta", "token-efficient-tools-2025-02-19");
+        headers.append_to_list("anthropic-beta", "output-128k-2025-02-19");
+        assert_eq!(
+            headers.get("anthropic-beta"),
+            Some("output-128k-2025-02-19,token-efficient-tools-2025-02-19")
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let headers = RequestHeaders::parse("X-Team=goose, X-Token = a=b ,invalid,=empty");
+        assert_eq!(
+            headers.iter().collect::<Vec<_>>(),
+            vec![("X-Team", "goose"), ("X-Token", "a=b")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_attaches_headers() {
+        use wiremock::matchers::{header, header_regex, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("X-Title", "Goose"))
+            .and(header_regex("anthropic-beta", "^a,b$"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut headers = RequestHeaders::new().with("X-Title", "Goose");
+        headers.append_to_list("anthropic-beta", "a");
+        headers.append_to_list("anthropic-beta", "b");
+
+        let request = headers.apply(reqwest::Client::new().get(server.uri()));
+        let response = request.send().await.unwrap();
+        assert_eq!(response.status(), 200);
+    }
+
+    #[test]
+    fn test_interpolated() {
+        let headers = RequestHeaders::new()
+            .with("X-Team", "${TEAM_NAME}")
+            .with("X-Route", "pre-${TEAM_NAME}-${MISSING}-post")
+            .with("X-Literal", "cost: $5 {unclosed ${");
+        let lookup = |name: &str| (name == "TEAM_NAME").then(|| "platform".to_string());
+        let interpolated = headers.interpolated(lookup);
+        assert_eq!(interpolated.get("X-Team"), Some("platform"));
+        assert_eq!(interpolated.get("X-Route"), Some("pre-platform--post"));
+        assert_eq!(interpolated.get("X-Literal"), Some("cost: $5 {unclosed ${"));
+    }
+
+    #[test]
+    fn test_configured_headers_forms() {
+        let map: ConfiguredHeaders =
+            serde_json::from_value(serde_json::json!({"X-Team": "${TEAM_NAME}"})).unwrap();
+        assert!(matches!(map, ConfiguredHeaders::Map(map) if map["X-Team"] == "${TEAM_NAME}"));
+        let list: ConfiguredHeaders =
+            serde_json::from_value(serde_json::json!("X-Team=goose")).unwrap();
+        assert!(matches!(list, ConfiguredHeaders::List(list) if list == "X-Team=goose"));
+    }
+
+    #[tokio::test]
+    async fn test_client_sends_default_headers() {
+        use wiremock::matchers::{header, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("User-Agent", user_agent().as_str()))
+            .and(header("X-Team", "platform"))
+            .and(header(REQUEST_ID_HEADER, "call-1"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let extra = RequestHeaders::new()
+            .with("X-Team", "${TEAM_NAME}")
+            .with("Bad Header", "skipped")
+            .interpolated(|_| Some("platform".to_string()));
+        let client = client_builder_with(&extra).build().unwrap();
+        let response = client
+            .get(server.uri())
+            .header(REQUEST_ID_HEADER, "call-1")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert!(user_agent().starts_with("goose/"));
+    }
+
+    #[test]
+    fn test_redacted() {
+        let headers = RequestHeaders::new()
+            .with("Authorization", "Bearer sk-123")
+            .with("x-api-key", "sk-ant-123")
+            .with("X-Auth-Token", "abc")
+            .with("anthropic-version", "2023-06-01");
+        let redacted = headers.redacted();
+        assert_eq!(redacted.get("authorization"), Some("[redacted]"));
+        assert_eq!(redacted.get("x-api-key"), Some("[redacted]"));
+        assert_eq!(redacted.get("x-auth-token"), Some("[redacted]"));
+        assert_eq!(redacted.get("anthropic-version"), Some("2023-06-01"));
+    }
+}
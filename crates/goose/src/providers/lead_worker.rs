@@ -3,12 +3,22 @@ use async_trait::async_trait;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
-use super::base::{LeadWorkerProviderTrait, Provider, ProviderMetadata, ProviderUsage};
+use super::base::{
+    CompletionOptions, DryRunRequest, LeadWorkerProviderTrait, Provider, ProviderMetadata,
+    ProviderUsage, ToolCallProgressSender,
+};
 use super::errors::ProviderError;
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
 use mcp_core::{tool::Tool, Content};
 
+/// The arguments beyond the conversation of a completion, for the provider it is routed to
+enum Completion<'a> {
+    Plain,
+    WithProgress(ToolCallProgressSender),
+    WithOptions(&'a CompletionOptions),
+}
+
 /// A provider that switches between a lead model and a worker model based on turn count
 /// and can fallback to lead model on consecutive failures
 pub struct LeadWorkerProvider {
@@ -282,53 +292,14 @@ impl LeadWorkerProvider {
             || text_lower.starts_with("wrong")
             || text_lower.starts_with("incorrect")
     }
-}
-
-impl LeadWorkerProviderTrait for LeadWorkerProvider {
-    /// Get information about the lead and worker models for logging
-    fn get_model_info(&self) -> (String, String) {
-        let lead_model = self.lead_provider.get_model_config().model_name;
-        let worker_model = self.worker_provider.get_model_config().model_name;
-        (lead_model, worker_model)
-    }
-
-    /// Get the currently active model name
-    fn get_active_model(&self) -> String {
-        // Read from the global store which was set during complete()
-        use super::base::get_current_model;
-        get_current_model().unwrap_or_else(|| {
-            // Fallback to lead model if no current model is set
-            self.lead_provider.get_model_config().model_name
-        })
-    }
-}
-
-#[async_trait]
-impl Provider for LeadWorkerProvider {
-    fn metadata() -> ProviderMetadata {
-        // This is a wrapper provider, so we return minimal metadata
-        ProviderMetadata::new(
-            "lead_worker",
-            "Lead/Worker Provider",
-            "A provider that switches between lead and worker models based on turn count",
-            "",     // No default model as this is determined by the wrapped providers
-            vec![], // No known models as this depends on wrapped providers
-            "",     // No doc link
-            vec![], // No config keys as configuration is done through wrapped providers
-        )
-    }
 
-    fn get_model_config(&self) -> ModelConfig {
-        // Return the lead provider's model config as the default
-        // In practice, this might need to be more sophisticated
-        self.lead_provider.get_model_config()
-    }
-
-    async fn complete(
+    /// Complete with the active provider, falling back to the lead provider on failure
+    async fn complete_routed(
         &self,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
+        completion: Completion<'_>,
     ) -> Result<(Message, ProviderUsage), ProviderError> {
         // Get the active provider
         let provider = self.get_active_provider().await;
@@ -375,7 +346,7 @@ impl Provider for LeadWorkerProvider {
         }
 
         // Make the completion request
-        let result = provider.complete(system, messages, tools).await;
+        let result = Self::complete_with(&provider, system, messages, tools, &completion).await;
 
         // For technical failures, try with default model (lead provider) instead
         let final_result = match &result {
@@ -383,7 +354,9 @@ impl Provider for LeadWorkerProvider {
                 tracing::warn!("Technical failure with {} provider, retrying with default model (lead provider)", provider_type);
 
                 // Try with lead provider as the default/fallback for technical failures
-                let default_result = self.lead_provider.complete(system, messages, tools).await;
+                let default_result =
+                    Self::complete_with(&self.lead_provider, system, messages, tools, &completion)
+                        .await;
 
                 match &default_result {
                     Ok(_) => {
@@ -407,6 +380,123 @@ impl Provider for LeadWorkerProvider {
         final_result
     }
 
+    async fn complete_with(
+        provider: &Arc<dyn Provider>,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        completion: &Completion<'_>,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        match completion {
+            Completion::Plain => provider.complete(system, messages, tools).await,
+            Completion::WithProgress(progress) => {
+                provider
+                    .complete_with_progress(system, messages, tools, progress.clone())
+                    .await
+            }
+            Completion::WithOptions(options) => {
+                provider
+                    .complete_with_options(system, messages, tools, options)
+                    .await
+            }
+        }
+    }
+}
+
+impl LeadWorkerProviderTrait for LeadWorkerProvider {
+    /// Get information about the lead and worker models for logging
+    fn get_model_info(&self) -> (String, String) {
+        let lead_model = self.lead_provider.get_model_config().model_name;
+        let worker_model = self.worker_provider.get_model_config().model_name;
+        (lead_model, worker_model)
+    }
+
+    /// Get the currently active model name
+    fn get_active_model(&self) -> String {
+        // Read from the global store which was set during complete()
+        use super::base::get_current_model;
+        get_current_model().unwrap_or_else(|| {
+            // Fallback to lead model if no current model is set
+            self.lead_provider.get_model_config().model_name
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for LeadWorkerProvider {
+    fn metadata() -> ProviderMetadata {
+        // This is a wrapper provider, so we return minimal metadata
+        ProviderMetadata::new(
+            "lead_worker",
+            "Lead/Worker Provider",
+            "A provider that switches between lead and worker models based on turn count",
+            "",     // No default model as this is determined by the wrapped providers
+            vec![], // No known models as this depends on wrapped providers
+            "",     // No doc link
+            vec![], // No config keys as configuration is done through wrapped providers
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        // Return the lead provider's model config as the default
+        // In practice, this might need to be more sophisticated
+        self.lead_provider.get_model_config()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_routed(system, messages, tools, Completion::Plain)
+            .await
+    }
+
+    async fn complete_with_progress(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        progress: ToolCallProgressSender,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_routed(system, messages, tools, Completion::WithProgress(progress))
+            .await
+    }
+
+    async fn complete_with_options(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        options: &CompletionOptions,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_routed(system, messages, tools, Completion::WithOptions(options))
+            .await
+    }
+
+    async fn dry_run(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<DryRunRequest, ProviderError> {
+        self.get_active_provider()
+            .await
+            .dry_run(system, messages, tools)
+            .await
+    }
+
+    // A call the active provider fails is retried with the lead provider, so the request
+    // may reach either of them and must suit both
+    fn supports_prefill(&self) -> bool {
+        self.lead_provider.supports_prefill() && self.worker_provider.supports_prefill()
+    }
+
+    fn supports_system_blocks(&self) -> bool {
+        self.lead_provider.supports_system_blocks() && self.worker_provider.supports_system_blocks()
+    }
+
     async fn fetch_supported_models_async(&self) -> Result<Option<Vec<String>>, ProviderError> {
         // Combine models from both providers
         let lead_models = self.lead_provider.fetch_supported_models_async().await?;
@@ -487,6 +577,7 @@ mod tests {
                         text: format!("Response from {}", self.name),
                         annotations: None,
                     })],
+                    extra: Default::default(),
                 },
                 ProviderUsage::new(self.name.clone(), Usage::default()),
             ))
@@ -533,6 +624,35 @@ mod tests {
         assert_eq!(usage.model, "lead");
     }
 
+    #[tokio::test]
+    async fn test_complete_with_options_switches_too() {
+        let lead_provider = Arc::new(MockProvider {
+            name: "lead".to_string(),
+            model_config: ModelConfig::new("lead-model".to_string()),
+        });
+        let worker_provider = Arc::new(MockProvider {
+            name: "worker".to_string(),
+            model_config: ModelConfig::new("worker-model".to_string()),
+        });
+        let provider = LeadWorkerProvider::new(lead_provider, worker_provider, Some(1));
+        let options = CompletionOptions::default().with_prefill("{");
+
+        let (_message, usage) = provider
+            .complete_with_options("system", &[], &[], &options)
+            .await
+            .unwrap();
+        assert_eq!(usage.model, "lead");
+        let (_message, usage) = provider
+            .complete_with_options("system", &[], &[], &options)
+            .await
+            .unwrap();
+        assert_eq!(usage.model, "worker");
+        assert_eq!(provider.get_turn_count().await, 2);
+
+        // Neither mock prefills natively
+        assert!(!provider.supports_prefill());
+    }
+
     #[tokio::test]
     async fn test_technical_failure_retry() {
         let lead_provider = Arc::new(MockFailureProvider {
@@ -650,6 +770,7 @@ mod tests {
                             text: format!("Response from {}", self.name),
                             annotations: None,
                         })],
+                        extra: Default::default(),
                     },
                     ProviderUsage::new(self.name.clone(), Usage::default()),
                 ))
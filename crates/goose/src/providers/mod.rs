@@ -3,29 +3,42 @@ pub mod azure;
 pub mod azureauth;
 pub mod base;
 pub mod bedrock;
+pub mod capabilities;
 pub mod claude_code;
+pub mod continuation;
 pub mod databricks;
+pub mod deviceauth;
+pub mod diagnosis;
 pub mod embedding;
 pub mod errors;
 mod factory;
+pub mod fireworks;
 pub mod formats;
 mod gcpauth;
 pub mod gcpvertexai;
 pub mod gemini_cli;
 pub mod githubcopilot;
+pub mod githubmodels;
 pub mod google;
 pub mod groq;
+pub mod headers;
 pub mod lead_worker;
 pub mod oauth;
 pub mod ollama;
 pub mod openai;
+pub mod openai_compatible;
 pub mod openrouter;
+pub mod partial_json;
 pub mod pricing;
+pub mod response_cache;
 pub mod sagemaker_tgi;
 pub mod snowflake;
+pub mod together;
+pub mod tool_call_ids;
 pub mod toolshim;
 pub mod utils;
 pub mod utils_universal_openai_stream;
+pub mod validation;
 pub mod venice;
 pub mod xai;
 
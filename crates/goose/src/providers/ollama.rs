@@ -1,8 +1,11 @@
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
+use super::headers::{client_builder, new_request_id, REQUEST_ID_HEADER};
 use super::utils::{get_model, handle_response_openai_compat};
+use super::validation::ConversationRules;
 use crate::message::Message;
 use crate::model::ModelConfig;
+use crate::providers::formats::alternation::enforce_alternation;
 use crate::providers::formats::openai::{create_request, get_usage, response_to_message};
 use anyhow::Result;
 use async_trait::async_trait;
@@ -41,9 +44,7 @@ impl OllamaProvider {
             .get_param("OLLAMA_HOST")
             .unwrap_or_else(|_| OLLAMA_HOST.to_string());
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(600))
-            .build()?;
+        let client = client_builder().timeout(Duration::from_secs(600)).build()?;
 
         Ok(Self {
             client,
@@ -80,7 +81,7 @@ impl OllamaProvider {
         Ok(base_url)
     }
 
-    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+    async fn post(&self, request_id: &str, payload: Value) -> Result<Value, ProviderError> {
         // TODO: remove this later when the UI handles provider config refresh
         let base_url = self.get_base_url()?;
 
@@ -88,7 +89,13 @@ impl OllamaProvider {
             ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
         })?;
 
-        let response = self.client.post(url).json(&payload).send().await?;
+        let response = self
+            .client
+            .post(url)
+            .header(REQUEST_ID_HEADER, request_id)
+            .json(&payload)
+            .send()
+            .await?;
 
         handle_response_openai_compat(response).await
     }
@@ -127,15 +134,22 @@ impl Provider for OllamaProvider {
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
-        let payload = create_request(
+        let mut payload = create_request(
             &self.model,
             system,
             messages,
             tools,
             &super::utils::ImageFormat::OpenAi,
         )?;
+        if ConversationRules::for_provider(&Self::metadata().name).strict_alternation {
+            if let Some(messages) = payload["messages"].as_array_mut() {
+                enforce_alternation(messages);
+            }
+        }
+
+        let request_id = new_request_id();
 
-        let response = self.post(payload.clone()).await?;
+        let response = self.post(&request_id, payload.clone()).await?;
         let message = response_to_message(response.clone())?;
 
         let usage = match get_usage(&response) {
@@ -147,7 +161,7 @@ impl Provider for OllamaProvider {
             Err(e) => return Err(e),
         };
         let model = get_model(&response);
-        super::utils::emit_debug_trace(&self.model, &payload, &response, &usage);
+        super::utils::emit_debug_trace(&self.model, &request_id, &payload, &response, &usage);
         Ok((message, ProviderUsage::new(model, usage)))
     }
 }
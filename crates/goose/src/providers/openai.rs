@@ -2,14 +2,22 @@ use anyhow::Result;
 use async_trait::async_trait;
 use reqwest::Client;
 use serde_json::Value;
-use std::collections::HashMap;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use super::base::{ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{
+    prefill_instruction, CompletionOptions, ConfigKey, DryRunRequest, ModelInfo, Provider,
+    ProviderMetadata, ProviderUsage, Usage,
+};
 use super::embedding::{EmbeddingCapable, EmbeddingRequest, EmbeddingResponse};
 use super::errors::ProviderError;
-use super::formats::openai::{create_request, get_usage, response_to_message};
-use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat, ImageFormat};
+use super::formats::tool_schema::ToolSchemaTokens;
+use super::formats::{openai, openai_responses};
+use super::headers::{
+    client_builder, client_headers, new_request_id, RequestHeaders, REQUEST_ID_HEADER,
+};
+use super::utils::{emit_debug_trace, get_model, handle_timed_response_openai_compat, ImageFormat};
+use super::validation::validate_conversation;
+use crate::config::ConfigError;
 use crate::message::Message;
 use crate::model::ModelConfig;
 use mcp_core::tool::Tool;
@@ -27,6 +35,25 @@ pub const OPEN_AI_KNOWN_MODELS: &[&str] = &[
 
 pub const OPEN_AI_DOC_URL: &str = "https://platform.openai.com/docs/models";
 
+/// The API completions are requested from, configured with OPENAI_API
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenAiApi {
+    #[default]
+    ChatCompletions,
+    /// The Responses API, which returns reasoning summaries of o-series models
+    Responses,
+}
+
+impl OpenAiApi {
+    fn default_base_path(&self) -> &'static str {
+        match self {
+            OpenAiApi::ChatCompletions => "v1/chat/completions",
+            OpenAiApi::Responses => "v1/responses",
+        }
+    }
+}
+
 #[derive(Debug, serde::Serialize)]
 pub struct OpenAiProvider {
     #[serde(skip)]
@@ -37,7 +64,8 @@ pub struct OpenAiProvider {
     organization: Option<String>,
     project: Option<String>,
     model: ModelConfig,
-    custom_headers: Option<HashMap<String, String>>,
+    custom_headers: RequestHeaders,
+    api: OpenAiApi,
 }
 
 impl Default for OpenAiProvider {
@@ -54,18 +82,22 @@ impl OpenAiProvider {
         let host: String = config
             .get_param("OPENAI_HOST")
             .unwrap_or_else(|_| "https://api.openai.com".to_string());
+        let api = match config.get_param::<OpenAiApi>("OPENAI_API") {
+            Err(ConfigError::NotFound(_)) => OpenAiApi::default(),
+            api => api?,
+        };
         let base_path: String = config
             .get_param("OPENAI_BASE_PATH")
-            .unwrap_or_else(|_| "v1/chat/completions".to_string());
+            .unwrap_or_else(|_| api.default_base_path().to_string());
         let organization: Option<String> = config.get_param("OPENAI_ORGANIZATION").ok();
         let project: Option<String> = config.get_param("OPENAI_PROJECT").ok();
-        let custom_headers: Option<HashMap<String, String>> = config
+        let custom_headers = config
             .get_secret("OPENAI_CUSTOM_HEADERS")
-            .or_else(|_| config.get_param("OPENAI_CUSTOM_HEADERS"))
-            .ok()
-            .map(parse_custom_headers);
+            .or_else(|_| config.get_param::<String>("OPENAI_CUSTOM_HEADERS"))
+            .map(|headers| RequestHeaders::parse(&headers))
+            .unwrap_or_default();
         let timeout_secs: u64 = config.get_param("OPENAI_TIMEOUT").unwrap_or(600);
-        let client = Client::builder()
+        let client = client_builder()
             .timeout(Duration::from_secs(timeout_secs))
             .build()?;
 
@@ -78,48 +110,87 @@ impl OpenAiProvider {
             project,
             model,
             custom_headers,
+            api,
         })
     }
 
-    /// Helper function to add OpenAI-specific headers to a request
-    fn add_headers(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
-        // Add organization header if present
+    /// OpenAI-specific headers, sent with every request
+    fn headers(&self) -> RequestHeaders {
+        let mut headers = RequestHeaders::new();
+
         if let Some(org) = &self.organization {
-            request = request.header("OpenAI-Organization", org);
+            headers.insert("OpenAI-Organization", org);
         }
-
-        // Add project header if present
         if let Some(project) = &self.project {
-            request = request.header("OpenAI-Project", project);
+            headers.insert("OpenAI-Project", project);
         }
+        headers.extend(self.custom_headers.clone());
+        headers
+    }
 
-        // Add custom headers if present
-        if let Some(custom_headers) = &self.custom_headers {
-            for (key, value) in custom_headers {
-                request = request.header(key, value);
-            }
-        }
+    /// Helper function to add OpenAI-specific headers to a request
+    fn add_headers(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        self.headers().apply(request)
+    }
 
-        request
+    /// The headers of a completion request, including credentials
+    fn completion_headers(&self) -> RequestHeaders {
+        let mut headers =
+            RequestHeaders::new().with("Authorization", format!("Bearer {}", self.api_key));
+        headers.extend(self.headers());
+        headers
     }
 
-    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+    fn endpoint(&self) -> Result<url::Url, ProviderError> {
         let base_url = url::Url::parse(&self.host)
             .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
-        let url = base_url.join(&self.base_path).map_err(|e| {
+        base_url.join(&self.base_path).map_err(|e| {
             ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
-        })?;
+        })
+    }
 
-        let request = self
-            .client
-            .post(url)
-            .header("Authorization", format!("Bearer {}", self.api_key));
+    fn create_request(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<Value, ProviderError> {
+        // A final assistant message would be answered rather than continued
+        validate_conversation(messages, &Self::metadata().name)?;
+        let request = match self.api {
+            OpenAiApi::ChatCompletions => {
+                openai::create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?
+            }
+            OpenAiApi::Responses => openai_responses::create_request(
+                &self.model,
+                system,
+                messages,
+                tools,
+                &ImageFormat::OpenAi,
+            )?,
+        };
+        Ok(request)
+    }
 
-        let request = self.add_headers(request);
+    /// Send a request and return the response along with the time until its headers arrived
+    async fn post(
+        &self,
+        request_id: &str,
+        payload: Value,
+    ) -> Result<(Value, Duration), ProviderError> {
+        let request = self
+            .completion_headers()
+            .with(REQUEST_ID_HEADER, request_id)
+            .apply(self.client.post(self.endpoint()?));
 
+        let start = Instant::now();
         let response = request.json(&payload).send().await?;
+        let time_to_first_byte = start.elapsed();
 
-        handle_response_openai_compat(response).await
+        Ok((
+            handle_timed_response_openai_compat(response, Some(time_to_first_byte)).await?,
+            time_to_first_byte,
+        ))
     }
 }
 
@@ -149,6 +220,7 @@ impl Provider for OpenAiProvider {
                 ConfigKey::new("OPENAI_PROJECT", false, false, None),
                 ConfigKey::new("OPENAI_CUSTOM_HEADERS", false, true, None),
                 ConfigKey::new("OPENAI_TIMEOUT", false, false, Some("600")),
+                ConfigKey::new("OPENAI_API", false, false, Some("chat_completions")),
             ],
         )
     }
@@ -157,24 +229,49 @@ impl Provider for OpenAiProvider {
         self.model.clone()
     }
 
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_with_options(system, messages, tools, &CompletionOptions::default())
+            .await
+    }
+
     #[tracing::instrument(
-        skip(self, system, messages, tools),
+        skip(self, system, messages, tools, options),
         fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
     )]
-    async fn complete(
+    async fn complete_with_options(
         &self,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
+        options: &CompletionOptions,
     ) -> Result<(Message, ProviderUsage), ProviderError> {
-        let payload = create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        // The response can't be prefilled, so the model is asked to start with the prefill
+        let system = match &options.prefill {
+            Some(prefill) => format!("{}\n\n{}", system, prefill_instruction(prefill)),
+            None => system.to_string(),
+        };
+        let mut payload = self.create_request(&system, messages, tools)?;
+        // Only chat completions return logprobs in the format we parse
+        if self.api == OpenAiApi::ChatCompletions {
+            openai::add_logprobs(&mut payload, options);
+        }
+        openai::add_tool_choice(&mut payload, options);
+        let request_id = new_request_id();
 
         // Make request
-        let response = self.post(payload.clone()).await?;
+        let (response, time_to_first_byte) = self.post(&request_id, payload.clone()).await?;
 
         // Parse response
-        let message = response_to_message(response.clone())?;
-        let usage = match get_usage(&response) {
+        let message = match self.api {
+            OpenAiApi::ChatCompletions => openai::response_to_message(response.clone())?,
+            OpenAiApi::Responses => openai_responses::response_to_message(response.clone())?,
+        };
+        let usage = match openai::get_usage(&response) {
             Ok(usage) => usage,
             Err(ProviderError::UsageError(e)) => {
                 tracing::debug!("Failed to get usage data: {}", e);
@@ -182,9 +279,39 @@ impl Provider for OpenAiProvider {
             }
             Err(e) => return Err(e),
         };
+        let logprobs = match self.api {
+            OpenAiApi::ChatCompletions if options.logprobs => {
+                openai::response_to_logprobs(&response)
+            }
+            _ => None,
+        };
         let model = get_model(&response);
-        emit_debug_trace(&self.model, &payload, &response, &usage);
-        Ok((message, ProviderUsage::new(model, usage)))
+        emit_debug_trace(&self.model, &request_id, &payload, &response, &usage);
+        Ok((
+            message,
+            ProviderUsage::new(model, usage)
+                .with_time_to_first_byte(time_to_first_byte)
+                .with_finish_reason(openai::get_finish_reason(&response))
+                .with_logprobs(logprobs),
+        ))
+    }
+
+    async fn dry_run(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<DryRunRequest, ProviderError> {
+        Ok(DryRunRequest {
+            url: self.endpoint()?.to_string(),
+            headers: client_headers()
+                .with_all(self.completion_headers())
+                .redacted(),
+            payload: self.create_request(system, messages, tools)?,
+            tool_schema_tokens: Some(ToolSchemaTokens::for_request(&self.model, messages, tools)),
+            system_blocks: None,
+            payload_size: None,
+        })
     }
 
     /// Fetch supported models from OpenAI; returns Err on any failure, Ok(None) if no data
@@ -195,18 +322,7 @@ impl Provider for OpenAiProvider {
         let url = base_url
             .join("v1/models")
             .map_err(|e| ProviderError::RequestFailed(e.to_string()))?;
-        let mut request = self.client.get(url).bearer_auth(&self.api_key);
-        if let Some(org) = &self.organization {
-            request = request.header("OpenAI-Organization", org);
-        }
-        if let Some(project) = &self.project {
-            request = request.header("OpenAI-Project", project);
-        }
-        if let Some(headers) = &self.custom_headers {
-            for (key, value) in headers {
-                request = request.header(key, value);
-            }
-        }
+        let request = self.add_headers(self.client.get(url).bearer_auth(&self.api_key));
         let response = request.send().await?;
         let json: serde_json::Value = response.json().await?;
         if let Some(err_obj) = json.get("error") {
@@ -238,17 +354,6 @@ impl Provider for OpenAiProvider {
     }
 }
 
-fn parse_custom_headers(s: String) -> HashMap<String, String> {
-    s.split(',')
-        .filter_map(|header| {
-            let mut parts = header.splitn(2, '=');
-            let key = parts.next().map(|s| s.trim().to_string())?;
-            let value = parts.next().map(|s| s.trim().to_string())?;
-            Some((key, value))
-        })
-        .collect()
-}
-
 #[async_trait]
 impl EmbeddingCapable for OpenAiProvider {
     async fn create_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
@@ -0,0 +1,279 @@
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use mcp_core::Tool;
+use reqwest::Client;
+use serde_json::Value;
+use url::Url;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::openai::{create_request, get_finish_reason, get_usage, response_to_message};
+use super::headers::{client_builder, new_request_id, REQUEST_ID_HEADER};
+use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat, ImageFormat};
+use crate::message::Message;
+use crate::model::ModelConfig;
+
+/// A hosted API that follows OpenAI's chat completions, and what sets it apart
+///
+/// Implementing this is all a compatible provider needs, [`OpenAiCompatibleProvider`]
+/// does the rest.
+pub trait OpenAiCompatible: Send + Sync + 'static {
+    const NAME: &'static str;
+    const DISPLAY_NAME: &'static str;
+    const DESCRIPTION: &'static str;
+    /// The base URL, which the chat completions and models endpoints are relative to
+    const HOST: &'static str;
+    /// The config key of the API key
+    const API_KEY: &'static str;
+    /// The config key that overrides the host
+    const HOST_KEY: &'static str;
+    const DEFAULT_MODEL: &'static str;
+    const KNOWN_MODELS: &'static [&'static str];
+    const DOC_URL: &'static str;
+    /// The context limit of models the model registry has no limit for
+    const CONTEXT_LIMIT: usize;
+
+    /// The name the API knows a model by, from the name it was configured with
+    fn model_name(model: &str) -> String {
+        model.to_string()
+    }
+
+    /// Whether the model calls tools natively, the others use the text-based tool fallback
+    fn supports_tools(_model: &str) -> bool {
+        true
+    }
+
+    /// The token usage of a response
+    fn usage(response: &Value) -> Result<Usage, ProviderError> {
+        get_usage(response)
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct OpenAiCompatibleProvider<S> {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    api_key: String,
+    model: ModelConfig,
+    #[serde(skip)]
+    api: PhantomData<S>,
+}
+
+impl<S: OpenAiCompatible> Default for OpenAiCompatibleProvider<S> {
+    fn default() -> Self {
+        let model = ModelConfig::new(S::DEFAULT_MODEL.to_string());
+        Self::from_env(model)
+            .unwrap_or_else(|e| panic!("Failed to initialize {} provider: {}", S::DISPLAY_NAME, e))
+    }
+}
+
+impl<S: OpenAiCompatible> OpenAiCompatibleProvider<S> {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let api_key: String = config.get_secret(S::API_KEY)?;
+        let host: String = config
+            .get_param(S::HOST_KEY)
+            .unwrap_or_else(|_| S::HOST.to_string());
+
+        let client = client_builder().timeout(Duration::from_secs(600)).build()?;
+
+        Ok(Self {
+            client,
+            host,
+            api_key,
+            model: Self::configure(model),
+            api: PhantomData,
+        })
+    }
+
+    /// The model config with the API's model name, context limit and tool support applied
+    pub(super) fn configure(mut model: ModelConfig) -> ModelConfig {
+        model.model_name = S::model_name(&model.model_name);
+        if model.context_limit.is_none() {
+            model = model.with_context_limit(Some(S::CONTEXT_LIMIT));
+        }
+        if !S::supports_tools(&model.model_name) {
+            model = model.with_toolshim(true);
+        }
+        model
+    }
+
+    fn url(&self, path: &str) -> Result<Url, ProviderError> {
+        // Without a trailing slash the last segment of the host would be replaced
+        let host = format!("{}/", self.host.trim_end_matches('/'));
+        Url::parse(&host)
+            .and_then(|base| base.join(path))
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid {} URL: {e}", S::NAME)))
+    }
+
+    async fn post(&self, request_id: &str, payload: &Value) -> Result<Value, ProviderError> {
+        let response = self
+            .client
+            .post(self.url("chat/completions")?)
+            .bearer_auth(&self.api_key)
+            .header(REQUEST_ID_HEADER, request_id)
+            .json(payload)
+            .send()
+            .await?;
+        handle_response_openai_compat(response).await
+    }
+}
+
+/// The model ids of a models listing, which is either OpenAI's `{"data": [...]}` or a bare array
+pub fn parse_model_list(json: &Value) -> Result<Vec<String>, ProviderError> {
+    if let Some(message) = json.get("error").map(|error| {
+        error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown error")
+    }) {
+        return Err(ProviderError::Authentication(message.to_string()));
+    }
+    let models = json
+        .as_array()
+        .or_else(|| json.get("data").and_then(Value::as_array))
+        .ok_or_else(|| ProviderError::UsageError("Missing data field in JSON response".into()))?;
+    let mut models: Vec<String> = models
+        .iter()
+        .filter_map(|model| model.get("id").and_then(Value::as_str).map(str::to_string))
+        .collect();
+    models.sort();
+    Ok(models)
+}
+
+#[async_trait]
+impl<S: OpenAiCompatible> Provider for OpenAiCompatibleProvider<S> {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            S::NAME,
+            S::DISPLAY_NAME,
+            S::DESCRIPTION,
+            S::DEFAULT_MODEL,
+            S::KNOWN_MODELS.to_vec(),
+            S::DOC_URL,
+            vec![
+                ConfigKey::new(S::API_KEY, true, true, None),
+                ConfigKey::new(S::HOST_KEY, false, false, Some(S::HOST)),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        let request_id = new_request_id();
+        let response = self.post(&request_id, &payload).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match S::usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = get_model(&response);
+        emit_debug_trace(&self.model, &request_id, &payload, &response, &usage);
+        Ok((
+            message,
+            ProviderUsage::new(model, usage).with_finish_reason(get_finish_reason(&response)),
+        ))
+    }
+
+    async fn fetch_supported_models_async(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        let response = self
+            .client
+            .get(self.url("models")?)
+            .bearer_auth(&self.api_key)
+            .send()
+            .await?;
+        let json: Value = response.json().await?;
+        parse_model_list(&json).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    struct Example;
+
+    impl OpenAiCompatible for Example {
+        const NAME: &'static str = "example";
+        const DISPLAY_NAME: &'static str = "Example";
+        const DESCRIPTION: &'static str = "An example API";
+        const HOST: &'static str = "https://api.example.com/v1";
+        const API_KEY: &'static str = "EXAMPLE_API_KEY";
+        const HOST_KEY: &'static str = "EXAMPLE_HOST";
+        const DEFAULT_MODEL: &'static str = "example-large";
+        const KNOWN_MODELS: &'static [&'static str] = &["example-large", "example-small"];
+        const DOC_URL: &'static str = "https://example.com/models";
+        const CONTEXT_LIMIT: usize = 32_768;
+
+        fn model_name(model: &str) -> String {
+            format!("models/{}", model)
+        }
+
+        fn supports_tools(model: &str) -> bool {
+            !model.ends_with("small")
+        }
+    }
+
+    #[test]
+    fn test_configure() {
+        let model = OpenAiCompatibleProvider::<Example>::configure(ModelConfig::new(
+            "example-small".to_string(),
+        ));
+        assert_eq!(model.model_name, "models/example-small");
+        assert_eq!(model.context_limit(), 32_768);
+        assert!(model.toolshim);
+
+        // Limits from the model registry and explicit ones are kept
+        let model = OpenAiCompatibleProvider::<Example>::configure(
+            ModelConfig::new("example-large".to_string()).with_context_limit(Some(8_192)),
+        );
+        assert_eq!(model.context_limit(), 8_192);
+        assert!(!model.toolshim);
+
+        let metadata = OpenAiCompatibleProvider::<Example>::metadata();
+        assert_eq!(metadata.name, "example");
+        assert_eq!(metadata.config_keys[0].name, "EXAMPLE_API_KEY");
+        assert_eq!(
+            metadata.config_keys[1].default.as_deref(),
+            Some(Example::HOST)
+        );
+    }
+
+    #[test]
+    fn test_parse_model_list() {
+        let openai = json!({"object": "list", "data": [{"id": "b"}, {"id": "a"}]});
+        assert_eq!(parse_model_list(&openai).unwrap(), vec!["a", "b"]);
+
+        let bare = json!([{"id": "a", "type": "chat"}, {"type": "no id"}]);
+        assert_eq!(parse_model_list(&bare).unwrap(), vec!["a"]);
+
+        let error = json!({"error": {"message": "invalid api key"}});
+        assert!(matches!(
+            parse_model_list(&error),
+            Err(ProviderError::Authentication(message)) if message == "invalid api key"
+        ));
+    }
+}
@@ -6,6 +6,7 @@ use std::time::Duration;
 
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
+use super::headers::{client_builder, new_request_id, RequestHeaders, REQUEST_ID_HEADER};
 use super::utils::{
     emit_debug_trace, get_model, handle_response_google_compat, handle_response_openai_compat,
     is_google_model,
@@ -53,9 +54,7 @@ impl OpenRouterProvider {
             .get_param("OPENROUTER_HOST")
             .unwrap_or_else(|_| "https://openrouter.ai".to_string());
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(600))
-            .build()?;
+        let client = client_builder().timeout(Duration::from_secs(600)).build()?;
 
         Ok(Self {
             client,
@@ -65,20 +64,23 @@ impl OpenRouterProvider {
         })
     }
 
-    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+    async fn post(&self, request_id: &str, payload: Value) -> Result<Value, ProviderError> {
         let base_url = Url::parse(&self.host)
             .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
         let url = base_url.join("api/v1/chat/completions").map_err(|e| {
             ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
         })?;
 
-        let response = self
-            .client
-            .post(url)
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("HTTP-Referer", "https://block.github.io/goose")
-            .header("X-Title", "Goose")
+        let headers = RequestHeaders::new()
+            .with("Content-Type", "application/json")
+            .with("Authorization", format!("Bearer {}", self.api_key))
+            // https://openrouter.ai/docs/api-reference/overview#headers
+            .with("HTTP-Referer", "https://block.github.io/goose")
+            .with("X-Title", "Goose")
+            .with(REQUEST_ID_HEADER, request_id);
+
+        let response = headers
+            .apply(self.client.post(url))
             .json(&payload)
             .send()
             .await?;
@@ -262,7 +264,8 @@ impl Provider for OpenRouterProvider {
         let payload = create_request_based_on_model(&self.model, system, messages, tools)?;
 
         // Make request
-        let response = self.post(payload.clone()).await?;
+        let request_id = new_request_id();
+        let response = self.post(&request_id, payload.clone()).await?;
 
         // Parse response
         let message = response_to_message(response.clone())?;
@@ -275,7 +278,46 @@ impl Provider for OpenRouterProvider {
             Err(e) => return Err(e),
         };
         let model = get_model(&response);
-        emit_debug_trace(&self.model, &payload, &response, &usage);
+        emit_debug_trace(&self.model, &request_id, &payload, &response, &usage);
         Ok((message, ProviderUsage::new(model, usage)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, header_regex, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_complete_sends_attribution_headers() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/chat/completions"))
+            .and(header("Authorization", "Bearer test-key"))
+            .and(header("HTTP-Referer", "https://block.github.io/goose"))
+            .and(header("X-Title", "Goose"))
+            .and(header_regex("User-Agent", "^goose/"))
+            .and(header_regex(REQUEST_ID_HEADER, "^[0-9a-f-]{36}$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "model": "openai/gpt-4o",
+                "choices": [{"message": {"role": "assistant", "content": "Hello"}}],
+                "usage": {"prompt_tokens": 10, "completion_tokens": 2, "total_tokens": 12}
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = OpenRouterProvider {
+            client: client_builder().build().unwrap(),
+            host: server.uri(),
+            api_key: "test-key".to_string(),
+            model: ModelConfig::new("openai/gpt-4o".to_string()),
+        };
+        let (message, _) = provider
+            .complete("system", &[Message::user().with_text("Hi")], &[])
+            .await
+            .unwrap();
+        assert_eq!(message.as_concat_text(), "Hello");
+    }
+}
@@ -0,0 +1,360 @@
+//! Incremental assembly of streamed tool call arguments
+//!
+//! Streaming APIs send the arguments of a tool call as fragments of a JSON object, split at
+//! arbitrary points. [`PartialJson`] scans each fragment as it arrives and remembers where the
+//! last complete member of the top level object ends, so the keys known so far can be shown
+//! before the object is finished. Only [`PartialJson::finish`] parses the whole text, and its
+//! result is the one a tool call may be dispatched with.
+
+use serde_json::{Map, Value};
+
+/// Where the scanner is inside the top level object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Position {
+    /// Before the opening brace
+    Start,
+    /// Waiting for a key, or inside one
+    Key,
+    /// After the colon of a member whose value has not finished
+    Value,
+    /// After a finished value, waiting for a comma or the closing brace
+    AfterValue,
+    /// The object was closed, anything after it makes the text malformed
+    Closed,
+    /// The text can't be an object, so there is nothing partial to show
+    Invalid,
+}
+
+#[derive(Debug, Clone)]
+pub struct PartialJson {
+    text: String,
+    position: Position,
+    /// Nesting depth, 1 inside the top level object
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    /// The end of the last complete member of the top level object
+    complete_end: Option<usize>,
+}
+
+impl Default for PartialJson {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PartialJson {
+    pub fn new() -> Self {
+        Self {
+            text: String::new(),
+            position: Position::Start,
+            depth: 0,
+            in_string: false,
+            escaped: false,
+            complete_end: None,
+        }
+    }
+
+    /// Append a fragment. Fragments may split the text anywhere, even inside a character
+    /// escape, since only whole characters arrive as `&str`.
+    pub fn push(&mut self, fragment: &str) {
+        let start = self.text.len();
+        self.text.push_str(fragment);
+        for (offset, c) in fragment.char_indices() {
+            self.scan(start + offset, c);
+        }
+    }
+
+    /// Everything received so far
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The members of the top level object whose values have fully arrived
+    ///
+    /// This is best effort and only for display: `None` when the text is not an object, or
+    /// when its complete members don't parse. Strings and nested values count once they
+    /// close, numbers and literals once the next member starts or the object closes.
+    pub fn partial(&self) -> Option<Map<String, Value>> {
+        match self.position {
+            Position::Start | Position::Invalid => None,
+            Position::Closed => match serde_json::from_str(&self.text) {
+                Ok(Value::Object(map)) => Some(map),
+                _ => None,
+            },
+            _ => match self.complete_end {
+                None => Some(Map::new()),
+                Some(end) => match serde_json::from_str(&format!("{}}}", &self.text[..end])) {
+                    Ok(Value::Object(map)) => Some(map),
+                    _ => None,
+                },
+            },
+        }
+    }
+
+    /// Parse the whole text, the only result a tool call may run with
+    pub fn finish(&self) -> serde_json::Result<Value> {
+        serde_json::from_str(&self.text)
+    }
+
+    fn scan(&mut self, index: usize, c: char) {
+        if matches!(self.position, Position::Invalid) {
+            return;
+        }
+        if self.in_string {
+            if self.escaped {
+                self.escaped = false;
+            } else if c == '\\' {
+                self.escaped = true;
+            } else if c == '"' {
+                self.in_string = false;
+                if self.depth == 1 && self.position == Position::Value {
+                    self.complete_member(index + 1);
+                }
+            }
+            return;
+        }
+        if c.is_whitespace() {
+            return;
+        }
+
+        match self.position {
+            Position::Start => {
+                if c == '{' {
+                    self.depth = 1;
+                    self.position = Position::Key;
+                } else {
+                    self.position = Position::Invalid;
+                }
+            }
+            Position::Closed => self.position = Position::Invalid,
+            _ if self.depth > 1 => match c {
+                '"' => self.in_string = true,
+                '{' | '[' => self.depth += 1,
+                '}' | ']' => {
+                    self.depth -= 1;
+                    if self.depth == 1 && self.position == Position::Value {
+                        self.complete_member(index + 1);
+                    }
+                }
+                _ => {}
+            },
+            Position::Key => match c {
+                '"' => self.in_string = true,
+                ':' => self.position = Position::Value,
+                // An empty object, or a trailing comma the final parse will reject
+                '}' => self.close(),
+                _ => {}
+            },
+            Position::Value => match c {
+                '"' => self.in_string = true,
+                '{' | '[' => self.depth += 1,
+                ',' => {
+                    self.complete_member(index);
+                    self.position = Position::Key;
+                }
+                '}' => {
+                    self.complete_member(index);
+                    self.close();
+                }
+                // Numbers and literals only end at the next delimiter
+                _ => {}
+            },
+            Position::AfterValue => match c {
+                ',' => self.position = Position::Key,
+                '}' => self.close(),
+                _ => self.position = Position::Invalid,
+            },
+            Position::Invalid => {}
+        }
+    }
+
+    fn complete_member(&mut self, end: usize) {
+        self.complete_end = Some(end);
+        self.position = Position::AfterValue;
+    }
+
+    fn close(&mut self) {
+        self.depth = 0;
+        self.position = Position::Closed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn assembled(fragments: &[&str]) -> PartialJson {
+        let mut partial = PartialJson::new();
+        for fragment in fragments {
+            partial.push(fragment);
+        }
+        partial
+    }
+
+    /// The partial after each character of `text`, pushed one at a time
+    fn partials_by_prefix(text: &str) -> Vec<(usize, Option<Map<String, Value>>)> {
+        let mut partial = PartialJson::new();
+        let mut result = Vec::new();
+        for (index, c) in text.char_indices() {
+            partial.push(&text[index..index + c.len_utf8()]);
+            result.push((index + c.len_utf8(), partial.partial()));
+        }
+        result
+    }
+
+    #[test]
+    fn test_path_is_known_before_the_object_ends() {
+        let partial = assembled(&[
+            r#"{"command": "wri"#,
+            r#"te", "path": "/src/ma"#,
+            r#"in.rs", "file_text": "fn main() {"#,
+        ]);
+        assert_eq!(
+            Value::Object(partial.partial().unwrap()),
+            json!({"command": "write", "path": "/src/main.rs"})
+        );
+        assert!(partial.finish().is_err());
+    }
+
+    #[test]
+    fn test_scalars_complete_at_the_next_delimiter() {
+        let partial = assembled(&[r#"{"line": 12"#]);
+        assert_eq!(partial.partial().unwrap(), Map::new());
+        let partial = assembled(&[r#"{"line": 12"#, "3,"]);
+        assert_eq!(
+            Value::Object(partial.partial().unwrap()),
+            json!({"line": 123})
+        );
+        let partial = assembled(&[r#"{"a": true, "b": null"#, "}"]);
+        assert_eq!(
+            Value::Object(partial.partial().unwrap()),
+            json!({"a": true, "b": null})
+        );
+    }
+
+    #[test]
+    fn test_nested_values_complete_when_they_close() {
+        let partial = assembled(&[r#"{"range": [1, "#]);
+        assert_eq!(partial.partial().unwrap(), Map::new());
+        let partial = assembled(&[r#"{"range": [1, 2], "opts": {"a": "}"#]);
+        assert_eq!(
+            Value::Object(partial.partial().unwrap()),
+            json!({"range": [1, 2]})
+        );
+        let partial = assembled(&[r#"{"range": [1, 2], "opts": {"a": "}"}"#]);
+        assert_eq!(
+            Value::Object(partial.partial().unwrap()),
+            json!({"range": [1, 2], "opts": {"a": "}"}})
+        );
+    }
+
+    #[test]
+    fn test_escapes_split_across_fragments() {
+        let partial = assembled(&[r#"{"path": "a\"#, r#"" b\\"#, r#"", "x": 1}"#]);
+        assert_eq!(
+            Value::Object(partial.partial().unwrap()),
+            json!({"path": "a\" b\\", "x": 1})
+        );
+        assert_eq!(
+            partial.finish().unwrap(),
+            json!({"path": "a\" b\\", "x": 1})
+        );
+    }
+
+    #[test]
+    fn test_not_an_object() {
+        assert!(assembled(&[]).partial().is_none());
+        assert!(assembled(&["[1, 2]"]).partial().is_none());
+        assert!(assembled(&["  \"text\""]).partial().is_none());
+        assert_eq!(assembled(&["  {"]).partial().unwrap(), Map::new());
+    }
+
+    #[test]
+    fn test_malformed_text_never_finishes() {
+        for text in [
+            r#"{"a": 1,}"#,
+            r#"{"a": 1} trailing"#,
+            r#"{"a" 1}"#,
+            r#"{"a": tru}"#,
+            r#"{"a": "b" "c": 1}"#,
+        ] {
+            let partial = assembled(&[text]);
+            assert!(partial.finish().is_err(), "{}", text);
+        }
+        // Garbage after a closed object leaves nothing to show
+        assert!(assembled(&[r#"{"a": 1} x"#]).partial().is_none());
+    }
+
+    /// Every split point of every sample agrees with parsing the same prefix at once, the
+    /// known keys only grow, and the finished object matches serde_json
+    #[test]
+    fn test_every_split_point() {
+        let samples = [
+            json!({"command": "str_replace", "path": "/tmp/a b/ü.rs", "old_str": "x\n\"y\"", "new_str": ""}),
+            json!({"n": -1.5e3, "flags": [true, false, null], "nested": {"deep": [{"k": "}]"}]}}),
+            json!({"emoji": "🦆 goose", "escaped": "\\u0041 \t", "empty": {}, "list": []}),
+            json!({}),
+        ];
+        for sample in samples {
+            for text in [
+                sample.to_string(),
+                serde_json::to_string_pretty(&sample).unwrap(),
+            ] {
+                let prefixes = partials_by_prefix(&text);
+                let mut known = 0;
+                for (end, partial) in &prefixes {
+                    let whole = assembled(&[&text[..*end]]).partial();
+                    assert_eq!(partial, &whole, "split at {} of {}", end, text);
+                    let partial = partial.as_ref().expect("an object prefix has a partial");
+                    assert!(partial.len() >= known, "keys shrank at {} of {}", end, text);
+                    known = partial.len();
+                    for (key, value) in partial {
+                        assert_eq!(&sample[key], value, "{} at {} of {}", key, end, text);
+                    }
+                }
+
+                // Two fragments, split at every boundary
+                for (split, _) in text.char_indices() {
+                    let partial = assembled(&[&text[..split], &text[split..]]);
+                    assert_eq!(partial.finish().unwrap(), sample);
+                    assert_eq!(Value::Object(partial.partial().unwrap()), sample);
+                }
+            }
+        }
+    }
+
+    /// Pseudo random fragment sizes, so splits land in escapes, keys and numbers alike
+    #[test]
+    fn test_random_fragments() {
+        let sample = json!({
+            "path": "/repo/src/lib.rs",
+            "view_range": [10, 20],
+            "file_text": "line one\nline \"two\"\t\\ end 🦆",
+            "count": 42,
+        });
+        let text = sample.to_string();
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let mut seed: u64 = 0x9E3779B97F4A7C15;
+        for _ in 0..200 {
+            let mut partial = PartialJson::new();
+            let mut start = 0;
+            while start < chars.len() {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                let len = 1 + (seed % 7) as usize;
+                let end = (start + len).min(chars.len());
+                let from = chars[start].0;
+                let to = chars.get(end).map_or(text.len(), |(index, _)| *index);
+                partial.push(&text[from..to]);
+                for (key, value) in partial.partial().unwrap() {
+                    assert_eq!(sample[&key], value);
+                }
+                start = end;
+            }
+            assert_eq!(partial.finish().unwrap(), sample);
+        }
+    }
+}
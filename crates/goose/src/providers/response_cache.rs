@@ -0,0 +1,399 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use super::base::{
+    CompletionOptions, DryRunRequest, LeadWorkerProviderTrait, Provider, ProviderMetadata,
+    ProviderUsage, ToolCallProgressSender, Usage,
+};
+use super::errors::ProviderError;
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+/// A hash of everything that decides the response to a request
+///
+/// Messages are hashed by their role and content, so copies of a conversation made at another
+/// time hash the same.
+pub fn request_hash(
+    model_config: &ModelConfig,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+    options: &CompletionOptions,
+) -> String {
+    let request = json!({
+        "model": model_config,
+        "system": system,
+        "messages": messages
+            .iter()
+            .map(|message| json!({"role": message.role, "content": message.content}))
+            .collect::<Vec<_>>(),
+        "tools": tools,
+        "prefill": options.prefill,
+        "logprobs": options.logprobs,
+        "top_logprobs": options.top_logprobs,
+        "no_tool_calls": options.no_tool_calls,
+    });
+    format!("{:x}", Sha256::digest(request.to_string().as_bytes()))
+}
+
+#[derive(Default)]
+struct CacheEntries {
+    responses: HashMap<String, (Message, ProviderUsage)>,
+    /// Hashes from the least to the most recently used
+    order: VecDeque<String>,
+}
+
+/// Responses of requests by their [`request_hash`], keeping the most recently used ones
+///
+/// Failed requests are not cached.
+pub struct ResponseCache {
+    capacity: usize,
+    entries: Mutex<CacheEntries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ResponseCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: Mutex::new(CacheEntries::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// The cached response for `hash`, or the response of `request` which is then cached
+    ///
+    /// A cached response reports no token usage, as answering it cost nothing.
+    pub async fn get_or_complete<F, Fut>(
+        &self,
+        hash: String,
+        request: F,
+    ) -> Result<(Message, ProviderUsage), ProviderError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<(Message, ProviderUsage), ProviderError>>,
+    {
+        if let Some((message, usage)) = self.get(&hash) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            tracing::debug!("Using the cached response of request {}", hash);
+            let usage = ProviderUsage {
+                usage: Usage::new(Some(0), Some(0), Some(0)),
+                time_to_first_byte_ms: None,
+                continuations: 0,
+                ..usage
+            };
+            return Ok((message, usage));
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let response = request().await?;
+        self.insert(hash, response.clone());
+        Ok(response)
+    }
+
+    fn get(&self, hash: &str) -> Option<(Message, ProviderUsage)> {
+        let mut entries = self.entries.lock().unwrap();
+        let response = entries.responses.get(hash)?.clone();
+        entries.order.retain(|key| key != hash);
+        entries.order.push_back(hash.to_string());
+        Some(response)
+    }
+
+    fn insert(&self, hash: String, response: (Message, ProviderUsage)) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.order.retain(|key| *key != hash);
+        entries.order.push_back(hash.clone());
+        entries.responses.insert(hash, response);
+        while entries.order.len() > self.capacity {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.responses.remove(&oldest);
+            }
+        }
+    }
+
+    /// Requests answered from the cache
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Requests sent to the provider
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// The number of cached responses
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().responses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A provider that answers repeated identical requests from a [`ResponseCache`], configured
+/// with GOOSE_RESPONSE_CACHE_SIZE
+///
+/// Retries and replays of a session often send the same request again, which doesn't need
+/// another round trip to the model.
+pub struct CachingProvider {
+    inner: Arc<dyn Provider>,
+    cache: ResponseCache,
+}
+
+impl CachingProvider {
+    pub fn new(inner: Arc<dyn Provider>, capacity: usize) -> Self {
+        Self {
+            inner,
+            cache: ResponseCache::new(capacity),
+        }
+    }
+
+    pub fn cache(&self) -> &ResponseCache {
+        &self.cache
+    }
+
+    fn hash(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        options: &CompletionOptions,
+    ) -> String {
+        request_hash(
+            &self.inner.get_model_config(),
+            system,
+            messages,
+            tools,
+            options,
+        )
+    }
+}
+
+#[async_trait]
+impl Provider for CachingProvider {
+    fn metadata() -> ProviderMetadata {
+        // This is a wrapper provider, so we return minimal metadata
+        ProviderMetadata::new(
+            "caching",
+            "Caching Provider",
+            "A provider that answers repeated identical requests from a cache",
+            "",     // No default model as this is determined by the wrapped provider
+            vec![], // No known models as this depends on the wrapped provider
+            "",     // No doc link
+            vec![], // No config keys as configuration is done through the wrapped provider
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let hash = self.hash(system, messages, tools, &CompletionOptions::default());
+        self.cache
+            .get_or_complete(hash, || self.inner.complete(system, messages, tools))
+            .await
+    }
+
+    async fn complete_with_progress(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        progress: ToolCallProgressSender,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let hash = self.hash(system, messages, tools, &CompletionOptions::default());
+        self.cache
+            .get_or_complete(hash, || {
+                self.inner
+                    .complete_with_progress(system, messages, tools, progress)
+            })
+            .await
+    }
+
+    async fn complete_with_options(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        options: &CompletionOptions,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let hash = self.hash(system, messages, tools, options);
+        self.cache
+            .get_or_complete(hash, || {
+                self.inner
+                    .complete_with_options(system, messages, tools, options)
+            })
+            .await
+    }
+
+    async fn dry_run(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<DryRunRequest, ProviderError> {
+        self.inner.dry_run(system, messages, tools).await
+    }
+
+    fn supports_prefill(&self) -> bool {
+        self.inner.supports_prefill()
+    }
+
+    fn supports_system_blocks(&self) -> bool {
+        self.inner.supports_system_blocks()
+    }
+
+    async fn fetch_supported_models_async(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        self.inner.fetch_supported_models_async().await
+    }
+
+    fn supports_embeddings(&self) -> bool {
+        self.inner.supports_embeddings()
+    }
+
+    async fn create_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, ProviderError> {
+        self.inner.create_embeddings(texts).await
+    }
+
+    fn as_lead_worker(&self) -> Option<&dyn LeadWorkerProviderTrait> {
+        self.inner.as_lead_worker()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Answers every request with the number of requests it was sent
+    struct CountingProvider {
+        requests: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Provider for CountingProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("counting".to_string())
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            let count = self.requests.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok((
+                Message::assistant().with_text(format!("response {}", count)),
+                ProviderUsage::new(
+                    "counting".to_string(),
+                    Usage::new(Some(10), Some(5), Some(15)),
+                ),
+            ))
+        }
+    }
+
+    fn text(message: &Message) -> &str {
+        message.content[0].as_text().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_identical_request_is_cached() {
+        let inner = Arc::new(CountingProvider {
+            requests: AtomicUsize::new(0),
+        });
+        let provider = CachingProvider::new(inner.clone(), 2);
+        let hello = vec![Message::user().with_text("Hello")];
+
+        let (first, first_usage) = provider.complete("system", &hello, &[]).await.unwrap();
+        // A copy of the conversation made later hashes the same
+        let hello_again = vec![Message {
+            created: 0,
+            ..Message::user().with_text("Hello")
+        }];
+        let (second, second_usage) = provider
+            .complete("system", &hello_again, &[])
+            .await
+            .unwrap();
+        assert_eq!(text(&first), "response 1");
+        assert_eq!(text(&second), "response 1");
+        // Only the request that reached the provider used tokens
+        assert_eq!(first_usage.usage.total_tokens, Some(15));
+        assert_eq!(second_usage.usage, Usage::new(Some(0), Some(0), Some(0)));
+        assert_eq!(second_usage.model, "counting");
+        assert_eq!(inner.requests.load(Ordering::SeqCst), 1);
+        assert_eq!(provider.cache().hits(), 1);
+        assert_eq!(provider.cache().misses(), 1);
+
+        // Another system prompt or option is another request
+        let (other, _) = provider.complete("other", &hello, &[]).await.unwrap();
+        assert_eq!(text(&other), "response 2");
+        let options = CompletionOptions::default().with_prefill("{");
+        provider
+            .complete_with_options("system", &hello, &[], &options)
+            .await
+            .unwrap();
+        assert_eq!(inner.requests.load(Ordering::SeqCst), 3);
+        assert_eq!(provider.cache().misses(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_cache_capacity() {
+        let cache = ResponseCache::new(2);
+        let response = |text: &str| {
+            let message = Message::assistant().with_text(text);
+            let usage = ProviderUsage::new("test".to_string(), Usage::default());
+            async move { Ok((message, usage)) }
+        };
+        for hash in ["a", "b", "a", "c"] {
+            cache
+                .get_or_complete(hash.to_string(), || response(hash))
+                .await
+                .unwrap();
+        }
+        assert_eq!(cache.len(), 2);
+        assert_eq!((cache.hits(), cache.misses()), (1, 3));
+
+        // "a" was used more recently than "b", so "b" was evicted for "c"
+        let (message, _) = cache
+            .get_or_complete("a".to_string(), || async {
+                panic!("the request should not be sent")
+            })
+            .await
+            .unwrap();
+        assert_eq!(text(&message), "a");
+        let (message, _) = cache
+            .get_or_complete("b".to_string(), || response("b again"))
+            .await
+            .unwrap();
+        assert_eq!(text(&message), "b again");
+
+        // Failed requests aren't cached
+        let failed = cache
+            .get_or_complete("d".to_string(), || async {
+                Err(ProviderError::ServerError("overloaded".to_string()))
+            })
+            .await;
+        assert!(failed.is_err());
+        assert_eq!(cache.len(), 2);
+    }
+}
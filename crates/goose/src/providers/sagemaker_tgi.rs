@@ -12,10 +12,10 @@ use tokio::time::sleep;
 
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
+use super::headers::new_request_id;
 use super::utils::emit_debug_trace;
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
-use chrono::Utc;
 use mcp_core::content::TextContent;
 use mcp_core::role::Role;
 
@@ -205,11 +205,12 @@ impl SageMakerTgiProvider {
 
         Ok(Message {
             role: Role::Assistant,
-            created: Utc::now().timestamp(),
+            created: crate::determinism::now().timestamp(),
             content: vec![MessageContent::Text(TextContent {
                 text: clean_text,
                 annotations: None,
             })],
+            extra: Default::default(),
         })
     }
 
@@ -333,6 +334,7 @@ impl Provider for SageMakerTgiProvider {
                     });
                     emit_debug_trace(
                         &self.model,
+                        &new_request_id(),
                         &debug_payload,
                         &serde_json::to_value(&message).unwrap_or_default(),
                         &usage,
@@ -8,6 +8,7 @@ use std::time::Duration;
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
 use super::errors::ProviderError;
 use super::formats::snowflake::{create_request, get_usage, response_to_message};
+use super::headers::{client_builder, new_request_id, REQUEST_ID_HEADER};
 use super::utils::{get_model, ImageFormat};
 use crate::config::ConfigError;
 use crate::message::Message;
@@ -86,9 +87,7 @@ impl SnowflakeProvider {
             .into());
         }
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(600))
-            .build()?;
+        let client = client_builder().timeout(Duration::from_secs(600)).build()?;
 
         // Use token-based authentication
         let api_key = token?;
@@ -108,7 +107,7 @@ impl SnowflakeProvider {
         }
     }
 
-    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+    async fn post(&self, request_id: &str, payload: Value) -> Result<Value, ProviderError> {
         let base_url_str =
             if !self.host.starts_with("https://") && !self.host.starts_with("http://") {
                 format!("https://{}", self.host)
@@ -127,7 +126,7 @@ impl SnowflakeProvider {
             .client
             .post(url)
             .header("Authorization", auth_header)
-            .header("User-Agent", "Goose")
+            .header(REQUEST_ID_HEADER, request_id)
             .json(&payload)
             .send()
             .await?;
@@ -426,13 +425,15 @@ impl Provider for SnowflakeProvider {
     ) -> Result<(Message, ProviderUsage), ProviderError> {
         let payload = create_request(&self.model, system, messages, tools)?;
 
-        let response = self.post(payload.clone()).await?;
+        let request_id = new_request_id();
+
+        let response = self.post(&request_id, payload.clone()).await?;
 
         // Parse response
         let message = response_to_message(response.clone())?;
         let usage = get_usage(&response)?;
         let model = get_model(&response);
-        super::utils::emit_debug_trace(&self.model, &payload, &response, &usage);
+        super::utils::emit_debug_trace(&self.model, &request_id, &payload, &response, &usage);
 
         Ok((message, ProviderUsage::new(model, usage)))
     }
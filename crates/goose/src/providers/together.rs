@@ -0,0 +1,95 @@
+use serde_json::Value;
+
+use super::base::Usage;
+use super::errors::ProviderError;
+use super::formats::openai::get_usage;
+use super::openai_compatible::{OpenAiCompatible, OpenAiCompatibleProvider};
+
+/// Models that call tools natively, https://docs.together.ai/docs/function-calling
+const TOGETHER_TOOL_MODELS: &[&str] = &[
+    "llama-4",
+    "llama-3.3-70b",
+    "meta-llama-3.1",
+    "qwen2.5-7b-instruct-turbo",
+    "qwen2.5-72b",
+    "qwen3",
+    "deepseek-v3",
+    "mistral-small",
+    "kimi-k2",
+];
+
+pub struct Together;
+
+impl OpenAiCompatible for Together {
+    const NAME: &'static str = "together";
+    const DISPLAY_NAME: &'static str = "Together AI";
+    const DESCRIPTION: &'static str = "Open models served by Together AI";
+    const HOST: &'static str = "https://api.together.xyz/v1";
+    const API_KEY: &'static str = "TOGETHER_API_KEY";
+    const HOST_KEY: &'static str = "TOGETHER_HOST";
+    const DEFAULT_MODEL: &'static str = "meta-llama/Llama-3.3-70B-Instruct-Turbo";
+    const KNOWN_MODELS: &'static [&'static str] = &[
+        "meta-llama/Llama-3.3-70B-Instruct-Turbo",
+        "meta-llama/Meta-Llama-3.1-405B-Instruct-Turbo",
+        "meta-llama/Meta-Llama-3.1-8B-Instruct-Turbo",
+        "Qwen/Qwen2.5-72B-Instruct-Turbo",
+        "deepseek-ai/DeepSeek-V3",
+        "mistralai/Mixtral-8x7B-Instruct-v0.1",
+    ];
+    const DOC_URL: &'static str = "https://docs.together.ai/docs/serverless-models";
+    const CONTEXT_LIMIT: usize = 32_768;
+
+    fn supports_tools(model: &str) -> bool {
+        let model = model.to_lowercase();
+        TOGETHER_TOOL_MODELS
+            .iter()
+            .any(|pattern| model.contains(pattern))
+    }
+
+    fn usage(response: &Value) -> Result<Usage, ProviderError> {
+        match response.get("usage").filter(|usage| !usage.is_null()) {
+            Some(_) => get_usage(response),
+            // Streamed responses carry the usage on their choice instead
+            None => get_usage(&response["choices"][0]),
+        }
+    }
+}
+
+pub type TogetherProvider = OpenAiCompatibleProvider<Together>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_together_models() {
+        assert!(Together::supports_tools(Together::DEFAULT_MODEL));
+        assert!(Together::supports_tools("Qwen/Qwen2.5-72B-Instruct-Turbo"));
+        assert!(!Together::supports_tools(
+            "mistralai/Mixtral-8x7B-Instruct-v0.1"
+        ));
+        assert_eq!(
+            Together::model_name(Together::DEFAULT_MODEL),
+            Together::DEFAULT_MODEL
+        );
+    }
+
+    #[test]
+    fn test_together_usage() {
+        let usage = Usage::new(Some(12), Some(5), Some(17));
+        let counts = json!({"prompt_tokens": 12, "completion_tokens": 5, "total_tokens": 17});
+
+        let response = json!({"choices": [{"message": {}}], "usage": counts});
+        assert_eq!(Together::usage(&response).unwrap(), usage);
+
+        let streamed = json!({"choices": [{"delta": {}, "usage": counts}], "usage": null});
+        assert_eq!(Together::usage(&streamed).unwrap(), usage);
+
+        let without = json!({"choices": [{"message": {}}]});
+        assert!(matches!(
+            Together::usage(&without),
+            Err(ProviderError::UsageError(_))
+        ));
+    }
+}
@@ -0,0 +1,54 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Where the ids of tool calls that goose makes up itself come from
+///
+/// Providers give their tool calls ids, but calls parsed from the legacy `function_call` form
+/// or interpreted by the toolshim have none. Tests use [`SequentialToolCallIds`] so the ids are
+/// the same on every run.
+pub trait ToolCallIdSource: Send + Sync {
+    fn next_id(&self) -> String;
+}
+
+/// Random ids from [`crate::determinism::new_uuid`], for production
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UuidToolCallIds;
+
+impl ToolCallIdSource for UuidToolCallIds {
+    fn next_id(&self) -> String {
+        crate::determinism::new_uuid().to_string()
+    }
+}
+
+/// Ids counting up from `call_0`
+#[derive(Debug, Default)]
+pub struct SequentialToolCallIds {
+    next: AtomicUsize,
+}
+
+impl SequentialToolCallIds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ToolCallIdSource for SequentialToolCallIds {
+    fn next_id(&self) -> String {
+        format!("call_{}", self.next.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_call_ids() {
+        let ids = SequentialToolCallIds::new();
+        assert_eq!(ids.next_id(), "call_0");
+        assert_eq!(ids.next_id(), "call_1");
+        assert_eq!(ids.next_id(), "call_2");
+
+        let ids = UuidToolCallIds;
+        assert_ne!(ids.next_id(), ids.next_id());
+    }
+}
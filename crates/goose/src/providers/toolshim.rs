@@ -31,8 +31,10 @@
 //!
 
 use super::errors::ProviderError;
+use super::headers::client_builder;
 use super::ollama::OLLAMA_DEFAULT_PORT;
 use super::ollama::OLLAMA_HOST;
+use super::tool_call_ids::{ToolCallIdSource, UuidToolCallIds};
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
 use crate::providers::formats::openai::create_request;
@@ -42,7 +44,6 @@ use mcp_core::Content;
 use reqwest::Client;
 use serde_json::{json, Value};
 use std::time::Duration;
-use uuid::Uuid;
 
 /// Default model to use for tool interpretation
 pub const DEFAULT_INTERPRETER_MODEL_OLLAMA: &str = "mistral-nemo";
@@ -59,6 +60,11 @@ pub trait ToolInterpreter {
         content: &str,
         tools: &[Tool],
     ) -> Result<Vec<ToolCall>, ProviderError>;
+
+    /// Where the ids of the interpreted tool calls come from
+    fn tool_call_ids(&self) -> &dyn ToolCallIdSource {
+        &UuidToolCallIds
+    }
 }
 
 /// Ollama-specific implementation of the ToolInterpreter trait
@@ -69,7 +75,7 @@ pub struct OllamaInterpreter {
 
 impl OllamaInterpreter {
     pub fn new() -> Result<Self, ProviderError> {
-        let client = Client::builder()
+        let client = client_builder()
             .timeout(Duration::from_secs(600))
             .build()
             .expect("Failed to create HTTP client");
@@ -342,6 +348,7 @@ pub fn convert_tool_messages_to_text(messages: &[Message]) -> Vec<Message> {
                                     .iter()
                                     .filter_map(|c| match c {
                                         Content::Text(t) => Some(t.text.clone()),
+                                        Content::Result(r) => Some(r.text.clone()),
                                         _ => None,
                                     })
                                     .collect();
@@ -363,6 +370,7 @@ pub fn convert_tool_messages_to_text(messages: &[Message]) -> Vec<Message> {
                     role: message.role.clone(),
                     content: new_content,
                     created: message.created,
+                    extra: message.extra.clone(),
                 }
             } else {
                 message.clone()
@@ -430,7 +438,7 @@ pub async fn augment_message_with_tool_calls<T: ToolInterpreter>(
     for tool_call in tool_calls {
         if tool_call.name != "noop" {
             // do not actually execute noop tool
-            let id = Uuid::new_v4().to_string();
+            let id = interpreter.tool_call_ids().next_id();
             final_message = final_message.with_tool_request(id, Ok(tool_call));
         }
     }
@@ -1,5 +1,7 @@
 use super::base::Usage;
+use super::diagnosis::{diagnose, SetupFailure};
 use super::errors::GoogleErrorCode;
+use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
 use anyhow::Result;
 use base64::Engine;
@@ -7,11 +9,17 @@ use regex::Regex;
 use reqwest::{Response, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::{from_value, json, Map, Value};
+use std::borrow::Cow;
 use std::io::Read;
+use std::ops::RangeInclusive;
 use std::path::Path;
+use std::time::Duration;
 
 use crate::providers::errors::{OpenAIError, ProviderError};
+use crate::providers::tool_call_ids::ToolCallIdSource;
 use mcp_core::content::ImageContent;
+use mcp_core::tool::Tool;
+use mcp_core::Content;
 
 #[derive(serde::Deserialize)]
 struct OpenAIErrorResponse {
@@ -24,8 +32,157 @@ pub enum ImageFormat {
     Anthropic,
 }
 
+/// How the text blocks of a tool result are joined when a provider takes it as one string
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolContentSeparator {
+    Space,
+    /// One block per line, which keeps multi-line output readable
+    #[default]
+    Newline,
+    /// A JSON array of the blocks, for models that handle structured results better
+    JsonArray,
+}
+
+impl std::str::FromStr for ToolContentSeparator {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "space" => Ok(Self::Space),
+            "newline" => Ok(Self::Newline),
+            "json_array" => Ok(Self::JsonArray),
+            _ => Err(anyhow::anyhow!(
+                "Unknown tool content separator '{}', expected space, newline or json_array",
+                s
+            )),
+        }
+    }
+}
+
+impl ToolContentSeparator {
+    pub fn join(&self, blocks: &[String]) -> String {
+        match self {
+            Self::Space => blocks.join(" "),
+            Self::Newline => blocks.join("\n"),
+            Self::JsonArray => json!(blocks).to_string(),
+        }
+    }
+}
+
+/// Where images in tool results go for providers that take tool results as text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolImagePolicy {
+    /// Replace the image with a note and send it in a user message after the tool result
+    #[default]
+    Hoist,
+    /// Send the image as a part of the tool result, for APIs that accept array content there
+    Inline,
+    /// Replace the image with a note and don't send it
+    Drop,
+}
+
+impl std::str::FromStr for ToolImagePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "hoist" => Ok(Self::Hoist),
+            "inline" => Ok(Self::Inline),
+            "drop" => Ok(Self::Drop),
+            _ => Err(anyhow::anyhow!(
+                "Unknown tool image policy '{}', expected hoist, inline or drop",
+                s
+            )),
+        }
+    }
+}
+
+impl ToolImagePolicy {
+    /// The policy to use when tool results can or can't hold array content
+    ///
+    /// Inline images fall back to hoisting when they can't be sent in the tool result.
+    pub fn for_capabilities(self, supports_array_content: bool) -> Self {
+        match self {
+            Self::Inline if !supports_array_content => Self::Hoist,
+            policy => policy,
+        }
+    }
+}
+
+/// What happens to a temperature outside of the range a provider accepts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemperaturePolicy {
+    /// Send the nearest accepted value and log a warning
+    #[default]
+    Clamp,
+    /// Fail the request before it is sent
+    Reject,
+}
+
+impl std::str::FromStr for TemperaturePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "clamp" => Ok(Self::Clamp),
+            "reject" => Ok(Self::Reject),
+            _ => Err(anyhow::anyhow!(
+                "Unknown temperature policy '{}', expected clamp or reject",
+                s
+            )),
+        }
+    }
+}
+
+/// The temperature to send to a provider that accepts values in `range`
+///
+/// Values outside of it are clamped or rejected according to the policy of the model config.
+/// A temperature that is not a number is dropped when clamping, as there is nothing near it.
+pub fn validate_temperature(
+    model_config: &ModelConfig,
+    range: RangeInclusive<f32>,
+) -> Result<Option<f32>> {
+    let Some(temperature) = model_config.temperature else {
+        return Ok(None);
+    };
+    if range.contains(&temperature) {
+        return Ok(Some(temperature));
+    }
+    if model_config.temperature_policy == TemperaturePolicy::Reject {
+        return Err(anyhow::anyhow!(
+            "Temperature {} of {} is outside of the accepted range {} to {}",
+            temperature,
+            model_config.model_name,
+            range.start(),
+            range.end()
+        ));
+    }
+    if temperature.is_nan() {
+        tracing::warn!(
+            "Dropping temperature {} of {}, as it is not a number",
+            temperature,
+            model_config.model_name
+        );
+        return Ok(None);
+    }
+    let clamped = temperature.clamp(*range.start(), *range.end());
+    tracing::warn!(
+        "Clamping temperature {} of {} to {}, the accepted range is {} to {}",
+        temperature,
+        model_config.model_name,
+        clamped,
+        range.start(),
+        range.end()
+    );
+    Ok(Some(clamped))
+}
+
 /// Convert an image content into an image json based on format
 pub fn convert_image(image: &ImageContent, image_format: &ImageFormat) -> Value {
+    let image = normalize_image(image.clone());
     match image_format {
         ImageFormat::OpenAi => json!({
             "type": "image_url",
@@ -44,35 +201,190 @@ pub fn convert_image(image: &ImageContent, image_format: &ImageFormat) -> Value
     }
 }
 
+/// The image with its data as raw standard base64 and an image media type, whatever
+/// provider it came from
+///
+/// Images from OpenAI messages may hold a whole data URL, while other providers take only
+/// the base64 after it. The media type of a data URL replaces a `mime_type` that is not an
+/// image type, and without either the type is taken from the first bytes of the image.
+pub fn normalize_image(mut image: ImageContent) -> ImageContent {
+    if let Some((media_type, data)) = split_data_url(&image.data) {
+        if !is_image_type(&image.mime_type) && is_image_type(media_type) {
+            image.mime_type = media_type.to_string();
+        }
+        image.data = data.to_string();
+    }
+    if let Cow::Owned(data) = normalize_base64(&image.data) {
+        image.data = data;
+    }
+    if !is_image_type(&image.mime_type) {
+        if let Some(media_type) = sniff_image_type(&image.data) {
+            image.mime_type = media_type.to_string();
+        }
+    }
+    image
+}
+
+/// The media type and the data of a base64 data URL, `data:image/png;base64,...`
+fn split_data_url(data: &str) -> Option<(&str, &str)> {
+    let (header, data) = data.strip_prefix("data:")?.split_once(',')?;
+    let media_type = header.strip_suffix(";base64")?;
+    Some((media_type.split(';').next().unwrap_or_default(), data))
+}
+
+fn is_image_type(mime_type: &str) -> bool {
+    mime_type
+        .strip_prefix("image/")
+        .is_some_and(|subtype| !subtype.is_empty())
+}
+
+/// The media type of base64 image data by its signature, for the formats models accept
+fn sniff_image_type(data: &str) -> Option<&'static str> {
+    // 16 characters decode to the 12 bytes the signatures need
+    let bytes = decode_base64(data.get(..16)?).ok()?;
+    match bytes.as_slice() {
+        [0x89, b'P', b'N', b'G', ..] => Some("image/png"),
+        [0xff, 0xd8, 0xff, ..] => Some("image/jpeg"),
+        [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// The data as standard base64 with padding, which data URLs and providers take
+///
+/// Some tools emit the URL-safe alphabet, or leave out the padding. Data that is not base64
+/// at all is returned as it is, for [`validate_image_data`] to report.
+pub fn normalize_base64(data: &str) -> Cow<'_, str> {
+    let url_safe = data.contains(['-', '_']);
+    let padding = match data.len() % 4 {
+        2 => "==",
+        3 => "=",
+        _ => "",
+    };
+    if !url_safe && padding.is_empty() {
+        return Cow::Borrowed(data);
+    }
+    let mut normalized = data.replace('-', "+").replace('_', "/");
+    normalized.push_str(padding);
+    Cow::Owned(normalized)
+}
+
+/// Decode base64 in the standard or URL-safe alphabet, with or without padding
+pub fn decode_base64(data: &str) -> Result<Vec<u8>, base64::DecodeError> {
+    base64::prelude::BASE64_STANDARD.decode(normalize_base64(data).as_bytes())
+}
+
+/// Check that the data of an image is base64, which providers otherwise reject with unclear errors
+pub fn validate_image_data(image: &ImageContent) -> Result<()> {
+    use base64::DecodeError;
+
+    // A data URL is sent as the base64 after its prefix
+    let data = split_data_url(&image.data).map_or(image.data.as_str(), |(_, data)| data);
+    // The decoder checks the length before the characters, look for a bad one first
+    let invalid = data
+        .char_indices()
+        .find(|(_, c)| !(c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '-' | '_' | '=')));
+    let problem = match invalid {
+        Some((offset, c)) => format!("invalid character {:?} at offset {}", c, offset),
+        None => match decode_base64(data) {
+            Ok(_) => return Ok(()),
+            Err(
+                DecodeError::InvalidByte(offset, byte)
+                | DecodeError::InvalidLastSymbol(offset, byte),
+            ) => format!(
+                "invalid character {:?} at offset {}",
+                char::from(byte),
+                offset
+            ),
+            Err(DecodeError::InvalidLength) => format!(
+                "its length of {} is not valid, the data may be cut off",
+                data.len()
+            ),
+            Err(DecodeError::InvalidPadding) => "its padding is invalid".to_string(),
+        },
+    };
+    Err(anyhow::anyhow!(
+        "The {} image data is not valid base64: {}",
+        image.mime_type,
+        problem
+    ))
+}
+
+/// Check the images of messages and of the tool results in them with [`validate_image_data`]
+pub fn validate_message_images(messages: &[Message]) -> Result<()> {
+    for message in messages {
+        for content in &message.content {
+            match content {
+                MessageContent::Image(image) => validate_image_data(image)?,
+                MessageContent::ToolResponse(response) => {
+                    for content in response.tool_result.iter().flatten() {
+                        if let Content::Image(image) = content {
+                            validate_image_data(image)?;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Handle response from OpenAI compatible endpoints
 /// Error codes: https://platform.openai.com/docs/guides/error-codes
 /// Context window exceeded: https://community.openai.com/t/help-needed-tackling-context-length-limits-in-openai-models/617543
 pub async fn handle_response_openai_compat(response: Response) -> Result<Value, ProviderError> {
+    handle_timed_response_openai_compat(response, None).await
+}
+
+/// Same as [`handle_response_openai_compat`], logging the latency the caller measured
+pub async fn handle_timed_response_openai_compat(
+    response: Response,
+    latency: Option<Duration>,
+) -> Result<Value, ProviderError> {
     let status = response.status();
+    let url = response.url().to_string();
+    log_provider_response(status, latency);
     // Try to parse the response body as JSON (if applicable)
-    let payload = match response.json::<Value>().await {
-        Ok(json) => json,
-        Err(e) => return Err(ProviderError::RequestFailed(e.to_string())),
-    };
+    let payload = response.json::<Value>().await;
+
+    // A wrong key, host or model is reported with how to fix it
+    if let Some(error) = diagnose(SetupFailure::Response {
+        url: &url,
+        status,
+        body: payload.as_ref().unwrap_or(&Value::Null),
+    }) {
+        return Err(error.into());
+    }
+    let payload = payload.map_err(|e| ProviderError::RequestFailed(e.to_string()))?;
 
     match status {
         StatusCode::OK => Ok(payload),
-        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
-            Err(ProviderError::Authentication(format!("Authentication failed. Please ensure your API keys are valid and have the required permissions. \
-                Status: {}. Response: {:?}", status, payload)))
-        }
         StatusCode::BAD_REQUEST | StatusCode::NOT_FOUND => {
             tracing::debug!(
-                "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, payload)
+                "{}",
+                format!(
+                    "Provider request failed with status: {}. Payload: {:?}",
+                    status, payload
+                )
             );
             if let Ok(err_resp) = from_value::<OpenAIErrorResponse>(payload) {
                 let err = err_resp.error;
                 if err.is_context_length_exceeded() {
-                    return Err(ProviderError::ContextLengthExceeded(err.message.unwrap_or("Unknown error".to_string())));
+                    return Err(ProviderError::ContextLengthExceeded(
+                        err.message.unwrap_or("Unknown error".to_string()),
+                    ));
                 }
-                return Err(ProviderError::RequestFailed(format!("{} (status {})", err, status.as_u16())));
+                return Err(ProviderError::Status {
+                    status: status.as_u16(),
+                    message: err.to_string(),
+                });
             }
-            Err(ProviderError::RequestFailed(format!("Unknown error (status {})", status)))
+            Err(ProviderError::Status {
+                status: status.as_u16(),
+                message: "Unknown error".to_string(),
+            })
         }
         StatusCode::TOO_MANY_REQUESTS => {
             Err(ProviderError::RateLimitExceeded(format!("{:?}", payload)))
@@ -82,9 +394,19 @@ pub async fn handle_response_openai_compat(response: Response) -> Result<Value,
         }
         _ => {
             tracing::debug!(
-                "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, payload)
+                "{}",
+                format!(
+                    "Provider request failed with status: {}. Payload: {:?}",
+                    status, payload
+                )
             );
-            Err(ProviderError::RequestFailed(format!("Request failed with status: {}", status)))
+            Err(ProviderError::Status {
+                status: status.as_u16(),
+                message: status
+                    .canonical_reason()
+                    .unwrap_or("Unknown error")
+                    .to_string(),
+            })
         }
     }
 }
@@ -142,6 +464,7 @@ pub async fn handle_response_google_compat(response: Response) -> Result<Value,
     let status = response.status();
     let payload: Option<Value> = response.json().await.ok();
     let final_status = get_google_final_status(status, payload.as_ref());
+    log_provider_response(final_status, None);
 
     match final_status {
         StatusCode::OK =>  payload.ok_or_else( || ProviderError::RequestFailed("Response body is not valid JSON".to_string()) ),
@@ -185,6 +508,53 @@ pub fn sanitize_function_name(name: &str) -> String {
     re.replace_all(name, "_").to_string()
 }
 
+/// Resolve a tool name returned by the model back to a registered tool.
+///
+/// An exact match always wins. Otherwise the names are compared after sanitizing, since
+/// that is the form providers send to the model. Returns None if no tool matches or if
+/// several tools sanitize to the same name, as the intended tool cannot be known.
+pub fn resolve_tool<'a>(tools: &'a [Tool], called_name: &str) -> Option<&'a Tool> {
+    if let Some(tool) = tools.iter().find(|tool| tool.name == called_name) {
+        return Some(tool);
+    }
+
+    let sanitized = sanitize_function_name(called_name);
+    let mut matches = tools
+        .iter()
+        .filter(|tool| sanitize_function_name(&tool.name) == sanitized);
+
+    match (matches.next(), matches.next()) {
+        (Some(tool), None) => Some(tool),
+        _ => None,
+    }
+}
+
+/// The id of the tool call at `index` of a response, made up when the provider left it out
+///
+/// Without an id the result of the call can't be matched to it, so a missing or empty id is
+/// replaced with one derived from the response id, or taken from `ids` if that is missing
+/// too. Identical responses in different turns then still get different ids.
+pub fn tool_call_id(
+    response: &Value,
+    tool_call: &Value,
+    index: usize,
+    ids: &dyn ToolCallIdSource,
+) -> String {
+    if let Some(id) = tool_call["id"].as_str().filter(|id| !id.is_empty()) {
+        return id.to_string();
+    }
+
+    let id = match response["id"].as_str().filter(|id| !id.is_empty()) {
+        Some(response_id) => format!("{}_call_{}", response_id, index),
+        None => ids.next_id(),
+    };
+    tracing::warn!(
+        "Provider returned a tool call without an id, using '{}' instead",
+        id
+    );
+    id
+}
+
 pub fn is_valid_function_name(name: &str) -> bool {
     let re = Regex::new(r"^[a-zA-Z0-9_-]+$").unwrap();
     re.is_match(name)
@@ -319,16 +689,153 @@ pub fn unescape_json_values(value: &Value) -> Value {
     }
 }
 
+/// Pretty-print tool arguments for display, without changing the underlying data.
+///
+/// The value is rendered as indented JSON, except that `\n` escapes inside strings are
+/// shown as real line breaks. Other escapes are kept, so a literal backslash followed by
+/// `n` still reads as `\\n` and can be told apart from a newline.
+pub fn format_tool_arguments(value: &Value) -> String {
+    let pretty = serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string());
+    let mut formatted = String::with_capacity(pretty.len());
+    let mut chars = pretty.chars();
+
+    // Backslashes in serialized JSON only ever start an escape sequence inside a string
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            formatted.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => formatted.push('\n'),
+            Some(escaped) => {
+                formatted.push('\\');
+                formatted.push(escaped);
+            }
+            None => formatted.push('\\'),
+        }
+    }
+
+    formatted
+}
+
+/// Provider error codes that mean the same request can succeed later
+/// https://docs.anthropic.com/en/api/errors
+/// https://platform.openai.com/docs/guides/error-codes
+const RETRYABLE_ERROR_CODES: &[&str] = &[
+    "overloaded_error",
+    "rate_limit_error",
+    "api_error",
+    "server_error",
+    "rate_limit_exceeded",
+];
+
+/// Provider error codes that fail again on retry, even with a status that is usually retried,
+/// such as a 429 for an exhausted quota
+const PERMANENT_ERROR_CODES: &[&str] = &[
+    "insufficient_quota",
+    "invalid_request_error",
+    "authentication_error",
+    "permission_error",
+    "not_found_error",
+];
+
+/// Whether a failed provider request is worth retrying after a backoff
+///
+/// The error code in the body takes precedence over the status, as providers return codes
+/// like `overloaded_error` with statuses that are otherwise final. Without a known code,
+/// rate limits, timeouts and server errors are retried, including Anthropic's 529 overloaded.
+pub fn is_retryable_error(status: StatusCode, body: &Value) -> bool {
+    let error = &body["error"];
+    for code in [&error["type"], &error["code"]]
+        .into_iter()
+        .filter_map(Value::as_str)
+    {
+        if PERMANENT_ERROR_CODES.contains(&code) {
+            return false;
+        }
+        if RETRYABLE_ERROR_CODES.contains(&code) {
+            return true;
+        }
+    }
+    matches!(status.as_u16(), 408 | 429 | 500 | 502 | 503 | 504 | 529)
+}
+
+/// Log the status of a provider response, and its latency when the provider measured it
+pub fn log_provider_response(status: StatusCode, latency: Option<Duration>) {
+    match latency {
+        Some(latency) => tracing::debug!(
+            status = status.as_u16(),
+            latency_ms = latency.as_millis() as u64,
+            "provider response"
+        ),
+        None => tracing::debug!(status = status.as_u16(), "provider response"),
+    }
+}
+
+/// Replace base64 image data in a provider payload or response, so it stays out of logs
+///
+/// Covers data URLs (OpenAI) and `data` fields next to a mime type (Anthropic, Google
+/// and goose messages).
+pub fn redact_images(value: &Value) -> Value {
+    fn redacted(data: &str) -> Value {
+        Value::String(format!("[image redacted, {} bytes]", data.len()))
+    }
+
+    match value {
+        Value::Object(map) => {
+            let is_image = ["mime_type", "mimeType", "media_type"]
+                .iter()
+                .any(|key| map.contains_key(*key));
+            Value::Object(
+                map.iter()
+                    .map(|(key, value)| match value {
+                        Value::String(data) if is_image && key == "data" => {
+                            (key.clone(), redacted(data))
+                        }
+                        _ => (key.clone(), redact_images(value)),
+                    })
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.iter().map(redact_images).collect()),
+        Value::String(url) if url.starts_with("data:") && url.contains(";base64,") => redacted(url),
+        other => other.clone(),
+    }
+}
+
+/// The number of messages in a request payload, including a system message if the
+/// provider sends it as one
+fn payload_message_count(payload: &Value) -> usize {
+    ["messages", "contents"]
+        .iter()
+        .find_map(|key| payload.get(*key)?.as_array().map(Vec::len))
+        .unwrap_or_default()
+}
+
+/// Log a completed provider call: a usage event, and the full payloads at debug level
+/// with images redacted. Both carry the request id sent with the call, to match them
+/// with gateway logs.
 pub fn emit_debug_trace(
     model_config: &ModelConfig,
+    request_id: &str,
     payload: &Value,
     response: &Value,
     usage: &Usage,
 ) {
+    tracing::info!(
+        request_id,
+        model = %model_config.model_name,
+        message_count = payload_message_count(payload),
+        input_tokens = ?usage.input_tokens.unwrap_or_default(),
+        output_tokens = ?usage.output_tokens.unwrap_or_default(),
+        total_tokens = ?usage.total_tokens.unwrap_or_default(),
+        "provider usage"
+    );
     tracing::debug!(
+        request_id,
         model_config = %serde_json::to_string_pretty(model_config).unwrap_or_default(),
-        input = %serde_json::to_string_pretty(payload).unwrap_or_default(),
-        output = %serde_json::to_string_pretty(response).unwrap_or_default(),
+        input = %serde_json::to_string_pretty(&redact_images(payload)).unwrap_or_default(),
+        output = %serde_json::to_string_pretty(&redact_images(response)).unwrap_or_default(),
         input_tokens = ?usage.input_tokens.unwrap_or_default(),
         output_tokens = ?usage.output_tokens.unwrap_or_default(),
         total_tokens = ?usage.total_tokens.unwrap_or_default(),
@@ -338,8 +845,282 @@ pub fn emit_debug_trace(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::providers::tool_call_ids::SequentialToolCallIds;
     use serde_json::json;
 
+    #[test]
+    fn test_validate_image_data() {
+        let valid = ImageContent {
+            data: "iVBORw0KGgo=".to_string(),
+            mime_type: "image/png".to_string(),
+            annotations: None,
+        };
+        assert!(validate_image_data(&valid).is_ok());
+
+        let corrupted = ImageContent {
+            data: "iVBORw0K!Ggo=".to_string(),
+            ..valid.clone()
+        };
+        let error = validate_image_data(&corrupted).unwrap_err().to_string();
+        assert!(
+            error.contains("invalid character '!' at offset 8"),
+            "{}",
+            error
+        );
+
+        let truncated = ImageContent {
+            data: "iVBORw0KG".to_string(),
+            ..valid.clone()
+        };
+        assert!(validate_image_data(&truncated).is_err());
+
+        let messages = vec![
+            Message::user().with_image(valid.data.clone(), "image/png"),
+            Message::user().with_tool_response(
+                "call_1",
+                Ok(vec![Content::image(corrupted.data.clone(), "image/png")]),
+            ),
+        ];
+        assert!(validate_message_images(&messages[..1]).is_ok());
+        assert!(validate_message_images(&messages).is_err());
+    }
+
+    #[test]
+    fn test_decode_base64_variants() {
+        let bytes = vec![0xfb, 0xff, 0xbf, 0xfb, 0xff];
+        let standard = base64::prelude::BASE64_STANDARD.encode(&bytes);
+        assert_eq!(standard, "+/+/+/8=");
+        let url_safe = base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(&bytes);
+        assert!(base64::prelude::BASE64_STANDARD.decode(&url_safe).is_err());
+
+        for data in [standard.as_str(), url_safe.as_str(), "+/+/+/8", "-_-_-_8="] {
+            assert_eq!(decode_base64(data).unwrap(), bytes, "{}", data);
+            assert_eq!(normalize_base64(data), standard);
+        }
+
+        // Images go to providers as standard base64 with padding
+        let image = ImageContent {
+            data: url_safe,
+            mime_type: "image/png".to_string(),
+            annotations: None,
+        };
+        assert!(validate_image_data(&image).is_ok());
+        assert_eq!(
+            convert_image(&image, &ImageFormat::OpenAi)["image_url"]["url"],
+            "data:image/png;base64,+/+/+/8="
+        );
+        assert_eq!(
+            convert_image(&image, &ImageFormat::Anthropic)["source"]["data"],
+            "+/+/+/8="
+        );
+    }
+
+    #[test]
+    fn test_normalize_data_url_image() {
+        let png = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=";
+        let image = |data: String, mime_type: &str| ImageContent {
+            data,
+            mime_type: mime_type.to_string(),
+            annotations: None,
+        };
+
+        // An image from an OpenAI message goes to Anthropic as raw base64
+        let from_openai = image(format!("data:image/png;base64,{}", png), "");
+        assert!(validate_image_data(&from_openai).is_ok());
+        let normalized = normalize_image(from_openai.clone());
+        assert_eq!(normalized.data, png);
+        assert_eq!(normalized.mime_type, "image/png");
+        assert_eq!(
+            convert_image(&from_openai, &ImageFormat::Anthropic)["source"],
+            json!({"type": "base64", "media_type": "image/png", "data": png})
+        );
+        assert_eq!(
+            convert_image(&from_openai, &ImageFormat::OpenAi)["image_url"]["url"],
+            format!("data:image/png;base64,{}", png)
+        );
+
+        // A valid mime type is kept, a missing one is taken from the data
+        let labeled = image(format!("data:image/jpeg;base64,{}", png), "image/png");
+        assert_eq!(normalize_image(labeled).mime_type, "image/png");
+        let unlabeled = normalize_image(image(png.to_string(), "application/octet-stream"));
+        assert_eq!(unlabeled.mime_type, "image/png");
+        assert_eq!(unlabeled.data, png);
+
+        // Data that is neither a data URL nor an image is left for validation to report
+        let text = image("data:text/plain,hello".to_string(), "");
+        assert_eq!(normalize_image(text.clone()), text);
+        assert!(validate_image_data(&text).is_err());
+    }
+
+    #[test]
+    fn test_tool_content_separator() {
+        let blocks = vec!["line one".to_string(), "say \"two\"".to_string()];
+        assert_eq!(
+            ToolContentSeparator::Space.join(&blocks),
+            "line one say \"two\""
+        );
+        assert_eq!(
+            ToolContentSeparator::Newline.join(&blocks),
+            "line one\nsay \"two\""
+        );
+        assert_eq!(
+            ToolContentSeparator::JsonArray.join(&blocks),
+            r#"["line one","say \"two\""]"#
+        );
+        assert_eq!(
+            ToolContentSeparator::default(),
+            ToolContentSeparator::Newline
+        );
+        assert_eq!(
+            "json_array".parse::<ToolContentSeparator>().unwrap(),
+            ToolContentSeparator::JsonArray
+        );
+        assert!("tab".parse::<ToolContentSeparator>().is_err());
+    }
+
+    #[test]
+    fn test_tool_image_policy() {
+        assert_eq!(ToolImagePolicy::default(), ToolImagePolicy::Hoist);
+        assert_eq!(
+            "Inline".parse::<ToolImagePolicy>().unwrap(),
+            ToolImagePolicy::Inline
+        );
+        assert!("embed".parse::<ToolImagePolicy>().is_err());
+
+        assert_eq!(
+            ToolImagePolicy::Inline.for_capabilities(true),
+            ToolImagePolicy::Inline
+        );
+        assert_eq!(
+            ToolImagePolicy::Inline.for_capabilities(false),
+            ToolImagePolicy::Hoist
+        );
+        assert_eq!(
+            ToolImagePolicy::Drop.for_capabilities(false),
+            ToolImagePolicy::Drop
+        );
+    }
+
+    #[test]
+    fn test_validate_temperature() {
+        let config =
+            |temperature| ModelConfig::new("gpt-4o".to_string()).with_temperature(temperature);
+        assert_eq!(
+            validate_temperature(&config(None), 0.0..=2.0).unwrap(),
+            None
+        );
+        assert_eq!(
+            validate_temperature(&config(Some(1.5)), 0.0..=2.0).unwrap(),
+            Some(1.5)
+        );
+        assert_eq!(
+            validate_temperature(&config(Some(2.5)), 0.0..=2.0).unwrap(),
+            Some(2.0)
+        );
+        assert_eq!(
+            validate_temperature(&config(Some(-0.5)), 0.0..=1.0).unwrap(),
+            Some(0.0)
+        );
+        assert_eq!(
+            validate_temperature(&config(Some(f32::NAN)), 0.0..=1.0).unwrap(),
+            None
+        );
+
+        let reject =
+            |temperature| config(temperature).with_temperature_policy(TemperaturePolicy::Reject);
+        assert_eq!(
+            validate_temperature(&reject(Some(2.0)), 0.0..=2.0).unwrap(),
+            Some(2.0)
+        );
+        let error = validate_temperature(&reject(Some(2.5)), 0.0..=2.0).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Temperature 2.5 of gpt-4o is outside of the accepted range 0 to 2"
+        );
+        assert!(validate_temperature(&reject(Some(f32::NAN)), 0.0..=2.0).is_err());
+
+        assert_eq!(
+            "Reject".parse::<TemperaturePolicy>().unwrap(),
+            TemperaturePolicy::Reject
+        );
+        assert!("ignore".parse::<TemperaturePolicy>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_openai_compat_error_keeps_status() {
+        use crate::agents::AgentError;
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(502).set_body_json(json!({"error": "upstream"})))
+            .mount(&server)
+            .await;
+        let response = reqwest::Client::new()
+            .post(server.uri())
+            .send()
+            .await
+            .unwrap();
+
+        let error = handle_response_openai_compat(response).await.unwrap_err();
+        assert_eq!(error.status(), Some(502));
+        assert_eq!(
+            AgentError::provider_failure("openai", &error),
+            AgentError::ProviderFailure {
+                provider: "openai".to_string(),
+                status: Some(502),
+                message: "Bad Gateway".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_tool_call_id() {
+        let ids = SequentialToolCallIds::new();
+        let response = json!({"id": "chatcmpl-1"});
+        assert_eq!(
+            tool_call_id(&response, &json!({"id": "call_a"}), 0, &ids),
+            "call_a"
+        );
+        assert_eq!(
+            tool_call_id(&response, &json!({"id": ""}), 1, &ids),
+            "chatcmpl-1_call_1"
+        );
+
+        // The same response without ids, such as one repeated in a later turn, gets new ids
+        let response = json!({"choices": [{"message": {"tool_calls": [{"function": {}}]}}]});
+        assert_eq!(tool_call_id(&response, &json!({}), 0, &ids), "call_0");
+        assert_eq!(tool_call_id(&response, &json!({}), 0, &ids), "call_1");
+    }
+
+    #[test]
+    fn test_redact_images() {
+        let data = "iVBORw0KGgo".repeat(100);
+        let payload = json!({
+            "messages": [
+                {"role": "user", "content": [
+                    {"type": "text", "text": "What is this?"},
+                    {"type": "image_url", "image_url": {"url": format!("data:image/png;base64,{}", data)}},
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": data}},
+                ]},
+                {"role": "user", "parts": [{"inline_data": {"mime_type": "image/png", "data": data}}]},
+            ],
+            "data": "not an image",
+        });
+
+        let redacted = redact_images(&payload);
+        let text = redacted.to_string();
+        assert!(!text.contains(&data));
+        assert_eq!(text.matches("[image redacted, ").count(), 3);
+        assert_eq!(
+            redacted["messages"][0]["content"][0]["text"],
+            "What is this?"
+        );
+        assert_eq!(redacted["data"], "not an image");
+        assert_eq!(payload_message_count(&redacted), 2);
+    }
+
     #[test]
     fn test_detect_image_path() {
         // Create a temporary PNG file with valid PNG magic numbers
@@ -437,6 +1218,31 @@ mod tests {
         assert_eq!(sanitize_function_name("hello@world"), "hello_world");
     }
 
+    fn tool(name: &str) -> Tool {
+        Tool::new(name, "", json!({"type": "object"}), None)
+    }
+
+    #[test]
+    fn test_resolve_tool_exact_match() {
+        let tools = vec![tool("developer__shell"), tool("developer.shell")];
+        let resolved = resolve_tool(&tools, "developer.shell").unwrap();
+        assert_eq!(resolved.name, "developer.shell");
+    }
+
+    #[test]
+    fn test_resolve_tool_sanitized_match() {
+        let tools = vec![tool("github.search issues"), tool("developer__shell")];
+        let resolved = resolve_tool(&tools, "github_search_issues").unwrap();
+        assert_eq!(resolved.name, "github.search issues");
+        assert!(resolve_tool(&tools, "missing").is_none());
+    }
+
+    #[test]
+    fn test_resolve_tool_ambiguous_collision() {
+        let tools = vec![tool("files.read"), tool("files read")];
+        assert!(resolve_tool(&tools, "files_read").is_none());
+    }
+
     #[test]
     fn test_is_valid_function_name() {
         assert!(is_valid_function_name("hello-world"));
@@ -495,6 +1301,23 @@ mod tests {
         assert_eq!(unescaped_value, json!({"text": "Hello World"}));
     }
 
+    #[test]
+    fn format_tool_arguments_with_newlines_and_backslashes() {
+        let value = json!({
+            "command": "echo one\ntwo",
+            "path": "C:\\temp\\new",
+            "nested": {"items": [1, "a\\nb"]}
+        });
+        let formatted = format_tool_arguments(&value);
+        assert_eq!(
+            formatted,
+            "{\n  \"command\": \"echo one\ntwo\",\n  \"nested\": {\n    \"items\": [\n      1,\n      \"a\\\\nb\"\n    ]\n  },\n  \"path\": \"C:\\\\temp\\\\new\"\n}"
+        );
+
+        // The value itself is left untouched
+        assert_eq!(value["command"], json!("echo one\ntwo"));
+    }
+
     #[test]
     fn test_is_google_model() {
         // Define the test cases as a vector of tuples
@@ -560,4 +1383,43 @@ mod tests {
             assert_eq!(result, expected_status);
         }
     }
+
+    #[test]
+    fn test_is_retryable_error() {
+        // Anthropic's overloaded error, with its own status and in an otherwise final one
+        let overloaded = json!({
+            "type": "error",
+            "error": {"type": "overloaded_error", "message": "Overloaded"}
+        });
+        assert!(is_retryable_error(
+            StatusCode::from_u16(529).unwrap(),
+            &overloaded
+        ));
+        assert!(is_retryable_error(StatusCode::BAD_REQUEST, &overloaded));
+        assert!(is_retryable_error(
+            StatusCode::from_u16(529).unwrap(),
+            &Value::Null
+        ));
+
+        let invalid = json!({
+            "type": "error",
+            "error": {"type": "invalid_request_error", "message": "max_tokens: Field required"}
+        });
+        assert!(!is_retryable_error(StatusCode::BAD_REQUEST, &invalid));
+        assert!(!is_retryable_error(StatusCode::BAD_REQUEST, &Value::Null));
+
+        // OpenAI's rate limits can be retried, an exhausted quota can't
+        let rate_limited = json!({"error": {"type": "requests", "code": "rate_limit_exceeded"}});
+        assert!(is_retryable_error(
+            StatusCode::TOO_MANY_REQUESTS,
+            &rate_limited
+        ));
+        let quota = json!({"error": {"type": "insufficient_quota", "code": "insufficient_quota"}});
+        assert!(!is_retryable_error(StatusCode::TOO_MANY_REQUESTS, &quota));
+        assert!(is_retryable_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &json!({"error": {"type": "server_error", "code": null}})
+        ));
+        assert!(!is_retryable_error(StatusCode::UNAUTHORIZED, &Value::Null));
+    }
 }
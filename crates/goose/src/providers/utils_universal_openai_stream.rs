@@ -1,6 +1,12 @@
+use futures::stream::{BoxStream, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::{BTreeMap, HashMap};
 
+use super::base::{TokenLogprob, ToolCallProgress, ToolCallProgressSender};
+use super::errors::ProviderError;
+use super::partial_json::PartialJson;
+
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct OAIUsage {
     pub prompt_tokens: Option<usize>,
@@ -44,11 +50,20 @@ pub struct OAIStreamDelta {
     pub tool_calls: Vec<OAIToolCall>,
 }
 
+/// The log probabilities of the tokens of a choice, or of a chunk of it when streaming
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct OAILogprobs {
+    #[serde(default, deserialize_with = "null_to_empty_vec")]
+    pub content: Vec<TokenLogprob>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct OAIStreamChoice {
     pub delta: OAIStreamDelta,
     pub finish_reason: Option<String>,
     pub index: usize,
+    #[serde(default)]
+    pub logprobs: Option<OAILogprobs>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -80,6 +95,8 @@ pub struct OAIChatChoice {
     #[serde(default)]
     pub content_filter_results: HashMap<String, OAIContentFilterResult>,
     pub message: OAIChatMessage,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<OAILogprobs>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -102,6 +119,8 @@ pub struct CollectedChoice {
     pub tool_calls_order: Vec<usize>,
     pub finish_reason: Option<String>,
     pub content_filter_results: HashMap<String, OAIContentFilterResult>,
+    /// The log probabilities of the content so far, if the chunks carry them
+    pub logprobs: Option<Vec<TokenLogprob>>,
 }
 
 pub struct OAIStreamCollector {
@@ -113,6 +132,9 @@ pub struct OAIStreamCollector {
     pub prompt_filter_results: Option<Vec<OAIPromptFilterResult>>,
     pub usage: Option<OAIUsage>,
     pub choices: BTreeMap<usize, CollectedChoice>,
+    /// Tool call arguments assembled so far, by choice and tool call index
+    partial_arguments: HashMap<(usize, usize), PartialJson>,
+    progress: Option<ToolCallProgressSender>,
 }
 
 impl Default for OAIStreamCollector {
@@ -132,9 +154,17 @@ impl OAIStreamCollector {
             prompt_filter_results: None,
             usage: None,
             choices: BTreeMap::new(),
+            partial_arguments: HashMap::new(),
+            progress: None,
         }
     }
 
+    /// Report the arguments of tool calls each time another of their keys completes
+    pub fn with_progress(mut self, progress: ToolCallProgressSender) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
     pub fn add_chunk(&mut self, chunk: &OAIStreamChunk) {
         for ch in chunk.choices.iter() {
             // Always ensure choice exists, even if all fields are absent!
@@ -146,6 +176,7 @@ impl OAIStreamCollector {
                 tool_calls_order: Vec::new(),
                 finish_reason: None,
                 content_filter_results: HashMap::new(),
+                logprobs: None,
             });
 
             if let Some(role) = &ch.delta.role {
@@ -156,6 +187,13 @@ impl OAIStreamCollector {
                 choice.content.push_str(c);
             }
 
+            if let Some(logprobs) = &ch.logprobs {
+                choice
+                    .logprobs
+                    .get_or_insert_with(Vec::new)
+                    .extend(logprobs.content.iter().cloned());
+            }
+
             for tc in &ch.delta.tool_calls {
                 let ix = tc.index;
                 let entry = choice.tool_calls.entry(ix).or_insert_with(|| tc.clone());
@@ -190,6 +228,22 @@ impl OAIStreamCollector {
                     if !(tc.function.arguments == "\"}" && entry.function.arguments.ends_with('\"'))
                     {
                         entry.function.arguments.push_str(&tc.function.arguments);
+                        if let Some(progress) = &self.progress {
+                            let partial = self.partial_arguments.entry((idx, ix)).or_default();
+                            let known = partial.partial().map_or(0, |arguments| arguments.len());
+                            partial.push(&tc.function.arguments);
+                            match (partial.partial(), &entry.function.name) {
+                                (Some(arguments), Some(name)) if arguments.len() > known => {
+                                    // A send only fails once nobody is listening, which is fine
+                                    let _ = progress.send(ToolCallProgress {
+                                        id: entry.id.clone().unwrap_or_default(),
+                                        name: name.clone(),
+                                        arguments: Value::Object(arguments),
+                                    });
+                                }
+                                _ => {}
+                            }
+                        }
                     }
                 }
                 if !choice.tool_calls_order.contains(&ix) {
@@ -227,6 +281,7 @@ impl OAIStreamCollector {
                     tool_calls,
                     padding: String::new(),
                 },
+                logprobs: ch.logprobs.map(|content| OAILogprobs { content }),
             });
         }
         OAIChatResponse {
@@ -241,6 +296,189 @@ impl OAIStreamCollector {
         }
     }
 }
+/// Default limit of the size of a single server-sent event, past which the stream fails
+pub const DEFAULT_MAX_SSE_EVENT_BYTES: usize = 8 * 1024 * 1024;
+
+/// An event of a server-sent event stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    /// The `event:` type, none for plain messages
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Incremental decoder of a server-sent event stream, fed with reads of any size
+///
+/// Lines may end with `\n` or `\r\n` and may be split across reads. Comment lines such as
+/// `: keep-alive` and fields other than `event` and `data` are ignored. Each `data` line is its
+/// own event, as chat completion APIs put a whole JSON chunk on one line and not all of them
+/// end events with a blank line; a blank line only resets the event type.
+#[derive(Debug)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    max_event_bytes: usize,
+}
+
+impl Default for SseDecoder {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_SSE_EVENT_BYTES)
+    }
+}
+
+impl SseDecoder {
+    pub fn new(max_event_bytes: usize) -> Self {
+        Self {
+            buffer: Vec::new(),
+            event: None,
+            max_event_bytes,
+        }
+    }
+
+    /// Decode the events completed by `bytes`, keeping a partial line for the next read
+    pub fn push(&mut self, bytes: &[u8]) -> Result<Vec<SseEvent>, ProviderError> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        let mut start = 0;
+        while let Some(len) = self.buffer[start..].iter().position(|byte| *byte == b'\n') {
+            let line = self.buffer[start..start + len].to_vec();
+            start += len + 1;
+            events.extend(self.decode_line(&line));
+        }
+        self.buffer.drain(..start);
+        if self.buffer.len() > self.max_event_bytes {
+            return Err(ProviderError::RequestFailed(format!(
+                "Stream event is larger than {} bytes",
+                self.max_event_bytes
+            )));
+        }
+        Ok(events)
+    }
+
+    /// The event of a last line that the stream ended without terminating
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        let line = std::mem::take(&mut self.buffer);
+        self.decode_line(&line)
+    }
+
+    fn decode_line(&mut self, line: &[u8]) -> Option<SseEvent> {
+        let line = String::from_utf8_lossy(line.strip_suffix(b"\r").unwrap_or(line));
+        if line.trim().is_empty() {
+            self.event = None;
+            return None;
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = line.split_once(':').unwrap_or((&line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field.trim() {
+            "event" => {
+                self.event = Some(value.trim().to_string()).filter(|event| !event.is_empty());
+                None
+            }
+            "data" => Some(SseEvent {
+                event: self.event.clone(),
+                data: value.trim().to_string(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// What an event of an OpenAI compatible stream holds
+enum StreamEvent {
+    Chunk(Box<OAIStreamChunk>),
+    Done,
+    Skip,
+}
+
+fn parse_stream_event(event: &SseEvent) -> Result<StreamEvent, ProviderError> {
+    match event.event.as_deref() {
+        None | Some("message") => {}
+        Some("error") => return Err(ProviderError::ServerError(event.data.clone())),
+        Some(other) => {
+            tracing::debug!("Skipping stream event of type {}", other);
+            return Ok(StreamEvent::Skip);
+        }
+    }
+    if event.data == "[DONE]" {
+        return Ok(StreamEvent::Done);
+    }
+    match serde_json::from_str::<OAIStreamChunk>(&event.data) {
+        Ok(chunk) => Ok(StreamEvent::Chunk(Box::new(chunk))),
+        Err(e) => {
+            tracing::debug!("Skipping unparseable stream chunk: {}", e);
+            Ok(StreamEvent::Skip)
+        }
+    }
+}
+
+/// Parse the chunks of an OpenAI compatible event stream from a response body
+///
+/// The returned stream owns the body and reads it only as chunks are polled, stopping at
+/// `[DONE]`. Dropping it, as happens when the agent's reply is cancelled, drops the response
+/// and aborts the HTTP read instead of downloading the rest of the tokens. Chunks that can't
+/// be decoded are skipped, an `error` event ends the stream with its data.
+pub fn oai_stream_chunks<S, B, E>(
+    body: S,
+) -> BoxStream<'static, Result<OAIStreamChunk, ProviderError>>
+where
+    S: Stream<Item = Result<B, E>> + Send + 'static,
+    B: AsRef<[u8]> + Send,
+    E: std::fmt::Display + Send,
+{
+    Box::pin(async_stream::try_stream! {
+        let mut body = Box::pin(body);
+        let mut decoder = SseDecoder::default();
+        let mut done = false;
+        'body: while let Some(bytes) = body.next().await {
+            let bytes = bytes.map_err(|e| ProviderError::RequestFailed(e.to_string()))?;
+            for event in decoder.push(bytes.as_ref())? {
+                match parse_stream_event(&event)? {
+                    StreamEvent::Chunk(chunk) => yield *chunk,
+                    StreamEvent::Done => {
+                        done = true;
+                        break 'body;
+                    }
+                    StreamEvent::Skip => {}
+                }
+            }
+        }
+        if !done {
+            if let Some(event) = decoder.finish() {
+                if let StreamEvent::Chunk(chunk) = parse_stream_event(&event)? {
+                    yield *chunk;
+                }
+            }
+        }
+    })
+}
+
+/// Read an event stream to its end and assemble the chunks into a chat completion response
+pub async fn collect_oai_stream(
+    chunks: BoxStream<'_, Result<OAIStreamChunk, ProviderError>>,
+) -> Result<Value, ProviderError> {
+    collect_oai_stream_with_progress(chunks, None).await
+}
+
+/// Like [`collect_oai_stream`], reporting tool call arguments to `progress` as they arrive.
+/// The response still holds the full argument text, which is what the tool call runs with.
+pub async fn collect_oai_stream_with_progress(
+    mut chunks: BoxStream<'_, Result<OAIStreamChunk, ProviderError>>,
+    progress: Option<ToolCallProgressSender>,
+) -> Result<Value, ProviderError> {
+    let mut collector = OAIStreamCollector::new();
+    if let Some(progress) = progress {
+        collector = collector.with_progress(progress);
+    }
+    while let Some(chunk) = chunks.next().await {
+        collector.add_chunk(&chunk?);
+    }
+    serde_json::to_value(collector.build_response())
+        .map_err(|e| ProviderError::RequestFailed(e.to_string()))
+}
+
 fn null_to_empty_string<'de, D>(deserializer: D) -> Result<String, D::Error>
 where
     D: serde::Deserializer<'de>,
@@ -248,10 +486,124 @@ where
     use serde::Deserialize;
     Ok(Option::<String>::deserialize(deserializer)?.unwrap_or_default())
 }
+
+fn null_to_empty_vec<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Ok(Option::<Vec<T>>::deserialize(deserializer)?.unwrap_or_default())
+}
 #[cfg(test)]
 mod tests {
     use super::*;
+    use futures::stream;
     use serde_json::from_str;
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Sets its flag when dropped, to tell whether a response body was released
+    struct DropFlag(Arc<AtomicBool>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// A response body that counts the reads made from it and never ends after `parts`
+    fn tracked_body(
+        parts: Vec<&'static str>,
+        reads: Arc<AtomicUsize>,
+        dropped: Arc<AtomicBool>,
+    ) -> impl Stream<Item = Result<&'static [u8], Infallible>> + Send + 'static {
+        let flag = DropFlag(dropped);
+        stream::iter(parts)
+            .chain(stream::pending())
+            .map(move |part| {
+                let _ = &flag;
+                reads.fetch_add(1, Ordering::SeqCst);
+                Ok(part.as_bytes())
+            })
+    }
+
+    fn text_chunk(text: &str) -> String {
+        format!(
+            "data: {}\n",
+            serde_json::json!({"choices": [{"index": 0, "delta": {"content": text}}]})
+        )
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_stream_stops_reading_body() {
+        let reads = Arc::new(AtomicUsize::new(0));
+        let dropped = Arc::new(AtomicBool::new(false));
+        let body = tracked_body(
+            vec![text_chunk("Hello").leak(), text_chunk(" world").leak()],
+            reads.clone(),
+            dropped.clone(),
+        );
+
+        let mut chunks = oai_stream_chunks(body);
+        let chunk = chunks.next().await.unwrap().unwrap();
+        assert_eq!(chunk.choices[0].delta.content.as_deref(), Some("Hello"));
+
+        // Cancelling before [DONE] releases the body without reading the rest of it
+        drop(chunks);
+        assert!(dropped.load(Ordering::SeqCst));
+        assert_eq!(reads.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stream_ends_at_done() {
+        let reads = Arc::new(AtomicUsize::new(0));
+        let dropped = Arc::new(AtomicBool::new(false));
+        let first: &'static str = text_chunk("Hel").leak();
+        // Events split across reads are joined before parsing
+        let (start, end) = first.split_at(20);
+        let body = tracked_body(
+            vec![start, end, "data: [DONE]\n", "data: {\"choices\": []}\n"],
+            reads.clone(),
+            dropped.clone(),
+        );
+
+        let response = collect_oai_stream(oai_stream_chunks(body)).await.unwrap();
+        assert_eq!(response["choices"][0]["message"]["content"], "Hel");
+        assert_eq!(reads.load(Ordering::SeqCst), 3);
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_stream_accumulates_logprobs() {
+        let chunk = |text: &str, logprob: f64| {
+            format!(
+                "data: {}\n",
+                serde_json::json!({"choices": [{
+                    "index": 0,
+                    "delta": {"content": text},
+                    "logprobs": {"content": [{"token": text, "logprob": logprob, "bytes": [], "top_logprobs": []}]}
+                }]})
+            )
+        };
+        let body = stream::iter(vec![
+            Ok::<_, Infallible>(chunk("Hi", -0.5)),
+            Ok(chunk(" there", -1.5)),
+            Ok(text_chunk("!")),
+            Ok("data: [DONE]\n".to_string()),
+        ]);
+
+        let response = collect_oai_stream(oai_stream_chunks(body)).await.unwrap();
+        let logprobs = crate::providers::formats::openai::response_to_logprobs(&response).unwrap();
+        let tokens: Vec<&str> = logprobs.tokens.iter().map(|t| t.token.as_str()).collect();
+        assert_eq!(tokens, vec!["Hi", " there"]);
+        assert_eq!(logprobs.tokens[1].logprob, -1.5);
+
+        // Streams without logprobs don't add them to the response
+        let body = stream::iter(vec![Ok::<_, Infallible>(text_chunk("Hi"))]);
+        let response = collect_oai_stream(oai_stream_chunks(body)).await.unwrap();
+        assert!(response["choices"][0].get("logprobs").is_none());
+    }
 
     const TOOL_STREAM: &str = r#"
 data: {"choices":[],"created":0,"id":"","prompt_filter_results":[{"content_filter_results":{"hate":{"filtered":false,"severity":"safe"},"self_harm":{"filtered":false,"severity":"safe"},"sexual":{"filtered":false,"severity":"safe"},"violence":{"filtered":false,"severity":"safe"}},"prompt_index":0}]}
@@ -300,6 +652,35 @@ data: [DONE]
         assert_eq!(choice.finish_reason, "tool_calls");
     }
 
+    #[tokio::test]
+    async fn test_tool_call_progress() {
+        let body = stream::iter(
+            TOOL_STREAM
+                .lines()
+                .map(|line| Ok::<_, Infallible>(format!("{}\n", line).into_bytes())),
+        );
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let response = collect_oai_stream_with_progress(oai_stream_chunks(body), Some(sender))
+            .await
+            .unwrap();
+
+        // The location is only reported once its string closes, and only once
+        let progress = receiver.recv().await.unwrap();
+        assert_eq!(progress.id, "call_7m75SYp4UrPhxhtdZdawEK5J");
+        assert_eq!(progress.name, "get_weather");
+        assert_eq!(
+            progress.arguments,
+            serde_json::json!({"location": "San Francisco"})
+        );
+        assert!(receiver.recv().await.is_none());
+
+        // Progress leaves the assembled arguments untouched
+        assert_eq!(
+            response["choices"][0]["message"]["tool_calls"][0]["function"]["arguments"],
+            r#"{"location":"San Francisco"}"#
+        );
+    }
+
     const TEXT_STREAM: &str = r#"
 data: {"choices":[],"created":0,"id":"","prompt_filter_results":[{"content_filter_results":{"hate":{"filtered":false,"severity":"safe"},"self_harm":{"filtered":false,"severity":"safe"},"sexual":{"filtered":false,"severity":"safe"},"violence":{"filtered":false,"severity":"safe"}},"prompt_index":0}]}
 data: {"choices":[{"index":0,"content_filter_offsets":{"check_offset":3458,"start_offset":3458,"end_offset":3494},"content_filter_results":{"hate":{"filtered":false,"severity":"safe"},"self_harm":{"filtered":false,"severity":"safe"},"sexual":{"filtered":false,"severity":"safe"},"violence":{"filtered":false,"severity":"safe"}},"delta":{"content":"","role":"assistant"}}],"created":1747592466,"id":"chatcmpl-BYcvCkaKJjQIM7e2j6vg08RIcY8qp","model":"gpt-4o-2024-11-20","system_fingerprint":"fp_ee1d74bde0"}
@@ -400,4 +781,110 @@ data: [DONE]
         );
         assert_eq!(choice.finish_reason, "tool_calls");
     }
+
+    #[test]
+    fn test_sse_decoder() {
+        let mut decoder = SseDecoder::default();
+        let events = decoder
+            .push(b": keep-alive\r\n\r\ndata:{\"a\":1}\r\n\r\nevent: ping\ndata: {}\n\nda")
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: None,
+                    data: "{\"a\":1}".to_string()
+                },
+                SseEvent {
+                    event: Some("ping".to_string()),
+                    data: "{}".to_string()
+                },
+            ]
+        );
+
+        // The blank line reset the type, and the split line is completed by the next read
+        let events = decoder
+            .push(b"ta: [DONE]\nid: 7\nretry: 100\ndata: last")
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, None);
+        assert_eq!(events[0].data, "[DONE]");
+        assert_eq!(decoder.finish().unwrap().data, "last");
+        assert_eq!(decoder.finish(), None);
+
+        let mut decoder = SseDecoder::new(16);
+        assert!(decoder.push(b"data: 0123456789").is_ok());
+        assert!(decoder.push(b"0123456789").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stream_skips_comments_and_bad_events() {
+        let body = stream::iter(vec![
+            Ok::<_, Infallible>(": OPENROUTER PROCESSING\n\n".to_string()),
+            Ok(text_chunk("Hello").replace('\n', "\r\n\r\n")),
+            Ok("event: ping\ndata: {\"type\": \"ping\"}\n\n".to_string()),
+            Ok("data: {\"choices\": [{\"index\": 0, \"del\n\n".to_string()),
+            Ok("data: \u{fffd}\u{0}\n\n".to_string()),
+            Ok(text_chunk(" world")),
+            Ok("data: [DONE]\n\n".to_string()),
+        ]);
+        let response = collect_oai_stream(oai_stream_chunks(body)).await.unwrap();
+        assert_eq!(response["choices"][0]["message"]["content"], "Hello world");
+
+        let body = stream::iter(vec![
+            Ok::<_, Infallible>(text_chunk("Hello")),
+            Ok("event: error\ndata: {\"message\": \"overloaded\"}\n\n".to_string()),
+        ]);
+        let error = collect_oai_stream(oai_stream_chunks(body))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ProviderError::ServerError(data) if data.contains("overloaded")));
+    }
+
+    /// The recorded streams as a gateway may send them, with keep-alives, blank lines between
+    /// events, CRLF line ends and a chunk that isn't valid JSON
+    fn gateway_stream(recorded: &str) -> Vec<u8> {
+        let mut body = String::from(": keep-alive\r\n\r\n");
+        for (i, line) in recorded.lines().filter(|line| !line.is_empty()).enumerate() {
+            body.push_str(line);
+            body.push_str("\r\n\r\n");
+            if i == 1 {
+                body.push_str(": keep-alive\r\n\r\ndata: {\"choices\": [\r\n\r\n");
+            }
+        }
+        body.into_bytes()
+    }
+
+    async fn collect_parts(parts: Vec<Vec<u8>>) -> Value {
+        let body = stream::iter(parts.into_iter().map(Ok::<_, Infallible>));
+        collect_oai_stream(oai_stream_chunks(body)).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_stream_random_splits() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(142);
+        for recorded in [TOOL_STREAM, TEXT_STREAM, CLAUDE_STREAM] {
+            let expected = collect_parts(vec![recorded.as_bytes().to_vec()]).await;
+            let body = gateway_stream(recorded);
+            assert_eq!(collect_parts(vec![body.clone()]).await, expected);
+
+            // Split at any byte, including inside line ends and multibyte characters
+            for _ in 0..100 {
+                let mut splits: Vec<usize> = (0..rng.gen_range(1..20))
+                    .map(|_| rng.gen_range(0..=body.len()))
+                    .collect();
+                splits.sort_unstable();
+                let mut parts = Vec::new();
+                let mut start = 0;
+                for split in splits.into_iter().chain([body.len()]) {
+                    parts.push(body[start..split].to_vec());
+                    start = split;
+                }
+                assert_eq!(collect_parts(parts).await, expected);
+            }
+        }
+    }
 }
@@ -0,0 +1,603 @@
+use std::collections::HashSet;
+
+use mcp_core::handler::ToolError;
+use mcp_core::role::Role;
+use thiserror::Error;
+
+use super::errors::ProviderError;
+use crate::message::{Message, MessageContent};
+
+/// A conversation that a provider would reject, with the index of the offending message
+#[derive(Debug, Error, PartialEq)]
+pub enum ConversationError {
+    #[error(
+        "Message {index} must be from the user, as it is the first message of the conversation"
+    )]
+    FirstMessageNotUser { index: usize },
+
+    #[error("Message {index} has the same role ({role:?}) as the message before it, but roles must alternate")]
+    RolesNotAlternating { index: usize, role: Role },
+
+    #[error("Message {index} contains a tool response for {id}, which was not requested in the previous message")]
+    OrphanedToolResponse { index: usize, id: String },
+
+    #[error("Message {index} requests tool call {id}, but the next message does not contain its response")]
+    MissingToolResponse { index: usize, id: String },
+
+    #[error("Message {index} is a partial assistant turn to continue, which this provider does not support")]
+    UnsupportedPrefill { index: usize },
+}
+
+impl From<ConversationError> for ProviderError {
+    fn from(error: ConversationError) -> Self {
+        ProviderError::RequestFailed(format!("Invalid conversation: {}", error))
+    }
+}
+
+/// The shape a provider requires a conversation to have. Every provider requires tool
+/// responses to directly follow the message with the matching tool requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConversationRules {
+    /// The conversation must start with a user message
+    pub first_message_user: bool,
+    /// User and assistant messages must alternate
+    pub alternating_roles: bool,
+    /// The provider requires alternating roles starting with a user turn, and fixes up role
+    /// order while converting, see
+    /// [`enforce_alternation`](super::formats::alternation::enforce_alternation), so the two
+    /// rules above are left unset and role order is not checked
+    pub strict_alternation: bool,
+    /// The conversation must not end with an assistant prefill, see [`assistant_prefill`],
+    /// since the API would answer it as a finished turn instead of continuing it
+    pub reject_prefill: bool,
+}
+
+impl ConversationRules {
+    /// The rules for a provider, by the name in its metadata
+    pub fn for_provider(provider: &str) -> Self {
+        match provider {
+            // Interjections and images split from tool results repeat the user role, so these
+            // providers merge repeated roles while converting. Bedrock documents its rules at
+            // https://docs.aws.amazon.com/bedrock/latest/APIReference/API_runtime_Converse.html,
+            // and many local models have chat templates that reject repeated roles.
+            "anthropic" | "aws_bedrock" | "gcp_vertex_ai" | "ollama" => Self {
+                strict_alternation: true,
+                ..Self::default()
+            },
+            "openai" => Self {
+                reject_prefill: true,
+                ..Self::default()
+            },
+            _ => Self::default(),
+        }
+    }
+}
+
+/// Check a conversation against the rules of a provider before sending it
+///
+/// Messages that only carry content that is never sent to providers, such as tool
+/// confirmation requests, are skipped. Error indices refer to positions in `messages`.
+pub fn validate_conversation(
+    messages: &[Message],
+    provider: &str,
+) -> Result<(), ConversationError> {
+    validate_conversation_with_rules(messages, ConversationRules::for_provider(provider))
+}
+
+pub fn validate_conversation_with_rules(
+    messages: &[Message],
+    rules: ConversationRules,
+) -> Result<(), ConversationError> {
+    let sent: Vec<(usize, &Message)> = messages
+        .iter()
+        .enumerate()
+        .filter(|(_, message)| message.content.iter().any(is_sent_to_provider))
+        .collect();
+
+    let check_roles = !rules.strict_alternation;
+    if let Some((index, message)) = sent.first() {
+        if check_roles && rules.first_message_user && message.role != Role::User {
+            return Err(ConversationError::FirstMessageNotUser { index: *index });
+        }
+    }
+
+    let mut requested: HashSet<&str> = HashSet::new();
+    for (position, (index, message)) in sent.iter().enumerate() {
+        if position > 0
+            && check_roles
+            && rules.alternating_roles
+            && sent[position - 1].1.role == message.role
+        {
+            return Err(ConversationError::RolesNotAlternating {
+                index: *index,
+                role: message.role.clone(),
+            });
+        }
+
+        let responses = tool_response_ids(message);
+        for id in &responses {
+            if !requested.contains(id) {
+                return Err(ConversationError::OrphanedToolResponse {
+                    index: *index,
+                    id: id.to_string(),
+                });
+            }
+        }
+
+        // Requests from the previous message must all be answered here
+        let previous_index = position.checked_sub(1).map(|previous| sent[previous].0);
+        if let Some(id) = requested.iter().find(|id| !responses.contains(*id)) {
+            return Err(ConversationError::MissingToolResponse {
+                index: previous_index.unwrap_or_default(),
+                id: id.to_string(),
+            });
+        }
+
+        requested = tool_request_ids(message);
+    }
+
+    // Requests in the last message have nothing left to answer them
+    if let (Some(id), Some((index, _))) = (requested.iter().next(), sent.last()) {
+        return Err(ConversationError::MissingToolResponse {
+            index: *index,
+            id: id.to_string(),
+        });
+    }
+
+    if rules.reject_prefill && assistant_prefill(messages).is_some() {
+        if let Some((index, _)) = sent.last() {
+            return Err(ConversationError::UnsupportedPrefill { index: *index });
+        }
+    }
+
+    Ok(())
+}
+
+/// The result given to a tool call that was never answered
+const MISSING_RESULT: &str = "The tool call was interrupted before it returned a result";
+
+/// A change [`repair_conversation`] made, with the index in the original messages of the
+/// message it concerns
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConversationRepair {
+    /// The tool call got an error result, as the message after it did not answer it
+    AddedToolResponse { index: usize, id: String },
+    /// The tool response was removed, as the message before it did not request it
+    DroppedToolResponse { index: usize, id: String },
+    /// The message was merged into the one before it, which has the same role
+    MergedMessages { index: usize, role: Role },
+}
+
+/// Fix what [`validate_conversation`] would reject and can be fixed without guessing
+///
+/// Tool calls left unanswered get an error result, and tool responses without their call
+/// are dropped. Messages with the same role as the one before them are merged when the
+/// provider requires roles to alternate. Returns the repaired messages with what was changed.
+pub fn repair_conversation(
+    messages: &[Message],
+    provider: &str,
+) -> (Vec<Message>, Vec<ConversationRepair>) {
+    repair_conversation_with_rules(messages, ConversationRules::for_provider(provider))
+}
+
+pub fn repair_conversation_with_rules(
+    messages: &[Message],
+    rules: ConversationRules,
+) -> (Vec<Message>, Vec<ConversationRepair>) {
+    let mut repaired: Vec<Message> = Vec::with_capacity(messages.len());
+    let mut repairs = Vec::new();
+    // The tool calls of the last message sent, in order, and its index in `messages`
+    let mut requested: Vec<String> = Vec::new();
+    let mut requested_index = 0;
+    let mut last_sent: Option<usize> = None;
+
+    for (index, message) in messages.iter().enumerate() {
+        if !message.content.iter().any(is_sent_to_provider) {
+            repaired.push(message.clone());
+            continue;
+        }
+        let mut message = message.clone();
+
+        message
+            .content
+            .retain(|content| match content.as_tool_response() {
+                Some(response) if !requested.contains(&response.id) => {
+                    repairs.push(ConversationRepair::DroppedToolResponse {
+                        index,
+                        id: response.id.clone(),
+                    });
+                    false
+                }
+                _ => true,
+            });
+
+        let answered = tool_response_ids(&message);
+        let missing = missing_responses(&requested, &answered, requested_index, &mut repairs);
+        if !missing.is_empty() {
+            if message.role == Role::User {
+                message.content.splice(0..0, missing);
+            } else {
+                let mut answer = Message::user();
+                answer.content = missing;
+                repaired.push(answer);
+                last_sent = Some(repaired.len() - 1);
+            }
+        }
+        if message.content.is_empty() {
+            continue;
+        }
+
+        requested_index = index;
+        match last_sent {
+            Some(previous)
+                if (rules.alternating_roles || rules.strict_alternation)
+                    && repaired[previous].role == message.role =>
+            {
+                repairs.push(ConversationRepair::MergedMessages {
+                    index,
+                    role: message.role.clone(),
+                });
+                repaired[previous].content.extend(message.content);
+                requested = ordered_request_ids(&repaired[previous]);
+            }
+            _ => {
+                requested = ordered_request_ids(&message);
+                repaired.push(message);
+                last_sent = Some(repaired.len() - 1);
+            }
+        }
+    }
+
+    // Nothing comes after the last tool calls to answer them
+    let missing = missing_responses(&requested, &HashSet::new(), requested_index, &mut repairs);
+    if !missing.is_empty() {
+        let mut answer = Message::user();
+        answer.content = missing;
+        repaired.push(answer);
+    }
+
+    (repaired, repairs)
+}
+
+/// Error results for the tool calls in `requested` that are not in `answered`, recording each
+/// in `repairs`
+fn missing_responses(
+    requested: &[String],
+    answered: &HashSet<&str>,
+    index: usize,
+    repairs: &mut Vec<ConversationRepair>,
+) -> Vec<MessageContent> {
+    requested
+        .iter()
+        .filter(|id| !answered.contains(id.as_str()))
+        .map(|id| {
+            repairs.push(ConversationRepair::AddedToolResponse {
+                index,
+                id: id.clone(),
+            });
+            MessageContent::tool_response(
+                id.clone(),
+                Err(ToolError::ExecutionError(MISSING_RESULT.to_string())),
+            )
+        })
+        .collect()
+}
+
+/// The text of a final assistant message that only holds text, a partial turn for the model
+/// to continue rather than answer
+///
+/// A final assistant message with tool requests is a finished turn waiting for its tool
+/// responses, so it is not a prefill.
+pub fn assistant_prefill(messages: &[Message]) -> Option<String> {
+    let message = messages
+        .iter()
+        .rev()
+        .find(|message| message.content.iter().any(is_sent_to_provider))?;
+    if message.role != Role::Assistant {
+        return None;
+    }
+    let mut prefill = String::new();
+    for content in message.content.iter().filter(|c| is_sent_to_provider(c)) {
+        prefill.push_str(content.as_text()?);
+    }
+    Some(prefill)
+}
+
+fn is_sent_to_provider(content: &MessageContent) -> bool {
+    !matches!(
+        content,
+        MessageContent::ToolConfirmationRequest(_)
+            | MessageContent::ContextLengthExceeded(_)
+            | MessageContent::SummarizationRequested(_)
+            | MessageContent::Citations(_)
+    )
+}
+
+fn tool_request_ids(message: &Message) -> HashSet<&str> {
+    message
+        .content
+        .iter()
+        .filter_map(|content| match content {
+            MessageContent::ToolRequest(request) => Some(request.id.as_str()),
+            MessageContent::FrontendToolRequest(request) => Some(request.id.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn ordered_request_ids(message: &Message) -> Vec<String> {
+    message
+        .content
+        .iter()
+        .filter_map(|content| match content {
+            MessageContent::ToolRequest(request) => Some(request.id.clone()),
+            MessageContent::FrontendToolRequest(request) => Some(request.id.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn tool_response_ids(message: &Message) -> HashSet<&str> {
+    message
+        .content
+        .iter()
+        .filter_map(|content| {
+            content
+                .as_tool_response()
+                .map(|response| response.id.as_str())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::tool::ToolCall;
+    use mcp_core::Content;
+    use serde_json::json;
+
+    /// Rules that leave role order to validation
+    const ALTERNATING: ConversationRules = ConversationRules {
+        first_message_user: true,
+        alternating_roles: true,
+        strict_alternation: false,
+        reject_prefill: false,
+    };
+
+    fn tool_request(id: &str) -> Message {
+        Message::assistant().with_tool_request(id, Ok(ToolCall::new("developer__shell", json!({}))))
+    }
+
+    fn tool_response(id: &str) -> Message {
+        Message::user().with_tool_response(id, Ok(vec![Content::text("ok")]))
+    }
+
+    #[test]
+    fn test_valid_conversation() {
+        let messages = vec![
+            Message::user().with_text("list the files"),
+            tool_request("1"),
+            tool_response("1"),
+            Message::assistant().with_text("done"),
+            Message::user().with_text("thanks"),
+        ];
+        assert_eq!(validate_conversation(&messages, "anthropic"), Ok(()));
+        assert_eq!(validate_conversation(&messages, "aws_bedrock"), Ok(()));
+    }
+
+    #[test]
+    fn test_orphaned_tool_response() {
+        let messages = vec![
+            Message::user().with_text("list the files"),
+            Message::assistant().with_text("sure"),
+            tool_response("1"),
+        ];
+        assert_eq!(
+            validate_conversation(&messages, "openai"),
+            Err(ConversationError::OrphanedToolResponse {
+                index: 2,
+                id: "1".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_missing_tool_response() {
+        let messages = vec![
+            Message::user().with_text("list the files"),
+            tool_request("1"),
+            Message::user().with_text("never mind"),
+        ];
+        assert_eq!(
+            validate_conversation(&messages, "openai"),
+            Err(ConversationError::MissingToolResponse {
+                index: 1,
+                id: "1".to_string()
+            })
+        );
+
+        // A trailing request has no message left to answer it
+        assert_eq!(
+            validate_conversation(&messages[..2], "openai"),
+            Err(ConversationError::MissingToolResponse {
+                index: 1,
+                id: "1".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_leading_assistant_message() {
+        let messages = vec![
+            Message::assistant().with_text("Hello, how can I help?"),
+            Message::user().with_text("list the files"),
+        ];
+        assert_eq!(
+            validate_conversation_with_rules(&messages, ALTERNATING),
+            Err(ConversationError::FirstMessageNotUser { index: 0 })
+        );
+        assert_eq!(validate_conversation(&messages, "openai"), Ok(()));
+        // Anthropic and Bedrock add a placeholder user turn instead
+        assert_eq!(validate_conversation(&messages, "anthropic"), Ok(()));
+        assert_eq!(validate_conversation(&messages, "aws_bedrock"), Ok(()));
+    }
+
+    #[test]
+    fn test_skipped_messages_keep_original_indices() {
+        let messages = vec![
+            Message::assistant().with_tool_confirmation_request(
+                "1",
+                "developer__shell".to_string(),
+                json!({}),
+                None,
+            ),
+            Message::assistant().with_text("hello"),
+        ];
+        assert_eq!(
+            validate_conversation_with_rules(&messages, ALTERNATING),
+            Err(ConversationError::FirstMessageNotUser { index: 1 })
+        );
+    }
+
+    #[test]
+    fn test_alternating_roles() {
+        let messages = vec![
+            Message::user().with_text("hello"),
+            Message::user().with_text("are you there?"),
+        ];
+        assert_eq!(
+            validate_conversation_with_rules(&messages, ALTERNATING),
+            Err(ConversationError::RolesNotAlternating {
+                index: 1,
+                role: Role::User
+            })
+        );
+        // Providers that merge repeated roles accept them
+        assert_eq!(validate_conversation(&messages, "anthropic"), Ok(()));
+        assert_eq!(validate_conversation(&messages, "aws_bedrock"), Ok(()));
+    }
+
+    #[test]
+    fn test_assistant_prefill() {
+        let mut messages = vec![
+            Message::user().with_text("Reply in JSON"),
+            Message::assistant().with_text("{").with_text("\"ok\":"),
+        ];
+        assert_eq!(assistant_prefill(&messages), Some("{\"ok\":".to_string()));
+        assert_eq!(
+            validate_conversation(&messages, "openai"),
+            Err(ConversationError::UnsupportedPrefill { index: 1 })
+        );
+        assert_eq!(validate_conversation(&messages, "anthropic"), Ok(()));
+
+        // Content that is never sent doesn't hide the prefill
+        messages.push(Message::assistant().with_context_length_exceeded("too long"));
+        assert_eq!(assistant_prefill(&messages), Some("{\"ok\":".to_string()));
+
+        // Finished turns are not prefills
+        assert_eq!(assistant_prefill(&[tool_request("1")]), None);
+        assert_eq!(assistant_prefill(&messages[..1]), None);
+        assert_eq!(
+            validate_conversation(&[tool_request("1"), tool_response("1")], "openai"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_repair_unanswered_tool_call() {
+        let messages = vec![
+            Message::user().with_text("list the files"),
+            tool_request("1")
+                .with_tool_request("2", Ok(ToolCall::new("developer__shell", json!({})))),
+            tool_response("2").with_text("never mind"),
+        ];
+        let (repaired, repairs) = repair_conversation(&messages, "openai");
+        assert_eq!(
+            repairs,
+            vec![ConversationRepair::AddedToolResponse {
+                index: 1,
+                id: "1".to_string()
+            }]
+        );
+        let answer = repaired[2].content[0].as_tool_response().unwrap();
+        assert_eq!(answer.id, "1");
+        assert_eq!(
+            answer.tool_result,
+            Err(ToolError::ExecutionError(MISSING_RESULT.to_string()))
+        );
+        assert_eq!(repaired[2].content.len(), 3);
+        assert_eq!(validate_conversation(&repaired, "openai"), Ok(()));
+
+        // An assistant message after the call gets the answer in a user message before it
+        let messages = vec![
+            Message::user().with_text("list the files"),
+            tool_request("1"),
+            Message::assistant().with_text("done"),
+        ];
+        let (repaired, repairs) = repair_conversation(&messages, "aws_bedrock");
+        assert_eq!(repairs.len(), 1);
+        assert_eq!(repaired.len(), 4);
+        assert_eq!(repaired[2].role, Role::User);
+        assert_eq!(validate_conversation(&repaired, "aws_bedrock"), Ok(()));
+
+        // A call in the last message gets its answer in a new user message
+        let (repaired, repairs) = repair_conversation(&messages[..2], "anthropic");
+        assert_eq!(
+            repairs,
+            vec![ConversationRepair::AddedToolResponse {
+                index: 1,
+                id: "1".to_string()
+            }]
+        );
+        assert_eq!(repaired.len(), 3);
+        assert_eq!(repaired[2].role, Role::User);
+        assert_eq!(repaired[2].content[0].as_tool_response().unwrap().id, "1");
+        assert_eq!(validate_conversation(&repaired, "anthropic"), Ok(()));
+    }
+
+    #[test]
+    fn test_repair_orphaned_tool_response() {
+        let messages = vec![
+            Message::user().with_text("list the files"),
+            Message::assistant().with_text("sure"),
+            tool_response("1"),
+            Message::assistant().with_text("anything else?"),
+        ];
+        let (repaired, repairs) = repair_conversation(&messages, "openai");
+        assert_eq!(
+            repairs,
+            vec![ConversationRepair::DroppedToolResponse {
+                index: 2,
+                id: "1".to_string()
+            }]
+        );
+        // The message held nothing else, so it is gone
+        assert_eq!(repaired.len(), 3);
+        assert_eq!(validate_conversation(&repaired, "openai"), Ok(()));
+    }
+
+    #[test]
+    fn test_repair_merges_repeated_roles() {
+        let messages = vec![
+            Message::user().with_text("hello"),
+            Message::user().with_text("are you there?"),
+            Message::assistant().with_text("yes"),
+        ];
+        let (repaired, repairs) = repair_conversation(&messages, "aws_bedrock");
+        assert_eq!(
+            repairs,
+            vec![ConversationRepair::MergedMessages {
+                index: 1,
+                role: Role::User
+            }]
+        );
+        assert_eq!(repaired.len(), 2);
+        assert_eq!(repaired[0].content.len(), 2);
+        assert_eq!(validate_conversation(&repaired, "aws_bedrock"), Ok(()));
+
+        // Providers that accept repeated roles get the messages unchanged
+        let (repaired, repairs) = repair_conversation(&messages, "openai");
+        assert_eq!(repaired, messages);
+        assert!(repairs.is_empty());
+    }
+}
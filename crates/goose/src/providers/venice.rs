@@ -1,6 +1,5 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use chrono::Utc;
 use reqwest::{Client, Response};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
@@ -8,6 +7,7 @@ use std::time::Duration;
 
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
+use super::headers::{client_builder, new_request_id, REQUEST_ID_HEADER};
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
 use mcp_core::{tool::Tool, Role, ToolCall, ToolResult};
@@ -103,9 +103,7 @@ impl VeniceProvider {
         // Ensure we only keep the bare model id internally
         model.model_name = strip_flags(&model.model_name).to_string();
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(600))
-            .build()?;
+        let client = client_builder().timeout(Duration::from_secs(600)).build()?;
 
         let instance = Self {
             client,
@@ -119,7 +117,12 @@ impl VeniceProvider {
         Ok(instance)
     }
 
-    async fn post(&self, path: &str, body: &str) -> Result<Response, ProviderError> {
+    async fn post(
+        &self,
+        request_id: &str,
+        path: &str,
+        body: &str,
+    ) -> Result<Response, ProviderError> {
         let base_url = url::Url::parse(&self.host)
             .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
         let url = base_url
@@ -135,12 +138,13 @@ impl VeniceProvider {
         };
 
         // Log the request details
-        tracing::debug!("Venice request URL: {}", url);
+        tracing::debug!("Venice request URL: {} (request id {})", url, request_id);
         tracing::debug!("Venice request body: {}", body);
 
         let response = method
             .header("Authorization", format!("Bearer {}", self.api_key))
             .header("Content-Type", "application/json")
+            .header(REQUEST_ID_HEADER, request_id)
             .body(body.to_string())
             .send()
             .await?;
@@ -475,7 +479,9 @@ impl Provider for VeniceProvider {
         tracing::debug!("Venice request payload: {}", payload.to_string());
 
         // Send request
-        let response = self.post(&self.base_path, &payload.to_string()).await?;
+        let response = self
+            .post(&new_request_id(), &self.base_path, &payload.to_string())
+            .await?;
 
         // Parse the response
         let response_text = response.text().await?;
@@ -559,8 +565,9 @@ impl Provider for VeniceProvider {
         Ok((
             Message {
                 role: Role::Assistant,
-                created: Utc::now().timestamp(),
+                created: crate::determinism::now().timestamp(),
                 content,
+                extra: Default::default(),
             },
             ProviderUsage::new(strip_flags(&self.model.model_name).to_string(), usage),
         ))
@@ -1,4 +1,5 @@
 use super::errors::ProviderError;
+use super::headers::{client_builder, new_request_id, REQUEST_ID_HEADER};
 use crate::message::Message;
 use crate::model::ModelConfig;
 use crate::providers::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
@@ -60,9 +61,7 @@ impl XaiProvider {
             .get_param("XAI_HOST")
             .unwrap_or_else(|_| XAI_API_HOST.to_string());
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(600))
-            .build()?;
+        let client = client_builder().timeout(Duration::from_secs(600)).build()?;
 
         Ok(Self {
             client,
@@ -72,7 +71,7 @@ impl XaiProvider {
         })
     }
 
-    async fn post(&self, payload: Value) -> anyhow::Result<Value, ProviderError> {
+    async fn post(&self, request_id: &str, payload: Value) -> anyhow::Result<Value, ProviderError> {
         // Ensure the host ends with a slash for proper URL joining
         let host = if self.host.ends_with('/') {
             self.host.clone()
@@ -92,6 +91,7 @@ impl XaiProvider {
             .client
             .post(url)
             .header("Authorization", format!("Bearer {}", self.api_key))
+            .header(REQUEST_ID_HEADER, request_id)
             .json(&payload)
             .send()
             .await?;
@@ -163,7 +163,9 @@ impl Provider for XaiProvider {
             &super::utils::ImageFormat::OpenAi,
         )?;
 
-        let response = self.post(payload.clone()).await?;
+        let request_id = new_request_id();
+
+        let response = self.post(&request_id, payload.clone()).await?;
 
         let message = response_to_message(response.clone())?;
         let usage = match get_usage(&response) {
@@ -175,7 +177,7 @@ impl Provider for XaiProvider {
             Err(e) => return Err(e),
         };
         let model = get_model(&response);
-        super::utils::emit_debug_trace(&self.model, &payload, &response, &usage);
+        super::utils::emit_debug_trace(&self.model, &request_id, &payload, &response, &usage);
         Ok((message, ProviderUsage::new(model, usage)))
     }
 }
@@ -0,0 +1,578 @@
+//! The agent over JSON-RPC 2.0, for driving goose from another process
+//!
+//! Messages are JSON objects, one per line, on any byte stream such as stdio or a unix socket.
+//! `send_message` answers as soon as the reply started, its events follow as `agent/*`
+//! notifications, one method per [`AgentEvent`] variant, and end with `agent/finish`, whose
+//! `reason` is `stop`, `cancelled` or `error`.
+//!
+//! - `start_session` with an optional `session_id` and `working_dir`, which resumes the saved
+//!   session of that id, answers with the `protocol_version`, `session_id` and the number of
+//!   `messages` it has
+//! - `send_message` with a `session_id` and `text`
+//! - `approve_tool` with the `request_id` of a tool confirmation request and a `decision` of
+//!   `allow_once`, `always_allow`, `deny_once` or `cancel`
+//! - `cancel` with a `session_id`, answers whether a reply was `cancelled`
+//! - `get_usage`, answers with the token usage of the agent per model
+//! - `list_sessions`, answers with the saved sessions, newest first
+//!
+//! Every notification has the `session_id` of the reply it belongs to in its params.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use futures::StreamExt;
+use mcp_core::handler::ToolError;
+use mcp_core::protocol::{
+    ErrorData, JsonRpcError, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse,
+    INTERNAL_ERROR, INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND, PARSE_ERROR,
+};
+use mcp_core::Role;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, oneshot, Mutex};
+
+use crate::agents::{Agent, AgentEvent, SessionConfig};
+use crate::message::{Message, MessageContent};
+use crate::permission::permission_confirmation::PrincipalType;
+use crate::permission::{Permission, PermissionConfirmation};
+use crate::session::{self, info::SortOrder};
+
+/// The version of the methods and notifications, which changes when they change incompatibly
+pub const PROTOCOL_VERSION: &str = "1.0";
+
+/// Serve the agent on a byte stream until the other side closes it
+///
+/// Replies that are still running when the stream closes are cancelled.
+pub async fn serve<R, W>(agent: Arc<Agent>, reader: R, writer: W) -> anyhow::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel(256);
+    let writing = tokio::spawn(write_messages(rx, writer));
+    let server = Arc::new(RpcServer {
+        agent,
+        sessions: Mutex::new(HashMap::new()),
+        outgoing: tx,
+    });
+
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request: JsonRpcRequest = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                server
+                    .send(error_response(None, PARSE_ERROR, e.to_string()))
+                    .await;
+                continue;
+            }
+        };
+        // Notifications from the client need no answer, and none of the methods are one
+        let Some(id) = request.id else {
+            continue;
+        };
+        let response = match server.clone().handle(&request.method, request.params).await {
+            Ok(result) => JsonRpcMessage::Response(JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: Some(id),
+                result: Some(result),
+                error: None,
+            }),
+            Err(error) => JsonRpcMessage::Error(JsonRpcError {
+                jsonrpc: "2.0".to_string(),
+                id: Some(id),
+                error,
+            }),
+        };
+        server.send(response).await;
+    }
+
+    for session in server.sessions.lock().await.values_mut() {
+        if let Some(cancel) = session.cancel.take() {
+            cancel.send(()).ok();
+        }
+    }
+    drop(server);
+    writing.await??;
+    Ok(())
+}
+
+async fn write_messages<W>(
+    mut rx: mpsc::Receiver<JsonRpcMessage>,
+    mut writer: W,
+) -> anyhow::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    while let Some(message) = rx.recv().await {
+        let mut line = serde_json::to_string(&message)?;
+        line.push('\n');
+        writer.write_all(line.as_bytes()).await?;
+        writer.flush().await?;
+    }
+    Ok(())
+}
+
+struct RpcSession {
+    path: PathBuf,
+    working_dir: PathBuf,
+    messages: Vec<Message>,
+    /// Cancels the running reply, if there is one
+    cancel: Option<oneshot::Sender<()>>,
+}
+
+struct RpcServer {
+    agent: Arc<Agent>,
+    sessions: Mutex<HashMap<String, RpcSession>>,
+    outgoing: mpsc::Sender<JsonRpcMessage>,
+}
+
+#[derive(Deserialize)]
+struct StartSessionParams {
+    session_id: Option<String>,
+    working_dir: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+struct SendMessageParams {
+    session_id: String,
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct ApproveToolParams {
+    request_id: String,
+    decision: ApprovalDecision,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ApprovalDecision {
+    AllowOnce,
+    AlwaysAllow,
+    DenyOnce,
+    Cancel,
+}
+
+#[derive(Deserialize)]
+struct CancelParams {
+    session_id: String,
+}
+
+impl RpcServer {
+    async fn send(&self, message: JsonRpcMessage) {
+        // The writer only stops once the stream is gone, and then nobody is listening
+        self.outgoing.send(message).await.ok();
+    }
+
+    async fn handle(
+        self: Arc<Self>,
+        method: &str,
+        params: Option<Value>,
+    ) -> Result<Value, ErrorData> {
+        match method {
+            "start_session" => self.start_session(parse_params(params)?).await,
+            "send_message" => self.send_message(parse_params(params)?).await,
+            "approve_tool" => {
+                let params: ApproveToolParams = parse_params(params)?;
+                let permission = match params.decision {
+                    ApprovalDecision::AllowOnce => Permission::AllowOnce,
+                    ApprovalDecision::AlwaysAllow => Permission::AlwaysAllow,
+                    ApprovalDecision::DenyOnce => Permission::DenyOnce,
+                    ApprovalDecision::Cancel => Permission::Cancel,
+                };
+                self.agent
+                    .handle_confirmation(
+                        params.request_id,
+                        PermissionConfirmation {
+                            principal_type: PrincipalType::Tool,
+                            permission,
+                        },
+                    )
+                    .await;
+                Ok(json!({}))
+            }
+            "cancel" => {
+                let params: CancelParams = parse_params(params)?;
+                let mut sessions = self.sessions.lock().await;
+                let session = sessions
+                    .get_mut(&params.session_id)
+                    .ok_or_else(|| unknown_session(&params.session_id))?;
+                let cancelled = session
+                    .cancel
+                    .take()
+                    .is_some_and(|cancel| cancel.send(()).is_ok());
+                Ok(json!({ "cancelled": cancelled }))
+            }
+            "get_usage" => Ok(serde_json::to_value(self.agent.usage().await)
+                .map_err(|e| error_data(INTERNAL_ERROR, e.to_string()))?),
+            "list_sessions" => session::get_session_info(SortOrder::Descending)
+                .and_then(|sessions| Ok(serde_json::to_value(sessions)?))
+                .map_err(|e| error_data(INTERNAL_ERROR, e.to_string())),
+            _ => Err(error_data(
+                METHOD_NOT_FOUND,
+                format!("Unknown method: {}", method),
+            )),
+        }
+    }
+
+    async fn start_session(&self, params: StartSessionParams) -> Result<Value, ErrorData> {
+        let session_id = params
+            .session_id
+            .unwrap_or_else(session::generate_session_id);
+        let path = session::get_path(session::Identifier::Name(session_id.clone()))
+            .map_err(|e| error_data(INVALID_PARAMS, e.to_string()))?;
+        let messages = if path.exists() {
+            session::read_messages(&path).map_err(|e| error_data(INTERNAL_ERROR, e.to_string()))?
+        } else {
+            Vec::new()
+        };
+        let working_dir = match params.working_dir {
+            Some(working_dir) => working_dir,
+            None => {
+                std::env::current_dir().map_err(|e| error_data(INTERNAL_ERROR, e.to_string()))?
+            }
+        };
+
+        let result = json!({
+            "protocol_version": PROTOCOL_VERSION,
+            "session_id": session_id,
+            "messages": messages.len(),
+        });
+        self.sessions.lock().await.insert(
+            session_id,
+            RpcSession {
+                path,
+                working_dir,
+                messages,
+                cancel: None,
+            },
+        );
+        Ok(result)
+    }
+
+    async fn send_message(self: Arc<Self>, params: SendMessageParams) -> Result<Value, ErrorData> {
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions
+            .get_mut(&params.session_id)
+            .ok_or_else(|| unknown_session(&params.session_id))?;
+        if session.cancel.is_some() {
+            return Err(error_data(
+                INVALID_REQUEST,
+                format!("Session {} is already replying", params.session_id),
+            ));
+        }
+
+        let (cancel, cancelled) = oneshot::channel();
+        session.cancel = Some(cancel);
+        session
+            .messages
+            .push(Message::user().with_text(params.text));
+        let config = SessionConfig {
+            id: session::Identifier::Name(params.session_id.clone()),
+            working_dir: session.working_dir.clone(),
+            schedule_id: None,
+            execution_mode: None,
+        };
+        let messages = session.messages.clone();
+        drop(sessions);
+
+        tokio::spawn(self.run_reply(params.session_id, config, messages, cancelled));
+        Ok(json!({}))
+    }
+
+    async fn run_reply(
+        self: Arc<Self>,
+        session_id: String,
+        config: SessionConfig,
+        mut messages: Vec<Message>,
+        mut cancelled: oneshot::Receiver<()>,
+    ) {
+        let finish = match self.agent.reply(&messages, Some(config)).await {
+            Ok(mut stream) => loop {
+                tokio::select! {
+                    _ = &mut cancelled => break json!({ "reason": "cancelled" }),
+                    event = stream.next() => match event {
+                        Some(Ok(event)) => {
+                            match &event {
+                                // Confirmation requests are answered through approve_tool and
+                                // are not part of the conversation
+                                AgentEvent::Message(message) if is_confirmation_request(message) => {}
+                                AgentEvent::Message(message) | AgentEvent::Interjection(message) => {
+                                    messages.push(message.clone())
+                                }
+                                _ => {}
+                            }
+                            self.send(event_notification(&session_id, &event)).await;
+                        }
+                        Some(Err(e)) => break json!({ "reason": "error", "error": e.to_string() }),
+                        None => break json!({ "reason": "stop" }),
+                    }
+                }
+            },
+            Err(e) => json!({ "reason": "error", "error": e.to_string() }),
+        };
+
+        if finish["reason"] != "stop" {
+            answer_interrupted_tool_requests(&mut messages);
+        }
+
+        let mut sessions = self.sessions.lock().await;
+        if let Some(session) = sessions.get_mut(&session_id) {
+            if let Err(e) = session::persist_messages(&session.path, &messages, None).await {
+                tracing::error!("Failed to store session history: {:?}", e);
+            }
+            session.messages = messages;
+            session.cancel = None;
+        }
+        drop(sessions);
+
+        let mut params = finish;
+        params["session_id"] = json!(session_id);
+        self.send(notification("agent/finish", params)).await;
+    }
+}
+
+fn is_confirmation_request(message: &Message) -> bool {
+    matches!(
+        message.content.first(),
+        Some(MessageContent::ToolConfirmationRequest(_))
+    )
+}
+
+/// Answer the tool requests of a reply that stopped before their results arrived, so the
+/// conversation can be continued
+fn answer_interrupted_tool_requests(messages: &mut Vec<Message>) {
+    let Some(last) = messages
+        .last()
+        .filter(|message| message.role == Role::Assistant)
+    else {
+        return;
+    };
+    let mut response = Message::user();
+    for content in &last.content {
+        if let MessageContent::ToolRequest(request) = content {
+            response.content.push(MessageContent::tool_response(
+                request.id.clone(),
+                Err(ToolError::ExecutionError(
+                    "The reply was stopped before the tool finished".to_string(),
+                )),
+            ));
+        }
+    }
+    if !response.content.is_empty() {
+        messages.push(response);
+    }
+}
+
+/// The notification of an agent event, with the session of the reply it belongs to
+pub fn event_notification(session_id: &str, event: &AgentEvent) -> JsonRpcMessage {
+    let (method, mut params) = match event {
+        AgentEvent::Message(message) => ("agent/message", json!({ "message": message })),
+        AgentEvent::McpNotification((request_id, message)) => (
+            "agent/mcp_notification",
+            json!({ "request_id": request_id, "message": message }),
+        ),
+        AgentEvent::ModelChange { model, mode } => (
+            "agent/model_change",
+            json!({ "model": model, "mode": mode }),
+        ),
+        AgentEvent::Interjection(message) => ("agent/interjection", json!({ "message": message })),
+        AgentEvent::TurnStats(stats) => ("agent/turn_stats", json!({ "stats": stats })),
+        AgentEvent::Thinking(text) => ("agent/thinking", json!({ "text": text })),
+        AgentEvent::ToolCall {
+            id,
+            name,
+            arguments,
+        } => (
+            "agent/tool_call",
+            json!({ "id": id, "name": name, "arguments": arguments }),
+        ),
+        AgentEvent::ToolCallProgress {
+            id,
+            name,
+            arguments,
+        } => (
+            "agent/tool_call_progress",
+            json!({ "id": id, "name": name, "arguments": arguments }),
+        ),
+        AgentEvent::ToolProgress {
+            id,
+            name,
+            message,
+            fraction,
+        } => (
+            "agent/tool_progress",
+            json!({ "id": id, "name": name, "message": message, "fraction": fraction }),
+        ),
+        AgentEvent::Usage(usage) => ("agent/usage", json!({ "usage": usage })),
+        AgentEvent::CumulativeUsage { turn, usage } => (
+            "agent/cumulative_usage",
+            json!({ "turn": turn, "usage": usage }),
+        ),
+        AgentEvent::ApprovalTimedOut {
+            request_id,
+            tool_name,
+            approved,
+        } => (
+            "agent/approval_timed_out",
+            json!({ "request_id": request_id, "tool_name": tool_name, "approved": approved }),
+        ),
+        AgentEvent::UserFacingToolOutput { tool_name, content } => (
+            "agent/user_facing_tool_output",
+            json!({ "tool_name": tool_name, "content": content }),
+        ),
+        AgentEvent::SoftStop => ("agent/soft_stop", json!({})),
+        AgentEvent::OversizedMessage(oversized) => (
+            "agent/oversized_message",
+            json!({
+                "tokens": oversized.tokens,
+                "limit": oversized.limit,
+                "replacement": oversized.replacement,
+                "summary": oversized.summary(),
+            }),
+        ),
+        AgentEvent::Compacted {
+            trigger,
+            tokens_before,
+            tokens_after,
+            steps,
+        } => (
+            "agent/compacted",
+            json!({
+                "trigger": trigger,
+                "tokens_before": tokens_before,
+                "tokens_after": tokens_after,
+                "steps": steps,
+            }),
+        ),
+    };
+    params["session_id"] = json!(session_id);
+    notification(method, params)
+}
+
+fn notification(method: &str, params: Value) -> JsonRpcMessage {
+    JsonRpcMessage::Notification(JsonRpcNotification {
+        jsonrpc: "2.0".to_string(),
+        method: method.to_string(),
+        params: Some(params),
+    })
+}
+
+fn parse_params<T: DeserializeOwned>(params: Option<Value>) -> Result<T, ErrorData> {
+    serde_json::from_value(params.unwrap_or_else(|| json!({})))
+        .map_err(|e| error_data(INVALID_PARAMS, e.to_string()))
+}
+
+fn error_data(code: i32, message: String) -> ErrorData {
+    ErrorData {
+        code,
+        message,
+        data: None,
+    }
+}
+
+fn error_response(id: Option<u64>, code: i32, message: String) -> JsonRpcMessage {
+    JsonRpcMessage::Error(JsonRpcError {
+        jsonrpc: "2.0".to_string(),
+        id,
+        error: error_data(code, message),
+    })
+}
+
+fn unknown_session(session_id: &str) -> ErrorData {
+    error_data(INVALID_PARAMS, format!("Unknown session: {}", session_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_notification() {
+        let event = AgentEvent::ToolProgress {
+            id: "call_1".to_string(),
+            name: "developer__shell".to_string(),
+            message: Some("Running for 5s".to_string()),
+            fraction: None,
+        };
+        let JsonRpcMessage::Notification(notification) = event_notification("s1", &event) else {
+            panic!("Expected a notification");
+        };
+        assert_eq!(notification.method, "agent/tool_progress");
+        assert_eq!(
+            notification.params.unwrap(),
+            json!({
+                "session_id": "s1",
+                "id": "call_1",
+                "name": "developer__shell",
+                "message": "Running for 5s",
+                "fraction": null,
+            })
+        );
+    }
+
+    #[test]
+    fn test_answer_interrupted_tool_requests() {
+        let mut messages = vec![
+            Message::user().with_text("run it"),
+            Message::assistant().with_tool_request(
+                "call_1",
+                Ok(mcp_core::tool::ToolCall::new("developer__shell", json!({}))),
+            ),
+        ];
+        answer_interrupted_tool_requests(&mut messages);
+        assert_eq!(messages.len(), 3);
+        let response = messages[2].content[0].as_tool_response().unwrap();
+        assert_eq!(response.id, "call_1");
+        assert!(response.tool_result.is_err());
+
+        // Nothing to answer after a complete reply
+        messages.push(Message::assistant().with_text("done"));
+        answer_interrupted_tool_requests(&mut messages);
+        assert_eq!(messages.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let (client, server) = tokio::io::duplex(4096);
+        let (server_reader, server_writer) = tokio::io::split(server);
+        let serving = tokio::spawn(serve(Arc::new(Agent::new()), server_reader, server_writer));
+
+        let (reader, mut writer) = tokio::io::split(client);
+        writer
+            .write_all(
+                b"not json\n\
+                {\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"fly\"}\n\
+                {\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"cancel\",\"params\":{\"session_id\":\"none\"}}\n\
+                {\"jsonrpc\":\"2.0\",\"id\":3,\"method\":\"send_message\"}\n",
+            )
+            .await
+            .unwrap();
+        drop(writer);
+
+        let mut lines = BufReader::new(reader).lines();
+        let mut codes = Vec::new();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            let message: Value = serde_json::from_str(&line).unwrap();
+            codes.push((message["id"].clone(), message["error"]["code"].clone()));
+        }
+        serving.await.unwrap().unwrap();
+
+        assert_eq!(
+            codes,
+            vec![
+                (Value::Null, json!(PARSE_ERROR)),
+                (json!(1), json!(METHOD_NOT_FOUND)),
+                (json!(2), json!(INVALID_PARAMS)),
+                (json!(3), json!(INVALID_PARAMS)),
+            ]
+        );
+    }
+}
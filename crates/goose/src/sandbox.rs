@@ -0,0 +1,567 @@
+//! Keeps the paths used by tools inside a workspace
+//!
+//! A [`Sandbox`] resolves every path a tool is about to touch. It follows `..` and symlinks
+//! the way the filesystem would, so a path that looks like it is in the workspace but points
+//! out of it is caught, and it reports why a path is refused as a [`SandboxViolation`].
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use thiserror::Error;
+
+/// Why a path was refused, worded so the model knows which rule it broke
+#[derive(Debug, Error)]
+pub enum SandboxViolation {
+    #[error(
+        "'{path}' is outside the workspace '{root}'; only paths inside the workspace can be used"
+    )]
+    OutsideWorkspace { path: PathBuf, root: PathBuf },
+    #[error(
+        "'{path}' is outside the workspace '{root}'; paths outside the workspace are read only"
+    )]
+    ReadOnlyOutsideWorkspace { path: PathBuf, root: PathBuf },
+    #[error("'{path}' resolves through a symlink to '{target}', which is outside the workspace '{root}'")]
+    SymlinkEscape {
+        path: PathBuf,
+        target: PathBuf,
+        root: PathBuf,
+    },
+    #[error("'{path}' goes through the symlink '{link}', and symlinks are not followed in this workspace")]
+    Symlink { path: PathBuf, link: PathBuf },
+    #[error("'{path}' is hidden, and hidden files and directories are denied in this workspace")]
+    Hidden { path: PathBuf },
+    #[error("'{path}' matches the deny pattern '{pattern}' of this workspace")]
+    Denied { path: PathBuf, pattern: String },
+    #[error("'{path}' does not match any of the allowed patterns of this workspace")]
+    NotAllowed { path: PathBuf },
+    #[error("'{path}' could not be resolved: {source}")]
+    Unresolvable {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// The workspace tools may use, with the patterns and rules that narrow it
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    /// The canonical workspace root, none for a sandbox without a workspace
+    root: Option<PathBuf>,
+    /// The root as it was given, which can differ from the canonical one through symlinks
+    given_root: Option<PathBuf>,
+    allow: Option<Gitignore>,
+    deny: Option<Gitignore>,
+    allow_outside_reads: bool,
+    deny_hidden: bool,
+    follow_symlinks: bool,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self::unrestricted()
+    }
+}
+
+impl Sandbox {
+    /// A sandbox for the workspace at `root`, which has to exist
+    pub fn new(root: impl AsRef<Path>) -> Result<Self> {
+        let root = root.as_ref();
+        let given_root = normalize(&absolute(root)?);
+        let canonical = fs::canonicalize(&given_root)
+            .with_context(|| format!("Failed to resolve the workspace root {}", root.display()))?;
+        Ok(Self {
+            root: Some(canonical),
+            given_root: Some(given_root),
+            ..Self::unrestricted()
+        })
+    }
+
+    /// A sandbox without a workspace, which still applies its deny patterns and hidden rule
+    pub fn unrestricted() -> Self {
+        Self {
+            root: None,
+            given_root: None,
+            allow: None,
+            deny: None,
+            allow_outside_reads: false,
+            deny_hidden: false,
+            follow_symlinks: true,
+        }
+    }
+
+    /// The sandbox configured by the `GOOSE_SANDBOX_*` environment variables
+    ///
+    /// Without `GOOSE_SANDBOX_ROOT` the sandbox has no workspace. Patterns are comma separated.
+    pub fn from_env() -> Result<Self> {
+        let sandbox = match env::var("GOOSE_SANDBOX_ROOT") {
+            Ok(root) if !root.trim().is_empty() => Self::new(root.trim())?,
+            _ => Self::unrestricted(),
+        };
+        Ok(sandbox
+            .with_allow(&env_list("GOOSE_SANDBOX_ALLOW"))?
+            .with_deny(&env_list("GOOSE_SANDBOX_DENY"))?
+            .with_allow_outside_reads(env_flag("GOOSE_SANDBOX_ALLOW_OUTSIDE_READS", false))
+            .with_deny_hidden(env_flag("GOOSE_SANDBOX_DENY_HIDDEN", false))
+            .with_follow_symlinks(env_flag("GOOSE_SANDBOX_FOLLOW_SYMLINKS", true)))
+    }
+
+    /// Only allow paths matching one of these globs, relative to the root. No globs allows all.
+    pub fn with_allow<S: AsRef<str>>(mut self, globs: &[S]) -> Result<Self> {
+        self.allow = self.build_globs(globs)?;
+        Ok(self)
+    }
+
+    /// Deny paths matching one of these globs, relative to the root
+    pub fn with_deny<S: AsRef<str>>(mut self, globs: &[S]) -> Result<Self> {
+        self.deny = self.build_globs(globs)?;
+        Ok(self)
+    }
+
+    /// Allow reading, but not writing, paths outside the workspace
+    pub fn with_allow_outside_reads(mut self, allow_outside_reads: bool) -> Self {
+        self.allow_outside_reads = allow_outside_reads;
+        self
+    }
+
+    /// Deny paths with a component starting with a dot
+    pub fn with_deny_hidden(mut self, deny_hidden: bool) -> Self {
+        self.deny_hidden = deny_hidden;
+        self
+    }
+
+    /// Follow symlinks that stay inside the workspace. Symlinks out of it are never followed.
+    pub fn with_follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// The canonical workspace root
+    pub fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+
+    /// The canonical path to write, or otherwise change, `path`
+    ///
+    /// Relative paths are relative to the workspace root, or to the current directory
+    /// for a sandbox without a workspace.
+    pub fn resolve(&self, path: impl AsRef<Path>) -> Result<PathBuf, SandboxViolation> {
+        self.resolve_for(path.as_ref(), false)
+    }
+
+    /// The canonical path to read `path`, which may be outside the workspace when allowed
+    pub fn resolve_read(&self, path: impl AsRef<Path>) -> Result<PathBuf, SandboxViolation> {
+        self.resolve_for(path.as_ref(), true)
+    }
+
+    fn resolve_for(&self, path: &Path, read: bool) -> Result<PathBuf, SandboxViolation> {
+        let lexical = match (&self.given_root, path.is_absolute()) {
+            (Some(root), false) => normalize(&root.join(path)),
+            _ => normalize(
+                &absolute(path).map_err(|source| SandboxViolation::Unresolvable {
+                    path: path.to_path_buf(),
+                    source,
+                })?,
+            ),
+        };
+        let resolved = canonicalize_existing(&lexical)?;
+
+        let Some(root) = &self.root else {
+            self.check_hidden(&lexical, &resolved)?;
+            self.check_deny(&resolved, None)?;
+            return Ok(resolved);
+        };
+
+        let lexical_inside = self.relative_to_root(&lexical).is_some();
+        if lexical_inside && !self.follow_symlinks {
+            self.check_no_symlinks(&lexical)?;
+        }
+
+        match resolved.strip_prefix(root) {
+            Ok(relative) => {
+                let relative = relative.to_path_buf();
+                self.check_hidden(&lexical, &relative)?;
+                self.check_deny(&resolved, Some(&relative))?;
+                self.check_allow(&resolved, &relative)?;
+                Ok(resolved)
+            }
+            Err(_) if lexical_inside => Err(SandboxViolation::SymlinkEscape {
+                path: lexical,
+                target: resolved,
+                root: root.clone(),
+            }),
+            Err(_) if read && self.allow_outside_reads => {
+                self.check_hidden(&lexical, &resolved)?;
+                self.check_deny(&resolved, None)?;
+                Ok(resolved)
+            }
+            Err(_) if self.allow_outside_reads => Err(SandboxViolation::ReadOnlyOutsideWorkspace {
+                path: lexical,
+                root: root.clone(),
+            }),
+            Err(_) => Err(SandboxViolation::OutsideWorkspace {
+                path: lexical,
+                root: root.clone(),
+            }),
+        }
+    }
+
+    fn build_globs<S: AsRef<str>>(&self, globs: &[S]) -> Result<Option<Gitignore>> {
+        if globs.is_empty() {
+            return Ok(None);
+        }
+        let base = self.root.clone().unwrap_or_default();
+        let mut builder = GitignoreBuilder::new(base);
+        for glob in globs {
+            builder
+                .add_line(None, glob.as_ref())
+                .with_context(|| format!("Invalid sandbox pattern '{}'", glob.as_ref()))?;
+        }
+        Ok(Some(builder.build()?))
+    }
+
+    /// The part of a lexical path under the given or the canonical root
+    fn relative_to_root<'a>(&self, path: &'a Path) -> Option<(&'a Path, &Path)> {
+        [&self.given_root, &self.root]
+            .into_iter()
+            .flatten()
+            .find_map(|root| Some((path.strip_prefix(root).ok()?, root.as_path())))
+    }
+
+    fn check_no_symlinks(&self, lexical: &Path) -> Result<(), SandboxViolation> {
+        let Some((relative, root)) = self.relative_to_root(lexical) else {
+            return Ok(());
+        };
+        let mut current = root.to_path_buf();
+        for component in relative.components() {
+            current.push(component);
+            match fs::symlink_metadata(&current) {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    return Err(SandboxViolation::Symlink {
+                        path: lexical.to_path_buf(),
+                        link: current,
+                    });
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn check_hidden(&self, lexical: &Path, checked: &Path) -> Result<(), SandboxViolation> {
+        let hidden = checked.components().any(|component| match component {
+            Component::Normal(name) => name.to_string_lossy().starts_with('.'),
+            _ => false,
+        });
+        if self.deny_hidden && hidden {
+            return Err(SandboxViolation::Hidden {
+                path: lexical.to_path_buf(),
+            });
+        }
+        Ok(())
+    }
+
+    fn check_deny(&self, resolved: &Path, relative: Option<&Path>) -> Result<(), SandboxViolation> {
+        let Some(deny) = &self.deny else {
+            return Ok(());
+        };
+        let is_dir = resolved.is_dir();
+        let matched = match relative {
+            Some(relative) => deny.matched_path_or_any_parents(relative, is_dir),
+            None => deny.matched(resolved, is_dir),
+        };
+        if let Match::Ignore(glob) = matched {
+            return Err(SandboxViolation::Denied {
+                path: resolved.to_path_buf(),
+                pattern: glob.original().to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn check_allow(&self, resolved: &Path, relative: &Path) -> Result<(), SandboxViolation> {
+        let Some(allow) = &self.allow else {
+            return Ok(());
+        };
+        // The root itself stays usable, so tools can list the workspace
+        if relative.as_os_str().is_empty() {
+            return Ok(());
+        }
+        if allow
+            .matched_path_or_any_parents(relative, resolved.is_dir())
+            .is_ignore()
+        {
+            return Ok(());
+        }
+        Err(SandboxViolation::NotAllowed {
+            path: resolved.to_path_buf(),
+        })
+    }
+}
+
+fn absolute(path: &Path) -> io::Result<PathBuf> {
+    if path.is_absolute() {
+        Ok(path.to_path_buf())
+    } else {
+        Ok(env::current_dir()?.join(path))
+    }
+}
+
+/// `path` without `.` components, with each `..` removing the component before it
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !matches!(
+                    normalized.components().next_back(),
+                    Some(Component::RootDir | Component::Prefix(_)) | None
+                ) {
+                    normalized.pop();
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// The canonical form of the longest existing ancestor of `path`, with the rest appended
+///
+/// A missing component that is a dangling symlink is resolved through its target, so a
+/// file created through it cannot land somewhere else than the path that was checked.
+fn canonicalize_existing(path: &Path) -> Result<PathBuf, SandboxViolation> {
+    let unresolvable = |source| SandboxViolation::Unresolvable {
+        path: path.to_path_buf(),
+        source,
+    };
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        match fs::canonicalize(existing) {
+            Ok(canonical) => {
+                let mut resolved = canonical;
+                for name in missing.iter().rev() {
+                    resolved.push(name);
+                    if let Ok(target) = fs::read_link(&resolved) {
+                        let target = match resolved.parent() {
+                            Some(parent) => normalize(&parent.join(target)),
+                            None => normalize(&target),
+                        };
+                        resolved = canonicalize_existing(&target)?;
+                    }
+                }
+                return Ok(resolved);
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else {
+                    return Err(unresolvable(error));
+                };
+                missing.push(name.to_os_string());
+                existing = parent;
+            }
+            Err(error) => return Err(unresolvable(error)),
+        }
+    }
+}
+
+fn env_list(key: &str) -> Vec<String> {
+    env::var(key)
+        .map(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|glob| !glob.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn env_flag(key: &str, default: bool) -> bool {
+    env::var(key)
+        .ok()
+        .and_then(|value| match value.trim().to_lowercase().as_str() {
+            "1" | "true" | "yes" => Some(true),
+            "0" | "false" | "no" => Some(false),
+            _ => None,
+        })
+        .unwrap_or(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[cfg(unix)]
+    fn symlink_dir(target: &Path, link: &Path) -> bool {
+        std::os::unix::fs::symlink(target, link).is_ok()
+    }
+
+    /// Creating symlinks on Windows needs developer mode or admin rights, so tests skip without
+    #[cfg(windows)]
+    fn symlink_dir(target: &Path, link: &Path) -> bool {
+        std::os::windows::fs::symlink_dir(target, link).is_ok()
+    }
+
+    fn workspace() -> (TempDir, Sandbox) {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src").join("main.rs"), "fn main() {}").unwrap();
+        let sandbox = Sandbox::new(dir.path()).unwrap();
+        (dir, sandbox)
+    }
+
+    #[test]
+    fn test_resolves_paths_inside_the_workspace() {
+        let (dir, sandbox) = workspace();
+        let root = fs::canonicalize(dir.path()).unwrap();
+
+        assert_eq!(
+            sandbox
+                .resolve(dir.path().join("src").join("main.rs"))
+                .unwrap(),
+            root.join("src").join("main.rs")
+        );
+        assert_eq!(
+            sandbox.resolve(Path::new("src").join("new.rs")).unwrap(),
+            root.join("src").join("new.rs")
+        );
+        assert_eq!(
+            sandbox
+                .resolve(dir.path().join("missing").join("..").join("src"))
+                .unwrap(),
+            root.join("src")
+        );
+    }
+
+    #[test]
+    fn test_parent_traversal_out_of_the_workspace() {
+        let (dir, sandbox) = workspace();
+        let escape = dir.path().join("src").join("..").join("..").join("secret");
+
+        let error = sandbox.resolve(&escape).unwrap_err();
+        assert!(matches!(error, SandboxViolation::OutsideWorkspace { .. }));
+        assert!(error
+            .to_string()
+            .contains("only paths inside the workspace"));
+        assert!(sandbox.resolve(Path::new("..").join("..")).is_err());
+
+        let reads = sandbox.clone().with_allow_outside_reads(true);
+        assert!(reads.resolve_read(&escape).is_ok());
+        assert!(matches!(
+            reads.resolve(&escape).unwrap_err(),
+            SandboxViolation::ReadOnlyOutsideWorkspace { .. }
+        ));
+    }
+
+    #[test]
+    fn test_symlinked_directory_out_of_the_workspace() {
+        let (dir, sandbox) = workspace();
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("passwords.txt"), "hunter2").unwrap();
+        let link = dir.path().join("linked");
+        if !symlink_dir(outside.path(), &link) {
+            return;
+        }
+
+        let error = sandbox
+            .resolve_read(link.join("passwords.txt"))
+            .unwrap_err();
+        assert!(matches!(error, SandboxViolation::SymlinkEscape { .. }));
+        assert!(matches!(
+            sandbox.resolve(link.join("new.txt")).unwrap_err(),
+            SandboxViolation::SymlinkEscape { .. }
+        ));
+
+        // Reads outside are allowed only for paths that are outside in the first place
+        let reads = sandbox.with_allow_outside_reads(true);
+        assert!(reads.resolve_read(link.join("passwords.txt")).is_err());
+        assert!(reads
+            .resolve_read(outside.path().join("passwords.txt"))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_symlinks_inside_the_workspace() {
+        let (dir, sandbox) = workspace();
+        let link = dir.path().join("source");
+        if !symlink_dir(&dir.path().join("src"), &link) {
+            return;
+        }
+        let root = fs::canonicalize(dir.path()).unwrap();
+
+        assert_eq!(
+            sandbox.resolve(link.join("main.rs")).unwrap(),
+            root.join("src").join("main.rs")
+        );
+
+        let strict = sandbox.with_follow_symlinks(false);
+        let error = strict.resolve(link.join("main.rs")).unwrap_err();
+        assert!(matches!(error, SandboxViolation::Symlink { .. }));
+        assert!(error.to_string().contains("symlinks are not followed"));
+        assert!(strict
+            .resolve(dir.path().join("src").join("main.rs"))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_hidden_and_patterns() {
+        let (dir, sandbox) = workspace();
+        fs::create_dir_all(dir.path().join(".git")).unwrap();
+
+        let hidden = sandbox.clone().with_deny_hidden(true);
+        assert!(matches!(
+            hidden
+                .resolve(dir.path().join(".git").join("config"))
+                .unwrap_err(),
+            SandboxViolation::Hidden { .. }
+        ));
+        assert!(hidden.resolve(dir.path().join("src")).is_ok());
+
+        let denied = sandbox.clone().with_deny(&["*.pem", "target/"]).unwrap();
+        match denied.resolve(dir.path().join("keys").join("server.pem")) {
+            Err(SandboxViolation::Denied { pattern, .. }) => assert_eq!(pattern, "*.pem"),
+            other => panic!("expected a denied path, got {:?}", other),
+        }
+        fs::create_dir_all(dir.path().join("target")).unwrap();
+        assert!(denied
+            .resolve(dir.path().join("target").join("debug"))
+            .is_err());
+        assert!(denied
+            .resolve(dir.path().join("src").join("main.rs"))
+            .is_ok());
+
+        let allowed = sandbox.with_allow(&["src/**"]).unwrap();
+        assert!(allowed
+            .resolve(dir.path().join("src").join("main.rs"))
+            .is_ok());
+        assert!(allowed.resolve(dir.path()).is_ok());
+        assert!(matches!(
+            allowed.resolve(dir.path().join("README.md")).unwrap_err(),
+            SandboxViolation::NotAllowed { .. }
+        ));
+    }
+
+    #[test]
+    fn test_unrestricted_sandbox() {
+        let (dir, _) = workspace();
+        let sandbox = Sandbox::unrestricted();
+        let root = fs::canonicalize(dir.path()).unwrap();
+
+        assert_eq!(
+            sandbox
+                .resolve(dir.path().join("src").join("..").join("src"))
+                .unwrap(),
+            root.join("src")
+        );
+        assert!(sandbox.root().is_none());
+    }
+}
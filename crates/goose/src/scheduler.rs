@@ -1202,6 +1202,36 @@ async fn run_scheduled_job_internal(
                         Ok(AgentEvent::ModelChange { .. }) => {
                             // Model change events are informational, just continue
                         }
+                        Ok(AgentEvent::TurnStats(_)) => {
+                            // Turn stats are recorded in the session metadata by the agent
+                        }
+                        Ok(AgentEvent::Thinking(_))
+                        | Ok(AgentEvent::ToolCall { .. })
+                        | Ok(AgentEvent::ToolCallProgress { .. })
+                        | Ok(AgentEvent::Usage(_))
+                        | Ok(AgentEvent::CumulativeUsage { .. })
+                        | Ok(AgentEvent::UserFacingToolOutput { .. })
+                        | Ok(AgentEvent::ToolProgress { .. })
+                        | Ok(AgentEvent::Compacted { .. })
+                        | Ok(AgentEvent::SoftStop)
+                        | Ok(AgentEvent::OversizedMessage(_)) => {
+                            // Progress events are also part of the messages, nothing to record
+                        }
+                        Ok(AgentEvent::ApprovalTimedOut {
+                            tool_name,
+                            approved,
+                            ..
+                        }) => {
+                            tracing::warn!(
+                                "[Job {}] Approval for {} timed out, approved: {}",
+                                job.id,
+                                tool_name,
+                                approved
+                            );
+                        }
+                        Ok(AgentEvent::Interjection(msg)) => {
+                            all_session_messages.push(msg);
+                        }
 
                         Err(e) => {
                             tracing::error!(
@@ -1246,6 +1276,11 @@ async fn run_scheduled_job_internal(
                             accumulated_total_tokens: None,
                             accumulated_input_tokens: None,
                             accumulated_output_tokens: None,
+                            usage_by_model: Default::default(),
+                            turn_stats: Vec::new(),
+                            guardrail_rewrites: Vec::new(),
+                            analytics: Default::default(),
+                            encryption: None,
                         };
                         if let Err(e_fb) = crate::session::storage::save_messages_with_metadata(
                             &session_file_path,
@@ -1349,6 +1384,7 @@ mod tests {
                         text: "Mocked scheduled response".to_string(),
                         annotations: None,
                     })],
+                    extra: Default::default(),
                 },
                 ProviderUsage::new("mock-scheduler-test".to_string(), Usage::default()),
             ))
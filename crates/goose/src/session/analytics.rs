@@ -0,0 +1,387 @@
+//! Which tools and models sessions use, and what they cost.
+//!
+//! Each session keeps its own [`SessionAnalytics`] in its metadata, built from the events of
+//! the agent by an [`AnalyticsRecorder`]. `goose stats` aggregates them over all sessions into
+//! an [`AnalyticsReport`], reading only the metadata of each session.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::agents::AgentEvent;
+use crate::message::MessageContent;
+use crate::providers::base::Usage;
+use crate::providers::pricing::PricingInfo;
+use crate::session::storage;
+
+/// Tool results are estimated at this many characters per token
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Calls of one tool in a session
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolUsage {
+    pub calls: usize,
+    /// Calls that returned an error
+    pub failures: usize,
+    /// Execution time of each timed call
+    pub durations_ms: Vec<u64>,
+    /// Estimated tokens of all results together
+    pub result_tokens: u64,
+}
+
+/// Requests sent to one model in a session
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelUsage {
+    pub requests: usize,
+    pub usage: Usage,
+}
+
+/// Tool and model usage of a session, by tool and model name
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionAnalytics {
+    #[serde(default)]
+    pub tools: BTreeMap<String, ToolUsage>,
+    #[serde(default)]
+    pub models: BTreeMap<String, ModelUsage>,
+}
+
+impl SessionAnalytics {
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty() && self.models.is_empty()
+    }
+
+    /// Add the usage of other analytics, such as those of a later reply
+    pub fn merge(&mut self, other: SessionAnalytics) {
+        for (name, usage) in other.tools {
+            let tool = self.tools.entry(name).or_default();
+            tool.calls += usage.calls;
+            tool.failures += usage.failures;
+            tool.durations_ms.extend(usage.durations_ms);
+            tool.result_tokens += usage.result_tokens;
+        }
+        for (name, usage) in other.models {
+            let model = self.models.entry(name).or_default();
+            model.requests += usage.requests;
+            model.usage += usage.usage;
+        }
+    }
+}
+
+/// Builds the analytics of a reply from the events of the agent
+#[derive(Debug, Default)]
+pub struct AnalyticsRecorder {
+    analytics: SessionAnalytics,
+    /// Names of the tool calls by their id, as results only carry the id
+    tool_names: HashMap<String, String>,
+}
+
+impl AnalyticsRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, event: &AgentEvent) {
+        match event {
+            AgentEvent::ToolCall { id, name, .. } => {
+                self.tool_names.insert(id.clone(), name.clone());
+            }
+            AgentEvent::Message(message) => {
+                for content in &message.content {
+                    let MessageContent::ToolResponse(response) = content else {
+                        continue;
+                    };
+                    let Some(name) = self.tool_names.get(&response.id) else {
+                        continue;
+                    };
+                    let tool = self.analytics.tools.entry(name.clone()).or_default();
+                    tool.calls += 1;
+                    if response.tool_result.is_err() {
+                        tool.failures += 1;
+                    }
+                    let chars = content.as_tool_response_text().map_or(0, |text| text.len());
+                    tool.result_tokens += chars.div_ceil(CHARS_PER_TOKEN) as u64;
+                }
+            }
+            AgentEvent::Usage(usage) => {
+                let model = self
+                    .analytics
+                    .models
+                    .entry(usage.model.clone())
+                    .or_default();
+                model.requests += 1;
+                model.usage += usage.usage.clone();
+            }
+            AgentEvent::TurnStats(stats) => {
+                for call in &stats.tool_calls {
+                    self.analytics
+                        .tools
+                        .entry(call.name.clone())
+                        .or_default()
+                        .durations_ms
+                        .push(call.duration_ms);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The analytics recorded since the last time they were taken
+    pub fn take(&mut self) -> SessionAnalytics {
+        std::mem::take(&mut self.analytics)
+    }
+}
+
+/// A tool aggregated over sessions
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ToolReport {
+    pub name: String,
+    pub calls: usize,
+    pub failures: usize,
+    /// Share of calls that failed, from 0 to 1
+    pub failure_rate: f64,
+    pub p50_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+    pub total_ms: u64,
+    pub average_result_tokens: Option<u64>,
+}
+
+/// A model aggregated over sessions
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModelReport {
+    pub model: String,
+    pub requests: usize,
+    pub input_tokens: Option<i32>,
+    pub output_tokens: Option<i32>,
+    pub total_tokens: Option<i32>,
+    /// Cost in US dollars, when the pricing of the model is known
+    pub estimated_cost: Option<f64>,
+}
+
+/// Tool and model usage aggregated over sessions
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AnalyticsReport {
+    /// Sessions that recorded any analytics
+    pub sessions: usize,
+    /// Tools that took the most time first
+    pub tools: Vec<ToolReport>,
+    /// Models with the most requests first
+    pub models: Vec<ModelReport>,
+}
+
+/// The value below which `percent` of the sorted values fall, by the nearest rank
+fn percentile(sorted: &[u64], percent: usize) -> Option<u64> {
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted.get(rank - 1).copied()
+}
+
+impl AnalyticsReport {
+    pub fn from_sessions<'a>(sessions: impl IntoIterator<Item = &'a SessionAnalytics>) -> Self {
+        let mut total = SessionAnalytics::default();
+        let mut count = 0;
+        for analytics in sessions {
+            if !analytics.is_empty() {
+                count += 1;
+                total.merge(analytics.clone());
+            }
+        }
+
+        let mut tools: Vec<ToolReport> = total
+            .tools
+            .into_iter()
+            .map(|(name, mut usage)| {
+                usage.durations_ms.sort_unstable();
+                ToolReport {
+                    name,
+                    calls: usage.calls,
+                    failures: usage.failures,
+                    failure_rate: if usage.calls > 0 {
+                        usage.failures as f64 / usage.calls as f64
+                    } else {
+                        0.0
+                    },
+                    p50_ms: percentile(&usage.durations_ms, 50),
+                    p95_ms: percentile(&usage.durations_ms, 95),
+                    total_ms: usage.durations_ms.iter().sum(),
+                    average_result_tokens: (usage.calls > 0)
+                        .then(|| usage.result_tokens / usage.calls as u64),
+                }
+            })
+            .collect();
+        tools.sort_by(|a, b| b.total_ms.cmp(&a.total_ms).then(a.name.cmp(&b.name)));
+
+        let mut models: Vec<ModelReport> = total
+            .models
+            .into_iter()
+            .map(|(model, usage)| ModelReport {
+                model,
+                requests: usage.requests,
+                input_tokens: usage.usage.input_tokens,
+                output_tokens: usage.usage.output_tokens,
+                total_tokens: usage.usage.total_tokens,
+                estimated_cost: None,
+            })
+            .collect();
+        models.sort_by(|a, b| b.requests.cmp(&a.requests).then(a.model.cmp(&b.model)));
+
+        Self {
+            sessions: count,
+            tools,
+            models,
+        }
+    }
+
+    /// Estimate the cost of each model with the pricing `pricing` finds for its name
+    pub fn estimate_costs(&mut self, pricing: impl Fn(&str) -> Option<PricingInfo>) {
+        for model in &mut self.models {
+            model.estimated_cost = pricing(&model.model).map(|pricing| {
+                model.input_tokens.unwrap_or(0) as f64 * pricing.input_cost
+                    + model.output_tokens.unwrap_or(0) as f64 * pricing.output_cost
+            });
+        }
+    }
+}
+
+/// Aggregate the analytics of all saved sessions, reading only their metadata
+pub fn aggregate_sessions() -> Result<AnalyticsReport> {
+    let mut analytics = Vec::new();
+    for (id, path) in storage::list_sessions()? {
+        match storage::read_metadata(&path) {
+            Ok(metadata) => analytics.push(metadata.analytics),
+            Err(e) => tracing::warn!("Failed to read the metadata of session {}: {}", id, e),
+        }
+    }
+    Ok(AnalyticsReport::from_sessions(&analytics))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+    use crate::providers::base::ProviderUsage;
+    use crate::session::{ToolCallTiming, TurnStats};
+    use mcp_core::content::Content;
+    use mcp_core::handler::ToolError;
+
+    fn tool_call(id: &str, name: &str) -> AgentEvent {
+        AgentEvent::ToolCall {
+            id: id.to_string(),
+            name: name.to_string(),
+            arguments: serde_json::json!({}),
+        }
+    }
+
+    fn timing(id: &str, name: &str, duration_ms: u64) -> ToolCallTiming {
+        ToolCallTiming {
+            id: id.to_string(),
+            name: name.to_string(),
+            duration_ms,
+        }
+    }
+
+    fn usage(model: &str, input: i32, output: i32) -> AgentEvent {
+        AgentEvent::Usage(ProviderUsage::new(
+            model.to_string(),
+            Usage::new(Some(input), Some(output), Some(input + output)),
+        ))
+    }
+
+    #[test]
+    fn test_recorder_follows_events() {
+        let mut recorder = AnalyticsRecorder::new();
+        recorder.record(&usage("gpt-4o", 1000, 100));
+        recorder.record(&tool_call("1", "shell"));
+        recorder.record(&tool_call("2", "shell"));
+        recorder.record(&tool_call("3", "read"));
+        recorder.record(&AgentEvent::Message(
+            Message::user()
+                .with_tool_response("1", Ok(vec![Content::text("x".repeat(400))]))
+                .with_tool_response(
+                    "2",
+                    Err(ToolError::ExecutionError("exit code 1".to_string())),
+                )
+                .with_tool_response("3", Ok(vec![Content::text("fn main() {}")])),
+        ));
+        recorder.record(&usage("gpt-4o", 1200, 50));
+        recorder.record(&AgentEvent::TurnStats(TurnStats {
+            tool_calls: vec![
+                timing("1", "shell", 300),
+                timing("2", "shell", 100),
+                timing("3", "read", 5),
+            ],
+            ..Default::default()
+        }));
+
+        let analytics = recorder.take();
+        assert_eq!(
+            analytics.tools["shell"],
+            ToolUsage {
+                calls: 2,
+                failures: 1,
+                durations_ms: vec![300, 100],
+                result_tokens: 100,
+            }
+        );
+        assert_eq!(analytics.tools["read"].result_tokens, 3);
+        assert_eq!(
+            analytics.models["gpt-4o"],
+            ModelUsage {
+                requests: 2,
+                usage: Usage::new(Some(2200), Some(150), Some(2350)),
+            }
+        );
+        assert!(recorder.take().is_empty());
+    }
+
+    #[test]
+    fn test_report_over_sessions() {
+        let session = |durations_ms: Vec<u64>, failures| SessionAnalytics {
+            tools: BTreeMap::from([(
+                "shell".to_string(),
+                ToolUsage {
+                    calls: durations_ms.len(),
+                    failures,
+                    result_tokens: 50 * durations_ms.len() as u64,
+                    durations_ms,
+                },
+            )]),
+            models: BTreeMap::from([(
+                "gpt-4o".to_string(),
+                ModelUsage {
+                    requests: 3,
+                    usage: Usage::new(Some(1_000_000), Some(100_000), Some(1_100_000)),
+                },
+            )]),
+        };
+        let sessions = vec![
+            session((1..=10).collect(), 1),
+            SessionAnalytics::default(),
+            session((91..=100).collect(), 4),
+        ];
+
+        let mut report = AnalyticsReport::from_sessions(&sessions);
+        assert_eq!(report.sessions, 2);
+        let shell = &report.tools[0];
+        assert_eq!(shell.calls, 20);
+        assert_eq!(shell.failure_rate, 0.25);
+        assert_eq!(shell.p50_ms, Some(10));
+        assert_eq!(shell.p95_ms, Some(99));
+        assert_eq!(shell.average_result_tokens, Some(50));
+
+        report.estimate_costs(|model| {
+            (model == "gpt-4o").then_some(PricingInfo {
+                input_cost: 0.000_002_5,
+                output_cost: 0.000_01,
+                context_length: None,
+            })
+        });
+        let model = &report.models[0];
+        assert_eq!(model.requests, 6);
+        assert_eq!(model.input_tokens, Some(2_000_000));
+        let cost = model.estimated_cost.unwrap();
+        assert!((cost - 7.0).abs() < 1e-9);
+
+        assert_eq!(percentile(&[], 50), None);
+        assert_eq!(percentile(&[7], 95), Some(7));
+    }
+}
@@ -0,0 +1,198 @@
+//! Content-addressed storage for large binary content of session messages.
+//!
+//! Images larger than [`BLOB_THRESHOLD`] are written once to `<session>.blobs/<sha256>` next to
+//! the session file, and the message line only keeps a reference to them. This keeps session
+//! lines small, so appending a turn does not rewrite or re-serialize embedded images.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use mcp_core::content::ImageContent;
+use mcp_core::Content;
+use sha2::{Digest, Sha256};
+
+use crate::message::{Message, MessageContent};
+use crate::providers::utils::decode_base64;
+
+/// Images with more base64 data than this are stored as blobs
+pub const BLOB_THRESHOLD: usize = 64 * 1024;
+
+const BLOB_REF_PREFIX: &str = "goose-blob:sha256:";
+
+/// The directory holding the blobs of a session file
+pub fn blob_dir(session_file: &Path) -> PathBuf {
+    session_file.with_extension("blobs")
+}
+
+fn images_mut(message: &mut Message) -> impl Iterator<Item = &mut ImageContent> {
+    message
+        .content
+        .iter_mut()
+        .flat_map(|content| -> Box<dyn Iterator<Item = &mut ImageContent>> {
+            match content {
+                MessageContent::Image(image) => Box::new(std::iter::once(image)),
+                MessageContent::ToolResponse(response) => match &mut response.tool_result {
+                    Ok(contents) => Box::new(contents.iter_mut().filter_map(|c| match c {
+                        Content::Image(image) => Some(image),
+                        _ => None,
+                    })),
+                    Err(_) => Box::new(std::iter::empty()),
+                },
+                _ => Box::new(std::iter::empty()),
+            }
+        })
+}
+
+fn blob_hash(data: &str) -> Option<&str> {
+    data.strip_prefix(BLOB_REF_PREFIX)
+        .filter(|hash| hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Return a copy of the message with large images replaced by references, writing any
+/// blobs that are not stored yet
+pub fn externalize_blobs(message: &Message, blob_dir: &Path, sync: bool) -> Result<Message> {
+    let mut message = message.clone();
+    for image in images_mut(&mut message) {
+        if image.data.len() <= BLOB_THRESHOLD || blob_hash(&image.data).is_some() {
+            continue;
+        }
+        let Ok(bytes) = decode_base64(&image.data) else {
+            continue;
+        };
+        let hash = format!("{:x}", Sha256::digest(&bytes));
+        write_blob(blob_dir, &hash, &bytes, sync)?;
+        image.data = format!("{}{}", BLOB_REF_PREFIX, hash);
+    }
+    Ok(message)
+}
+
+fn write_blob(blob_dir: &Path, hash: &str, bytes: &[u8], sync: bool) -> Result<()> {
+    let path = blob_dir.join(hash);
+    if path.exists() {
+        return Ok(());
+    }
+    fs::create_dir_all(blob_dir)?;
+
+    let temp_path = blob_dir.join(format!("{}.tmp", hash));
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(bytes)?;
+    if sync {
+        file.sync_all()?;
+    }
+    fs::rename(&temp_path, &path)?;
+    Ok(())
+}
+
+/// Replace blob references in a message with the stored data
+///
+/// A missing blob is logged and leaves the reference in place, so the rest of the
+/// session still loads.
+pub fn resolve_blobs(message: &mut Message, blob_dir: &Path) {
+    for image in images_mut(message) {
+        let Some(hash) = blob_hash(&image.data) else {
+            continue;
+        };
+        match fs::read(blob_dir.join(hash)) {
+            Ok(bytes) => image.data = STANDARD.encode(bytes),
+            Err(e) => tracing::warn!("Failed to read session blob {}: {}", hash, e),
+        }
+    }
+}
+
+/// Hashes of the blobs a message refers to
+pub fn referenced_blobs(message: &Message) -> HashSet<String> {
+    let mut message = message.clone();
+    images_mut(&mut message)
+        .filter_map(|image| blob_hash(&image.data).map(str::to_string))
+        .collect()
+}
+
+/// Remove blobs that are not in `referenced`, returning how many were removed and their size
+pub fn remove_orphaned_blobs(
+    blob_dir: &Path,
+    referenced: &HashSet<String>,
+) -> Result<(usize, u64)> {
+    if !blob_dir.exists() {
+        return Ok((0, 0));
+    }
+
+    let mut removed = 0;
+    let mut freed_bytes = 0;
+    for entry in fs::read_dir(blob_dir)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if referenced.contains(&name) {
+            continue;
+        }
+        freed_bytes += fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        fs::remove_file(&path)?;
+        removed += 1;
+    }
+
+    if fs::read_dir(blob_dir)?.next().is_none() {
+        fs::remove_dir(blob_dir)?;
+    }
+    Ok((removed, freed_bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn large_image() -> String {
+        STANDARD.encode(vec![7u8; BLOB_THRESHOLD])
+    }
+
+    #[test]
+    fn test_externalize_and_resolve_round_trip() -> Result<()> {
+        let dir = tempdir()?;
+        let blob_dir = dir.path().join("session.blobs");
+        let data = large_image();
+        let message = Message::user()
+            .with_image(data.clone(), "image/png")
+            .with_image("c21hbGw=", "image/png")
+            .with_tool_response(
+                "1",
+                Ok(vec![Content::image(data.clone(), "image/png".to_string())]),
+            );
+
+        let stored = externalize_blobs(&message, &blob_dir, false)?;
+        let refs = referenced_blobs(&stored);
+        assert_eq!(refs.len(), 1);
+        assert_eq!(fs::read_dir(&blob_dir)?.count(), 1);
+        assert!(serde_json::to_string(&stored)?.len() < 1024);
+
+        let mut loaded = stored.clone();
+        resolve_blobs(&mut loaded, &blob_dir);
+        assert_eq!(loaded, message);
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_orphaned_blobs() -> Result<()> {
+        let dir = tempdir()?;
+        let blob_dir = dir.path().join("session.blobs");
+        let stored = externalize_blobs(
+            &Message::user().with_image(large_image(), "image/png"),
+            &blob_dir,
+            false,
+        )?;
+
+        assert_eq!(
+            remove_orphaned_blobs(&blob_dir, &referenced_blobs(&stored))?.0,
+            0
+        );
+        assert_eq!(remove_orphaned_blobs(&blob_dir, &HashSet::new())?.0, 1);
+        assert!(!blob_dir.exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,362 @@
+//! Encryption of session message content at rest.
+//!
+//! Each encrypted session has its own random key, stored in the metadata line wrapped by a key
+//! derived from a passphrase with argon2, or by a key kept in the OS keyring. Message lines
+//! keep their role, timestamps and other fields in clear and replace the content with a
+//! chacha20poly1305 ciphertext, so sessions can be listed without the key. The clear fields
+//! and the position of the message are authenticated along with the content, so a modified,
+//! moved or injected line fails to open instead of being loaded.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex, OnceLock};
+
+use anyhow::{anyhow, Result};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::{Config, ConfigError};
+use crate::message::Message;
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+
+/// The field holding the encrypted content of a message line
+const SEALED_FIELD: &str = "sealed";
+
+/// The secret holding the key that wraps session keys in keyring mode
+const KEYRING_SECRET: &str = "GOOSE_SESSION_KEY";
+
+/// Where the key that wraps session keys comes from, configured with GOOSE_SESSION_ENCRYPTION
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySource {
+    /// Derived from a passphrase, read from GOOSE_SESSION_PASSPHRASE or asked for once per process
+    Passphrase,
+    /// A random key stored with the other goose secrets in the OS keyring
+    Keyring,
+}
+
+impl KeySource {
+    /// The key source new sessions are encrypted with, none when encryption is off
+    pub fn from_config() -> Option<Self> {
+        Config::global().get_param("GOOSE_SESSION_ENCRYPTION").ok()
+    }
+}
+
+/// How the key of an encrypted session is stored, kept in its metadata line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptionHeader {
+    pub key_source: KeySource,
+    /// The argon2 salt of the passphrase, base64 encoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+    /// The session key encrypted with the wrapping key, base64 encoded after its nonce
+    pub wrapped_key: String,
+}
+
+/// The key that encrypts the message content of one session
+#[derive(Clone)]
+pub struct SessionKey([u8; KEY_LEN]);
+
+type PassphrasePrompt = Box<dyn Fn(&str) -> Result<String> + Send + Sync>;
+
+static PASSPHRASE_PROMPT: OnceLock<PassphrasePrompt> = OnceLock::new();
+static PASSPHRASE: Mutex<Option<String>> = Mutex::new(None);
+static SESSION_KEYS: LazyLock<Mutex<HashMap<String, SessionKey>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Set how the passphrase is asked for when GOOSE_SESSION_PASSPHRASE is not set
+///
+/// The prompt is given the message to show. Without one, passphrase sessions can only be
+/// opened with the environment variable.
+pub fn set_passphrase_prompt(prompt: impl Fn(&str) -> Result<String> + Send + Sync + 'static) {
+    let _ = PASSPHRASE_PROMPT.set(Box::new(prompt));
+}
+
+/// The passphrase of this process, asked for the first time it is needed
+fn passphrase(confirm: bool) -> Result<String> {
+    let mut cached = PASSPHRASE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(passphrase) = cached.as_ref() {
+        return Ok(passphrase.clone());
+    }
+    let passphrase = match std::env::var("GOOSE_SESSION_PASSPHRASE") {
+        Ok(passphrase) => passphrase,
+        Err(_) => {
+            let prompt = PASSPHRASE_PROMPT.get().ok_or_else(|| {
+                anyhow!("Session encryption needs a passphrase, set GOOSE_SESSION_PASSPHRASE")
+            })?;
+            let passphrase = prompt("Session passphrase")?;
+            if confirm && prompt("Repeat the session passphrase")? != passphrase {
+                return Err(anyhow!("The session passphrases do not match"));
+            }
+            passphrase
+        }
+    };
+    if passphrase.is_empty() {
+        return Err(anyhow!("The session passphrase is empty"));
+    }
+    *cached = Some(passphrase.clone());
+    Ok(passphrase)
+}
+
+fn forget_passphrase() {
+    *PASSPHRASE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+fn passphrase_key(passphrase: &str, salt: &[u8]) -> Result<[u8; KEY_LEN]> {
+    let mut key = [0u8; KEY_LEN];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Failed to derive the session key: {}", e))?;
+    Ok(key)
+}
+
+/// The key in the keyring, created the first time it is needed
+fn keyring_key(create: bool) -> Result<[u8; KEY_LEN]> {
+    let config = Config::global();
+    match config.get_secret::<String>(KEYRING_SECRET) {
+        Ok(encoded) => STANDARD
+            .decode(encoded)
+            .ok()
+            .and_then(|key| key.try_into().ok())
+            .ok_or_else(|| anyhow!("The session key in the keyring is not valid")),
+        Err(ConfigError::NotFound(_)) if create => {
+            let key = random_bytes::<KEY_LEN>();
+            config.set_secret(KEYRING_SECRET, Value::String(STANDARD.encode(key)))?;
+            Ok(key)
+        }
+        Err(e) => Err(anyhow!(
+            "Failed to read the session key from the keyring: {}",
+            e
+        )),
+    }
+}
+
+fn seal(key: &[u8; KEY_LEN], plaintext: &[u8], aad: &[u8]) -> Result<String> {
+    let nonce = random_bytes::<NONCE_LEN>();
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| anyhow!("Failed to encrypt session content"))?;
+    Ok(STANDARD.encode([nonce.as_slice(), &ciphertext].concat()))
+}
+
+/// Decrypt what [`seal`] returned, failing when the ciphertext or the associated data changed
+fn open(key: &[u8; KEY_LEN], sealed: &str, aad: &[u8]) -> Option<Vec<u8>> {
+    let sealed = STANDARD.decode(sealed).ok()?;
+    if sealed.len() < NONCE_LEN {
+        return None;
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .ok()
+}
+
+fn wrap(
+    key_source: KeySource,
+    salt: Option<&[u8]>,
+    wrapping_key: &[u8; KEY_LEN],
+) -> Result<EncryptionHeader> {
+    let session_key = SessionKey(random_bytes::<KEY_LEN>());
+    let header = EncryptionHeader {
+        key_source,
+        salt: salt.map(|salt| STANDARD.encode(salt)),
+        wrapped_key: seal(wrapping_key, &session_key.0, &[])?,
+    };
+    SESSION_KEYS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(header.wrapped_key.clone(), session_key);
+    Ok(header)
+}
+
+/// Create the key of a new encrypted session
+pub fn create_header(key_source: KeySource) -> Result<EncryptionHeader> {
+    match key_source {
+        KeySource::Passphrase => header_with_passphrase(&passphrase(true)?),
+        KeySource::Keyring => wrap(KeySource::Keyring, None, &keyring_key(true)?),
+    }
+}
+
+/// Create the key of a new session encrypted with the given passphrase
+pub fn header_with_passphrase(passphrase: &str) -> Result<EncryptionHeader> {
+    let salt = random_bytes::<SALT_LEN>();
+    wrap(
+        KeySource::Passphrase,
+        Some(&salt),
+        &passphrase_key(passphrase, &salt)?,
+    )
+}
+
+/// Unwrap the key of an encrypted session
+pub fn unlock(header: &EncryptionHeader) -> Result<SessionKey> {
+    if let Some(key) = SESSION_KEYS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&header.wrapped_key)
+    {
+        return Ok(key.clone());
+    }
+
+    let wrapping_key = match header.key_source {
+        KeySource::Passphrase => {
+            let salt = header
+                .salt
+                .as_ref()
+                .and_then(|salt| STANDARD.decode(salt).ok())
+                .ok_or_else(|| anyhow!("The session key has no valid salt"))?;
+            passphrase_key(&passphrase(false)?, &salt)?
+        }
+        KeySource::Keyring => keyring_key(false)?,
+    };
+    let key: [u8; KEY_LEN] = open(&wrapping_key, &header.wrapped_key, &[])
+        .and_then(|key| key.try_into().ok())
+        .ok_or_else(|| {
+            if header.key_source == KeySource::Passphrase {
+                // Ask again next time instead of failing for the rest of the process
+                forget_passphrase();
+                anyhow!("Wrong session passphrase, or the session key was modified")
+            } else {
+                anyhow!("The keyring key does not open this session, or its key was modified")
+            }
+        })?;
+
+    let key = SessionKey(key);
+    SESSION_KEYS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(header.wrapped_key.clone(), key.clone());
+    Ok(key)
+}
+
+/// The data authenticated with the content of a message line: its position and clear fields
+fn associated_data(index: usize, clear: &Value) -> Result<Vec<u8>> {
+    let mut aad = (index as u64).to_le_bytes().to_vec();
+    serde_json::to_writer(&mut aad, clear)?;
+    Ok(aad)
+}
+
+/// Serialize a message with its content encrypted, for the line at `index` of the session
+pub fn seal_message(message: &Message, index: usize, key: &SessionKey) -> Result<Value> {
+    let mut value = serde_json::to_value(message)?;
+    let fields = value
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("Message is not a JSON object"))?;
+    let content = fields.remove("content").unwrap_or(Value::Array(Vec::new()));
+    let aad = associated_data(index, &value)?;
+    let sealed = seal(&key.0, &serde_json::to_vec(&content)?, &aad)?;
+    if let Some(fields) = value.as_object_mut() {
+        fields.insert(SEALED_FIELD.to_string(), Value::String(sealed));
+    }
+    Ok(value)
+}
+
+/// Decrypt a message line written by [`seal_message`], returning the plain message JSON
+///
+/// Fails when the line is not encrypted, or was modified or moved since it was written.
+pub fn open_message(line: &str, index: usize, key: &SessionKey) -> Result<String> {
+    let not_verified = || anyhow!("message {} failed verification", index + 1);
+    let mut value: Value = serde_json::from_str(line).map_err(|_| not_verified())?;
+    let sealed = value
+        .as_object_mut()
+        .and_then(|fields| fields.remove(SEALED_FIELD))
+        .ok_or_else(|| anyhow!("message {} is not encrypted", index + 1))?;
+    let sealed = sealed.as_str().ok_or_else(not_verified)?;
+    let content =
+        open(&key.0, sealed, &associated_data(index, &value)?).ok_or_else(not_verified)?;
+    let content: Value = serde_json::from_slice(&content).map_err(|_| not_verified())?;
+    if let Some(fields) = value.as_object_mut() {
+        fields.insert("content".to_string(), content);
+    }
+    Ok(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message() -> Message {
+        Message::user().with_text("fn secret() -> u32 { 42 }")
+    }
+
+    #[test]
+    fn test_seal_and_open_message() -> Result<()> {
+        let header = header_with_passphrase("correct horse")?;
+        let key = unlock(&header)?;
+
+        let sealed = seal_message(&message(), 3, &key)?;
+        assert_eq!(sealed["role"], "user");
+        assert!(sealed.get("content").is_none());
+        assert!(!sealed.to_string().contains("secret"));
+
+        let opened = open_message(&sealed.to_string(), 3, &key)?;
+        assert_eq!(serde_json::from_str::<Message>(&opened)?, message());
+        Ok(())
+    }
+
+    #[test]
+    fn test_tampering_fails() -> Result<()> {
+        let key = unlock(&header_with_passphrase("correct horse")?)?;
+        let sealed = seal_message(&message(), 0, &key)?;
+
+        // A flipped bit in the ciphertext
+        let mut modified = sealed.clone();
+        let mut bytes = STANDARD.decode(sealed[SEALED_FIELD].as_str().unwrap())?;
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        modified[SEALED_FIELD] = Value::String(STANDARD.encode(bytes));
+        let error = open_message(&modified.to_string(), 0, &key).unwrap_err();
+        assert_eq!(error.to_string(), "message 1 failed verification");
+
+        // A clear field that was changed, a line that was moved, and one that was injected
+        let mut modified = sealed.clone();
+        modified["role"] = Value::String("assistant".to_string());
+        assert!(open_message(&modified.to_string(), 0, &key).is_err());
+        assert!(open_message(&sealed.to_string(), 1, &key).is_err());
+        let plain = serde_json::to_string(&message())?;
+        assert!(open_message(&plain, 0, &key).is_err());
+
+        // Another session's key
+        let other = unlock(&header_with_passphrase("correct horse")?)?;
+        assert!(open_message(&sealed.to_string(), 0, &other).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrapped_key_needs_the_passphrase() -> Result<()> {
+        let header = header_with_passphrase("correct horse")?;
+        let key = unlock(&header)?;
+
+        let salt = STANDARD.decode(header.salt.as_ref().unwrap())?;
+        let wrong = passphrase_key("battery staple", &salt)?;
+        assert!(open(&wrong, &header.wrapped_key, &[]).is_none());
+        let right = passphrase_key("correct horse", &salt)?;
+        assert_eq!(open(&right, &header.wrapped_key, &[]), Some(key.0.to_vec()));
+        Ok(())
+    }
+}
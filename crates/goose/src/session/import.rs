@@ -0,0 +1,643 @@
+//! Import conversations exported from other chat tools into goose messages.
+//!
+//! Two export formats are supported:
+//! - ChatGPT (`conversations.json`), where each conversation is a `mapping` tree of nodes
+//!   and `current_node` marks the branch that was last shown to the user.
+//! - Claude (`conversations.json`), where each conversation has a flat list of `chat_messages`.
+//!
+//! Content that has no equivalent in the goose message model is dropped with a warning.
+
+use crate::message::Message;
+use crate::providers::utils::sanitize_function_name;
+use anyhow::{anyhow, Result};
+use chrono::DateTime;
+use mcp_core::role::Role;
+use mcp_core::tool::ToolCall;
+use mcp_core::{Content, ToolError};
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Export formats that can be imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    ChatGpt,
+    Anthropic,
+}
+
+/// A conversation converted into goose messages
+#[derive(Debug, Clone)]
+pub struct ImportedConversation {
+    pub title: Option<String>,
+    pub messages: Vec<Message>,
+    /// Descriptions of content that could not be imported
+    pub warnings: Vec<String>,
+}
+
+/// Detect the export format of a conversation, or of the first conversation in a list
+pub fn detect_format(value: &Value) -> Option<ImportFormat> {
+    let conversation = match value {
+        Value::Array(items) => items.first()?,
+        _ => value,
+    };
+
+    if conversation.get("mapping").is_some_and(Value::is_object) {
+        Some(ImportFormat::ChatGpt)
+    } else if conversation
+        .get("chat_messages")
+        .is_some_and(Value::is_array)
+    {
+        Some(ImportFormat::Anthropic)
+    } else {
+        None
+    }
+}
+
+/// Read an export file and convert every conversation in it
+pub fn import_file(path: &Path) -> Result<Vec<ImportedConversation>> {
+    let contents = std::fs::read_to_string(path)?;
+    let value: Value = serde_json::from_str(&contents)
+        .map_err(|e| anyhow!("Failed to parse {} as JSON: {}", path.display(), e))?;
+    import_conversations(&value)
+}
+
+/// Convert a single exported conversation or a list of them
+pub fn import_conversations(value: &Value) -> Result<Vec<ImportedConversation>> {
+    let format = detect_format(value).ok_or_else(|| {
+        anyhow!("Unrecognized export format, expected a ChatGPT or Claude conversation export")
+    })?;
+
+    let conversations = match value {
+        Value::Array(items) => items.iter().collect(),
+        _ => vec![value],
+    };
+
+    conversations
+        .into_iter()
+        .map(|conversation| match format {
+            ImportFormat::ChatGpt => import_chatgpt(conversation),
+            ImportFormat::Anthropic => import_anthropic(conversation),
+        })
+        .collect()
+}
+
+/// Collects converted messages and keeps tool requests paired with their responses
+#[derive(Default)]
+struct ConversationBuilder {
+    messages: Vec<Message>,
+    warnings: Vec<String>,
+    pending_tool_ids: Vec<String>,
+    next_tool_id: usize,
+}
+
+impl ConversationBuilder {
+    fn warn(&mut self, warning: String) {
+        tracing::warn!("{}", warning);
+        self.warnings.push(warning);
+    }
+
+    fn push(&mut self, mut message: Message, created: Option<i64>) {
+        if message.content.is_empty() {
+            return;
+        }
+        if let Some(created) = created {
+            message.created = created;
+        }
+
+        // Consecutive messages from the same role are merged so the history alternates
+        if let Some(last) = self.messages.last_mut() {
+            if last.role == message.role {
+                last.content.extend(message.content);
+                return;
+            }
+        }
+        self.messages.push(message);
+    }
+
+    fn push_text(&mut self, role: Role, text: &str, created: Option<i64>) {
+        if text.trim().is_empty() {
+            return;
+        }
+        let message = match role {
+            Role::User => {
+                // Tool results have to come before anything else the user says
+                self.close_pending_tool_requests();
+                Message::user()
+            }
+            Role::Assistant => Message::assistant(),
+        };
+        self.push(message.with_text(text), created);
+    }
+
+    fn push_tool_request(
+        &mut self,
+        id: Option<&str>,
+        name: &str,
+        arguments: Value,
+        created: Option<i64>,
+    ) {
+        let id = match id {
+            Some(id) => id.to_string(),
+            None => {
+                self.next_tool_id += 1;
+                format!("imported_tool_{}", self.next_tool_id)
+            }
+        };
+        let tool_call = ToolCall::new(sanitize_function_name(name), arguments);
+        self.push(
+            Message::assistant().with_tool_request(id.clone(), Ok(tool_call)),
+            created,
+        );
+        self.pending_tool_ids.push(id);
+    }
+
+    fn push_tool_response(
+        &mut self,
+        id: Option<&str>,
+        output: String,
+        is_error: bool,
+        created: Option<i64>,
+    ) -> bool {
+        // Results without an id answer the oldest request that is still open
+        let position = match id {
+            Some(id) => self
+                .pending_tool_ids
+                .iter()
+                .position(|pending| pending == id),
+            None => (!self.pending_tool_ids.is_empty()).then_some(0),
+        };
+        let Some(position) = position else {
+            return false;
+        };
+
+        let id = self.pending_tool_ids.remove(position);
+        let result = if is_error {
+            Err(ToolError::ExecutionError(output))
+        } else {
+            Ok(vec![Content::text(output)])
+        };
+        self.push(Message::user().with_tool_response(id, result), created);
+        true
+    }
+
+    /// Answer requests the export has no result for, since providers reject unpaired calls
+    fn close_pending_tool_requests(&mut self) {
+        for id in std::mem::take(&mut self.pending_tool_ids) {
+            self.push(
+                Message::user().with_tool_response(
+                    id,
+                    Err(ToolError::ExecutionError(
+                        "No tool result was recorded in the imported conversation".to_string(),
+                    )),
+                ),
+                None,
+            );
+        }
+    }
+
+    fn finish(mut self, title: Option<String>) -> ImportedConversation {
+        self.close_pending_tool_requests();
+        ImportedConversation {
+            title,
+            messages: self.messages,
+            warnings: self.warnings,
+        }
+    }
+}
+
+fn string_field<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(Value::as_str)
+}
+
+/// Tool arguments are kept as JSON when the export recorded them that way
+fn parse_arguments(input: &str) -> Value {
+    match serde_json::from_str::<Value>(input) {
+        Ok(value @ Value::Object(_)) => value,
+        _ => json!({ "input": input }),
+    }
+}
+
+fn import_chatgpt(conversation: &Value) -> Result<ImportedConversation> {
+    let title = string_field(conversation, "title").map(String::from);
+    let mapping = conversation
+        .get("mapping")
+        .and_then(Value::as_object)
+        .ok_or_else(|| anyhow!("ChatGPT conversation is missing its mapping"))?;
+
+    // Walk up from the current node so only the branch the user last saw is imported.
+    // Older exports have no current node, in which case the latest child is followed down.
+    let mut branch = Vec::new();
+    if let Some(current) = string_field(conversation, "current_node") {
+        let mut node_id = Some(current);
+        while let Some(id) = node_id {
+            let Some(node) = mapping.get(id) else {
+                break;
+            };
+            if branch.len() > mapping.len() {
+                return Err(anyhow!("ChatGPT conversation contains a cycle"));
+            }
+            branch.push(node);
+            node_id = string_field(node, "parent");
+        }
+        branch.reverse();
+    } else {
+        let mut node = mapping
+            .values()
+            .find(|node| node.get("parent").is_none_or(Value::is_null));
+        while let Some(current) = node {
+            if branch.len() > mapping.len() {
+                return Err(anyhow!("ChatGPT conversation contains a cycle"));
+            }
+            branch.push(current);
+            node = current
+                .get("children")
+                .and_then(Value::as_array)
+                .and_then(|children| children.last())
+                .and_then(Value::as_str)
+                .and_then(|id| mapping.get(id));
+        }
+    }
+
+    let mut builder = ConversationBuilder::default();
+    for node in branch {
+        let Some(message) = node.get("message").filter(|m| !m.is_null()) else {
+            continue;
+        };
+        let created = message
+            .get("create_time")
+            .and_then(Value::as_f64)
+            .map(|time| time as i64);
+        let role = message
+            .get("author")
+            .and_then(|author| string_field(author, "role"))
+            .unwrap_or("");
+        let text = chatgpt_text(message, &mut builder);
+
+        match role {
+            "user" => builder.push_text(Role::User, &text, created),
+            "assistant" => {
+                let recipient = string_field(message, "recipient").unwrap_or("all");
+                if recipient == "all" {
+                    builder.push_text(Role::Assistant, &text, created);
+                } else {
+                    builder.push_tool_request(None, recipient, parse_arguments(&text), created);
+                }
+            }
+            "tool" => {
+                if !builder.push_tool_response(None, text.clone(), false, created)
+                    && !text.trim().is_empty()
+                {
+                    let name = message
+                        .get("author")
+                        .and_then(|author| string_field(author, "name"))
+                        .unwrap_or("tool");
+                    builder.push_text(
+                        Role::Assistant,
+                        &format!("Output from {}:\n{}", name, text),
+                        created,
+                    );
+                }
+            }
+            "system" => {
+                // Goose messages have no system role, so instructions that were part of
+                // the conversation are kept as user context at the same position
+                if !text.trim().is_empty() {
+                    builder.push_text(Role::User, &format!("System: {}", text), created);
+                }
+            }
+            other => builder.warn(format!(
+                "Skipped a message with unsupported role '{}'",
+                other
+            )),
+        }
+    }
+
+    Ok(builder.finish(title))
+}
+
+/// Extract the text of a ChatGPT message, warning about parts that cannot be represented
+fn chatgpt_text(message: &Value, builder: &mut ConversationBuilder) -> String {
+    let Some(content) = message.get("content") else {
+        return String::new();
+    };
+    let content_type = string_field(content, "content_type").unwrap_or("text");
+
+    match content_type {
+        "text" | "multimodal_text" => {
+            let mut texts = Vec::new();
+            for part in content
+                .get("parts")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                match part {
+                    Value::String(text) => texts.push(text.as_str()),
+                    Value::Object(_) => {
+                        let part_type = string_field(part, "content_type").unwrap_or("unknown");
+                        builder.warn(format!(
+                            "Dropped unsupported '{}' content from a message",
+                            part_type
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+            texts.join("\n")
+        }
+        "code" | "execution_output" | "tether_quote" | "tether_browsing_display" => {
+            string_field(content, "text")
+                .or_else(|| string_field(content, "result"))
+                .unwrap_or_default()
+                .to_string()
+        }
+        other => {
+            builder.warn(format!(
+                "Dropped unsupported '{}' content from a message",
+                other
+            ));
+            String::new()
+        }
+    }
+}
+
+fn import_anthropic(conversation: &Value) -> Result<ImportedConversation> {
+    let title = string_field(conversation, "name")
+        .filter(|name| !name.is_empty())
+        .map(String::from);
+    let chat_messages = conversation
+        .get("chat_messages")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("Claude conversation is missing its chat messages"))?;
+
+    let mut builder = ConversationBuilder::default();
+    for chat_message in chat_messages {
+        let created = string_field(chat_message, "created_at")
+            .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+            .map(|time| time.timestamp());
+        let role = match string_field(chat_message, "sender") {
+            Some("human") => Role::User,
+            Some("assistant") => Role::Assistant,
+            other => {
+                builder.warn(format!(
+                    "Skipped a message from unsupported sender '{}'",
+                    other.unwrap_or("unknown")
+                ));
+                continue;
+            }
+        };
+
+        match chat_message.get("content").and_then(Value::as_array) {
+            Some(items) if !items.is_empty() => {
+                for item in items {
+                    import_anthropic_content(item, role.clone(), created, &mut builder);
+                }
+            }
+            _ => {
+                let text = string_field(chat_message, "text").unwrap_or_default();
+                builder.push_text(role.clone(), text, created);
+            }
+        }
+
+        for attachment in chat_message
+            .get("attachments")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let file_name = string_field(attachment, "file_name").unwrap_or("attachment");
+            match string_field(attachment, "extracted_content") {
+                Some(content) if !content.trim().is_empty() => builder.push_text(
+                    role.clone(),
+                    &format!("Attachment {}:\n{}", file_name, content),
+                    created,
+                ),
+                _ => builder.warn(format!(
+                    "Dropped attachment '{}' without text content",
+                    file_name
+                )),
+            }
+        }
+
+        for file in chat_message
+            .get("files")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            builder.warn(format!(
+                "Dropped file '{}', file contents are not part of the export",
+                string_field(file, "file_name").unwrap_or("unknown")
+            ));
+        }
+    }
+
+    Ok(builder.finish(title))
+}
+
+fn import_anthropic_content(
+    item: &Value,
+    role: Role,
+    created: Option<i64>,
+    builder: &mut ConversationBuilder,
+) {
+    match string_field(item, "type").unwrap_or("") {
+        "text" => {
+            let text = string_field(item, "text").unwrap_or_default();
+            builder.push_text(role, text, created);
+        }
+        "tool_use" => {
+            let name = string_field(item, "name").unwrap_or("tool");
+            let arguments = match item.get("input") {
+                Some(input @ Value::Object(_)) => input.clone(),
+                Some(Value::String(input)) => parse_arguments(input),
+                _ => json!({}),
+            };
+            builder.push_tool_request(string_field(item, "id"), name, arguments, created);
+        }
+        "tool_result" => {
+            let output = match item.get("content") {
+                Some(Value::String(text)) => text.clone(),
+                Some(Value::Array(parts)) => parts
+                    .iter()
+                    .filter_map(|part| string_field(part, "text"))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                _ => String::new(),
+            };
+            let is_error = item
+                .get("is_error")
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+            let id = string_field(item, "tool_use_id").or_else(|| string_field(item, "id"));
+            if !builder.push_tool_response(id, output, is_error, created) {
+                builder.warn("Dropped a tool result without a matching tool use".to_string());
+            }
+        }
+        other => builder.warn(format!(
+            "Dropped unsupported '{}' content from a message",
+            other
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chatgpt_node(id: &str, parent: Option<&str>, children: &[&str], message: Value) -> Value {
+        json!({
+            "id": id,
+            "parent": parent,
+            "children": children,
+            "message": message,
+        })
+    }
+
+    fn chatgpt_message(role: &str, parts: Value) -> Value {
+        json!({
+            "author": {"role": role},
+            "create_time": 1700000000.5,
+            "content": {"content_type": "text", "parts": parts},
+            "recipient": "all",
+        })
+    }
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(
+            detect_format(&json!([{"mapping": {}}])),
+            Some(ImportFormat::ChatGpt)
+        );
+        assert_eq!(
+            detect_format(&json!({"chat_messages": []})),
+            Some(ImportFormat::Anthropic)
+        );
+        assert_eq!(detect_format(&json!({"messages": []})), None);
+        assert!(import_conversations(&json!({"messages": []})).is_err());
+    }
+
+    #[test]
+    fn test_chatgpt_follows_current_branch() {
+        let conversation = json!({
+            "title": "Branches",
+            "current_node": "b2",
+            "mapping": {
+                "root": chatgpt_node("root", None, &["sys"], Value::Null),
+                "sys": chatgpt_node("sys", Some("root"), &["u1"], chatgpt_message("system", json!([""]))),
+                "u1": chatgpt_node("u1", Some("sys"), &["b1", "b2"], chatgpt_message("user", json!(["hello"]))),
+                "b1": chatgpt_node("b1", Some("u1"), &[], chatgpt_message("assistant", json!(["first answer"]))),
+                "b2": chatgpt_node("b2", Some("u1"), &[], chatgpt_message("assistant", json!(["second answer"]))),
+            }
+        });
+
+        let imported = import_conversations(&conversation).unwrap();
+        assert_eq!(imported.len(), 1);
+        let conversation = &imported[0];
+        assert_eq!(conversation.title.as_deref(), Some("Branches"));
+        assert_eq!(conversation.messages.len(), 2);
+        assert_eq!(conversation.messages[0].as_concat_text(), "hello");
+        assert_eq!(conversation.messages[0].created, 1700000000);
+        assert_eq!(conversation.messages[1].as_concat_text(), "second answer");
+    }
+
+    #[test]
+    fn test_chatgpt_system_multimodal_and_tools() {
+        let mut tool_call = chatgpt_message("assistant", Value::Null);
+        tool_call["recipient"] = json!("python");
+        tool_call["content"] = json!({"content_type": "code", "text": "print(1)"});
+        let mut tool_output = chatgpt_message("tool", Value::Null);
+        tool_output["author"] = json!({"role": "tool", "name": "python"});
+        tool_output["content"] = json!({"content_type": "execution_output", "text": "1"});
+        let mut image = chatgpt_message(
+            "user",
+            json!([{"content_type": "image_asset_pointer", "asset_pointer": "file-service://x"}, "what is this?"]),
+        );
+        image["content"]["content_type"] = json!("multimodal_text");
+
+        let conversation = json!({
+            "current_node": "a2",
+            "mapping": {
+                "u1": chatgpt_node("u1", None, &["s1"], image),
+                "s1": chatgpt_node("s1", Some("u1"), &["c1"], chatgpt_message("system", json!(["be brief"]))),
+                "c1": chatgpt_node("c1", Some("s1"), &["o1"], tool_call),
+                "o1": chatgpt_node("o1", Some("c1"), &["a2"], tool_output),
+                "a2": chatgpt_node("a2", Some("o1"), &[], chatgpt_message("assistant", json!(["It prints 1"]))),
+            }
+        });
+
+        let imported = &import_conversations(&conversation).unwrap()[0];
+        assert_eq!(imported.warnings.len(), 1);
+        assert!(imported.warnings[0].contains("image_asset_pointer"));
+
+        let messages = &imported.messages;
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0].role, Role::User);
+        assert_eq!(
+            messages[0].as_concat_text(),
+            "what is this?\nSystem: be brief"
+        );
+
+        let request = messages[1].content[0].as_tool_request().unwrap();
+        let call = request.tool_call.as_ref().unwrap();
+        assert_eq!(call.name, "python");
+        assert_eq!(call.arguments, json!({"input": "print(1)"}));
+
+        let response = messages[2].content[0].as_tool_response().unwrap();
+        assert_eq!(response.id, request.id);
+        assert_eq!(messages[3].as_concat_text(), "It prints 1");
+    }
+
+    #[test]
+    fn test_anthropic_tools_attachments_and_empty_messages() {
+        let conversation = json!([{
+            "uuid": "c1",
+            "name": "Weather",
+            "chat_messages": [
+                {
+                    "sender": "human",
+                    "created_at": "2024-05-01T10:00:00.000000Z",
+                    "text": "",
+                    "content": [{"type": "text", "text": "weather in Paris?"}],
+                    "attachments": [{"file_name": "notes.txt", "extracted_content": "bring umbrella"}],
+                    "files": [{"file_name": "photo.png"}]
+                },
+                {
+                    "sender": "assistant",
+                    "text": "",
+                    "content": [
+                        {"type": "text", "text": "Let me check."},
+                        {"type": "tool_use", "id": "toolu_1", "name": "web_search", "input": {"query": "Paris weather"}},
+                        {"type": "tool_result", "tool_use_id": "toolu_1", "content": [{"type": "text", "text": "Rainy"}]},
+                        {"type": "text", "text": "It is rainy."},
+                        {"type": "tool_use", "name": "web_fetch", "input": {"url": "https://example.com"}}
+                    ]
+                },
+                {"sender": "human", "text": "", "content": []}
+            ]
+        }]);
+
+        let imported = &import_conversations(&conversation).unwrap()[0];
+        assert_eq!(imported.title.as_deref(), Some("Weather"));
+        assert_eq!(imported.warnings.len(), 1);
+        assert!(imported.warnings[0].contains("photo.png"));
+
+        let messages = &imported.messages;
+        assert_eq!(messages.len(), 5);
+        assert_eq!(
+            messages[0].as_concat_text(),
+            "weather in Paris?\nAttachment notes.txt:\nbring umbrella"
+        );
+        assert_eq!(messages[0].created, 1714557600);
+
+        assert_eq!(messages[1].role, Role::Assistant);
+        assert!(messages[1].get_tool_request_ids().contains("toolu_1"));
+        assert!(messages[2].get_tool_response_ids().contains("toolu_1"));
+        assert_eq!(messages[3].as_concat_text(), "It is rainy.");
+
+        // The unanswered tool use gets a synthesized error result
+        let request_id = messages[3].content[1].as_tool_request().unwrap().id.clone();
+        let response = messages[4].content[0].as_tool_response().unwrap();
+        assert_eq!(response.id, request_id);
+        assert!(response.tool_result.is_err());
+    }
+}
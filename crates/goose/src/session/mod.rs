@@ -1,12 +1,21 @@
+pub mod analytics;
+pub mod blobs;
+pub mod encryption;
+pub mod import;
 pub mod info;
+pub mod search;
+pub mod stats;
 pub mod storage;
 
 // Re-export common session types and functions
 pub use storage::{
-    ensure_session_dir, generate_description, generate_description_with_schedule_id,
-    generate_session_id, get_most_recent_session, get_path, list_sessions, persist_messages,
-    persist_messages_with_schedule_id, read_messages, read_metadata, update_metadata, Identifier,
-    SessionMetadata,
+    compact_session, delete_session, ensure_session_dir, generate_description,
+    generate_description_with_schedule_id, generate_session_id, get_most_recent_session, get_path,
+    list_sessions, persist_messages, persist_messages_with_schedule_id, read_messages,
+    read_metadata, update_metadata, CompactionReport, FsyncPolicy, Identifier, SessionMetadata,
 };
 
+pub use analytics::{AnalyticsRecorder, AnalyticsReport, SessionAnalytics};
 pub use info::{get_session_info, SessionInfo};
+pub use search::{search_sessions, SearchFilter, SearchHit, SearchRole};
+pub use stats::{SessionStats, ToolCallTiming, TurnStats};
@@ -0,0 +1,705 @@
+//! Full text search across saved sessions
+//!
+//! Sessions are indexed into an inverted index kept next to them in the session directory.
+//! Writing a session appends the words of the written messages to an update log, see
+//! [`record_write`], which the next search folds into the index, so a search never reads the
+//! sessions that didn't change. An index that can't be read, was written by another version or
+//! has an unreadable update log is rebuilt from the session files.
+
+use crate::message::{Message, MessageContent};
+use crate::session::storage::{ensure_session_dir, is_encrypted, read_messages_with_truncation};
+use anyhow::Result;
+use mcp_core::role::Role;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Bump when the layout of the index or the way text is tokenized changes
+const INDEX_VERSION: u32 = 2;
+const INDEX_FILE_NAME: &str = "search_index.json";
+/// Updates written since the index was saved, one [`IndexUpdate`] per line
+const UPDATE_LOG_FILE_NAME: &str = "search_index.log";
+
+/// Characters of context kept before the first match in a snippet
+const SNIPPET_CONTEXT: usize = 40;
+/// Maximum length of a snippet, not counting the ellipses around it
+const SNIPPET_LENGTH: usize = 160;
+
+/// Who a matching message came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchRole {
+    User,
+    Assistant,
+    /// Results of tool calls, which are sent back to the model as user messages
+    Tool,
+}
+
+impl SearchRole {
+    fn of(message: &Message) -> Self {
+        let is_tool_result = message
+            .content
+            .iter()
+            .any(|content| matches!(content, MessageContent::ToolResponse(_)));
+        match message.role {
+            Role::Assistant => SearchRole::Assistant,
+            Role::User if is_tool_result => SearchRole::Tool,
+            Role::User => SearchRole::User,
+        }
+    }
+}
+
+impl FromStr for SearchRole {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "user" => Ok(SearchRole::User),
+            "assistant" => Ok(SearchRole::Assistant),
+            "tool" => Ok(SearchRole::Tool),
+            _ => Err(anyhow::anyhow!(
+                "Unknown role '{}', expected user, assistant or tool",
+                s
+            )),
+        }
+    }
+}
+
+/// Restrictions on the messages a search returns
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilter {
+    /// Only messages created at or after this unix timestamp
+    pub since: Option<i64>,
+    /// Only messages created before this unix timestamp
+    pub until: Option<i64>,
+    /// Only messages from this role
+    pub role: Option<SearchRole>,
+    /// Maximum number of hits, the most recent messages are kept
+    pub limit: Option<usize>,
+}
+
+impl SearchFilter {
+    fn matches(&self, message: &IndexedMessage) -> bool {
+        self.since.is_none_or(|since| message.created >= since)
+            && self.until.is_none_or(|until| message.created < until)
+            && self.role.is_none_or(|role| message.role == role)
+    }
+}
+
+/// A message that contains every term of a search
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct SearchHit {
+    /// ID of the session, which is the file name without extension
+    pub session_id: String,
+    /// Position of the message in the session
+    pub message_index: usize,
+    pub role: SearchRole,
+    /// Unix timestamp of the message
+    pub created: i64,
+    /// The text around the first match
+    pub snippet: String,
+    /// Byte ranges of the matched terms within the snippet
+    pub highlights: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SearchIndex {
+    version: u32,
+    sessions: HashMap<String, IndexedSession>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IndexedSession {
+    messages: Vec<IndexedMessage>,
+    /// The messages each term occurs in, in ascending order
+    terms: HashMap<String, Vec<usize>>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct IndexedMessage {
+    role: SearchRole,
+    created: i64,
+}
+
+/// A message as it goes into the index
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexEntry {
+    #[serde(flatten)]
+    message: IndexedMessage,
+    words: BTreeSet<String>,
+}
+
+/// A change to a session, logged when it's written and applied at the next search
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "update", rename_all = "snake_case")]
+enum IndexUpdate {
+    /// The session holds these messages from the message at `from` on, and nothing after them
+    Written {
+        session_id: String,
+        from: usize,
+        messages: Vec<IndexEntry>,
+    },
+    Removed {
+        session_id: String,
+    },
+}
+
+/// Lowercased words of a text with their byte ranges
+fn tokens(text: &str) -> impl Iterator<Item = (usize, usize, String)> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(move |word| {
+            let start = word.as_ptr() as usize - text.as_ptr() as usize;
+            (start, start + word.len(), word.to_lowercase())
+        })
+}
+
+/// The text of a message that can be searched: its text and the text output of tool calls
+fn searchable_text(message: &Message) -> String {
+    let mut parts = Vec::new();
+    for content in &message.content {
+        match content {
+            MessageContent::Text(text) => parts.push(text.text.as_str()),
+            MessageContent::ToolResponse(response) => {
+                if let Ok(contents) = &response.tool_result {
+                    parts.extend(contents.iter().filter_map(|content| content.as_text()));
+                }
+            }
+            _ => {}
+        }
+    }
+    parts.join("\n")
+}
+
+impl IndexEntry {
+    fn of(message: &Message) -> Self {
+        Self {
+            message: IndexedMessage {
+                role: SearchRole::of(message),
+                created: message.created,
+            },
+            words: tokens(&searchable_text(message))
+                .map(|(_, _, word)| word)
+                .collect(),
+        }
+    }
+}
+
+impl IndexedSession {
+    fn new(messages: &[Message]) -> Self {
+        let mut session = Self::default();
+        session.write(0, messages.iter().map(IndexEntry::of).collect());
+        session
+    }
+
+    /// Replace the messages from the message at `from` on
+    fn write(&mut self, from: usize, entries: Vec<IndexEntry>) {
+        if from < self.messages.len() {
+            self.messages.truncate(from);
+            self.terms.retain(|_, posting| {
+                posting.retain(|index| *index < from);
+                !posting.is_empty()
+            });
+        }
+        for entry in entries {
+            let index = self.messages.len();
+            for word in entry.words {
+                self.terms.entry(word).or_default().push(index);
+            }
+            self.messages.push(entry.message);
+        }
+    }
+
+    /// The messages containing every term
+    fn find(&self, terms: &[String]) -> Vec<usize> {
+        let mut postings = Vec::with_capacity(terms.len());
+        for term in terms {
+            match self.terms.get(term) {
+                Some(posting) => postings.push(posting),
+                None => return Vec::new(),
+            }
+        }
+        postings.sort_by_key(|posting| posting.len());
+
+        let Some((shortest, rest)) = postings.split_first() else {
+            return Vec::new();
+        };
+        shortest
+            .iter()
+            .copied()
+            .filter(|index| {
+                rest.iter()
+                    .all(|posting| posting.binary_search(index).is_ok())
+            })
+            .collect()
+    }
+}
+
+impl SearchIndex {
+    /// Load the index of a session directory, none if it's missing or unusable
+    fn load(session_dir: &Path) -> Option<Self> {
+        let path = session_dir.join(INDEX_FILE_NAME);
+        let contents = fs::read_to_string(&path).ok()?;
+        match serde_json::from_str::<SearchIndex>(&contents) {
+            Ok(index) if index.version == INDEX_VERSION => Some(index),
+            Ok(index) => {
+                tracing::info!(
+                    "Rebuilding session search index of version {}",
+                    index.version
+                );
+                None
+            }
+            Err(e) => {
+                tracing::warn!("Rebuilding unreadable session search index: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Index every session in a directory
+    fn build(session_dir: &Path) -> Result<Self> {
+        let mut index = Self {
+            version: INDEX_VERSION,
+            sessions: HashMap::new(),
+        };
+        for (id, path) in sessions_in(session_dir)? {
+            index.reindex(&id, &path);
+        }
+        Ok(index)
+    }
+
+    /// Index a session from its file
+    fn reindex(&mut self, id: &str, path: &Path) {
+        // The index is stored in clear, so the text of encrypted sessions is left out of it
+        if is_encrypted(path) {
+            self.sessions.remove(id);
+            return;
+        }
+        match read_messages_with_truncation(path, None) {
+            Ok(messages) => {
+                self.sessions
+                    .insert(id.to_string(), IndexedSession::new(&messages));
+            }
+            Err(e) => {
+                tracing::warn!("Skipping session {} in search index: {}", id, e);
+                self.sessions.remove(id);
+            }
+        }
+    }
+
+    fn apply(&mut self, update: IndexUpdate, session_dir: &Path) {
+        match update {
+            IndexUpdate::Written {
+                session_id,
+                from,
+                messages,
+            } => {
+                let session = self.sessions.entry(session_id.clone()).or_default();
+                if from <= session.messages.len() {
+                    session.write(from, messages);
+                } else {
+                    // Writes the index missed, the session file has them all
+                    let path = session_dir.join(format!("{}.jsonl", session_id));
+                    self.reindex(&session_id, &path);
+                }
+            }
+            IndexUpdate::Removed { session_id } => {
+                self.sessions.remove(&session_id);
+            }
+        }
+    }
+
+    /// Atomically replace the index file
+    fn save(&self, session_dir: &Path) -> Result<()> {
+        let path = session_dir.join(INDEX_FILE_NAME);
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_vec(self)?)?;
+        fs::rename(&temp_path, &path).inspect_err(|_| {
+            let _ = fs::remove_file(&temp_path);
+        })?;
+        Ok(())
+    }
+}
+
+/// The index of a session directory with the logged updates applied, or built from the
+/// session files when it's unusable
+fn current_index(session_dir: &Path) -> Result<SearchIndex> {
+    use fs2::FileExt;
+
+    let log_path = session_dir.join(UPDATE_LOG_FILE_NAME);
+    let log = fs::OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(&log_path)?;
+    // Writers wait while the log is applied, so no update is lost when it's cleared
+    log.lock_exclusive()?;
+
+    let updates: Option<Vec<IndexUpdate>> = BufReader::new(&log)
+        .lines()
+        .map(|line| serde_json::from_str(&line.ok()?).ok())
+        .collect();
+    let (index, changed) = match (SearchIndex::load(session_dir), updates) {
+        (Some(mut index), Some(updates)) => {
+            let changed = !updates.is_empty();
+            for update in updates {
+                index.apply(update, session_dir);
+            }
+            (index, changed)
+        }
+        (_, updates) => {
+            if updates.is_none() {
+                tracing::warn!("Rebuilding session search index with an unreadable update log");
+            }
+            (SearchIndex::build(session_dir)?, true)
+        }
+    };
+    if changed {
+        match index.save(session_dir) {
+            Ok(()) => log.set_len(0)?,
+            Err(e) => tracing::warn!("Failed to save session search index: {}", e),
+        }
+    }
+
+    fs2::FileExt::unlock(&log)?;
+    Ok(index)
+}
+
+fn log_update(session_file: &Path, update: impl FnOnce(String) -> IndexUpdate) -> Result<()> {
+    use fs2::FileExt;
+
+    let (Some(session_dir), Some(id)) = (session_file.parent(), session_file.file_stem()) else {
+        return Ok(());
+    };
+    // Without an index, the next search builds one from the session files
+    if !session_dir.join(INDEX_FILE_NAME).exists() {
+        return Ok(());
+    }
+
+    let mut line = serde_json::to_vec(&update(id.to_string_lossy().to_string()))?;
+    line.push(b'\n');
+    let mut log = fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(session_dir.join(UPDATE_LOG_FILE_NAME))?;
+    log.lock_exclusive()?;
+    let written = log.write_all(&line);
+    fs2::FileExt::unlock(&log)?;
+    Ok(written?)
+}
+
+/// Record in the search index that a session holds `messages` from the message at `from` on
+pub(crate) fn record_write(session_file: &Path, from: usize, messages: &[Message]) -> Result<()> {
+    log_update(session_file, |session_id| IndexUpdate::Written {
+        session_id,
+        from,
+        messages: messages.iter().map(IndexEntry::of).collect(),
+    })
+}
+
+/// Record in the search index that a session was deleted, or is no longer indexed
+pub(crate) fn record_removal(session_file: &Path) -> Result<()> {
+    log_update(session_file, |session_id| IndexUpdate::Removed {
+        session_id,
+    })
+}
+
+/// The sessions in a directory by ID
+fn sessions_in(session_dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    Ok(fs::read_dir(session_dir)?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension().is_some_and(|ext| ext == "jsonl") {
+                let id = path.file_stem()?.to_string_lossy().to_string();
+                Some((id, path))
+            } else {
+                None
+            }
+        })
+        .collect())
+}
+
+/// Cut the text around the first match of any term, returning the snippet and the byte
+/// ranges of the matches within it
+fn snippet(text: &str, terms: &[String]) -> (String, Vec<(usize, usize)>) {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let matches: Vec<(usize, usize)> = tokens(&text)
+        .filter(|(_, _, word)| terms.contains(word))
+        .map(|(start, end, _)| (start, end))
+        .collect();
+
+    let first = matches.first().map_or(0, |(start, _)| *start);
+    let mut start = first.saturating_sub(SNIPPET_CONTEXT);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    let mut end = (start + SNIPPET_LENGTH).min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let prefix = if start > 0 { "…" } else { "" };
+    let suffix = if end < text.len() { "…" } else { "" };
+    let highlights = matches
+        .into_iter()
+        .filter(|(match_start, match_end)| *match_start >= start && *match_end <= end)
+        .map(|(match_start, match_end)| {
+            (
+                match_start - start + prefix.len(),
+                match_end - start + prefix.len(),
+            )
+        })
+        .collect();
+
+    (
+        format!("{}{}{}", prefix, &text[start..end], suffix),
+        highlights,
+    )
+}
+
+/// Search the saved sessions for messages containing every word of the query
+///
+/// Hits are ordered from the most recent message to the oldest.
+pub fn search_sessions(query: &str, filter: &SearchFilter) -> Result<Vec<SearchHit>> {
+    search_session_dir(&ensure_session_dir()?, query, filter)
+}
+
+/// Search the sessions in a directory, see [`search_sessions`]
+pub fn search_session_dir(
+    session_dir: &Path,
+    query: &str,
+    filter: &SearchFilter,
+) -> Result<Vec<SearchHit>> {
+    let terms: Vec<String> = tokens(query)
+        .map(|(_, _, word)| word)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let index = current_index(session_dir)?;
+
+    let mut candidates: Vec<(&String, usize, IndexedMessage)> = Vec::new();
+    for (id, indexed) in &index.sessions {
+        for message_index in indexed.find(&terms) {
+            let message = indexed.messages[message_index];
+            if filter.matches(&message) {
+                candidates.push((id, message_index, message));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| (b.2.created, b.0, b.1).cmp(&(a.2.created, a.0, a.1)));
+    if let Some(limit) = filter.limit {
+        candidates.truncate(limit);
+    }
+
+    // Snippets come from the sessions themselves, which are read once each
+    let mut loaded: HashMap<&String, Vec<Message>> = HashMap::new();
+    let mut hits = Vec::with_capacity(candidates.len());
+    for (id, message_index, indexed) in candidates {
+        if !loaded.contains_key(id) {
+            // A session removed without deleting it through goose is still in the index
+            let path = session_dir.join(format!("{}.jsonl", id));
+            loaded.insert(
+                id,
+                read_messages_with_truncation(&path, None).unwrap_or_default(),
+            );
+        }
+        let Some(message) = loaded[id].get(message_index) else {
+            continue;
+        };
+        let (snippet, highlights) = snippet(&searchable_text(message), &terms);
+        hits.push(SearchHit {
+            session_id: id.clone(),
+            message_index,
+            role: indexed.role,
+            created: indexed.created,
+            snippet,
+            highlights,
+        });
+    }
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::storage::{
+        delete_session, persist_messages, save_messages_with_metadata, SessionMetadata,
+    };
+    use mcp_core::tool::ToolCall;
+    use mcp_core::Content;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn message_at(message: Message, created: i64) -> Message {
+        Message { created, ..message }
+    }
+
+    fn write_session(dir: &Path, id: &str, messages: &[Message]) -> Result<()> {
+        let metadata = SessionMetadata::new(dir.to_path_buf());
+        save_messages_with_metadata(&dir.join(format!("{}.jsonl", id)), &metadata, messages)
+    }
+
+    fn debugging_session() -> Vec<Message> {
+        vec![
+            message_at(
+                Message::user().with_text("Why does the server hit a tokio panic on shutdown?"),
+                1_000,
+            ),
+            message_at(
+                Message::assistant()
+                    .with_text("Let me run the tests.")
+                    .with_tool_request(
+                        "call_1",
+                        Ok(ToolCall::new("developer__shell", json!({"command": "cargo test"}))),
+                    ),
+                1_010,
+            ),
+            message_at(
+                Message::user().with_tool_response(
+                    "call_1",
+                    Ok(vec![Content::text(
+                        "thread 'main' panicked: Cannot drop a runtime in a context where blocking is not allowed (tokio)",
+                    )]),
+                ),
+                1_020,
+            ),
+            message_at(
+                Message::assistant().with_text("The Tokio runtime is dropped inside async code."),
+                1_030,
+            ),
+        ]
+    }
+
+    fn ids(hits: &[SearchHit]) -> Vec<(&str, usize)> {
+        hits.iter()
+            .map(|hit| (hit.session_id.as_str(), hit.message_index))
+            .collect()
+    }
+
+    #[test]
+    fn test_search_messages_and_tool_results() -> Result<()> {
+        let dir = tempdir()?;
+        write_session(dir.path(), "debugging", &debugging_session())?;
+        write_session(
+            dir.path(),
+            "unrelated",
+            &[message_at(
+                Message::user().with_text("A panic attack"),
+                2_000,
+            )],
+        )?;
+
+        let hits = search_session_dir(dir.path(), "Tokio", &SearchFilter::default())?;
+        assert_eq!(
+            ids(&hits),
+            vec![("debugging", 3), ("debugging", 2), ("debugging", 0)]
+        );
+        assert_eq!(hits[1].role, SearchRole::Tool);
+        let (start, end) = hits[0].highlights[0];
+        assert_eq!(&hits[0].snippet[start..end], "Tokio");
+
+        // Every word of the query has to match
+        let hits = search_session_dir(dir.path(), "tokio panic", &SearchFilter::default())?;
+        assert_eq!(ids(&hits), vec![("debugging", 0)]);
+        assert_eq!(hits[0].highlights.len(), 2);
+
+        let filter = SearchFilter {
+            role: Some(SearchRole::User),
+            ..Default::default()
+        };
+        let hits = search_session_dir(dir.path(), "panic", &filter)?;
+        assert_eq!(ids(&hits), vec![("unrelated", 0), ("debugging", 0)]);
+
+        let filter = SearchFilter {
+            since: Some(1_010),
+            until: Some(2_000),
+            limit: Some(1),
+            ..Default::default()
+        };
+        let hits = search_session_dir(dir.path(), "tokio", &filter)?;
+        assert_eq!(ids(&hits), vec![("debugging", 3)]);
+
+        assert!(search_session_dir(dir.path(), " ?! ", &SearchFilter::default())?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_index_follows_session_changes() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("debugging.jsonl");
+        let log_path = dir.path().join(UPDATE_LOG_FILE_NAME);
+        let mut messages = debugging_session();
+        write_session(dir.path(), "debugging", &messages)?;
+        assert!(search_session_dir(dir.path(), "deadlock", &SearchFilter::default())?.is_empty());
+        assert!(dir.path().join(INDEX_FILE_NAME).exists());
+
+        // Appended messages are logged, and folded into the index by the next search
+        messages.push(Message::user().with_text("Could it also deadlock?"));
+        persist_messages(&path, &messages, None).await?;
+        assert!(fs::metadata(&log_path)?.len() > 0);
+        let hits = search_session_dir(dir.path(), "deadlock", &SearchFilter::default())?;
+        assert_eq!(ids(&hits), vec![("debugging", 4)]);
+        assert_eq!(fs::metadata(&log_path)?.len(), 0);
+
+        // Rewritten sessions replace what was indexed
+        write_session(dir.path(), "debugging", &messages[..2])?;
+        assert!(search_session_dir(dir.path(), "deadlock", &SearchFilter::default())?.is_empty());
+
+        delete_session(&path)?;
+        assert!(search_session_dir(dir.path(), "tokio", &SearchFilter::default())?.is_empty());
+        let index = SearchIndex::load(dir.path()).unwrap();
+        assert!(index.sessions.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unusable_index_is_rebuilt() -> Result<()> {
+        let dir = tempdir()?;
+        write_session(dir.path(), "debugging", &debugging_session())?;
+        let index_path = dir.path().join(INDEX_FILE_NAME);
+
+        fs::write(&index_path, "{\"version\": 1, \"sessions\": {\"debug")?;
+        let hits = search_session_dir(dir.path(), "tokio", &SearchFilter::default())?;
+        assert_eq!(hits.len(), 3);
+
+        // An index from another version is discarded even though it parses
+        let stale = json!({ "version": INDEX_VERSION + 1, "sessions": {} });
+        fs::write(&index_path, stale.to_string())?;
+        let hits = search_session_dir(dir.path(), "tokio", &SearchFilter::default())?;
+        assert_eq!(hits.len(), 3);
+        assert_eq!(
+            SearchIndex::load(dir.path()).unwrap().version,
+            INDEX_VERSION
+        );
+
+        // So is an index whose updates can't be read
+        fs::write(dir.path().join(UPDATE_LOG_FILE_NAME), "{\"update\": \"writ")?;
+        let hits = search_session_dir(dir.path(), "tokio", &SearchFilter::default())?;
+        assert_eq!(hits.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_snippet() {
+        let terms = vec!["panic".to_string()];
+        let text = format!("{} the   tokio\npanic happened here", "word ".repeat(20));
+        let (cut, highlights) = snippet(&text, &terms);
+        assert!(cut.starts_with('…'));
+        assert!(cut.contains("tokio panic happened here"));
+        assert_eq!(highlights.len(), 1);
+        let (start, end) = highlights[0];
+        assert_eq!(&cut[start..end], "panic");
+
+        let (cut, highlights) = snippet("short panic", &terms);
+        assert_eq!(cut, "short panic");
+        assert_eq!(highlights, vec![(6, 11)]);
+    }
+}
@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+fn duration_ms(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+/// Execution time of a single tool call within a turn
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ToolCallTiming {
+    /// ID of the tool request
+    pub id: String,
+    /// Name of the tool that was called
+    pub name: String,
+    /// Time from the start of tool execution until the result was available
+    pub duration_ms: u64,
+}
+
+/// Latency measurements for a single reply turn of the agent
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TurnStats {
+    /// Time until the first response bytes of the turn arrived from the provider.
+    /// Providers that do not stream report the time until the response headers arrived.
+    pub time_to_first_token_ms: Option<u64>,
+    /// Number of provider calls made during the turn
+    pub provider_calls: usize,
+    /// Total time spent waiting on the provider during the turn
+    pub provider_latency_ms: u64,
+    /// Execution time of each tool call made during the turn
+    pub tool_calls: Vec<ToolCallTiming>,
+    /// Time from the start of the turn until the reply finished
+    pub turn_duration_ms: u64,
+}
+
+impl TurnStats {
+    /// Record a completed provider call and its time to first byte, if the provider reported one
+    pub fn record_provider_call(&mut self, latency: Duration, time_to_first_byte_ms: Option<u64>) {
+        if self.provider_calls == 0 {
+            self.time_to_first_token_ms = time_to_first_byte_ms;
+        }
+        self.provider_calls += 1;
+        self.provider_latency_ms += duration_ms(latency);
+    }
+
+    /// Record the execution time of a tool call
+    pub fn record_tool_call(&mut self, id: String, name: String, duration: Duration) {
+        self.tool_calls.push(ToolCallTiming {
+            id,
+            name,
+            duration_ms: duration_ms(duration),
+        });
+    }
+
+    /// Record the end-to-end duration of the turn
+    pub fn finish(&mut self, turn_duration: Duration) {
+        self.turn_duration_ms = duration_ms(turn_duration);
+    }
+
+    /// Total time spent executing tools during the turn
+    pub fn tool_time_ms(&self) -> u64 {
+        self.tool_calls.iter().map(|call| call.duration_ms).sum()
+    }
+}
+
+/// Aggregated execution time of one tool across a session
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ToolStats {
+    pub name: String,
+    pub calls: usize,
+    pub total_ms: u64,
+    pub max_ms: u64,
+}
+
+/// Latency statistics aggregated over all turns of a session
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SessionStats {
+    pub turns: usize,
+    pub provider_calls: usize,
+    pub total_turn_duration_ms: u64,
+    pub total_provider_latency_ms: u64,
+    pub average_turn_duration_ms: Option<u64>,
+    pub average_provider_latency_ms: Option<u64>,
+    pub average_time_to_first_token_ms: Option<u64>,
+    /// Per-tool totals, slowest first
+    pub tools: Vec<ToolStats>,
+}
+
+impl SessionStats {
+    pub fn from_turns(turns: &[TurnStats]) -> Self {
+        let average = |total: u64, count: usize| (count > 0).then(|| total / count as u64);
+
+        let provider_calls = turns.iter().map(|turn| turn.provider_calls).sum();
+        let total_turn_duration_ms = turns.iter().map(|turn| turn.turn_duration_ms).sum();
+        let total_provider_latency_ms = turns.iter().map(|turn| turn.provider_latency_ms).sum();
+
+        let first_tokens: Vec<u64> = turns
+            .iter()
+            .filter_map(|turn| turn.time_to_first_token_ms)
+            .collect();
+
+        let mut tools: HashMap<&str, ToolStats> = HashMap::new();
+        for call in turns.iter().flat_map(|turn| &turn.tool_calls) {
+            let stats = tools.entry(&call.name).or_insert_with(|| ToolStats {
+                name: call.name.clone(),
+                calls: 0,
+                total_ms: 0,
+                max_ms: 0,
+            });
+            stats.calls += 1;
+            stats.total_ms += call.duration_ms;
+            stats.max_ms = stats.max_ms.max(call.duration_ms);
+        }
+        let mut tools: Vec<ToolStats> = tools.into_values().collect();
+        tools.sort_by(|a, b| b.total_ms.cmp(&a.total_ms).then(a.name.cmp(&b.name)));
+
+        Self {
+            turns: turns.len(),
+            provider_calls,
+            total_turn_duration_ms,
+            total_provider_latency_ms,
+            average_turn_duration_ms: average(total_turn_duration_ms, turns.len()),
+            average_provider_latency_ms: average(total_provider_latency_ms, provider_calls),
+            average_time_to_first_token_ms: average(first_tokens.iter().sum(), first_tokens.len()),
+            tools,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_turn_stats_records_first_token_from_first_call() {
+        let mut stats = TurnStats::default();
+        stats.record_provider_call(Duration::from_millis(300), Some(120));
+        stats.record_provider_call(Duration::from_millis(200), Some(80));
+        stats.record_tool_call("1".into(), "shell".into(), Duration::from_millis(50));
+        stats.finish(Duration::from_millis(600));
+
+        assert_eq!(stats.time_to_first_token_ms, Some(120));
+        assert_eq!(stats.provider_calls, 2);
+        assert_eq!(stats.provider_latency_ms, 500);
+        assert_eq!(stats.tool_time_ms(), 50);
+        assert_eq!(stats.turn_duration_ms, 600);
+    }
+
+    #[test]
+    fn test_session_stats_from_turns() {
+        let tool = |name: &str, duration_ms| ToolCallTiming {
+            id: "id".to_string(),
+            name: name.to_string(),
+            duration_ms,
+        };
+        let turns = vec![
+            TurnStats {
+                time_to_first_token_ms: Some(100),
+                provider_calls: 2,
+                provider_latency_ms: 800,
+                tool_calls: vec![tool("shell", 40), tool("read", 10)],
+                turn_duration_ms: 900,
+            },
+            TurnStats {
+                time_to_first_token_ms: None,
+                provider_calls: 2,
+                provider_latency_ms: 400,
+                tool_calls: vec![tool("shell", 60)],
+                turn_duration_ms: 500,
+            },
+        ];
+
+        let stats = SessionStats::from_turns(&turns);
+        assert_eq!(stats.turns, 2);
+        assert_eq!(stats.provider_calls, 4);
+        assert_eq!(stats.average_turn_duration_ms, Some(700));
+        assert_eq!(stats.average_provider_latency_ms, Some(300));
+        assert_eq!(stats.average_time_to_first_token_ms, Some(100));
+        assert_eq!(
+            stats.tools,
+            vec![
+                ToolStats {
+                    name: "shell".to_string(),
+                    calls: 2,
+                    total_ms: 100,
+                    max_ms: 60,
+                },
+                ToolStats {
+                    name: "read".to_string(),
+                    calls: 1,
+                    total_ms: 10,
+                    max_ms: 10,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_session_stats_empty() {
+        let stats = SessionStats::from_turns(&[]);
+        assert_eq!(stats.turns, 0);
+        assert_eq!(stats.average_turn_duration_ms, None);
+        assert!(stats.tools.is_empty());
+    }
+}
@@ -5,17 +5,29 @@
 // - Backup creation
 // Additional debug logging can be added if needed for troubleshooting.
 
+use crate::agents::GuardrailRewrite;
+use crate::config::Config;
+use crate::determinism;
 use crate::message::Message;
-use crate::providers::base::Provider;
+use crate::providers::base::{Provider, UsageTotals};
+use crate::session::analytics::SessionAnalytics;
+use crate::session::blobs;
+use crate::session::encryption::{self, EncryptionHeader, KeySource, SessionKey};
+use crate::session::search;
+use crate::session::stats::TurnStats;
+use crate::utils::{safe_truncate, truncate_to_char_boundary};
 use anyhow::Result;
 use chrono::Local;
 use etcetera::{choose_app_strategy, AppStrategy, AppStrategyArgs};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::hash::{Hash, Hasher};
 use std::io::{self, BufRead, Write};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock, Mutex};
 use utoipa::ToSchema;
 
 // Security limits
@@ -31,6 +43,8 @@ fn get_home_dir() -> PathBuf {
 }
 
 /// Metadata for a session, stored as the first line in the session file
+///
+/// Updates made between full rewrites of the file are kept in a `.meta.json` file next to it.
 #[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct SessionMetadata {
     /// Working directory for the session
@@ -54,6 +68,24 @@ pub struct SessionMetadata {
     pub accumulated_input_tokens: Option<i32>,
     /// The number of output tokens used in the session. Accumulated across all messages.
     pub accumulated_output_tokens: Option<i32>,
+    /// Usage accumulated across all messages, per model
+    #[serde(default, skip_serializing_if = "UsageTotals::is_empty")]
+    #[schema(value_type = Object)]
+    pub usage_by_model: UsageTotals,
+    /// Latency measurements for each reply turn in the session
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub turn_stats: Vec<TurnStats>,
+    /// Model responses that guardrails replaced, with the original response
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub guardrail_rewrites: Vec<GuardrailRewrite>,
+    /// Tool calls and model requests of the session, for `goose stats`
+    #[serde(default, skip_serializing_if = "SessionAnalytics::is_empty")]
+    #[schema(value_type = Object)]
+    pub analytics: SessionAnalytics,
+    /// How the key of an encrypted session is stored, none when messages are stored in clear
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub encryption: Option<EncryptionHeader>,
 }
 
 // Custom deserializer to handle old sessions without working_dir
@@ -73,7 +105,17 @@ impl<'de> Deserialize<'de> for SessionMetadata {
             accumulated_total_tokens: Option<i32>,
             accumulated_input_tokens: Option<i32>,
             accumulated_output_tokens: Option<i32>,
+            #[serde(default)]
+            usage_by_model: UsageTotals,
             working_dir: Option<PathBuf>,
+            #[serde(default)]
+            turn_stats: Vec<TurnStats>,
+            #[serde(default)]
+            guardrail_rewrites: Vec<GuardrailRewrite>,
+            #[serde(default)]
+            analytics: SessionAnalytics,
+            #[serde(default)]
+            encryption: Option<EncryptionHeader>,
         }
 
         let helper = Helper::deserialize(deserializer)?;
@@ -94,7 +136,12 @@ impl<'de> Deserialize<'de> for SessionMetadata {
             accumulated_total_tokens: helper.accumulated_total_tokens,
             accumulated_input_tokens: helper.accumulated_input_tokens,
             accumulated_output_tokens: helper.accumulated_output_tokens,
+            usage_by_model: helper.usage_by_model,
             working_dir,
+            turn_stats: helper.turn_stats,
+            guardrail_rewrites: helper.guardrail_rewrites,
+            analytics: helper.analytics,
+            encryption: helper.encryption,
         })
     }
 }
@@ -119,6 +166,11 @@ impl SessionMetadata {
             accumulated_total_tokens: None,
             accumulated_input_tokens: None,
             accumulated_output_tokens: None,
+            usage_by_model: UsageTotals::new(),
+            turn_stats: Vec::new(),
+            guardrail_rewrites: Vec::new(),
+            analytics: SessionAnalytics::default(),
+            encryption: None,
         }
     }
 }
@@ -129,6 +181,157 @@ impl Default for SessionMetadata {
     }
 }
 
+/// When session writes are flushed to disk, configured with GOOSE_SESSION_FSYNC
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FsyncPolicy {
+    /// Sync every write, so a crash never loses a persisted turn
+    #[default]
+    Always,
+    /// Leave flushing to the operating system, trading durability for write latency
+    Never,
+}
+
+impl FsyncPolicy {
+    pub fn from_config() -> Self {
+        Config::global()
+            .get_param("GOOSE_SESSION_FSYNC")
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn should_sync(&self) -> bool {
+        matches!(self, FsyncPolicy::Always)
+    }
+}
+
+/// The result of compacting a session file with [`compact_session`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CompactionReport {
+    pub messages: usize,
+    pub removed_blobs: usize,
+    pub freed_bytes: u64,
+}
+
+/// What this process last wrote to a session file, used to append new messages
+/// instead of rewriting the whole history
+#[derive(Debug, Clone, Copy)]
+struct JournalState {
+    message_count: usize,
+    file_len: u64,
+    /// Hash over every persisted message, in order
+    prefix_hash: u64,
+}
+
+static JOURNALS: LazyLock<Mutex<HashMap<PathBuf, JournalState>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Extend a running hash with the serialized messages, so a change to any of them changes it.
+/// Extending by `a` and then by `b` gives the same hash as extending by both at once.
+fn extend_hash(mut hash: u64, messages: &[Message]) -> u64 {
+    for message in messages {
+        let mut hasher = DefaultHasher::new();
+        hash.hash(&mut hasher);
+        serde_json::to_string(message)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        hash = hasher.finish();
+    }
+    hash
+}
+
+impl JournalState {
+    fn new(messages: &[Message], file_len: u64) -> Self {
+        Self {
+            message_count: messages.len(),
+            file_len,
+            prefix_hash: extend_hash(0, messages),
+        }
+    }
+
+    /// The state after appending `messages`, hashing only the new messages
+    fn appended(self, messages: &[Message], file_len: u64) -> Self {
+        Self {
+            message_count: self.message_count + messages.len(),
+            file_len,
+            prefix_hash: extend_hash(self.prefix_hash, messages),
+        }
+    }
+
+    /// Whether `messages` extends what was written, with the file unchanged since.
+    /// Histories that were truncated, summarized or edited change a persisted message
+    /// and fall back to a full rewrite.
+    fn is_extended_by(&self, messages: &[Message], file_len: u64) -> bool {
+        self.message_count > 0
+            && self.file_len == file_len
+            && messages.len() >= self.message_count
+            && extend_hash(0, &messages[..self.message_count]) == self.prefix_hash
+    }
+}
+
+/// The file holding metadata updates made since the session file was last rewritten
+fn metadata_sidecar(session_file: &Path) -> PathBuf {
+    session_file.with_extension("meta.json")
+}
+
+/// The directory holding tool results of a session that were too large to return at once
+pub fn tool_results_dir(session_file: &Path) -> PathBuf {
+    session_file.with_extension("results")
+}
+
+/// The journal of the turn in progress in a session, see [`crate::agents::TurnJournal`]. It
+/// doesn't end in `.jsonl`, so it is never taken for a session.
+pub fn turn_journal_path(session_file: &Path) -> PathBuf {
+    session_file.with_extension("turn")
+}
+
+/// The encryption header in the first line of a session file, none for sessions in clear
+fn read_encryption_header(session_file: &Path) -> Result<Option<EncryptionHeader>> {
+    #[derive(Deserialize)]
+    struct FirstLine {
+        #[serde(default)]
+        encryption: Option<EncryptionHeader>,
+    }
+
+    let file = match fs::File::open(session_file) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut first_line = String::new();
+    io::BufReader::new(file).read_line(&mut first_line)?;
+    Ok(serde_json::from_str::<FirstLine>(&first_line)
+        .ok()
+        .and_then(|line| line.encryption))
+}
+
+/// Whether the messages of a session file are encrypted
+pub fn is_encrypted(session_file: &Path) -> bool {
+    read_encryption_header(session_file).is_ok_and(|header| header.is_some())
+}
+
+/// The encryption a session file is written with: the key it already has, or a new one
+/// when GOOSE_SESSION_ENCRYPTION is set
+fn session_encryption(
+    secure_path: &Path,
+    metadata: &SessionMetadata,
+) -> Result<Option<(EncryptionHeader, SessionKey)>> {
+    let header = match &metadata.encryption {
+        Some(header) => Some(header.clone()),
+        None => match read_encryption_header(secure_path)? {
+            Some(header) => Some(header),
+            None => KeySource::from_config()
+                .map(encryption::create_header)
+                .transpose()?,
+        },
+    };
+    header
+        .map(|header| {
+            let key = encryption::unlock(&header)?;
+            Ok((header, key))
+        })
+        .transpose()
+}
+
 // The single app name used for all Goose applications
 const APP_NAME: &str = "goose";
 
@@ -326,8 +529,12 @@ pub fn ensure_session_dir() -> Result<PathBuf> {
 
 /// Get the path to the most recently modified session file
 pub fn get_most_recent_session() -> Result<PathBuf> {
-    let session_dir = ensure_session_dir()?;
-    let mut entries = fs::read_dir(&session_dir)?
+    most_recent_session_in(&ensure_session_dir()?)
+}
+
+/// The most recently modified session file in a directory
+fn most_recent_session_in(session_dir: &Path) -> Result<PathBuf> {
+    let mut entries = fs::read_dir(session_dir)?
         .filter_map(|entry| entry.ok())
         .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "jsonl"))
         .collect::<Vec<_>>();
@@ -373,7 +580,10 @@ pub fn list_sessions() -> Result<Vec<(String, PathBuf)>> {
 
 /// Generate a session ID using timestamp format (yyyymmdd_hhmmss)
 pub fn generate_session_id() -> String {
-    Local::now().format("%Y%m%d_%H%M%S").to_string()
+    determinism::now()
+        .with_timezone(&Local)
+        .format("%Y%m%d_%H%M%S")
+        .to_string()
 }
 
 /// Read messages from a session file with corruption recovery
@@ -456,6 +666,7 @@ pub fn read_messages_with_truncation(
     let mut corrupted_lines = Vec::new();
     let mut line_number = 1;
     let mut message_count = 0;
+    let mut key = None;
 
     // Read the first line as metadata or create default if empty/missing
     if let Some(line_result) = lines.next() {
@@ -468,8 +679,11 @@ pub fn read_messages_with_truncation(
                 }
 
                 // Try to parse as metadata, but if it fails, treat it as a message
-                if let Ok(_metadata) = serde_json::from_str::<SessionMetadata>(&line) {
+                if let Ok(metadata) = serde_json::from_str::<SessionMetadata>(&line) {
                     // Metadata successfully parsed, continue with the rest of the lines as messages
+                    if let Some(header) = &metadata.encryption {
+                        key = Some(encryption::unlock(header)?);
+                    }
                 } else {
                     // This is not metadata, it's a message
                     match parse_message_with_truncation(&line, max_content_size) {
@@ -537,6 +751,13 @@ pub fn read_messages_with_truncation(
                     continue;
                 }
 
+                // Encrypted lines that fail to open are not recovered, they were tampered with
+                let line = match &key {
+                    Some(key) => encryption::open_message(&line, message_count, key)
+                        .map_err(|e| anyhow::anyhow!("Encrypted session can't be read: {}", e))?,
+                    None => line,
+                };
+
                 match parse_message_with_truncation(&line, max_content_size) {
                     Ok(message) => {
                         messages.push(message);
@@ -604,12 +825,15 @@ pub fn read_messages_with_truncation(
 
         // Log details about corrupted lines (with limited detail for security)
         for (num, line) in &corrupted_lines {
-            let preview = if line.len() > 50 {
-                format!("{}... (truncated)", &line[..50])
-            } else {
-                line.clone()
-            };
-            tracing::debug!("Corrupted line {}: {}", num, preview);
+            tracing::debug!("Corrupted line {}: {}", num, safe_truncate(line, 50));
+        }
+    }
+
+    // Load images that were stored separately from the message lines
+    let blob_dir = blobs::blob_dir(session_file);
+    if blob_dir.exists() {
+        for message in &mut messages {
+            blobs::resolve_blobs(message, &blob_dir);
         }
     }
 
@@ -675,19 +899,29 @@ fn truncate_message_content_in_place(message: &mut Message, max_content_size: us
     use crate::message::MessageContent;
     use mcp_core::{Content, ResourceContents};
 
+    // Where the text before the current content was cut, citations past it are dropped
+    let mut cut_at = None;
     for content in &mut message.content {
+        let text_cut_at = cut_at.take();
         match content {
             MessageContent::Text(text_content) => {
                 if text_content.text.len() > max_content_size {
+                    let kept = truncate_to_char_boundary(&text_content.text, max_content_size);
+                    cut_at = Some(kept.len());
                     let truncated = format!(
                         "{}\n\n[... content truncated during session loading from {} to {} characters ...]",
-                        &text_content.text[..max_content_size.min(text_content.text.len())],
+                        kept,
                         text_content.text.len(),
                         max_content_size
                     );
                     text_content.text = truncated;
                 }
             }
+            MessageContent::Citations(citations) => {
+                if let Some(cut) = text_cut_at {
+                    citations.citations.retain(|citation| citation.end <= cut);
+                }
+            }
             MessageContent::ToolResponse(tool_response) => {
                 if let Ok(ref mut result) = tool_response.tool_result {
                     for content_item in result {
@@ -696,13 +930,24 @@ fn truncate_message_content_in_place(message: &mut Message, max_content_size: us
                                 if text_content.text.len() > max_content_size {
                                     let truncated = format!(
                                         "{}\n\n[... tool response truncated during session loading from {} to {} characters ...]",
-                                        &text_content.text[..max_content_size.min(text_content.text.len())],
+                                        truncate_to_char_boundary(&text_content.text, max_content_size),
                                         text_content.text.len(),
                                         max_content_size
                                     );
                                     text_content.text = truncated;
                                 }
                             }
+                            Content::Result(ref mut result) => {
+                                if result.text.len() > max_content_size {
+                                    let truncated = format!(
+                                        "{}\n\n[... tool response truncated during session loading from {} to {} characters ...]",
+                                        truncate_to_char_boundary(&result.text, max_content_size),
+                                        result.text.len(),
+                                        max_content_size
+                                    );
+                                    result.text = truncated;
+                                }
+                            }
                             Content::Resource(ref mut resource_content) => {
                                 if let ResourceContents::TextResourceContents { text, .. } =
                                     &mut resource_content.resource
@@ -710,7 +955,7 @@ fn truncate_message_content_in_place(message: &mut Message, max_content_size: us
                                     if text.len() > max_content_size {
                                         let truncated = format!(
                                             "{}\n\n[... resource content truncated during session loading from {} to {} characters ...]",
-                                            &text[..max_content_size.min(text.len())],
+                                            truncate_to_char_boundary(text, max_content_size),
                                             text.len(),
                                             max_content_size
                                         );
@@ -750,11 +995,7 @@ fn attempt_corruption_recovery(json_str: &str, max_content_size: Option<usize>)
 
     // Strategy 4: Create a placeholder message with the raw content
     println!("[SESSION] All recovery strategies failed, creating placeholder message");
-    let preview = if json_str.len() > 200 {
-        format!("{}...", &json_str[..200])
-    } else {
-        json_str.to_string()
-    };
+    let preview = safe_truncate(json_str, 200);
 
     Ok(Message::user().with_text(format!(
         "[RECOVERED FROM CORRUPTED LINE]\nOriginal content preview: {}\n\n[This message was recovered from a corrupted session file line. The original data may be incomplete.]",
@@ -968,7 +1209,7 @@ fn truncate_json_string(json_str: &str, max_content_size: usize) -> String {
             if text_content.len() > max_content_size {
                 let truncated_text = format!(
                     "{}\n\n[... content truncated during JSON parsing from {} to {} characters ...]",
-                    &text_content[..max_content_size.min(text_content.len())],
+                    truncate_to_char_boundary(text_content, max_content_size),
                     text_content.len(),
                     max_content_size
                 );
@@ -999,6 +1240,18 @@ pub fn read_metadata(session_file: &Path) -> Result<SessionMetadata> {
         return Err(anyhow::anyhow!("Session file too large"));
     }
 
+    // Metadata updated since the last full rewrite takes precedence over the first line
+    let sidecar = metadata_sidecar(&secure_path);
+    if sidecar.exists() {
+        match fs::read_to_string(&sidecar)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(serde_json::from_str::<SessionMetadata>(&json)?))
+        {
+            Ok(metadata) => return Ok(metadata),
+            Err(e) => tracing::warn!("Failed to read session metadata file: {}", e),
+        }
+    }
+
     let file = fs::File::open(&secure_path).map_err(|e| {
         tracing::error!("Failed to open session file for metadata read: {}", e);
         anyhow::anyhow!("Failed to access session file")
@@ -1031,7 +1284,8 @@ pub fn read_metadata(session_file: &Path) -> Result<SessionMetadata> {
 
 /// Write messages to a session file with metadata
 ///
-/// Overwrites the file with metadata as the first line, followed by all messages in JSONL format.
+/// Appends the messages that are new since the last write by this process, and otherwise
+/// overwrites the file with metadata as the first line, followed by all messages in JSONL format.
 /// If a provider is supplied, it will automatically generate a description when appropriate.
 ///
 /// Security features:
@@ -1047,13 +1301,14 @@ pub async fn persist_messages(
 
 /// Write messages to a session file with metadata, including an optional scheduled job ID
 ///
-/// Overwrites the file with metadata as the first line, followed by all messages in JSONL format.
+/// Appends the messages that are new since the last write by this process, and otherwise
+/// overwrites the file with metadata as the first line, followed by all messages in JSONL format.
 /// If a provider is supplied, it will automatically generate a description when appropriate.
 ///
 /// Security features:
 /// - Validates file paths to prevent directory traversal
 /// - Limits error message details in logs
-/// - Uses atomic file operations via save_messages_with_metadata
+/// - Uses atomic file operations when the file is rewritten
 pub async fn persist_messages_with_schedule_id(
     session_file: &Path,
     messages: &[Message],
@@ -1083,16 +1338,149 @@ pub async fn persist_messages_with_schedule_id(
                 .await
         }
         _ => {
-            // Read existing metadata
-            let mut metadata = read_metadata(&secure_path)?;
-            // Update the schedule_id if provided
+            // Only write metadata when the schedule_id changes
+            let mut metadata = None;
             if schedule_id.is_some() {
-                metadata.schedule_id = schedule_id;
+                let mut current = read_metadata(&secure_path)?;
+                if current.schedule_id != schedule_id {
+                    current.schedule_id = schedule_id;
+                    metadata = Some(current);
+                }
+            }
+            append_messages(&secure_path, metadata.as_ref(), messages)
+        }
+    }
+}
+
+/// Write the messages that are new since the last write, falling back to a full rewrite
+/// when the file does not hold what this process last wrote to it
+///
+/// Metadata, when given, goes to the metadata file so the message lines stay untouched.
+fn append_messages(
+    secure_path: &Path,
+    metadata: Option<&SessionMetadata>,
+    messages: &[Message],
+) -> Result<()> {
+    use fs2::FileExt;
+
+    let policy = FsyncPolicy::from_config();
+    let mut journals = JOURNALS.lock().unwrap_or_else(|e| e.into_inner());
+    let file_len = fs::metadata(secure_path).map(|m| m.len()).unwrap_or(0);
+    let header = read_encryption_header(secure_path)?;
+    // Sessions in clear are rewritten once encryption is turned on
+    let encrypt_in_full = header.is_none() && KeySource::from_config().is_some();
+    let state = match journals.get(secure_path) {
+        Some(state) if !encrypt_in_full && state.is_extended_by(messages, file_len) => *state,
+        _ => {
+            drop(journals);
+            let metadata = match metadata {
+                Some(metadata) => metadata.clone(),
+                None => read_metadata(secure_path)?,
+            };
+            return save_messages_with_metadata(secure_path, &metadata, messages);
+        }
+    };
+
+    if let Some(metadata) = metadata {
+        write_metadata_sidecar(secure_path, metadata, policy)?;
+    }
+
+    let new_messages = &messages[state.message_count..];
+    if new_messages.is_empty() {
+        return Ok(());
+    }
+
+    let key = header.as_ref().map(encryption::unlock).transpose()?;
+    let blob_dir = blobs::blob_dir(secure_path);
+    let mut lines = Vec::new();
+    for (i, message) in new_messages.iter().enumerate() {
+        match &key {
+            Some(key) => {
+                let sealed = encryption::seal_message(message, state.message_count + i, key)?;
+                serde_json::to_writer(&mut lines, &sealed)
+            }
+            None => {
+                let stored = blobs::externalize_blobs(message, &blob_dir, policy.should_sync())?;
+                serde_json::to_writer(&mut lines, &stored)
             }
-            // Write the file with metadata and messages
-            save_messages_with_metadata(&secure_path, &metadata, messages)
         }
+        .map_err(|e| {
+            tracing::error!("Failed to serialize message: {}", e);
+            anyhow::anyhow!("Failed to write session message")
+        })?;
+        lines.push(b'\n');
     }
+
+    let mut file = fs::OpenOptions::new()
+        .append(true)
+        .open(secure_path)
+        .map_err(|e| {
+            tracing::error!("Failed to open session file for append: {}", e);
+            anyhow::anyhow!("Failed to open session file")
+        })?;
+    file.try_lock_exclusive().map_err(|e| {
+        tracing::error!("Failed to lock file: {}", e);
+        anyhow::anyhow!("Failed to lock session file")
+    })?;
+    file.write_all(&lines)?;
+    if policy.should_sync() {
+        file.sync_data().map_err(|e| {
+            tracing::error!("Failed to sync data: {}", e);
+            anyhow::anyhow!("Failed to sync session data")
+        })?;
+    }
+    fs2::FileExt::unlock(&file).map_err(|e| {
+        tracing::error!("Failed to unlock file: {}", e);
+        anyhow::anyhow!("Failed to unlock session file")
+    })?;
+
+    journals.insert(
+        secure_path.to_path_buf(),
+        state.appended(new_messages, file_len + lines.len() as u64),
+    );
+    drop(journals);
+
+    // Encrypted sessions were taken out of the search index when they were encrypted
+    if key.is_none() {
+        if let Err(e) = search::record_write(secure_path, state.message_count, new_messages) {
+            tracing::warn!("Failed to update the session search index: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Atomically replace the metadata file of a session
+fn write_metadata_sidecar(
+    secure_path: &Path,
+    metadata: &SessionMetadata,
+    policy: FsyncPolicy,
+) -> Result<()> {
+    let sidecar = metadata_sidecar(secure_path);
+    let temp_file = secure_path.with_extension("meta.tmp");
+
+    let mut file = fs::File::create(&temp_file).map_err(|e| {
+        tracing::error!("Failed to create temporary metadata file: {}", e);
+        anyhow::anyhow!("Failed to write session metadata")
+    })?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&temp_file, fs::Permissions::from_mode(0o600))?;
+    }
+    serde_json::to_writer(&mut file, metadata).map_err(|e| {
+        tracing::error!("Failed to serialize metadata: {}", e);
+        anyhow::anyhow!("Failed to write session metadata")
+    })?;
+    if policy.should_sync() {
+        file.sync_all()?;
+    }
+
+    fs::rename(&temp_file, &sidecar).map_err(|e| {
+        tracing::error!("Failed to move temporary metadata file: {}", e);
+        let _ = fs::remove_file(&temp_file);
+        anyhow::anyhow!("Failed to finalize session metadata")
+    })?;
+    Ok(())
 }
 
 /// Write messages to a session file with the provided metadata using secure atomic operations
@@ -1103,6 +1491,9 @@ pub async fn persist_messages_with_schedule_id(
 /// 3. Atomically moves the temp file to the final location
 /// 4. Includes comprehensive error handling and recovery
 ///
+/// Large images are stored as blobs next to the file, and any pending metadata file is
+/// folded into the first line. Data is synced to disk unless GOOSE_SESSION_FSYNC is `never`.
+///
 /// Security features:
 /// - Secure temporary file creation with restricted permissions
 /// - Path validation to prevent directory traversal
@@ -1113,10 +1504,28 @@ pub fn save_messages_with_metadata(
     metadata: &SessionMetadata,
     messages: &[Message],
 ) -> Result<()> {
+    rewrite_session_file(session_file, metadata, messages).map(|_| ())
+}
+
+/// Rewrite a session file, returning the hashes of the blobs it refers to
+fn rewrite_session_file(
+    session_file: &Path,
+    metadata: &SessionMetadata,
+    messages: &[Message],
+) -> Result<HashSet<String>> {
     use fs2::FileExt;
 
     // Validate the path for security
     let secure_path = get_path(Identifier::Path(session_file.to_path_buf()))?;
+    let policy = FsyncPolicy::from_config();
+    let blob_dir = blobs::blob_dir(&secure_path);
+    let mut referenced = HashSet::new();
+    let encryption = session_encryption(&secure_path, metadata)?;
+    let metadata = SessionMetadata {
+        encryption: encryption.as_ref().map(|(header, _)| header.clone()),
+        ..metadata.clone()
+    };
+    let mut journals = JOURNALS.lock().unwrap_or_else(|e| e.into_inner());
 
     // Security check: message count limit
     if messages.len() > MAX_MESSAGE_COUNT {
@@ -1179,8 +1588,21 @@ pub fn save_messages_with_metadata(
         writeln!(writer)?;
 
         // Write all messages with progress tracking
+        // Images of encrypted sessions stay in their lines instead of going to blobs in clear
         for (i, message) in messages.iter().enumerate() {
-            serde_json::to_writer(&mut writer, &message).map_err(|e| {
+            match &encryption {
+                Some((_, key)) => {
+                    let sealed = encryption::seal_message(message, i, key)?;
+                    serde_json::to_writer(&mut writer, &sealed)
+                }
+                None => {
+                    let message =
+                        blobs::externalize_blobs(message, &blob_dir, policy.should_sync())?;
+                    referenced.extend(blobs::referenced_blobs(&message));
+                    serde_json::to_writer(&mut writer, &message)
+                }
+            }
+            .map_err(|e| {
                 tracing::error!("Failed to serialize message {}: {}", i, e);
                 anyhow::anyhow!("Failed to write session message")
             })?;
@@ -1195,10 +1617,13 @@ pub fn save_messages_with_metadata(
     }
 
     // Sync to ensure data is persisted
-    file.sync_all().map_err(|e| {
-        tracing::error!("Failed to sync data: {}", e);
-        anyhow::anyhow!("Failed to sync session data")
-    })?;
+    if policy.should_sync() {
+        file.sync_all().map_err(|e| {
+            tracing::error!("Failed to sync data: {}", e);
+            anyhow::anyhow!("Failed to sync session data")
+        })?;
+    }
+    let file_len = file.metadata()?.len();
 
     // Release the lock
     fs2::FileExt::unlock(&file).map_err(|e| {
@@ -1214,8 +1639,25 @@ pub fn save_messages_with_metadata(
         anyhow::anyhow!("Failed to finalize session file")
     })?;
 
+    // The first line now holds the latest metadata
+    let sidecar = metadata_sidecar(&secure_path);
+    if sidecar.exists() {
+        fs::remove_file(&sidecar)?;
+    }
+    journals.insert(secure_path.clone(), JournalState::new(messages, file_len));
+    drop(journals);
+
+    // The index is stored in clear, so the text of encrypted sessions is left out of it
+    let indexed = match encryption {
+        Some(_) => search::record_removal(&secure_path),
+        None => search::record_write(&secure_path, 0, messages),
+    };
+    if let Err(e) = indexed {
+        tracing::warn!("Failed to update the session search index: {}", e);
+    }
+
     tracing::debug!("Successfully saved session file: {:?}", secure_path);
-    Ok(())
+    Ok(referenced)
 }
 
 /// Generate a description for the session using the provider
@@ -1267,14 +1709,7 @@ pub async fn generate_description_with_schedule_id(
         .iter()
         .filter(|m| m.role == mcp_core::role::Role::User)
         .take(3) // Use up to first 3 user messages for context
-        .map(|m| {
-            let text = m.as_concat_text();
-            if text.len() > 300 {
-                format!("{}...", &text[..300])
-            } else {
-                text
-            }
-        })
+        .map(|m| safe_truncate(&m.as_concat_text(), 300))
         .collect();
 
     if !context.is_empty() {
@@ -1302,12 +1737,10 @@ pub async fn generate_description_with_schedule_id(
     let description = result.0.as_concat_text();
 
     // Validate description length for security
-    let sanitized_description = if description.len() > 100 {
+    let sanitized_description = safe_truncate(&description, 100);
+    if sanitized_description != description {
         tracing::warn!("Generated description too long, truncating");
-        format!("{}...", &description[..97])
-    } else {
-        description
-    };
+    }
 
     // Read current metadata
     let mut metadata = read_metadata(&secure_path)?;
@@ -1319,23 +1752,79 @@ pub async fn generate_description_with_schedule_id(
     }
 
     // Update the file with the new metadata and existing messages
-    save_messages_with_metadata(&secure_path, &metadata, messages)
+    append_messages(&secure_path, Some(&metadata), messages)
 }
 
 /// Update only the metadata in a session file, preserving all messages
 ///
+/// The metadata is written to a separate file, so the cost does not grow with the
+/// length of the session. The next full rewrite folds it back into the first line.
+///
 /// Security features:
 /// - Validates file paths to prevent directory traversal
-/// - Uses secure file operations for reading and writing
+/// - Uses atomic file operations for writing
 pub async fn update_metadata(session_file: &Path, metadata: &SessionMetadata) -> Result<()> {
     // Validate the path for security
     let secure_path = get_path(Identifier::Path(session_file.to_path_buf()))?;
 
-    // Read all messages from the file
-    let messages = read_messages(&secure_path)?;
+    if !secure_path.exists() {
+        return save_messages_with_metadata(&secure_path, metadata, &[]);
+    }
+    write_metadata_sidecar(&secure_path, metadata, FsyncPolicy::from_config())
+}
 
-    // Rewrite the file with the new metadata and existing messages
-    save_messages_with_metadata(&secure_path, metadata, &messages)
+/// Rewrite a session file in full and remove blobs that no message refers to anymore
+///
+/// Appended sessions are never rewritten during normal use, so this is where pending
+/// metadata is folded back into the first line and orphaned blobs are cleaned up.
+pub fn compact_session(session_file: &Path) -> Result<CompactionReport> {
+    let secure_path = get_path(Identifier::Path(session_file.to_path_buf()))?;
+    if !secure_path.exists() {
+        return Err(anyhow::anyhow!("Session file not found"));
+    }
+
+    let metadata = read_metadata(&secure_path)?;
+    let messages = read_messages_with_truncation(&secure_path, None)?;
+    let referenced = rewrite_session_file(&secure_path, &metadata, &messages)?;
+    let (removed_blobs, freed_bytes) =
+        blobs::remove_orphaned_blobs(&blobs::blob_dir(&secure_path), &referenced)?;
+
+    Ok(CompactionReport {
+        messages: messages.len(),
+        removed_blobs,
+        freed_bytes,
+    })
+}
+
+/// Delete a session file along with its metadata file, blobs and stored tool results
+pub fn delete_session(session_file: &Path) -> Result<()> {
+    let secure_path = get_path(Identifier::Path(session_file.to_path_buf()))?;
+    fs::remove_file(&secure_path)?;
+
+    for sidecar in [
+        metadata_sidecar(&secure_path),
+        turn_journal_path(&secure_path),
+    ] {
+        if sidecar.exists() {
+            fs::remove_file(&sidecar)?;
+        }
+    }
+    for dir in [
+        blobs::blob_dir(&secure_path),
+        tool_results_dir(&secure_path),
+    ] {
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+    }
+    JOURNALS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&secure_path);
+    if let Err(e) = search::record_removal(&secure_path) {
+        tracing::warn!("Failed to update the session search index: {}", e);
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -1589,6 +2078,24 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_truncate_message_content_multibyte() {
+        use mcp_core::Content;
+
+        // "日" is 3 bytes, so the limit falls inside a character
+        let mut message = Message::user()
+            .with_text("日本".repeat(10))
+            .with_tool_response("call_1", Ok(vec![Content::text("本日".repeat(10))]));
+        truncate_message_content_in_place(&mut message, 10);
+
+        assert!(message
+            .as_concat_text()
+            .starts_with("日本日\n\n[... content truncated"));
+        let response = message.content[1].as_tool_response().unwrap();
+        let text = response.tool_result.as_ref().unwrap()[0].as_text().unwrap();
+        assert!(text.starts_with("本日本\n\n[... tool response truncated"));
+    }
+
     #[tokio::test]
     async fn test_large_content_truncation() -> Result<()> {
         let dir = tempdir()?;
@@ -1639,6 +2146,82 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_citations_after_save_and_load() -> Result<()> {
+        use crate::message::{Citation, CitationSource};
+
+        let dir = tempdir()?;
+        let file_path = dir.path().join("citations.jsonl");
+        let citation = |start, end| Citation {
+            start,
+            end,
+            source: CitationSource {
+                url: Some("https://example.com".to_string()),
+                ..Default::default()
+            },
+        };
+        let large_text = format!("Café {}", "A".repeat(100_000));
+        let messages = vec![
+            Message::assistant()
+                .with_text("Le café de Flore opened in 1887.")
+                .with_citations(vec![citation(0, 32)]),
+            Message::assistant()
+                .with_text(&large_text)
+                .with_citations(vec![citation(0, 5), citation(90_000, 95_000)]),
+        ];
+
+        persist_messages(&file_path, &messages, None).await?;
+        let read_messages = read_messages(&file_path)?;
+        assert_eq!(read_messages[0], messages[0]);
+
+        // Citations of text cut off by truncation go with it
+        let text = read_messages[1].content[0].as_text().unwrap();
+        let citations = &read_messages[1].content[1]
+            .as_citations()
+            .unwrap()
+            .citations;
+        assert_eq!(citations.len(), 1);
+        assert_eq!(&text[citations[0].start..citations[0].end], "Café");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tool_provenance_after_save_and_load() -> Result<()> {
+        use crate::message::ToolProvenance;
+        use mcp_core::tool::ToolCall;
+        use mcp_core::Content;
+        use serde_json::json;
+
+        let dir = tempdir()?;
+        let file_path = dir.path().join("provenance.jsonl");
+        let provenance = ToolProvenance {
+            message_index: 1,
+            extension: "developer".to_string(),
+            started_at: 1_700_000_000_000,
+            finished_at: 1_700_000_001_250,
+        };
+        let messages = vec![
+            Message::user().with_text("List the files"),
+            Message::assistant().with_tool_request(
+                "call_1",
+                Ok(ToolCall::new("developer__shell", json!({"command": "ls"}))),
+            ),
+            Message::user().with_traced_tool_response(
+                "call_1",
+                Ok(vec![Content::text("Cargo.toml")]),
+                provenance.clone(),
+            ),
+        ];
+
+        persist_messages(&file_path, &messages, None).await?;
+        let read_messages = read_messages(&file_path)?;
+        assert_eq!(read_messages, messages);
+        let response = read_messages[2].content[0].as_tool_response().unwrap();
+        assert_eq!(response.provenance.as_ref(), Some(&provenance));
+        assert_eq!(provenance.duration_ms(), 1250);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_metadata_special_chars() -> Result<()> {
         let dir = tempdir()?;
@@ -1743,4 +2326,225 @@ mod tests {
         let normalized_existing = normalize_path_for_comparison(&test_path);
         assert!(!normalized_existing.as_os_str().is_empty());
     }
+
+    #[tokio::test]
+    async fn test_persist_appends_new_messages() -> Result<()> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("append.jsonl");
+
+        let mut messages = vec![
+            Message::user().with_text("Hello"),
+            Message::assistant().with_text("Hi there"),
+        ];
+        persist_messages(&file_path, &messages, None).await?;
+        let before = fs::read(&file_path)?;
+
+        let reply = Message::assistant().with_text("Anything else?");
+        messages.push(reply.clone());
+        persist_messages(&file_path, &messages, None).await?;
+
+        // Only the new line is written, after the existing content
+        let after = fs::read(&file_path)?;
+        let mut expected = before.clone();
+        expected.extend(serde_json::to_vec(&reply)?);
+        expected.push(b'\n');
+        assert_eq!(after, expected);
+
+        // A history that no longer extends the file is rewritten in full
+        let truncated = messages[1..].to_vec();
+        persist_messages(&file_path, &truncated, None).await?;
+        assert_eq!(read_messages(&file_path)?, truncated);
+
+        // Also when only a message between the first and last one changed
+        let mut edited = vec![
+            Message::user().with_text("Hello"),
+            Message::assistant().with_text("Hi there"),
+            Message::user().with_text("Bye"),
+        ];
+        persist_messages(&file_path, &edited, None).await?;
+        edited[1] = Message::assistant().with_text("Hello again");
+        edited.push(Message::assistant().with_text("Goodbye"));
+        persist_messages(&file_path, &edited, None).await?;
+        assert_eq!(read_messages(&file_path)?, edited);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_journal_hash_extends_with_appended_messages() {
+        let messages = vec![
+            Message::user().with_text("Hello"),
+            Message::assistant().with_text("Hi there"),
+            Message::user().with_text("Bye"),
+        ];
+        let appended = JournalState::new(&messages[..1], 10).appended(&messages[1..], 30);
+        let written = JournalState::new(&messages, 30);
+        assert_eq!(appended.message_count, written.message_count);
+        assert_eq!(appended.prefix_hash, written.prefix_hash);
+        assert!(appended.is_extended_by(&messages, 30));
+    }
+
+    #[tokio::test]
+    async fn test_large_images_are_stored_as_blobs() -> Result<()> {
+        use base64::Engine;
+
+        let dir = tempdir()?;
+        let file_path = dir.path().join("images.jsonl");
+        let data = base64::engine::general_purpose::STANDARD.encode(vec![1u8; 100 * 1024]);
+        let messages = vec![
+            Message::user().with_text("What is in this image?"),
+            Message::user().with_image(data, "image/png"),
+        ];
+
+        persist_messages(&file_path, &messages, None).await?;
+        assert!(fs::metadata(&file_path)?.len() < 4096);
+        assert_eq!(fs::read_dir(blobs::blob_dir(&file_path))?.count(), 1);
+        assert_eq!(read_messages(&file_path)?, messages);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_update_metadata_leaves_messages_untouched() -> Result<()> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("metadata.jsonl");
+        let messages = vec![Message::user().with_text("Hello")];
+        persist_messages(&file_path, &messages, None).await?;
+        let before = fs::read(&file_path)?;
+
+        let mut metadata = read_metadata(&file_path)?;
+        metadata.description = "Updated".to_string();
+        update_metadata(&file_path, &metadata).await?;
+
+        assert_eq!(fs::read(&file_path)?, before);
+        assert_eq!(read_metadata(&file_path)?.description, "Updated");
+
+        // Rewriting the file folds the metadata back into the first line
+        compact_session(&file_path)?;
+        assert!(!metadata_sidecar(&file_path).exists());
+        assert_eq!(read_metadata(&file_path)?.description, "Updated");
+        assert_eq!(read_messages(&file_path)?, messages);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_metadata_usage_by_model() -> Result<()> {
+        use crate::providers::base::Usage;
+
+        let dir = tempdir()?;
+        let file_path = dir.path().join("usage.jsonl");
+        persist_messages(&file_path, &[Message::user().with_text("Hello")], None).await?;
+        assert!(read_metadata(&file_path)?.usage_by_model.is_empty());
+
+        let mut metadata = read_metadata(&file_path)?;
+        metadata
+            .usage_by_model
+            .add("gpt-4o", Usage::new(Some(10), Some(5), Some(15)));
+        update_metadata(&file_path, &metadata).await?;
+
+        let usage_by_model = read_metadata(&file_path)?.usage_by_model;
+        assert_eq!(
+            usage_by_model.get("gpt-4o"),
+            Some(&Usage::new(Some(10), Some(5), Some(15)))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_compaction_removes_orphaned_blobs() -> Result<()> {
+        use base64::Engine;
+
+        let dir = tempdir()?;
+        let file_path = dir.path().join("compact.jsonl");
+        let image = |byte: u8| {
+            Message::user().with_image(
+                base64::engine::general_purpose::STANDARD.encode(vec![byte; 100 * 1024]),
+                "image/png",
+            )
+        };
+
+        persist_messages(&file_path, &[image(1), image(2)], None).await?;
+        assert_eq!(fs::read_dir(blobs::blob_dir(&file_path))?.count(), 2);
+
+        // Dropping a message from the history orphans its blob
+        persist_messages(&file_path, &[image(2)], None).await?;
+        let report = compact_session(&file_path)?;
+        assert_eq!(report.messages, 1);
+        assert_eq!(report.removed_blobs, 1);
+        assert_eq!(report.freed_bytes, 100 * 1024);
+        assert_eq!(read_messages(&file_path)?, vec![image(2)]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_session() -> Result<()> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("encrypted.jsonl");
+        let metadata = SessionMetadata {
+            description: "Billing service".to_string(),
+            encryption: Some(encryption::header_with_passphrase("correct horse")?),
+            ..Default::default()
+        };
+        let mut messages = vec![
+            Message::user().with_text("The API key is hunter2"),
+            Message::assistant().with_text("I won't repeat hunter2"),
+        ];
+        save_messages_with_metadata(&file_path, &metadata, &messages)?;
+        messages.push(Message::user().with_text("Good, forget hunter2"));
+        persist_messages(&file_path, &messages, None).await?;
+
+        // Listing only needs the metadata, the content is only readable with the key
+        let contents = fs::read_to_string(&file_path)?;
+        assert!(!contents.contains("hunter2"));
+        assert!(is_encrypted(&file_path));
+        assert_eq!(read_metadata(&file_path)?.description, "Billing service");
+        assert_eq!(read_messages(&file_path)?, messages);
+
+        // A modified line fails loudly instead of being recovered
+        let tampered = contents.replacen("\"role\":\"assistant\"", "\"role\":\"user\"", 1);
+        assert_ne!(tampered, contents);
+        fs::write(&file_path, tampered)?;
+        let error = read_messages(&file_path).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Encrypted session can't be read: message 2 failed verification"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_session_removes_stored_data() -> Result<()> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("delete.jsonl");
+        persist_messages(&file_path, &[Message::user().with_text("Hello")], None).await?;
+        update_metadata(&file_path, &SessionMetadata::default()).await?;
+        fs::create_dir_all(tool_results_dir(&file_path))?;
+        fs::write(tool_results_dir(&file_path).join("result_1.txt"), "output")?;
+        fs::write(turn_journal_path(&file_path), "{}\n")?;
+
+        delete_session(&file_path)?;
+        assert_eq!(fs::read_dir(dir.path())?.count(), 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_most_recent_session_ignores_turn_journal() -> Result<()> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("20250101_120000.jsonl");
+        persist_messages(&file_path, &[Message::user().with_text("Hello")], None).await?;
+
+        // A journal left behind by a crash is written after the session
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        crate::agents::TurnJournal::for_session(&file_path)
+            .begin(&Message::assistant().with_text("Working on it"))?;
+        assert!(turn_journal_path(&file_path).exists());
+
+        assert_eq!(most_recent_session_in(dir.path())?, file_path);
+        Ok(())
+    }
 }
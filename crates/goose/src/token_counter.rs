@@ -11,6 +11,67 @@ use crate::message::Message;
 // If one of them doesn’t exist, we’ll download it at startup.
 static TOKENIZER_FILES: Dir = include_dir!("$CARGO_MANIFEST_DIR/../../tokenizer_files");
 
+/// Tokenizer families that split text differently enough to matter for token budgets
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TokenizerFamily {
+    /// OpenAI's cl100k_base, used by GPT-4 and GPT-3.5
+    Cl100k,
+    /// OpenAI's o200k_base, used by GPT-4o and the o-series models
+    O200k,
+    /// Anthropic's Claude models
+    Claude,
+    /// Any other model, estimated with 1 token per 4 bytes
+    #[default]
+    Generic,
+}
+
+impl TokenizerFamily {
+    /// The family of a model, by its name
+    pub fn for_model(model_name: &str) -> Self {
+        let name = model_name.to_lowercase();
+        if name.contains("claude") {
+            Self::Claude
+        } else if name.contains("gpt-4o")
+            || name.contains("gpt-4.1")
+            || name.contains("gpt-5")
+            || ["o1", "o3", "o4"]
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+        {
+            Self::O200k
+        } else if name.contains("gpt-4") || name.contains("gpt-3.5") {
+            Self::Cl100k
+        } else {
+            Self::Generic
+        }
+    }
+}
+
+/// Estimate the number of tokens in a text without loading a tokenizer
+///
+/// ASCII text is counted in characters per token and other text, such as CJK, in tokens per
+/// character, as tokenizers split scripts outside their main training data into much smaller
+/// pieces. The ratios are averages measured on English prose, code and Chinese text.
+pub fn estimate_tokens(text: &str, family: TokenizerFamily) -> usize {
+    if text.is_empty() {
+        return 0;
+    }
+
+    // (ASCII characters per 10 tokens, tokens per 100 other characters)
+    let (ascii_chars_per_10_tokens, tokens_per_100_other) = match family {
+        TokenizerFamily::Cl100k => (40, 100),
+        TokenizerFamily::O200k => (42, 70),
+        TokenizerFamily::Claude => (35, 110),
+        TokenizerFamily::Generic => return (text.len() / 4).max(1),
+    };
+
+    let ascii = text.chars().filter(char::is_ascii).count();
+    let other = text.chars().count() - ascii;
+    let tokens = (ascii * 10).div_ceil(ascii_chars_per_10_tokens)
+        + (other * tokens_per_100_other).div_ceil(100);
+    tokens.max(1)
+}
+
 /// The `TokenCounter` now stores exactly one `Tokenizer`.
 pub struct TokenCounter {
     tokenizer: Tokenizer,
@@ -240,6 +301,65 @@ mod tests {
     use mcp_core::tool::Tool;
     use serde_json::json;
 
+    #[test]
+    fn test_tokenizer_family_for_model() {
+        assert_eq!(
+            TokenizerFamily::for_model("claude-3-5-sonnet-latest"),
+            TokenizerFamily::Claude
+        );
+        assert_eq!(
+            TokenizerFamily::for_model("gpt-4o-mini"),
+            TokenizerFamily::O200k
+        );
+        assert_eq!(
+            TokenizerFamily::for_model("o3-mini"),
+            TokenizerFamily::O200k
+        );
+        assert_eq!(
+            TokenizerFamily::for_model("gpt-4-turbo"),
+            TokenizerFamily::Cl100k
+        );
+        assert_eq!(
+            TokenizerFamily::for_model("llama3.2"),
+            TokenizerFamily::Generic
+        );
+    }
+
+    #[test]
+    fn test_estimate_tokens_ascii_vs_cjk() {
+        let ascii = "The quick brown fox jumps over the lazy dog. ".repeat(20);
+        let cjk = "敏捷的棕色狐狸跳过了懒狗。".repeat(69);
+        assert_eq!(ascii.chars().count(), 900);
+        assert_eq!(cjk.chars().count(), 897);
+
+        for family in [
+            TokenizerFamily::Cl100k,
+            TokenizerFamily::O200k,
+            TokenizerFamily::Claude,
+        ] {
+            let ascii_tokens = estimate_tokens(&ascii, family);
+            let cjk_tokens = estimate_tokens(&cjk, family);
+            // About 4 characters per token for English, at least 2 tokens per 3 CJK characters
+            assert!((180..=300).contains(&ascii_tokens), "{:?}", family);
+            assert!(cjk_tokens > 2 * ascii_tokens, "{:?}", family);
+        }
+
+        // o200k has a larger vocabulary for non-English text than cl100k
+        assert!(
+            estimate_tokens(&cjk, TokenizerFamily::O200k)
+                < estimate_tokens(&cjk, TokenizerFamily::Cl100k)
+        );
+        assert!(
+            estimate_tokens(&ascii, TokenizerFamily::Claude)
+                > estimate_tokens(&ascii, TokenizerFamily::Cl100k)
+        );
+
+        // The generic heuristic counts bytes, so each CJK character is 3/4 of a token
+        assert_eq!(estimate_tokens(&ascii, TokenizerFamily::Generic), 225);
+        assert_eq!(estimate_tokens(&cjk, TokenizerFamily::Generic), 672);
+        assert_eq!(estimate_tokens("", TokenizerFamily::Claude), 0);
+    }
+
     #[test]
     fn test_claude_tokenizer() {
         let counter = TokenCounter::new(CLAUDE_TOKENIZER);
@@ -278,6 +398,7 @@ mod tests {
                 content: vec![MessageContent::text(
                     "What's the weather like in San Francisco?",
                 )],
+                extra: Default::default(),
             },
             Message {
                 role: Role::Assistant,
@@ -285,11 +406,13 @@ mod tests {
                 content: vec![MessageContent::text(
                     "Looks like it's 60 degrees Fahrenheit in San Francisco.",
                 )],
+                extra: Default::default(),
             },
             Message {
                 role: Role::User,
                 created: 2,
                 content: vec![MessageContent::text("How about New York?")],
+                extra: Default::default(),
             },
         ];
 
@@ -0,0 +1,90 @@
+//! Helpers to cut text to a size without splitting a multibyte character
+
+/// The longest prefix of `text` of at most `max_bytes` bytes that ends on a char boundary
+pub fn truncate_to_char_boundary(text: &str, max_bytes: usize) -> &str {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let end = text
+        .char_indices()
+        .map(|(index, _)| index)
+        .take_while(|&index| index <= max_bytes)
+        .last()
+        .unwrap_or(0);
+    &text[..end]
+}
+
+/// `text` shortened to at most `max_chars` characters for display
+///
+/// When the text is longer, it is cut and ends with "...", which counts towards the limit.
+/// Shorter text is returned as it is.
+pub fn safe_truncate(text: &str, max_chars: usize) -> String {
+    if text.char_indices().nth(max_chars).is_none() {
+        return text.to_string();
+    }
+    let keep = max_chars.saturating_sub(3);
+    let end = text
+        .char_indices()
+        .nth(keep)
+        .map_or(text.len(), |(index, _)| index);
+    format!("{}...", &text[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_to_char_boundary() {
+        assert_eq!(truncate_to_char_boundary("hello", 10), "hello");
+        assert_eq!(truncate_to_char_boundary("hello", 5), "hello");
+        assert_eq!(truncate_to_char_boundary("hello", 3), "hel");
+        assert_eq!(truncate_to_char_boundary("hello", 0), "");
+
+        // "é" is 2 bytes, "日" 3 and "🦆" 4
+        let text = "é日🦆x";
+        let expected = [
+            "",
+            "",
+            "é",
+            "é",
+            "é",
+            "é日",
+            "é日",
+            "é日",
+            "é日",
+            "é日🦆",
+            "é日🦆x",
+        ];
+        for (max_bytes, expected) in expected.iter().enumerate() {
+            assert_eq!(
+                truncate_to_char_boundary(text, max_bytes),
+                *expected,
+                "{}",
+                max_bytes
+            );
+        }
+    }
+
+    #[test]
+    fn test_safe_truncate() {
+        assert_eq!(safe_truncate("short", 10), "short");
+        assert_eq!(safe_truncate("exactly10!", 10), "exactly10!");
+        assert_eq!(safe_truncate("this is too long", 10), "this is...");
+        assert_eq!(safe_truncate("", 0), "");
+
+        // The limit counts characters, not bytes
+        assert_eq!(safe_truncate("日本語のテキスト", 8), "日本語のテキスト");
+        assert_eq!(safe_truncate("日本語のテキストです", 8), "日本語のテ...");
+        assert_eq!(safe_truncate("🦆🦆🦆🦆🦆", 4), "🦆...");
+        assert_eq!(safe_truncate("🦆🦆🦆🦆🦆", 5), "🦆🦆🦆🦆🦆");
+        for max_chars in 0..12 {
+            let truncated = safe_truncate("🦆é日🦆é日🦆é日", max_chars);
+            assert!(
+                truncated.chars().count() <= max_chars.max(3),
+                "{}",
+                truncated
+            );
+        }
+    }
+}
@@ -142,6 +142,23 @@ async fn run_truncate_test(
             Ok(AgentEvent::ModelChange { .. }) => {
                 // Model change events are informational, just continue
             }
+            Ok(AgentEvent::TurnStats(_))
+            | Ok(AgentEvent::Thinking(_))
+            | Ok(AgentEvent::ToolCall { .. })
+            | Ok(AgentEvent::ToolCallProgress { .. })
+            | Ok(AgentEvent::Usage(_))
+            | Ok(AgentEvent::CumulativeUsage { .. })
+            | Ok(AgentEvent::ApprovalTimedOut { .. })
+            | Ok(AgentEvent::UserFacingToolOutput { .. })
+            | Ok(AgentEvent::ToolProgress { .. })
+            | Ok(AgentEvent::Compacted { .. })
+            | Ok(AgentEvent::SoftStop)
+            | Ok(AgentEvent::OversizedMessage(_)) => {
+                // Progress events are informational, just continue
+            }
+            Ok(AgentEvent::Interjection(_)) => {
+                // Interjections are user messages, not responses
+            }
 
             Err(e) => {
                 println!("Error: {:?}", e);
@@ -531,3 +548,848 @@ mod schedule_tool_tests {
         }
     }
 }
+
+#[cfg(test)]
+mod reply_tests {
+    use super::*;
+    use async_trait::async_trait;
+    use goose::agents::{Guardrail, GuardrailDecision, ToolLimits};
+    use goose::context_mgmt::compaction::{
+        CompactionResult, CompactionStrategy, CompactionTrigger, MessageTokenCounter, TokenBudget,
+    };
+    use goose::message::MessageContent;
+    use goose::providers::base::{ProviderMetadata, ProviderUsage, Usage};
+    use goose::providers::errors::ProviderError;
+    use mcp_client::client::{ClientCapabilities, ClientInfo, Error, McpClientTrait};
+    use mcp_core::protocol::{
+        CallToolResult, GetPromptResult, InitializeResult, JsonRpcMessage, JsonRpcNotification,
+        ListPromptsResult, ListResourcesResult, ListToolsResult, ReadResourceResult,
+    };
+    use mcp_core::tool::{Tool, ToolCall};
+    use mcp_core::{Content, Role, ToolError};
+    use serde_json::{json, Value};
+    use std::sync::Mutex;
+    use tokio::sync::mpsc;
+
+    /// Requests a tool on the first call and answers with text afterwards,
+    /// recording the conversation it was given each time
+    struct MockProvider {
+        model_config: ModelConfig,
+        tool_call: ToolCall,
+        seen: Mutex<Vec<Vec<Message>>>,
+    }
+
+    impl MockProvider {
+        fn new(tool_call: ToolCall) -> Self {
+            Self {
+                model_config: ModelConfig::new("test-model".to_string()),
+                tool_call,
+                seen: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Provider for MockProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model_config.clone()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            let mut seen = self.seen.lock().unwrap();
+            seen.push(messages.to_vec());
+            let response = if seen.len() == 1 {
+                Message::assistant()
+                    .with_thinking("the tool should help", "signature")
+                    .with_tool_request("call_1", Ok(self.tool_call.clone()))
+            } else {
+                Message::assistant().with_text("done")
+            };
+            Ok((
+                response,
+                ProviderUsage::new(
+                    "mock".to_string(),
+                    Usage::new(Some(100), Some(20), Some(120)),
+                ),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interjection_added_after_tool_response() -> Result<()> {
+        let provider = Arc::new(MockProvider::new(ToolCall::new(
+            "missing__tool",
+            json!({"path": "a.txt"}),
+        )));
+        let agent = Agent::new();
+        agent.update_provider(provider.clone()).await?;
+        agent.interject("use the other approach").await;
+
+        let messages = vec![Message::user().with_text("do the thing")];
+        let mut stream = agent.reply(&messages, None).await?;
+        let mut events = Vec::new();
+        while let Some(event) = stream.next().await {
+            events.push(event?);
+        }
+
+        let interjection_index = events
+            .iter()
+            .position(|event| matches!(event, AgentEvent::Interjection(_)))
+            .expect("interjection event");
+        let tool_response_index = events
+            .iter()
+            .position(|event| {
+                matches!(event, AgentEvent::Message(message)
+                    if matches!(message.content.first(), Some(MessageContent::ToolResponse(_))))
+            })
+            .expect("tool response event");
+        assert!(tool_response_index < interjection_index);
+
+        assert!(agent.take_pending_interjections().await.is_empty());
+
+        let seen = provider.seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        let last = seen[1].last().unwrap();
+        assert_eq!(last.role, Role::User);
+        assert_eq!(last.as_concat_text(), "use the other approach");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reply_stream_ordering_for_tool_call() -> Result<()> {
+        let provider = Arc::new(MockProvider::new(ToolCall::new(
+            "missing__tool",
+            json!({"path": "a.txt"}),
+        )));
+        let agent = Agent::new();
+        agent.update_provider(provider).await?;
+
+        let messages = vec![Message::user().with_text("read the file")];
+        let mut stream = agent.reply(&messages, None).await?;
+        let mut events = Vec::new();
+        while let Some(event) = stream.next().await {
+            events.push(event?);
+        }
+
+        let kinds: Vec<&str> = events
+            .iter()
+            .map(|event| match event {
+                AgentEvent::Usage(_) => "usage",
+                AgentEvent::CumulativeUsage { .. } => "cumulative_usage",
+                AgentEvent::Thinking(_) => "thinking",
+                AgentEvent::ToolCall { .. } => "tool_call",
+                AgentEvent::ToolCallProgress { .. } => "tool_call_progress",
+                AgentEvent::Message(message) if message.role == Role::Assistant => "assistant",
+                AgentEvent::Message(_) => "tool_response",
+                AgentEvent::TurnStats(_) => "turn_stats",
+                _ => "other",
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "usage",
+                "cumulative_usage",
+                "thinking",
+                "tool_call",
+                "assistant",
+                "tool_response",
+                "usage",
+                "cumulative_usage",
+                "assistant",
+                "turn_stats",
+            ]
+        );
+
+        match &events[3] {
+            AgentEvent::ToolCall {
+                id,
+                name,
+                arguments,
+            } => {
+                assert_eq!(id, "call_1");
+                assert_eq!(name, "missing__tool");
+                assert_eq!(arguments, &json!({"path": "a.txt"}));
+            }
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cumulative_usage_after_each_provider_call() -> Result<()> {
+        let provider = Arc::new(MockProvider::new(ToolCall::new(
+            "missing__tool",
+            json!({"path": "a.txt"}),
+        )));
+        let agent = Agent::new();
+        agent.update_provider(provider).await?;
+
+        let messages = vec![Message::user().with_text("read the file")];
+        let mut stream = agent.reply(&messages, None).await?;
+        let mut cumulative = Vec::new();
+        while let Some(event) = stream.next().await {
+            if let AgentEvent::CumulativeUsage { turn, usage } = event? {
+                cumulative.push((turn, usage.total()));
+            }
+        }
+
+        assert_eq!(
+            cumulative,
+            vec![
+                (1, Usage::new(Some(100), Some(20), Some(120))),
+                (2, Usage::new(Some(200), Some(40), Some(240))),
+            ]
+        );
+        Ok(())
+    }
+
+    /// Requests another tool call every time it is asked
+    struct LoopingProvider {
+        model_config: ModelConfig,
+        calls: Mutex<usize>,
+    }
+
+    #[async_trait]
+    impl Provider for LoopingProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model_config.clone()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            let mut calls = self.calls.lock().unwrap();
+            *calls += 1;
+            let tool_call = ToolCall::new("missing__tool", json!({"attempt": *calls}));
+            Ok((
+                Message::assistant().with_tool_request(format!("call_{}", calls), Ok(tool_call)),
+                ProviderUsage::new("mock".to_string(), Usage::default()),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reply_stops_at_max_tool_iterations() -> Result<()> {
+        let provider = Arc::new(LoopingProvider {
+            model_config: ModelConfig::new("test-model".to_string()),
+            calls: Mutex::new(0),
+        });
+        let agent = Agent::new();
+        agent.update_provider(provider.clone()).await?;
+        agent.set_max_tool_iterations(3).await;
+
+        let messages = vec![Message::user().with_text("keep going")];
+        let mut stream = agent.reply(&messages, None).await?;
+        let mut replies = Vec::new();
+        while let Some(event) = stream.next().await {
+            if let AgentEvent::Message(message) = event? {
+                replies.push(message);
+            }
+        }
+
+        assert_eq!(*provider.calls.lock().unwrap(), 3);
+        let last = replies.last().unwrap();
+        assert_eq!(last.role, Role::Assistant);
+        assert_eq!(
+            last.as_concat_text(),
+            "I stopped after 3 rounds of tool calls, the limit for one reply. Send a message to let me continue."
+        );
+        Ok(())
+    }
+
+    /// Rejects the first request as too long, then answers
+    struct OverflowingProvider {
+        model_config: ModelConfig,
+        seen: Mutex<Vec<Vec<Message>>>,
+    }
+
+    #[async_trait]
+    impl Provider for OverflowingProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model_config.clone()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            let mut seen = self.seen.lock().unwrap();
+            seen.push(messages.to_vec());
+            if seen.len() == 1 {
+                return Err(ProviderError::ContextLengthExceeded("too long".to_string()));
+            }
+            Ok((
+                Message::assistant().with_text("done"),
+                ProviderUsage::new("mock".to_string(), Usage::default()),
+            ))
+        }
+    }
+
+    /// Keeps only the last message
+    struct KeepLast;
+
+    #[async_trait]
+    impl CompactionStrategy for KeepLast {
+        fn name(&self) -> String {
+            "keep_last".to_string()
+        }
+
+        async fn compact(
+            &self,
+            messages: &[Message],
+            _budget: TokenBudget,
+            counter: &dyn MessageTokenCounter,
+        ) -> anyhow::Result<CompactionResult> {
+            let kept = messages[messages.len() - 1..].to_vec();
+            let kept_counts = counter.count_messages(&kept);
+            Ok(CompactionResult::new(
+                self.name(),
+                &counter.count_messages(messages),
+                kept,
+                kept_counts,
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compacts_and_retries_after_context_length_exceeded() -> Result<()> {
+        let provider = Arc::new(OverflowingProvider {
+            model_config: ModelConfig::new("test-model".to_string()),
+            seen: Mutex::new(Vec::new()),
+        });
+        let agent = Agent::new();
+        agent.update_provider(provider.clone()).await?;
+        agent.set_compaction_strategy(Arc::new(KeepLast)).await;
+
+        let messages = vec![
+            Message::user().with_text("an old question"),
+            Message::assistant().with_text("an old answer"),
+            Message::user().with_text("a new question"),
+        ];
+        let mut stream = agent.reply(&messages, None).await?;
+        let mut compactions = Vec::new();
+        let mut replies = Vec::new();
+        while let Some(event) = stream.next().await {
+            match event? {
+                AgentEvent::Compacted { trigger, steps, .. } => compactions.push((trigger, steps)),
+                AgentEvent::Message(message) => replies.push(message.as_concat_text()),
+                _ => {}
+            }
+        }
+
+        assert_eq!(compactions.len(), 1);
+        let (trigger, steps) = &compactions[0];
+        assert_eq!(*trigger, CompactionTrigger::ContextLengthExceeded);
+        assert_eq!(steps[0].strategy, "keep_last");
+        assert_eq!(steps[0].messages_removed(), 2);
+        assert_eq!(replies, vec!["done".to_string()]);
+
+        let seen = provider.seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[1].len(), 1);
+        assert_eq!(seen[1][0].as_concat_text(), "a new question");
+        Ok(())
+    }
+
+    /// An extension whose tools never finish in time
+    struct SlowSystem;
+
+    #[async_trait]
+    impl McpClientTrait for SlowSystem {
+        async fn initialize(
+            &mut self,
+            _info: ClientInfo,
+            _capabilities: ClientCapabilities,
+        ) -> Result<InitializeResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn list_resources(
+            &self,
+            _next_cursor: Option<String>,
+        ) -> Result<ListResourcesResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn read_resource(&self, _uri: &str) -> Result<ReadResourceResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn list_tools(&self, _next_cursor: Option<String>) -> Result<ListToolsResult, Error> {
+            Ok(ListToolsResult {
+                tools: vec![],
+                next_cursor: None,
+            })
+        }
+
+        async fn call_tool(&self, _name: &str, _arguments: Value) -> Result<CallToolResult, Error> {
+            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+            Ok(CallToolResult {
+                content: vec![Content::text("finally")],
+                is_error: None,
+            })
+        }
+
+        async fn list_prompts(
+            &self,
+            _next_cursor: Option<String>,
+        ) -> Result<ListPromptsResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn get_prompt(
+            &self,
+            _name: &str,
+            _arguments: Value,
+        ) -> Result<GetPromptResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn subscribe(&self) -> mpsc::Receiver<JsonRpcMessage> {
+            mpsc::channel(1).1
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_timeout_continues_turn() -> Result<()> {
+        let provider = Arc::new(MockProvider::new(ToolCall::new("slow__wait", json!({}))));
+        let agent = Agent::new();
+        agent.update_provider(provider.clone()).await?;
+        agent
+            .add_client(
+                "slow",
+                Box::new(SlowSystem),
+                ToolLimits::new()
+                    .with_timeout(60)
+                    .with_tool_timeout("wait", 1),
+            )
+            .await;
+
+        let messages = vec![Message::user().with_text("wait for it")];
+        let mut stream = agent.reply(&messages, None).await?;
+        let mut responses = Vec::new();
+        while let Some(event) = stream.next().await {
+            if let AgentEvent::Message(message) = event? {
+                responses.push(message);
+            }
+        }
+
+        let tool_result = responses
+            .iter()
+            .flat_map(|message| message.content.iter())
+            .find_map(|content| content.as_tool_response())
+            .map(|response| response.tool_result.clone())
+            .expect("tool response");
+        let Err(ToolError::Timeout(message)) = tool_result else {
+            panic!("Expected a timeout, got {:?}", tool_result);
+        };
+        assert!(message.contains("within its 1s limit"));
+
+        // The model got the error and the turn went on
+        assert_eq!(provider.seen.lock().unwrap().len(), 2);
+        assert_eq!(responses.last().unwrap().as_concat_text(), "done");
+        Ok(())
+    }
+
+    /// An extension whose tool reports progress while it works
+    #[derive(Default)]
+    struct ProgressSystem {
+        notifier: Mutex<Option<mpsc::Sender<JsonRpcMessage>>>,
+    }
+
+    fn progress_notification(progress: f64, total: Option<f64>, message: &str) -> JsonRpcMessage {
+        let mut params =
+            json!({"progressToken": "prog-1", "progress": progress, "message": message});
+        if let Some(total) = total {
+            params["total"] = json!(total);
+        }
+        JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: "2.0".to_string(),
+            method: "notifications/progress".to_string(),
+            params: Some(params),
+        })
+    }
+
+    #[async_trait]
+    impl McpClientTrait for ProgressSystem {
+        async fn initialize(
+            &mut self,
+            _info: ClientInfo,
+            _capabilities: ClientCapabilities,
+        ) -> Result<InitializeResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn list_resources(
+            &self,
+            _next_cursor: Option<String>,
+        ) -> Result<ListResourcesResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn read_resource(&self, _uri: &str) -> Result<ReadResourceResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn list_tools(&self, _next_cursor: Option<String>) -> Result<ListToolsResult, Error> {
+            Ok(ListToolsResult {
+                tools: vec![],
+                next_cursor: None,
+            })
+        }
+
+        async fn call_tool(&self, _name: &str, _arguments: Value) -> Result<CallToolResult, Error> {
+            let notifier = self.notifier.lock().unwrap().take().unwrap();
+            let sleep = || tokio::time::sleep(std::time::Duration::from_millis(50));
+            notifier
+                .send(progress_notification(1.0, None, "Running for 5s"))
+                .await
+                .unwrap();
+            sleep().await;
+            notifier
+                .send(progress_notification(3.0, Some(4.0), "3 of 4 suites"))
+                .await
+                .unwrap();
+            sleep().await;
+            Ok(CallToolResult {
+                content: vec![Content::text("all tests passed")],
+                is_error: None,
+            })
+        }
+
+        async fn list_prompts(
+            &self,
+            _next_cursor: Option<String>,
+        ) -> Result<ListPromptsResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn get_prompt(
+            &self,
+            _name: &str,
+            _arguments: Value,
+        ) -> Result<GetPromptResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn subscribe(&self) -> mpsc::Receiver<JsonRpcMessage> {
+            let (tx, rx) = mpsc::channel(8);
+            *self.notifier.lock().unwrap() = Some(tx);
+            rx
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_progress_arrives_while_the_tool_runs() -> Result<()> {
+        let provider = Arc::new(MockProvider::new(ToolCall::new(
+            "progress__run_tests",
+            json!({}),
+        )));
+        let agent = Agent::new();
+        agent.update_provider(provider.clone()).await?;
+        agent
+            .add_client(
+                "progress",
+                Box::new(ProgressSystem::default()),
+                ToolLimits::new(),
+            )
+            .await;
+
+        let messages = vec![Message::user().with_text("run the tests")];
+        let mut stream = agent.reply(&messages, None).await?;
+        let mut events = Vec::new();
+        while let Some(event) = stream.next().await {
+            match event? {
+                AgentEvent::ToolProgress {
+                    id,
+                    name,
+                    message,
+                    fraction,
+                } => {
+                    assert_eq!(id, "call_1");
+                    assert_eq!(name, "progress__run_tests");
+                    events.push(format!("{} {:?}", message.unwrap(), fraction));
+                }
+                AgentEvent::McpNotification(_) => events.push("notification".to_string()),
+                AgentEvent::Message(message) if message.role == Role::User => {
+                    events.push("tool response".to_string())
+                }
+                _ => {}
+            }
+        }
+
+        // Progress is its own event, and it arrives before the result
+        assert_eq!(
+            events,
+            vec![
+                "Running for 5s None".to_string(),
+                "3 of 4 suites Some(0.75)".to_string(),
+                "tool response".to_string(),
+            ]
+        );
+        Ok(())
+    }
+
+    /// Blocks shell commands that escalate privileges
+    struct NoSudo;
+
+    #[async_trait]
+    impl Guardrail for NoSudo {
+        fn name(&self) -> &str {
+            "no-sudo"
+        }
+
+        async fn before_tool_dispatch(&self, tool_call: &ToolCall) -> GuardrailDecision {
+            let command = tool_call.arguments["command"].as_str().unwrap_or_default();
+            if tool_call.name == "developer__shell" && command.contains("sudo") {
+                GuardrailDecision::Block {
+                    reason: "sudo is not allowed".to_string(),
+                }
+            } else {
+                GuardrailDecision::Allow
+            }
+        }
+    }
+
+    /// Replaces every text response of the model
+    struct Redact;
+
+    #[async_trait]
+    impl Guardrail for Redact {
+        fn name(&self) -> &str {
+            "redact"
+        }
+
+        async fn after_model_response(&self, response: &Message) -> Option<Message> {
+            (response.as_concat_text() == "done")
+                .then(|| Message::assistant().with_text("[redacted]"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_guardrail_blocks_sudo_shell_call() -> Result<()> {
+        let provider = Arc::new(MockProvider::new(ToolCall::new(
+            "developer__shell",
+            json!({"command": "sudo rm -rf /tmp/cache"}),
+        )));
+        let agent = Agent::new();
+        agent.update_provider(provider.clone()).await?;
+        agent.add_guardrail(Arc::new(NoSudo)).await;
+
+        let messages = vec![Message::user().with_text("clear the cache")];
+        let mut stream = agent.reply(&messages, None).await?;
+        let mut tool_results = Vec::new();
+        while let Some(event) = stream.next().await {
+            if let AgentEvent::Message(message) = event? {
+                for content in message.content {
+                    if let MessageContent::ToolResponse(response) = content {
+                        tool_results.push(response.tool_result);
+                    }
+                }
+            }
+        }
+
+        assert_eq!(tool_results.len(), 1);
+        let error = tool_results[0].as_ref().unwrap_err().to_string();
+        assert!(error.contains("no-sudo"));
+        assert!(error.contains("sudo is not allowed"));
+
+        let (_, allowed) = agent
+            .dispatch_tool_call(
+                ToolCall::new("developer__shell", json!({"command": "ls"})),
+                "call_2".to_string(),
+            )
+            .await;
+        assert!(!allowed
+            .err()
+            .is_some_and(|e| e.to_string().contains("guardrail")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_guardrail_rewrites_model_response() -> Result<()> {
+        let provider = Arc::new(MockProvider::new(ToolCall::new("missing__tool", json!({}))));
+        let agent = Agent::new();
+        agent.update_provider(provider.clone()).await?;
+        agent.add_guardrail(Arc::new(Redact)).await;
+
+        let messages = vec![Message::user().with_text("do the thing")];
+        let mut stream = agent.reply(&messages, None).await?;
+        let mut last_text = String::new();
+        while let Some(event) = stream.next().await {
+            if let AgentEvent::Message(message) = event? {
+                if message.role == Role::Assistant {
+                    last_text = message.as_concat_text();
+                }
+            }
+        }
+
+        assert_eq!(last_text, "[redacted]");
+        Ok(())
+    }
+
+    /// Calls a frontend tool, then follows the paging notes in tool responses until
+    /// the last page, like a model reading a large result
+    struct PagingProvider {
+        model_config: ModelConfig,
+        calls: Mutex<usize>,
+    }
+
+    #[async_trait]
+    impl Provider for PagingProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model_config.clone()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            let mut calls = self.calls.lock().unwrap();
+            *calls += 1;
+            let usage = ProviderUsage::new("mock".to_string(), Usage::default());
+            if *calls == 1 {
+                let response = Message::assistant()
+                    .with_tool_request("call_0", Ok(ToolCall::new("dump_logs", json!({}))));
+                return Ok((response, usage));
+            }
+
+            let note = messages
+                .last()
+                .and_then(|message| message.content.first())
+                .and_then(|content| content.as_tool_response())
+                .and_then(|response| response.tool_result.as_ref().ok())
+                .and_then(|contents| contents.first())
+                .and_then(|content| content.as_text())
+                .unwrap_or_default();
+            let next = note.split_once("with id \"").and_then(|(_, rest)| {
+                let (id, rest) = rest.split_once("\" and page ")?;
+                let page: u64 = rest.trim_end_matches(" for more.]").parse().ok()?;
+                Some((id.to_string(), page))
+            });
+            let response = match next {
+                Some((id, page)) => Message::assistant().with_tool_request(
+                    format!("call_{}", page),
+                    Ok(ToolCall::new(
+                        "platform__read_result",
+                        json!({"id": id, "page": page}),
+                    )),
+                ),
+                None => Message::assistant().with_text("done"),
+            };
+            Ok((response, usage))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_large_tool_result_is_paged() -> Result<()> {
+        let provider = Arc::new(PagingProvider {
+            model_config: ModelConfig::new("test-model".to_string()),
+            calls: Mutex::new(0),
+        });
+        let agent = Agent::new();
+        agent.update_provider(provider.clone()).await?;
+        agent
+            .add_extension(goose::agents::ExtensionConfig::Frontend {
+                name: "logs".to_string(),
+                tools: vec![Tool::new(
+                    "dump_logs",
+                    "Dump all logs",
+                    json!({"type": "object"}),
+                    None,
+                )],
+                instructions: None,
+                bundled: None,
+                network: None,
+            })
+            .await?;
+        // 1MB of output at 360,000 characters per page
+        agent.set_result_page_tokens(90_000).await;
+        let mut output = String::new();
+        for i in 0.. {
+            if output.len() >= 1024 * 1024 {
+                break;
+            }
+            output.push_str(&format!("log line {:07}\n", i));
+        }
+        output.truncate(1024 * 1024);
+
+        let messages = vec![Message::user().with_text("show me the logs")];
+        let mut stream = agent.reply(&messages, None).await?;
+        let mut pages = Vec::new();
+        while let Some(event) = stream.next().await {
+            let AgentEvent::Message(message) = event? else {
+                continue;
+            };
+            for content in message.content {
+                match content {
+                    MessageContent::FrontendToolRequest(request) => {
+                        agent
+                            .handle_tool_result(request.id, Ok(vec![Content::text(output.clone())]))
+                            .await;
+                    }
+                    MessageContent::ToolResponse(response) => {
+                        pages.push(
+                            response.tool_result.unwrap()[0]
+                                .as_text()
+                                .unwrap()
+                                .to_string(),
+                        );
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        assert_eq!(*provider.calls.lock().unwrap(), 4);
+        assert_eq!(pages.len(), 3);
+        assert!(pages[0].contains("page 1/3"));
+        assert!(pages[2].ends_with("page 3/3. This is the last page.]"));
+
+        let (id, _) = pages[0]
+            .split_once("[Result ")
+            .and_then(|(_, rest)| rest.split_once(' '))
+            .unwrap();
+        let text: String = pages
+            .iter()
+            .map(|page| page.rsplit_once("\n\n[Result ").unwrap().0)
+            .collect();
+        assert_eq!(text, output);
+
+        let _ = std::fs::remove_file(
+            std::env::temp_dir()
+                .join("goose_tool_results")
+                .join(format!("{}.txt", id)),
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+//! Canonical conversations that every provider format is tested against
+
+use goose::message::Message;
+use mcp_core::tool::{Tool, ToolCall};
+use mcp_core::{Content, ToolError};
+use serde_json::json;
+
+pub const SYSTEM_PROMPT: &str = "You are a helpful assistant.";
+
+// A 1x1 transparent PNG
+const PNG_DATA: &str =
+    "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=";
+
+pub struct Fixture {
+    pub name: &'static str,
+    pub messages: Vec<Message>,
+}
+
+/// Messages are created with the current time, pin it so payloads are stable
+fn at_epoch(mut message: Message) -> Message {
+    message.created = 0;
+    message
+}
+
+fn conversation(messages: Vec<Message>) -> Vec<Message> {
+    messages.into_iter().map(at_epoch).collect()
+}
+
+fn shell_call(command: &str) -> ToolCall {
+    ToolCall::new("developer__shell", json!({ "command": command }))
+}
+
+pub fn tools() -> Vec<Tool> {
+    vec![Tool::new(
+        "developer__shell",
+        "Run a shell command.",
+        json!({
+            "type": "object",
+            "required": ["command"],
+            "properties": {
+                "command": {
+                    "type": "string",
+                    "description": "The command to run"
+                }
+            }
+        }),
+        None,
+    )]
+}
+
+pub fn fixtures() -> Vec<Fixture> {
+    vec![
+        Fixture {
+            name: "text_only",
+            messages: conversation(vec![
+                Message::user().with_text("What is the capital of France?")
+            ]),
+        },
+        Fixture {
+            name: "multi_turn_tools",
+            messages: conversation(vec![
+                Message::user().with_text("Which files are here, and what is in the README?"),
+                Message::assistant()
+                    .with_text("Let me look.")
+                    .with_tool_request("call_1", Ok(shell_call("ls"))),
+                Message::user()
+                    .with_tool_response("call_1", Ok(vec![Content::text("README.md\nsrc")])),
+                Message::assistant()
+                    .with_tool_request("call_2", Ok(shell_call("cat README.md")))
+                    .with_tool_request("call_3", Ok(shell_call("ls src"))),
+                Message::user()
+                    .with_tool_response("call_2", Ok(vec![Content::text("# Hello")]))
+                    .with_tool_response("call_3", Ok(vec![Content::text("main.rs")])),
+                Message::assistant().with_text("There is a README with a heading and a src dir."),
+                Message::user().with_text("Thanks!"),
+            ]),
+        },
+        Fixture {
+            name: "images",
+            messages: conversation(vec![
+                Message::user()
+                    .with_text("What is in this image and on my screen?")
+                    .with_image(PNG_DATA, "image/png"),
+                Message::assistant().with_tool_request(
+                    "call_1",
+                    Ok(ToolCall::new("developer__screen_capture", json!({}))),
+                ),
+                Message::user().with_tool_response(
+                    "call_1",
+                    Ok(vec![
+                        Content::text("Captured the screen"),
+                        Content::image(PNG_DATA, "image/png"),
+                    ]),
+                ),
+            ]),
+        },
+        Fixture {
+            name: "tool_errors",
+            messages: conversation(vec![
+                Message::user().with_text("Delete the build dir"),
+                Message::assistant().with_tool_request(
+                    "call_1",
+                    Err(ToolError::NotFound(
+                        "The provided function name 'rm -rf' had invalid characters".to_string(),
+                    )),
+                ),
+                Message::user()
+                    .with_tool_response("call_1", Err(ToolError::NotFound("rm -rf".to_string()))),
+                Message::assistant().with_tool_request("call_2", Ok(shell_call("rm -r build"))),
+                Message::user().with_tool_response(
+                    "call_2",
+                    Err(ToolError::ExecutionError(
+                        "rm: build: No such file or directory".to_string(),
+                    )),
+                ),
+            ]),
+        },
+        Fixture {
+            name: "unicode",
+            messages: conversation(vec![
+                Message::user().with_text("Grüße 👋, ¿qué tal? 你好 \"quoted\" \\ back\\slash"),
+                Message::assistant()
+                    .with_text("こんにちは 🎉")
+                    .with_tool_request("call_1", Ok(shell_call("echo '🦆 ünïcödé'"))),
+                Message::user()
+                    .with_tool_response("call_1", Ok(vec![Content::text("🦆 ünïcödé\n")])),
+            ]),
+        },
+        Fixture {
+            name: "empty_messages",
+            messages: conversation(vec![
+                Message::user().with_text(""),
+                Message::assistant(),
+                Message::user().with_text("Hello?"),
+            ]),
+        },
+    ]
+}
@@ -0,0 +1,152 @@
+//! Golden files for provider payloads
+//!
+//! Snapshots are pretty printed JSON checked into `snapshots/`. Run the tests with
+//! `GOOSE_UPDATE_SNAPSHOTS=1` to write new and changed snapshots and remove stale ones,
+//! then review the diff like any other change.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+const UPDATE_ENV: &str = "GOOSE_UPDATE_SNAPSHOTS";
+
+// Only show the start of a diff, the full snapshot can be regenerated
+const MAX_DIFF_LINES: usize = 20;
+
+pub fn updating() -> bool {
+    std::env::var(UPDATE_ENV).is_ok_and(|value| value == "1" || value == "true")
+}
+
+pub fn snapshot_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/provider_formats/snapshots")
+}
+
+/// Replaces values that change between runs with stable placeholders
+///
+/// `created` timestamps become 0, and ids that do not appear in `source` were generated by
+/// the converter and become `generated_id_<n>`, numbered in order of appearance so links
+/// between tool requests and responses stay visible.
+pub struct Canonicalizer<'a> {
+    source: &'a str,
+    ids: HashMap<String, String>,
+}
+
+impl<'a> Canonicalizer<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            ids: HashMap::new(),
+        }
+    }
+
+    pub fn canonicalize(&mut self, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    match (key.as_str(), &value) {
+                        ("created", Value::Number(_)) => *value = Value::from(0),
+                        ("id", Value::String(id)) if !self.source.contains(id.as_str()) => {
+                            let next = format!("generated_id_{}", self.ids.len() + 1);
+                            let placeholder = self.ids.entry(id.clone()).or_insert(next);
+                            *value = Value::String(placeholder.clone());
+                        }
+                        _ => self.canonicalize(value),
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.canonicalize(item)),
+            _ => {}
+        }
+    }
+}
+
+/// Compare `actual` to the snapshot at `path`, relative to the snapshot dir
+///
+/// Returns a description of the mismatch, so a test can report every failing snapshot at once.
+pub fn check(path: &str, actual: &Value) -> Option<String> {
+    let file = snapshot_dir().join(path);
+    let actual = format!("{}\n", serde_json::to_string_pretty(actual).unwrap());
+
+    if updating() {
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        if fs::read_to_string(&file).ok().as_deref() != Some(actual.as_str()) {
+            fs::write(&file, actual).unwrap();
+        }
+        return None;
+    }
+
+    let Ok(expected) = fs::read_to_string(&file) else {
+        return Some(format!(
+            "{}: snapshot is missing, run with {}=1 to create it",
+            path, UPDATE_ENV
+        ));
+    };
+    if expected == actual {
+        return None;
+    }
+
+    let expected_lines: Vec<&str> = expected.lines().collect();
+    let actual_lines: Vec<&str> = actual.lines().collect();
+    let mut diff = Vec::new();
+    for i in 0..expected_lines.len().max(actual_lines.len()) {
+        let (old, new) = (expected_lines.get(i), actual_lines.get(i));
+        if old != new {
+            if let Some(old) = old {
+                diff.push(format!("{:>4} - {}", i + 1, old));
+            }
+            if let Some(new) = new {
+                diff.push(format!("{:>4} + {}", i + 1, new));
+            }
+        }
+    }
+    diff.truncate(MAX_DIFF_LINES);
+    Some(format!(
+        "{}: snapshot differs, run with {}=1 to accept the change\n{}",
+        path,
+        UPDATE_ENV,
+        diff.join("\n")
+    ))
+}
+
+/// Report snapshots that no test produces, removing them when updating
+pub fn check_stale(expected: &HashSet<String>) -> Vec<String> {
+    let dir = snapshot_dir();
+    let mut stale = Vec::new();
+    for provider in fs::read_dir(&dir).into_iter().flatten().flatten() {
+        for file in fs::read_dir(provider.path())
+            .into_iter()
+            .flatten()
+            .flatten()
+        {
+            let path = file
+                .path()
+                .strip_prefix(&dir)
+                .unwrap()
+                .to_string_lossy()
+                .replace('\\', "/");
+            if expected.contains(&path) {
+                continue;
+            }
+            if updating() {
+                fs::remove_file(file.path()).unwrap();
+            } else {
+                stale.push(format!(
+                    "{}: snapshot is not produced by any test, run with {}=1 to remove it",
+                    path, UPDATE_ENV
+                ));
+            }
+        }
+    }
+    stale
+}
+
+pub fn assert_no_failures(failures: Vec<String>) {
+    assert!(
+        failures.is_empty(),
+        "{} snapshot(s) failed:\n\n{}",
+        failures.len(),
+        failures.join("\n\n")
+    );
+}
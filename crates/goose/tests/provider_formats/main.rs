@@ -0,0 +1,303 @@
+//! Snapshot tests of the payloads each provider format builds and of parsing their responses
+//!
+//! Every provider in [`providers`] is run against all conversations in [`fixtures`], and
+//! against the canned responses in `responses/<provider>/`. Supporting a new provider only
+//! takes adding it to [`providers`] and dropping a few of its responses in that directory.
+//!
+//! Each adapter also gets a test through [`convert_roundtrip`], the entry point other tests
+//! can snapshot without knowing how an adapter is called.
+
+mod fixtures;
+mod golden;
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use goose::message::Message;
+use goose::model::ModelConfig;
+use goose::providers::formats::gcpvertexai::RequestContext;
+use goose::providers::formats::tool_schema::ToolSchemaVerbosity;
+use goose::providers::formats::{
+    anthropic, convert_roundtrip, databricks, gcpvertexai, google, openai, openai_responses,
+    snowflake, ROUNDTRIP_ADAPTERS,
+};
+use goose::providers::utils::ImageFormat;
+use mcp_core::tool::Tool;
+use serde_json::{json, Value};
+
+use fixtures::{fixtures, tools, SYSTEM_PROMPT};
+use golden::{assert_no_failures, check, check_stale, Canonicalizer};
+
+type CreateRequest = fn(&ModelConfig, &str, &[Message], &[Tool]) -> Result<Value>;
+type ResponseToMessage = fn(Value) -> Result<Message>;
+
+const GCP_CLAUDE_MODEL: &str = "claude-sonnet-4@20250514";
+const GCP_GEMINI_MODEL: &str = "gemini-2.0-flash-001";
+
+/// A provider's conversion between goose messages and its API
+struct ProviderFormat {
+    /// Names the snapshot and canned response directories
+    name: &'static str,
+    model: &'static str,
+    create_request: CreateRequest,
+    response_to_message: ResponseToMessage,
+}
+
+// Bedrock converts to AWS SDK types, which can't be serialized to JSON for a snapshot
+fn providers() -> Vec<ProviderFormat> {
+    vec![
+        ProviderFormat {
+            name: "anthropic",
+            model: "claude-3-5-sonnet-latest",
+            create_request: anthropic::create_request,
+            response_to_message: anthropic::response_to_message,
+        },
+        ProviderFormat {
+            name: "openai",
+            model: "gpt-4o",
+            create_request: |config, system, messages, tools| {
+                openai::create_request(config, system, messages, tools, &ImageFormat::OpenAi)
+            },
+            response_to_message: openai::response_to_message,
+        },
+        ProviderFormat {
+            name: "openai_legacy_functions",
+            model: "gpt-4o",
+            create_request: |config, system, messages, tools| {
+                let config = config.clone().with_legacy_function_calling(true);
+                openai::create_request(&config, system, messages, tools, &ImageFormat::OpenAi)
+            },
+            response_to_message: openai::response_to_message,
+        },
+        ProviderFormat {
+            name: "openai_responses",
+            model: "gpt-4o",
+            create_request: |config, system, messages, tools| {
+                openai_responses::create_request(
+                    config,
+                    system,
+                    messages,
+                    tools,
+                    &ImageFormat::OpenAi,
+                )
+            },
+            response_to_message: openai_responses::response_to_message,
+        },
+        ProviderFormat {
+            name: "databricks",
+            model: "databricks-meta-llama-3-3-70b-instruct",
+            create_request: |config, system, messages, tools| {
+                databricks::create_request(config, system, messages, tools, &ImageFormat::OpenAi)
+            },
+            response_to_message: databricks::response_to_message,
+        },
+        ProviderFormat {
+            name: "google",
+            model: "gemini-2.0-flash",
+            create_request: google::create_request,
+            response_to_message: google::response_to_message,
+        },
+        ProviderFormat {
+            name: "snowflake",
+            model: "claude-3-7-sonnet",
+            create_request: snowflake::create_request,
+            response_to_message: snowflake::response_to_message,
+        },
+        ProviderFormat {
+            name: "gcp_vertex_ai_claude",
+            model: GCP_CLAUDE_MODEL,
+            create_request: |config, system, messages, tools| {
+                gcpvertexai::create_request(config, system, messages, tools)
+                    .map(|(request, _)| request)
+            },
+            response_to_message: |response| {
+                gcpvertexai::response_to_message(response, RequestContext::new(GCP_CLAUDE_MODEL)?)
+            },
+        },
+        ProviderFormat {
+            name: "gcp_vertex_ai_gemini",
+            model: GCP_GEMINI_MODEL,
+            create_request: |config, system, messages, tools| {
+                gcpvertexai::create_request(config, system, messages, tools)
+                    .map(|(request, _)| request)
+            },
+            response_to_message: |response| {
+                gcpvertexai::response_to_message(response, RequestContext::new(GCP_GEMINI_MODEL)?)
+            },
+        },
+    ]
+}
+
+/// Settings are pinned so environment variables such as GOOSE_TEMPERATURE don't leak in
+fn model_config(model: &str) -> ModelConfig {
+    ModelConfig::new(model.to_string())
+        .with_temperature(None)
+        .with_max_tokens(Some(1024))
+        .with_toolshim(false)
+        .with_legacy_function_calling(false)
+        .with_strict_tools(Some(false))
+        .with_tool_schema_verbosity(ToolSchemaVerbosity::Full)
+}
+
+/// A conversion result as a snapshot, errors are part of the expected behavior
+fn outcome(result: Result<Value>, source: &str) -> Value {
+    match result {
+        Ok(mut value) => {
+            Canonicalizer::new(source).canonicalize(&mut value);
+            value
+        }
+        Err(e) => json!({ "error": e.to_string() }),
+    }
+}
+
+fn responses_dir(provider: &ProviderFormat) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/provider_formats/responses")
+        .join(provider.name)
+}
+
+/// The canned responses of a provider, by name
+fn canned_responses(provider: &ProviderFormat) -> Vec<(String, String)> {
+    let mut responses: Vec<(String, String)> = fs::read_dir(responses_dir(provider))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .map(|path| {
+            let name = path.file_stem().unwrap().to_string_lossy().to_string();
+            (name, fs::read_to_string(&path).unwrap())
+        })
+        .collect();
+    responses.sort();
+    responses
+}
+
+fn request_snapshot(provider: &ProviderFormat, fixture: &str) -> String {
+    format!("{}/request__{}.json", provider.name, fixture)
+}
+
+fn response_snapshot(provider: &ProviderFormat, response: &str) -> String {
+    format!("{}/response__{}.json", provider.name, response)
+}
+
+fn roundtrip_snapshot(adapter: &str) -> String {
+    format!("roundtrip/{}.json", adapter)
+}
+
+/// The multi turn tool conversation through [`convert_roundtrip`]
+fn check_roundtrip(adapter: &str) {
+    let fixture = fixtures()
+        .into_iter()
+        .find(|fixture| fixture.name == "multi_turn_tools")
+        .unwrap();
+    let source = serde_json::to_string(&fixture.messages).unwrap();
+    let result = convert_roundtrip(&fixture.messages, &tools(), SYSTEM_PROMPT, adapter);
+    let snapshot = outcome(result, &source);
+    assert_no_failures(
+        check(&roundtrip_snapshot(adapter), &snapshot)
+            .into_iter()
+            .collect(),
+    );
+}
+
+#[test]
+fn test_request_payloads() {
+    let tools = tools();
+    let mut failures = Vec::new();
+    for provider in providers() {
+        let config = model_config(provider.model);
+        for fixture in fixtures() {
+            let source = serde_json::to_string(&fixture.messages).unwrap();
+            let result =
+                (provider.create_request)(&config, SYSTEM_PROMPT, &fixture.messages, &tools);
+            let snapshot = outcome(result, &source);
+            failures.extend(check(&request_snapshot(&provider, fixture.name), &snapshot));
+        }
+    }
+    assert_no_failures(failures);
+}
+
+#[test]
+fn test_response_parsing() {
+    let mut failures = Vec::new();
+    for provider in providers() {
+        let responses = canned_responses(&provider);
+        assert!(
+            !responses.is_empty(),
+            "{} has no canned responses in {}",
+            provider.name,
+            responses_dir(&provider).display()
+        );
+        for (name, response) in responses {
+            let value: Value = serde_json::from_str(&response).unwrap_or_else(|e| {
+                panic!("{}/{}.json is not valid JSON: {}", provider.name, name, e)
+            });
+            let result = (provider.response_to_message)(value)
+                .and_then(|message| Ok(serde_json::to_value(message)?));
+            let snapshot = outcome(result, &response);
+            failures.extend(check(&response_snapshot(&provider, &name), &snapshot));
+        }
+    }
+    assert_no_failures(failures);
+}
+
+#[test]
+fn test_roundtrip_anthropic() {
+    check_roundtrip("anthropic");
+}
+
+#[test]
+fn test_roundtrip_databricks() {
+    check_roundtrip("databricks");
+}
+
+#[test]
+fn test_roundtrip_gcpvertexai() {
+    check_roundtrip("gcpvertexai");
+}
+
+#[test]
+fn test_roundtrip_google() {
+    check_roundtrip("google");
+}
+
+#[test]
+fn test_roundtrip_openai() {
+    check_roundtrip("openai");
+}
+
+#[test]
+fn test_roundtrip_openai_responses() {
+    check_roundtrip("openai_responses");
+}
+
+#[test]
+fn test_roundtrip_snowflake() {
+    check_roundtrip("snowflake");
+}
+
+#[test]
+fn test_roundtrip_unknown_adapter() {
+    let error = convert_roundtrip(&[], &tools(), SYSTEM_PROMPT, "bedrock").unwrap_err();
+    assert!(error.to_string().contains("No roundtrip for 'bedrock'"));
+}
+
+#[test]
+fn test_no_stale_snapshots() {
+    let mut expected = HashSet::new();
+    for adapter in ROUNDTRIP_ADAPTERS {
+        expected.insert(roundtrip_snapshot(adapter));
+    }
+    for provider in providers() {
+        for fixture in fixtures() {
+            expected.insert(request_snapshot(&provider, fixture.name));
+        }
+        for (name, _) in canned_responses(&provider) {
+            expected.insert(response_snapshot(&provider, &name));
+        }
+    }
+    assert_no_failures(check_stale(&expected));
+}
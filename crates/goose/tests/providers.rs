@@ -4,7 +4,8 @@ use goose::message::{Message, MessageContent};
 use goose::providers::base::Provider;
 use goose::providers::errors::ProviderError;
 use goose::providers::{
-    anthropic, azure, bedrock, databricks, google, groq, ollama, openai, openrouter, snowflake, xai,
+    anthropic, azure, bedrock, databricks, fireworks, google, groq, ollama, openai, openrouter,
+    snowflake, together, xai,
 };
 use mcp_core::content::Content;
 use mcp_core::tool::Tool;
@@ -506,6 +507,28 @@ async fn test_xai_provider() -> Result<()> {
     test_provider("Xai", &["XAI_API_KEY"], None, xai::XaiProvider::default).await
 }
 
+#[tokio::test]
+async fn test_fireworks_provider() -> Result<()> {
+    test_provider(
+        "Fireworks",
+        &["FIREWORKS_API_KEY"],
+        None,
+        fireworks::FireworksProvider::default,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_together_provider() -> Result<()> {
+    test_provider(
+        "Together",
+        &["TOGETHER_API_KEY"],
+        None,
+        together::TogetherProvider::default,
+    )
+    .await
+}
+
 // Print the final test report
 #[ctor::dtor]
 fn print_test_report() {
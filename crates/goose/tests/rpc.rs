@@ -0,0 +1,288 @@
+#![cfg(unix)]
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use goose::agents::{Agent, ToolLimits};
+use goose::message::Message;
+use goose::model::ModelConfig;
+use goose::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+use goose::providers::errors::ProviderError;
+use goose::rpc::{serve, PROTOCOL_VERSION};
+use goose::session;
+use mcp_client::client::{ClientCapabilities, ClientInfo, Error, McpClientTrait};
+use mcp_core::protocol::{
+    CallToolResult, GetPromptResult, InitializeResult, JsonRpcMessage, ListPromptsResult,
+    ListResourcesResult, ListToolsResult, ReadResourceResult,
+};
+use mcp_core::tool::{Tool, ToolCall};
+use mcp_core::Content;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::UnixStream;
+use tokio::sync::mpsc;
+
+/// Asks to echo something on the first call and answers with text afterwards
+struct EchoProvider {
+    model_config: ModelConfig,
+    calls: Mutex<usize>,
+}
+
+#[async_trait]
+impl Provider for EchoProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model_config.clone()
+    }
+
+    async fn complete(
+        &self,
+        _system: &str,
+        _messages: &[Message],
+        _tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let mut calls = self.calls.lock().unwrap();
+        *calls += 1;
+        let response = if *calls == 1 {
+            Message::assistant().with_tool_request(
+                "call_1",
+                Ok(ToolCall::new("echo__say", json!({"text": "hello"}))),
+            )
+        } else {
+            Message::assistant().with_text("done")
+        };
+        Ok((
+            response,
+            ProviderUsage::new(
+                "mock".to_string(),
+                Usage::new(Some(100), Some(20), Some(120)),
+            ),
+        ))
+    }
+}
+
+/// An extension whose tool answers with its text argument
+struct EchoSystem;
+
+#[async_trait]
+impl McpClientTrait for EchoSystem {
+    async fn initialize(
+        &mut self,
+        _info: ClientInfo,
+        _capabilities: ClientCapabilities,
+    ) -> Result<InitializeResult, Error> {
+        Err(Error::NotInitialized)
+    }
+
+    async fn list_resources(
+        &self,
+        _next_cursor: Option<String>,
+    ) -> Result<ListResourcesResult, Error> {
+        Err(Error::NotInitialized)
+    }
+
+    async fn read_resource(&self, _uri: &str) -> Result<ReadResourceResult, Error> {
+        Err(Error::NotInitialized)
+    }
+
+    async fn list_tools(&self, _next_cursor: Option<String>) -> Result<ListToolsResult, Error> {
+        Ok(ListToolsResult {
+            tools: vec![Tool::new(
+                "say",
+                "Say something",
+                json!({"type": "object"}),
+                None,
+            )],
+            next_cursor: None,
+        })
+    }
+
+    async fn call_tool(&self, _name: &str, arguments: Value) -> Result<CallToolResult, Error> {
+        Ok(CallToolResult {
+            content: vec![Content::text(
+                arguments["text"].as_str().unwrap_or_default(),
+            )],
+            is_error: None,
+        })
+    }
+
+    async fn list_prompts(&self, _next_cursor: Option<String>) -> Result<ListPromptsResult, Error> {
+        Err(Error::NotInitialized)
+    }
+
+    async fn get_prompt(&self, _name: &str, _arguments: Value) -> Result<GetPromptResult, Error> {
+        Err(Error::NotInitialized)
+    }
+
+    async fn subscribe(&self) -> mpsc::Receiver<JsonRpcMessage> {
+        mpsc::channel(1).1
+    }
+}
+
+struct Client {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+    next_id: u64,
+}
+
+impl Client {
+    async fn send(&mut self, method: &str, params: Value) -> Result<u64> {
+        self.next_id += 1;
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id,
+            "method": method,
+            "params": params,
+        });
+        self.writer
+            .write_all(format!("{}\n", request).as_bytes())
+            .await?;
+        Ok(self.next_id)
+    }
+
+    async fn next(&mut self) -> Result<Value> {
+        let line = self
+            .lines
+            .next_line()
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("The server closed the socket"))?;
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    /// Send a request and wait for its result, skipping late answers to earlier requests
+    async fn call(&mut self, method: &str, params: Value) -> Result<Value> {
+        let id = self.send(method, params).await?;
+        loop {
+            let response = self.next().await?;
+            if response["id"] == json!(id) {
+                return Ok(response["result"].clone());
+            }
+            assert!(
+                response.get("result").is_some(),
+                "unexpected message {}",
+                response
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_tool_using_turn_over_a_socket() -> Result<()> {
+    // Every tool call waits for approval over the socket
+    std::env::set_var("GOOSE_MODE", "approve");
+
+    let agent = Agent::new();
+    agent
+        .update_provider(Arc::new(EchoProvider {
+            model_config: ModelConfig::new("test-model".to_string()),
+            calls: Mutex::new(0),
+        }))
+        .await?;
+    agent
+        .add_client("echo", Box::new(EchoSystem), ToolLimits::new())
+        .await;
+
+    let (server, client) = UnixStream::pair()?;
+    let (server_reader, server_writer) = server.into_split();
+    let serving = tokio::spawn(serve(Arc::new(agent), server_reader, server_writer));
+    let (reader, writer) = client.into_split();
+    let mut client = Client {
+        lines: BufReader::new(reader).lines(),
+        writer,
+        next_id: 0,
+    };
+
+    let session_id = format!("rpc_test_{}", std::process::id());
+    let working_dir = tempfile::tempdir()?;
+    let started = client
+        .call(
+            "start_session",
+            json!({"session_id": session_id, "working_dir": working_dir.path()}),
+        )
+        .await?;
+    assert_eq!(started["protocol_version"], PROTOCOL_VERSION);
+    assert_eq!(started["session_id"], json!(session_id));
+    assert_eq!(started["messages"], 0);
+
+    // Events of the reply may arrive before the answer to send_message
+    client
+        .send(
+            "send_message",
+            json!({"session_id": session_id, "text": "say hello"}),
+        )
+        .await?;
+
+    let mut methods = Vec::new();
+    let mut texts = Vec::new();
+    let finish = loop {
+        let message = client.next().await?;
+        if message.get("id").is_some() {
+            // The answers to send_message and approve_tool
+            assert_eq!(
+                message["result"],
+                json!({}),
+                "unexpected message {}",
+                message
+            );
+            continue;
+        }
+        assert_eq!(message["params"]["session_id"], json!(session_id));
+        let method = message["method"].as_str().unwrap().to_string();
+        if method == "agent/finish" {
+            break message["params"].clone();
+        }
+        if method == "agent/message" {
+            for content in message["params"]["message"]["content"].as_array().unwrap() {
+                match content["type"].as_str().unwrap() {
+                    "toolConfirmationRequest" => {
+                        assert_eq!(content["toolName"], "echo__say");
+                        client
+                            .send(
+                                "approve_tool",
+                                json!({"request_id": content["id"], "decision": "allow_once"}),
+                            )
+                            .await?;
+                    }
+                    "toolResponse" => {
+                        texts.push(content["toolResult"]["value"][0]["text"].clone());
+                    }
+                    "text" => texts.push(content["text"].clone()),
+                    _ => {}
+                }
+            }
+        }
+        methods.push(method);
+    };
+
+    assert_eq!(finish["reason"], "stop");
+    assert!(methods.contains(&"agent/tool_call".to_string()));
+    assert!(methods.contains(&"agent/usage".to_string()));
+    assert_eq!(texts, vec![json!("hello"), json!("done")]);
+
+    let usage = client.call("get_usage", json!({})).await?;
+    assert_eq!(usage["mock"]["total_tokens"], 240);
+
+    let sessions = client.call("list_sessions", json!({})).await?;
+    let saved = sessions
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|session| session["id"] == json!(session_id))
+        .expect("the session was saved");
+    assert_eq!(saved["metadata"]["message_count"], 4);
+
+    let cancelled = client
+        .call("cancel", json!({"session_id": session_id}))
+        .await?;
+    assert_eq!(cancelled["cancelled"], false);
+
+    drop(client);
+    serving.await??;
+    session::delete_session(&session::get_path(session::Identifier::Name(session_id))?)?;
+    Ok(())
+}
@@ -410,5 +410,10 @@ pub fn create_test_session_metadata(message_count: usize, working_dir: &str) ->
         accumulated_total_tokens: Some(100),
         accumulated_input_tokens: Some(50),
         accumulated_output_tokens: Some(50),
+        usage_by_model: Default::default(),
+        turn_stats: Vec::new(),
+        guardrail_rewrites: Vec::new(),
+        analytics: Default::default(),
+        encryption: None,
     }
 }
@@ -170,42 +170,44 @@ where
     where
         R: for<'de> Deserialize<'de>,
     {
-        let mut service = self.service.lock().await;
-        service.ready().await.map_err(|_| Error::NotReady)?;
-        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let request_id = self.next_id.fetch_add(1, Ordering::SeqCst);
 
         let mut params = params.clone();
         params["_meta"] = json!({
-            "progressToken": format!("prog-{}", id),
+            "progressToken": format!("prog-{}", request_id),
         });
 
         let request = JsonRpcMessage::Request(JsonRpcRequest {
             jsonrpc: "2.0".to_string(),
-            id: Some(id),
+            id: Some(request_id),
             method: method.to_string(),
             params: Some(params),
         });
 
-        let response_msg = service
-            .call(request)
-            .await
-            .map_err(|e| Error::McpServerError {
-                server: self
-                    .server_info
-                    .as_ref()
-                    .map(|s| s.name.clone())
-                    .unwrap_or("".to_string()),
-                method: method.to_string(),
-                // we don't need include params because it can be really large
-                source: Box::<Error>::new(e.into()),
-            })?;
+        // Responses are matched to their request by id, so the service is only held while
+        // sending and other requests can be made while this one waits
+        let response = {
+            let mut service = self.service.lock().await;
+            service.ready().await.map_err(|_| Error::NotReady)?;
+            service.call(request)
+        };
+        let response_msg = response.await.map_err(|e| Error::McpServerError {
+            server: self
+                .server_info
+                .as_ref()
+                .map(|s| s.name.clone())
+                .unwrap_or("".to_string()),
+            method: method.to_string(),
+            // we don't need include params because it can be really large
+            source: Box::<Error>::new(e.into()),
+        })?;
 
         match response_msg {
             JsonRpcMessage::Response(JsonRpcResponse {
                 id, result, error, ..
             }) => {
                 // Verify id matches
-                if id != Some(self.next_id.load(Ordering::SeqCst) - 1) {
+                if id != Some(request_id) {
                     return Err(Error::UnexpectedResponse(
                         "id mismatch for JsonRpcResponse".to_string(),
                     ));
@@ -222,7 +224,7 @@ where
                 }
             }
             JsonRpcMessage::Error(JsonRpcError { id, error, .. }) => {
-                if id != Some(self.next_id.load(Ordering::SeqCst) - 1) {
+                if id != Some(request_id) {
                     return Err(Error::UnexpectedResponse(
                         "id mismatch for JsonRpcError".to_string(),
                     ));
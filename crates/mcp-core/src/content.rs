@@ -5,6 +5,7 @@ use super::role::Role;
 use crate::resource::ResourceContents;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use utoipa::ToSchema;
 
 #[derive(ToSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -18,6 +19,9 @@ pub struct Annotations {
     #[schema(value_type = String, format = "date-time", example = "2023-01-01T00:00:00Z")]
     // for openapi
     pub timestamp: Option<DateTime<Utc>>,
+    /// Shown to the user but left out of the history sent to models, like status notes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ephemeral: Option<bool>,
 }
 
 impl Annotations {
@@ -32,6 +36,7 @@ impl Annotations {
             priority: Some(priority),
             timestamp: Some(timestamp),
             audience: None,
+            ephemeral: None,
         }
     }
 }
@@ -70,12 +75,25 @@ impl EmbeddedResource {
     }
 }
 
+/// A tool result with a text form for the model and a structured form for programs
+#[derive(ToSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultContent {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Object)]
+    pub structured: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Annotations>,
+}
+
 #[derive(ToSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum Content {
     Text(TextContent),
     Image(ImageContent),
     Resource(EmbeddedResource),
+    Result(ResultContent),
 }
 
 impl Content {
@@ -112,10 +130,28 @@ impl Content {
         })
     }
 
-    /// Get the text content if this is a TextContent variant
+    /// A result whose text is shown to the model and whose structured form is kept for callers
+    pub fn result<S: Into<String>>(text: S, structured: Option<Value>) -> Self {
+        Content::Result(ResultContent {
+            text: text.into(),
+            structured,
+            annotations: None,
+        })
+    }
+
+    /// Get the text content if this is a TextContent variant, or the text of a result
     pub fn as_text(&self) -> Option<&str> {
         match self {
             Content::Text(text) => Some(&text.text),
+            Content::Result(result) => Some(&result.text),
+            _ => None,
+        }
+    }
+
+    /// Get the structured form of a result, if it has one
+    pub fn as_structured(&self) -> Option<&Value> {
+        match self {
+            Content::Result(result) => result.structured.as_ref(),
             _ => None,
         }
     }
@@ -134,6 +170,7 @@ impl Content {
             Content::Text(text) => &mut text.annotations,
             Content::Image(image) => &mut image.annotations,
             Content::Resource(resource) => &mut resource.annotations,
+            Content::Result(result) => &mut result.annotations,
         };
         *annotations = Some(match annotations.take() {
             Some(mut a) => {
@@ -144,6 +181,7 @@ impl Content {
                 audience: Some(audience),
                 priority: None,
                 timestamp: None,
+                ephemeral: None,
             },
         });
         self
@@ -160,6 +198,7 @@ impl Content {
             Content::Text(text) => &mut text.annotations,
             Content::Image(image) => &mut image.annotations,
             Content::Resource(resource) => &mut resource.annotations,
+            Content::Result(result) => &mut result.annotations,
         };
         *annotations = Some(match annotations.take() {
             Some(mut a) => {
@@ -170,11 +209,46 @@ impl Content {
                 audience: None,
                 priority: Some(priority),
                 timestamp: None,
+                ephemeral: None,
             },
         });
         self
     }
 
+    /// Mark the content as shown to the user but never sent to models
+    pub fn with_ephemeral(mut self) -> Self {
+        let annotations = match &mut self {
+            Content::Text(text) => &mut text.annotations,
+            Content::Image(image) => &mut image.annotations,
+            Content::Resource(resource) => &mut resource.annotations,
+            Content::Result(result) => &mut result.annotations,
+        };
+        *annotations = Some(match annotations.take() {
+            Some(mut a) => {
+                a.ephemeral = Some(true);
+                a
+            }
+            None => Annotations {
+                audience: None,
+                priority: None,
+                timestamp: None,
+                ephemeral: Some(true),
+            },
+        });
+        self
+    }
+
+    /// Whether the content is only for display, see [`Content::with_ephemeral`]
+    pub fn is_ephemeral(&self) -> bool {
+        let annotations = match self {
+            Content::Text(text) => &text.annotations,
+            Content::Image(image) => &image.annotations,
+            Content::Resource(resource) => &resource.annotations,
+            Content::Result(result) => &result.annotations,
+        };
+        annotations.as_ref().and_then(|a| a.ephemeral) == Some(true)
+    }
+
     /// Get the audience if set
     pub fn audience(&self) -> Option<&Vec<Role>> {
         match self {
@@ -184,6 +258,10 @@ impl Content {
                 .annotations
                 .as_ref()
                 .and_then(|a| a.audience.as_ref()),
+            Content::Result(result) => result
+                .annotations
+                .as_ref()
+                .and_then(|a| a.audience.as_ref()),
         }
     }
 
@@ -193,6 +271,46 @@ impl Content {
             Content::Text(text) => text.annotations.as_ref().and_then(|a| a.priority),
             Content::Image(image) => image.annotations.as_ref().and_then(|a| a.priority),
             Content::Resource(resource) => resource.annotations.as_ref().and_then(|a| a.priority),
+            Content::Result(result) => result.annotations.as_ref().and_then(|a| a.priority),
+        }
+    }
+
+    /// Cut text content to `max_chars` characters, noting how many were dropped
+    ///
+    /// Returns whether the text was truncated. Images, resources and the structured form of
+    /// results are left unchanged.
+    pub fn truncate_text(&mut self, max_chars: usize) -> bool {
+        let text = match self {
+            Content::Text(text) => &mut text.text,
+            Content::Result(result) => &mut result.text,
+            _ => return false,
+        };
+        let Some((end, _)) = text.char_indices().nth(max_chars) else {
+            return false;
+        };
+        let dropped = text[end..].chars().count();
+        text.truncate(end);
+        text.push_str(&format!("\n[truncated {} chars]", dropped));
+        true
+    }
+
+    /// The size of the content in bytes: the UTF-8 length of text, and the decoded length of
+    /// base64 images and blobs. Results count their text and their structured form as JSON.
+    pub fn byte_len(&self) -> usize {
+        match self {
+            Content::Text(text) => text.text.len(),
+            Content::Image(image) => base64_decoded_len(&image.data),
+            Content::Resource(resource) => match &resource.resource {
+                ResourceContents::TextResourceContents { text, .. } => text.len(),
+                ResourceContents::BlobResourceContents { blob, .. } => base64_decoded_len(blob),
+            },
+            Content::Result(result) => {
+                result.text.len()
+                    + result
+                        .structured
+                        .as_ref()
+                        .map_or(0, |structured| structured.to_string().len())
+            }
         }
     }
 
@@ -201,13 +319,27 @@ impl Content {
             Content::Text(text) => Content::text(text.text.clone()),
             Content::Image(image) => Content::image(image.data.clone(), image.mime_type.clone()),
             Content::Resource(resource) => Content::resource(resource.resource.clone()),
+            Content::Result(result) => {
+                Content::result(result.text.clone(), result.structured.clone())
+            }
         }
     }
 }
 
+/// The number of bytes base64 data decodes to, counted without decoding it
+fn base64_decoded_len(data: &str) -> usize {
+    let digits = data
+        .trim_end_matches(|c: char| c == '=' || c.is_ascii_whitespace())
+        .bytes()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .count();
+    digits * 3 / 4
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
 
     #[test]
     fn test_content_text() {
@@ -284,6 +416,71 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_truncate_text() {
+        let mut content = Content::text("日本語".repeat(1000));
+        assert!(content.truncate_text(10));
+        assert_eq!(
+            content.as_text(),
+            Some("日本語日本語日本語日\n[truncated 2990 chars]")
+        );
+
+        let mut short = Content::text("short").with_priority(0.5);
+        assert!(!short.truncate_text(5));
+        assert_eq!(short.as_text(), Some("short"));
+        assert_eq!(short.priority(), Some(0.5));
+    }
+
+    #[test]
+    fn test_truncate_text_leaves_images() {
+        let mut content = Content::image("a".repeat(1000), "image/png");
+        assert!(!content.truncate_text(10));
+        assert_eq!(
+            content.as_image(),
+            Some(("a".repeat(1000).as_str(), "image/png"))
+        );
+    }
+
+    #[test]
+    fn test_content_result_roundtrip() {
+        let content = Content::result("2 files changed", Some(json!({"files": ["a.rs", "b.rs"]})))
+            .with_priority(0.5);
+        let value = serde_json::to_value(&content).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "type": "result",
+                "text": "2 files changed",
+                "structured": {"files": ["a.rs", "b.rs"]},
+                "annotations": {"priority": 0.5},
+            })
+        );
+        assert_eq!(serde_json::from_value::<Content>(value).unwrap(), content);
+        assert_eq!(content.as_text(), Some("2 files changed"));
+        assert_eq!(
+            content.as_structured(),
+            Some(&json!({"files": ["a.rs", "b.rs"]}))
+        );
+
+        // Without a structured form, a result reads back like text
+        let value = json!({"type": "result", "text": "no changes"});
+        let content: Content = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(content, Content::result("no changes", None));
+        assert_eq!(serde_json::to_value(&content).unwrap(), value);
+        assert_eq!(content.as_text(), Some("no changes"));
+        assert_eq!(content.as_structured(), None);
+        assert_eq!(Content::text("no changes").as_structured(), None);
+    }
+
+    #[test]
+    fn test_truncate_result_keeps_structured() {
+        let mut content = Content::result("a".repeat(20), Some(json!({"count": 20})));
+        assert!(content.truncate_text(10));
+        assert_eq!(content.as_text(), Some("aaaaaaaaaa\n[truncated 10 chars]"));
+        assert_eq!(content.as_structured(), Some(&json!({"count": 20})));
+        assert_eq!(content.unannotated(), content);
+    }
+
     #[test]
     #[should_panic(expected = "Priority must be between 0.0 and 1.0")]
     fn test_invalid_priority() {
@@ -310,4 +507,37 @@ mod tests {
         assert_eq!(content.audience(), Some(&vec![Role::User]));
         assert_eq!(content.priority(), None);
     }
+
+    #[test]
+    fn test_content_byte_len() {
+        use base64::engine::{general_purpose::STANDARD as BASE64_STANDARD, Engine};
+
+        assert_eq!(Content::text("hello").byte_len(), 5);
+        assert_eq!(Content::text("héllo 🦆").byte_len(), 11);
+
+        // Images count the bytes they decode to, whatever the padding
+        for len in 0..8 {
+            let bytes = vec![0xab; len];
+            let data = BASE64_STANDARD.encode(&bytes);
+            assert_eq!(Content::image(data.clone(), "image/png").byte_len(), len);
+            let unpadded = data.trim_end_matches('=').to_string();
+            assert_eq!(Content::image(unpadded, "image/png").byte_len(), len);
+        }
+        let wrapped = BASE64_STANDARD.encode([1u8; 100]).replace("AQEB", "AQEB\n");
+        assert_eq!(Content::image(wrapped, "image/png").byte_len(), 100);
+
+        assert_eq!(Content::embedded_text("file:///a.txt", "abc").byte_len(), 3);
+        let blob = Content::resource(ResourceContents::BlobResourceContents {
+            uri: "file:///a.bin".to_string(),
+            mime_type: None,
+            blob: BASE64_STANDARD.encode([0u8; 1024]),
+        });
+        assert_eq!(blob.byte_len(), 1024);
+
+        assert_eq!(Content::result("ok", None).byte_len(), 2);
+        assert_eq!(
+            Content::result("ok", Some(json!({"a": 1}))).byte_len(),
+            2 + r#"{"a":1}"#.len()
+        );
+    }
 }
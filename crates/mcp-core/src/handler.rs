@@ -18,6 +18,9 @@ pub enum ToolError {
     SchemaError(String),
     #[error("Tool not found: {0}")]
     NotFound(String),
+    /// The call did not finish in time, and may succeed if retried
+    #[error("Timed out: {0}")]
+    Timeout(String),
 }
 
 pub type ToolResult<T> = std::result::Result<T, ToolError>;
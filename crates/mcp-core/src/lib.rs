@@ -1,5 +1,5 @@
 pub mod content;
-pub use content::{Annotations, Content, ImageContent, TextContent};
+pub use content::{Annotations, Content, ImageContent, ResultContent, TextContent};
 pub mod handler;
 pub mod role;
 pub use role::Role;
@@ -16,6 +16,9 @@ use tower_service::Service;
 mod errors;
 pub use errors::{BoxError, RouterError, ServerError, TransportError};
 
+pub mod progress;
+pub use progress::ProgressReporter;
+
 pub mod router;
 pub use router::Router;
 
@@ -0,0 +1,97 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use mcp_core::protocol::{JsonRpcMessage, JsonRpcNotification};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+
+/// Reports the progress of a tool call to the client, as `notifications/progress`
+///
+/// Reports never block or fail, a client that is not listening misses them.
+#[derive(Clone)]
+pub struct ProgressReporter {
+    notifier: mpsc::Sender<JsonRpcMessage>,
+    token: Option<Value>,
+    reports: Arc<AtomicU64>,
+}
+
+impl ProgressReporter {
+    /// A reporter for the request with the `progressToken` of its `_meta`, if it has one
+    pub fn new(notifier: mpsc::Sender<JsonRpcMessage>, token: Option<Value>) -> Self {
+        Self {
+            notifier,
+            token,
+            reports: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Report a status message, with the fraction of the work done when it is known
+    pub fn report(&self, message: impl Into<String>, fraction: Option<f64>) {
+        let count = self.reports.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut params = json!({ "message": message.into() });
+        match fraction {
+            Some(fraction) => {
+                params["progress"] = json!(fraction.clamp(0.0, 1.0));
+                params["total"] = json!(1.0);
+            }
+            // Progress has to increase with each report, so without a fraction it counts them
+            None => params["progress"] = json!(count),
+        }
+        if let Some(token) = &self.token {
+            params["progressToken"] = token.clone();
+        }
+
+        self.notifier
+            .try_send(JsonRpcMessage::Notification(JsonRpcNotification {
+                jsonrpc: "2.0".to_string(),
+                method: "notifications/progress".to_string(),
+                params: Some(params),
+            }))
+            .ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(message: JsonRpcMessage) -> Value {
+        match message {
+            JsonRpcMessage::Notification(notification) => {
+                assert_eq!(notification.method, "notifications/progress");
+                notification.params.unwrap()
+            }
+            other => panic!("Expected a notification, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_report() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let reporter = ProgressReporter::new(tx, Some(json!("prog-7")));
+
+        reporter.report("Running for 5s", None);
+        reporter.report("Running for 10s", None);
+        reporter.report("Halfway", Some(0.5));
+
+        assert_eq!(
+            params(rx.recv().await.unwrap()),
+            json!({"message": "Running for 5s", "progress": 1, "progressToken": "prog-7"})
+        );
+        assert_eq!(params(rx.recv().await.unwrap())["progress"], json!(2));
+        assert_eq!(
+            params(rx.recv().await.unwrap()),
+            json!({"message": "Halfway", "progress": 0.5, "total": 1.0, "progressToken": "prog-7"})
+        );
+    }
+
+    #[test]
+    fn test_report_without_listener() {
+        let (tx, rx) = mpsc::channel(1);
+        drop(rx);
+        let reporter = ProgressReporter::new(tx, None);
+        reporter.report("nobody is listening", None);
+    }
+}
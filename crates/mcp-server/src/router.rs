@@ -22,7 +22,7 @@ use serde_json::Value;
 use tokio::sync::mpsc;
 use tower_service::Service;
 
-use crate::{BoxError, RouterError};
+use crate::{BoxError, ProgressReporter, RouterError};
 
 /// Builder for configuring and constructing capabilities
 pub struct CapabilitiesBuilder {
@@ -94,6 +94,17 @@ pub trait Router: Send + Sync + 'static {
         arguments: Value,
         notifier: mpsc::Sender<JsonRpcMessage>,
     ) -> Pin<Box<dyn Future<Output = Result<Vec<Content>, ToolError>> + Send + 'static>>;
+    /// Call a tool with a reporter for its progress. Routers with long running tools override
+    /// this, the others only implement `call_tool`.
+    fn call_tool_with_progress(
+        &self,
+        tool_name: &str,
+        arguments: Value,
+        notifier: mpsc::Sender<JsonRpcMessage>,
+        _progress: ProgressReporter,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Content>, ToolError>> + Send + 'static>> {
+        self.call_tool(tool_name, arguments, notifier)
+    }
     fn list_resources(&self) -> Vec<mcp_core::resource::Resource>;
     fn read_resource(
         &self,
@@ -174,8 +185,16 @@ pub trait Router: Send + Sync + 'static {
                 .ok_or_else(|| RouterError::InvalidParams("Missing tool name".into()))?;
 
             let arguments = params.get("arguments").cloned().unwrap_or(Value::Null);
-
-            let result = match self.call_tool(name, arguments, notifier).await {
+            let token = params
+                .get("_meta")
+                .and_then(|meta| meta.get("progressToken"))
+                .cloned();
+            let progress = ProgressReporter::new(notifier.clone(), token);
+
+            let result = match self
+                .call_tool_with_progress(name, arguments, notifier, progress)
+                .await
+            {
                 Ok(result) => CallToolResult {
                     content: result,
                     is_error: None,
@@ -0,0 +1,69 @@
+"""Drive a goose agent over JSON-RPC on stdio.
+
+Starts `goosed rpc`, which uses the provider and extensions of `goose configure`,
+sends one message, prints the events of the reply and approves every tool call.
+
+    python goose_rpc.py "list the files in this directory"
+"""
+
+import itertools
+import json
+import subprocess
+import sys
+
+
+class Goose:
+    def __init__(self, command=("goosed", "rpc")):
+        self.process = subprocess.Popen(
+            command, stdin=subprocess.PIPE, stdout=subprocess.PIPE, text=True
+        )
+        self.ids = itertools.count(1)
+
+    def send(self, method, **params):
+        request_id = next(self.ids)
+        request = {"jsonrpc": "2.0", "id": request_id, "method": method, "params": params}
+        self.process.stdin.write(json.dumps(request) + "\n")
+        self.process.stdin.flush()
+        return request_id
+
+    def messages(self):
+        for line in self.process.stdout:
+            yield json.loads(line)
+
+    def call(self, method, **params):
+        request_id = self.send(method, **params)
+        for message in self.messages():
+            if message.get("id") == request_id:
+                if "error" in message:
+                    raise RuntimeError(message["error"]["message"])
+                return message["result"]
+
+
+def main():
+    text = " ".join(sys.argv[1:]) or "What can you do?"
+    goose = Goose()
+    session = goose.call("start_session")
+    print(f"session {session['session_id']}, protocol {session['protocol_version']}")
+
+    goose.send("send_message", session_id=session["session_id"], text=text)
+    for message in goose.messages():
+        method, params = message.get("method"), message.get("params", {})
+        if method == "agent/finish":
+            print(f"[finished: {params['reason']}]")
+            break
+        if method == "agent/tool_call":
+            print(f"[tool] {params['name']} {json.dumps(params['arguments'])}")
+        elif method == "agent/message":
+            for content in params["message"]["content"]:
+                if content["type"] == "toolConfirmationRequest":
+                    goose.send("approve_tool", request_id=content["id"], decision="allow_once")
+                elif content["type"] == "text" and params["message"]["role"] == "assistant":
+                    print(content["text"])
+
+    print("usage:", json.dumps(goose.call("get_usage")))
+    goose.process.stdin.close()
+    goose.process.wait()
+
+
+if __name__ == "__main__":
+    main()